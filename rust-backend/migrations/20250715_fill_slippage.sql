@@ -0,0 +1,13 @@
+-- migrations/20250715_fill_slippage.sql
+-- Execution-quality reporting: remember what we *wanted* at submission and
+-- how far each fill landed from it.
+
+ALTER TABLE orders
+    ADD COLUMN signal_price   NUMERIC,          -- price the strategy / leader acted on
+    ADD COLUMN mid_at_submit  NUMERIC;          -- book mid right before submission
+
+ALTER TABLE fills
+    ADD COLUMN slippage_bps      NUMERIC(10,4), -- vs. signal price, + = adverse
+    ADD COLUMN mid_slippage_bps  NUMERIC(10,4); -- vs. mid at submit, + = adverse
+
+CREATE INDEX fills_executed_at_idx ON fills(executed_at);
@@ -0,0 +1,19 @@
+-- migrations/20250716_user_plans.sql
+-- Subscription state mirrored from Stripe. Users without a row are on 'free'.
+
+CREATE TABLE user_plans (
+    user_id                 BIGINT PRIMARY KEY REFERENCES users(user_id) ON DELETE CASCADE,
+    plan                    VARCHAR(16) NOT NULL DEFAULT 'free',   -- free / pro
+    stripe_customer_id      VARCHAR(64) UNIQUE,
+    stripe_subscription_id  VARCHAR(64),
+    subscription_status     VARCHAR(32),                           -- active / past_due / canceled …
+    current_period_end      TIMESTAMPTZ,
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Stripe retries webhooks; remember what we already applied
+CREATE TABLE stripe_events (
+    event_id     VARCHAR(255) PRIMARY KEY,
+    event_type   VARCHAR(64)  NOT NULL,
+    received_at  TIMESTAMPTZ  NOT NULL DEFAULT now()
+);
@@ -0,0 +1,33 @@
+-- migrations/20250717_referrals.sql
+-- Referral codes, who-referred-whom, and the rewards ledger.
+
+CREATE TABLE referral_codes (
+    code        VARCHAR(16) PRIMARY KEY,
+    user_id     BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    active      BOOLEAN NOT NULL DEFAULT true,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX referral_codes_user_idx ON referral_codes(user_id);
+
+-- one referrer per user, ever
+CREATE TABLE referrals (
+    referred_user_id  BIGINT PRIMARY KEY REFERENCES users(user_id) ON DELETE CASCADE,
+    referrer_user_id  BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    code              VARCHAR(16) NOT NULL REFERENCES referral_codes(code),
+    converted_at      TIMESTAMPTZ,                 -- first paid plan
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CONSTRAINT no_self_referral CHECK (referred_user_id <> referrer_user_id)
+);
+CREATE INDEX referrals_referrer_idx ON referrals(referrer_user_id);
+
+-- append-only
+CREATE TABLE referral_rewards (
+    reward_id         UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id           BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    referred_user_id  BIGINT REFERENCES users(user_id) ON DELETE SET NULL,
+    kind              VARCHAR(24) NOT NULL,        -- premium_days / fee_discount_bps
+    amount            NUMERIC NOT NULL,
+    reason            VARCHAR(32) NOT NULL,        -- signup / conversion
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX referral_rewards_user_idx ON referral_rewards(user_id, created_at DESC);
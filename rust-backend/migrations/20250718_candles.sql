@@ -0,0 +1,16 @@
+-- migrations/20250718_candles.sql
+-- Closed candles recorded off the MarketBus (batched writer).
+
+CREATE TABLE candles (
+    symbol    VARCHAR(32)  NOT NULL,
+    interval  VARCHAR(8)   NOT NULL,          -- 1h / 4h …
+    ts        TIMESTAMPTZ  NOT NULL,          -- bar close time
+    open      DOUBLE PRECISION NOT NULL,
+    high      DOUBLE PRECISION NOT NULL,
+    low       DOUBLE PRECISION NOT NULL,
+    close     DOUBLE PRECISION NOT NULL,
+    volume    DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (symbol, interval, ts)
+);
+
+CREATE INDEX audit_log_user_ts_idx ON audit_log(user_id, ts DESC);
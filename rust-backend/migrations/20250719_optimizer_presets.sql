@@ -0,0 +1,25 @@
+-- migrations/20250719_optimizer_presets.sql
+-- Optimizer results + provenance on strategies created from them.
+
+CREATE TABLE optimizer_jobs (
+    job_id       UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id      BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    strategy     TEXT NOT NULL,                -- 'mean_reversion' / 'trend_follow' / 'vcsr'
+    exchange     TEXT NOT NULL DEFAULT 'blowfin',
+    symbol       TEXT NOT NULL,
+    interval     TEXT NOT NULL,                -- candle interval the search ran on
+    data_from    TIMESTAMPTZ NOT NULL,
+    data_to      TIMESTAMPTZ NOT NULL,
+    objective    TEXT NOT NULL DEFAULT 'sharpe',
+    status       TEXT NOT NULL DEFAULT 'queued', -- queued / running / done / failed
+    best_params  JSONB,                        -- set when status = 'done'
+    best_score   DOUBLE PRECISION,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    finished_at  TIMESTAMPTZ,
+    CONSTRAINT optimizer_range CHECK (data_from < data_to)
+);
+CREATE INDEX optimizer_jobs_user_idx ON optimizer_jobs(user_id, created_at DESC);
+
+-- where a strategy's params came from (NULL = entered by hand)
+ALTER TABLE user_strategies
+    ADD COLUMN IF NOT EXISTS provenance JSONB;
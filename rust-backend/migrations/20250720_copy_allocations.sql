@@ -0,0 +1,26 @@
+-- migrations/20250720_copy_allocations.sql
+-- Aggregated copy flow: one parent order per leader fill, split back to
+-- followers pro-rata at the parent's average price.
+
+CREATE TABLE copy_parent_orders (
+    parent_id     UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    leader_id     BIGINT NOT NULL,
+    symbol        TEXT NOT NULL,
+    side          TEXT NOT NULL,
+    style         TEXT NOT NULL,                -- twap:5:60 / iceberg:0.5
+    wanted_size   NUMERIC NOT NULL,
+    filled_size   NUMERIC NOT NULL,
+    avg_price     NUMERIC,                      -- NULL when no slice reported a price
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX copy_parent_orders_leader_idx ON copy_parent_orders(leader_id, created_at DESC);
+
+CREATE TABLE copy_allocations (
+    parent_id     UUID NOT NULL REFERENCES copy_parent_orders(parent_id) ON DELETE CASCADE,
+    follower_id   BIGINT NOT NULL,
+    wanted_size   NUMERIC NOT NULL,
+    size          NUMERIC NOT NULL,
+    avg_price     NUMERIC,
+    PRIMARY KEY (parent_id, follower_id)
+);
+CREATE INDEX copy_allocations_follower_idx ON copy_allocations(follower_id);
@@ -0,0 +1,11 @@
+-- migrations/20250721_watchlist.sql
+-- Per-user symbol watchlist; the market-data layer subscribes feeds for the
+-- union of all watchlisted symbols.
+
+CREATE TABLE user_watchlist (
+    user_id     BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    symbol      VARCHAR(24) NOT NULL,              -- canonical "BTC-USDT"
+    added_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, symbol)
+);
+CREATE INDEX user_watchlist_symbol_idx ON user_watchlist(symbol);
@@ -0,0 +1,19 @@
+-- migrations/20250722_alerts.sql
+-- Price alerts with an optional automation (trade template or strategy
+-- start/stop) executed once when the alert fires.
+
+CREATE TABLE alerts (
+    alert_id     UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id      BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    symbol       VARCHAR(24) NOT NULL,
+    condition    VARCHAR(8) NOT NULL CHECK (condition IN ('above', 'below')),
+    threshold    NUMERIC NOT NULL,
+    action       JSONB NOT NULL DEFAULT '{"kind": "none"}',
+    status       VARCHAR(12) NOT NULL DEFAULT 'active',  -- active / firing / fired / failed / cancelled
+    fired_at     TIMESTAMPTZ,
+    fired_price  NUMERIC,
+    result       JSONB,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX alerts_user_idx ON alerts(user_id, created_at DESC);
+CREATE INDEX alerts_active_idx ON alerts(symbol) WHERE status = 'active';
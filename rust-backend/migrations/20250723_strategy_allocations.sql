@@ -0,0 +1,23 @@
+-- migrations/20250723_strategy_allocations.sql
+-- Virtual sub-accounts: a fixed capital allocation per strategy, the
+-- strategy's own net position and its realised PnL ledger.
+
+ALTER TABLE user_strategies ADD COLUMN allocated_capital NUMERIC
+    CHECK (allocated_capital IS NULL OR allocated_capital > 0);
+
+CREATE TABLE strategy_positions (
+    strategy_id  UUID PRIMARY KEY REFERENCES user_strategies(strategy_id) ON DELETE CASCADE,
+    qty          NUMERIC NOT NULL DEFAULT 0,       -- signed: + long / − short
+    avg_price    NUMERIC NOT NULL DEFAULT 0,
+    updated_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- append-only
+CREATE TABLE strategy_pnl (
+    entry_id     BIGSERIAL PRIMARY KEY,
+    strategy_id  UUID NOT NULL REFERENCES user_strategies(strategy_id) ON DELETE CASCADE,
+    user_id      BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    pnl          NUMERIC NOT NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX strategy_pnl_strategy_idx ON strategy_pnl(strategy_id, created_at);
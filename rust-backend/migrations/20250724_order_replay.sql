@@ -0,0 +1,9 @@
+-- migrations/20250724_order_replay.sql
+-- Trade replay: which strategy placed an order, with the params it ran
+-- with and the decision trace that led to it.
+
+ALTER TABLE orders
+    ADD COLUMN strategy_id     UUID REFERENCES user_strategies(strategy_id) ON DELETE SET NULL,
+    ADD COLUMN signal_context  JSONB;           -- { strategy, params, trace: [{step, detail}] }
+
+CREATE INDEX orders_strategy_idx ON orders(strategy_id, opened_at);
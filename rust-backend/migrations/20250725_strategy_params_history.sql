@@ -0,0 +1,19 @@
+-- migrations/20250725_strategy_params_history.sql
+-- Content-addressed strategy configs: every distinct params document a
+-- strategy has run with gets a version; orders and PnL entries carry the
+-- hash that was live so performance can be split per version.
+
+CREATE TABLE strategy_params_history (
+    strategy_id  UUID     NOT NULL REFERENCES user_strategies(strategy_id) ON DELETE CASCADE,
+    version      INT      NOT NULL,
+    params_hash  CHAR(64) NOT NULL,               -- sha256 of canonical JSON
+    params       JSONB    NOT NULL,
+    first_run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_run_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (strategy_id, version),
+    UNIQUE (strategy_id, params_hash)
+);
+
+ALTER TABLE user_strategies ADD COLUMN params_hash CHAR(64);
+ALTER TABLE orders          ADD COLUMN params_hash CHAR(64);
+ALTER TABLE strategy_pnl    ADD COLUMN params_hash CHAR(64);
@@ -0,0 +1,18 @@
+-- migrations/20250726_integration_keys.sql
+-- Per-integration HMAC keys: callers send `X-RR-KEY-ID` and sign with that
+-- key's secret; each key only reaches the route prefixes in `scopes`.
+-- Secrets are envelope-encrypted like exchange API keys.
+
+CREATE TABLE integration_keys (
+    key_id             TEXT PRIMARY KEY,            -- sent as X-RR-KEY-ID
+    integration        TEXT   NOT NULL,
+    scopes             TEXT[] NOT NULL,             -- route prefixes, '/' = all
+    encrypted_data_key BYTEA  NOT NULL,
+    nonce_secret       BYTEA  NOT NULL,
+    encrypted_secret   BYTEA  NOT NULL,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at         TIMESTAMPTZ,                 -- set on rotation (grace period)
+    revoked_at         TIMESTAMPTZ
+);
+
+CREATE INDEX integration_keys_integration_idx ON integration_keys (integration);
@@ -0,0 +1,5 @@
+-- migrations/20250727_strategy_status_error.sql
+-- Strategies whose params fail to parse/validate are parked as
+-- status = 'invalid'; the reason is kept here for the API
+ALTER TABLE user_strategies
+    ADD COLUMN IF NOT EXISTS status_error TEXT;
@@ -0,0 +1,14 @@
+-- migrations/20250728_feature_flags.sql
+-- Runtime feature flags (see services::feature_flags). A flag is on for a
+-- user when `enabled` and either the user is listed in `user_ids` or their
+-- stable bucket (0-99) falls below `rollout_pct`. Unknown flags are off.
+
+CREATE TABLE feature_flags (
+    name         TEXT PRIMARY KEY,
+    enabled      BOOLEAN  NOT NULL DEFAULT false,   -- kill switch
+    rollout_pct  SMALLINT NOT NULL DEFAULT 0
+                 CHECK (rollout_pct BETWEEN 0 AND 100),
+    user_ids     BIGINT[] NOT NULL DEFAULT '{}',    -- always on for these
+    description  TEXT     NOT NULL DEFAULT '',
+    updated_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,15 @@
+-- migrations/20250729_strategy_plugins.sql
+-- Uploaded WASM strategy plugins (see strategies::plugin). Modules are
+-- validated (compile + ABI check) before they are stored; strategies refer
+-- to them by `plugin_id` in their params.
+
+CREATE TABLE strategy_plugins (
+    plugin_id   UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id     BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    name        TEXT   NOT NULL,
+    sha256      TEXT   NOT NULL,
+    size_bytes  INTEGER NOT NULL,
+    wasm        BYTEA  NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (user_id, name)
+);
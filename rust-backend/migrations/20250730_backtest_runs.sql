@@ -0,0 +1,19 @@
+-- migrations/20250730_backtest_runs.sql
+-- Every finished backtest: what ran, on which data, how it did and the
+-- individual trades, so runs can be listed and compared later.
+
+CREATE TABLE backtest_runs (
+    run_id      UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id     BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    strategy    TEXT NOT NULL,
+    symbol      TEXT NOT NULL,
+    interval    TEXT NOT NULL,
+    params      JSONB NOT NULL,
+    data_from   TIMESTAMPTZ NOT NULL,
+    data_to     TIMESTAMPTZ NOT NULL,
+    metrics     JSONB NOT NULL,                -- services::backtest::Metrics
+    trades      JSONB NOT NULL,                -- [services::backtest::BacktestTrade]
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CONSTRAINT backtest_range CHECK (data_from <= data_to)
+);
+CREATE INDEX backtest_runs_user_idx ON backtest_runs(user_id, created_at DESC);
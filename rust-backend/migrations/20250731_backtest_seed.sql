@@ -0,0 +1,6 @@
+-- migrations/20250731_backtest_seed.sql
+-- RNG seed a backtest ran with (slippage draws, Monte-Carlo resampling), so
+-- the exact run can be reproduced. NULL for runs stored before seeding.
+
+ALTER TABLE backtest_runs
+    ADD COLUMN IF NOT EXISTS seed BIGINT;
@@ -0,0 +1,6 @@
+-- migrations/20250801_strategy_auto_stop.sql
+-- Optional per-strategy guardrail, e.g.
+--   {"max_consecutive_losses": 5, "max_drawdown_pct": 15}
+-- A tripped guard disables the strategy; the reason goes to status_error.
+ALTER TABLE user_strategies
+    ADD COLUMN IF NOT EXISTS auto_stop JSONB;
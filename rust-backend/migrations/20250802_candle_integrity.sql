@@ -0,0 +1,25 @@
+-- migrations/20250802_candle_integrity.sql
+-- Where each stored bar came from and a checksum over its values, plus the
+-- discrepancies the verification job finds (see services::candle_integrity).
+--   source:   binance:ws | binance:rest | import:csv | import:s3 | rollup
+--   checksum: first 16 hex chars of sha256 over the bar; NULL until sealed
+
+ALTER TABLE candles
+    ADD COLUMN IF NOT EXISTS source   VARCHAR(32) NOT NULL DEFAULT 'unknown',
+    ADD COLUMN IF NOT EXISTS checksum CHAR(16);
+
+CREATE INDEX IF NOT EXISTS candles_unsealed_idx
+    ON candles (symbol, interval, ts) WHERE checksum IS NULL;
+
+CREATE TABLE IF NOT EXISTS candle_discrepancies (
+    symbol      VARCHAR(32) NOT NULL,
+    interval    VARCHAR(8)  NOT NULL,
+    ts          TIMESTAMPTZ NOT NULL,
+    kind        VARCHAR(16) NOT NULL,             -- checksum | mismatch | gap
+    details     JSONB       NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (symbol, interval, ts, kind)
+);
+
+CREATE INDEX IF NOT EXISTS candle_discrepancies_detected_idx
+    ON candle_discrepancies (detected_at DESC);
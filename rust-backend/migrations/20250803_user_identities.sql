@@ -0,0 +1,21 @@
+-- migrations/20250803_user_identities.sql
+-- Several sign-in identities (Discord accounts, email login) per trading
+-- account. `users.user_id` stays the account id every other table keys on;
+-- a JWT's `sub` is looked up here (see services::identities). Exactly one
+-- identity per account is primary – it's where notifications go.
+
+CREATE TABLE user_identities (
+    provider    VARCHAR(16)  NOT NULL,            -- discord / email
+    subject     VARCHAR(255) NOT NULL,            -- snowflake / lower-cased address
+    user_id     BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    is_primary  BOOLEAN NOT NULL DEFAULT false,
+    linked_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (provider, subject)
+);
+CREATE INDEX user_identities_user_idx ON user_identities(user_id);
+CREATE UNIQUE INDEX user_identities_primary_idx ON user_identities(user_id) WHERE is_primary;
+
+-- every existing account signs in with the Discord id it was created from
+INSERT INTO user_identities (provider, subject, user_id, is_primary)
+SELECT 'discord', user_id::text, user_id, true FROM users
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,13 @@
+-- migrations/20250804_account_deletion.sql
+-- Soft deletion: `DELETE /api/account` stops the account at once and queues
+-- it here; once `purge_after` passes, services::account_deletion moves its
+-- history onto an anonymous placeholder user and drops the row.
+
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE TABLE account_deletions (
+    user_id      BIGINT PRIMARY KEY,           -- no FK: the users row goes at purge time
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    purge_after  TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX account_deletions_due_idx ON account_deletions(purge_after);
@@ -0,0 +1,14 @@
+-- migrations/20250805_user_fee_tiers.sql
+-- Each user's maker/taker rates per exchange, set by hand or inferred from
+-- their fills (see services::fees). No row = the configured default tier.
+
+CREATE TABLE user_fee_tiers (
+    user_id     BIGINT      NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    exchange    VARCHAR(16) NOT NULL,
+    tier        VARCHAR(32),                      -- exchange's label, e.g. VIP1
+    maker_bps   NUMERIC(8,3) NOT NULL,            -- negative = rebate
+    taker_bps   NUMERIC(8,3) NOT NULL,
+    source      VARCHAR(16) NOT NULL,             -- manual / fills
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, exchange)
+);
@@ -0,0 +1,20 @@
+-- migrations/20250806_transfers.sql
+-- Deposits and withdrawals pulled read-only from the exchange (see
+-- services::transfers), so equity curves can tell funding from trading.
+
+CREATE TABLE transfers (
+    user_id      BIGINT      NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    exchange     VARCHAR(16) NOT NULL,
+    kind         VARCHAR(10) NOT NULL CHECK (kind IN ('deposit', 'withdrawal')),
+    transfer_id  VARCHAR(64) NOT NULL,                -- exchange's deposit/withdraw id
+    currency     VARCHAR(16) NOT NULL,
+    amount       NUMERIC     NOT NULL,                -- always positive
+    fee          NUMERIC     NOT NULL DEFAULT 0,
+    state        VARCHAR(8)  NOT NULL,                -- exchange's raw state code
+    settled      BOOLEAN     NOT NULL,                -- moved money on the account
+    tx_id        TEXT,
+    occurred_at  TIMESTAMPTZ NOT NULL,
+    synced_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, exchange, kind, transfer_id)
+);
+CREATE INDEX transfers_user_time_idx ON transfers(user_id, occurred_at);
@@ -0,0 +1,14 @@
+-- migrations/20250807_leader_profiles.sql
+-- Leaders listed for discovery, the equity bracket they claim and the one
+-- their balance snapshots back up (see services::leader_verification).
+
+CREATE TABLE leader_profiles (
+    user_id           BIGINT PRIMARY KEY REFERENCES users(user_id) ON DELETE CASCADE,
+    reported_bracket  VARCHAR(8)  NOT NULL,           -- 1k / 10k / 100k / 1m
+    verified_bracket  VARCHAR(8),                     -- NULL = no badge
+    verified_at       TIMESTAMPTZ,                    -- when the badge was last confirmed
+    checked_at        TIMESTAMPTZ,
+    listed            BOOLEAN     NOT NULL DEFAULT TRUE,
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX leader_profiles_listed_idx ON leader_profiles(listed) WHERE listed;
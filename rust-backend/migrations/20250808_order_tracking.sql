@@ -0,0 +1,15 @@
+-- migrations/20250808_order_tracking.sql
+-- Follow submitted orders to their fills (see services::order_tracker).
+
+ALTER TABLE orders
+    ADD COLUMN client_order_id  VARCHAR(64),              -- ours, sent with the order
+    ADD COLUMN filled_size      NUMERIC NOT NULL DEFAULT 0;
+
+-- what the tracker polls
+CREATE INDEX orders_open_idx ON orders(user_id, opened_at)
+    WHERE closed_at IS NULL AND status IN ('live', 'partially_filled');
+
+ALTER TABLE fills
+    ADD COLUMN external_trade_id VARCHAR(64);             -- exchange trade id
+
+CREATE UNIQUE INDEX fills_trade_idx ON fills(order_id, external_trade_id);
@@ -0,0 +1,10 @@
+-- migrations/20250809_copy_trials.sql
+-- Time-boxed trial copies: status 'trial' until confirmed ('active') or
+-- past trial_ends_at ('expired'). See services::copy_trading.
+
+ALTER TABLE copy_relations
+    ADD COLUMN trial_ends_at   TIMESTAMPTZ,
+    ADD COLUMN trial_max_size  NUMERIC;            -- cap per copied order, contracts
+
+CREATE INDEX copy_relations_trial_idx ON copy_relations(trial_ends_at)
+    WHERE status = 'trial';
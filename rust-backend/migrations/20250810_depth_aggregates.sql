@@ -0,0 +1,16 @@
+-- migrations/20250810_depth_aggregates.sql
+-- Per-minute order-book aggregates off the MarketBus depth feed; the
+-- order-flow indicators read their history from here. See
+-- services::depth_history.
+
+CREATE TABLE depth_aggregates (
+    symbol      TEXT        NOT NULL,
+    bucket      TIMESTAMPTZ NOT NULL,           -- minute start
+    samples     INT         NOT NULL,
+    bid_depth   DOUBLE PRECISION NOT NULL,      -- means over the minute
+    ask_depth   DOUBLE PRECISION NOT NULL,
+    imbalance   DOUBLE PRECISION,               -- (bid − ask) / (bid + ask)
+    bid_slope   DOUBLE PRECISION,               -- size per bp from mid
+    ask_slope   DOUBLE PRECISION,
+    PRIMARY KEY (symbol, bucket)
+);
@@ -0,0 +1,10 @@
+-- migrations/20250811_copy_sizing.sql
+-- Per-relation copy sizing: a fixed ratio of the leader's size (default 1:1)
+-- or a share of the follower's equity, optionally capped per order by
+-- notional. See services::copy_trading::CopyConfig.
+
+ALTER TABLE copy_relations
+    ADD COLUMN size_mode     TEXT    NOT NULL DEFAULT 'ratio'
+        CHECK (size_mode IN ('ratio', 'equity_pct')),
+    ADD COLUMN size_value    NUMERIC NOT NULL DEFAULT 1,   -- ratio, or % of equity
+    ADD COLUMN max_notional  NUMERIC;                      -- USDT per copied entry
@@ -0,0 +1,20 @@
+-- migrations/20250812_copy_events_results.sql
+-- One copy_events row per replicated follower order, whatever became of
+-- it: 'ok' (placed), 'failed' (permanent error) or 'dead_letter' (transient
+-- errors outlasted the retries; `request` keeps the order for a replay).
+-- See services::copy_queue.
+
+ALTER TABLE copy_events
+    ALTER COLUMN leader_order_id   DROP NOT NULL,
+    ALTER COLUMN follower_order_id DROP NOT NULL,
+    ADD COLUMN leader_user_id    BIGINT,
+    ADD COLUMN follower_user_id  BIGINT,
+    ADD COLUMN status            TEXT NOT NULL DEFAULT 'ok'
+        CHECK (status IN ('ok', 'failed', 'dead_letter')),
+    ADD COLUMN attempts          INT  NOT NULL DEFAULT 1,
+    ADD COLUMN error             TEXT,
+    ADD COLUMN request           JSONB;
+
+CREATE INDEX copy_events_follower_idx ON copy_events(follower_user_id, copied_at DESC);
+CREATE INDEX copy_events_dead_letter_idx ON copy_events(copied_at)
+    WHERE status = 'dead_letter';
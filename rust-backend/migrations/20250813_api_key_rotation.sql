@@ -0,0 +1,5 @@
+-- migrations/20250813_api_key_rotation.sql
+-- When a key's credentials were last replaced through /api/keys; NULL =
+-- never rotated since it was stored.
+
+ALTER TABLE api_keys ADD COLUMN rotated_at TIMESTAMPTZ;
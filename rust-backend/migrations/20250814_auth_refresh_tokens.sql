@@ -0,0 +1,20 @@
+-- migrations/20250814_auth_refresh_tokens.sql
+-- Refresh tokens minted by /api/auth (see services::auth_tokens). Only the
+-- SHA-256 of a token is kept. Every refresh spends a row (`used_at`) and
+-- adds the next one of its family; a spent token coming back revokes the
+-- whole family. `provider`/`subject` is the identity it signed in with,
+-- re-resolved on every refresh.
+
+CREATE TABLE auth_refresh_tokens (
+    token_hash  CHAR(64) PRIMARY KEY,            -- hex SHA-256
+    family_id   UUID NOT NULL,
+    user_id     BIGINT NOT NULL,
+    provider    VARCHAR(16)  NOT NULL,
+    subject     VARCHAR(255) NOT NULL,
+    issued_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at  TIMESTAMPTZ NOT NULL,
+    used_at     TIMESTAMPTZ,
+    revoked_at  TIMESTAMPTZ
+);
+CREATE INDEX auth_refresh_tokens_family_idx ON auth_refresh_tokens(family_id);
+CREATE INDEX auth_refresh_tokens_user_idx ON auth_refresh_tokens(user_id) WHERE revoked_at IS NULL;
@@ -0,0 +1,6 @@
+-- migrations/20250815_user_roles.sql
+-- Roles an account holds (see services::roles); 'admin' opens /api/admin.
+-- Everyone starts with none – grant by hand:
+--   UPDATE users SET roles = array_append(roles, 'admin') WHERE user_id = …;
+
+ALTER TABLE users ADD COLUMN roles TEXT[] NOT NULL DEFAULT '{}';
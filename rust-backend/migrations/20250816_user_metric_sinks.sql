@@ -0,0 +1,23 @@
+-- migrations/20250816_user_metric_sinks.sql
+-- Where a user's own strategy metrics are pushed (see services::metric_sinks).
+-- The signing secret is sealed like integration keys'. `last_push_at` is
+-- set when a push is claimed, so it also paces the sink; `failures` counts
+-- failed pushes in a row and switches the sink off at the limit.
+
+CREATE TABLE user_metric_sinks (
+    sink_id             UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id             BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    kind                VARCHAR(16) NOT NULL,        -- webhook / pushgateway
+    url                 TEXT NOT NULL,
+    interval_secs       INT NOT NULL DEFAULT 60 CHECK (interval_secs >= 60),
+    encrypted_data_key  BYTEA NOT NULL,
+    nonce_secret        BYTEA NOT NULL,
+    encrypted_secret    BYTEA NOT NULL,
+    enabled             BOOLEAN NOT NULL DEFAULT true,
+    failures            INT NOT NULL DEFAULT 0,
+    last_push_at        TIMESTAMPTZ,
+    last_error          TEXT,
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX user_metric_sinks_user_idx ON user_metric_sinks(user_id);
+CREATE INDEX user_metric_sinks_due_idx ON user_metric_sinks(last_push_at) WHERE enabled;
@@ -0,0 +1,16 @@
+-- migrations/20250817_webhook_tokens.sql
+-- Secret tokens a user pastes into TradingView alerts so they can trade
+-- through /api/webhooks/tradingview (see services::webhooks). Only the
+-- SHA-256 of a token is kept, like refresh tokens'; `last_used_at` is set
+-- by every alert that presents it.
+
+CREATE TABLE webhook_tokens (
+    token_id      UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id       BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    token_hash    CHAR(64) NOT NULL UNIQUE,          -- hex SHA-256
+    label         VARCHAR(64),
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used_at  TIMESTAMPTZ,
+    revoked_at    TIMESTAMPTZ
+);
+CREATE INDEX webhook_tokens_user_idx ON webhook_tokens(user_id) WHERE revoked_at IS NULL;
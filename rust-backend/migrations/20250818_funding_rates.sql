@@ -0,0 +1,15 @@
+-- migrations/20250818_funding_rates.sql
+-- Perpetual funding recorded off the MarketBus (see services::funding):
+-- one row per exchange, symbol and settlement, rewritten as the predicted
+-- rate moves, so the last write before `funding_time` is about what was
+-- paid. `rate` is per settlement; longs pay when it is positive.
+
+CREATE TABLE funding_rates (
+    exchange      VARCHAR(16) NOT NULL,
+    symbol        VARCHAR(32) NOT NULL,
+    funding_time  TIMESTAMPTZ NOT NULL,
+    rate          DOUBLE PRECISION NOT NULL,
+    captured_at   TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (exchange, symbol, funding_time)
+);
+CREATE INDEX funding_rates_symbol_time_idx ON funding_rates(symbol, funding_time DESC);
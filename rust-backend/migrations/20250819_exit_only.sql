@@ -0,0 +1,6 @@
+-- migrations/20250819_exit_only.sql
+-- Withdrawal-safe mode: until `exit_only_until` passes, the risk layer
+-- refuses every order that isn't reduce-only, whoever places it (see
+-- services::risk). NULL or a past time means normal trading.
+
+ALTER TABLE users ADD COLUMN exit_only_until TIMESTAMPTZ;
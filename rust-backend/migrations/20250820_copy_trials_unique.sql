@@ -0,0 +1,8 @@
+-- migrations/20250820_copy_trials_unique.sql
+-- One trial per leader/follower pair, enforced by the database: two
+-- concurrent `start_trial` calls both pass the "not tried yet" check, and
+-- only this index keeps the second insert out (it becomes a no-op).
+
+CREATE UNIQUE INDEX copy_relations_one_trial_idx
+    ON copy_relations(leader_user_id, follower_user_id)
+    WHERE trial_ends_at IS NOT NULL;
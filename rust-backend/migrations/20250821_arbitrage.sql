@@ -0,0 +1,34 @@
+-- migrations/20250821_arbitrage.sql
+-- Cross-exchange arbitrage: which symbols a user watches (and whether the
+-- monitor only alerts or trades), and the two-legged hedges it opened.
+
+CREATE TABLE arbitrage_settings (
+    user_id       BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    symbol        VARCHAR(24) NOT NULL,            -- canonical "BTCUSDT"
+    min_edge_bps  DOUBLE PRECISION NOT NULL CHECK (min_edge_bps > 0),  -- net of taker fees
+    qty           DOUBLE PRECISION NOT NULL CHECK (qty > 0),           -- base units per leg
+    mode          TEXT NOT NULL DEFAULT 'alert' CHECK (mode IN ('alert', 'execute')),
+    enabled       BOOLEAN NOT NULL DEFAULT true,
+    updated_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, symbol)
+);
+
+CREATE TABLE arbitrage_positions (
+    position_id     UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id         BIGINT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    symbol          VARCHAR(24) NOT NULL,
+    buy_on          TEXT NOT NULL,                 -- venue holding the long leg
+    sell_on         TEXT NOT NULL,                 -- venue holding the short leg
+    qty             DOUBLE PRECISION NOT NULL,
+    entry_buy       DOUBLE PRECISION NOT NULL,
+    entry_sell      DOUBLE PRECISION NOT NULL,
+    entry_edge_bps  DOUBLE PRECISION NOT NULL,
+    status          TEXT NOT NULL CHECK (status IN ('open', 'closed', 'unwound', 'broken')),
+    exit_buy        DOUBLE PRECISION,
+    exit_sell       DOUBLE PRECISION,
+    note            TEXT,
+    opened_at       TIMESTAMPTZ NOT NULL DEFAULT now(),
+    closed_at       TIMESTAMPTZ
+);
+CREATE INDEX arbitrage_positions_user_idx ON arbitrage_positions(user_id, opened_at DESC);
+CREATE INDEX arbitrage_positions_open_idx ON arbitrage_positions(status) WHERE status = 'open';
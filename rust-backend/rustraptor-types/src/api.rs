@@ -0,0 +1,87 @@
+// rust-backend/rustraptor-types/src/api.rs
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Envelope of every backend response; `data` on success, `message` on
+/// failure
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub message: Option<String>,
+    pub data: Option<T>,
+    /// Per-symbol render hints, only when asked for with `?display=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<BTreeMap<String, DisplayMeta>>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn ok(data: T) -> Self {
+        Self {
+            success: true,
+            message: None,
+            data: Some(data),
+            display: None,
+        }
+    }
+    pub fn err(msg: &str) -> Self {
+        Self {
+            success: false,
+            message: Some(msg.into()),
+            data: None,
+            display: None,
+        }
+    }
+    pub fn with_display(mut self, display: Option<BTreeMap<String, DisplayMeta>>) -> Self {
+        self.display = display;
+        self
+    }
+
+    /// `data` of a successful response, `message` of a failed one
+    pub fn into_result(self) -> Result<Option<T>, String> {
+        if self.success {
+            Ok(self.data)
+        } else {
+            Err(self.message.unwrap_or_default())
+        }
+    }
+}
+
+/// How a client should render one symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayMeta {
+    pub price_precision: u32,
+    pub tick_size: f64,
+    /// Order sizes are multiples of this (contracts)
+    pub qty_step: f64,
+    pub qty_precision: u32,
+    pub min_qty: f64,
+    /// Base units per contract
+    pub contract_value: f64,
+    pub base_currency: String,
+    pub quote_currency: String,
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn envelope_round_trips() {
+        let ok: ApiResponse<u32> =
+            serde_json::from_value(json!({ "success": true, "message": null, "data": 7 })).unwrap();
+        assert_eq!(ok.into_result(), Ok(Some(7)));
+
+        let err = serde_json::to_value(ApiResponse::<()>::err("no user id")).unwrap();
+        assert_eq!(
+            err,
+            json!({ "success": false, "message": "no user id", "data": null })
+        );
+        let back: ApiResponse<()> = serde_json::from_value(err).unwrap();
+        assert_eq!(back.into_result(), Err("no user id".to_string()));
+    }
+}
@@ -0,0 +1,22 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! RustRaptor domain types
+//! ──────────────────────────────────────────────────────────────────────────
+//! What goes over the wire to and from the backend, without actix or sqlx,
+//! so Rust clients (the SDK, the Discord bot) can share the backend's
+//! definitions:
+//!
+//! * `api`      – the `{success, message, data}` envelope of every response
+//! * `trade`    – the body of `POST /api/trade`
+//! * `strategy` – the body of `POST /api/strategies/start`
+//! * `market`   – candles and depth snapshots, behind the `market` feature
+//!   (the only part needing `chrono`)
+//!
+//! The backend re-exports these from their old homes; it defines its own
+//! types only where a field is backend-internal.
+//! ──────────────────────────────────────────────────────────────────────────
+
+pub mod api;
+#[cfg(feature = "market")]
+pub mod market;
+pub mod strategy;
+pub mod trade;
@@ -0,0 +1,28 @@
+// rust-backend/rustraptor-types/src/market.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Candle {
+    pub ts: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    /// Optional cumulative delta
+    pub delta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderBookSnapshot {
+    pub bid_depth: f64,
+    pub ask_depth: f64,
+    /// Depth slope per side (`indicators::depth_slope` in the backend),
+    /// when the feed carries levels
+    pub bid_slope: Option<f64>,
+    pub ask_slope: Option<f64>,
+    /// Top of book, when the feed carries levels
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+}
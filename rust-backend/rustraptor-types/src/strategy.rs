@@ -0,0 +1,45 @@
+// rust-backend/rustraptor-types/src/strategy.rs
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/strategies/start`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartStrategy {
+    pub exchange: String,
+    /// Trading pair, e.g., "BTCUSDT"
+    pub symbol: String,
+    /// Name of the strategy ("mean_reversion", "trend_follow", etc)
+    pub strategy: String,
+    /// Params for the strategy (periods, thresholds, etc); the fields each
+    /// strategy accepts are served by `GET /api/strategies/schemas/{strategy}`
+    pub params: serde_json::Value,
+    /// Disable the strategy after a losing streak / drawdown
+    #[serde(default)]
+    pub auto_stop: Option<AutoStop>,
+}
+
+/// A strategy's performance guardrail (`user_strategies.auto_stop`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutoStop {
+    #[serde(default)]
+    pub max_consecutive_losses: Option<u32>,
+    /// Percent, `(0, 100)`
+    #[serde(default)]
+    pub max_drawdown_pct: Option<f64>,
+}
+
+impl AutoStop {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_consecutive_losses == Some(0) {
+            return Err("auto_stop.max_consecutive_losses must be at least 1".into());
+        }
+        if let Some(dd) = self.max_drawdown_pct {
+            if !(dd > 0.0 && dd < 100.0) {
+                return Err(format!(
+                    "auto_stop.max_drawdown_pct must be in (0, 100), got {dd}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+// rust-backend/rustraptor-types/src/trade.rs
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/trade`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeRequest {
+    pub exchange: String,
+    pub symbol: String,
+    pub side: String,
+    pub order_type: String,
+    pub price: Option<f64>,
+    pub size: f64,
+    #[serde(default)]
+    pub reduce_only: bool,
+    /// Reference price for slippage reporting (defaults to `price`)
+    #[serde(default)]
+    pub signal_price: Option<f64>,
+    /// Native take-profit / stop-loss triggers attached to an entry
+    #[serde(default)]
+    pub tp_sl: Option<TpSl>,
+}
+
+/// Native take-profit / stop-loss trigger prices; both fill at market
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TpSl {
+    #[serde(default)]
+    pub take_profit: Option<f64>,
+    #[serde(default)]
+    pub stop_loss: Option<f64>,
+}
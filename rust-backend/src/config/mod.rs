@@ -1 +1,2 @@
+pub mod preflight;
 pub mod settings;
@@ -0,0 +1,342 @@
+//  src/config/preflight.rs
+//! ──────────────────────────────────────────────────────────────────────────
+//! Startup preflight
+//! ──────────────────────────────────────────────────────────────────────────
+//! Runs once before the port is bound and prints a table like
+//!
+//! ```text
+//!  check        status  detail
+//!  postgres     ok      connected
+//!  schema       FAIL    applied 20250716, expected 20250718 – run migrations
+//!  redis        warn    unreachable (memory cache backend)
+//! ```
+//!
+//! Any `FAIL` aborts start-up; `warn` rows are informational. Secrets are
+//! never echoed – only their shape is checked.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::fmt;
+use std::time::Duration;
+
+use sqlx::postgres::PgPoolOptions;
+
+use crate::config::settings::Settings;
+use crate::services::crypto::EnvelopeCrypto;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const PLACEHOLDER: &str = "replace_me";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn failed(&self) -> bool {
+        self.checks.iter().any(|c| c.status == Status::Fail)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let w = self
+            .checks
+            .iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        writeln!(f, " {:<w$}  {:<6}  detail", "check", "status")?;
+        for c in &self.checks {
+            writeln!(f, " {:<w$}  {:<6}  {}", c.name, c.status.label(), c.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every check; never panics.
+pub async fn run(settings: &Settings) -> Report {
+    let mut checks = Vec::new();
+    checks.extend(check_postgres(&settings.database_url).await);
+    checks.push(check_redis(&settings.redis_url, &settings.cache_backend).await);
+    checks.push(check_master_key(
+        &std::env::var("MASTER_KEY").unwrap_or_default(),
+    ));
+    checks.push(check_envelope(EnvelopeCrypto::from_env()));
+    checks.push(check_credentials(settings));
+    Report { checks }
+}
+
+// ─── Postgres & schema ────────────────────────────────────────────────────
+/// Newest migration compiled into this binary
+fn expected_schema_version() -> i64 {
+    sqlx::migrate!("./migrations")
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or(0)
+}
+
+async fn check_postgres(url: &str) -> Vec<Check> {
+    let pool = match PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(CONNECT_TIMEOUT)
+        .connect(url)
+        .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            return vec![
+                Check::new("postgres", Status::Fail, e.to_string()),
+                Check::new("schema", Status::Fail, "skipped – no database"),
+            ]
+        }
+    };
+
+    let applied = sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT MAX(version) FROM _sqlx_migrations WHERE success",
+    )
+    .fetch_one(&pool)
+    .await;
+    pool.close().await;
+
+    let schema = match applied {
+        Ok(v) => schema_check(v, expected_schema_version()),
+        // table missing: migrations were applied by hand, can't tell
+        Err(e) => Check::new("schema", Status::Warn, format!("version unknown: {e}")),
+    };
+    vec![Check::new("postgres", Status::Ok, "connected"), schema]
+}
+
+fn schema_check(applied: Option<i64>, expected: i64) -> Check {
+    match applied {
+        Some(v) if v >= expected => Check::new("schema", Status::Ok, format!("version {v}")),
+        Some(v) => Check::new(
+            "schema",
+            Status::Fail,
+            format!("applied {v}, expected {expected} – run migrations"),
+        ),
+        None => Check::new("schema", Status::Fail, "no migrations applied"),
+    }
+}
+
+// ─── Redis ────────────────────────────────────────────────────────────────
+async fn check_redis(url: &str, cache_backend: &str) -> Check {
+    let ping = async {
+        let client = redis::Client::open(url)?;
+        let mut con = client.get_multiplexed_async_connection().await?;
+        redis::cmd("PING").query_async::<_, String>(&mut con).await
+    };
+    let res = match tokio::time::timeout(CONNECT_TIMEOUT, ping).await {
+        Ok(r) => r.map_err(|e| e.to_string()),
+        Err(_) => Err("timed out".to_string()),
+    };
+    match res {
+        Ok(_) => Check::new("redis", Status::Ok, "PONG"),
+        // the in-memory cache doesn't need it; only the event bus is lost
+        Err(e) if cache_backend == "memory" => {
+            Check::new("redis", Status::Warn, format!("{e} (memory cache backend)"))
+        }
+        Err(e) => Check::new("redis", Status::Fail, e),
+    }
+}
+
+// ─── Keys & credentials ───────────────────────────────────────────────────
+fn check_master_key(master_key: &str) -> Check {
+    if master_key.trim().is_empty() {
+        Check::new("master_key", Status::Fail, "MASTER_KEY is empty")
+    } else {
+        Check::new(
+            "master_key",
+            Status::Ok,
+            format!("{} bytes", master_key.len()),
+        )
+    }
+}
+
+/// Seal + open a fixed test vector with the envelope keys
+fn check_envelope(crypto: anyhow::Result<EnvelopeCrypto>) -> Check {
+    const VECTOR: &str = "rustraptor-preflight";
+    let crypto = match crypto {
+        Ok(c) => c,
+        Err(e) => {
+            return Check::new(
+                "envelope_keys",
+                Status::Fail,
+                format!("MASTER_PK_B64 / MASTER_SK_B64: {e}"),
+            )
+        }
+    };
+    let (wrapped, nonce, ct) = crypto.seal(VECTOR.as_bytes());
+    match crypto.open(&wrapped, &nonce, &ct) {
+        Ok(s) if s == VECTOR => Check::new("envelope_keys", Status::Ok, "test vector round-trips"),
+        Ok(_) => Check::new("envelope_keys", Status::Fail, "test vector mismatch"),
+        Err(e) => Check::new(
+            "envelope_keys",
+            Status::Fail,
+            format!("{e} – public/secret key pair mismatch?"),
+        ),
+    }
+}
+
+/// Shape problems with one credential, if any
+fn credential_problem(value: &str, alnum_only: bool) -> Option<&'static str> {
+    let v = value.trim();
+    if v.is_empty() {
+        Some("empty")
+    } else if v.eq_ignore_ascii_case(PLACEHOLDER) {
+        Some("placeholder")
+    } else if v.len() != value.len() || v.chars().any(char::is_whitespace) {
+        Some("contains whitespace")
+    } else if alnum_only && !v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        Some("unexpected characters")
+    } else {
+        None
+    }
+}
+
+fn check_credentials(settings: &Settings) -> Check {
+    let problems: Vec<String> = [
+        ("BLOFIN_API_KEY", settings.blowfin_api_key.as_str(), true),
+        (
+            "BLOFIN_API_SECRET",
+            settings.blowfin_api_secret.as_str(),
+            true,
+        ),
+        (
+            "BLOFIN_API_PASSPHRASE",
+            settings.blowfin_api_passphrase.as_str(),
+            false,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(k, v, alnum)| credential_problem(v, alnum).map(|p| format!("{k} {p}")))
+    .collect();
+
+    if problems.is_empty() {
+        Check::new(
+            "exchange_creds",
+            Status::Ok,
+            "blofin key/secret/passphrase look valid",
+        )
+    } else if settings.is_demo() {
+        // demo feeds are public; only order placement needs real creds
+        Check::new("exchange_creds", Status::Warn, problems.join(", "))
+    } else {
+        Check::new("exchange_creds", Status::Fail, problems.join(", "))
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sodiumoxide::crypto::box_;
+
+    #[test]
+    fn schema_behind_fails() {
+        assert_eq!(schema_check(Some(20250718), 20250718).status, Status::Ok);
+        assert_eq!(schema_check(Some(20250719), 20250718).status, Status::Ok);
+        assert_eq!(schema_check(Some(20250716), 20250718).status, Status::Fail);
+        assert_eq!(schema_check(None, 20250718).status, Status::Fail);
+    }
+
+    #[test]
+    fn expected_version_is_newest_migration() {
+        assert!(expected_schema_version() >= 20250718);
+    }
+
+    #[test]
+    fn empty_master_key_fails() {
+        assert_eq!(check_master_key("  ").status, Status::Fail);
+        assert_eq!(check_master_key("secret").status, Status::Ok);
+    }
+
+    #[test]
+    fn envelope_round_trip() {
+        sodiumoxide::init().unwrap();
+        let (pk, sk) = box_::gen_keypair();
+        assert_eq!(
+            check_envelope(Ok(EnvelopeCrypto::new(pk.0, sk.0))).status,
+            Status::Ok
+        );
+
+        // secret key from a different pair can't unwrap
+        let (_, other_sk) = box_::gen_keypair();
+        assert_eq!(
+            check_envelope(Ok(EnvelopeCrypto::new(pk.0, other_sk.0))).status,
+            Status::Fail
+        );
+        assert_eq!(
+            check_envelope(Err(anyhow::anyhow!("missing"))).status,
+            Status::Fail
+        );
+    }
+
+    #[test]
+    fn credential_shapes() {
+        assert_eq!(credential_problem("abc123DEF", true), None);
+        assert_eq!(credential_problem("", true), Some("empty"));
+        assert_eq!(credential_problem("replace_me", true), Some("placeholder"));
+        assert_eq!(
+            credential_problem("abc ", true),
+            Some("contains whitespace")
+        );
+        assert_eq!(
+            credential_problem("ab$c", true),
+            Some("unexpected characters")
+        );
+        assert_eq!(credential_problem("p@ss!", false), None);
+    }
+
+    #[test]
+    fn report_renders_and_flags_failures() {
+        let mut r = Report::default();
+        r.checks
+            .push(Check::new("postgres", Status::Ok, "connected"));
+        assert!(!r.failed());
+        r.checks.push(Check::new("redis", Status::Fail, "refused"));
+        assert!(r.failed());
+
+        let out = r.to_string();
+        assert!(out.lines().next().unwrap().contains("status"));
+        assert!(out.contains("FAIL"));
+        assert_eq!(out.lines().count(), 3);
+    }
+}
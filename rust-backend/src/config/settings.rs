@@ -1,5 +1,30 @@
 use dotenv::dotenv;
 use std::env;
+use std::str::FromStr;
+
+use crate::middleware::request_log::{self, RequestLogConfig};
+use crate::services::{
+    auth_tokens::{DiscordOAuth, TokenConfig},
+    calendar::{Calendar, SymbolClass},
+    candle_retention::RetentionPolicy,
+    copy_aggregate::ParentStyle,
+    fees::FeeSchedule,
+    history::HistorySource,
+    liquidity::SymbolFilter,
+    telemetry::{LogConfig, LogSink, OtelConfig},
+};
+use std::collections::HashMap;
+
+/// Optional numeric env var with a default; present-but-garbage is an error.
+fn env_or<T: FromStr>(key: &'static str, default: T) -> Result<T, String> {
+    match env::var(key) {
+        Ok(v) if !v.trim().is_empty() => v
+            .trim()
+            .parse::<T>()
+            .map_err(|_| format!("{key} must be a number")),
+        _ => Ok(default),
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Settings {
@@ -10,7 +35,109 @@ pub struct Settings {
     pub app_mode: String,
     pub default_strategy: String,
     pub database_url: String,
+    /// Optional read replica for analytics / reports
+    pub database_read_url: Option<String>,
+    // Postgres pool tuning
+    pub db_max_connections: u32,
+    pub db_min_connections: u32,
+    pub db_replica_max_connections: u32,
+    pub db_acquire_timeout_ms: u64,
+    /// Server-side `statement_timeout`; 0 = unlimited
+    pub db_statement_timeout_ms: u64,
     pub redis_url: String,
+    /// `redis` (default) or `memory` – see `db::cache`
+    pub cache_backend: String,
+    // billing (optional – endpoints answer 503 when unset)
+    pub stripe_secret_key: Option<String>,
+    pub stripe_webhook_secret: Option<String>,
+    pub stripe_price_pro: Option<String>,
+    pub billing_success_url: String,
+    pub billing_cancel_url: String,
+    // copy replication worker pool
+    pub copy_workers: usize,
+    /// Per priority class (exits / entries)
+    pub copy_queue_capacity: usize,
+    /// Max random delay before an entry copy (0 = off)
+    pub copy_jitter_max_ms: u64,
+    /// Max ± size randomisation for entry copies, in percent (0 = off)
+    pub copy_size_jitter_pct: f64,
+    /// Omnibus account for aggregated copy execution (off when unset)
+    pub copy_aggregate_user_id: Option<i64>,
+    /// Parents at least this big are worked with `copy_aggregate_style`
+    pub copy_aggregate_min_size: f64,
+    pub copy_aggregate_style: ParentStyle,
+    // drain mode (rolling deploys)
+    /// Shared secret for `POST /health/drain`; endpoint disabled when unset
+    pub drain_token: Option<String>,
+    pub drain_timeout_secs: u64,
+    // strategy sharding – see `services::sharding`
+    /// `off` (default), `static` or `dynamic`
+    pub shard_mode: String,
+    pub shard_index: u32,
+    pub shard_count: u32,
+    /// Stable node name for sharding and user leases (defaults to `$HOSTNAME`)
+    pub instance_id: String,
+    /// Per-symbol liquidity / trading-hours filters, keyed by normalised symbol
+    pub symbol_filters: HashMap<String, SymbolFilter>,
+    // trading hours per symbol class – see `services::calendar`
+    pub market_calendars: HashMap<SymbolClass, Calendar>,
+    /// Normalised symbol → class; unlisted symbols are crypto
+    pub symbol_classes: HashMap<String, SymbolClass>,
+    // exchange clock sync – see `services::time_sync`
+    pub time_sync_interval_secs: u64,
+    pub clock_skew_alert_ms: i64,
+    /// Ping the exchange REST hosts this often; 0 = off – see `services::keepalive`
+    pub exchange_keepalive_secs: u64,
+    // account anomaly detection – see `services::anomaly`
+    pub anomaly_volume_factor: f64,
+    /// Freeze entries on a volume spike until the user confirms
+    pub anomaly_auto_freeze: bool,
+    /// Days a deleted account's history stays attributable before it is
+    /// anonymised – see `services::account_deletion`
+    pub account_retention_days: i64,
+    /// Fee tier for users without one of their own – see `services::fees`
+    pub default_fees: FeeSchedule,
+    // copy trials – see `services::copy_trading::start_trial`
+    pub copy_trial_days: i64,
+    /// Largest order size a trial follower receives, in exchange contracts
+    pub copy_trial_max_size: f64,
+    // order latency budget – see `trading_engine::OrderTimeouts`
+    pub order_submit_timeout_ms: u64,
+    pub order_followup_timeout_ms: u64,
+    // exchange payload log – see `services::exchange_log`
+    /// Calls kept in the ring buffer; 0 (default) turns the log off
+    pub exchange_log_capacity: usize,
+    // candle storage – see `services::candle_retention`
+    pub candle_retention: Vec<RetentionPolicy>,
+    pub candle_compaction_interval_secs: u64,
+    /// Seal + cross-check recent candles this often; 0 = off – see `services::candle_integrity`
+    pub candle_verify_interval_secs: u64,
+    /// Bars close this long after their boundary, for late exchange updates – see `services::bar_clock`
+    pub candle_close_grace_ms: u64,
+    /// Backtest CPU pool size; 0 = all cores but one – see `services::backtest_pool`
+    pub backtest_threads: usize,
+    /// Backtest jobs running at once across all users – see `services::backtest_queue`
+    pub backtest_max_jobs: usize,
+    /// Where missing candle history comes from; `None` = recorded candles only
+    pub history_source: Option<HistorySource>,
+    /// OTLP collector (`otel` builds); `None` = Prometheus + logs only – see `services::telemetry`
+    pub otlp_endpoint: Option<String>,
+    pub otel_service_name: String,
+    /// Where JSON log lines go – see `services::telemetry`
+    pub log_sinks: Vec<LogSink>,
+    /// Keep 1 in this many DEBUG / TRACE events per call site
+    pub log_debug_sample: u64,
+    /// Per-route request log levels and the verbose-mode token – see `middleware::request_log`
+    pub request_log: RequestLogConfig,
+    /// Calls to `/api/public/stats` per IP and minute; 0 = unlimited
+    pub public_stats_rate_per_min: u32,
+    /// Window notification bursts are digested over; 0 = no digests – see `services::notify`
+    pub notify_digest_secs: u64,
+    /// Token lifetimes and the Discord OAuth app – see `services::auth_tokens`
+    pub auth_tokens: TokenConfig,
+    /// Grace a strategy task gets for newer bus data before it counts as
+    /// stalled and is restarted; 0 = never – see `strategies::heartbeat`
+    pub strategy_stall_secs: u64,
 }
 
 impl Settings {
@@ -33,7 +160,212 @@ impl Settings {
         let default_strategy =
             env::var("DEFAULT_STRATEGY").map_err(|_| "DEFAULT_STRATEGY missing")?;
         let database_url = env::var("DATABASE_URL").map_err(|_| "DATABASE_URL missing")?;
+        let database_read_url = env::var("DATABASE_READ_URL").ok().filter(|s| !s.is_empty());
+        let db_max_connections = env_or("DB_MAX_CONNECTIONS", 5)?;
+        let db_min_connections = env_or("DB_MIN_CONNECTIONS", 0)?;
+        let db_replica_max_connections = env_or("DB_REPLICA_MAX_CONNECTIONS", db_max_connections)?;
+        let db_acquire_timeout_ms = env_or("DB_ACQUIRE_TIMEOUT_MS", 30_000)?;
+        let db_statement_timeout_ms = env_or("DB_STATEMENT_TIMEOUT_MS", 0)?;
         let redis_url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".into());
+        let cache_backend = env::var("CACHE_BACKEND")
+            .map(|s| s.trim().to_lowercase())
+            .unwrap_or_else(|_| "redis".into());
+        if !matches!(cache_backend.as_str(), "redis" | "memory") {
+            return Err("CACHE_BACKEND must be `redis` or `memory`".into());
+        }
+        let stripe_secret_key = env::var("STRIPE_SECRET_KEY").ok();
+        let stripe_webhook_secret = env::var("STRIPE_WEBHOOK_SECRET").ok();
+        let stripe_price_pro = env::var("STRIPE_PRICE_PRO").ok();
+        let billing_success_url = env::var("BILLING_SUCCESS_URL")
+            .unwrap_or_else(|_| "http://localhost:3000/billing/success".into());
+        let billing_cancel_url = env::var("BILLING_CANCEL_URL")
+            .unwrap_or_else(|_| "http://localhost:3000/billing/cancel".into());
+        let copy_workers = env_or("COPY_WORKERS", 4)?;
+        let copy_queue_capacity = env_or("COPY_QUEUE_CAPACITY", 1_000)?;
+        let copy_jitter_max_ms = env_or("COPY_JITTER_MAX_MS", 0)?;
+        let copy_size_jitter_pct: f64 = env_or("COPY_SIZE_JITTER_PCT", 0.0)?;
+        if !(0.0..=50.0).contains(&copy_size_jitter_pct) {
+            return Err("COPY_SIZE_JITTER_PCT must be between 0 and 50".into());
+        }
+        let copy_aggregate_user_id = env::var("COPY_AGGREGATE_USER_ID")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.trim().parse::<i64>())
+            .transpose()
+            .map_err(|_| "COPY_AGGREGATE_USER_ID must be a user id".to_string())?;
+        let copy_aggregate_min_size = env_or("COPY_AGGREGATE_MIN_SIZE", 0.0)?;
+        let copy_aggregate_style = env::var("COPY_AGGREGATE_STYLE")
+            .unwrap_or_else(|_| "twap:5:60".into())
+            .parse::<ParentStyle>()
+            .map_err(|e| format!("COPY_AGGREGATE_STYLE: {e}"))?;
+        let drain_token = env::var("DRAIN_TOKEN").ok().filter(|s| !s.is_empty());
+        let drain_timeout_secs = env_or("DRAIN_TIMEOUT_SECS", 60)?;
+        let shard_mode = env::var("SHARD_MODE")
+            .map(|s| s.trim().to_lowercase())
+            .unwrap_or_else(|_| "off".into());
+        let shard_index = env_or("SHARD_INDEX", 0)?;
+        let shard_count = env_or("SHARD_COUNT", 1)?;
+        match shard_mode.as_str() {
+            "off" | "dynamic" => {}
+            "static" if shard_count >= 1 && shard_index < shard_count => {}
+            "static" => return Err("SHARD_INDEX must be < SHARD_COUNT".into()),
+            _ => return Err("SHARD_MODE must be `off`, `static` or `dynamic`".into()),
+        }
+        // leases and membership live in the cache; a per-process one can't arbitrate
+        if shard_mode != "off" && cache_backend == "memory" {
+            return Err("SHARD_MODE other than `off` needs CACHE_BACKEND=redis".into());
+        }
+        let instance_id = env::var("INSTANCE_ID")
+            .or_else(|_| env::var("HOSTNAME"))
+            .unwrap_or_else(|_| uuid::Uuid::new_v4().to_string());
+        let symbol_filters = crate::services::liquidity::parse_filters(
+            &env::var("SYMBOL_FILTERS").unwrap_or_default(),
+        )
+        .map_err(|e| format!("SYMBOL_FILTERS: {e}"))?;
+        let market_calendars = crate::services::calendar::parse_calendars(
+            &env::var("MARKET_CALENDARS").unwrap_or_else(|_| {
+                "crypto:24/7;equity:America/New_York mon-fri 09:30-16:00;\
+                 fx:America/New_York sun-thu 17:00-17:00"
+                    .into()
+            }),
+        )
+        .map_err(|e| format!("MARKET_CALENDARS: {e}"))?;
+        let symbol_classes = crate::services::calendar::parse_classes(
+            &env::var("SYMBOL_CLASSES").unwrap_or_default(),
+        )
+        .map_err(|e| format!("SYMBOL_CLASSES: {e}"))?;
+        let time_sync_interval_secs = env_or("TIME_SYNC_INTERVAL_SECS", 60)?;
+        if time_sync_interval_secs == 0 {
+            return Err("TIME_SYNC_INTERVAL_SECS must be > 0".into());
+        }
+        let clock_skew_alert_ms = env_or("CLOCK_SKEW_ALERT_MS", 1_000)?;
+        let exchange_keepalive_secs = env_or("EXCHANGE_KEEPALIVE_SECS", 30)?;
+        let anomaly_volume_factor: f64 = env_or("ANOMALY_VOLUME_FACTOR", 100.0)?;
+        if anomaly_volume_factor <= 1.0 {
+            return Err("ANOMALY_VOLUME_FACTOR must be > 1".into());
+        }
+        let anomaly_auto_freeze = env_or("ANOMALY_AUTO_FREEZE", false)
+            .map_err(|_| "ANOMALY_AUTO_FREEZE must be true or false")?;
+        let account_retention_days = env_or("ACCOUNT_RETENTION_DAYS", 30)?;
+        if account_retention_days < 0 {
+            return Err("ACCOUNT_RETENTION_DAYS must be >= 0".into());
+        }
+        let default_fees = FeeSchedule {
+            maker_bps: env_or("DEFAULT_MAKER_FEE_BPS", FeeSchedule::default().maker_bps)?,
+            taker_bps: env_or("DEFAULT_TAKER_FEE_BPS", FeeSchedule::default().taker_bps)?,
+        };
+        default_fees
+            .validate()
+            .map_err(|e| format!("DEFAULT_*_FEE_BPS: {e}"))?;
+        let copy_trial_days = env_or("COPY_TRIAL_DAYS", 7)?;
+        if !(1..=30).contains(&copy_trial_days) {
+            return Err("COPY_TRIAL_DAYS must be between 1 and 30".into());
+        }
+        let copy_trial_max_size: f64 = env_or("COPY_TRIAL_MAX_SIZE", 1.0)?;
+        if copy_trial_max_size <= 0.0 {
+            return Err("COPY_TRIAL_MAX_SIZE must be > 0".into());
+        }
+        let order_submit_timeout_ms = env_or("ORDER_SUBMIT_TIMEOUT_MS", 5_000)?;
+        let order_followup_timeout_ms = env_or("ORDER_FOLLOWUP_TIMEOUT_MS", 3_000)?;
+        if order_submit_timeout_ms == 0 || order_followup_timeout_ms == 0 {
+            return Err("ORDER_*_TIMEOUT_MS must be > 0".into());
+        }
+        let exchange_log_capacity = env_or("EXCHANGE_LOG_CAPACITY", 0)?;
+        let candle_retention = crate::services::candle_retention::parse_policies(
+            &env::var("CANDLE_RETENTION").unwrap_or_else(|_| "1m:90d>1h".into()),
+        )
+        .map_err(|e| format!("CANDLE_RETENTION: {e}"))?;
+        let candle_compaction_interval_secs = env_or("CANDLE_COMPACTION_INTERVAL_SECS", 3_600)?;
+        if candle_compaction_interval_secs == 0 {
+            return Err("CANDLE_COMPACTION_INTERVAL_SECS must be > 0".into());
+        }
+        let candle_verify_interval_secs = env_or("CANDLE_VERIFY_INTERVAL_SECS", 3_600)?;
+        let candle_close_grace_ms = env_or("CANDLE_CLOSE_GRACE_MS", 2_000)?;
+        let backtest_threads = env_or("BACKTEST_THREADS", 0)?;
+        let backtest_max_jobs = env_or("BACKTEST_MAX_JOBS", 2)?;
+        if backtest_max_jobs == 0 {
+            return Err("BACKTEST_MAX_JOBS must be > 0".into());
+        }
+        let history_source = match env::var("HISTORY_SOURCE") {
+            Ok(s) if !s.trim().is_empty() => {
+                Some(HistorySource::parse(&s).map_err(|e| format!("HISTORY_SOURCE: {e}"))?)
+            }
+            _ => None,
+        };
+
+        let otlp_endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .ok()
+            .filter(|s| !s.trim().is_empty());
+        let otel_service_name =
+            env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "rustraptor-backend".into());
+        let log_sinks = env::var("LOG_SINKS")
+            .unwrap_or_else(|_| "stdout".into())
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|sink| -> Result<LogSink, String> {
+                match sink {
+                    "stdout" => Ok(LogSink::Stdout),
+                    "file" => Ok(LogSink::File {
+                        dir: env::var("LOG_FILE_DIR")
+                            .unwrap_or_else(|_| "logs".into())
+                            .into(),
+                        prefix: env::var("LOG_FILE_PREFIX")
+                            .unwrap_or_else(|_| "rustraptor.log".into()),
+                        rotation: env::var("LOG_FILE_ROTATION")
+                            .unwrap_or_else(|_| "daily".into())
+                            .parse()
+                            .map_err(|e| format!("LOG_FILE_ROTATION: {e}"))?,
+                        keep: env_or("LOG_FILE_KEEP", 7)?,
+                    }),
+                    "loki" => env::var("LOKI_URL")
+                        .ok()
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|url| LogSink::Loki { url })
+                        .ok_or_else(|| "LOG_SINKS has loki but LOKI_URL is unset".to_string()),
+                    other => Err(format!(
+                        "LOG_SINKS: unknown sink `{other}` (stdout | file | loki)"
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let log_debug_sample = env_or("LOG_DEBUG_SAMPLE", 1)?;
+        if log_debug_sample == 0 {
+            return Err("LOG_DEBUG_SAMPLE must be > 0".into());
+        }
+        let request_log = RequestLogConfig {
+            default: request_log::parse_level(
+                &env::var("REQUEST_LOG_LEVEL").unwrap_or_else(|_| "info".into()),
+            )
+            .map_err(|e| format!("REQUEST_LOG_LEVEL: {e}"))?,
+            routes: RequestLogConfig::parse_routes(
+                &env::var("REQUEST_LOG_ROUTES").unwrap_or_default(),
+            )
+            .map_err(|e| format!("REQUEST_LOG_ROUTES: {e}"))?,
+            verbose_token: env::var("REQUEST_LOG_VERBOSE_TOKEN")
+                .ok()
+                .filter(|s| !s.trim().is_empty()),
+        };
+        let public_stats_rate_per_min = env_or("PUBLIC_STATS_RATE_PER_MIN", 60)?;
+        let notify_digest_secs = env_or("NOTIFY_DIGEST_SECS", 60)?;
+        let strategy_stall_secs = env_or("STRATEGY_STALL_SECS", 120)?;
+        let auth_tokens = TokenConfig {
+            access_ttl_secs: env_or("AUTH_ACCESS_TTL_SECS", 900)?,
+            refresh_ttl_days: env_or("AUTH_REFRESH_TTL_DAYS", 30)?,
+            discord: match (
+                env::var("DISCORD_CLIENT_ID").unwrap_or_default(),
+                env::var("DISCORD_CLIENT_SECRET").unwrap_or_default(),
+            ) {
+                (id, secret) if !id.trim().is_empty() && !secret.trim().is_empty() => {
+                    Some(DiscordOAuth {
+                        client_id: id.trim().to_string(),
+                        client_secret: secret.trim().to_string(),
+                        redirect_uri: env::var("DISCORD_REDIRECT_URI").unwrap_or_default(),
+                    })
+                }
+                _ => None,
+            },
+        };
 
         Ok(Self {
             server_port,
@@ -43,11 +375,82 @@ impl Settings {
             app_mode,
             default_strategy,
             database_url,
+            database_read_url,
+            db_max_connections,
+            db_min_connections,
+            db_replica_max_connections,
+            db_acquire_timeout_ms,
+            db_statement_timeout_ms,
             redis_url,
+            cache_backend,
+            stripe_secret_key,
+            stripe_webhook_secret,
+            stripe_price_pro,
+            billing_success_url,
+            billing_cancel_url,
+            copy_workers,
+            copy_queue_capacity,
+            copy_jitter_max_ms,
+            copy_size_jitter_pct,
+            copy_aggregate_user_id,
+            copy_aggregate_min_size,
+            copy_aggregate_style,
+            drain_token,
+            drain_timeout_secs,
+            shard_mode,
+            shard_index,
+            shard_count,
+            instance_id,
+            symbol_filters,
+            market_calendars,
+            symbol_classes,
+            time_sync_interval_secs,
+            clock_skew_alert_ms,
+            exchange_keepalive_secs,
+            anomaly_volume_factor,
+            anomaly_auto_freeze,
+            account_retention_days,
+            default_fees,
+            copy_trial_days,
+            copy_trial_max_size,
+            order_submit_timeout_ms,
+            order_followup_timeout_ms,
+            exchange_log_capacity,
+            candle_retention,
+            candle_compaction_interval_secs,
+            candle_verify_interval_secs,
+            candle_close_grace_ms,
+            backtest_threads,
+            backtest_max_jobs,
+            history_source,
+            otlp_endpoint,
+            otel_service_name,
+            log_sinks,
+            log_debug_sample,
+            request_log,
+            public_stats_rate_per_min,
+            notify_digest_secs,
+            auth_tokens,
+            strategy_stall_secs,
         })
     }
 
     pub fn is_demo(&self) -> bool {
         self.app_mode == "demo"
     }
+
+    pub fn otel(&self) -> OtelConfig {
+        OtelConfig {
+            endpoint: self.otlp_endpoint.clone(),
+            service_name: self.otel_service_name.clone(),
+        }
+    }
+
+    pub fn logs(&self) -> LogConfig {
+        LogConfig {
+            sinks: self.log_sinks.clone(),
+            debug_sample: self.log_debug_sample,
+            environment: self.app_mode.clone(),
+        }
+    }
 }
@@ -1,12 +1,24 @@
 // src/db/api_keys.rs
 
 pub(crate) use crate::db::models::ApiKey;
-use sqlx::PgPool;
-use uuid::Uuid;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use crate::services::crypto::EnvelopeCrypto;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use sqlx::{FromRow, PgExecutor, PgPool};
+use uuid::Uuid;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// How long decrypted credentials stay in memory. Updates made on this
+/// instance invalidate immediately; other instances catch up within this.
+const CREDS_TTL: Duration = Duration::from_secs(60);
 
 /// **Optional**: Public struct to use when returning decrypted data
-#[derive(Debug, Clone)]
+/// (wiped from memory when dropped)
+#[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct DecryptedApiKey {
     pub api_key: String,
     pub api_secret: String,
@@ -30,9 +42,9 @@ impl ApiKey {
         .await
     }
 
-    #[allow(dead_code)]
-    pub async fn insert(
-        db: &PgPool,
+    /// Seal and store a key; `db` may be a pool or an open transaction.
+    pub async fn insert<'e, E: PgExecutor<'e>>(
+        db: E,
         crypto: &EnvelopeCrypto,
         user_id: i64,
         exchange: &str,
@@ -40,15 +52,18 @@ impl ApiKey {
         secret_plain: &str,
         passphrase_plain: Option<&str>,
     ) -> sqlx::Result<Uuid> {
-        let (wrapped_key, nonce_k, ct_k) = crypto.seal(api_key_plain.as_bytes());
-        let (_, nonce_s, ct_s)          = crypto.seal(secret_plain.as_bytes());
-        let (wrapped_pp, nonce_p, ct_p) = if let Some(pp) = passphrase_plain {
-            let (wk, n, c) = crypto.seal(pp.as_bytes());
-            (Some(wk), Some(n), Some(c))
-        } else { (None, None, None) };
+        let Sealed {
+            wrapped_key,
+            nonce_k,
+            ct_k,
+            nonce_s,
+            ct_s,
+            nonce_p,
+            ct_p,
+        } = Sealed::new(crypto, api_key_plain, secret_plain, passphrase_plain);
 
         let rec = sqlx::query!(
-        r#"INSERT INTO api_keys (
+            r#"INSERT INTO api_keys (
                user_id, exchange,
                encrypted_data_key,
                nonce_key, encrypted_api_key,
@@ -56,30 +71,265 @@ impl ApiKey {
                nonce_passphrase, encrypted_passphrase
            ) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)
            RETURNING key_id"#,
-        user_id, exchange,
-        wrapped_key,
-        nonce_k, ct_k,
-        nonce_s, ct_s,
-        nonce_p, ct_p
-    )
-            .fetch_one(db)
-            .await?;
+            user_id,
+            exchange,
+            wrapped_key,
+            nonce_k,
+            ct_k,
+            nonce_s,
+            ct_s,
+            nonce_p,
+            ct_p
+        )
+        .fetch_one(db)
+        .await?;
+        forget_creds(user_id, exchange);
         Ok(rec.key_id)
     }
-    pub fn decrypt(&self, crypto:&EnvelopeCrypto)
-                   -> anyhow::Result<DecryptedApiKey> {
+
+    /// Replace the credentials behind `key_id` (rotation) under a fresh data
+    /// key; returns the key's exchange, `None` when it isn't the user's.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn reseal(
+        db: &PgPool,
+        crypto: &EnvelopeCrypto,
+        user_id: i64,
+        key_id: Uuid,
+        api_key_plain: &str,
+        secret_plain: &str,
+        passphrase_plain: Option<&str>,
+    ) -> sqlx::Result<Option<String>> {
+        let s = Sealed::new(crypto, api_key_plain, secret_plain, passphrase_plain);
+        let exchange: Option<String> = sqlx::query_scalar(
+            r#"
+            UPDATE api_keys
+               SET encrypted_data_key   = $3,
+                   nonce_key            = $4, encrypted_api_key    = $5,
+                   nonce_secret         = $6, encrypted_secret     = $7,
+                   nonce_passphrase     = $8, encrypted_passphrase = $9,
+                   rotated_at           = now()
+             WHERE key_id = $1 AND user_id = $2
+            RETURNING exchange
+            "#,
+        )
+        .bind(key_id)
+        .bind(user_id)
+        .bind(s.wrapped_key)
+        .bind(s.nonce_k)
+        .bind(s.ct_k)
+        .bind(s.nonce_s)
+        .bind(s.ct_s)
+        .bind(s.nonce_p)
+        .bind(s.ct_p)
+        .fetch_optional(db)
+        .await?;
+        if let Some(exchange) = &exchange {
+            forget_creds(user_id, exchange);
+        }
+        Ok(exchange)
+    }
+
+    /// Every key the user stored, oldest first
+    pub async fn list_for_user(db: &PgPool, user_id: i64) -> sqlx::Result<Vec<StoredKey>> {
+        sqlx::query_as::<_, StoredKey>(
+            "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at, key_id",
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+    }
+
+    pub async fn get_for_user(
+        db: &PgPool,
+        user_id: i64,
+        key_id: Uuid,
+    ) -> sqlx::Result<Option<StoredKey>> {
+        sqlx::query_as::<_, StoredKey>("SELECT * FROM api_keys WHERE key_id = $1 AND user_id = $2")
+            .bind(key_id)
+            .bind(user_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Remove a key; returns its exchange, `None` when it isn't the user's
+    pub async fn delete(db: &PgPool, user_id: i64, key_id: Uuid) -> sqlx::Result<Option<String>> {
+        let exchange: Option<String> = sqlx::query_scalar(
+            "DELETE FROM api_keys WHERE key_id = $1 AND user_id = $2 RETURNING exchange",
+        )
+        .bind(key_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+        if let Some(exchange) = &exchange {
+            forget_creds(user_id, exchange);
+        }
+        Ok(exchange)
+    }
+
+    pub fn decrypt(&self, crypto: &EnvelopeCrypto) -> anyhow::Result<DecryptedApiKey> {
         Ok(DecryptedApiKey {
-            api_key: crypto.open(&self.encrypted_data_key,
-                                 &self.nonce_key,
-                                 &self.encrypted_api_key)?,
-            api_secret: crypto.open(&self.encrypted_data_key,
-                                    &self.nonce_secret,
-                                    &self.encrypted_secret)?,
-            api_passphrase: self.encrypted_passphrase.as_ref()
+            api_key: crypto.open(
+                &self.encrypted_data_key,
+                &self.nonce_key,
+                &self.encrypted_api_key,
+            )?,
+            api_secret: crypto.open(
+                &self.encrypted_data_key,
+                &self.nonce_secret,
+                &self.encrypted_secret,
+            )?,
+            api_passphrase: self
+                .encrypted_passphrase
+                .as_ref()
                 .zip(self.nonce_passphrase.as_ref())
-                .map(|(ct,nonce)| crypto.open(&self.encrypted_data_key, nonce, ct))
+                .map(|(ct, nonce)| crypto.open(&self.encrypted_data_key, nonce, ct))
                 .transpose()?
                 .unwrap_or_default(),
         })
     }
 }
+
+/// A stored key with when its credentials were last replaced
+#[derive(Debug, FromRow)]
+pub struct StoredKey {
+    #[sqlx(flatten)]
+    pub key: ApiKey,
+    pub rotated_at: Option<DateTime<Utc>>,
+}
+
+/// One row's credentials sealed under a single data key – the row keeps
+/// one wrapped key, so every field must open with it
+struct Sealed {
+    wrapped_key: Vec<u8>,
+    nonce_k: Vec<u8>,
+    ct_k: Vec<u8>,
+    nonce_s: Vec<u8>,
+    ct_s: Vec<u8>,
+    nonce_p: Option<Vec<u8>>,
+    ct_p: Option<Vec<u8>>,
+}
+
+impl Sealed {
+    fn new(crypto: &EnvelopeCrypto, api_key: &str, secret: &str, passphrase: Option<&str>) -> Self {
+        let mut fields = vec![api_key.as_bytes(), secret.as_bytes()];
+        fields.extend(passphrase.map(str::as_bytes));
+        let (wrapped_key, sealed) = crypto.seal_all(&fields);
+        let mut sealed = sealed.into_iter();
+        let (nonce_k, ct_k) = sealed.next().expect("sealed api key");
+        let (nonce_s, ct_s) = sealed.next().expect("sealed secret");
+        let (nonce_p, ct_p) = sealed.next().unzip();
+        Self {
+            wrapped_key,
+            nonce_k,
+            ct_k,
+            nonce_s,
+            ct_s,
+            nonce_p,
+            ct_p,
+        }
+    }
+}
+
+// ──────────────────────────────────────────────────────────────
+//  Decrypted-credential cache
+// ──────────────────────────────────────────────────────────────
+#[derive(thiserror::Error, Debug)]
+pub enum CredsError {
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("decrypt failed: {0}")]
+    Decrypt(anyhow::Error),
+}
+
+/// `(user_id, exchange)` → (decrypted at, credentials)
+type CredsSlot = (Instant, Arc<DecryptedApiKey>);
+
+/// Entries hold the only long-lived copy; the `Arc` lets an eviction drop
+/// (and zeroize) it without waiting on readers.
+static CREDS: Lazy<DashMap<(i64, String), CredsSlot>> = Lazy::new(DashMap::new);
+
+/// Drop cached credentials for `(user_id, exchange)` – call on key changes
+pub fn forget_creds(user_id: i64, exchange: &str) {
+    CREDS.remove(&(user_id, exchange.to_string()));
+}
+
+fn sweep_expired() {
+    CREDS.retain(|_, (at, _)| at.elapsed() < CREDS_TTL);
+}
+
+/// Cached credentials still fresh at `now`
+fn cached(user_id: i64, exchange: &str, now: Instant) -> Option<DecryptedApiKey> {
+    let hit = CREDS.get(&(user_id, exchange.to_string()))?;
+    (now.saturating_duration_since(hit.0) < CREDS_TTL).then(|| (*hit.1).clone())
+}
+
+fn remember(user_id: i64, exchange: &str, creds: &DecryptedApiKey, at: Instant) {
+    CREDS.insert(
+        (user_id, exchange.to_string()),
+        (at, Arc::new(creds.clone())),
+    );
+}
+
+impl ApiKey {
+    /// Decrypted credentials, served from memory for `CREDS_TTL` so hot
+    /// strategies skip the sealed-box + AES-GCM round trip on every order.
+    /// `Ok(None)` when the user has no key for `exchange`.
+    pub async fn decrypted_cached(
+        db: &PgPool,
+        crypto: &EnvelopeCrypto,
+        user_id: i64,
+        exchange: &str,
+    ) -> Result<Option<DecryptedApiKey>, CredsError> {
+        if let Some(hit) = cached(user_id, exchange, Instant::now()) {
+            return Ok(Some(hit));
+        }
+        sweep_expired();
+
+        let Some(row) = Self::get_by_user_and_exchange(db, user_id, exchange).await? else {
+            return Ok(None);
+        };
+        let creds = row.decrypt(crypto).map_err(CredsError::Decrypt)?;
+        remember(user_id, exchange, &creds, Instant::now());
+        Ok(Some(creds))
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creds(secret: &str) -> DecryptedApiKey {
+        DecryptedApiKey {
+            api_key: "key".into(),
+            api_secret: secret.into(),
+            api_passphrase: String::new(),
+        }
+    }
+
+    // user ids are per test: the cache is process-wide
+    #[test]
+    fn cached_creds_expire_after_the_ttl() {
+        let now = Instant::now();
+        remember(9_001, "blowfin", &creds("s"), now);
+        assert_eq!(cached(9_001, "blowfin", now).unwrap().api_secret, "s");
+        assert!(cached(9_001, "blowfin", now + CREDS_TTL - Duration::from_secs(1)).is_some());
+        assert!(cached(9_001, "blowfin", now + CREDS_TTL).is_none());
+    }
+
+    #[test]
+    fn forget_evicts_only_that_slot() {
+        let now = Instant::now();
+        remember(9_002, "blowfin", &creds("mine"), now);
+        remember(9_003, "blowfin", &creds("theirs"), now);
+        // another user's slot is never served
+        assert_eq!(cached(9_003, "blowfin", now).unwrap().api_secret, "theirs");
+        assert!(cached(9_002, "binance", now).is_none());
+
+        forget_creds(9_002, "blowfin");
+        assert!(cached(9_002, "blowfin", now).is_none());
+        assert!(cached(9_003, "blowfin", now).is_some());
+    }
+}
@@ -0,0 +1,220 @@
+//  src/db/batch.rs
+//! Generic batched writer for high-frequency inserts.
+//!
+//! Producers push rows into a bounded channel; one task per table drains it
+//! and issues multi-row `INSERT … VALUES (…), (…)` statements whenever
+//! `max_rows` are buffered or `flush_every` elapses – whichever comes first.
+//! A drain (`services::drain`) flushes at once instead of on the next tick.
+//!
+//! ```ignore
+//! let w = BatchWriter::<AuditRow>::spawn(pool.clone(), BatchConfig::default());
+//! w.try_push(row);            // never blocks the hot path
+//! ```
+
+use metrics::{histogram, increment_counter};
+use sqlx::query_builder::Separated;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::services::drain;
+
+/// Postgres caps a statement at 65 535 bind parameters
+const PG_MAX_BINDS: usize = 65_535;
+
+/// A row type that knows how to bind itself into a multi-row INSERT.
+pub trait BatchRow: Send + Sync + 'static {
+    const TABLE: &'static str;
+    const COLUMNS: &'static [&'static str];
+    /// Appended verbatim, e.g. `"ON CONFLICT DO NOTHING"`
+    const ON_CONFLICT: &'static str = "";
+
+    /// Push exactly `COLUMNS.len()` binds, in column order.
+    fn bind_row<'args>(&self, b: &mut Separated<'_, 'args, Postgres, &'static str>);
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    pub max_rows: usize,
+    pub flush_every: Duration,
+    /// Channel capacity; `try_push` drops rows beyond this
+    pub capacity: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_rows: 500,
+            flush_every: Duration::from_millis(500),
+            capacity: 10_000,
+        }
+    }
+}
+
+/// Cheap-to-clone producer handle.
+pub struct BatchWriter<T: BatchRow> {
+    tx: mpsc::Sender<T>,
+}
+
+impl<T: BatchRow> Clone for BatchWriter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<T: BatchRow> BatchWriter<T> {
+    /// Start the flush task. It exits (after a final flush) once every
+    /// writer handle has been dropped.
+    pub fn spawn(pool: PgPool, cfg: BatchConfig) -> Self {
+        let (tx, rx) = mpsc::channel(cfg.capacity.max(1));
+        tokio::spawn(run(pool, cfg, rx));
+        Self { tx }
+    }
+
+    /// Back-pressure variant: waits for channel space.
+    pub async fn push(&self, row: T) {
+        if self.tx.send(row).await.is_err() {
+            tracing::error!("batch writer for {} is gone – row dropped", T::TABLE);
+        }
+    }
+
+    /// Non-blocking; drops (and counts) the row when the buffer is full.
+    pub fn try_push(&self, row: T) -> bool {
+        match self.tx.try_send(row) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                increment_counter!("batch_rows_dropped_total", "table" => T::TABLE);
+                false
+            }
+            Err(TrySendError::Closed(_)) => {
+                tracing::error!("batch writer for {} is gone – row dropped", T::TABLE);
+                false
+            }
+        }
+    }
+}
+
+async fn run<T: BatchRow>(pool: PgPool, cfg: BatchConfig, mut rx: mpsc::Receiver<T>) {
+    let mut buf: Vec<T> = Vec::with_capacity(cfg.max_rows);
+    let mut iv = tokio::time::interval(cfg.flush_every);
+    let mut drained = false;
+    loop {
+        tokio::select! {
+            maybe = rx.recv() => match maybe {
+                Some(row) => {
+                    buf.push(row);
+                    if buf.len() >= cfg.max_rows {
+                        flush(&pool, &mut buf).await;
+                    }
+                }
+                None => {
+                    flush(&pool, &mut buf).await;
+                    return;
+                }
+            },
+            _ = iv.tick() => flush(&pool, &mut buf).await,
+            _ = drain::stopping(), if !drained => {
+                drained = true;
+                let _in_flight = drain::track();
+                flush(&pool, &mut buf).await;
+            }
+        }
+    }
+}
+
+async fn flush<T: BatchRow>(pool: &PgPool, buf: &mut Vec<T>) {
+    if buf.is_empty() {
+        return;
+    }
+    let started = Instant::now();
+    // one retry for transient failures, then give up on this batch
+    let mut res = insert_rows(pool, buf).await;
+    if let Err(e) = &res {
+        tracing::warn!("batch insert into {} failed ({e}) – retrying", T::TABLE);
+        res = insert_rows(pool, buf).await;
+    }
+    match res {
+        Ok(()) => {
+            histogram!("batch_flush_rows", buf.len() as f64, "table" => T::TABLE);
+            histogram!(
+                "batch_flush_ms",
+                started.elapsed().as_secs_f64() * 1_000.0,
+                "table" => T::TABLE
+            );
+        }
+        Err(e) => {
+            increment_counter!("batch_flush_errors_total", "table" => T::TABLE);
+            tracing::error!(
+                "batch insert into {}: dropped {} rows: {e}",
+                T::TABLE,
+                buf.len()
+            );
+        }
+    }
+    buf.clear();
+}
+
+/// Rows per statement that stay under the bind-parameter cap
+fn rows_per_statement(columns: usize) -> usize {
+    (PG_MAX_BINDS / columns.max(1)).max(1)
+}
+
+/// Multi-row INSERT, chunked to respect the bind limit. Usable directly
+/// for one-off writes that want the same SQL as the batched path.
+pub async fn insert_rows<T: BatchRow>(pool: &PgPool, rows: &[T]) -> Result<(), sqlx::Error> {
+    for chunk in rows.chunks(rows_per_statement(T::COLUMNS.len())) {
+        let mut qb = insert_builder(chunk);
+        qb.build().execute(pool).await?;
+    }
+    Ok(())
+}
+
+fn insert_builder<T: BatchRow>(rows: &[T]) -> QueryBuilder<'static, Postgres> {
+    let mut qb = QueryBuilder::new(format!(
+        "INSERT INTO {} ({}) ",
+        T::TABLE,
+        T::COLUMNS.join(", ")
+    ));
+    qb.push_values(rows, |mut b, row| row.bind_row(&mut b));
+    if !T::ON_CONFLICT.is_empty() {
+        qb.push(" ").push(T::ON_CONFLICT);
+    }
+    qb
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Row(i64, String);
+    impl BatchRow for Row {
+        const TABLE: &'static str = "t";
+        const COLUMNS: &'static [&'static str] = &["a", "b"];
+        const ON_CONFLICT: &'static str = "ON CONFLICT DO NOTHING";
+        fn bind_row<'args>(&self, b: &mut Separated<'_, 'args, Postgres, &'static str>) {
+            b.push_bind(self.0).push_bind(self.1.clone());
+        }
+    }
+
+    #[test]
+    fn builds_multi_row_insert() {
+        let rows = [Row(1, "x".into()), Row(2, "y".into())];
+        let sql = insert_builder(&rows).into_sql();
+        assert_eq!(
+            sql,
+            "INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4) ON CONFLICT DO NOTHING"
+        );
+    }
+
+    #[test]
+    fn chunks_respect_bind_limit() {
+        assert_eq!(rows_per_statement(2), 32_767);
+        assert!(rows_per_statement(10) * 10 <= PG_MAX_BINDS);
+        assert_eq!(rows_per_statement(0), PG_MAX_BINDS);
+    }
+}
@@ -0,0 +1,619 @@
+//  src/db/cache.rs
+//! ──────────────────────────────────────────────────────────────────────────
+//! Key/value cache abstraction
+//! ──────────────────────────────────────────────────────────────────────────
+//! Services talk to `dyn Cache` instead of `RedisPool` so a laptop without a
+//! Redis server can still run the API (`CACHE_BACKEND=memory`).
+//!
+//! * `RedisPool`   – production backend, shared across instances
+//! * `MemoryCache` – per-process `DashMap` with lazy TTL eviction
+//!
+//! Only the handful of commands we actually use are modelled: strings /
+//! counters (incl. `SET NX` claims for HMAC nonces and compare-and-set
+//! leases for strategy sharding), sets (follower lists) and lists (draw-down
+//! window, spilled candle history).
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use dashmap::{mapref::entry::Entry as MapEntry, DashMap};
+use redis::AsyncCommands;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::redis::RedisPool;
+
+pub type SharedCache = Arc<dyn Cache>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("redis: {0}")]
+    Redis(#[from] redis::RedisError),
+    #[error("serde: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error("wrong type for key {0}")]
+    WrongType(String),
+    #[error("value at {0} is not an integer")]
+    NotInteger(String),
+    /// Injected by `services::chaos`
+    #[cfg(feature = "chaos")]
+    #[error("chaos: {0}")]
+    Chaos(String),
+}
+
+/// Backend-agnostic subset of Redis. `ttl_secs == 0` means "no expiry".
+#[async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError>;
+    async fn set(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), CacheError>;
+    /// Set only if absent (Redis `SET NX`); `false` when the key exists
+    async fn set_nx(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError>;
+    async fn del(&self, key: &str) -> Result<(), CacheError>;
+    /// Reset the TTL only while `key` still holds `value`; `false` otherwise
+    /// (lease renewal — a lease someone else took over is left alone)
+    async fn renew_if(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError>;
+    /// Delete only while `key` still holds `value` (lease release)
+    async fn del_if(&self, key: &str, value: &str) -> Result<bool, CacheError>;
+    /// Atomic add; missing keys start at 0 (Redis `INCRBY`)
+    async fn incr_by(&self, key: &str, by: i64) -> Result<i64, CacheError>;
+    async fn expire(&self, key: &str, ttl_secs: u64) -> Result<(), CacheError>;
+
+    async fn sadd(&self, key: &str, members: &[String]) -> Result<(), CacheError>;
+    async fn srem(&self, key: &str, member: &str) -> Result<(), CacheError>;
+    async fn smembers(&self, key: &str) -> Result<Vec<String>, CacheError>;
+
+    async fn lpush(&self, key: &str, value: &str) -> Result<(), CacheError>;
+    /// Whole list, head first (Redis `LRANGE key 0 -1`)
+    async fn lrange_all(&self, key: &str) -> Result<Vec<String>, CacheError>;
+    /// Keep the first `keep` entries (Redis `LTRIM key 0 keep-1`)
+    async fn ltrim(&self, key: &str, keep: usize) -> Result<(), CacheError>;
+}
+
+impl dyn Cache + '_ {
+    pub async fn set_json<T>(&self, key: &str, value: &T, ttl_secs: u64) -> Result<(), CacheError>
+    where
+        T: Serialize + ?Sized + Sync,
+    {
+        let payload = serde_json::to_string(value)?;
+        self.set(key, &payload, ttl_secs).await
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, CacheError> {
+        match self.get(key).await? {
+            Some(s) => Ok(Some(serde_json::from_str(&s)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Integer value, `0` when missing
+    pub async fn get_i64(&self, key: &str) -> Result<i64, CacheError> {
+        match self.get(key).await? {
+            Some(s) => s
+                .parse()
+                .map_err(|_| CacheError::NotInteger(key.to_string())),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Build the backend named by `CACHE_BACKEND`. `memory` never touches the
+/// network; anything else needs a live Redis connection.
+pub fn from_backend(backend: &str, redis: Option<RedisPool>) -> Option<SharedCache> {
+    match backend {
+        "memory" => Some(Arc::new(MemoryCache::new())),
+        _ => redis.map(|r| Arc::new(r) as SharedCache),
+    }
+}
+
+// ─── Redis ────────────────────────────────────────────────────────────────
+#[async_trait]
+impl Cache for RedisPool {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        Ok(con.get(key).await?)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        if ttl_secs == 0 {
+            con.set::<_, _, ()>(key, value).await?;
+        } else {
+            con.set_ex::<_, _, ()>(key, value, ttl_secs).await?;
+        }
+        Ok(())
+    }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value).arg("NX");
+        if ttl_secs > 0 {
+            cmd.arg("EX").arg(ttl_secs);
+        }
+        // `OK` when set, nil when the key already existed
+        let reply: Option<String> = cmd.query_async(&mut con).await?;
+        Ok(reply.is_some())
+    }
+
+    async fn del(&self, key: &str) -> Result<(), CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        con.del::<_, ()>(key).await?;
+        Ok(())
+    }
+
+    async fn renew_if(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        // compare and extend in one round trip; `PERSIST` for ttl 0
+        let renewed: i64 = redis::cmd("EVAL")
+            .arg(
+                "if redis.call('GET', KEYS[1]) ~= ARGV[1] then return 0 end \
+                 if ARGV[2] == '0' then redis.call('PERSIST', KEYS[1]) \
+                 else redis.call('EXPIRE', KEYS[1], ARGV[2]) end return 1",
+            )
+            .arg(1)
+            .arg(key)
+            .arg(value)
+            .arg(ttl_secs)
+            .query_async(&mut con)
+            .await?;
+        Ok(renewed == 1)
+    }
+
+    async fn del_if(&self, key: &str, value: &str) -> Result<bool, CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        let deleted: i64 = redis::cmd("EVAL")
+            .arg(
+                "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+                 return redis.call('DEL', KEYS[1]) end return 0",
+            )
+            .arg(1)
+            .arg(key)
+            .arg(value)
+            .query_async(&mut con)
+            .await?;
+        Ok(deleted == 1)
+    }
+
+    async fn incr_by(&self, key: &str, by: i64) -> Result<i64, CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        Ok(con.incr(key, by).await?)
+    }
+
+    async fn expire(&self, key: &str, ttl_secs: u64) -> Result<(), CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        con.expire::<_, ()>(key, ttl_secs as i64).await?;
+        Ok(())
+    }
+
+    async fn sadd(&self, key: &str, members: &[String]) -> Result<(), CacheError> {
+        if members.is_empty() {
+            return Ok(());
+        }
+        let mut con = self.manager().as_ref().clone();
+        con.sadd::<_, _, ()>(key, members).await?;
+        Ok(())
+    }
+
+    async fn srem(&self, key: &str, member: &str) -> Result<(), CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        con.srem::<_, _, ()>(key, member).await?;
+        Ok(())
+    }
+
+    async fn smembers(&self, key: &str) -> Result<Vec<String>, CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        Ok(con.smembers(key).await?)
+    }
+
+    async fn lpush(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        con.lpush::<_, _, ()>(key, value).await?;
+        Ok(())
+    }
+
+    async fn lrange_all(&self, key: &str) -> Result<Vec<String>, CacheError> {
+        let mut con = self.manager().as_ref().clone();
+        Ok(con.lrange(key, 0, -1).await?)
+    }
+
+    async fn ltrim(&self, key: &str, keep: usize) -> Result<(), CacheError> {
+        if keep == 0 {
+            return self.del(key).await;
+        }
+        let mut con = self.manager().as_ref().clone();
+        con.ltrim::<_, ()>(key, 0, keep as isize - 1).await?;
+        Ok(())
+    }
+}
+
+// ─── In-memory ────────────────────────────────────────────────────────────
+#[derive(Debug, Clone)]
+enum Value {
+    Str(String),
+    Set(HashSet<String>),
+    List(VecDeque<String>),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|t| t > now)
+    }
+}
+
+/// Writes between full sweeps of expired keys
+const SWEEP_EVERY: usize = 1024;
+
+/// Single-process stand-in for Redis (dev / tests). Expired keys are dropped
+/// on access and by a periodic sweep piggy-backed on writes.
+#[derive(Default)]
+pub struct MemoryCache {
+    map: DashMap<String, Entry>,
+    writes: AtomicUsize,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn ttl(ttl_secs: u64) -> Option<Instant> {
+        (ttl_secs > 0).then(|| Instant::now() + Duration::from_secs(ttl_secs))
+    }
+
+    /// Drop every expired key
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        self.map.retain(|_, e| e.live(now));
+    }
+
+    fn on_write(&self) {
+        if self.writes.fetch_add(1, Ordering::Relaxed) % SWEEP_EVERY == SWEEP_EVERY - 1 {
+            self.purge_expired();
+        }
+    }
+
+    /// Live entry for `key`, evicting it first if it has expired
+    fn live_entry(&self, key: &str) -> Option<dashmap::mapref::one::RefMut<'_, String, Entry>> {
+        let now = Instant::now();
+        self.map.remove_if(key, |_, e| !e.live(now));
+        self.map.get_mut(key)
+    }
+
+    /// Mutate (or create) the entry at `key`, keeping any TTL already set
+    fn upsert<R>(
+        &self,
+        key: &str,
+        init: impl FnOnce() -> Value,
+        f: impl FnOnce(&mut Value) -> Result<R, CacheError>,
+    ) -> Result<R, CacheError> {
+        self.on_write();
+        let now = Instant::now();
+        self.map.remove_if(key, |_, e| !e.live(now));
+        let mut e = self.map.entry(key.to_string()).or_insert_with(|| Entry {
+            value: init(),
+            expires_at: None,
+        });
+        f(&mut e.value)
+    }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        match self.live_entry(key).as_deref() {
+            Some(Entry {
+                value: Value::Str(s),
+                ..
+            }) => Ok(Some(s.clone())),
+            Some(_) => Err(CacheError::WrongType(key.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), CacheError> {
+        self.on_write();
+        self.map.insert(
+            key.to_string(),
+            Entry {
+                value: Value::Str(value.to_string()),
+                expires_at: Self::ttl(ttl_secs),
+            },
+        );
+        Ok(())
+    }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        self.on_write();
+        let now = Instant::now();
+        self.map.remove_if(key, |_, e| !e.live(now));
+        match self.map.entry(key.to_string()) {
+            MapEntry::Occupied(_) => Ok(false),
+            MapEntry::Vacant(slot) => {
+                slot.insert(Entry {
+                    value: Value::Str(value.to_string()),
+                    expires_at: Self::ttl(ttl_secs),
+                });
+                Ok(true)
+            }
+        }
+    }
+
+    async fn del(&self, key: &str) -> Result<(), CacheError> {
+        self.map.remove(key);
+        Ok(())
+    }
+
+    async fn renew_if(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        Ok(match self.live_entry(key) {
+            Some(mut e) if matches!(&e.value, Value::Str(s) if s == value) => {
+                e.expires_at = Self::ttl(ttl_secs);
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn del_if(&self, key: &str, value: &str) -> Result<bool, CacheError> {
+        let now = Instant::now();
+        let held = |e: &Entry| e.live(now) && matches!(&e.value, Value::Str(s) if s == value);
+        Ok(self.map.remove_if(key, |_, e| held(e)).is_some())
+    }
+
+    async fn incr_by(&self, key: &str, by: i64) -> Result<i64, CacheError> {
+        self.upsert(
+            key,
+            || Value::Str("0".into()),
+            |v| match v {
+                Value::Str(s) => {
+                    let n = s
+                        .parse::<i64>()
+                        .map_err(|_| CacheError::NotInteger(key.to_string()))?
+                        + by;
+                    *s = n.to_string();
+                    Ok(n)
+                }
+                _ => Err(CacheError::WrongType(key.to_string())),
+            },
+        )
+    }
+
+    async fn expire(&self, key: &str, ttl_secs: u64) -> Result<(), CacheError> {
+        if let Some(mut e) = self.live_entry(key) {
+            e.expires_at = Self::ttl(ttl_secs);
+        }
+        Ok(())
+    }
+
+    async fn sadd(&self, key: &str, members: &[String]) -> Result<(), CacheError> {
+        if members.is_empty() {
+            return Ok(());
+        }
+        self.upsert(
+            key,
+            || Value::Set(HashSet::new()),
+            |v| match v {
+                Value::Set(set) => {
+                    set.extend(members.iter().cloned());
+                    Ok(())
+                }
+                _ => Err(CacheError::WrongType(key.to_string())),
+            },
+        )
+    }
+
+    async fn srem(&self, key: &str, member: &str) -> Result<(), CacheError> {
+        match self.live_entry(key).as_deref_mut() {
+            Some(Entry {
+                value: Value::Set(set),
+                ..
+            }) => {
+                set.remove(member);
+                Ok(())
+            }
+            Some(_) => Err(CacheError::WrongType(key.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    async fn smembers(&self, key: &str) -> Result<Vec<String>, CacheError> {
+        match self.live_entry(key).as_deref() {
+            Some(Entry {
+                value: Value::Set(set),
+                ..
+            }) => Ok(set.iter().cloned().collect()),
+            Some(_) => Err(CacheError::WrongType(key.to_string())),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn lpush(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.upsert(
+            key,
+            || Value::List(VecDeque::new()),
+            |v| match v {
+                Value::List(list) => {
+                    list.push_front(value.to_string());
+                    Ok(())
+                }
+                _ => Err(CacheError::WrongType(key.to_string())),
+            },
+        )
+    }
+
+    async fn lrange_all(&self, key: &str) -> Result<Vec<String>, CacheError> {
+        match self.live_entry(key).as_deref() {
+            Some(Entry {
+                value: Value::List(list),
+                ..
+            }) => Ok(list.iter().cloned().collect()),
+            Some(_) => Err(CacheError::WrongType(key.to_string())),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn ltrim(&self, key: &str, keep: usize) -> Result<(), CacheError> {
+        let Some(mut e) = self.live_entry(key) else {
+            return Ok(());
+        };
+        match &mut e.value {
+            Value::List(list) => list.truncate(keep),
+            _ => return Err(CacheError::WrongType(key.to_string())),
+        }
+        let empty = matches!(&e.value, Value::List(l) if l.is_empty());
+        drop(e);
+        if empty {
+            self.map.remove(key);
+        }
+        Ok(())
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> SharedCache {
+        Arc::new(MemoryCache::new())
+    }
+
+    #[tokio::test]
+    async fn set_get_del_roundtrip() {
+        let c = cache();
+        c.set("k", "v", 0).await.unwrap();
+        assert_eq!(c.get("k").await.unwrap().as_deref(), Some("v"));
+        c.del("k").await.unwrap();
+        assert_eq!(c.get("k").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn ttl_expires_entries() {
+        let mem = MemoryCache::new();
+        mem.set("k", "v", 60).await.unwrap();
+        assert!(mem.get("k").await.unwrap().is_some());
+
+        // force the deadline into the past instead of sleeping
+        mem.map.get_mut("k").unwrap().expires_at = Some(Instant::now() - Duration::from_secs(1));
+        assert_eq!(mem.get("k").await.unwrap(), None);
+        assert!(mem.map.is_empty(), "expired key should be evicted on read");
+    }
+
+    #[tokio::test]
+    async fn incr_starts_at_zero_and_keeps_ttl() {
+        let mem = MemoryCache::new();
+        assert_eq!(mem.incr_by("n", 5).await.unwrap(), 5);
+        mem.expire("n", 30).await.unwrap();
+        assert_eq!(mem.incr_by("n", -2).await.unwrap(), 3);
+        assert!(mem.map.get("n").unwrap().expires_at.is_some());
+
+        let c: SharedCache = Arc::new(mem);
+        assert_eq!(c.get_i64("n").await.unwrap(), 3);
+        assert_eq!(c.get_i64("missing").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn set_nx_only_claims_absent_or_expired_keys() {
+        let mem = MemoryCache::new();
+        assert!(mem.set_nx("n", "1", 60).await.unwrap());
+        assert!(!mem.set_nx("n", "2", 60).await.unwrap());
+        assert_eq!(mem.get("n").await.unwrap().as_deref(), Some("1"));
+
+        mem.map.get_mut("n").unwrap().expires_at = Some(Instant::now() - Duration::from_secs(1));
+        assert!(mem.set_nx("n", "3", 60).await.unwrap());
+        assert_eq!(mem.get("n").await.unwrap().as_deref(), Some("3"));
+    }
+
+    #[tokio::test]
+    async fn renew_and_del_only_touch_a_matching_value() {
+        let mem = MemoryCache::new();
+        assert!(!mem.renew_if("lease", "a", 60).await.unwrap());
+        mem.set("lease", "a", 5).await.unwrap();
+
+        assert!(!mem.renew_if("lease", "b", 60).await.unwrap());
+        assert!(!mem.del_if("lease", "b").await.unwrap());
+        assert!(mem.renew_if("lease", "a", 60).await.unwrap());
+        let left = mem.map.get("lease").unwrap().expires_at.unwrap() - Instant::now();
+        assert!(left > Duration::from_secs(30));
+
+        // an expired lease is nobody's
+        mem.map.get_mut("lease").unwrap().expires_at =
+            Some(Instant::now() - Duration::from_secs(1));
+        assert!(!mem.renew_if("lease", "a", 60).await.unwrap());
+        mem.set("lease", "a", 60).await.unwrap();
+        assert!(mem.del_if("lease", "a").await.unwrap());
+        assert_eq!(mem.get("lease").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn incr_on_non_integer_fails() {
+        let c = cache();
+        c.set("s", "abc", 0).await.unwrap();
+        assert!(matches!(
+            c.incr_by("s", 1).await,
+            Err(CacheError::NotInteger(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sets_deduplicate_and_remove() {
+        let c = cache();
+        c.sadd("f", &["1".into(), "2".into(), "2".into()])
+            .await
+            .unwrap();
+        c.srem("f", "1").await.unwrap();
+        assert_eq!(c.smembers("f").await.unwrap(), vec!["2".to_string()]);
+        assert!(matches!(c.get("f").await, Err(CacheError::WrongType(_))));
+    }
+
+    #[tokio::test]
+    async fn lists_push_to_head() {
+        let c = cache();
+        c.lpush("l", "a").await.unwrap();
+        c.lpush("l", "b").await.unwrap();
+        assert_eq!(c.lrange_all("l").await.unwrap(), vec!["b", "a"]);
+
+        c.lpush("l", "c").await.unwrap();
+        c.ltrim("l", 2).await.unwrap();
+        assert_eq!(c.lrange_all("l").await.unwrap(), vec!["c", "b"]);
+        c.ltrim("l", 0).await.unwrap();
+        assert!(c.lrange_all("l").await.unwrap().is_empty());
+        c.ltrim("missing", 3).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn json_helpers_roundtrip() {
+        let c = cache();
+        c.set_json("j", &vec![1, 2, 3], 0).await.unwrap();
+        let v: Option<Vec<i32>> = c.get_json("j").await.unwrap();
+        assert_eq!(v, Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn purge_drops_only_expired() {
+        let mem = MemoryCache::new();
+        let past = Some(Instant::now() - Duration::from_secs(1));
+        for (k, exp) in [("old", past), ("new", None)] {
+            mem.map.insert(
+                k.into(),
+                Entry {
+                    value: Value::Str("x".into()),
+                    expires_at: exp,
+                },
+            );
+        }
+        mem.purge_expired();
+        assert!(mem.map.contains_key("new"));
+        assert!(!mem.map.contains_key("old"));
+    }
+}
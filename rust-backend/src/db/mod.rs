@@ -1,4 +1,8 @@
 pub(crate) mod api_keys;
+pub mod batch;
+pub mod cache;
 pub(crate) mod models;
+pub mod pool;
 mod queries;
 pub mod redis;
+pub mod replica;
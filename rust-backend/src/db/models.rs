@@ -34,6 +34,7 @@ pub struct ApiKey {
 
 /* -------------------- EXCHANGE ACCOUNTS -------------------- */
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct ExchangeAccount {
     pub acct_id: Uuid,
@@ -46,6 +47,7 @@ pub struct ExchangeAccount {
 
 /* ------------------------- STRATEGIES DEPRECATED ---------------------- */
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct StrategyDeprecated {
     pub strategy_id: Uuid,
@@ -153,6 +155,7 @@ pub struct Balance {
 
 /* ------------------------- COPY EVENTS --------------------- */
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct CopyEvent {
     pub copy_id: Uuid,
@@ -164,6 +167,7 @@ pub struct CopyEvent {
 
 /* -------------------------- AUDIT LOG ---------------------- */
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct AuditLog {
     pub event_id: Uuid,
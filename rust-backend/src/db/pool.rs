@@ -0,0 +1,155 @@
+//  src/db/pool.rs
+//! Postgres pool construction from `Settings` + saturation metrics.
+//!
+//! Exported (per `pool` label: `primary` / `replica`):
+//! * `db_pool_connections` / `db_pool_idle` / `db_pool_in_use` – gauges
+//! * `db_pool_utilization` – in-use ÷ max (0–1)
+//! * `db_pool_acquire_ms`  – time a probe waits for a connection
+//! * `db_pool_acquire_timeouts_total` – probes that could not get one
+
+use metrics::{gauge, histogram, increment_counter};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::{str::FromStr, time::Duration, time::Instant};
+
+use crate::config::settings::Settings;
+
+const METRICS_EVERY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    /// `None` = no server-side statement timeout
+    pub statement_timeout: Option<Duration>,
+}
+
+impl PoolConfig {
+    pub fn primary(s: &Settings) -> Self {
+        Self {
+            max_connections: s.db_max_connections.max(1),
+            min_connections: s.db_min_connections.min(s.db_max_connections),
+            acquire_timeout: Duration::from_millis(s.db_acquire_timeout_ms),
+            statement_timeout: (s.db_statement_timeout_ms > 0)
+                .then(|| Duration::from_millis(s.db_statement_timeout_ms)),
+        }
+    }
+
+    pub fn replica(s: &Settings) -> Self {
+        Self {
+            max_connections: s.db_replica_max_connections.max(1),
+            min_connections: 0,
+            ..Self::primary(s)
+        }
+    }
+
+    fn options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+    }
+
+    fn connect_options(&self, url: &str) -> Result<PgConnectOptions, sqlx::Error> {
+        let opts = PgConnectOptions::from_str(url)?;
+        Ok(match self.statement_timeout {
+            Some(t) => opts.options([("statement_timeout", t.as_millis().to_string())]),
+            None => opts,
+        })
+    }
+
+    pub async fn connect(&self, url: &str) -> Result<PgPool, sqlx::Error> {
+        self.options()
+            .connect_with(self.connect_options(url)?)
+            .await
+    }
+
+    /// No socket is opened until first use
+    pub fn connect_lazy(&self, url: &str) -> Result<PgPool, sqlx::Error> {
+        Ok(self.options().connect_lazy_with(self.connect_options(url)?))
+    }
+}
+
+/// Periodically publish pool gauges and an acquire-latency probe.
+pub fn spawn_pool_metrics(name: &'static str, pool: PgPool, max_connections: u32) {
+    tokio::spawn(async move {
+        let mut iv = tokio::time::interval(METRICS_EVERY);
+        loop {
+            iv.tick().await;
+            if pool.is_closed() {
+                return;
+            }
+            let size = pool.size();
+            let idle = pool.num_idle() as u32;
+            let in_use = size.saturating_sub(idle);
+            gauge!("db_pool_connections", size as f64, "pool" => name);
+            gauge!("db_pool_idle", idle as f64, "pool" => name);
+            gauge!("db_pool_in_use", in_use as f64, "pool" => name);
+            gauge!(
+                "db_pool_utilization",
+                utilization(in_use, max_connections),
+                "pool" => name
+            );
+
+            // acquire probe ≈ what a request would wait right now
+            let started = Instant::now();
+            match pool.acquire().await {
+                Ok(conn) => {
+                    histogram!(
+                        "db_pool_acquire_ms",
+                        started.elapsed().as_secs_f64() * 1_000.0,
+                        "pool" => name
+                    );
+                    drop(conn);
+                }
+                Err(sqlx::Error::PoolTimedOut) => {
+                    increment_counter!("db_pool_acquire_timeouts_total", "pool" => name);
+                    tracing::warn!("db pool '{name}' saturated: acquire timed out");
+                }
+                Err(e) => tracing::warn!("db pool '{name}' probe failed: {e}"),
+            }
+        }
+    });
+}
+
+fn utilization(in_use: u32, max: u32) -> f64 {
+    if max == 0 {
+        0.0
+    } else {
+        in_use as f64 / max as f64
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statement_timeout_is_sent_as_startup_option() {
+        let cfg = PoolConfig {
+            max_connections: 3,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(1),
+            statement_timeout: Some(Duration::from_millis(2_500)),
+        };
+        let opts = cfg.connect_options("postgres://u@localhost/db").unwrap();
+        assert!(format!("{opts:?}").contains("statement_timeout"));
+
+        let off = PoolConfig {
+            statement_timeout: None,
+            ..cfg
+        };
+        let opts = off.connect_options("postgres://u@localhost/db").unwrap();
+        assert!(!format!("{opts:?}").contains("statement_timeout"));
+    }
+
+    #[test]
+    fn utilization_ratio() {
+        assert_eq!(utilization(0, 0), 0.0);
+        assert_eq!(utilization(5, 10), 0.5);
+    }
+}
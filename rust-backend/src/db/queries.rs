@@ -29,6 +29,7 @@ pub async fn get_api_keys_for_user(pool: &PgPool, user_id: i64) -> Result<Vec<Ap
         r#"
         SELECT key_id, user_id, exchange,
                encrypted_api_key, encrypted_secret, encrypted_passphrase,
+               encrypted_data_key, nonce_key, nonce_secret, nonce_passphrase,
                created_at
         FROM   api_keys
         WHERE  user_id = $1
@@ -50,7 +50,7 @@ impl RedisPool {
                 .query_async::<_, ()>(&mut con)
                 .await?;
         }
-        log::debug!("redis SET took {:?}", started.elapsed());
+        tracing::debug!("redis SET took {:?}", started.elapsed());
         Ok(())
     }
 
@@ -62,7 +62,7 @@ impl RedisPool {
         let mut con = self.manager().as_ref().clone();
         let started = Instant::now();
         let raw: Option<String> = con.get(key).await?;
-        log::debug!("redis GET took {:?}", started.elapsed());
+        tracing::debug!("redis GET took {:?}", started.elapsed());
 
         match raw {
             Some(s) => Ok(Some(serde_json::from_str(&s).map_err(|e| {
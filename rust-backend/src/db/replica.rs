@@ -0,0 +1,211 @@
+//  src/db/replica.rs
+//! Optional read replica for heavy, staleness-tolerant queries (analytics,
+//! reports, stats). Writes and the trade path always use the primary pool.
+//!
+//! * no `DATABASE_READ_URL`  → every read goes to the primary
+//! * replica down / lagging  → a background probe flips reads back to the
+//!   primary, and `read()` retries there on connection-level errors
+
+use sqlx::PgPool;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::pool::PoolConfig;
+
+const PROBE_EVERY: Duration = Duration::from_secs(15);
+
+/// Cheap-to-clone handle; share via `.app_data(web::Data::new(..))`.
+#[derive(Clone)]
+pub struct ReadPool {
+    primary: PgPool,
+    replica: Option<PgPool>,
+    healthy: Arc<AtomicBool>,
+}
+
+impl ReadPool {
+    /// Replica-less pool: all reads hit the primary.
+    pub fn primary_only(primary: PgPool) -> Self {
+        Self {
+            primary,
+            replica: None,
+            healthy: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Lazily connects to the replica so a dead replica never blocks boot.
+    pub fn new(primary: PgPool, replica_url: Option<&str>, cfg: &PoolConfig) -> Self {
+        let replica = replica_url.and_then(|url| {
+            cfg.connect_lazy(url)
+                .map_err(|e| tracing::error!("read replica disabled – bad url: {e}"))
+                .ok()
+        });
+        let healthy = Arc::new(AtomicBool::new(replica.is_some()));
+        Self {
+            primary,
+            replica,
+            healthy,
+        }
+    }
+
+    /// Pool reads should go to right now
+    pub fn get(&self) -> &PgPool {
+        match &self.replica {
+            Some(r) if self.healthy.load(Ordering::Relaxed) => r,
+            _ => &self.primary,
+        }
+    }
+
+    pub fn primary(&self) -> &PgPool {
+        &self.primary
+    }
+
+    pub fn replica(&self) -> Option<&PgPool> {
+        self.replica.as_ref()
+    }
+
+    pub fn using_replica(&self) -> bool {
+        self.replica.is_some() && self.healthy.load(Ordering::Relaxed)
+    }
+
+    fn mark(&self, ok: bool) {
+        if self.healthy.swap(ok, Ordering::Relaxed) != ok {
+            if ok {
+                tracing::info!("read replica healthy – routing reads to replica");
+            } else {
+                tracing::warn!("read replica unavailable – falling back to primary");
+            }
+        }
+    }
+
+    /// Run a read query on the replica, retrying once on the primary when
+    /// the replica fails at the connection level.
+    pub async fn read<T, F, Fut>(&self, f: F) -> Result<T, sqlx::Error>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        if !self.using_replica() {
+            return f(self.primary.clone()).await;
+        }
+        match f(self.get().clone()).await {
+            Err(e) if is_connection_error(&e) => {
+                tracing::warn!("replica read failed ({e}) – retrying on primary");
+                self.mark(false);
+                f(self.primary.clone()).await
+            }
+            other => other,
+        }
+    }
+
+    /// Background `SELECT 1` probe that re-enables the replica once it is back.
+    pub fn spawn_health_check(&self) {
+        let Some(replica) = self.replica.clone() else {
+            return;
+        };
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut iv = tokio::time::interval(PROBE_EVERY);
+            loop {
+                iv.tick().await;
+                let ok = sqlx::query("SELECT 1").execute(&replica).await.is_ok();
+                this.mark(ok);
+            }
+        });
+    }
+}
+
+/// Errors worth failing over for (as opposed to bad SQL / missing rows)
+fn is_connection_error(e: &sqlx::Error) -> bool {
+    matches!(
+        e,
+        sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+    )
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+
+    fn cfg() -> PoolConfig {
+        PoolConfig {
+            max_connections: 1,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(1),
+            statement_timeout: None,
+        }
+    }
+
+    fn lazy(url: &str) -> PgPool {
+        PgPoolOptions::new()
+            .max_connections(1)
+            .connect_lazy(url)
+            .expect("lazy PgPool")
+    }
+
+    #[tokio::test]
+    async fn no_replica_means_primary() {
+        let rp = ReadPool::new(lazy("postgres://p@localhost/p"), None, &cfg());
+        assert!(!rp.using_replica());
+        assert!(std::ptr::eq(rp.get(), rp.primary()));
+    }
+
+    #[tokio::test]
+    async fn unhealthy_replica_routes_to_primary() {
+        let rp = ReadPool::new(
+            lazy("postgres://p@localhost/p"),
+            Some("postgres://r@localhost/r"),
+            &cfg(),
+        );
+        assert!(rp.using_replica());
+        rp.mark(false);
+        assert!(std::ptr::eq(rp.get(), rp.primary()));
+        rp.mark(true);
+        assert!(!std::ptr::eq(rp.get(), rp.primary()));
+    }
+
+    #[tokio::test]
+    async fn read_falls_back_on_connection_errors() {
+        let rp = ReadPool::new(
+            lazy("postgres://p@localhost/p"),
+            Some("postgres://r@localhost/r"),
+            &cfg(),
+        );
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let out = rp
+            .read(|_pool| {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if n == 0 {
+                        Err(sqlx::Error::PoolTimedOut)
+                    } else {
+                        Ok(42)
+                    }
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(out, 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(!rp.using_replica());
+    }
+
+    #[test]
+    fn query_errors_do_not_fail_over() {
+        assert!(!is_connection_error(&sqlx::Error::RowNotFound));
+        assert!(is_connection_error(&sqlx::Error::PoolClosed));
+    }
+}
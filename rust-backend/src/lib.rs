@@ -1,28 +1,139 @@
+// `Result<_, HttpResponse>` guards and `TradeError` (which carries a
+// `sqlx::Error`) are this crate's error idiom; boxing them buys nothing
+#![allow(clippy::result_large_err)]
+
 pub mod config;
 pub mod db;
 pub mod middleware;
 pub mod routes {
+    pub mod account;
+    pub mod admin;
+    pub mod alerts;
+    pub mod analytics;
+    pub mod arbitrage;
+    pub mod auth;
+    pub mod backtests;
+    pub mod billing;
+    #[cfg(feature = "chaos")]
+    pub mod chaos;
     pub mod copy;
+    pub mod exchange_log;
+    pub mod exposure;
+    pub mod fees;
+    pub mod flags;
     pub mod health;
+    pub mod integrations;
+    pub mod keys;
+    pub mod metric_sinks;
+    pub mod onboarding;
+    pub mod optimize;
+    pub mod orders;
+    #[cfg(feature = "wasm")]
+    pub mod plugins;
+    pub mod portfolio;
+    pub mod positions;
+    pub mod public;
+    pub mod referrals;
+    pub mod security;
+    pub mod storage;
     pub mod strategies;
     pub mod trading;
+    pub mod usage;
+    pub mod watchlist;
+    pub mod webhooks;
 }
 pub mod services {
+    pub mod account_deletion;
+    pub mod alerts;
+    pub mod allocation;
+    pub mod analytics;
+    pub mod anomaly;
+    pub mod api_keys;
+    pub mod arbitrage;
+    pub mod audit;
+    pub mod auth_tokens;
+    pub mod auto_stop;
+    pub mod backtest;
+    pub mod backtest_pool;
+    pub mod backtest_queue;
+    pub mod bar_clock;
+    pub mod billing;
+    pub mod calendar;
+    pub mod candle_integrity;
+    pub mod candle_recorder;
+    pub mod candle_retention;
+    #[cfg(feature = "chaos")]
+    pub mod chaos;
+    pub mod copy_aggregate;
+    pub mod copy_queue;
+    pub mod depth_history;
+    pub mod drain;
+    pub mod event_bus;
+    pub mod exchange_log;
+    pub mod exchanges;
+    pub mod exposure;
+    pub mod feature_flags;
+    pub mod fees;
+    pub mod funding;
+    pub mod history;
+    pub mod identities;
+    pub mod instruments;
+    pub mod integration_keys;
+    pub mod keepalive;
+    pub mod leader_verification;
+    pub mod liquidity;
     pub mod market_data;
+    pub mod metric_sinks;
+    pub mod notify;
+    pub mod onboarding;
+    pub mod optimizer;
+    pub mod order_tracker;
+    pub mod params_history;
+    pub mod portfolio;
+    pub mod portfolio_history;
+    pub mod public_stats;
+    pub mod roles;
     pub mod scheduler;
+    pub mod seasonality;
+    pub mod sharding;
+    pub mod strategy_performance;
+    pub mod strategy_pnl;
+    pub mod telemetry;
+    pub mod time_sync;
     pub mod trading_engine;
+    pub mod transfers;
 
     pub mod crypto;
+    pub mod position_manager;
+    pub mod positions;
+    pub mod referrals;
+    pub mod replay;
     pub mod risk;
+    pub mod usage;
+    pub mod watchlist;
+    pub mod webhooks;
 
+    pub mod binance;
     pub mod blowfin;
     pub mod copy_trading;
     pub mod strategies {
         pub mod common;
-        pub use common::{Candle, OrderBookSnapshot};
+        pub use common::{Candle, OrderBookSnapshot, StrategyError};
+        pub mod buffer;
+        pub mod custom;
+        pub mod heartbeat;
+        pub mod indicators;
+        pub mod market_maker;
         pub mod mean_reversion;
+        #[cfg(feature = "wasm")]
+        pub mod plugin;
+        pub mod schema;
+        pub mod script;
+        #[cfg(any(test, feature = "testkit"))]
+        pub mod testkit;
         pub mod trend_follow;
         pub mod vcsr;
+        pub mod warmup;
     }
 }
 
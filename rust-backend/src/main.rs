@@ -1,104 +1,284 @@
-use tracing_subscriber::{fmt, EnvFilter};
-use actix_web::{middleware::Logger, web, App, HttpServer};
-use metrics_exporter_prometheus::PrometheusBuilder;
+use actix_web::{web, App, HttpServer};
 use rustraptor_backend::services::risk;
-use sqlx::postgres::PgPoolOptions;
 
+use rustraptor_backend::middleware::{metrics::Metrics, RequestLog};
 use rustraptor_backend::{
-    config::settings::Settings,
-    db::redis::RedisPool,
+    config::{preflight, settings::Settings},
+    db::{
+        cache,
+        pool::{self, PoolConfig},
+        redis::RedisPool,
+        replica::ReadPool,
+    },
     routes::{
-        copy::copy_scope, health::health_scope, strategies::strategy_scope, trading::trading_scope,
+        account::account_scope, admin::admin_scope, alerts::alerts_scope,
+        analytics::analytics_scope, arbitrage::arbitrage_scope, auth::auth_scope,
+        backtests::backtests_scope, billing::billing_scope, copy::copy_scope,
+        exposure::exposure_scope, fees::fees_scope, flags::flags_scope, health::health_scope,
+        keys::keys_scope, metric_sinks::metric_sinks_scope, onboarding::onboarding_scope,
+        optimize::optimize_scope, orders::orders_scope, portfolio::portfolio_scope,
+        positions::positions_scope, public::public_scope, referrals::referrals_scope,
+        security::security_scope, strategies::strategy_scope, trading::trading_scope,
+        usage::usage_scope, watchlist::watchlist_scope, webhooks::webhooks_scope,
     },
     services,
-    services::scheduler,
-    utils::route_debug::{dump_routes, param_test, request_info},
+    services::{scheduler, sharding::ShardSource},
 };
-use rustraptor_backend::middleware::metrics::Metrics;
-
-// fn init_logging() {
-//     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-// }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    fmt::Subscriber::builder().with_env_filter(EnvFilter::from_default_env()).json().init();
-
-    PrometheusBuilder::new()
-        .with_http_listener(([0, 0, 0, 0], 9000))
-        .install()
-        .expect("metrics exporter");
-
-    // init_logging();
-    println!("Starting RustRaptor backend…");
-
     let settings = Settings::new().unwrap_or_else(|e| {
         eprintln!("Failed to load settings: {e}");
         std::process::exit(1);
     });
 
-    println!("Connecting to database: {}", &settings.database_url);
+    // logs + Prometheus, and OTLP export when configured
+    services::telemetry::init(&settings.otel(), &settings.logs());
+    services::public_stats::init();
+
+    tracing::info!("starting RustRaptor backend");
+
+    // fail fast with a readable table instead of a panic deep in start-up
+    let report = preflight::run(&settings).await;
+    tracing::info!("preflight:\n{report}");
+    if report.failed() {
+        tracing::error!("preflight failed – fix the FAIL rows above and restart");
+        std::process::exit(1);
+    }
+
+    tracing::info!("connecting to database");
 
-    let bus = services::market_data::spawn_all_feeds(&settings).await;
     let port = settings.server_port;
     let settings_clone = settings.clone();
 
-    let pg_pool = PgPoolOptions::new()
-        .max_connections(5)
+    let pool_cfg = PoolConfig::primary(&settings);
+    let pg_pool = pool_cfg
         .connect(&settings.database_url)
         .await
         .expect("postgres");
+    pool::spawn_pool_metrics("primary", pg_pool.clone(), pool_cfg.max_connections);
+
+    // feeds subscribe to the symbols of enabled strategies
+    let bus = services::market_data::spawn_all_feeds(&settings, pg_pool.clone()).await;
+
+    let replica_cfg = PoolConfig::replica(&settings);
+    let read_pool = ReadPool::new(
+        pg_pool.clone(),
+        settings.database_read_url.as_deref(),
+        &replica_cfg,
+    );
+    read_pool.spawn_health_check();
+    if let Some(replica) = read_pool.replica() {
+        pool::spawn_pool_metrics("replica", replica.clone(), replica_cfg.max_connections);
+    }
 
-    let redis_pool = RedisPool::new(&settings.redis_url).await.expect("redis");
+    // Redis is only mandatory for the redis cache backend; with `memory` we
+    // still use it (event bus) when reachable.
+    let redis_pool = match RedisPool::new(&settings.redis_url).await {
+        Ok(r) => Some(r),
+        Err(e) if settings.cache_backend == "memory" => {
+            tracing::warn!("redis unavailable ({e}) – running without event bus");
+            None
+        }
+        Err(e) => panic!("redis: {e}"),
+    };
+    let cache =
+        cache::from_backend(&settings.cache_backend, redis_pool.clone()).expect("cache backend");
+    tracing::info!("cache backend: {}", settings.cache_backend);
+    #[cfg(feature = "chaos")]
+    let cache = {
+        tracing::warn!("chaos build: fault injection available via /api/admin/chaos");
+        services::chaos::wrap_cache(cache)
+    };
 
-    risk::spawn_guardian(pg_pool.clone(), redis_pool.clone());
+    let event_bus = redis_pool.map(services::event_bus::EventBus::new);
+    if let Some(events) = &event_bus {
+        services::notify::init(events.clone(), settings.notify_digest_secs);
+    }
+
+    // batched writers (audit trail, candle, depth and funding history)
+    services::audit::init(pg_pool.clone());
+    services::candle_recorder::spawn(pg_pool.clone(), bus.clone());
+    services::depth_history::spawn(pg_pool.clone(), bus.clone());
+    services::funding::spawn(pg_pool.clone(), bus.clone());
+    services::strategy_pnl::init(pg_pool.clone(), cache.clone(), bus.clone());
+    services::account_deletion::spawn(pg_pool.clone());
+    services::transfers::spawn(pg_pool.clone(), settings.is_demo());
+    services::leader_verification::spawn(pg_pool.clone(), settings.is_demo());
+    services::candle_retention::spawn(
+        pg_pool.clone(),
+        settings.candle_retention.clone(),
+        std::time::Duration::from_secs(settings.candle_compaction_interval_secs),
+    );
+    services::market_data::spawn_watchlist_feed(pg_pool.clone());
+    services::arbitrage::spawn_monitor(pg_pool.clone(), cache.clone(), settings.is_demo());
+    services::alerts::spawn_evaluator(services::alerts::Automations {
+        pg: pg_pool.clone(),
+        cache: cache.clone(),
+        is_demo: settings.is_demo(),
+        master_key: std::env::var("MASTER_KEY").unwrap_or_default().into_bytes(),
+    });
+
+    risk::spawn_guardian(pg_pool.clone(), cache.clone());
+    services::order_tracker::spawn(pg_pool.clone(), cache.clone(), settings.is_demo());
+    services::metric_sinks::spawn(pg_pool.clone(), cache.clone());
+    services::anomaly::init(
+        cache.clone(),
+        services::anomaly::AnomalyConfig {
+            volume_factor: settings.anomaly_volume_factor,
+            auto_freeze: settings.anomaly_auto_freeze,
+        },
+    );
+    services::time_sync::spawn(
+        settings.is_demo(),
+        std::time::Duration::from_secs(settings.time_sync_interval_secs),
+        settings.clock_skew_alert_ms,
+    );
+    services::liquidity::init(settings.symbol_filters.clone());
+    services::fees::init(settings.default_fees);
+    services::calendar::init(
+        settings.market_calendars.clone(),
+        settings.symbol_classes.clone(),
+    );
+    services::instruments::spawn(settings.is_demo());
+    services::exchange_log::init(settings.exchange_log_capacity);
+    services::backtest_pool::init(settings.backtest_threads);
+    services::backtest_queue::init(settings.backtest_max_jobs);
+    services::history::init(settings.history_source.as_ref());
+    services::history::spawn_backfill(pg_pool.clone());
+    // after `history::init` – verification cross-checks against the provider
+    services::candle_integrity::spawn(
+        pg_pool.clone(),
+        std::time::Duration::from_secs(settings.candle_verify_interval_secs),
+    );
+    services::trading_engine::init_timeouts(services::trading_engine::OrderTimeouts {
+        submit: std::time::Duration::from_millis(settings.order_submit_timeout_ms),
+        followup: std::time::Duration::from_millis(settings.order_followup_timeout_ms),
+    });
+    // after `init_timeouts` – the shared client is built with them
+    services::keepalive::spawn(
+        settings.is_demo(),
+        std::time::Duration::from_secs(settings.exchange_keepalive_secs),
+    );
+
+    services::copy_queue::init(
+        settings.copy_queue_capacity,
+        settings.copy_workers,
+        services::copy_queue::Jitter {
+            max_delay: std::time::Duration::from_millis(settings.copy_jitter_max_ms),
+            size_frac: settings.copy_size_jitter_pct / 100.0,
+        },
+        services::copy_queue::TradeExecutor {
+            pg: pg_pool.clone(),
+            cache: cache.clone(),
+            is_demo: settings.is_demo(),
+            master_key: std::env::var("MASTER_KEY").unwrap_or_default().into_bytes(),
+        },
+    );
+
+    if let Some(account_user_id) = settings.copy_aggregate_user_id {
+        tracing::info!(
+            "copy: aggregated via omnibus user {account_user_id} ({} from size {})",
+            settings.copy_aggregate_style,
+            settings.copy_aggregate_min_size
+        );
+        services::copy_aggregate::init(
+            pg_pool.clone(),
+            services::copy_aggregate::AggregateConfig {
+                account_user_id,
+                min_size: settings.copy_aggregate_min_size,
+                style: settings.copy_aggregate_style,
+            },
+            services::copy_aggregate::OmnibusExecutor {
+                pg: pg_pool.clone(),
+                account_user_id,
+                is_demo: settings.is_demo(),
+                master_key: std::env::var("MASTER_KEY").unwrap_or_default().into_bytes(),
+            },
+        );
+    }
 
     // --- scheduler reconciler ----------------------------------------------
     {
         let pg = pg_pool.clone();
-        let redis = redis_pool.clone();
+        let cache = cache.clone();
         let s_copy = settings.clone();
         let bus_c = bus.clone();
+        let shards = ShardSource::from_settings(&settings, cache.clone());
         tokio::spawn(async move {
             let mut iv = tokio::time::interval(std::time::Duration::from_secs(30));
             loop {
                 iv.tick().await;
-                if let Err(e) = scheduler::reconcile(&pg, &redis, &s_copy, &bus_c).await {
-                    log::error!("scheduler: {e:?}");
+                if let Err(e) = scheduler::reconcile(&pg, &cache, &s_copy, &bus_c, &shards).await {
+                    tracing::error!("scheduler: {e:?}");
                 }
             }
         });
     }
 
-    HttpServer::new(move || {
-        App::new()
+    let request_log = RequestLog::new(settings.request_log.clone());
+    let server = HttpServer::new(move || {
+        let mut app = App::new()
             .wrap(Metrics)
-            .wrap(Logger::default())
+            .wrap(request_log.clone())
+            .wrap(rustraptor_backend::middleware::UsageCounter) // inner: runs after Auth
             .wrap(rustraptor_backend::middleware::Auth)
             .app_data(web::Data::new(settings_clone.clone()))
             .app_data(web::Data::new(pg_pool.clone()))
-            .app_data(web::Data::new(redis_pool.clone()))
-            .app_data(web::Data::new(bus.clone()))
+            .app_data(web::Data::new(read_pool.clone()))
+            .app_data(web::Data::from(cache.clone()))
+            .app_data(web::Data::new(bus.clone()));
+        if let Some(events) = &event_bus {
+            app = app.app_data(web::Data::new(events.clone()));
+        }
+        #[cfg(feature = "wasm")]
+        {
+            app = app.service(rustraptor_backend::routes::plugins::plugins_scope());
+        }
+        app
             //scope
             .service(health_scope())
+            .service(public_scope())
+            .service(auth_scope())
+            .service(admin_scope())
+            .service(analytics_scope()) // before the catch-all `/api` scope
+            .service(usage_scope())
+            .service(security_scope())
+            .service(account_scope())
+            .service(fees_scope())
+            .service(billing_scope())
+            .service(referrals_scope())
+            .service(optimize_scope())
+            .service(watchlist_scope())
+            .service(arbitrage_scope())
+            .service(alerts_scope())
+            .service(exposure_scope())
+            .service(flags_scope())
+            .service(onboarding_scope())
+            .service(keys_scope())
+            .service(metric_sinks_scope())
+            .service(backtests_scope())
+            .service(orders_scope())
+            .service(portfolio_scope())
+            .service(positions_scope())
+            .service(webhooks_scope())
             .service(trading_scope())
             .service(copy_scope())
             .service(strategy_scope())
-            //degug
-            .service(dump_routes)
-            .service(request_info)
-            .service(param_test)
     })
     .bind(("0.0.0.0", port))?
-    .run()
-    .await
-}
-
-/*todo
-find away to implement early close triggers for failed moves for each stratagies (premium feature)
+    .disable_signals() // SIGTERM drains first, see below
+    .run();
 
+    // --- drain on SIGTERM / Ctrl-C ------------------------------------------
+    services::drain::install_server(server.handle());
+    let drain_timeout = std::time::Duration::from_secs(settings.drain_timeout_secs);
+    tokio::spawn(async move {
+        services::drain::shutdown_signal().await;
+        services::drain::drain_and_stop(drain_timeout).await;
+    });
 
- for all stratagies review indetail with reasearch and refine the for bgest performance
-
- adding customizable peramters as needed
-*/
+    let served = server.await;
+    services::telemetry::shutdown();
+    served
+}
@@ -0,0 +1,73 @@
+//-------------------------------------------------------------
+// src/middleware/admin.rs
+//-------------------------------------------------------------
+//! `AdminUser` – an extractor that lets a handler run only for an
+//! authenticated account holding the `admin` role (`services::roles`).
+//! No user id → 401, not an admin → 403, role lookup failed → 503.
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::{
+    dev::Payload, error::InternalError, web, Error, FromRequest, HttpMessage, HttpRequest,
+    HttpResponse,
+};
+use sqlx::PgPool;
+
+use crate::services::roles::{self, Role};
+use crate::utils::types::ApiResponse;
+
+/// The admin's account id
+#[derive(Debug, Clone, Copy)]
+pub struct AdminUser(pub i64);
+
+fn refuse(msg: &'static str, resp: HttpResponse) -> Error {
+    InternalError::from_response(msg, resp).into()
+}
+
+impl FromRequest for AdminUser {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let uid = req
+            .extensions()
+            .get::<String>()
+            .and_then(|s| s.parse::<i64>().ok());
+        let db = req.app_data::<web::Data<PgPool>>().cloned();
+        let path = req.path().to_string();
+        Box::pin(async move {
+            let Some(uid) = uid else {
+                return Err(refuse(
+                    "no user id",
+                    HttpResponse::Unauthorized().json(ApiResponse::<()>::err("no user id")),
+                ));
+            };
+            let Some(db) = db else {
+                tracing::error!("roles: no pool registered");
+                return Err(refuse(
+                    "roles unavailable",
+                    HttpResponse::ServiceUnavailable()
+                        .json(ApiResponse::<()>::err("roles temporarily unavailable")),
+                ));
+            };
+            match roles::has(db.get_ref(), uid, Role::Admin).await {
+                Ok(true) => Ok(AdminUser(uid)),
+                Ok(false) => {
+                    tracing::warn!("user {uid} refused on {path}: not an admin");
+                    Err(refuse(
+                        "admin only",
+                        HttpResponse::Forbidden().json(ApiResponse::<()>::err("admin only")),
+                    ))
+                }
+                Err(e) => {
+                    tracing::error!("roles: {e}");
+                    Err(refuse(
+                        "roles unavailable",
+                        HttpResponse::ServiceUnavailable()
+                            .json(ApiResponse::<()>::err("roles temporarily unavailable")),
+                    ))
+                }
+            }
+        })
+    }
+}
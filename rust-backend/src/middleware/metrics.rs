@@ -51,11 +51,11 @@ where
         // ---------------------------  before  ---------------------------
         let start = Instant::now();
         let method = req.method().as_str().to_string();
-        let path   = req.path().to_string();
+        let path = req.path().to_string();
 
         // Leak the strings so we can hand `'static` references to the macro
-        let method_leaked : &'static str = Box::leak(method.into_boxed_str());
-        let path_leaked   : &'static str = Box::leak(path.into_boxed_str());
+        let method_leaked: &'static str = Box::leak(method.into_boxed_str());
+        let path_leaked: &'static str = Box::leak(path.into_boxed_str());
 
         // ---------------------------  call next  ------------------------
         let fut = self.inner.call(req);
@@ -64,7 +64,7 @@ where
             let res = fut.await?;
             let latency = start.elapsed().as_secs_f64() * 1_000.0; // → ms
             let status_string = res.status().as_u16().to_string();
-            let status_leaked : &'static str = Box::leak(status_string.into_boxed_str());
+            let status_leaked: &'static str = Box::leak(status_string.into_boxed_str());
 
             increment_counter!(
                 "http_requests_total",
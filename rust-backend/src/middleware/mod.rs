@@ -1,4 +1,8 @@
+pub mod admin;
 pub(crate) mod auth;
 pub use auth::Auth;
-pub(crate) mod path_logger;
 pub mod metrics;
+pub mod request_log;
+pub use request_log::RequestLog;
+pub(crate) mod usage;
+pub use usage::UsageCounter;
@@ -0,0 +1,276 @@
+//-------------------------------------------------------------
+// src/middleware/request_log.rs
+//-------------------------------------------------------------
+//! One tracing event per request, at the level configured for its route
+//! (`REQUEST_LOG_ROUTES`, longest path prefix wins, `off` silences it).
+//! A request carrying `X-Debug-Log: <REQUEST_LOG_VERBOSE_TOKEN>` is logged
+//! in full at INFO whatever its route's level – query, matched route, user,
+//! redacted headers – and its handler's events run inside a `debug_request`
+//! span, for following a single call through production logs.
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::HeaderMap;
+use actix_web::{Error, HttpMessage};
+use subtle::ConstantTimeEq;
+use tracing::{Instrument, Level};
+
+/// Header that turns on verbose logging for one request
+pub const VERBOSE_HEADER: &str = "x-debug-log";
+
+/// Headers whose values never reach the logs
+const REDACTED: &[&str] = &[
+    "authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "x-signature",
+    VERBOSE_HEADER,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLogConfig {
+    /// Level for paths no rule matches; `None` = off
+    pub default: Option<Level>,
+    /// (path prefix, level), longest prefix first
+    pub routes: Vec<(String, Option<Level>)>,
+    /// `X-Debug-Log` value enabling verbose mode; disabled when unset
+    pub verbose_token: Option<String>,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        Self {
+            default: Some(Level::INFO),
+            routes: Vec::new(),
+            verbose_token: None,
+        }
+    }
+}
+
+/// `off` | `error` | `warn` | `info` | `debug` | `trace`
+pub fn parse_level(s: &str) -> Result<Option<Level>, String> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+    s.parse::<Level>()
+        .map(Some)
+        .map_err(|_| format!("unknown level `{s}` (off | error | warn | info | debug | trace)"))
+}
+
+impl RequestLogConfig {
+    /// Rules like `/health=off,/api/trading=debug`
+    pub fn parse_routes(spec: &str) -> Result<Vec<(String, Option<Level>)>, String> {
+        let mut routes = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(|rule| {
+                let (prefix, level) = rule
+                    .split_once('=')
+                    .ok_or_else(|| format!("`{rule}` is not prefix=level"))?;
+                Ok((prefix.trim().to_string(), parse_level(level)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        routes.sort_by_key(|r| std::cmp::Reverse(r.0.len()));
+        Ok(routes)
+    }
+
+    /// Level a request to `path` is logged at; `None` = not logged
+    pub fn level(&self, path: &str) -> Option<Level> {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn verbose(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = self.verbose_token.as_deref() else {
+            return false;
+        };
+        headers
+            .get(VERBOSE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|given| bool::from(given.as_bytes().ct_eq(expected.as_bytes())))
+    }
+}
+
+/// Header name/value pairs with credentials blanked
+fn redacted(headers: &HeaderMap) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| {
+            let name = name.as_str().to_string();
+            let value = if REDACTED.contains(&name.as_str()) {
+                "[redacted]".to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name, value)
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+macro_rules! event_at {
+    ($level:expr, $($arg:tt)+) => {
+        match $level {
+            Level::ERROR => tracing::error!($($arg)+),
+            Level::WARN => tracing::warn!($($arg)+),
+            Level::INFO => tracing::info!($($arg)+),
+            Level::DEBUG => tracing::debug!($($arg)+),
+            _ => tracing::trace!($($arg)+),
+        }
+    };
+}
+
+#[derive(Clone)]
+pub struct RequestLog {
+    cfg: Arc<RequestLogConfig>,
+}
+
+impl RequestLog {
+    pub fn new(cfg: RequestLogConfig) -> Self {
+        Self { cfg: Arc::new(cfg) }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestLogSvc<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, srv: S) -> Self::Future {
+        ready(Ok(RequestLogSvc {
+            inner: srv,
+            cfg: self.cfg.clone(),
+        }))
+    }
+}
+
+pub struct RequestLogSvc<S> {
+    inner: S,
+    cfg: Arc<RequestLogConfig>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestLogSvc<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        if !self.cfg.verbose(req.headers()) {
+            let Some(level) = self.cfg.level(&path) else {
+                return Box::pin(self.inner.call(req));
+            };
+            let fut = self.inner.call(req);
+            return Box::pin(async move {
+                let res = fut.await?;
+                let status = res.status().as_u16();
+                let latency_ms = start.elapsed().as_millis() as u64;
+                event_at!(level, %method, %path, status, latency_ms, "request");
+                Ok(res)
+            });
+        }
+
+        let query = req.query_string().to_string();
+        let request_headers = redacted(req.headers());
+        let span = tracing::info_span!("debug_request", %method, %path);
+        let fut = self.inner.call(req).instrument(span.clone());
+        Box::pin(async move {
+            let res = fut.await?;
+            let _entered = span.enter();
+            let request = res.request();
+            tracing::info!(
+                %method,
+                %path,
+                %query,
+                route = request.match_pattern().as_deref().unwrap_or("unmatched"),
+                user_id = request.extensions().get::<String>().map(String::as_str),
+                status = res.status().as_u16(),
+                latency_ms = start.elapsed().as_millis() as u64,
+                request_headers = ?request_headers,
+                response_headers = ?redacted(res.headers()),
+                "request (verbose)"
+            );
+            Ok(res)
+        })
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    #[test]
+    fn longest_matching_prefix_sets_the_level() {
+        let cfg = RequestLogConfig {
+            default: Some(Level::INFO),
+            routes: RequestLogConfig::parse_routes(
+                "/api/health=off, /api=warn, /api/trading=debug",
+            )
+            .unwrap(),
+            verbose_token: None,
+        };
+        assert_eq!(cfg.level("/api/health/ready"), None);
+        assert_eq!(cfg.level("/api/trading/orders"), Some(Level::DEBUG));
+        assert_eq!(cfg.level("/api/keys"), Some(Level::WARN));
+        assert_eq!(cfg.level("/metrics"), Some(Level::INFO));
+
+        assert!(RequestLogConfig::parse_routes("/api=loud").is_err());
+        assert!(RequestLogConfig::parse_routes("/api").is_err());
+    }
+
+    #[test]
+    fn verbose_needs_the_token_and_hides_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static(VERBOSE_HEADER),
+            HeaderValue::from_static("s3cret"),
+        );
+        headers.insert(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_static("Bearer abc"),
+        );
+
+        let mut cfg = RequestLogConfig::default();
+        // disabled without a configured token
+        assert!(!cfg.verbose(&headers));
+        cfg.verbose_token = Some("s3cret".into());
+        assert!(cfg.verbose(&headers));
+        cfg.verbose_token = Some("other".into());
+        assert!(!cfg.verbose(&headers));
+
+        let shown = redacted(&headers);
+        assert!(shown.iter().all(|(_, v)| v == "[redacted]"));
+    }
+}
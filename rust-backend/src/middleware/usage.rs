@@ -0,0 +1,97 @@
+//-------------------------------------------------------------
+// src/middleware/usage.rs
+//-------------------------------------------------------------
+//! Counts authenticated API calls per user per day and per route and hour
+//! (`db::cache`), and feeds the caller's address to new-IP detection
+//! (`services::anomaly`). Must sit *inside* `Auth` so the user id extension
+//! is already set.
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpMessage};
+
+use crate::db::cache::Cache;
+use crate::services::{anomaly, usage};
+
+pub struct UsageCounter;
+
+impl<S, B> Transform<S, ServiceRequest> for UsageCounter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = UsageCounterSvc<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, srv: S) -> Self::Future {
+        ready(Ok(UsageCounterSvc { inner: srv }))
+    }
+}
+
+pub struct UsageCounterSvc<S> {
+    inner: S,
+}
+
+impl<S, B> Service<ServiceRequest> for UsageCounterSvc<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let uid = req
+            .extensions()
+            .get::<String>()
+            .and_then(|s| s.parse::<i64>().ok());
+        let cache = req.app_data::<web::Data<dyn Cache>>().cloned();
+        let ip = req
+            .connection_info()
+            .realip_remote_addr()
+            .map(str::to_owned);
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?;
+            // fire-and-forget: accounting must never slow down or fail a request
+            if let (Some(uid), Some(cache)) = (uid, cache) {
+                // the matched pattern is only known once routing has run
+                let route = format!(
+                    "{} {}",
+                    res.request().method(),
+                    res.request()
+                        .match_pattern()
+                        .unwrap_or_else(|| "unmatched".into())
+                );
+                actix_web::rt::spawn(async move {
+                    let cache = cache.get_ref();
+                    if let Err(e) = usage::record_api_call(cache, uid).await {
+                        tracing::warn!("usage: api counter for {uid}: {e}");
+                    }
+                    if let Err(e) = usage::record_endpoint_call(cache, uid, &route).await {
+                        tracing::warn!("usage: route counter for {uid}: {e}");
+                    }
+                    if let Some(ip) = ip {
+                        if let Err(e) = anomaly::check_ip(cache, uid, &ip).await {
+                            tracing::warn!("anomaly: ip check for {uid}: {e}");
+                        }
+                    }
+                });
+            }
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,316 @@
+// src/routes/account.rs
+//! The caller's account: linked sign-in identities (`services::identities`),
+//! the exit-only window (`services::risk`) and deletion
+//! (`services::account_deletion`).
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    db::cache::Cache,
+    middleware::auth::token_identity,
+    routes::strategies::user_id,
+    services::{
+        account_deletion::{self, DeletionError},
+        audit,
+        identities::{self, IdentityError, IdentityRef, Provider},
+        risk,
+    },
+    utils::types::ApiResponse,
+};
+
+fn failed(ctx: &str, e: IdentityError) -> HttpResponse {
+    let msg = e.to_string();
+    match e {
+        IdentityError::NotFound => HttpResponse::NotFound().json(ApiResponse::<()>::err(&msg)),
+        IdentityError::AlreadyLinked
+        | IdentityError::OtherAccount
+        | IdentityError::IsPrimary
+        | IdentityError::LastIdentity
+        | IdentityError::Busy(_) => HttpResponse::Conflict().json(ApiResponse::<()>::err(&msg)),
+        IdentityError::SameAccount | IdentityError::Invalid(_) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg))
+        }
+        IdentityError::Db(e) => {
+            tracing::error!("{ctx}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+fn path_identity(path: &(String, String)) -> Result<IdentityRef, HttpResponse> {
+    Provider::parse(&path.0)
+        .and_then(|p| IdentityRef::new(p, &path.1))
+        .map_err(|e| failed("identity path", e))
+}
+
+/// A token minted for the identity being linked proves the caller owns it
+#[derive(Deserialize, Debug)]
+pub struct ProofReq {
+    pub token: String,
+}
+
+fn proven(body: &ProofReq) -> Result<IdentityRef, HttpResponse> {
+    token_identity(&body.token).ok_or_else(|| {
+        HttpResponse::Unauthorized().json(ApiResponse::<()>::err("invalid identity token"))
+    })
+}
+
+/// GET /api/account/identities
+#[get("/identities")]
+async fn list(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match identities::list(db.as_ref(), uid).await {
+        Ok(rows) => HttpResponse::Ok().json(ApiResponse::ok(rows)),
+        Err(e) => failed("list identities", e),
+    }
+}
+
+/// POST /api/account/identities `{token}` – link a sign-in nobody uses yet
+#[post("/identities")]
+async fn link(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    body: web::Json<ProofReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let id = match proven(&body) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match identities::link(db.as_ref(), uid, &id).await {
+        Ok(rows) => {
+            audit::record(
+                Some(uid),
+                "identity.link",
+                json!({ "provider": id.provider, "subject": id.subject }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(rows))
+        }
+        Err(e) => failed("link identity", e),
+    }
+}
+
+/// POST /api/account/identities/merge `{token}` – fold the account behind
+/// that sign-in (an alt that already traded) into the caller's
+#[post("/identities/merge")]
+async fn merge(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    body: web::Json<ProofReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let id = match proven(&body) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let other = match identities::resolve(db.as_ref(), &id).await {
+        Ok(Some(other)) => other,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ApiResponse::<()>::err(
+                "no account behind that identity – link it instead",
+            ))
+        }
+        Err(e) => return failed("merge accounts", e.into()),
+    };
+    match identities::merge(db.as_ref(), uid, other).await {
+        Ok(report) => {
+            audit::record(
+                Some(uid),
+                "identity.merge",
+                json!({ "merged_user_id": other, "moved": report.moved, "dropped": report.dropped }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(report))
+        }
+        Err(e) => failed("merge accounts", e),
+    }
+}
+
+/// POST /api/account/identities/{provider}/{subject}/primary
+#[post("/identities/{provider}/{subject}/primary")]
+async fn make_primary(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let id = match path_identity(&path) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match identities::set_primary(db.as_ref(), uid, &id).await {
+        Ok(rows) => {
+            audit::record(
+                Some(uid),
+                "identity.primary",
+                json!({ "provider": id.provider, "subject": id.subject }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(rows))
+        }
+        Err(e) => failed("set primary identity", e),
+    }
+}
+
+/// DELETE /api/account/identities/{provider}/{subject}
+#[delete("/identities/{provider}/{subject}")]
+async fn unlink(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let id = match path_identity(&path) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match identities::unlink(db.as_ref(), uid, &id).await {
+        Ok(rows) => {
+            audit::record(
+                Some(uid),
+                "identity.unlink",
+                json!({ "provider": id.provider, "subject": id.subject }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(rows))
+        }
+        Err(e) => failed("unlink identity", e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExitOnlyReq {
+    /// How long only reducing orders are accepted, from now
+    pub duration_secs: i64,
+}
+
+fn exit_only_view(until: Option<chrono::DateTime<chrono::Utc>>) -> serde_json::Value {
+    json!({ "exit_only": until.is_some(), "until": until })
+}
+
+/// GET /api/account/exit-only
+#[get("/exit-only")]
+async fn get_exit_only(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match risk::exit_only_until(db.as_ref(), uid).await {
+        Ok(until) => HttpResponse::Ok().json(ApiResponse::ok(exit_only_view(until))),
+        Err(e) => {
+            tracing::error!("exit-only for {uid}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// POST /api/account/exit-only `{duration_secs}` – every strategy, copy and
+/// manual order must reduce a position until the window ends (e.g. while
+/// funds move off the exchange); replaces a window already running
+#[post("/exit-only")]
+async fn start_exit_only(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    body: web::Json<ExitOnlyReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let secs = body.duration_secs;
+    if !(1..=risk::MAX_EXIT_ONLY_SECS).contains(&secs) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(&format!(
+            "duration_secs must be in 1..={}",
+            risk::MAX_EXIT_ONLY_SECS
+        )));
+    }
+    let until = chrono::Utc::now() + chrono::Duration::seconds(secs);
+    match risk::set_exit_only(db.as_ref(), uid, Some(until)).await {
+        Ok(until) => {
+            audit::record(
+                Some(uid),
+                "account.exit_only_start",
+                json!({ "until": until }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(exit_only_view(until)))
+        }
+        Err(e) => {
+            tracing::error!("exit-only for {uid}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// DELETE /api/account/exit-only – back to normal trading before the
+/// window ends
+#[delete("/exit-only")]
+async fn end_exit_only(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match risk::set_exit_only(db.as_ref(), uid, None).await {
+        Ok(until) => {
+            audit::record(Some(uid), "account.exit_only_end", json!({}));
+            HttpResponse::Ok().json(ApiResponse::ok(exit_only_view(until)))
+        }
+        Err(e) => {
+            tracing::error!("exit-only for {uid}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// DELETE /api/account – stop everything now, anonymise the history after
+/// `ACCOUNT_RETENTION_DAYS`
+#[delete("")]
+async fn delete_account(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    settings: web::Data<Settings>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let retention = chrono::Duration::days(settings.account_retention_days);
+    match account_deletion::schedule(db.as_ref(), cache.get_ref(), uid, retention).await {
+        Ok(done) => HttpResponse::Accepted().json(ApiResponse::ok(done)),
+        Err(e @ DeletionError::AlreadyScheduled) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => {
+            tracing::error!("delete account {uid}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("deletion failed"))
+        }
+    }
+}
+
+pub fn account_scope() -> Scope {
+    web::scope("/api/account")
+        .service(delete_account)
+        .service(get_exit_only)
+        .service(start_exit_only)
+        .service(end_exit_only)
+        .service(list)
+        .service(merge)
+        .service(link)
+        .service(make_primary)
+        .service(unlink)
+}
@@ -0,0 +1,97 @@
+// src/routes/alerts.rs
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    routes::strategies::user_id,
+    services::{
+        alerts::{self, AlertError, NewAlert},
+        audit, usage,
+    },
+    utils::types::ApiResponse,
+};
+
+/// GET /api/alerts → newest first
+#[get("")]
+async fn list(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match alerts::list(db.as_ref(), uid).await {
+        Ok(a) => HttpResponse::Ok().json(ApiResponse::ok(a)),
+        Err(e) => {
+            tracing::error!("list alerts: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// POST /api/alerts
+/// `{ "symbol": "BTC-USDT", "condition": "below", "threshold": 58000,
+///    "action": { "kind": "trade", "side": "buy", "size": 0.01 } }`
+#[post("")]
+async fn create(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    body: web::Json<NewAlert>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let is_free = usage::plan_for(db.as_ref(), uid).await == usage::Plan::Free;
+    match alerts::create(db.as_ref(), uid, &body, is_free).await {
+        Ok(a) => {
+            audit::record(
+                Some(uid),
+                "alert.create",
+                json!({ "alert_id": a.alert_id, "action": a.action }),
+            );
+            HttpResponse::Created().json(ApiResponse::ok(a))
+        }
+        Err(AlertError::Db(e)) => {
+            tracing::error!("create alert: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        Err(e @ AlertError::StrategyNotFound) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e @ AlertError::PlanRequired) => {
+            HttpResponse::Forbidden().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e @ AlertError::TooMany) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+/// DELETE /api/alerts/{id} – cancel an active alert
+#[delete("/{id}")]
+async fn cancel(req: HttpRequest, db: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match alerts::cancel(db.as_ref(), uid, *path).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::<()>::ok(())),
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::err("no active alert")),
+        Err(e) => {
+            tracing::error!("cancel alert: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn alerts_scope() -> Scope {
+    web::scope("/api/alerts")
+        .service(list)
+        .service(create)
+        .service(cancel)
+}
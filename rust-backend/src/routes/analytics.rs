@@ -0,0 +1,271 @@
+// src/routes/analytics.rs
+use crate::{
+    config::settings::Settings,
+    db::replica::ReadPool,
+    routes::strategies::user_id,
+    services::{
+        allocation, analytics, depth_history, seasonality,
+        transfers::{self, TransferError},
+    },
+    utils::types::ApiResponse,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug)]
+pub struct RangeQuery {
+    /// Inclusive lower bound (default: 30 days ago)
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound (default: now)
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// GET /api/analytics/execution?since=…&until=…
+#[get("/execution")]
+async fn execution(
+    req: HttpRequest,
+    db: web::Data<ReadPool>,
+    q: web::Query<RangeQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let until = q.until.unwrap_or_else(Utc::now);
+    let since = q.since.unwrap_or(until - Duration::days(30));
+    if since >= until {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("since must be before until"));
+    }
+
+    // reporting only – served from the replica when one is configured
+    match db
+        .read(|pool| async move { analytics::execution_report(&pool, uid, since, until).await })
+        .await
+    {
+        Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r)),
+        Err(e) => {
+            tracing::error!("execution report: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/analytics/allocations → per-strategy sub-account returns
+#[get("/allocations")]
+async fn allocations(req: HttpRequest, db: web::Data<ReadPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match db
+        .read(|pool| async move { allocation::report(&pool, uid).await })
+        .await
+    {
+        Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r)),
+        Err(e) => {
+            tracing::error!("allocation report: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/analytics/equity?since=…&until=… → equity curve with deposits
+/// and withdrawals taken out of the trading PnL
+#[get("/equity")]
+async fn equity(
+    req: HttpRequest,
+    db: web::Data<ReadPool>,
+    q: web::Query<RangeQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let until = q.until.unwrap_or_else(Utc::now);
+    let since = q.since.unwrap_or(until - Duration::days(30));
+    if since >= until {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("since must be before until"));
+    }
+
+    match db
+        .read(|pool| async move { transfers::equity_curve(&pool, uid, since, until).await })
+        .await
+    {
+        Ok(c) => HttpResponse::Ok().json(ApiResponse::ok(c)),
+        Err(e) => {
+            tracing::error!("equity curve: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SeasonalityQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// One strategy instead of all of the caller's
+    pub strategy_id: Option<Uuid>,
+}
+
+/// GET /api/analytics/seasonality?since=…&until=…&strategy_id=… → realised
+/// strategy PnL by hour of day, weekday and session (UTC)
+#[get("/seasonality")]
+async fn get_seasonality(
+    req: HttpRequest,
+    db: web::Data<ReadPool>,
+    q: web::Query<SeasonalityQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    // a season needs a few cycles of each weekday: default to 90 days
+    let until = q.until.unwrap_or_else(Utc::now);
+    let since = q.since.unwrap_or(until - Duration::days(90));
+    if since >= until {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("since must be before until"));
+    }
+    let strategy_id = q.strategy_id;
+
+    match db
+        .read(
+            |pool| async move { seasonality::report(&pool, uid, strategy_id, since, until).await },
+        )
+        .await
+    {
+        Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r)),
+        Err(e) => {
+            tracing::error!("seasonality report: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrderFlowQuery {
+    pub symbol: String,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Minutes in the rolling window (default 30)
+    pub window: Option<usize>,
+}
+
+/// Longest rolling window, a day of minutes
+const MAX_FLOW_WINDOW: usize = 1_440;
+
+/// GET /api/analytics/orderflow?symbol=…&since=…&until=…&window=… →
+/// per-minute depth aggregates with rolling imbalance and depth slope
+#[get("/orderflow")]
+async fn orderflow(
+    req: HttpRequest,
+    db: web::Data<ReadPool>,
+    q: web::Query<OrderFlowQuery>,
+) -> impl Responder {
+    if let Err(e) = user_id(&req) {
+        return e;
+    }
+
+    let until = q.until.unwrap_or_else(Utc::now);
+    let since = q.since.unwrap_or(until - Duration::hours(6));
+    if since >= until {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("since must be before until"));
+    }
+    let window = q.window.unwrap_or(30);
+    if window == 0 || window > MAX_FLOW_WINDOW {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("window must be 1..=1440 minutes"));
+    }
+    let symbol = q.symbol.clone();
+
+    match db
+        .read(|pool| {
+            let symbol = symbol.clone();
+            async move { depth_history::report(&pool, &symbol, since, until, window).await }
+        })
+        .await
+    {
+        Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r)),
+        Err(e) => {
+            tracing::error!("order flow: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/analytics/transfers?since=…&until=… → synced deposits/withdrawals
+#[get("/transfers")]
+async fn list_transfers(
+    req: HttpRequest,
+    db: web::Data<ReadPool>,
+    q: web::Query<RangeQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let until = q.until.unwrap_or_else(Utc::now);
+    let since = q.since.unwrap_or(until - Duration::days(30));
+    if since >= until {
+        return HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::err("since must be before until"));
+    }
+
+    match db
+        .read(|pool| async move { transfers::list(&pool, uid, since, until).await })
+        .await
+    {
+        Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r)),
+        Err(e) => {
+            tracing::error!("transfers: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// POST /api/analytics/transfers/sync – pull the history now
+#[post("/transfers/sync")]
+async fn sync_transfers(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
+    match transfers::sync(db.as_ref(), uid, settings.is_demo(), master_key.as_bytes()).await {
+        Ok(s) => HttpResponse::Ok().json(ApiResponse::ok(s)),
+        Err(TransferError::Exchange(e)) => {
+            tracing::warn!("transfer sync: {e}");
+            HttpResponse::BadGateway().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(TransferError::Db(e)) => {
+            tracing::error!("transfer sync: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn analytics_scope() -> Scope {
+    web::scope("/api/analytics")
+        .service(execution)
+        .service(allocations)
+        .service(equity)
+        .service(get_seasonality)
+        .service(orderflow)
+        .service(list_transfers)
+        .service(sync_transfers)
+}
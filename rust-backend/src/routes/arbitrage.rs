@@ -0,0 +1,107 @@
+// src/routes/arbitrage.rs
+use actix_web::{delete, get, put, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    routes::strategies::user_id,
+    services::arbitrage::{self, ArbError, SettingsReq},
+    utils::types::ApiResponse,
+};
+
+fn arbitrage_error(ctx: &str, e: ArbError) -> HttpResponse {
+    match e {
+        ArbError::Db(e) => {
+            tracing::error!("{ctx}: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        ArbError::Full => HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string())),
+        e => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+/// GET /api/arbitrage/settings → every symbol the user watches
+#[get("/settings")]
+async fn list_settings(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match arbitrage::list(db.as_ref(), uid).await {
+        Ok(rows) => HttpResponse::Ok().json(ApiResponse::ok(rows)),
+        Err(e) => arbitrage_error("list arbitrage settings", e),
+    }
+}
+
+/// PUT /api/arbitrage/settings/{symbol}
+/// `{ "min_edge_bps": 8, "qty": 0.01, "mode": "alert" | "execute", "enabled": true }`
+#[put("/settings/{symbol}")]
+async fn put_settings(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<String>,
+    body: web::Json<SettingsReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match arbitrage::upsert(db.as_ref(), uid, &path.into_inner(), &body).await {
+        Ok(row) => HttpResponse::Ok().json(ApiResponse::ok(row)),
+        Err(e) => arbitrage_error("save arbitrage settings", e),
+    }
+}
+
+/// DELETE /api/arbitrage/settings/{symbol} – open hedges stay tracked
+#[delete("/settings/{symbol}")]
+async fn delete_settings(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match arbitrage::remove(db.as_ref(), uid, &path.into_inner()).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "removed": true }))),
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::err("not watched")),
+        Err(e) => arbitrage_error("remove arbitrage settings", e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PositionsQuery {
+    pub limit: Option<i64>,
+}
+
+/// GET /api/arbitrage/positions?limit=50 → hedges, newest first
+#[get("/positions")]
+async fn list_positions(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    q: web::Query<PositionsQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    match arbitrage::positions(db.as_ref(), uid, limit).await {
+        Ok(rows) => HttpResponse::Ok().json(ApiResponse::ok(rows)),
+        Err(e) => arbitrage_error("list arbitrage positions", e),
+    }
+}
+
+pub fn arbitrage_scope() -> Scope {
+    web::scope("/api/arbitrage")
+        .service(list_settings)
+        .service(put_settings)
+        .service(delete_settings)
+        .service(list_positions)
+}
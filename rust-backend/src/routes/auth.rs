@@ -0,0 +1,171 @@
+// src/routes/auth.rs
+//! Minting, refreshing and revoking the tokens the auth middleware checks
+//! (`services::auth_tokens`). `/discord`, `/refresh` and `/revoke` are
+//! public – their body is the credential; `/token` takes a request signed
+//! with the master HMAC secret (not an integration key) from a trusted
+//! service.
+use actix_web::{delete, post, web, HttpMessage, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    middleware::auth::SignedRequest,
+    routes::strategies::user_id,
+    services::{
+        audit,
+        auth_tokens::{self, TokenError},
+        identities::{IdentityRef, Provider},
+    },
+    utils::types::ApiResponse,
+};
+
+fn failed(ctx: &str, e: TokenError) -> HttpResponse {
+    let msg = e.to_string();
+    match e {
+        TokenError::Invalid(_) => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg)),
+        TokenError::BadRefresh | TokenError::Discord(_) => {
+            HttpResponse::Unauthorized().json(ApiResponse::<()>::err(&msg))
+        }
+        TokenError::Disabled(_) => {
+            HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::err(&msg))
+        }
+        TokenError::Jwt(e) => {
+            tracing::error!("{ctx}: jwt: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("token error"))
+        }
+        TokenError::Db(e) => {
+            tracing::error!("{ctx}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BootstrapReq {
+    /// Defaults to `discord`
+    pub provider: Option<String>,
+    pub subject: String,
+}
+
+/// POST /api/auth/token `{provider?, subject}` – master-secret signed only
+#[post("/token")]
+async fn bootstrap(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    body: web::Json<BootstrapReq>,
+) -> impl Responder {
+    if req.extensions().get::<SignedRequest>().is_none() {
+        return HttpResponse::Forbidden().json(ApiResponse::<()>::err(
+            "token bootstrap needs a request signed with the master secret",
+        ));
+    }
+    let id = match Provider::parse(body.provider.as_deref().unwrap_or("discord"))
+        .and_then(|p| IdentityRef::new(p, &body.subject))
+    {
+        Ok(id) => id,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    };
+    match auth_tokens::issue(db.as_ref(), &settings.auth_tokens, &id).await {
+        Ok(issued) => {
+            audit::record(
+                issued.user_id,
+                "auth.issue",
+                json!({ "via": "bootstrap", "provider": id.provider, "subject": id.subject }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(issued))
+        }
+        Err(e) => failed("bootstrap token", e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DiscordReq {
+    pub code: String,
+    /// Only when the code was issued for another redirect than the default
+    pub redirect_uri: Option<String>,
+}
+
+/// POST /api/auth/discord `{code, redirect_uri?}` – OAuth sign-in
+#[post("/discord")]
+async fn discord(
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    body: web::Json<DiscordReq>,
+) -> impl Responder {
+    let Some(oauth) = settings.auth_tokens.discord.as_ref() else {
+        return HttpResponse::NotFound().json(ApiResponse::<()>::err("discord sign-in disabled"));
+    };
+    let id = match auth_tokens::discord_identity(oauth, &body.code, body.redirect_uri.as_deref())
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => return failed("discord sign-in", e),
+    };
+    match auth_tokens::issue(db.as_ref(), &settings.auth_tokens, &id).await {
+        Ok(issued) => {
+            audit::record(
+                issued.user_id,
+                "auth.issue",
+                json!({ "via": "discord", "subject": id.subject }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(issued))
+        }
+        Err(e) => failed("discord sign-in", e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RefreshReq {
+    pub refresh_token: String,
+}
+
+/// POST /api/auth/refresh `{refresh_token}` – spends it for a new pair
+#[post("/refresh")]
+async fn refresh(
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    body: web::Json<RefreshReq>,
+) -> impl Responder {
+    match auth_tokens::refresh(db.as_ref(), &settings.auth_tokens, &body.refresh_token).await {
+        Ok(issued) => HttpResponse::Ok().json(ApiResponse::ok(issued)),
+        Err(e) => failed("refresh token", e),
+    }
+}
+
+/// POST /api/auth/revoke `{refresh_token}` – ends that sign-in's session
+#[post("/revoke")]
+async fn revoke(db: web::Data<PgPool>, body: web::Json<RefreshReq>) -> impl Responder {
+    match auth_tokens::revoke(db.as_ref(), &body.refresh_token).await {
+        // unknown tokens too: nothing to learn from the answer
+        Ok(_) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "revoked": true }))),
+        Err(e) => failed("revoke token", e.into()),
+    }
+}
+
+/// DELETE /api/auth/sessions – revoke every refresh token of the caller's account
+#[delete("/sessions")]
+async fn revoke_all(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match auth_tokens::revoke_all(db.as_ref(), uid).await {
+        Ok(n) => {
+            audit::record(Some(uid), "auth.revoke_all", json!({ "tokens": n }));
+            HttpResponse::Ok().json(ApiResponse::ok(json!({ "revoked": n })))
+        }
+        Err(e) => failed("revoke sessions", e.into()),
+    }
+}
+
+pub fn auth_scope() -> Scope {
+    web::scope("/api/auth")
+        .service(bootstrap)
+        .service(discord)
+        .service(refresh)
+        .service(revoke)
+        .service(revoke_all)
+}
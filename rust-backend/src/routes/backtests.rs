@@ -0,0 +1,143 @@
+// src/routes/backtests.rs
+//! Stored backtest runs – list, details and side-by-side comparison – and
+//! progress, queue state and cancellation of backtest jobs.
+use actix_web::{delete, get, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    routes::strategies::user_id,
+    services::{
+        backtest, backtest_pool,
+        backtest_queue::{self, QueueError},
+    },
+    utils::types::ApiResponse,
+};
+
+#[derive(Deserialize)]
+struct ListQuery {
+    strategy: Option<String>,
+    limit: Option<i64>,
+}
+
+/// GET /api/backtests?strategy=vcsr&limit=50 – newest first, without trades
+#[get("")]
+async fn list_runs(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    q: web::Query<ListQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    let limit = q.limit.unwrap_or(50);
+    match backtest::list(db.as_ref(), uid, q.strategy.as_deref(), limit).await {
+        Ok(runs) => HttpResponse::Ok().json(ApiResponse::ok(runs)),
+        Err(e) => {
+            tracing::error!("list_runs: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CompareQuery {
+    a: Uuid,
+    b: Uuid,
+}
+
+/// GET /api/backtests/compare?a={run}&b={run} – metric deltas are `b - a`
+#[get("/compare")]
+async fn compare_runs(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    q: web::Query<CompareQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    let (a, b) = match tokio::try_join!(
+        backtest::get(db.as_ref(), uid, q.a),
+        backtest::get(db.as_ref(), uid, q.b),
+    ) {
+        Ok((Some(a), Some(b))) => (a, b),
+        Ok(_) => {
+            return HttpResponse::NotFound().json(ApiResponse::<()>::err("backtest run not found"))
+        }
+        Err(e) => {
+            tracing::error!("compare_runs: DB error: {e}");
+            return HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"));
+        }
+    };
+    HttpResponse::Ok().json(ApiResponse::ok(backtest::compare(a.summary, b.summary)))
+}
+
+/// GET /api/backtests/progress/{job} – percent done / ETA of a running job
+#[get("/progress/{job}")]
+async fn job_progress(req: HttpRequest, path: web::Path<Uuid>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    match backtest_pool::status(*path, uid) {
+        Some(p) => HttpResponse::Ok().json(ApiResponse::ok(p)),
+        None => HttpResponse::NotFound().json(ApiResponse::<()>::err("backtest job not found")),
+    }
+}
+
+/// GET /api/backtests/jobs – the user's queued, running and recently
+/// finished jobs with their queue position
+#[get("/jobs")]
+async fn list_jobs(req: HttpRequest) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    HttpResponse::Ok().json(ApiResponse::ok(backtest_queue::list(uid)))
+}
+
+/// DELETE /api/backtests/{job} – drop a queued job or stop a running one
+#[delete("/{job}")]
+async fn cancel_job(req: HttpRequest, path: web::Path<Uuid>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    match backtest_queue::cancel(*path, uid) {
+        Ok(job) => HttpResponse::Ok().json(ApiResponse::ok(job)),
+        Err(e @ QueueError::Finished) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+/// GET /api/backtests/{run} – params, range, metrics and every trade
+#[get("/{run}")]
+async fn get_run(req: HttpRequest, db: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    match backtest::get(db.as_ref(), uid, *path).await {
+        Ok(Some(run)) => HttpResponse::Ok().json(ApiResponse::ok(run)),
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()>::err("backtest run not found")),
+        Err(e) => {
+            tracing::error!("get_run: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn backtests_scope() -> Scope {
+    web::scope("/api/backtests")
+        .service(list_runs)
+        .service(compare_runs)
+        .service(job_progress)
+        .service(list_jobs)
+        .service(cancel_job)
+        .service(get_run)
+}
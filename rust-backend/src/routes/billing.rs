@@ -0,0 +1,96 @@
+// src/routes/billing.rs
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    routes::strategies::user_id,
+    services::{
+        billing::{self, BillingError, StripeEvent},
+        usage::Plan,
+    },
+    utils::types::ApiResponse,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct CheckoutReq {
+    /// Premium plan to subscribe to ("pro")
+    pub plan: String,
+}
+
+/// POST /api/billing/checkout → `{ url }` of a Stripe Checkout page
+#[post("/checkout")]
+async fn checkout(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    body: web::Json<CheckoutReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match billing::create_checkout_session(&settings, uid, Plan::parse(&body.plan)).await {
+        Ok(url) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "url": url }))),
+        Err(BillingError::NotConfigured(what)) => {
+            tracing::warn!("checkout: {what} not set");
+            HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::err("billing disabled"))
+        }
+        Err(e @ BillingError::UnsupportedPlan(_)) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => {
+            tracing::error!("checkout for {uid}: {e}");
+            HttpResponse::BadGateway().json(ApiResponse::<()>::err("stripe error"))
+        }
+    }
+}
+
+/// POST /api/billing/webhook – called by Stripe, authenticated by signature
+/// only (listed in the auth middleware's public paths).
+#[post("/webhook")]
+async fn webhook(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    body: web::Bytes,
+) -> impl Responder {
+    let Some(secret) = settings.stripe_webhook_secret.as_deref() else {
+        return HttpResponse::ServiceUnavailable().finish();
+    };
+    let sig = req
+        .headers()
+        .get("Stripe-Signature")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    if billing::verify_signature(&body, sig, secret, chrono::Utc::now().timestamp()).is_err() {
+        tracing::warn!("billing webhook: bad signature");
+        return HttpResponse::BadRequest().finish();
+    }
+
+    let ev: StripeEvent = match serde_json::from_slice(&body) {
+        Ok(ev) => ev,
+        Err(e) => {
+            tracing::warn!("billing webhook: bad payload: {e}");
+            return HttpResponse::BadRequest().finish();
+        }
+    };
+
+    match billing::handle_event(&db, &ev).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        // 5xx → Stripe retries later
+        Err(e) => {
+            tracing::error!("billing webhook {}: {e}", ev.id);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+pub fn billing_scope() -> Scope {
+    web::scope("/api/billing")
+        .service(checkout)
+        .service(webhook)
+}
@@ -0,0 +1,44 @@
+// src/routes/chaos.rs
+//! Fault-injection controls – only compiled with `--features chaos`.
+use actix_web::{delete, get, put, web, HttpResponse, Responder, Scope};
+
+use crate::{
+    middleware::admin::AdminUser,
+    services::chaos::{self, Faults, Target},
+    utils::types::ApiResponse,
+};
+
+/// GET /api/admin/chaos – active faults per target
+#[get("")]
+async fn list_faults(_admin: AdminUser) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::ok(chaos::current()))
+}
+
+/// PUT /api/admin/chaos/{target} – `http`, `exchange` or `cache`
+#[put("/{target}")]
+async fn set_faults(
+    _admin: AdminUser,
+    path: web::Path<Target>,
+    body: web::Json<Faults>,
+) -> impl Responder {
+    if let Err(msg) = body.validate() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg));
+    }
+    chaos::set(path.into_inner(), body.into_inner());
+    HttpResponse::Ok().json(ApiResponse::ok(chaos::current()))
+}
+
+/// DELETE /api/admin/chaos – back to normal
+#[delete("")]
+async fn clear_faults(_admin: AdminUser) -> impl Responder {
+    chaos::clear();
+    HttpResponse::Ok().json(ApiResponse::ok(chaos::current()))
+}
+
+/// Mounted under `/api/admin` (see `routes::admin`)
+pub fn chaos_scope() -> Scope {
+    web::scope("/chaos")
+        .service(list_faults)
+        .service(set_faults)
+        .service(clear_faults)
+}
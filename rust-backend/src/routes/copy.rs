@@ -1,26 +1,34 @@
 //src/routes/copy.rs
 
 use crate::{
-    db::redis::RedisPool,
-    services::copy_trading::{add_follower, remove_follower},
+    config::settings::Settings,
+    db::cache::Cache,
+    routes::strategies::user_id,
+    services::{
+        copy_trading::{self, add_follower, remove_follower, CopyConfig, CopyError},
+        leader_verification::{self, Bracket, LeaderError},
+    },
+    utils::types::ApiResponse,
 };
-use actix_web::{delete, post, web, HttpResponse};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
 use sqlx::PgPool;
 
 #[post("/copy/{leader_id}")]
 async fn follow(
     path: web::Path<i64>,
     pg: web::Data<PgPool>,
-    redis: web::Data<RedisPool>,
+    cache: web::Data<dyn Cache>,
     auth: actix_web::web::ReqData<i64>, // (discord user id inserted by auth middleware)
 ) -> HttpResponse {
     let leader = path.into_inner();
     let follower = *auth; // our own id
 
-    match add_follower(&pg, &redis, leader, follower).await {
+    match add_follower(&pg, cache.get_ref(), leader, follower).await {
         Ok(_) => HttpResponse::Ok().body("following"),
         Err(e) => {
-            log::warn!("follow failed: {}", e);
+            tracing::warn!("follow failed: {}", e);
             HttpResponse::BadRequest().body(e.to_string())
         }
     }
@@ -30,23 +38,211 @@ async fn follow(
 async fn unfollow(
     path: web::Path<i64>,
     pg: web::Data<PgPool>,
-    redis: web::Data<RedisPool>,
+    cache: web::Data<dyn Cache>,
     auth: actix_web::web::ReqData<i64>,
 ) -> HttpResponse {
     let leader = path.into_inner();
     let follower = *auth;
 
-    match remove_follower(&pg, &redis, leader, follower).await {
+    match remove_follower(&pg, cache.get_ref(), leader, follower).await {
         Ok(_) => HttpResponse::Ok().body("un-followed"),
         Err(e) => {
-            log::warn!("unfollow failed: {}", e);
+            tracing::warn!("unfollow failed: {}", e);
             HttpResponse::BadRequest().body(e.to_string())
         }
     }
 }
 
+/// POST /api/copy/{leader_id}/trial – copy for `COPY_TRIAL_DAYS` with each
+/// order capped at `COPY_TRIAL_MAX_SIZE`; expires unless confirmed
+#[post("/copy/{leader_id}/trial")]
+async fn start_trial(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    pg: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    settings: web::Data<Settings>,
+) -> HttpResponse {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let leader = path.into_inner();
+    if leader == uid {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("cannot copy yourself"));
+    }
+    match copy_trading::start_trial(
+        &pg,
+        cache.get_ref(),
+        leader,
+        uid,
+        settings.copy_trial_days,
+        settings.copy_trial_max_size,
+    )
+    .await
+    {
+        Ok(ends_at) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "leader_id": leader,
+            "ends_at": ends_at,
+            "max_size": settings.copy_trial_max_size,
+        }))),
+        Err(e @ (CopyError::AlreadyFollowing | CopyError::TrialUsed)) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => {
+            tracing::warn!("start trial failed: {e}");
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err("could not start trial"))
+        }
+    }
+}
+
+/// POST /api/copy/{leader_id}/trial/confirm – keep copying, without the cap
+#[post("/copy/{leader_id}/trial/confirm")]
+async fn confirm_trial(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    pg: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+) -> HttpResponse {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let leader = path.into_inner();
+    match copy_trading::confirm_trial(&pg, cache.get_ref(), leader, uid).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok("following")),
+        Ok(false) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&CopyError::NoTrial.to_string()))
+        }
+        Err(e) => {
+            tracing::warn!("confirm trial failed: {e}");
+            HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::err("could not confirm trial"))
+        }
+    }
+}
+
+/// GET /api/copy/{leader_id}/config → how your copies of this leader are sized
+#[get("/copy/{leader_id}/config")]
+async fn get_config(req: HttpRequest, path: web::Path<i64>, pg: web::Data<PgPool>) -> HttpResponse {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match copy_trading::copy_config(&pg, path.into_inner(), uid).await {
+        Ok(cfg) => HttpResponse::Ok().json(ApiResponse::ok(cfg)),
+        Err(e @ CopyError::NotFollowing) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => {
+            tracing::error!("copy config: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// PUT /api/copy/{leader_id}/config
+/// `{mode: "ratio", ratio} | {mode: "equity_pct", pct}` plus optional
+/// `max_notional` (USDT per copied entry)
+#[put("/copy/{leader_id}/config")]
+async fn put_config(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    pg: web::Data<PgPool>,
+    body: web::Json<CopyConfig>,
+) -> HttpResponse {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let cfg = body.into_inner();
+    match copy_trading::set_copy_config(&pg, path.into_inner(), uid, cfg).await {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::ok(cfg)),
+        Err(e @ CopyError::InvalidConfig(_)) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e @ CopyError::NotFollowing) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => {
+            tracing::error!("set copy config: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DiscoverQuery {
+    pub limit: Option<i64>,
+}
+
+/// GET /api/copy/leaders?limit=… → listed leaders, verified badges first
+#[get("/copy/leaders")]
+async fn leaders(pg: web::Data<PgPool>, q: web::Query<DiscoverQuery>) -> HttpResponse {
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    match leader_verification::discover(&pg, limit).await {
+        Ok(l) => HttpResponse::Ok().json(ApiResponse::ok(l)),
+        Err(e) => {
+            tracing::error!("leader discovery: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListLeaderReq {
+    pub reported_bracket: Bracket,
+}
+
+/// PUT /api/copy/leader `{reported_bracket}` – list yourself for discovery
+#[put("/copy/leader")]
+async fn list_leader(
+    req: HttpRequest,
+    pg: web::Data<PgPool>,
+    body: web::Json<ListLeaderReq>,
+) -> HttpResponse {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match leader_verification::list(&pg, uid, body.reported_bracket).await {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::ok("listed")),
+        Err(e) => {
+            tracing::error!("list leader: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// DELETE /api/copy/leader – leave discovery; existing followers stay
+#[delete("/copy/leader")]
+async fn unlist_leader(req: HttpRequest, pg: web::Data<PgPool>) -> HttpResponse {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match leader_verification::unlist(&pg, uid).await {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::ok("unlisted")),
+        Err(LeaderError::NotListed) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err("not listed as a leader"))
+        }
+        Err(e) => {
+            tracing::error!("unlist leader: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
 pub fn copy_scope() -> actix_web::Scope {
     web::scope("/api") // shares `/api` prefix
+        .service(leaders)
+        .service(list_leader)
+        .service(unlist_leader)
+        .service(start_trial)
+        .service(confirm_trial)
+        .service(get_config)
+        .service(put_config)
         .service(follow)
         .service(unfollow)
 }
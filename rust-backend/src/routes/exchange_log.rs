@@ -0,0 +1,39 @@
+// src/routes/exchange_log.rs
+//! Exchange payload log – what we sent and what came back, secrets masked.
+use actix_web::{delete, get, web, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+
+use crate::{middleware::admin::AdminUser, services::exchange_log, utils::types::ApiResponse};
+
+#[derive(Deserialize)]
+struct LogQuery {
+    limit: Option<usize>,
+    /// Only transport errors, non-200s and rejected orders
+    #[serde(default)]
+    failed: bool,
+}
+
+/// GET /api/admin/exchange-log?limit=50&failed=true – newest first
+#[get("")]
+async fn list_calls(_admin: AdminUser, q: web::Query<LogQuery>) -> impl Responder {
+    if !exchange_log::enabled() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("exchange log disabled"));
+    }
+    let limit = q.limit.unwrap_or(50).clamp(1, 1_000);
+    HttpResponse::Ok().json(ApiResponse::ok(exchange_log::recent(limit, q.failed)))
+}
+
+/// DELETE /api/admin/exchange-log
+#[delete("")]
+async fn clear_calls(_admin: AdminUser) -> impl Responder {
+    exchange_log::clear();
+    HttpResponse::Ok().json(ApiResponse::ok(()))
+}
+
+/// Mounted under `/api/admin` (see `routes::admin`)
+pub fn exchange_log_scope() -> Scope {
+    web::scope("/exchange-log")
+        .service(list_calls)
+        .service(clear_calls)
+}
@@ -0,0 +1,42 @@
+// src/routes/exposure.rs
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, Scope};
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    routes::strategies::user_id,
+    services::exposure,
+    utils::types::{ApiResponse, DisplayQuery},
+};
+
+/// GET /api/exposure[?display=true] → net/gross per symbol & strategy,
+/// leverage, margin use
+#[get("")]
+async fn exposure_report(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    q: web::Query<DisplayQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
+    match exposure::report(db.as_ref(), uid, settings.is_demo(), master_key.as_bytes()).await {
+        Ok(r) => {
+            let symbols = r.by_symbol.iter().map(|s| s.symbol.as_str());
+            let display = q.meta(symbols.chain(r.by_strategy.iter().map(|s| s.symbol.as_str())));
+            HttpResponse::Ok().json(ApiResponse::ok(r).with_display(display))
+        }
+        Err(e) => {
+            tracing::error!("exposure report: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn exposure_scope() -> Scope {
+    web::scope("/api/exposure").service(exposure_report)
+}
@@ -0,0 +1,122 @@
+// src/routes/fees.rs
+//! The caller's maker/taker fee tier – see `services::fees`.
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    routes::strategies::user_id,
+    services::{
+        audit,
+        fees::{self, FeeError, FeeSchedule},
+    },
+    utils::types::ApiResponse,
+};
+
+/// Only BlowFin executes orders today
+const EXCHANGE: &str = "blowfin";
+
+fn failed(ctx: &str, e: FeeError) -> HttpResponse {
+    match e {
+        FeeError::Invalid(_) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        FeeError::NoFills => HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string())),
+        FeeError::Exchange(_) => {
+            tracing::warn!("{ctx}: {e}");
+            HttpResponse::BadGateway().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        FeeError::Db(e) => {
+            tracing::error!("{ctx}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/fees → stored tier, or the default the engine assumes
+#[get("")]
+async fn get_fees(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match fees::get(db.as_ref(), uid, EXCHANGE).await {
+        Ok(Some(tier)) => HttpResponse::Ok().json(ApiResponse::ok(tier)),
+        Ok(None) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "exchange": EXCHANGE,
+            "maker_bps": fees::default_schedule().maker_bps,
+            "taker_bps": fees::default_schedule().taker_bps,
+            "source": "default",
+        }))),
+        Err(e) => failed("get fees", e.into()),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SetFeesReq {
+    pub tier: Option<String>,
+    #[serde(flatten)]
+    pub schedule: FeeSchedule,
+}
+
+/// PUT /api/fees `{maker_bps, taker_bps, tier?}` – set the tier by hand
+#[put("")]
+async fn set_fees(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    body: web::Json<SetFeesReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let body = body.into_inner();
+    match fees::set(
+        db.as_ref(),
+        uid,
+        EXCHANGE,
+        body.tier.as_deref(),
+        body.schedule,
+        "manual",
+    )
+    .await
+    {
+        Ok(tier) => {
+            audit::record(Some(uid), "fees.set", json!(tier));
+            HttpResponse::Ok().json(ApiResponse::ok(tier))
+        }
+        Err(e) => failed("set fees", e),
+    }
+}
+
+/// POST /api/fees/sync – infer the tier from the last exchange fills
+#[post("/sync")]
+async fn sync_fees(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
+    match fees::sync_from_exchange(db.as_ref(), uid, settings.is_demo(), master_key.as_bytes())
+        .await
+    {
+        Ok(tier) => {
+            audit::record(Some(uid), "fees.sync", json!(tier));
+            HttpResponse::Ok().json(ApiResponse::ok(tier))
+        }
+        Err(e) => failed("sync fees", e),
+    }
+}
+
+pub fn fees_scope() -> Scope {
+    web::scope("/api/fees")
+        .service(get_fees)
+        .service(set_fees)
+        .service(sync_fees)
+}
@@ -0,0 +1,112 @@
+// src/routes/flags.rs
+//! Feature flags: `/me` for the caller, the rest for admins.
+use actix_web::{delete, get, put, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    db::cache::Cache,
+    middleware::admin::AdminUser,
+    routes::strategies::user_id,
+    services::{
+        audit,
+        feature_flags::{self, FlagError, FlagUpdate},
+    },
+    utils::types::ApiResponse,
+};
+
+/// GET /api/flags/me – `{ flag: on/off }` for the caller
+#[get("/me")]
+async fn my_flags(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    let flags = feature_flags::evaluate_all(db.as_ref(), cache.as_ref(), uid).await;
+    HttpResponse::Ok().json(ApiResponse::ok(flags))
+}
+
+/// GET /api/admin/flags – every flag with its rollout
+#[get("")]
+async fn list_flags(_admin: AdminUser, db: web::Data<PgPool>) -> impl Responder {
+    match feature_flags::list(db.as_ref()).await {
+        Ok(flags) => HttpResponse::Ok().json(ApiResponse::ok(flags)),
+        Err(e) => {
+            tracing::error!("list_flags: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// PUT /api/admin/flags/{name} – create or replace
+/// `{ "enabled": true, "rollout_pct": 10, "user_ids": [42] }`
+#[put("/{name}")]
+async fn set_flag(
+    admin: AdminUser,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    path: web::Path<String>,
+    body: web::Json<FlagUpdate>,
+) -> impl Responder {
+    let name = path.into_inner();
+    match feature_flags::upsert(db.as_ref(), cache.as_ref(), &name, &body).await {
+        Ok(flag) => {
+            audit::record(
+                Some(admin.0),
+                "flag.set",
+                json!({
+                    "name": flag.name,
+                    "enabled": flag.enabled,
+                    "rollout_pct": flag.rollout_pct,
+                    "user_ids": flag.user_ids,
+                }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(flag))
+        }
+        Err(FlagError::Invalid(msg)) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg))
+        }
+        Err(e) => {
+            tracing::error!("set_flag: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// DELETE /api/admin/flags/{name} – the flag reads as off from then on
+#[delete("/{name}")]
+async fn delete_flag(
+    admin: AdminUser,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let name = path.into_inner();
+    match feature_flags::delete(db.as_ref(), cache.as_ref(), &name).await {
+        Ok(true) => {
+            audit::record(Some(admin.0), "flag.delete", json!({ "name": name }));
+            HttpResponse::Ok().json(ApiResponse::ok(json!({ "deleted": name })))
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::err("no such flag")),
+        Err(e) => {
+            tracing::error!("delete_flag: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn flags_scope() -> Scope {
+    web::scope("/api/flags").service(my_flags)
+}
+
+/// Mounted under `/api/admin` (see `routes::admin`)
+pub fn flags_admin_scope() -> Scope {
+    web::scope("/flags")
+        .service(list_flags)
+        .service(set_flag)
+        .service(delete_flag)
+}
@@ -1,11 +1,63 @@
 // src/routes/health.rs
-use actix_web::{get, web, HttpResponse, Scope};
+use std::time::Duration;
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Scope};
+use serde_json::json;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    config::settings::Settings,
+    services::{drain, time_sync},
+    utils::types::ApiResponse,
+};
 
 #[get("")]
 async fn health_check() -> HttpResponse {
     HttpResponse::Ok().body("OK")
 }
 
+/// GET /health/ready – load-balancer probe; 503 once draining
+#[get("/ready")]
+async fn ready() -> HttpResponse {
+    if drain::is_draining() {
+        HttpResponse::ServiceUnavailable()
+            .json(json!({ "draining": true, "in_flight": drain::in_flight() }))
+    } else {
+        HttpResponse::Ok().body("ready")
+    }
+}
+
+/// GET /health/clock – measured offset to each exchange's clock
+#[get("/clock")]
+async fn clock() -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse::ok(time_sync::status()))
+}
+
+/// POST /health/drain – `X-Drain-Token: <DRAIN_TOKEN>`; returns immediately,
+/// the server stops once in-flight work settles.
+#[post("/drain")]
+async fn start_drain(req: HttpRequest, settings: web::Data<Settings>) -> HttpResponse {
+    let Some(expected) = settings.drain_token.as_deref() else {
+        return HttpResponse::NotFound().finish();
+    };
+    let given = req
+        .headers()
+        .get("X-Drain-Token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !bool::from(given.as_bytes().ct_eq(expected.as_bytes())) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::err("bad drain token"));
+    }
+
+    let timeout = Duration::from_secs(settings.drain_timeout_secs);
+    actix_web::rt::spawn(drain::drain_and_stop(timeout));
+    HttpResponse::Accepted().json(ApiResponse::ok(json!({ "in_flight": drain::in_flight() })))
+}
+
 pub fn health_scope() -> Scope {
-    web::scope("/health").service(health_check)
+    web::scope("/health")
+        .service(health_check)
+        .service(ready)
+        .service(clock)
+        .service(start_drain)
 }
@@ -0,0 +1,137 @@
+// src/routes/integrations.rs
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    middleware::admin::AdminUser,
+    services::{
+        audit,
+        integration_keys::{self, IntegrationKeyError},
+    },
+    utils::types::ApiResponse,
+};
+
+/// Old key stays valid this long after a rotation unless told otherwise
+const DEFAULT_GRACE_SECS: i64 = 3_600;
+
+fn key_error(e: IntegrationKeyError) -> HttpResponse {
+    match e {
+        IntegrationKeyError::NotFound => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err("key not found or inactive"))
+        }
+        IntegrationKeyError::Invalid(msg) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg))
+        }
+        e => {
+            tracing::error!("integration keys: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("internal error"))
+        }
+    }
+}
+
+/// GET /api/admin/integrations/keys – every key, no secrets
+#[get("/keys")]
+async fn list_keys(_admin: AdminUser, db: web::Data<PgPool>) -> impl Responder {
+    match integration_keys::list(db.as_ref()).await {
+        Ok(keys) => HttpResponse::Ok().json(ApiResponse::ok(keys)),
+        Err(e) => {
+            tracing::error!("list_keys: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateKey {
+    integration: String,
+    /// Route prefixes the key may call, e.g. `["/api/alerts"]`
+    scopes: Vec<String>,
+}
+
+/// POST /api/admin/integrations/keys – the secret is only ever shown here
+#[post("/keys")]
+async fn create_key(
+    admin: AdminUser,
+    db: web::Data<PgPool>,
+    body: web::Json<CreateKey>,
+) -> impl Responder {
+    match integration_keys::create(db.as_ref(), &body.integration, &body.scopes).await {
+        Ok(k) => {
+            audit::record(
+                Some(admin.0),
+                "integration_key.create",
+                json!({ "key_id": k.key_id, "integration": k.integration, "scopes": k.scopes }),
+            );
+            HttpResponse::Created().json(ApiResponse::ok(k))
+        }
+        Err(e) => key_error(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct RotateQuery {
+    grace_secs: Option<i64>,
+}
+
+/// POST /api/admin/integrations/keys/{id}/rotate?grace_secs= – successor key with
+/// the same scopes; the old one expires after the grace period
+#[post("/keys/{id}/rotate")]
+async fn rotate_key(
+    admin: AdminUser,
+    db: web::Data<PgPool>,
+    path: web::Path<String>,
+    q: web::Query<RotateQuery>,
+) -> impl Responder {
+    let grace_secs = q.grace_secs.unwrap_or(DEFAULT_GRACE_SECS);
+    if grace_secs < 0 {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("grace_secs must be >= 0"));
+    }
+    let old = path.into_inner();
+    match integration_keys::rotate(db.as_ref(), &old, chrono::Duration::seconds(grace_secs)).await {
+        Ok(k) => {
+            audit::record(
+                Some(admin.0),
+                "integration_key.rotate",
+                json!({ "old_key_id": old, "key_id": k.key_id, "grace_secs": grace_secs }),
+            );
+            HttpResponse::Created().json(ApiResponse::ok(k))
+        }
+        Err(e) => key_error(e),
+    }
+}
+
+/// DELETE /api/admin/integrations/keys/{id} – revoke immediately
+#[delete("/keys/{id}")]
+async fn revoke_key(
+    admin: AdminUser,
+    db: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let key_id = path.into_inner();
+    match integration_keys::revoke(db.as_ref(), &key_id).await {
+        Ok(true) => {
+            audit::record(
+                Some(admin.0),
+                "integration_key.revoke",
+                json!({ "key_id": key_id }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(json!({ "revoked": key_id })))
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::err("key not found")),
+        Err(e) => {
+            tracing::error!("revoke_key: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// Mounted under `/api/admin` (see `routes::admin`)
+pub fn integrations_scope() -> Scope {
+    web::scope("/integrations")
+        .service(list_keys)
+        .service(create_key)
+        .service(rotate_key)
+        .service(revoke_key)
+}
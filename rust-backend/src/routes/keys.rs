@@ -0,0 +1,131 @@
+// src/routes/keys.rs
+//! Exchange API keys: register, list, rotate and remove. Secrets go in, never out.
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::settings::Settings,
+    routes::strategies::user_id,
+    services::{
+        api_keys::{self, KeyCreds, KeyError, NewKeyReq},
+        audit,
+    },
+    utils::types::ApiResponse,
+};
+
+fn key_error(op: &str, e: KeyError) -> HttpResponse {
+    match e {
+        KeyError::Db(e) => {
+            tracing::error!("keys {op}: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        e @ (KeyError::Exists(_) | KeyError::InUse(_)) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        e @ KeyError::NotFound => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        e @ KeyError::Exchange(_) => {
+            tracing::warn!("keys {op}: {e}");
+            HttpResponse::BadGateway().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        e => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+/// GET /api/keys
+#[get("")]
+async fn list(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    match api_keys::list(db.as_ref(), uid).await {
+        Ok(keys) => HttpResponse::Ok().json(ApiResponse::ok(keys)),
+        Err(e) => key_error("list", e),
+    }
+}
+
+/// POST /api/keys
+#[post("")]
+async fn add(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    body: web::Json<NewKeyReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    match api_keys::add(db.as_ref(), uid, body.into_inner(), settings.is_demo()).await {
+        Ok(key) => {
+            audit::record(
+                Some(uid),
+                "keys.add",
+                json!({ "key_id": key.key_id, "exchange": key.exchange }),
+            );
+            HttpResponse::Created().json(ApiResponse::ok(key))
+        }
+        Err(e) => key_error("add", e),
+    }
+}
+
+/// PUT /api/keys/{key_id} – swap in new credentials for the same exchange
+#[put("/{key_id}")]
+async fn rotate(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    body: web::Json<KeyCreds>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    let key_id = path.into_inner();
+    let creds = body.into_inner();
+    match api_keys::rotate(db.as_ref(), uid, key_id, creds, settings.is_demo()).await {
+        Ok(key) => {
+            audit::record(
+                Some(uid),
+                "keys.rotate",
+                json!({ "key_id": key_id, "exchange": key.exchange }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(key))
+        }
+        Err(e) => key_error("rotate", e),
+    }
+}
+
+/// DELETE /api/keys/{key_id}
+#[delete("/{key_id}")]
+async fn remove(req: HttpRequest, db: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    let key_id = path.into_inner();
+    match api_keys::remove(db.as_ref(), uid, key_id).await {
+        Ok(exchange) => {
+            audit::record(
+                Some(uid),
+                "keys.remove",
+                json!({ "key_id": key_id, "exchange": exchange }),
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => key_error("remove", e),
+    }
+}
+
+pub fn keys_scope() -> Scope {
+    web::scope("/api/keys")
+        .service(list)
+        .service(add)
+        .service(rotate)
+        .service(remove)
+}
@@ -0,0 +1,113 @@
+// src/routes/metric_sinks.rs
+//! The caller's metric sinks (`services::metric_sinks`): where their own
+//! strategy metrics are pushed.
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::cache::Cache,
+    routes::strategies::user_id,
+    services::{
+        audit,
+        metric_sinks::{self, NewSink, SinkError},
+    },
+    utils::types::ApiResponse,
+};
+
+fn sink_error(e: SinkError) -> HttpResponse {
+    let msg = e.to_string();
+    match e {
+        SinkError::Invalid(_) => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg)),
+        SinkError::NotAllowed => HttpResponse::Forbidden().json(ApiResponse::<()>::err(&msg)),
+        SinkError::TooMany => HttpResponse::Conflict().json(ApiResponse::<()>::err(&msg)),
+        SinkError::NotFound => HttpResponse::NotFound().json(ApiResponse::<()>::err(&msg)),
+        SinkError::Db(e) => {
+            tracing::error!("metric sinks: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/metrics/sinks
+#[get("")]
+async fn list(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match metric_sinks::list(db.as_ref(), uid).await {
+        Ok(sinks) => HttpResponse::Ok().json(ApiResponse::ok(sinks)),
+        Err(e) => sink_error(e.into()),
+    }
+}
+
+/// POST /api/metrics/sinks `{kind, url, interval_secs?}` – the signing
+/// secret is only ever shown here
+#[post("")]
+async fn add(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    body: web::Json<NewSink>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match metric_sinks::add(db.as_ref(), cache.get_ref(), uid, body.into_inner()).await {
+        Ok(issued) => {
+            audit::record(
+                Some(uid),
+                "metrics.sink_add",
+                json!({ "sink_id": issued.sink.sink_id, "kind": issued.sink.kind, "url": issued.sink.url }),
+            );
+            HttpResponse::Created().json(ApiResponse::ok(issued))
+        }
+        Err(e) => sink_error(e),
+    }
+}
+
+/// POST /api/metrics/sinks/{sink_id}/enable – after it was switched off
+#[post("/{sink_id}/enable")]
+async fn enable(req: HttpRequest, db: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match metric_sinks::enable(db.as_ref(), uid, path.into_inner()).await {
+        Ok(sink) => HttpResponse::Ok().json(ApiResponse::ok(sink)),
+        Err(e) => sink_error(e),
+    }
+}
+
+/// DELETE /api/metrics/sinks/{sink_id}
+#[delete("/{sink_id}")]
+async fn remove(req: HttpRequest, db: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let sink_id = path.into_inner();
+    match metric_sinks::remove(db.as_ref(), uid, sink_id).await {
+        Ok(true) => {
+            audit::record(
+                Some(uid),
+                "metrics.sink_remove",
+                json!({ "sink_id": sink_id }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(json!({ "removed": sink_id })))
+        }
+        Ok(false) => sink_error(SinkError::NotFound),
+        Err(e) => sink_error(e.into()),
+    }
+}
+
+pub fn metric_sinks_scope() -> Scope {
+    web::scope("/api/metrics/sinks")
+        .service(list)
+        .service(add)
+        .service(enable)
+        .service(remove)
+}
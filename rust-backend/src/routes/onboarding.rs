@@ -0,0 +1,71 @@
+// src/routes/onboarding.rs
+//! Guided first setup: key, balance check and a starter strategy in one call.
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    db::cache::Cache,
+    routes::strategies::user_id,
+    services::{
+        audit, drain,
+        onboarding::{self, OnboardingError, QuickstartReq},
+        usage,
+    },
+    utils::types::ApiResponse,
+};
+
+/// POST /api/onboarding/quickstart
+#[post("/quickstart")]
+async fn quickstart(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    body: web::Json<QuickstartReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    if drain::is_draining() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("server is draining – retry shortly"));
+    }
+
+    match onboarding::quickstart(db.as_ref(), uid, body.into_inner(), settings.is_demo()).await {
+        Ok(summary) => {
+            usage::invalidate_strategies(cache.get_ref(), uid).await;
+            audit::record(
+                Some(uid),
+                "onboarding.quickstart",
+                json!({
+                    "key_id": summary.key_id,
+                    "strategy_id": summary.strategy_id,
+                    "symbol": summary.symbol,
+                }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(summary))
+        }
+        Err(OnboardingError::Db(e)) => {
+            tracing::error!("quickstart: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        Err(e @ OnboardingError::KeyExists(_)) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e @ OnboardingError::Exchange(_)) => {
+            tracing::warn!("quickstart: {e}");
+            HttpResponse::BadGateway().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e @ OnboardingError::InsufficientBalance { .. }) => {
+            HttpResponse::UnprocessableEntity().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+pub fn onboarding_scope() -> Scope {
+    web::scope("/api/onboarding").service(quickstart)
+}
@@ -0,0 +1,90 @@
+// src/routes/optimize.rs
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::cache::Cache,
+    routes::strategies::{user_id, ALLOWED_FREE_STRATS},
+    services::{
+        audit, drain,
+        optimizer::{self, OptimizerError},
+        scheduler, usage,
+    },
+    utils::types::ApiResponse,
+};
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ApplyReq {
+    /// Update this strategy instead of creating a new one
+    pub strategy_id: Option<Uuid>,
+}
+
+/// POST /api/optimize/{job}/apply – run the job's best params
+#[post("/{job}/apply")]
+async fn apply(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    path: web::Path<Uuid>,
+    body: Option<web::Json<ApplyReq>>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    if drain::is_draining() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("server is draining – retry shortly"));
+    }
+    let job_id = path.into_inner();
+    let target = body.and_then(|b| b.into_inner().strategy_id);
+
+    // same tier gate as POST /api/strategies
+    if target.is_none() && usage::plan_for(db.as_ref(), uid).await == usage::Plan::Free {
+        match optimizer::get_job(db.as_ref(), uid, job_id).await {
+            Ok(job) if !ALLOWED_FREE_STRATS.contains(&job.strategy.as_str()) => {
+                return HttpResponse::Forbidden().json(ApiResponse::<()>::err(
+                    "upgrade required for custom strategies",
+                ))
+            }
+            _ => {} // errors are reported by apply_job below
+        }
+    }
+
+    match optimizer::apply_job(db.as_ref(), uid, job_id, target).await {
+        Ok(applied) => {
+            if applied.created {
+                usage::invalidate_strategies(cache.get_ref(), uid).await;
+            } else {
+                scheduler::respawn(applied.strategy_id);
+            }
+            audit::record(
+                Some(uid),
+                "strategy.apply_optimizer",
+                json!({
+                    "job_id": job_id,
+                    "strategy_id": applied.strategy_id,
+                    "created": applied.created,
+                }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(applied))
+        }
+        Err(e @ (OptimizerError::JobNotFound | OptimizerError::StrategyNotFound)) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e @ (OptimizerError::NotFinished(_) | OptimizerError::StrategyMismatch { .. })) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(OptimizerError::Db(e)) => {
+            tracing::error!("apply optimizer job {job_id}: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn optimize_scope() -> Scope {
+    web::scope("/api/optimize").service(apply)
+}
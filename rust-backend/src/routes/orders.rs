@@ -0,0 +1,51 @@
+// src/routes/orders.rs
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, Scope};
+use uuid::Uuid;
+
+use crate::{
+    db::replica::ReadPool,
+    routes::strategies::user_id,
+    services::replay::{self, ReplayQuery},
+    utils::types::{ApiResponse, DisplayQuery},
+};
+
+/// GET /api/orders/{id}/replay?interval=1h&before=60&after=30[&display=true]
+/// → candles around the order, entry/exit markers and the decision trace
+#[get("/{id}/replay")]
+async fn trade_replay(
+    req: HttpRequest,
+    db: web::Data<ReadPool>,
+    path: web::Path<Uuid>,
+    q: web::Query<ReplayQuery>,
+    dq: web::Query<DisplayQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let w = match q.window() {
+        Ok(w) => w,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    };
+    let order_id = path.into_inner();
+    let w = &w;
+    match db
+        .read(|pool| async move { replay::replay(&pool, uid, order_id, w).await })
+        .await
+    {
+        Ok(Some(r)) => {
+            let display = dq.meta([r.order.symbol.as_str()]);
+            HttpResponse::Ok().json(ApiResponse::ok(r).with_display(display))
+        }
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()>::err("order not found")),
+        Err(e) => {
+            tracing::error!("trade replay: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn orders_scope() -> Scope {
+    web::scope("/api/orders").service(trade_replay)
+}
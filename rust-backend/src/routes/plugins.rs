@@ -0,0 +1,104 @@
+// src/routes/plugins.rs
+//! Uploaded WASM strategy plugins – only compiled with `--features wasm`.
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    routes::strategies::user_id,
+    services::{
+        audit,
+        strategies::plugin::{self, PluginError, MAX_WASM},
+        usage,
+    },
+    utils::types::ApiResponse,
+};
+
+/// POST /api/plugins/{name} – raw `.wasm` body, validated before it's stored
+#[post("/{name}")]
+async fn upload_plugin(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    if usage::plan_for(db.as_ref(), uid).await == usage::Plan::Free {
+        return HttpResponse::Forbidden().json(ApiResponse::<()>::err(
+            "upgrade required for custom strategies",
+        ));
+    }
+
+    let name = path.into_inner();
+    match plugin::upload(db.as_ref(), uid, &name, body.to_vec()).await {
+        Ok(info) => {
+            audit::record(
+                Some(uid),
+                "plugin.upload",
+                json!({ "plugin_id": info.plugin_id, "name": info.name, "sha256": info.sha256 }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(info))
+        }
+        Err(PluginError::Db(e)) => {
+            tracing::error!("upload_plugin: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        Err(e @ PluginError::Exists(_)) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+/// GET /api/plugins – the caller's plugins (metadata only)
+#[get("")]
+async fn list_plugins(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    match plugin::list(db.as_ref(), uid).await {
+        Ok(list) => HttpResponse::Ok().json(ApiResponse::ok(list)),
+        Err(e) => {
+            tracing::error!("list_plugins: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// DELETE /api/plugins/{id} – strategies still using it park as invalid on
+/// their next start
+#[delete("/{id}")]
+async fn delete_plugin(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(u) => u,
+        Err(e) => return e,
+    };
+    match plugin::delete(db.as_ref(), uid, *path).await {
+        Ok(true) => {
+            audit::record(Some(uid), "plugin.delete", json!({ "plugin_id": *path }));
+            HttpResponse::Ok().json(ApiResponse::<()>::ok(()))
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::err("plugin not found")),
+        Err(e) => {
+            tracing::error!("delete_plugin: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn plugins_scope() -> Scope {
+    web::scope("/api/plugins")
+        .app_data(web::PayloadConfig::new(MAX_WASM))
+        .service(list_plugins)
+        .service(upload_plugin)
+        .service(delete_plugin)
+}
@@ -0,0 +1,63 @@
+// src/routes/portfolio.rs
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, Scope};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{
+    db::replica::ReadPool,
+    routes::strategies::user_id,
+    services::portfolio_history,
+    utils::types::{ApiResponse, DisplayQuery},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct AtQuery {
+    /// RFC 3339 (default: now)
+    pub at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub display: bool,
+}
+
+/// GET /api/portfolio?at=…[&display=true] → positions, balances and open
+/// orders as they stood at `at`
+#[get("")]
+async fn portfolio_at(
+    req: HttpRequest,
+    db: web::Data<ReadPool>,
+    q: web::Query<AtQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let now = Utc::now();
+    let at = q.at.unwrap_or(now);
+    if at > now {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("at is in the future"));
+    }
+
+    // an audit read – served from the replica when one is configured
+    match db
+        .read(|pool| async move { portfolio_history::as_of(&pool, uid, at).await })
+        .await
+    {
+        Ok(p) => {
+            let display = DisplayQuery { display: q.display }.meta(
+                p.positions
+                    .iter()
+                    .map(|h| h.symbol.as_str())
+                    .chain(p.open_orders.iter().map(|o| o.symbol.as_str())),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(p).with_display(display))
+        }
+        Err(e) => {
+            tracing::error!("portfolio at {at}: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn portfolio_scope() -> Scope {
+    web::scope("/api/portfolio").service(portfolio_at)
+}
@@ -0,0 +1,133 @@
+// src/routes/positions.rs
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    routes::strategies::user_id,
+    services::{
+        audit, drain,
+        event_bus::{EventBus, Topic},
+        positions::{self, CloseAmount, CloseError},
+    },
+    utils::types::ApiResponse,
+};
+
+/// POST /api/positions/{symbol}/close[?percent=50 | ?size=0.2]
+/// → reduce-only market order trimming (or, without an amount, flattening)
+/// the position
+#[post("/{symbol}/close")]
+async fn close_position(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    events: Option<web::Data<EventBus>>,
+    path: web::Path<String>,
+    q: web::Query<CloseAmount>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    if drain::is_draining() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("server is draining – retry shortly"));
+    }
+
+    let symbol = path.into_inner();
+    let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
+    let closed = positions::close(
+        db.as_ref(),
+        uid,
+        &symbol,
+        q.into_inner(),
+        settings.is_demo(),
+        master_key.as_bytes(),
+    )
+    .await;
+    match closed {
+        Ok(fill) => {
+            // same fan-out as `/api/trade`, so followers trim too
+            if let Some(events) = events {
+                let evt = serde_json::json!({ "user_id": uid, "fill": &fill });
+                if let Err(e) = events.publish(Topic::Fills, &evt).await {
+                    tracing::warn!("publish fill event: {e}");
+                }
+            }
+            HttpResponse::Ok().json(ApiResponse::ok(fill))
+        }
+        Err(e @ CloseError::NoPosition(_)) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e @ CloseError::Invalid(_)) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => {
+            tracing::error!("close position: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+    }
+}
+
+/// POST /api/positions/close-all → cancel every open order and market-close
+/// every position; meant for emergencies, so it runs even while draining
+#[post("/close-all")]
+async fn close_all(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    events: Option<web::Data<EventBus>>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
+    let flattened =
+        positions::close_all(db.as_ref(), uid, settings.is_demo(), master_key.as_bytes()).await;
+    match flattened {
+        Ok(report) => {
+            audit::record(
+                Some(uid),
+                "positions.close_all",
+                json!({
+                    "closed": report.closed.iter().map(|f| &f.symbol).collect::<Vec<_>>(),
+                    "orders_cancelled": report.orders_cancelled,
+                    "stops_cancelled": report.stops_cancelled,
+                    "errors": report.errors,
+                }),
+            );
+            if let Some(events) = events {
+                for fill in &report.closed {
+                    let evt = json!({ "user_id": uid, "fill": fill });
+                    if let Err(e) = events.publish(Topic::Fills, &evt).await {
+                        tracing::warn!("publish fill event: {e}");
+                    }
+                }
+            }
+            let mut status = if report.is_complete() {
+                HttpResponse::Ok()
+            } else {
+                HttpResponse::MultiStatus()
+            };
+            status.json(ApiResponse::ok(report))
+        }
+        Err(e) => {
+            audit::record(
+                Some(uid),
+                "positions.close_all",
+                json!({ "error": e.to_string() }),
+            );
+            tracing::error!("close all: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+    }
+}
+
+pub fn positions_scope() -> Scope {
+    web::scope("/api/positions")
+        .service(close_all)
+        .service(close_position)
+}
@@ -0,0 +1,49 @@
+// src/routes/public.rs
+//! Unauthenticated, aggregate-only endpoints for a public status page.
+use actix_web::{get, http::header, web, HttpRequest, HttpResponse, Scope};
+use chrono::Utc;
+
+use crate::{
+    config::settings::Settings, db::cache::Cache, services::public_stats, utils::types::ApiResponse,
+};
+
+/// GET /api/public/stats
+#[get("/stats")]
+async fn stats(
+    req: HttpRequest,
+    settings: web::Data<Settings>,
+    db: web::Data<sqlx::PgPool>,
+    cache: web::Data<dyn Cache>,
+) -> HttpResponse {
+    let ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or("unknown")
+        .to_string();
+    if let Err(retry_after) = public_stats::admit(
+        cache.get_ref(),
+        &ip,
+        settings.public_stats_rate_per_min,
+        Utc::now(),
+    )
+    .await
+    {
+        return HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+            .json(ApiResponse::<()>::err("rate limited"));
+    }
+
+    match public_stats::get(db.as_ref(), cache.get_ref()).await {
+        Ok(stats) => HttpResponse::Ok()
+            .insert_header((header::CACHE_CONTROL, "public, max-age=30"))
+            .json(ApiResponse::ok(stats)),
+        Err(e) => {
+            tracing::error!("public stats: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn public_scope() -> Scope {
+    web::scope("/api/public").service(stats)
+}
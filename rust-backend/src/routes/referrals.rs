@@ -0,0 +1,96 @@
+// src/routes/referrals.rs
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    db::replica::ReadPool,
+    routes::strategies::user_id,
+    services::{
+        audit,
+        referrals::{self, ReferralError},
+    },
+    utils::types::ApiResponse,
+};
+
+/// POST /api/referrals/codes → a fresh code for the caller
+#[post("/codes")]
+async fn create_code(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match referrals::generate_code(db.as_ref(), uid).await {
+        Ok(code) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "code": code }))),
+        Err(e @ ReferralError::TooManyCodes) => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        Err(e) => {
+            tracing::error!("create_code: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AttributeReq {
+    pub code: String,
+}
+
+/// POST /api/referrals/attribute – redeem a code right after signup
+#[post("/attribute")]
+async fn attribute(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    body: web::Json<AttributeReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match referrals::attribute_signup(db.as_ref(), uid, &body.code).await {
+        Ok(referrer) => {
+            audit::record(
+                Some(uid),
+                "referral.attribute",
+                json!({ "referrer": referrer }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(json!({ "referrer": referrer })))
+        }
+        Err(ReferralError::Db(e)) => {
+            tracing::error!("attribute: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+/// GET /api/referrals/stats
+#[get("/stats")]
+async fn get_stats(req: HttpRequest, db: web::Data<ReadPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let stats = db
+        .read(|pool| async move { referrals::stats(&pool, uid).await })
+        .await;
+    match stats {
+        Ok(s) => HttpResponse::Ok().json(ApiResponse::ok(s)),
+        Err(e) => {
+            tracing::error!("referral stats: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+pub fn referrals_scope() -> Scope {
+    web::scope("/api/referrals")
+        .service(create_code)
+        .service(attribute)
+        .service(get_stats)
+}
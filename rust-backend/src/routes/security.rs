@@ -0,0 +1,59 @@
+// src/routes/security.rs
+//! Account activity and the anomaly freeze – see `services::anomaly`.
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde_json::json;
+
+use crate::{
+    db::cache::Cache,
+    routes::strategies::user_id,
+    services::{anomaly, usage},
+    utils::types::ApiResponse,
+};
+
+/// GET /api/security/activity – calls per route over the last 24 h and any
+/// active trading freeze
+#[get("/activity")]
+async fn activity(req: HttpRequest, cache: web::Data<dyn Cache>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let cache = cache.get_ref();
+
+    let routes = usage::endpoint_activity(cache, uid).await;
+    let frozen = anomaly::frozen(cache, uid).await;
+    match (routes, frozen) {
+        (Ok(routes), Ok(frozen)) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "hours": usage::ACTIVITY_HOURS,
+            "routes": routes,
+            "frozen": frozen,
+        }))),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::error!("security activity for {uid}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("activity unavailable"))
+        }
+    }
+}
+
+/// POST /api/security/confirm – "that was me": lift a trading freeze
+#[post("/confirm")]
+async fn confirm(req: HttpRequest, cache: web::Data<dyn Cache>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match anomaly::confirm(cache.get_ref(), uid).await {
+        Ok(Some(lifted)) => HttpResponse::Ok().json(ApiResponse::ok(lifted)),
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()>::err("trading is not frozen")),
+        Err(e) => {
+            tracing::error!("security confirm for {uid}: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("cache error"))
+        }
+    }
+}
+
+pub fn security_scope() -> Scope {
+    web::scope("/api/security")
+        .service(activity)
+        .service(confirm)
+}
@@ -0,0 +1,110 @@
+// src/routes/storage.rs
+//! Candle storage: usage per series, on-demand compaction and integrity
+//! checks (admins).
+use actix_web::{get, post, web, HttpResponse, Responder, Scope};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    middleware::admin::AdminUser,
+    services::{audit, candle_integrity, candle_retention},
+    utils::types::ApiResponse,
+};
+
+/// GET /api/admin/storage/candles – table size, bars per series, active policies
+#[get("/candles")]
+async fn candle_storage(
+    _admin: AdminUser,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+) -> impl Responder {
+    match candle_retention::storage(db.as_ref()).await {
+        Ok(report) => HttpResponse::Ok().json(ApiResponse::ok(json!({
+            "table_bytes": report.table_bytes,
+            "series": report.series,
+            "policies": settings.candle_retention,
+        }))),
+        Err(e) => {
+            tracing::error!("candle_storage: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// POST /api/admin/storage/candles/compact – run a compaction pass now
+#[post("/candles/compact")]
+async fn compact_candles(
+    admin: AdminUser,
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+) -> impl Responder {
+    match candle_retention::compact(db.as_ref(), &settings.candle_retention, Utc::now()).await {
+        Ok(done) => {
+            audit::record(Some(admin.0), "candles.compact", json!({ "result": done }));
+            HttpResponse::Ok().json(ApiResponse::ok(done))
+        }
+        Err(e) => {
+            tracing::error!("compact_candles: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VerifyQuery {
+    /// Window to verify, default `candle_integrity::VERIFY_WINDOW`
+    hours: Option<i64>,
+}
+
+/// POST /api/admin/storage/candles/verify?hours=48 – seal unchecked bars and
+/// cross-check recent ones against the history provider now
+#[post("/candles/verify")]
+async fn verify_candles(
+    _admin: AdminUser,
+    db: web::Data<PgPool>,
+    q: web::Query<VerifyQuery>,
+) -> impl Responder {
+    let window = q.hours.map_or(candle_integrity::VERIFY_WINDOW, |h| {
+        Duration::hours(h.clamp(1, 24 * 90))
+    });
+    match candle_integrity::run_once(db.as_ref(), window).await {
+        Ok(reports) => HttpResponse::Ok().json(ApiResponse::ok(reports)),
+        Err(e) => {
+            tracing::error!("verify_candles: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DiscrepancyQuery {
+    limit: Option<i64>,
+}
+
+/// GET /api/admin/storage/candles/discrepancies?limit=100 – newest findings first
+#[get("/candles/discrepancies")]
+async fn candle_discrepancies(
+    _admin: AdminUser,
+    db: web::Data<PgPool>,
+    q: web::Query<DiscrepancyQuery>,
+) -> impl Responder {
+    match candle_integrity::discrepancies(db.as_ref(), q.limit.unwrap_or(100)).await {
+        Ok(rows) => HttpResponse::Ok().json(ApiResponse::ok(rows)),
+        Err(e) => {
+            tracing::error!("candle_discrepancies: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// Mounted under `/api/admin` (see `routes::admin`)
+pub fn storage_scope() -> Scope {
+    web::scope("/storage")
+        .service(candle_storage)
+        .service(compact_candles)
+        .service(verify_candles)
+        .service(candle_discrepancies)
+}
@@ -1,73 +1,130 @@
 // src/routes/strategies.rs
-use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse, Responder, Scope};
-use serde::Deserialize;
-use sqlx::PgPool;
+use actix_web::{
+    delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse, Responder, Scope,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
-use crate::{db::models::UserStrategy, utils::types::ApiResponse};
+use crate::{
+    db::{cache::Cache, models::UserStrategy},
+    services::{
+        allocation, audit, drain,
+        params_history::{self, ParamsHistoryError},
+        scheduler,
+        strategies::{
+            heartbeat::{self, HeartbeatView},
+            schema::FieldError,
+            warmup::WarmupView,
+            StrategyError,
+        },
+        strategy_performance, strategy_pnl, usage,
+    },
+    utils::types::{ApiResponse, DisplayQuery},
+};
 
-fn user_id(req: &HttpRequest) -> Result<i64, HttpResponse> {
+pub(crate) fn user_id(req: &HttpRequest) -> Result<i64, HttpResponse> {
     req.extensions()
         .get::<String>()
         .and_then(|s| s.parse::<i64>().ok())
         .ok_or_else(|| HttpResponse::Unauthorized().json(ApiResponse::<()>::err("no user id")))
 }
 
-#[derive(Deserialize, Debug)]
-pub struct StartReq {
-    pub exchange: String,
-    /// Trading pair, e.g., "BTCUSDT"
-    pub symbol: String,
-    /// Name of the strategy ("mean_reversion", "trend_follow", etc)
-    pub strategy: String,
-    /// Params for the strategy (periods, thresholds, etc)
-    pub params: serde_json::Value,
+/// Body of `POST /api/strategies/start`, as clients build it
+pub use rustraptor_types::strategy::StartStrategy as StartReq;
+
+pub(crate) const ALLOWED_FREE_STRATS: &[&str] = &["mean_reversion", "trend_follow", "vcsr"];
+
+/// 422 with one entry per rejected field in `data`; 400 for a strategy that
+/// doesn't exist
+fn rejected_params(e: StrategyError) -> HttpResponse {
+    let msg = e.to_string();
+    match e {
+        StrategyError::Unknown(_) => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg)),
+        StrategyError::Fields(errors) => {
+            HttpResponse::UnprocessableEntity().json(ApiResponse::<Vec<FieldError>> {
+                success: false,
+                message: Some(msg),
+                data: Some(errors),
+                display: None,
+            })
+        }
+        _ => HttpResponse::UnprocessableEntity().json(ApiResponse::<()>::err(&msg)),
+    }
 }
 
-const ALLOWED_FREE_STRATS: &[&str] = &["mean_reversion", "trend_follow", "vcsr"];
+/// GET /api/strategies/schemas/{strategy} → its params as a JSON Schema
+#[get("/schemas/{strategy}")]
+async fn get_schema(path: web::Path<String>) -> impl Responder {
+    match scheduler::param_schema(&path) {
+        Some(schema) => HttpResponse::Ok().json(ApiResponse::ok(schema.json_schema())),
+        None => HttpResponse::NotFound().json(ApiResponse::<()>::err("unknown strategy")),
+    }
+}
 
 /// Generic “launch strategy” endpoint
 #[post("")]
 async fn start_strategy(
     req: HttpRequest,
     db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
     body: web::Json<StartReq>,
 ) -> impl Responder {
     let uid = match user_id(&req) {
         Ok(v) => v,
         Err(e) => return e,
     };
+    if drain::is_draining() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("server is draining – retry shortly"));
+    }
 
     // ─── Tier / plan check ────────────────────────────────────────────────
-    // In v1 we assume every user is on the free plan.
-    let is_free = true;
+    let is_free = usage::plan_for(db.as_ref(), uid).await == usage::Plan::Free;
     if is_free && !ALLOWED_FREE_STRATS.contains(&body.strategy.as_str()) {
         return HttpResponse::Forbidden().json(ApiResponse::<()>::err(
             "upgrade required for custom strategies",
         ));
     }
 
+    if let Err(e) = scheduler::check_params(&body.strategy, &body.params) {
+        return rejected_params(e);
+    }
+    if let Some(Err(e)) = body.auto_stop.map(|a| a.validate()) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e));
+    }
+
     // ─── Insert row ───────────────────────────────────────────────────────
     let row = sqlx::query!(
         r#"
         INSERT INTO user_strategies
-              (user_id, exchange, symbol, strategy, params)
-        VALUES ($1      , $2      , $3    , $4      , $5)
+              (user_id, exchange, symbol, strategy, params, auto_stop)
+        VALUES ($1      , $2      , $3    , $4      , $5    , $6)
         RETURNING strategy_id
         "#,
         uid,
         body.exchange,
         body.symbol,
         body.strategy,
-        body.params
+        body.params,
+        body.auto_stop.map(|a| json!(a))
     )
     .fetch_one(db.as_ref())
     .await;
 
     match row {
-        Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r.strategy_id)),
+        Ok(r) => {
+            usage::invalidate_strategies(cache.get_ref(), uid).await;
+            audit::record(
+                Some(uid),
+                "strategy.start",
+                json!({ "strategy_id": r.strategy_id, "strategy": body.strategy }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(r.strategy_id))
+        }
         Err(e) => {
-            log::error!("start_strategy: DB error: {e}");
+            tracing::error!("start_strategy: DB error: {e}");
             HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
         }
     }
@@ -78,6 +135,7 @@ async fn start_strategy(
 async fn stop_strategy(
     req: HttpRequest,
     db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
     path: web::Path<Uuid>,
 ) -> impl Responder {
     let uid = match user_id(&req) {
@@ -99,9 +157,13 @@ async fn stop_strategy(
     .await;
 
     match result {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::<()>::ok(())),
+        Ok(_) => {
+            usage::invalidate_strategies(cache.get_ref(), uid).await;
+            audit::record(Some(uid), "strategy.stop", json!({ "strategy_id": *path }));
+            HttpResponse::Ok().json(ApiResponse::<()>::ok(()))
+        }
         Err(e) => {
-            log::error!("stop_strategy: DB error: {e}");
+            tracing::error!("stop_strategy: DB error: {e}");
             HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
         }
     }
@@ -138,14 +200,255 @@ async fn list_active(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder
     match rows {
         Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r)),
         Err(e) => {
-            log::error!("list_active: DB error: {e}");
+            tracing::error!("list_active: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+#[derive(Serialize, FromRow)]
+struct StrategyStatus {
+    strategy_id: Uuid,
+    strategy: String,
+    status: String,
+    /// Why the strategy was parked as `invalid` or auto-stopped
+    error: Option<String>,
+    /// Set while an enabled strategy is still short of its warmup history
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warmup: Option<WarmupView>,
+    /// Last bar (or book) its task processed and how often it was
+    /// restarted for stalling
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heartbeat: Option<HeartbeatView>,
+}
+
+/// GET /api/strategies/{id} → status, with the error for `invalid` ones,
+/// warmup progress ("warming up (x/y candles)") for enabled ones and the
+/// task's heartbeat
+#[get("/{id}")]
+async fn get_status(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let row = sqlx::query_as::<_, StrategyStatus>(
+        r#"
+        SELECT strategy_id, strategy, status, status_error AS error
+          FROM user_strategies
+         WHERE strategy_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(*path)
+    .bind(uid)
+    .fetch_optional(db.as_ref())
+    .await;
+
+    match row {
+        Ok(Some(mut s)) => {
+            if s.status == "enabled" {
+                s.warmup = cache
+                    .get_json::<WarmupView>(&scheduler::warmup_key(s.strategy_id))
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("get_status: warmup progress: {e}");
+                        None
+                    });
+            }
+            s.heartbeat = match heartbeat::view(cache.get_ref(), s.strategy_id).await {
+                Ok(h) => Some(h),
+                Err(e) => {
+                    tracing::warn!("get_status: heartbeat: {e}");
+                    None
+                }
+            };
+            HttpResponse::Ok().json(ApiResponse::ok(s))
+        }
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()>::err("strategy not found")),
+        Err(e) => {
+            tracing::error!("get_status: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/strategies/{id}/pnl[?display=true] → realised + unrealised PnL
+/// (cached, cheap to poll)
+#[get("/{id}/pnl")]
+async fn get_pnl(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    path: web::Path<Uuid>,
+    q: web::Query<DisplayQuery>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let owned = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM user_strategies WHERE strategy_id = $1 AND user_id = $2)",
+    )
+    .bind(*path)
+    .bind(uid)
+    .fetch_one(db.as_ref())
+    .await;
+    match owned {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ApiResponse::<()>::err("strategy not found"))
+        }
+        Err(e) => {
+            tracing::error!("get_pnl: DB error: {e}");
+            return HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"));
+        }
+    }
+
+    match strategy_pnl::get(db.as_ref(), cache.as_ref(), *path).await {
+        Ok(Some(pnl)) => {
+            let display = q.meta([pnl.symbol.as_str()]);
+            HttpResponse::Ok().json(ApiResponse::ok(pnl).with_display(display))
+        }
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()>::err("strategy not found")),
+        Err(e) => {
+            tracing::error!("get_pnl: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/strategies/{id}/performance → realised / unrealised PnL, win
+/// rate, trade count and the daily equity curve
+#[get("/{id}/performance")]
+async fn get_performance(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match strategy_performance::get(db.as_ref(), cache.as_ref(), uid, *path).await {
+        Ok(Some(p)) => HttpResponse::Ok().json(ApiResponse::ok(p)),
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()>::err("strategy not found")),
+        Err(e) => {
+            tracing::error!("get_performance: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AllocationReq {
+    /// Virtual capital for this strategy; `null` sizes off the full account
+    pub capital: Option<f64>,
+}
+
+/// PUT /api/strategies/{id}/allocation  { "capital": 2500 }
+#[put("/{id}/allocation")]
+async fn set_allocation(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    body: web::Json<AllocationReq>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    if body.capital.is_some_and(|c| !(c.is_finite() && c > 0.0)) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("capital must be positive"));
+    }
+
+    match allocation::set(db.as_ref(), uid, *path, body.capital).await {
+        Ok(true) => {
+            audit::record(
+                Some(uid),
+                "strategy.allocation",
+                json!({ "strategy_id": *path, "capital": body.capital }),
+            );
+            HttpResponse::Ok().json(ApiResponse::<()>::ok(()))
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::err("strategy not found")),
+        Err(e) => {
+            tracing::error!("set_allocation: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+    }
+}
+
+/// GET /api/strategies/{id}/versions → params versions with their performance
+#[get("/{id}/versions")]
+async fn list_versions(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match params_history::versions(db.as_ref(), uid, *path).await {
+        Ok(Some(v)) => HttpResponse::Ok().json(ApiResponse::ok(v)),
+        Ok(None) => HttpResponse::NotFound().json(ApiResponse::<()>::err("strategy not found")),
+        Err(e) => {
+            tracing::error!("list_versions: DB error: {e}");
             HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
         }
     }
 }
+
+/// POST /api/strategies/{id}/revert/{version}
+#[post("/{id}/revert/{version}")]
+async fn revert_params(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<(Uuid, i32)>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let (strategy_id, version) = path.into_inner();
+
+    match params_history::revert(db.as_ref(), uid, strategy_id, version).await {
+        Ok(params) => {
+            scheduler::respawn(strategy_id);
+            audit::record(
+                Some(uid),
+                "strategy.revert",
+                json!({ "strategy_id": strategy_id, "version": version }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(params))
+        }
+        Err(ParamsHistoryError::Db(e)) => {
+            tracing::error!("revert_params: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        Err(e) => HttpResponse::NotFound().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
 pub fn strategy_scope() -> Scope {
     web::scope("/api/strategies")
         .service(start_strategy)
         .service(stop_strategy)
         .service(list_active)
+        .service(get_schema)
+        .service(get_status)
+        .service(get_pnl)
+        .service(get_performance)
+        .service(set_allocation)
+        .service(list_versions)
+        .service(revert_params)
 }
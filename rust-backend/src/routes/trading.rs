@@ -1,39 +1,40 @@
 // src/routes/trading.rs
 
 use crate::config::settings::Settings;
-use crate::middleware::path_logger::PathLogger;
 use crate::services::blowfin::api::get_balance;
+use crate::services::blowfin::dto::{Balance, BlowFinResponse};
+use crate::services::drain;
+use crate::services::event_bus::{EventBus, Topic};
+use crate::services::exchanges;
 use crate::services::trading_engine::{execute_trade, Exchange, TradeRequest, TradeResponse};
 use crate::utils::types::ApiResponse;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::{get, post, web, HttpMessage, HttpResponse, Responder};
-use serde::Deserialize;
-use serde_json::Value;
 
-#[derive(Debug, Deserialize)]
-pub struct TradeParams {
-    pub exchange: String,
-    pub symbol: String,
-    pub side: String,
-    pub order_type: String,
-    pub price: Option<f64>,
-    pub size: f64,
-}
+/// Body of `POST /api/trade`, as clients build it
+pub use rustraptor_types::trade::TradeRequest as TradeParams;
 
 #[post("/trade")]
 pub async fn trade(
     params: web::Json<TradeParams>,
     settings: web::Data<Settings>,
     db: web::Data<sqlx::PgPool>,
+    events: Option<web::Data<EventBus>>,
     req: actix_web::HttpRequest,
 ) -> impl Responder {
-    let exchange = match params.exchange.to_lowercase().as_str() {
-        "blowfin" => Exchange::Blowfin,
+    if drain::is_draining() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("server is draining – retry shortly"));
+    }
+
+    let exchange = match params.exchange.parse::<Exchange>() {
+        Ok(e) if exchanges::spec(e).is_some() => e,
         _ => {
             return HttpResponse::BadRequest().json(ApiResponse::<()> {
                 success: false,
                 message: Some("Unsupported exchange".to_string()),
                 data: None,
+                display: None,
             })
         }
     };
@@ -59,18 +60,33 @@ pub async fn trade(
         order_type: params.order_type.clone(),
         price: params.price,
         size: params.size,
+        reduce_only: params.reduce_only,
+        signal_price: params.signal_price.or(params.price),
+        tp_sl: params.tp_sl,
     };
 
     match execute_trade(req_struct, db.as_ref(), user_id, is_demo, master_key_bytes).await {
-        Ok(resp) => HttpResponse::Ok().json(ApiResponse::<TradeResponse> {
-            success: true,
-            message: Some("Trade executed successfully".to_string()),
-            data: Some(resp),
-        }),
+        Ok(resp) => {
+            // durable fan-out (copy trading, analytics consumers); absent
+            // when running without Redis
+            if let Some(events) = events {
+                let evt = serde_json::json!({ "user_id": user_id, "fill": &resp });
+                if let Err(e) = events.publish(Topic::Fills, &evt).await {
+                    tracing::warn!("publish fill event: {e}");
+                }
+            }
+            HttpResponse::Ok().json(ApiResponse::<TradeResponse> {
+                success: true,
+                message: Some("Trade executed successfully".to_string()),
+                data: Some(resp),
+                display: None,
+            })
+        }
         Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()> {
             success: false,
             message: Some(format!("Trade error: {}", e)),
             data: None,
+            display: None,
         }),
     }
 }
@@ -91,16 +107,21 @@ pub async fn balance(
     let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
     let master_key_bytes = master_key.as_bytes();
 
-    match get_balance(db.as_ref(), user_id, is_demo, master_key_bytes).await {
-        Ok(resp) => HttpResponse::Ok().json(ApiResponse::<Value> {
+    let balance = get_balance(db.as_ref(), user_id, is_demo, master_key_bytes)
+        .await
+        .and_then(BlowFinResponse::into_data);
+    match balance {
+        Ok(balance) => HttpResponse::Ok().json(ApiResponse::<Balance> {
             success: true,
             message: Some("Balance fetched successfully".to_string()),
-            data: Some(resp.data),
+            data: Some(balance),
+            display: None,
         }),
         Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()> {
             success: false,
             message: Some(format!("Balance error: {}", e)),
             data: None,
+            display: None,
         }),
     }
 }
@@ -110,13 +131,6 @@ pub async fn test_trade_api() -> impl Responder {
     HttpResponse::Ok().body("Trading scope is active.")
 }
 
-#[get("/routes")]
-pub async fn list_routes() -> impl Responder {
-    let routes = vec!["/health", "/api/trade", "/api/balance", "/api/test"];
-
-    HttpResponse::Ok().json(routes)
-}
-
 #[get("/simple")]
 pub async fn simple_test() -> impl Responder {
     HttpResponse::Ok().body("Simple test route")
@@ -124,10 +138,8 @@ pub async fn simple_test() -> impl Responder {
 
 pub fn trading_scope() -> impl HttpServiceFactory {
     web::scope("/api")
-        .wrap(PathLogger)
         .service(simple_test)
         .service(test_trade_api)
         .service(balance)
         .service(trade)
-        .service(list_routes)
 }
@@ -0,0 +1,32 @@
+// src/routes/usage.rs
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, Scope};
+use sqlx::PgPool;
+
+use crate::{
+    db::cache::Cache, routes::strategies::user_id, services::usage, utils::types::ApiResponse,
+};
+
+/// GET /api/usage – consumption vs. plan limits (for quota bars)
+#[get("")]
+async fn get_usage(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match usage::usage_report(db.as_ref(), cache.get_ref(), uid).await {
+        Ok(r) => HttpResponse::Ok().json(ApiResponse::ok(r)),
+        Err(e) => {
+            tracing::error!("usage report: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("usage unavailable"))
+        }
+    }
+}
+
+pub fn usage_scope() -> Scope {
+    web::scope("/api/usage").service(get_usage)
+}
@@ -0,0 +1,84 @@
+// src/routes/watchlist.rs
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    routes::strategies::user_id,
+    services::watchlist::{self, WatchlistError},
+    utils::types::ApiResponse,
+};
+
+fn watchlist_error(ctx: &str, e: WatchlistError) -> HttpResponse {
+    match e {
+        WatchlistError::Db(e) => {
+            tracing::error!("{ctx}: DB error: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err("db error"))
+        }
+        WatchlistError::Full => {
+            HttpResponse::Conflict().json(ApiResponse::<()>::err(&e.to_string()))
+        }
+        e => HttpResponse::BadRequest().json(ApiResponse::<()>::err(&e.to_string())),
+    }
+}
+
+/// GET /api/watchlist → symbols with their latest mid
+#[get("")]
+async fn list(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match watchlist::list(db.as_ref(), uid).await {
+        Ok(items) => HttpResponse::Ok().json(ApiResponse::ok(items)),
+        Err(e) => watchlist_error("list watchlist", e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AddReq {
+    pub symbol: String,
+}
+
+/// POST /api/watchlist  { "symbol": "BTC-USDT" }
+#[post("")]
+async fn add(req: HttpRequest, db: web::Data<PgPool>, body: web::Json<AddReq>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match watchlist::add(db.as_ref(), uid, &body.symbol).await {
+        Ok(true) => HttpResponse::Created().json(ApiResponse::ok(json!({ "added": true }))),
+        Ok(false) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "added": false }))),
+        Err(e) => watchlist_error("add to watchlist", e),
+    }
+}
+
+/// DELETE /api/watchlist/{symbol}
+#[delete("/{symbol}")]
+async fn remove(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    match watchlist::remove(db.as_ref(), uid, &path.into_inner()).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "removed": true }))),
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::err("not on watchlist")),
+        Err(e) => watchlist_error("remove from watchlist", e),
+    }
+}
+
+pub fn watchlist_scope() -> Scope {
+    web::scope("/api/watchlist")
+        .service(list)
+        .service(add)
+        .service(remove)
+}
@@ -0,0 +1,193 @@
+// src/routes/webhooks.rs
+//! TradingView alerts (`services::webhooks`): the public alert endpoint,
+//! authenticated by the token in its body, and the caller's token management.
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, Scope};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::settings::Settings,
+    db::cache::Cache,
+    routes::strategies::user_id,
+    services::{
+        audit, drain,
+        event_bus::{EventBus, Topic},
+        positions::CloseError,
+        webhooks::{self, Alert, WebhookError},
+    },
+    utils::{errors::TradeError, types::ApiResponse},
+};
+
+fn webhook_error(e: WebhookError) -> HttpResponse {
+    let msg = e.to_string();
+    match e {
+        WebhookError::BadToken => HttpResponse::Unauthorized().json(ApiResponse::<()>::err(&msg)),
+        WebhookError::Invalid(_)
+        | WebhookError::Close(CloseError::Invalid(_))
+        | WebhookError::Trade(TradeError::InvalidRequest(_)) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::err(&msg))
+        }
+        WebhookError::TooMany => HttpResponse::Conflict().json(ApiResponse::<()>::err(&msg)),
+        WebhookError::NotFound | WebhookError::Close(CloseError::NoPosition(_)) => {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err(&msg))
+        }
+        WebhookError::Trade(TradeError::RiskViolation(_)) => {
+            HttpResponse::UnprocessableEntity().json(ApiResponse::<()>::err(&msg))
+        }
+        e => {
+            tracing::error!("webhooks: {e}");
+            HttpResponse::InternalServerError().json(ApiResponse::<()>::err(&msg))
+        }
+    }
+}
+
+/// POST /api/webhooks/tradingview – the alert message is the body; it is
+/// parsed whatever content type TradingView sent it with
+#[post("/tradingview")]
+async fn tradingview(
+    settings: web::Data<Settings>,
+    db: web::Data<PgPool>,
+    cache: web::Data<dyn Cache>,
+    events: Option<web::Data<EventBus>>,
+    body: web::Bytes,
+) -> impl Responder {
+    let alert: Alert = match serde_json::from_slice(&body) {
+        Ok(a) => a,
+        Err(e) => {
+            return HttpResponse::BadRequest()
+                .json(ApiResponse::<()>::err(&format!("bad alert: {e}")))
+        }
+    };
+    let caller = match webhooks::resolve(db.as_ref(), &alert.token).await {
+        Ok(c) => c,
+        Err(e) => return webhook_error(e),
+    };
+    if drain::is_draining() {
+        return HttpResponse::ServiceUnavailable()
+            .json(ApiResponse::<()>::err("server is draining – retry shortly"));
+    }
+
+    let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
+    let done = webhooks::execute(
+        db.as_ref(),
+        cache.get_ref(),
+        caller.user_id,
+        &alert,
+        settings.is_demo(),
+        master_key.as_bytes(),
+    )
+    .await;
+    let details = json!({
+        "token_id": caller.token_id,
+        "symbol": alert.symbol,
+        "side": alert.side,
+        "size": alert.size,
+        "risk_pct": alert.risk_pct,
+        "stop": alert.stop,
+        "price": alert.price,
+    });
+    match done {
+        Ok(executed) => {
+            audit::record(
+                Some(caller.user_id),
+                "webhook.tradingview",
+                json!({ "alert": details, "order_id": executed.fill.order_id, "filled": executed.fill.size }),
+            );
+            // same fan-out as `/api/trade`, so followers copy it
+            if let Some(events) = events {
+                let evt = json!({ "user_id": caller.user_id, "fill": &executed.fill });
+                if let Err(e) = events.publish(Topic::Fills, &evt).await {
+                    tracing::warn!("publish fill event: {e}");
+                }
+            }
+            HttpResponse::Ok().json(ApiResponse::ok(executed))
+        }
+        Err(e) => {
+            audit::record(
+                Some(caller.user_id),
+                "webhook.tradingview_rejected",
+                json!({ "alert": details, "error": e.to_string() }),
+            );
+            webhook_error(e)
+        }
+    }
+}
+
+/// GET /api/webhooks/tokens
+#[get("/tokens")]
+async fn list_tokens(req: HttpRequest, db: web::Data<PgPool>) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match webhooks::list(db.as_ref(), uid).await {
+        Ok(tokens) => HttpResponse::Ok().json(ApiResponse::ok(tokens)),
+        Err(e) => webhook_error(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewToken {
+    #[serde(default)]
+    label: Option<String>,
+}
+
+/// POST /api/webhooks/tokens `{label?}` – the token is only ever shown here
+#[post("/tokens")]
+async fn create_token(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    body: web::Json<NewToken>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match webhooks::issue(db.as_ref(), uid, body.into_inner().label).await {
+        Ok(issued) => {
+            audit::record(
+                Some(uid),
+                "webhook.token_create",
+                json!({ "token_id": issued.info.token_id, "label": issued.info.label }),
+            );
+            HttpResponse::Created().json(ApiResponse::ok(issued))
+        }
+        Err(e) => webhook_error(e),
+    }
+}
+
+/// DELETE /api/webhooks/tokens/{token_id}
+#[delete("/tokens/{token_id}")]
+async fn revoke_token(
+    req: HttpRequest,
+    db: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let uid = match user_id(&req) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let token_id = path.into_inner();
+    match webhooks::revoke(db.as_ref(), uid, token_id).await {
+        Ok(true) => {
+            audit::record(
+                Some(uid),
+                "webhook.token_revoke",
+                json!({ "token_id": token_id }),
+            );
+            HttpResponse::Ok().json(ApiResponse::ok(json!({ "revoked": token_id })))
+        }
+        Ok(false) => webhook_error(WebhookError::NotFound),
+        Err(e) => webhook_error(e.into()),
+    }
+}
+
+pub fn webhooks_scope() -> Scope {
+    web::scope("/api/webhooks")
+        .service(tradingview)
+        .service(list_tokens)
+        .service(create_token)
+        .service(revoke_token)
+}
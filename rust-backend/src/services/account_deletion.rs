@@ -0,0 +1,268 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Account deletion & data purge
+//! ──────────────────────────────────────────────────────────────────────────
+//! * [`schedule`] (`DELETE /api/account`) takes effect at once: strategies
+//!   are disabled, copy relations on either side ended, exchange API keys
+//!   and sign-in identities deleted – the account can no longer sign in
+//! * Orders, fills, PnL, billing and audit rows are kept for
+//!   `ACCOUNT_RETENTION_DAYS` (disputes, tax questions), then [`purge_due`]
+//!   moves them onto a fresh anonymous user (negative id, `deleted-…`
+//!   username), drops the remaining personal rows and the `users` row
+//! * Platform aggregates stay correct; nothing left points back at the
+//!   Discord id
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeMap;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::{api_keys, cache::Cache};
+use crate::services::{audit, auth_tokens, copy_trading, identities, scheduler, usage};
+
+/// How often the purge job looks for due accounts
+const PURGE_EVERY: StdDuration = StdDuration::from_secs(3_600);
+/// Accounts purged per pass – the rest wait for the next one
+const PURGE_BATCH: i64 = 100;
+
+/// Personal configuration with no history value – deleted, not anonymised
+const PERSONAL: &[&str] = &[
+    "api_keys",
+    "exchange_accts",
+    "user_identities",
+    "auth_refresh_tokens",
+    "user_watchlist",
+    "arbitrage_settings",
+    "alerts",
+    "referral_codes",
+    "user_fee_tiers",
+    "leader_profiles",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum DeletionError {
+    #[error("account deletion already scheduled")]
+    AlreadyScheduled,
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Scheduled {
+    pub purge_after: DateTime<Utc>,
+    pub strategies_stopped: Vec<Uuid>,
+    pub copy_relations_ended: usize,
+    pub api_keys_deleted: u64,
+}
+
+/// Placeholder id the history moves to; snowflakes are positive
+pub fn anonymous_id() -> i64 {
+    -1 - (rand::random::<u64>() >> 1) as i64
+}
+
+/// Fits `users.rr_username` (32 chars)
+pub fn anonymous_username(anon_id: i64) -> String {
+    format!("deleted-{:016x}", anon_id as u64)
+}
+
+/// Stop the account now; anonymise its history after `retention`
+pub async fn schedule(
+    db: &PgPool,
+    cache: &dyn Cache,
+    user_id: i64,
+    retention: Duration,
+) -> Result<Scheduled, DeletionError> {
+    let purge_after = Utc::now() + retention;
+    let mut tx = db.begin().await?;
+
+    let queued = sqlx::query(
+        "INSERT INTO account_deletions (user_id, purge_after) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(purge_after)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if queued == 0 {
+        return Err(DeletionError::AlreadyScheduled);
+    }
+    sqlx::query("UPDATE users SET deleted_at = now() WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let strategies_stopped: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        UPDATE user_strategies
+           SET status = 'disabled', status_error = 'account deleted'
+         WHERE user_id = $1 AND status = 'enabled'
+        RETURNING strategy_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    // TradingView alerts stop trading for it straight away
+    sqlx::query(
+        "UPDATE webhook_tokens SET revoked_at = now() WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    // its PnL and exposure stop leaving for the user's own endpoints now,
+    // not at purge when the rows cascade away
+    sqlx::query("UPDATE user_metric_sinks SET enabled = false WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    // the arbitrage monitor neither trades nor alerts for it any more
+    sqlx::query("UPDATE arbitrage_settings SET enabled = false WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let key_exchanges: Vec<String> =
+        sqlx::query_scalar("DELETE FROM api_keys WHERE user_id = $1 RETURNING exchange")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    // no more sign-ins; the legacy snowflake fallback checks account_deletions
+    sqlx::query("DELETE FROM user_identities WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let relations: Vec<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT leader_user_id, follower_user_id
+          FROM copy_relations
+         WHERE (leader_user_id = $1 OR follower_user_id = $1) AND status IN ('active', 'trial')
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    identities::forget_all();
+    // already committed – refreshes fail anyway once the identities are gone
+    if let Err(e) = auth_tokens::revoke_all(db, user_id).await {
+        tracing::error!("account deletion {user_id}: revoking refresh tokens: {e}");
+    }
+    // running strategies must not keep trading on the cached credentials
+    for exchange in &key_exchanges {
+        api_keys::forget_creds(user_id, exchange);
+    }
+
+    // running tasks stop here; other instances reap them on their next reconcile
+    for id in &strategies_stopped {
+        scheduler::respawn(*id);
+    }
+    usage::invalidate_strategies(cache, user_id).await;
+    // already committed – a failure here leaves one relation to end by hand
+    for &(leader, follower) in &relations {
+        if let Err(e) = copy_trading::remove_follower(db, cache, leader, follower).await {
+            tracing::error!("account deletion {user_id}: ending copy {leader}→{follower}: {e}");
+        }
+    }
+
+    let done = Scheduled {
+        purge_after,
+        strategies_stopped,
+        copy_relations_ended: relations.len(),
+        api_keys_deleted: key_exchanges.len() as u64,
+    };
+    audit::record(Some(user_id), "account.delete", json!(done));
+    Ok(done)
+}
+
+/// Anonymise one scheduled account now; returns the placeholder id
+pub async fn purge(db: &PgPool, user_id: i64) -> Result<i64, sqlx::Error> {
+    let anon = anonymous_id();
+    let mut tx = db.begin().await?;
+    sqlx::query(
+        "INSERT INTO users (user_id, rr_username, email, deleted_at) VALUES ($1, $2, NULL, now())",
+    )
+    .bind(anon)
+    .bind(anonymous_username(anon))
+    .execute(&mut *tx)
+    .await?;
+    for table in PERSONAL {
+        sqlx::query(&format!("DELETE FROM {table} WHERE user_id = $1"))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    let mut moved = BTreeMap::new();
+    identities::rekey_all(&mut tx, user_id, anon, &mut moved).await?;
+    sqlx::query("DELETE FROM users WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM account_deletions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    // keyed by the placeholder: the trail must not name the person either
+    audit::record(Some(anon), "account.purge", json!({ "moved": moved }));
+    Ok(anon)
+}
+
+/// Purge every account past its retention window; returns how many
+pub async fn purge_due(db: &PgPool, now: DateTime<Utc>) -> Result<usize, sqlx::Error> {
+    let due: Vec<i64> = sqlx::query_scalar(
+        "SELECT user_id FROM account_deletions WHERE purge_after <= $1 ORDER BY purge_after LIMIT $2",
+    )
+    .bind(now)
+    .bind(PURGE_BATCH)
+    .fetch_all(db)
+    .await?;
+    let mut purged = 0;
+    for user_id in due {
+        // one bad account must not hold up the rest
+        match purge(db, user_id).await {
+            Ok(_) => purged += 1,
+            Err(e) => tracing::error!("account purge: {user_id}: {e}"),
+        }
+    }
+    Ok(purged)
+}
+
+pub fn spawn(db: PgPool) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(PURGE_EVERY);
+        loop {
+            tick.tick().await;
+            match purge_due(&db, Utc::now()).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("account purge: {n} accounts anonymised"),
+                Err(e) => tracing::error!("account purge: DB error: {e}"),
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_never_collide_with_snowflakes() {
+        for _ in 0..1_000 {
+            let id = anonymous_id();
+            assert!(id < 0);
+            let name = anonymous_username(id);
+            assert!(name.len() <= 32, "{name}");
+            assert!(name.starts_with("deleted-"));
+        }
+        assert_eq!(anonymous_username(-1), "deleted-ffffffffffffffff");
+    }
+}
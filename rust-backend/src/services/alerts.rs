@@ -0,0 +1,526 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Price alerts & alert-to-trade automations
+//! ──────────────────────────────────────────────────────────────────────────
+//! An alert watches the live mid of one symbol (`above` / `below` a
+//! threshold) and fires **once**. On firing it may run an action:
+//!
+//! * `none`           – just record the fire
+//! * `trade`          – submit a predefined order on the alert's symbol;
+//!   goes through the normal risk path (draw-down for entries, slippage &
+//!   liquidity in the engine)
+//! * `start_strategy` / `stop_strategy` – flip one of the user's strategies;
+//!   the scheduler picks the change up on its next reconcile
+//!
+//! Firing is claimed with a conditional UPDATE (`active → firing`) so
+//! several instances never execute the same alert twice. A crash mid-fire
+//! leaves the row in `firing` rather than risk a second order.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use metrics::increment_counter;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    db::cache::SharedCache,
+    routes::strategies::ALLOWED_FREE_STRATS,
+    services::{
+        analytics, audit, drain, risk,
+        trading_engine::{execute_trade, Exchange, TradeRequest},
+        usage, watchlist,
+    },
+};
+
+pub const MAX_ACTIVE_ALERTS: i64 = 25;
+const EVAL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Condition {
+    Above,
+    Below,
+}
+
+impl Condition {
+    fn as_str(self) -> &'static str {
+        match self {
+            Condition::Above => "above",
+            Condition::Below => "below",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "above" => Some(Condition::Above),
+            "below" => Some(Condition::Below),
+            _ => None,
+        }
+    }
+
+    pub fn hit(self, price: f64, threshold: f64) -> bool {
+        match self {
+            Condition::Above => price >= threshold,
+            Condition::Below => price <= threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertAction {
+    #[default]
+    None,
+    Trade {
+        side: String,
+        #[serde(default = "market")]
+        order_type: String,
+        size: f64,
+        #[serde(default)]
+        price: Option<f64>,
+        #[serde(default)]
+        reduce_only: bool,
+    },
+    StartStrategy {
+        strategy_id: Uuid,
+    },
+    StopStrategy {
+        strategy_id: Uuid,
+    },
+}
+
+fn market() -> String {
+    "market".into()
+}
+
+impl AlertAction {
+    fn kind(&self) -> &'static str {
+        match self {
+            AlertAction::None => "none",
+            AlertAction::Trade { .. } => "trade",
+            AlertAction::StartStrategy { .. } => "start_strategy",
+            AlertAction::StopStrategy { .. } => "stop_strategy",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), AlertError> {
+        let AlertAction::Trade {
+            side,
+            order_type,
+            size,
+            price,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+        let invalid = |m: &str| Err(AlertError::Invalid(m.into()));
+        if !matches!(side.as_str(), "buy" | "sell") {
+            return invalid("side must be buy or sell");
+        }
+        if !(size.is_finite() && *size > 0.0) {
+            return invalid("size must be positive");
+        }
+        match (order_type.as_str(), price) {
+            ("market", _) => Ok(()),
+            ("limit", Some(p)) if p.is_finite() && *p > 0.0 => Ok(()),
+            ("limit", _) => invalid("limit orders need a positive price"),
+            _ => invalid("order_type must be market or limit"),
+        }
+    }
+
+    /// The order a `trade` action submits when fired at `price`
+    pub fn trade_request(&self, symbol: &str, price: f64) -> Option<TradeRequest> {
+        let AlertAction::Trade {
+            side,
+            order_type,
+            size,
+            price: limit,
+            reduce_only,
+        } = self
+        else {
+            return None;
+        };
+        Some(TradeRequest {
+            exchange: Exchange::Blowfin,
+            symbol: symbol.to_string(),
+            side: side.clone(),
+            order_type: order_type.clone(),
+            price: *limit,
+            size: *size,
+            reduce_only: *reduce_only,
+            signal_price: Some(price),
+            tp_sl: None,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AlertError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("too many active alerts (max {MAX_ACTIVE_ALERTS})")]
+    TooMany,
+    #[error("strategy not found")]
+    StrategyNotFound,
+    #[error("upgrade required for custom strategies")]
+    PlanRequired,
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewAlert {
+    pub symbol: String,
+    pub condition: Condition,
+    pub threshold: f64,
+    #[serde(default)]
+    pub action: AlertAction,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Alert {
+    pub alert_id: Uuid,
+    pub user_id: i64,
+    pub symbol: String,
+    pub condition: String,
+    pub threshold: f64,
+    pub action: Value,
+    pub status: String,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub fired_price: Option<f64>,
+    pub result: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+const ALERT_COLUMNS: &str =
+    "alert_id, user_id, symbol, condition, threshold::float8 AS threshold, \
+     action, status, fired_at, fired_price::float8 AS fired_price, result, created_at";
+
+pub async fn create(
+    db: &PgPool,
+    user_id: i64,
+    new: &NewAlert,
+    free_plan: bool,
+) -> Result<Alert, AlertError> {
+    let symbol =
+        watchlist::normalize(&new.symbol).map_err(|e| AlertError::Invalid(e.to_string()))?;
+    if !(new.threshold.is_finite() && new.threshold > 0.0) {
+        return Err(AlertError::Invalid("threshold must be positive".into()));
+    }
+    new.action.validate()?;
+
+    if let AlertAction::StartStrategy { strategy_id } | AlertAction::StopStrategy { strategy_id } =
+        &new.action
+    {
+        let kind: Option<String> = sqlx::query_scalar(
+            "SELECT strategy FROM user_strategies WHERE strategy_id = $1 AND user_id = $2",
+        )
+        .bind(strategy_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+        let kind = kind.ok_or(AlertError::StrategyNotFound)?;
+        if free_plan
+            && matches!(new.action, AlertAction::StartStrategy { .. })
+            && !ALLOWED_FREE_STRATS.contains(&kind.as_str())
+        {
+            return Err(AlertError::PlanRequired);
+        }
+    }
+
+    let active: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM alerts WHERE user_id = $1 AND status = 'active'")
+            .bind(user_id)
+            .fetch_one(db)
+            .await?;
+    if active >= MAX_ACTIVE_ALERTS {
+        return Err(AlertError::TooMany);
+    }
+
+    let alert = sqlx::query_as::<_, Alert>(&format!(
+        r#"
+        INSERT INTO alerts (user_id, symbol, condition, threshold, action)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {ALERT_COLUMNS}
+        "#
+    ))
+    .bind(user_id)
+    .bind(&symbol)
+    .bind(new.condition.as_str())
+    .bind(new.threshold)
+    .bind(json!(new.action))
+    .fetch_one(db)
+    .await?;
+    Ok(alert)
+}
+
+/// Newest first, last 100
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<Alert>, sqlx::Error> {
+    sqlx::query_as::<_, Alert>(&format!(
+        "SELECT {ALERT_COLUMNS} FROM alerts WHERE user_id = $1 ORDER BY created_at DESC LIMIT 100"
+    ))
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+/// `Ok(false)` unless an active alert was cancelled
+pub async fn cancel(db: &PgPool, user_id: i64, alert_id: Uuid) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query(
+        "UPDATE alerts SET status = 'cancelled' WHERE alert_id = $1 AND user_id = $2 AND status = 'active'",
+    )
+    .bind(alert_id)
+    .bind(user_id)
+    .execute(db)
+    .await?
+    .rows_affected()
+        == 1)
+}
+
+// ─── Evaluator ────────────────────────────────────────────────────────────
+#[derive(FromRow)]
+struct ActiveAlert {
+    alert_id: Uuid,
+    user_id: i64,
+    symbol: String,
+    condition: String,
+    threshold: f64,
+    action: Value,
+}
+
+pub struct Automations {
+    pub pg: PgPool,
+    pub cache: SharedCache,
+    pub is_demo: bool,
+    pub master_key: Vec<u8>,
+}
+
+impl Automations {
+    /// Run a fired alert's action; `Ok` carries the stored result
+    async fn run(
+        &self,
+        user_id: i64,
+        symbol: &str,
+        action: &AlertAction,
+        price: f64,
+    ) -> Result<Value, String> {
+        match action {
+            AlertAction::None => Ok(json!({ "action": "none" })),
+            AlertAction::Trade { reduce_only, .. } => {
+                let req = action.trade_request(symbol, price).expect("trade action");
+                if !reduce_only {
+                    risk::check_drawdown(self.cache.as_ref(), user_id)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                let resp = execute_trade(req, &self.pg, user_id, self.is_demo, &self.master_key)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if !resp.success {
+                    return Err(format!("order rejected: {}", resp.data));
+                }
+                Ok(json!({ "action": "trade", "order": resp.data }))
+            }
+            AlertAction::StartStrategy { strategy_id }
+            | AlertAction::StopStrategy { strategy_id } => {
+                let start = matches!(action, AlertAction::StartStrategy { .. });
+                let updated = sqlx::query(
+                    "UPDATE user_strategies SET status = $1 WHERE strategy_id = $2 AND user_id = $3",
+                )
+                .bind(if start { "enabled" } else { "disabled" })
+                .bind(strategy_id)
+                .bind(user_id)
+                .execute(&self.pg)
+                .await
+                .map_err(|e| e.to_string())?
+                .rows_affected();
+                if updated == 0 {
+                    return Err("strategy no longer exists".into());
+                }
+                usage::invalidate_strategies(self.cache.as_ref(), user_id).await;
+                Ok(json!({ "action": action.kind(), "strategy_id": strategy_id }))
+            }
+        }
+    }
+
+    async fn fire(&self, alert: ActiveAlert, action: AlertAction, price: f64) {
+        let outcome = self.run(alert.user_id, &alert.symbol, &action, price).await;
+        let (status, result) = match &outcome {
+            Ok(v) => ("fired", v.clone()),
+            Err(e) => ("failed", json!({ "error": e })),
+        };
+        increment_counter!("alerts_fired_total", "action" => action.kind(), "status" => status);
+        audit::record(
+            Some(alert.user_id),
+            "alert.fired",
+            json!({ "alert_id": alert.alert_id, "price": price, "status": status }),
+        );
+        if let Err(e) =
+            sqlx::query("UPDATE alerts SET status = $1, result = $2 WHERE alert_id = $3")
+                .bind(status)
+                .bind(&result)
+                .bind(alert.alert_id)
+                .execute(&self.pg)
+                .await
+        {
+            tracing::error!("alert {}: store result: {e}", alert.alert_id);
+        }
+    }
+
+    async fn tick(&'static self) -> Result<(), sqlx::Error> {
+        let alerts = sqlx::query_as::<_, ActiveAlert>(
+            r#"
+            SELECT alert_id, user_id, symbol, condition, threshold::float8 AS threshold, action
+              FROM alerts
+             WHERE status = 'active'
+            "#,
+        )
+        .fetch_all(&self.pg)
+        .await?;
+
+        for alert in alerts {
+            let Some(price) = analytics::mid_for(&alert.symbol) else {
+                continue;
+            };
+            let Some(cond) = Condition::parse(&alert.condition) else {
+                continue;
+            };
+            if !cond.hit(price, alert.threshold) {
+                continue;
+            }
+            let action = match serde_json::from_value::<AlertAction>(alert.action.clone()) {
+                Ok(a) => a,
+                Err(e) => {
+                    tracing::warn!("alert {}: bad action: {e}", alert.alert_id);
+                    AlertAction::None
+                }
+            };
+
+            // claim: only one instance moves it out of `active`
+            let claimed = sqlx::query(
+                r#"
+                UPDATE alerts
+                   SET status = 'firing', fired_at = now(), fired_price = $2
+                 WHERE alert_id = $1 AND status = 'active'
+                "#,
+            )
+            .bind(alert.alert_id)
+            .bind(price)
+            .execute(&self.pg)
+            .await?
+            .rows_affected()
+                == 1;
+            if claimed {
+                let in_flight = drain::track();
+                tokio::spawn(async move {
+                    let _in_flight = in_flight;
+                    self.fire(alert, action, price).await;
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Poll active alerts against live mids every `EVAL_INTERVAL`
+pub fn spawn_evaluator(automations: Automations) {
+    let automations: &'static Automations = Box::leak(Box::new(automations));
+    tokio::spawn(async move {
+        let mut iv = tokio::time::interval(EVAL_INTERVAL);
+        loop {
+            iv.tick().await;
+            // draining: alerts stay active and fire on the next instance
+            if drain::is_draining() {
+                continue;
+            }
+            if let Err(e) = automations.tick().await {
+                tracing::warn!("alert evaluator: {e}");
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_include_the_threshold() {
+        assert!(Condition::Above.hit(100.0, 100.0));
+        assert!(Condition::Above.hit(101.0, 100.0));
+        assert!(!Condition::Above.hit(99.9, 100.0));
+        assert!(Condition::Below.hit(100.0, 100.0));
+        assert!(!Condition::Below.hit(100.1, 100.0));
+        assert_eq!(Condition::parse("below"), Some(Condition::Below));
+        assert_eq!(Condition::parse("sideways"), None);
+    }
+
+    #[test]
+    fn actions_round_trip_through_json() {
+        let a: AlertAction =
+            serde_json::from_value(json!({ "kind": "trade", "side": "buy", "size": 0.5 })).unwrap();
+        assert_eq!(
+            a,
+            AlertAction::Trade {
+                side: "buy".into(),
+                order_type: "market".into(),
+                size: 0.5,
+                price: None,
+                reduce_only: false,
+            }
+        );
+        let back: AlertAction = serde_json::from_value(json!(a)).unwrap();
+        assert_eq!(back, a);
+
+        let none: AlertAction = serde_json::from_value(json!({ "kind": "none" })).unwrap();
+        assert_eq!(none, AlertAction::default());
+        assert!(serde_json::from_value::<AlertAction>(json!({ "kind": "rm_rf" })).is_err());
+    }
+
+    #[test]
+    fn trade_actions_are_validated() {
+        let trade =
+            |side: &str, order_type: &str, size: f64, price: Option<f64>| AlertAction::Trade {
+                side: side.into(),
+                order_type: order_type.into(),
+                size,
+                price,
+                reduce_only: false,
+            };
+        assert!(trade("buy", "market", 1.0, None).validate().is_ok());
+        assert!(trade("sell", "limit", 1.0, Some(99.0)).validate().is_ok());
+        assert!(trade("long", "market", 1.0, None).validate().is_err());
+        assert!(trade("buy", "market", 0.0, None).validate().is_err());
+        assert!(trade("buy", "limit", 1.0, None).validate().is_err());
+        assert!(trade("buy", "stop", 1.0, None).validate().is_err());
+        assert!(AlertAction::StopStrategy {
+            strategy_id: Uuid::nil()
+        }
+        .validate()
+        .is_ok());
+    }
+
+    #[test]
+    fn trade_request_uses_alert_symbol_and_fire_price() {
+        let a = AlertAction::Trade {
+            side: "sell".into(),
+            order_type: "market".into(),
+            size: 2.0,
+            price: None,
+            reduce_only: true,
+        };
+        let req = a.trade_request("ETH-USDT", 3_100.0).unwrap();
+        assert_eq!(req.symbol, "ETH-USDT");
+        assert_eq!(req.size, 2.0);
+        assert!(req.reduce_only);
+        assert_eq!(req.signal_price, Some(3_100.0));
+        assert!(AlertAction::None.trade_request("ETH-USDT", 1.0).is_none());
+    }
+}
@@ -0,0 +1,471 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Strategy capital allocation (virtual sub-accounts)
+//! ──────────────────────────────────────────────────────────────────────────
+//! A strategy with `allocated_capital` trades as if it owned only that
+//! capital plus its own realised PnL:
+//!
+//! * `equity = allocated_capital + Σ strategy_pnl`
+//! * fixed-qty strategies (`Sizing::ScaleQty`) scale their configured qty
+//!   by `equity / allocated_capital`; risk-sized ones read `equity()`
+//! * an exhausted allocation (`equity <= 0`) refuses new entries
+//!
+//! Every fill routed through [`execute`] is netted into the strategy's own
+//! position (`strategy_positions`); reducing fills realise PnL into the
+//! `strategy_pnl` ledger, after which the strategy's `auto_stop` guard is
+//! re-evaluated. Fill prices are the submitted limit, else the book
+//! mid at submit, else the signal price – estimates until fills reconcile.
+//! Each fill's fee (the user's maker/taker tier, see `services::fees`) is
+//! booked against the strategy too, so its PnL is net.
+//! Strategies without an allocation size exactly as before.
+//! Entries of a strategy with `max_funding_bps` in its params are refused
+//! while funding runs against them (`services::funding`).
+//! ──────────────────────────────────────────────────────────────────────────
+
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    services::{
+        auto_stop, fees, funding,
+        instruments::decimals,
+        replay::{self, DecisionTrace},
+        strategy_pnl,
+        trading_engine::{execute_trade, TradeRequest, TradeResponse},
+    },
+    utils::errors::TradeError,
+};
+
+/// Positions smaller than this are flat
+const QTY_EPSILON: f64 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Allocation {
+    pub capital: f64,
+    pub realized_pnl: f64,
+}
+
+impl Allocation {
+    pub fn equity(&self) -> f64 {
+        self.capital + self.realized_pnl
+    }
+
+    /// `base_qty` grown/shrunk with the allocation's equity, at the same
+    /// precision; `0` once the allocation is exhausted
+    pub fn scale_qty(&self, base_qty: f64) -> f64 {
+        let equity = self.equity();
+        if equity <= 0.0 || self.capital <= 0.0 {
+            return 0.0;
+        }
+        let scale = 10f64.powi(decimals(base_qty) as i32);
+        (base_qty * equity / self.capital * scale).round() / scale
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Position {
+    /// Signed: positive long, negative short
+    pub qty: f64,
+    pub avg_price: f64,
+}
+
+/// Net a signed fill into `pos`; returns the new position and realised PnL
+pub fn apply_fill(pos: Position, fill_qty: f64, price: f64) -> (Position, f64) {
+    if pos.qty.abs() < QTY_EPSILON || pos.qty.signum() == fill_qty.signum() {
+        let qty = pos.qty + fill_qty;
+        let avg = (pos.qty.abs() * pos.avg_price + fill_qty.abs() * price) / qty.abs();
+        return (
+            Position {
+                qty,
+                avg_price: avg,
+            },
+            0.0,
+        );
+    }
+
+    let closing = fill_qty.abs().min(pos.qty.abs());
+    let pnl = closing * (price - pos.avg_price) * pos.qty.signum();
+    let qty = pos.qty + fill_qty;
+    let next = if qty.abs() < QTY_EPSILON {
+        Position::default()
+    } else if qty.signum() != pos.qty.signum() {
+        // flipped through flat: the remainder opened at this price
+        Position {
+            qty,
+            avg_price: price,
+        }
+    } else {
+        Position {
+            qty,
+            avg_price: pos.avg_price,
+        }
+    };
+    (next, pnl)
+}
+
+/// `None` when the strategy has no allocation (sized off the full account)
+pub async fn get(db: &PgPool, strategy_id: Uuid) -> Result<Option<Allocation>, sqlx::Error> {
+    let row: Option<(Option<f64>, f64)> = sqlx::query_as(
+        r#"
+        SELECT s.allocated_capital::float8,
+               COALESCE((SELECT SUM(pnl) FROM strategy_pnl p
+                          WHERE p.strategy_id = s.strategy_id), 0)::float8
+          FROM user_strategies s
+         WHERE s.strategy_id = $1
+        "#,
+    )
+    .bind(strategy_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(row.and_then(|(capital, realized_pnl)| {
+        capital.map(|capital| Allocation {
+            capital,
+            realized_pnl,
+        })
+    }))
+}
+
+/// Equity a risk-sized strategy should size from
+pub async fn equity(db: &PgPool, strategy_id: Uuid, account_equity: f64) -> f64 {
+    match get(db, strategy_id).await {
+        Ok(Some(a)) => a.equity(),
+        Ok(None) => account_equity,
+        Err(e) => {
+            tracing::warn!("allocation for {strategy_id}: {e}");
+            account_equity
+        }
+    }
+}
+
+/// Set or (with `None`) clear the allocation. `Ok(false)` = not the owner.
+pub async fn set(
+    db: &PgPool,
+    user_id: i64,
+    strategy_id: Uuid,
+    capital: Option<f64>,
+) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query(
+        "UPDATE user_strategies SET allocated_capital = $1 WHERE strategy_id = $2 AND user_id = $3",
+    )
+    .bind(capital)
+    .bind(strategy_id)
+    .bind(user_id)
+    .execute(db)
+    .await?
+    .rows_affected()
+        == 1)
+}
+
+/// Net one fill into the strategy's position, booking any realised PnL
+/// less the fill's `fee`; returns the booked amount
+#[tracing::instrument(name = "fill", skip(db))]
+pub async fn record_fill(
+    db: &PgPool,
+    strategy_id: Uuid,
+    user_id: i64,
+    side: &str,
+    size: f64,
+    price: f64,
+    fee: f64,
+) -> Result<f64, sqlx::Error> {
+    let signed = if side.eq_ignore_ascii_case("sell") {
+        -size
+    } else {
+        size
+    };
+    let mut tx = db.begin().await?;
+    let pos = sqlx::query_as::<_, (f64, f64)>(
+        r#"
+        SELECT qty::float8, avg_price::float8
+          FROM strategy_positions
+         WHERE strategy_id = $1
+           FOR UPDATE
+        "#,
+    )
+    .bind(strategy_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|(qty, avg_price)| Position { qty, avg_price })
+    .unwrap_or_default();
+
+    let (next, gross) = apply_fill(pos, signed, price);
+    let pnl = gross - fee;
+    sqlx::query(
+        r#"
+        INSERT INTO strategy_positions (strategy_id, qty, avg_price)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (strategy_id) DO UPDATE
+           SET qty = EXCLUDED.qty, avg_price = EXCLUDED.avg_price, updated_at = now()
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(next.qty)
+    .bind(next.avg_price)
+    .execute(&mut *tx)
+    .await?;
+    if pnl != 0.0 {
+        // attributed to the params version live at the closing fill
+        sqlx::query(
+            r#"
+            INSERT INTO strategy_pnl (strategy_id, user_id, pnl, params_hash)
+            VALUES ($1, $2, $3,
+                    (SELECT params_hash FROM user_strategies WHERE strategy_id = $1))
+            "#,
+        )
+        .bind(strategy_id)
+        .bind(user_id)
+        .bind(pnl)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    strategy_pnl::refresh_soon(strategy_id);
+    if pnl != 0.0 {
+        if let Err(e) = auto_stop::check(db, strategy_id).await {
+            tracing::warn!("auto-stop check for {strategy_id}: {e}");
+        }
+    }
+    Ok(pnl)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sizing {
+    /// Fixed configured qty, scaled with the allocation's equity
+    ScaleQty,
+    /// Size already derived from [`equity`]
+    AsIs,
+}
+
+/// `execute_trade` for a strategy task: applies allocation sizing to
+/// entries and books the fill against the strategy's sub-account.
+pub async fn execute(
+    db: &PgPool,
+    strategy_id: Uuid,
+    req: TradeRequest,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    sizing: Sizing,
+) -> Result<TradeResponse, TradeError> {
+    execute_traced(
+        db,
+        strategy_id,
+        req,
+        user_id,
+        is_demo,
+        master_key,
+        sizing,
+        DecisionTrace::new(),
+    )
+    .await
+}
+
+/// [`execute`], storing the strategy's decision `trace` (extended with the
+/// sizing and submission steps) on the order for trade replay.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(
+    name = "signal",
+    skip_all,
+    fields(%strategy_id, user_id, symbol = %req.symbol, side = %req.side, reduce_only = req.reduce_only)
+)]
+pub async fn execute_traced(
+    db: &PgPool,
+    strategy_id: Uuid,
+    mut req: TradeRequest,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    sizing: Sizing,
+    mut trace: DecisionTrace,
+) -> Result<TradeResponse, TradeError> {
+    trace.push(
+        "request",
+        json!({
+            "side": req.side,
+            "size": req.size,
+            "signal_price": req.signal_price,
+            "reduce_only": req.reduce_only,
+        }),
+    );
+    if !req.reduce_only {
+        if let Some(max_bps) = funding::limit_for(db, strategy_id).await {
+            funding::check_entry(&req.symbol, &req.side, max_bps)
+                .map_err(TradeError::RiskViolation)?;
+            trace.push("funding", json!({ "max_bps": max_bps }));
+        }
+        let alloc = get(db, strategy_id).await.map_err(TradeError::Db)?;
+        if let Some(a) = alloc {
+            if a.equity() <= 0.0 {
+                return Err(TradeError::RiskViolation(format!(
+                    "strategy allocation exhausted (equity {:.2})",
+                    a.equity()
+                )));
+            }
+            if sizing == Sizing::ScaleQty {
+                let base = req.size;
+                req.size = a.scale_qty(base);
+                if req.size <= 0.0 {
+                    return Err(TradeError::RiskViolation(
+                        "allocation too small for minimum size".into(),
+                    ));
+                }
+                trace.push(
+                    "allocation",
+                    json!({
+                        "capital": a.capital,
+                        "equity": a.equity(),
+                        "base_qty": base,
+                        "qty": req.size,
+                    }),
+                );
+            }
+        }
+    }
+
+    let exchange = req.exchange.as_str();
+    let resp = execute_trade(req, db, user_id, is_demo, master_key).await?;
+    if let Some(order_id) = resp.order_id {
+        trace.push(
+            "submit",
+            json!({
+                "size": resp.size,
+                "price": resp.price,
+                "mid_at_submit": resp.mid_at_submit,
+            }),
+        );
+        if let Err(e) = replay::attach(db, order_id, strategy_id, &trace).await {
+            tracing::warn!("replay context for order {order_id}: {e}");
+        }
+    }
+    if resp.success {
+        match resp.price.or(resp.mid_at_submit).or(resp.signal_price) {
+            Some(px) => {
+                // a limit price on the response means the order rested
+                let fee = fees::schedule_for(db, user_id, exchange)
+                    .await
+                    .fee(resp.size * px, resp.price.is_some());
+                if let Err(e) =
+                    record_fill(db, strategy_id, user_id, &resp.side, resp.size, px, fee).await
+                {
+                    tracing::warn!("allocation fill for {strategy_id}: {e}");
+                }
+            }
+            None => tracing::warn!("allocation fill for {strategy_id}: no price reference"),
+        }
+    }
+    Ok(resp)
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AllocationReport {
+    pub strategy_id: Uuid,
+    pub strategy: String,
+    pub symbol: String,
+    pub status: String,
+    pub allocated_capital: f64,
+    pub realized_pnl: f64,
+    pub equity: f64,
+    /// `realized_pnl / allocated_capital`, in percent
+    pub return_pct: f64,
+    pub open_qty: f64,
+}
+
+/// Per-allocation returns for every allocated strategy of the user
+pub async fn report(db: &PgPool, user_id: i64) -> Result<Vec<AllocationReport>, sqlx::Error> {
+    sqlx::query_as::<_, AllocationReport>(
+        r#"
+        SELECT s.strategy_id, s.strategy, s.symbol, s.status,
+               s.allocated_capital::float8                     AS allocated_capital,
+               COALESCE(p.pnl, 0)::float8                      AS realized_pnl,
+               (s.allocated_capital + COALESCE(p.pnl, 0))::float8 AS equity,
+               (COALESCE(p.pnl, 0) / s.allocated_capital * 100)::float8 AS return_pct,
+               COALESCE(pos.qty, 0)::float8                    AS open_qty
+          FROM user_strategies s
+          LEFT JOIN (SELECT strategy_id, SUM(pnl) AS pnl
+                       FROM strategy_pnl GROUP BY strategy_id) p
+                 ON p.strategy_id = s.strategy_id
+          LEFT JOIN strategy_positions pos ON pos.strategy_id = s.strategy_id
+         WHERE s.user_id = $1
+           AND s.allocated_capital IS NOT NULL
+         ORDER BY s.strategy_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn qty_scales_with_own_pnl() {
+        let a = Allocation {
+            capital: 1_000.0,
+            realized_pnl: 250.0,
+        };
+        assert_eq!(a.equity(), 1_250.0);
+        assert_eq!(a.scale_qty(0.01), 0.01); // 0.0125 → 2 dp
+        assert_eq!(a.scale_qty(0.04), 0.05);
+        let down = Allocation {
+            capital: 1_000.0,
+            realized_pnl: -500.0,
+        };
+        assert_eq!(down.scale_qty(0.4), 0.2);
+        let gone = Allocation {
+            capital: 1_000.0,
+            realized_pnl: -1_000.0,
+        };
+        assert_eq!(gone.scale_qty(0.4), 0.0);
+    }
+
+    #[test]
+    fn adding_to_a_position_averages_price() {
+        let (p, pnl) = apply_fill(Position::default(), 1.0, 100.0);
+        assert_eq!(pnl, 0.0);
+        let (p, pnl) = apply_fill(p, 3.0, 104.0);
+        assert_eq!(pnl, 0.0);
+        assert!(close(p.qty, 4.0));
+        assert!(close(p.avg_price, 103.0));
+    }
+
+    #[test]
+    fn reducing_realises_pnl_for_long_and_short() {
+        let long = Position {
+            qty: 2.0,
+            avg_price: 100.0,
+        };
+        let (p, pnl) = apply_fill(long, -1.0, 110.0);
+        assert!(close(pnl, 10.0));
+        assert!(close(p.qty, 1.0) && close(p.avg_price, 100.0));
+
+        let short = Position {
+            qty: -2.0,
+            avg_price: 100.0,
+        };
+        let (p, pnl) = apply_fill(short, 2.0, 110.0);
+        assert!(close(pnl, -20.0));
+        assert_eq!(p, Position::default());
+    }
+
+    #[test]
+    fn flipping_opens_remainder_at_fill_price() {
+        let long = Position {
+            qty: 1.0,
+            avg_price: 100.0,
+        };
+        let (p, pnl) = apply_fill(long, -3.0, 90.0);
+        assert!(close(pnl, -10.0));
+        assert!(close(p.qty, -2.0));
+        assert!(close(p.avg_price, 90.0));
+    }
+}
@@ -0,0 +1,324 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Execution analytics – slippage & fill quality
+//! ──────────────────────────────────────────────────────────────────────────
+//! * `set_mid` / `mid_for` – last book mid per symbol, fed by the depth feed
+//! * `record_submission`   – order row incl. signal price + mid at submit
+//! * `record_fill`         – fill row with slippage vs. both references,
+//!   queued on the batched fill writer
+//! * `execution_report`    – aggregates by symbol, order type, hour (UTC)
+//!
+//! Slippage is expressed in basis points and signed so that **positive is
+//! adverse** for either side (paid more on a buy, received less on a sell).
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::query_builder::Separated;
+use sqlx::{FromRow, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::db::batch::{BatchRow, BatchWriter};
+
+use crate::services::{market_data::bus_symbol, trading_engine::TradeResponse};
+use crate::utils::types::{MakerTaker, OrderStatus};
+
+/// ─── Live mids ───────────────────────────────────────────────────────────
+static MIDS: Lazy<DashMap<String, f64>> = Lazy::new(DashMap::new);
+
+pub fn set_mid(symbol: &str, mid: f64) {
+    if mid.is_finite() && mid > 0.0 {
+        MIDS.insert(bus_symbol(symbol), mid);
+    }
+}
+
+pub fn mid_for(symbol: &str) -> Option<f64> {
+    MIDS.get(&bus_symbol(symbol)).map(|m| *m)
+}
+
+/// Signed slippage in bps, positive = worse than `reference`.
+pub fn slippage_bps(side: &str, reference: f64, fill: f64) -> Option<f64> {
+    if !(reference.is_finite() && reference > 0.0 && fill.is_finite()) {
+        return None;
+    }
+    let raw = (fill - reference) / reference * 10_000.0;
+    match side.to_ascii_lowercase().as_str() {
+        "buy" => Some(raw),
+        "sell" => Some(-raw),
+        _ => None,
+    }
+}
+
+/// ─── Persistence ─────────────────────────────────────────────────────────
+/// Exchange order id out of a place-order response (`[{orderId}]` or `{orderId}`)
+fn external_order_id(data: &serde_json::Value) -> Option<String> {
+    let obj = data.get(0).unwrap_or(data);
+    obj.get("orderId")
+        .and_then(|v| v.as_str())
+        .map(str::to_owned)
+}
+
+/// Persist a submitted order together with its slippage references;
+/// accepted orders start `live`, refused ones are stored `rejected`.
+pub async fn record_submission(
+    db: &PgPool,
+    user_id: i64,
+    resp: &TradeResponse,
+) -> Result<Uuid, sqlx::Error> {
+    let status = if resp.success {
+        OrderStatus::Live
+    } else {
+        OrderStatus::Rejected
+    };
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO orders
+              (external_order_id, client_order_id, user_id, exchange, market_type,
+               symbol, side, order_type, price, size, reduce_only, status,
+               signal_price, mid_at_submit, closed_at)
+        VALUES ($1, $2, $3, $4, 'swap',
+                $5, $6, $7::order_type_enum, $8, $9, $10, $11,
+                $12, $13, CASE WHEN $14 THEN NULL ELSE now() END)
+        RETURNING order_id
+        "#,
+    )
+    .bind(external_order_id(&resp.data))
+    .bind(&resp.client_order_id)
+    .bind(user_id)
+    .bind(resp.exchange.as_str())
+    .bind(&resp.symbol)
+    .bind(&resp.side)
+    .bind(resp.order_type.to_ascii_lowercase())
+    .bind(resp.price)
+    .bind(resp.size)
+    .bind(resp.reduce_only)
+    .bind(status)
+    .bind(resp.signal_price)
+    .bind(resp.mid_at_submit)
+    .bind(resp.success)
+    .fetch_one(db)
+    .await
+}
+
+#[derive(Debug, FromRow)]
+struct OrderRefs {
+    side: String,
+    signal_price: Option<f64>,
+    mid_at_submit: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FillRow {
+    pub order_id: Uuid,
+    pub maker_taker: MakerTaker,
+    pub fill_price: f64,
+    pub fill_size: f64,
+    pub executed_at: DateTime<Utc>,
+    pub slippage_bps: Option<f64>,
+    pub mid_slippage_bps: Option<f64>,
+}
+
+impl BatchRow for FillRow {
+    const TABLE: &'static str = "fills";
+    const COLUMNS: &'static [&'static str] = &[
+        "order_id",
+        "maker_taker",
+        "fill_price",
+        "fill_size",
+        "executed_at",
+        "slippage_bps",
+        "mid_slippage_bps",
+    ];
+
+    fn bind_row<'args>(&self, b: &mut Separated<'_, 'args, Postgres, &'static str>) {
+        b.push_bind(self.order_id)
+            .push_bind(self.maker_taker)
+            .push_bind(self.fill_price)
+            .push_bind(self.fill_size)
+            .push_bind(self.executed_at)
+            .push_bind(self.slippage_bps)
+            .push_bind(self.mid_slippage_bps);
+    }
+}
+
+/// Queue one fill, computing slippage against the order's references.
+#[tracing::instrument(name = "fill", skip_all, fields(%order_id, fill_price, fill_size))]
+pub async fn record_fill(
+    db: &PgPool,
+    writer: &BatchWriter<FillRow>,
+    order_id: Uuid,
+    maker_taker: MakerTaker,
+    fill_price: f64,
+    fill_size: f64,
+    executed_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let refs = sqlx::query_as::<_, OrderRefs>(
+        r#"
+        SELECT side,
+               signal_price::float8  AS signal_price,
+               mid_at_submit::float8 AS mid_at_submit
+        FROM   orders
+        WHERE  order_id = $1
+        "#,
+    )
+    .bind(order_id)
+    .fetch_one(db)
+    .await?;
+
+    let vs_signal = refs
+        .signal_price
+        .and_then(|p| slippage_bps(&refs.side, p, fill_price));
+    let vs_mid = refs
+        .mid_at_submit
+        .and_then(|p| slippage_bps(&refs.side, p, fill_price));
+
+    writer
+        .push(FillRow {
+            order_id,
+            maker_taker,
+            fill_price,
+            fill_size,
+            executed_at,
+            slippage_bps: vs_signal,
+            mid_slippage_bps: vs_mid,
+        })
+        .await;
+    Ok(())
+}
+
+/// ─── Reporting ───────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy)]
+enum Dimension {
+    Symbol,
+    OrderType,
+    HourUtc,
+}
+
+impl Dimension {
+    /// SQL expression used as the grouping key (never user input)
+    fn column(self) -> &'static str {
+        match self {
+            Dimension::Symbol => "o.symbol",
+            Dimension::OrderType => "o.order_type::text",
+            Dimension::HourUtc => {
+                "LPAD(EXTRACT(HOUR FROM f.executed_at AT TIME ZONE 'UTC')::int::text, 2, '0')"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExecutionBucket {
+    pub bucket: String,
+    pub fills: i64,
+    pub avg_slippage_bps: Option<f64>,
+    pub avg_mid_slippage_bps: Option<f64>,
+    pub worst_slippage_bps: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExecutionReport {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub by_symbol: Vec<ExecutionBucket>,
+    pub by_order_type: Vec<ExecutionBucket>,
+    pub by_hour_utc: Vec<ExecutionBucket>,
+}
+
+async fn buckets(
+    db: &PgPool,
+    user_id: i64,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    dim: Dimension,
+) -> Result<Vec<ExecutionBucket>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        SELECT {key}                             AS bucket,
+               COUNT(*)                          AS fills,
+               AVG(f.slippage_bps)::float8       AS avg_slippage_bps,
+               AVG(f.mid_slippage_bps)::float8   AS avg_mid_slippage_bps,
+               MAX(f.slippage_bps)::float8       AS worst_slippage_bps
+        FROM   fills  f
+        JOIN   orders o USING (order_id)
+        WHERE  o.user_id = $1
+          AND  f.executed_at >= $2
+          AND  f.executed_at <  $3
+        GROUP  BY 1
+        ORDER  BY 1
+        "#,
+        key = dim.column()
+    );
+    sqlx::query_as::<_, ExecutionBucket>(&sql)
+        .bind(user_id)
+        .bind(since)
+        .bind(until)
+        .fetch_all(db)
+        .await
+}
+
+pub async fn execution_report(
+    db: &PgPool,
+    user_id: i64,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<ExecutionReport, sqlx::Error> {
+    Ok(ExecutionReport {
+        since,
+        until,
+        by_symbol: buckets(db, user_id, since, until, Dimension::Symbol).await?,
+        by_order_type: buckets(db, user_id, since, until, Dimension::OrderType).await?,
+        by_hour_utc: buckets(db, user_id, since, until, Dimension::HourUtc).await?,
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn slippage_sign_is_adverse_positive() {
+        // paid 10 bps more on a buy
+        let b = slippage_bps("buy", 100.0, 100.1).unwrap();
+        assert!((b - 10.0).abs() < 1e-9);
+        // sold 10 bps lower
+        let s = slippage_bps("SELL", 100.0, 99.9).unwrap();
+        assert!((s - 10.0).abs() < 1e-9);
+        // price improvement is negative
+        assert!(slippage_bps("buy", 100.0, 99.9).unwrap() < 0.0);
+    }
+
+    #[test]
+    fn slippage_rejects_bad_inputs() {
+        assert!(slippage_bps("buy", 0.0, 1.0).is_none());
+        assert!(slippage_bps("hold", 100.0, 101.0).is_none());
+        assert!(slippage_bps("buy", 100.0, f64::NAN).is_none());
+    }
+
+    #[test]
+    fn mids_are_keyed_by_normalised_symbol() {
+        set_mid("ETH-USDT-SWAP", 3_000.5);
+        assert_eq!(mid_for("ETHUSDT"), Some(3_000.5));
+        assert_eq!(mid_for("eth-usdt"), Some(3_000.5));
+        set_mid("ETHUSDT", -1.0); // ignored
+        assert_eq!(mid_for("ETHUSDT"), Some(3_000.5));
+    }
+
+    #[test]
+    fn order_id_extraction() {
+        assert_eq!(
+            external_order_id(&json!([{"orderId":"42"}])).as_deref(),
+            Some("42")
+        );
+        assert_eq!(
+            external_order_id(&json!({"orderId":"7"})).as_deref(),
+            Some("7")
+        );
+        assert!(external_order_id(&json!({"order_id":"MOCK"})).is_none());
+    }
+}
@@ -0,0 +1,301 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Account anomaly detection & trading freeze
+//! ──────────────────────────────────────────────────────────────────────────
+//! * New IP      – the first authenticated call from an address the user
+//!   has not used in `IP_MEMORY_SECS` raises `security.new_ip` (never for a
+//!   user's very first address)
+//! * Volume      – submitted notional is summed per hour; an hour above
+//!   `ANOMALY_VOLUME_FACTOR` × the user's 24 h hourly mean (floored at
+//!   `MIN_BASELINE_USD`) raises `security.volume_spike`, once per hour
+//! * Freeze      – with `ANOMALY_AUTO_FREEZE` a spike also freezes entries
+//!   until the user confirms via `POST /api/security/confirm`; exits still
+//!   go through
+//!
+//! Everything lives in the cache under `anomaly:{uid}:…`; alerts go out as
+//! notifications and into the audit trail.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::db::cache::{Cache, CacheError, SharedCache};
+use crate::services::{audit, notify};
+use crate::utils::errors::TradeError;
+
+/// An address unseen this long counts as new again
+const IP_MEMORY_SECS: u64 = 90 * 24 * 3600;
+const HOUR_TTL_SECS: u64 = 26 * 3600;
+/// Hours of history behind the volume baseline
+const BASELINE_HOURS: i64 = 24;
+/// New or quiet accounts still get some headroom before a spike fires
+const MIN_BASELINE_USD: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnomalyConfig {
+    /// Current hour vs. baseline hourly mean that counts as a spike
+    pub volume_factor: f64,
+    pub auto_freeze: bool,
+}
+
+struct State {
+    cache: SharedCache,
+    cfg: AnomalyConfig,
+}
+
+static STATE: OnceCell<State> = OnceCell::new();
+
+/// Enable volume tracking and the freeze check (idempotent).
+pub fn init(cache: SharedCache, cfg: AnomalyConfig) {
+    let _ = STATE.set(State { cache, cfg });
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Freeze {
+    pub reason: String,
+    pub details: Value,
+    pub at: DateTime<Utc>,
+}
+
+/// ─── Keys ────────────────────────────────────────────────────────────────
+fn key(user_id: i64, suffix: &str) -> String {
+    format!("anomaly:{user_id}:{suffix}")
+}
+
+fn hour_tag(t: DateTime<Utc>) -> String {
+    t.format("%Y%m%d%H").to_string()
+}
+
+fn notional_key(user_id: i64, hour: DateTime<Utc>) -> String {
+    key(user_id, &format!("notional:{}", hour_tag(hour)))
+}
+
+fn freeze_key(user_id: i64) -> String {
+    key(user_id, "frozen")
+}
+
+/// ─── Detection ───────────────────────────────────────────────────────────
+/// `current` is a spike against the mean of `history` (missing hours are 0)
+pub fn is_spike(current: f64, history: &[f64], factor: f64) -> bool {
+    let mean = if history.is_empty() {
+        0.0
+    } else {
+        history.iter().sum::<f64>() / history.len() as f64
+    };
+    current > factor * mean.max(MIN_BASELINE_USD)
+}
+
+/// Remember `ip` for the user; alert when it's new and not their first.
+pub async fn check_ip(cache: &dyn Cache, user_id: i64, ip: &str) -> Result<bool, CacheError> {
+    let fresh = cache
+        .set_nx(&key(user_id, &format!("ip:{ip}")), "1", IP_MEMORY_SECS)
+        .await?;
+    if !fresh {
+        return Ok(false);
+    }
+    // the first address on record is the baseline, not an anomaly
+    let first = cache.set_nx(&key(user_id, "ip_seen"), "1", 0).await?;
+    if first {
+        return Ok(false);
+    }
+    tracing::warn!("anomaly: user {user_id} active from new address {ip}");
+    notify::send(
+        user_id,
+        "security.new_ip",
+        &format!(
+            "New sign-in to your account from {ip}. If this wasn't you, revoke your API keys."
+        ),
+        json!({ "ip": ip }),
+    );
+    audit::record(Some(user_id), "security.new_ip", json!({ "ip": ip }));
+    Ok(true)
+}
+
+/// Add one submission's notional to the current hour and check for a spike.
+/// Returns whether this call raised the alert.
+pub async fn record_volume(
+    cache: &dyn Cache,
+    cfg: AnomalyConfig,
+    user_id: i64,
+    notional_usd: f64,
+    now: DateTime<Utc>,
+) -> Result<bool, CacheError> {
+    let cents = (notional_usd.abs() * 100.0).round() as i64;
+    if cents == 0 {
+        return Ok(false);
+    }
+    let key_now = notional_key(user_id, now);
+    let total = cache.incr_by(&key_now, cents).await?;
+    if total == cents {
+        cache.expire(&key_now, HOUR_TTL_SECS).await?;
+    }
+    let current = total as f64 / 100.0;
+
+    let mut history = Vec::with_capacity(BASELINE_HOURS as usize);
+    for h in 1..=BASELINE_HOURS {
+        let cents = cache
+            .get_i64(&notional_key(user_id, now - Duration::hours(h)))
+            .await?;
+        history.push(cents as f64 / 100.0);
+    }
+    if !is_spike(current, &history, cfg.volume_factor) {
+        return Ok(false);
+    }
+    let once = key(user_id, &format!("alerted:{}", hour_tag(now)));
+    if !cache.set_nx(&once, "1", HOUR_TTL_SECS).await? {
+        return Ok(false);
+    }
+
+    let baseline = history.iter().sum::<f64>() / history.len() as f64;
+    let details = json!({
+        "hour_notional_usd": current,
+        "baseline_hourly_usd": baseline,
+        "factor": cfg.volume_factor,
+    });
+    tracing::warn!(
+        "anomaly: user {user_id} traded {current:.2} USD this hour vs {baseline:.2} baseline"
+    );
+    audit::record(Some(user_id), "security.volume_spike", details.clone());
+    let message = if cfg.auto_freeze {
+        freeze(cache, user_id, "volume_spike", details.clone()).await?;
+        "Unusual trading volume on your account – new entries are paused until you confirm it was you."
+    } else {
+        "Unusual trading volume on your account. If this wasn't you, revoke your API keys."
+    };
+    notify::send(user_id, "security.volume_spike", message, details);
+    Ok(true)
+}
+
+/// Fire-and-forget volume accounting for a submitted order
+pub fn record_trade(user_id: i64, notional_usd: f64) {
+    let Some(state) = STATE.get() else {
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = record_volume(
+            state.cache.as_ref(),
+            state.cfg,
+            user_id,
+            notional_usd,
+            Utc::now(),
+        )
+        .await
+        {
+            tracing::warn!("anomaly: volume for {user_id}: {e}");
+        }
+    });
+}
+
+/// ─── Freeze ──────────────────────────────────────────────────────────────
+pub async fn freeze(
+    cache: &dyn Cache,
+    user_id: i64,
+    reason: &str,
+    details: Value,
+) -> Result<(), CacheError> {
+    let f = Freeze {
+        reason: reason.into(),
+        details,
+        at: Utc::now(),
+    };
+    cache.set_json(&freeze_key(user_id), &f, 0).await?;
+    audit::record(
+        Some(user_id),
+        "security.freeze",
+        json!({ "reason": reason }),
+    );
+    Ok(())
+}
+
+pub async fn frozen(cache: &dyn Cache, user_id: i64) -> Result<Option<Freeze>, CacheError> {
+    cache.get_json(&freeze_key(user_id)).await
+}
+
+/// Lift the freeze; `None` when there was none
+pub async fn confirm(cache: &dyn Cache, user_id: i64) -> Result<Option<Freeze>, CacheError> {
+    let f = frozen(cache, user_id).await?;
+    if f.is_some() {
+        cache.del(&freeze_key(user_id)).await?;
+        audit::record(Some(user_id), "security.confirm", json!({}));
+    }
+    Ok(f)
+}
+
+/// Entry gate for `execute_trade`; a cache outage doesn't block trading
+pub async fn check_not_frozen(user_id: i64) -> Result<(), TradeError> {
+    let Some(state) = STATE.get() else {
+        return Ok(());
+    };
+    match frozen(state.cache.as_ref(), user_id).await {
+        Ok(Some(f)) => Err(TradeError::RiskViolation(format!(
+            "trading frozen ({}) – confirm via POST /api/security/confirm",
+            f.reason
+        ))),
+        Ok(None) => Ok(()),
+        Err(e) => {
+            tracing::warn!("anomaly: freeze check for {user_id}: {e}");
+            Ok(())
+        }
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::cache::MemoryCache;
+
+    const CFG: AnomalyConfig = AnomalyConfig {
+        volume_factor: 100.0,
+        auto_freeze: true,
+    };
+
+    #[test]
+    fn spike_needs_factor_over_floored_mean() {
+        let quiet = [0.0; 24];
+        assert!(!is_spike(9_999.0, &quiet, 100.0));
+        assert!(is_spike(10_001.0, &quiet, 100.0));
+        let busy = [1_000.0; 24];
+        assert!(!is_spike(50_000.0, &busy, 100.0));
+        assert!(is_spike(100_001.0, &busy, 100.0));
+        assert!(is_spike(201.0, &[], 2.0));
+    }
+
+    #[tokio::test]
+    async fn first_ip_is_baseline_then_new_ones_alert() {
+        let cache = MemoryCache::new();
+        assert!(!check_ip(&cache, 1, "10.0.0.1").await.unwrap());
+        assert!(!check_ip(&cache, 1, "10.0.0.1").await.unwrap());
+        assert!(check_ip(&cache, 1, "10.0.0.2").await.unwrap());
+        assert!(!check_ip(&cache, 1, "10.0.0.2").await.unwrap());
+        // other users keep their own history
+        assert!(!check_ip(&cache, 2, "10.0.0.2").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn volume_spike_alerts_once_and_freezes() {
+        let cache = MemoryCache::new();
+        let now = Utc::now();
+        for h in 1..=24 {
+            record_volume(&cache, CFG, 5, 200.0, now - Duration::hours(h))
+                .await
+                .unwrap();
+        }
+        assert!(!record_volume(&cache, CFG, 5, 1_000.0, now).await.unwrap());
+        assert!(frozen(&cache, 5).await.unwrap().is_none());
+
+        assert!(record_volume(&cache, CFG, 5, 20_000.0, now).await.unwrap());
+        assert!(!record_volume(&cache, CFG, 5, 20_000.0, now).await.unwrap());
+        assert_eq!(
+            frozen(&cache, 5).await.unwrap().unwrap().reason,
+            "volume_spike"
+        );
+
+        assert!(confirm(&cache, 5).await.unwrap().is_some());
+        assert!(frozen(&cache, 5).await.unwrap().is_none());
+        assert!(confirm(&cache, 5).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,313 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Exchange API keys – register, list, rotate, remove
+//! ──────────────────────────────────────────────────────────────────────────
+//! * A key is proven against its exchange (a balance call with the submitted
+//!   credentials) before it is stored or replaces the old one
+//! * Credentials are sealed with `EnvelopeCrypto`, one data key per row;
+//!   listings show the exchange and the key's last characters, never a
+//!   secret or passphrase
+//! * Rotation swaps the credentials in place: the key id – and every
+//!   strategy trading on it – stays, cached decrypted creds are dropped
+//! * A key enabled strategies still trade on can't be removed
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::api_keys::{ApiKey, StoredKey};
+use crate::services::binance::client as binance;
+use crate::services::blowfin::api::{self, Credentials};
+use crate::services::crypto::{EnvelopeCrypto, GLOBAL_CRYPTO};
+use crate::services::trading_engine::Exchange;
+use crate::utils::errors::TradeError;
+
+/// Characters of the API key a listing shows
+const HINT_CHARS: usize = 4;
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyError {
+    #[error("{0}")]
+    Invalid(&'static str),
+    #[error("unsupported exchange `{0}`")]
+    UnsupportedExchange(String),
+    #[error("an API key for {0} is already stored – rotate it instead")]
+    Exists(String),
+    #[error("key not found")]
+    NotFound,
+    #[error("{0} enabled strategies trade with this key – stop them first")]
+    InUse(i64),
+    #[error("exchange rejected the API key: {0}")]
+    Rejected(String),
+    #[error("exchange unreachable: {0}")]
+    Exchange(String),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// Credentials as submitted; never echoed back
+#[derive(Deserialize)]
+pub struct KeyCreds {
+    pub api_key: String,
+    pub api_secret: String,
+    #[serde(default)]
+    pub api_passphrase: String,
+}
+
+impl KeyCreds {
+    fn validate(self) -> Result<Credentials, KeyError> {
+        let creds = Credentials {
+            api_key: self.api_key.trim().to_string(),
+            api_secret: self.api_secret.trim().to_string(),
+            api_passphrase: self.api_passphrase.trim().to_string(),
+        };
+        if creds.api_key.is_empty() || creds.api_secret.is_empty() {
+            return Err(KeyError::Invalid("api_key and api_secret are required"));
+        }
+        Ok(creds)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewKeyReq {
+    pub exchange: String,
+    #[serde(flatten)]
+    pub creds: KeyCreds,
+}
+
+/// What a listing shows of a stored key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KeyInfo {
+    pub key_id: Uuid,
+    pub exchange: String,
+    /// `••••` + the key's last characters; just `••••` when it can't be read
+    pub api_key_hint: String,
+    pub has_passphrase: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub rotated_at: Option<DateTime<Utc>>,
+}
+
+fn hint(api_key: &str) -> String {
+    let chars: Vec<char> = api_key.chars().collect();
+    // short keys would be shown whole
+    if chars.len() <= HINT_CHARS * 2 {
+        return "••••".into();
+    }
+    let tail: String = chars[chars.len() - HINT_CHARS..].iter().collect();
+    format!("••••{tail}")
+}
+
+fn info(row: StoredKey, crypto: &EnvelopeCrypto) -> KeyInfo {
+    let k = row.key;
+    let api_key_hint = crypto
+        .open(&k.encrypted_data_key, &k.nonce_key, &k.encrypted_api_key)
+        .map(|plain| hint(&plain))
+        .unwrap_or_else(|_| "••••".into());
+    KeyInfo {
+        key_id: k.key_id,
+        exchange: k.exchange,
+        api_key_hint,
+        has_passphrase: k.encrypted_passphrase.is_some(),
+        created_at: k.created_at,
+        rotated_at: row.rotated_at,
+    }
+}
+
+fn exchange(name: &str) -> Result<Exchange, KeyError> {
+    name.parse()
+        .map_err(|_| KeyError::UnsupportedExchange(name.to_string()))
+}
+
+/// One authenticated read with the submitted credentials
+async fn prove(
+    db: &PgPool,
+    exchange: Exchange,
+    creds: Credentials,
+    is_demo: bool,
+) -> Result<(), KeyError> {
+    match exchange {
+        Exchange::Blowfin => {
+            let resp = api::get_balance_as(db, creds, is_demo)
+                .await
+                .map_err(|e| KeyError::Exchange(e.to_string()))?;
+            if !resp.is_ok() {
+                return Err(KeyError::Rejected(format!(
+                    "code {}: {}",
+                    resp.code, resp.msg
+                )));
+            }
+        }
+        Exchange::Binance => binance::prove(&creds, is_demo).await.map_err(|e| match e {
+            TradeError::InvalidRequest(msg) => KeyError::Rejected(msg),
+            e => KeyError::Exchange(e.to_string()),
+        })?,
+    }
+    Ok(())
+}
+
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<KeyInfo>, KeyError> {
+    let rows = ApiKey::list_for_user(db, user_id).await?;
+    Ok(rows.into_iter().map(|r| info(r, &GLOBAL_CRYPTO)).collect())
+}
+
+/// Prove and store a key for an exchange the user has none for yet
+pub async fn add(
+    db: &PgPool,
+    user_id: i64,
+    req: NewKeyReq,
+    is_demo: bool,
+) -> Result<KeyInfo, KeyError> {
+    let exchange = exchange(&req.exchange)?;
+    let creds = req.creds.validate()?;
+    if ApiKey::get_by_user_and_exchange(db, user_id, exchange.as_str())
+        .await?
+        .is_some()
+    {
+        return Err(KeyError::Exists(exchange.as_str().into()));
+    }
+    prove(db, exchange, creds.clone(), is_demo).await?;
+
+    let passphrase = Some(creds.api_passphrase.as_str()).filter(|p| !p.is_empty());
+    let key_id = match ApiKey::insert(
+        db,
+        &GLOBAL_CRYPTO,
+        user_id,
+        exchange.as_str(),
+        &creds.api_key,
+        &creds.api_secret,
+        passphrase,
+    )
+    .await
+    {
+        Ok(id) => id,
+        // a concurrent add got there first
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(KeyError::Exists(exchange.as_str().into()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(KeyInfo {
+        key_id,
+        exchange: exchange.as_str().into(),
+        api_key_hint: hint(&creds.api_key),
+        has_passphrase: passphrase.is_some(),
+        created_at: Some(Utc::now()),
+        rotated_at: None,
+    })
+}
+
+/// Replace a stored key's credentials once the new ones are proven
+pub async fn rotate(
+    db: &PgPool,
+    user_id: i64,
+    key_id: Uuid,
+    creds: KeyCreds,
+    is_demo: bool,
+) -> Result<KeyInfo, KeyError> {
+    let stored = ApiKey::get_for_user(db, user_id, key_id)
+        .await?
+        .ok_or(KeyError::NotFound)?;
+    let exchange = exchange(&stored.key.exchange)?;
+    let creds = creds.validate()?;
+    prove(db, exchange, creds.clone(), is_demo).await?;
+
+    let passphrase = Some(creds.api_passphrase.as_str()).filter(|p| !p.is_empty());
+    ApiKey::reseal(
+        db,
+        &GLOBAL_CRYPTO,
+        user_id,
+        key_id,
+        &creds.api_key,
+        &creds.api_secret,
+        passphrase,
+    )
+    .await?
+    .ok_or(KeyError::NotFound)?;
+    let rotated = ApiKey::get_for_user(db, user_id, key_id)
+        .await?
+        .ok_or(KeyError::NotFound)?;
+    Ok(info(rotated, &GLOBAL_CRYPTO))
+}
+
+/// Delete a key no enabled strategy trades with; returns its exchange
+pub async fn remove(db: &PgPool, user_id: i64, key_id: Uuid) -> Result<String, KeyError> {
+    let stored = ApiKey::get_for_user(db, user_id, key_id)
+        .await?
+        .ok_or(KeyError::NotFound)?;
+    let in_use: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+          FROM user_strategies
+         WHERE user_id = $1 AND lower(exchange) = lower($2) AND status = 'enabled'
+        "#,
+    )
+    .bind(user_id)
+    .bind(&stored.key.exchange)
+    .fetch_one(db)
+    .await?;
+    if in_use > 0 {
+        return Err(KeyError::InUse(in_use));
+    }
+    ApiKey::delete(db, user_id, key_id)
+        .await?
+        .ok_or(KeyError::NotFound)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sodiumoxide::crypto::box_;
+
+    fn stored(crypto: &EnvelopeCrypto, api_key: &str, passphrase: Option<&str>) -> StoredKey {
+        let mut fields = vec![api_key.as_bytes(), b"secret".as_slice()];
+        fields.extend(passphrase.map(str::as_bytes));
+        let (wrapped, sealed) = crypto.seal_all(&fields);
+        let key = ApiKey {
+            key_id: Uuid::nil(),
+            user_id: 1,
+            exchange: "blowfin".into(),
+            encrypted_api_key: sealed[0].1.clone(),
+            encrypted_secret: sealed[1].1.clone(),
+            encrypted_passphrase: sealed.get(2).map(|s| s.1.clone()),
+            encrypted_data_key: wrapped,
+            nonce_key: sealed[0].0.clone(),
+            nonce_secret: sealed[1].0.clone(),
+            nonce_passphrase: sealed.get(2).map(|s| s.0.clone()),
+            created_at: None,
+        };
+        StoredKey {
+            key,
+            rotated_at: None,
+        }
+    }
+
+    #[test]
+    fn listings_show_a_hint_never_the_secrets() {
+        sodiumoxide::init().unwrap();
+        let (pk, sk) = box_::gen_keypair();
+        let crypto = EnvelopeCrypto::new(pk.0, sk.0);
+
+        let row = stored(&crypto, "AKIA1234567890wxyz", Some("hunter2"));
+        // every field opens with the row's one data key
+        let creds = row.key.decrypt(&crypto).unwrap();
+        assert_eq!(creds.api_secret, "secret");
+        assert_eq!(creds.api_passphrase, "hunter2");
+
+        let shown = info(row, &crypto);
+        assert_eq!(shown.api_key_hint, "••••wxyz");
+        assert!(shown.has_passphrase);
+        let json = serde_json::to_string(&shown).unwrap();
+        assert!(!json.contains("secret") && !json.contains("hunter2"));
+    }
+
+    #[test]
+    fn short_keys_are_fully_masked() {
+        assert_eq!(hint("abcd1234"), "••••");
+        assert_eq!(hint("abcd12345"), "••••2345");
+    }
+}
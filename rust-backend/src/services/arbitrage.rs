@@ -0,0 +1,1174 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Cross-exchange arbitrage – spread monitor, alerts & two-legged execution
+//! ──────────────────────────────────────────────────────────────────────────
+//! * An opportunity is buying one venue's ask and selling another venue's
+//!   bid for the same symbol, both as takers; the edge is what is left of
+//!   the price gap after both legs' taker fees ([`net_edge_bps`])
+//! * [`best_spread`] picks the widest such edge from one quote per venue
+//! * Each venue's feed reports its top of book here ([`record_quote`]):
+//!   BlowFin's depth stream and Binance USDⓈ-M book tickers. A quote older
+//!   than `QUOTE_MAX_AGE` doesn't count
+//! * Users opt in per symbol (`arbitrage_settings`): the net edge that
+//!   counts, the quantity per leg (base units) and `alert` or `execute`
+//! * [`spawn_monitor`] scans every `SCAN_EVERY`. `alert` notifies at most
+//!   once per `ALERT_COOLDOWN_SECS` per user & symbol; `execute` buys on the
+//!   cheap venue, then sells on the rich one. If the second leg fails the
+//!   first is flattened reduce-only straight away, so a hedge is either
+//!   both legs or nothing – and the user hears about it when that unwind
+//!   fails too
+//! * Hedges are tracked in `arbitrage_positions`, one open per user &
+//!   symbol, and closed (both legs reduce-only) once unwinding them gives
+//!   back no more than entering them made
+//! * Every instance scans; a cache claim per user & symbol keeps a single
+//!   one acting on an opportunity
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    db::cache::SharedCache,
+    services::{
+        audit,
+        blowfin::dto::Instrument,
+        drain,
+        fees::{self, FeeSchedule},
+        instruments,
+        market_data::bus_symbol,
+        notify,
+        trading_engine::{self, Exchange, TradeRequest, TradeResponse},
+        watchlist,
+    },
+    utils::errors::TradeError,
+};
+
+const SCAN_EVERY: Duration = Duration::from_secs(1);
+/// Re-read settings and open hedges this often (edits on other instances)
+const RELOAD_EVERY: Duration = Duration::from_secs(30);
+/// A venue whose book hasn't moved this long is left out
+const QUOTE_MAX_AGE: Duration = Duration::from_secs(5);
+const ALERT_COOLDOWN_SECS: u64 = 300;
+/// Held while one instance trades a user's symbol; outlives both legs'
+/// submit budgets
+const CLAIM_TTL_SECS: u64 = 60;
+/// Symbols one user may watch
+pub const MAX_SYMBOLS: i64 = 20;
+/// Sanity bound on a threshold; a wider gap is a broken feed, not an edge
+const MAX_EDGE_BPS: f64 = 1_000.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ArbError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("already watching {MAX_SYMBOLS} symbols")]
+    Full,
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// Top of book on one venue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VenueQuote {
+    pub exchange: Exchange,
+    pub bid: f64,
+    pub ask: f64,
+    /// The user's rates on this venue
+    pub fees: FeeSchedule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Spread {
+    pub buy_on: Exchange,
+    pub sell_on: Exchange,
+    pub buy_price: f64,
+    pub sell_price: f64,
+    /// After both taker fees, in bps of the buy price
+    pub net_edge_bps: f64,
+}
+
+/// Edge of buying at `ask` and selling at `bid`, net of both taker fees;
+/// negative when the gap doesn't pay for them
+pub fn net_edge_bps(ask: f64, buy_taker_bps: f64, bid: f64, sell_taker_bps: f64) -> Option<f64> {
+    if !(ask > 0.0 && bid > 0.0 && ask.is_finite() && bid.is_finite()) {
+        return None;
+    }
+    Some((bid - ask) / ask * 10_000.0 - buy_taker_bps - sell_taker_bps)
+}
+
+/// Widest spread between two different venues clearing `min_edge_bps`
+pub fn best_spread(quotes: &[VenueQuote], min_edge_bps: f64) -> Option<Spread> {
+    let mut best: Option<Spread> = None;
+    for buy in quotes {
+        for sell in quotes.iter().filter(|q| q.exchange != buy.exchange) {
+            let Some(edge) =
+                net_edge_bps(buy.ask, buy.fees.taker_bps, sell.bid, sell.fees.taker_bps)
+            else {
+                continue;
+            };
+            if edge < min_edge_bps || best.is_some_and(|b| b.net_edge_bps >= edge) {
+                continue;
+            }
+            best = Some(Spread {
+                buy_on: buy.exchange,
+                sell_on: sell.exchange,
+                buy_price: buy.ask,
+                sell_price: sell.bid,
+                net_edge_bps: edge,
+            });
+        }
+    }
+    best
+}
+
+// ─── Venue books ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy)]
+struct Top {
+    bid: f64,
+    ask: f64,
+    at: Instant,
+}
+
+/// Latest top of book per (canonical symbol, venue)
+static BOOKS: Lazy<DashMap<(String, Exchange), Top>> = Lazy::new(DashMap::new);
+
+/// A venue feed's best bid / ask for `symbol` (any spelling)
+pub fn record_quote(exchange: Exchange, symbol: &str, bid: f64, ask: f64) {
+    if !(bid > 0.0 && ask >= bid && ask.is_finite()) {
+        return;
+    }
+    let top = Top {
+        bid,
+        ask,
+        at: Instant::now(),
+    };
+    BOOKS.insert((bus_symbol(symbol), exchange), top);
+}
+
+/// (venue, bid, ask) of every book on `symbol` fresher than `QUOTE_MAX_AGE`
+fn fresh_tops(symbol: &str) -> Vec<(Exchange, f64, f64)> {
+    let symbol = bus_symbol(symbol);
+    let mut tops: Vec<_> = BOOKS
+        .iter()
+        .filter(|e| e.key().0 == symbol && e.value().at.elapsed() < QUOTE_MAX_AGE)
+        .map(|e| (e.key().1, e.value().bid, e.value().ask))
+        .collect();
+    tops.sort_by_key(|t| t.0.as_str());
+    tops
+}
+
+/// Fresh top of book per venue for `symbol`, priced with the user's fees
+async fn venue_quotes(db: &PgPool, user_id: i64, symbol: &str) -> Vec<VenueQuote> {
+    let mut quotes = Vec::new();
+    for (exchange, bid, ask) in fresh_tops(symbol) {
+        quotes.push(VenueQuote {
+            exchange,
+            bid,
+            ask,
+            fees: fees::schedule_for(db, user_id, exchange.as_str()).await,
+        });
+    }
+    quotes
+}
+
+// ─── Settings ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Notify only
+    Alert,
+    /// Trade both legs
+    Execute,
+}
+
+impl Mode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Mode::Alert => "alert",
+            Mode::Execute => "execute",
+        }
+    }
+}
+
+/// One user's opt-in for one symbol
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct ArbSettings {
+    #[serde(skip)]
+    pub user_id: i64,
+    /// Canonical `BTCUSDT`
+    pub symbol: String,
+    pub min_edge_bps: f64,
+    /// Per leg, in base units
+    pub qty: f64,
+    pub mode: String,
+    pub enabled: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ArbSettings {
+    fn executes(&self) -> bool {
+        self.mode == Mode::Execute.as_str()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SettingsReq {
+    pub min_edge_bps: f64,
+    pub qty: f64,
+    pub mode: Mode,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl SettingsReq {
+    fn validate(&self) -> Result<(), ArbError> {
+        if !(self.min_edge_bps > 0.0 && self.min_edge_bps <= MAX_EDGE_BPS) {
+            return Err(ArbError::Invalid(format!(
+                "min_edge_bps must be in (0, {MAX_EDGE_BPS}] – it is net of fees"
+            )));
+        }
+        if !(self.qty > 0.0 && self.qty.is_finite()) {
+            return Err(ArbError::Invalid("qty must be positive".into()));
+        }
+        Ok(())
+    }
+}
+
+/// "btc-usdt", "BTC/USDT" → "BTCUSDT"
+pub fn normalize(symbol: &str) -> Result<String, ArbError> {
+    watchlist::normalize(symbol)
+        .map(|s| bus_symbol(&s))
+        .map_err(|e| ArbError::Invalid(e.to_string()))
+}
+
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<ArbSettings>, ArbError> {
+    Ok(sqlx::query_as::<_, ArbSettings>(
+        r#"
+        SELECT user_id, symbol, min_edge_bps, qty, mode, enabled, updated_at
+          FROM arbitrage_settings
+         WHERE user_id = $1
+         ORDER BY symbol
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?)
+}
+
+/// Create or replace the user's settings for `symbol`
+pub async fn upsert(
+    db: &PgPool,
+    user_id: i64,
+    symbol: &str,
+    req: &SettingsReq,
+) -> Result<ArbSettings, ArbError> {
+    let symbol = normalize(symbol)?;
+    req.validate()?;
+    let mut tx = db.begin().await?;
+    // serialise concurrent upserts for this user so the cap holds
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let (count, present): (i64, bool) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*), COALESCE(BOOL_OR(symbol = $2), false)
+          FROM arbitrage_settings
+         WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&symbol)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !present && count >= MAX_SYMBOLS {
+        return Err(ArbError::Full);
+    }
+    let row = sqlx::query_as::<_, ArbSettings>(
+        r#"
+        INSERT INTO arbitrage_settings (user_id, symbol, min_edge_bps, qty, mode, enabled)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, symbol) DO UPDATE
+           SET min_edge_bps = EXCLUDED.min_edge_bps,
+               qty          = EXCLUDED.qty,
+               mode         = EXCLUDED.mode,
+               enabled      = EXCLUDED.enabled,
+               updated_at   = now()
+        RETURNING user_id, symbol, min_edge_bps, qty, mode, enabled, updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&symbol)
+    .bind(req.min_edge_bps)
+    .bind(req.qty)
+    .bind(req.mode.as_str())
+    .bind(req.enabled)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    audit::record(Some(user_id), "arbitrage.settings", json!(row));
+    Ok(row)
+}
+
+/// `Ok(false)` when the symbol wasn't watched
+pub async fn remove(db: &PgPool, user_id: i64, symbol: &str) -> Result<bool, ArbError> {
+    let symbol = normalize(symbol)?;
+    let removed = sqlx::query("DELETE FROM arbitrage_settings WHERE user_id = $1 AND symbol = $2")
+        .bind(user_id)
+        .bind(&symbol)
+        .execute(db)
+        .await?
+        .rows_affected()
+        == 1;
+    Ok(removed)
+}
+
+async fn enabled_settings(db: &PgPool) -> Result<Vec<ArbSettings>, sqlx::Error> {
+    sqlx::query_as::<_, ArbSettings>(
+        r#"
+        SELECT user_id, symbol, min_edge_bps, qty, mode, enabled, updated_at
+          FROM arbitrage_settings
+         WHERE enabled
+        "#,
+    )
+    .fetch_all(db)
+    .await
+}
+
+// ─── Hedges ──────────────────────────────────────────────────────────────
+
+/// Both legs of one executed opportunity
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct ArbPosition {
+    pub position_id: Uuid,
+    #[serde(skip)]
+    pub user_id: i64,
+    pub symbol: String,
+    /// Venue holding the long leg
+    pub buy_on: String,
+    /// Venue holding the short leg
+    pub sell_on: String,
+    pub qty: f64,
+    pub entry_buy: f64,
+    pub entry_sell: f64,
+    pub entry_edge_bps: f64,
+    /// `open` · `closed` · `unwound` (second leg failed, first flattened) ·
+    /// `broken` (left one-legged – needs a human)
+    pub status: String,
+    pub exit_buy: Option<f64>,
+    pub exit_sell: Option<f64>,
+    pub note: Option<String>,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+const POSITION_COLS: &str = "position_id, user_id, symbol, buy_on, sell_on, qty, entry_buy, \
+     entry_sell, entry_edge_bps, status, exit_buy, exit_sell, note, opened_at, closed_at";
+
+/// The user's hedges, newest first
+pub async fn positions(
+    db: &PgPool,
+    user_id: i64,
+    limit: i64,
+) -> Result<Vec<ArbPosition>, ArbError> {
+    Ok(sqlx::query_as::<_, ArbPosition>(&format!(
+        "SELECT {POSITION_COLS} FROM arbitrage_positions WHERE user_id = $1 \
+         ORDER BY opened_at DESC LIMIT $2"
+    ))
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(db)
+    .await?)
+}
+
+async fn open_positions(db: &PgPool) -> Result<Vec<ArbPosition>, sqlx::Error> {
+    sqlx::query_as::<_, ArbPosition>(&format!(
+        "SELECT {POSITION_COLS} FROM arbitrage_positions WHERE status = 'open'"
+    ))
+    .fetch_all(db)
+    .await
+}
+
+async fn has_open(db: &PgPool, user_id: i64, symbol: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM arbitrage_positions \
+          WHERE user_id = $1 AND symbol = $2 AND status = 'open')",
+    )
+    .bind(user_id)
+    .bind(symbol)
+    .fetch_one(db)
+    .await
+}
+
+/// Net bps of unwinding `pos` now: selling its long at the buy venue's bid
+/// and buying back its short at the sell venue's ask
+pub fn exit_edge_bps(pos: &ArbPosition, quotes: &[VenueQuote]) -> Option<f64> {
+    let on = |name: &str| quotes.iter().find(|q| q.exchange.as_str() == name);
+    let (long, short) = (on(&pos.buy_on)?, on(&pos.sell_on)?);
+    net_edge_bps(
+        short.ask,
+        short.fees.taker_bps,
+        long.bid,
+        long.fees.taker_bps,
+    )
+}
+
+/// Close once the round trip keeps at least nothing: the exit gives back no
+/// more than the entry made
+pub fn should_close(pos: &ArbPosition, quotes: &[VenueQuote]) -> bool {
+    exit_edge_bps(pos, quotes).is_some_and(|exit| pos.entry_edge_bps + exit >= 0.0)
+}
+
+// ─── Execution ───────────────────────────────────────────────────────────
+
+/// One leg in a venue's own terms
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leg {
+    pub exchange: Exchange,
+    pub symbol: String,
+    /// Order units of that venue: BlowFin contracts, Binance base quantity
+    pub size: f64,
+    pub price: f64,
+}
+
+/// Both legs of an opportunity, sized alike in base units
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub buy: Leg,
+    pub sell: Leg,
+    /// Per leg, in base units, after rounding to what both venues trade
+    pub qty: f64,
+}
+
+/// `v` rounded down to a multiple of `step`, without float noise
+fn floor_to(v: f64, step: f64) -> f64 {
+    let lots = (v / step + 1e-9).floor() * step;
+    format!("{lots:.*}", instruments::decimals(step) as usize)
+        .parse()
+        .unwrap_or(lots)
+}
+
+/// Size `qty` base units on both venues. BlowFin trades whole lots of
+/// contracts, so the hedge is trimmed to that grid and Binance sends the
+/// same base quantity. `blowfin` is the symbol's instrument there.
+pub fn plan(
+    spread: &Spread,
+    symbol: &str,
+    qty: f64,
+    blowfin: Option<&Instrument>,
+) -> Result<Plan, ArbError> {
+    let inst = blowfin.ok_or_else(|| {
+        ArbError::Invalid(format!("{symbol}: no BlowFin instrument to size against"))
+    })?;
+    let contracts = floor_to(qty / inst.contract_value, inst.lot_size);
+    if contracts <= 0.0 || contracts < inst.min_size {
+        return Err(ArbError::Invalid(format!(
+            "{symbol}: qty {qty} is below BlowFin's minimum order"
+        )));
+    }
+    let base = floor_to(
+        contracts * inst.contract_value,
+        inst.lot_size * inst.contract_value,
+    );
+    let leg = |exchange: Exchange, price: f64| match exchange {
+        Exchange::Blowfin => Leg {
+            exchange,
+            symbol: inst.inst_id.clone(),
+            size: contracts,
+            price,
+        },
+        Exchange::Binance => Leg {
+            exchange,
+            symbol: bus_symbol(symbol),
+            size: base,
+            price,
+        },
+    };
+    Ok(Plan {
+        buy: leg(spread.buy_on, spread.buy_price),
+        sell: leg(spread.sell_on, spread.sell_price),
+        qty: base,
+    })
+}
+
+fn market(leg: &Leg, side: &str, reduce_only: bool) -> TradeRequest {
+    TradeRequest {
+        exchange: leg.exchange,
+        symbol: leg.symbol.clone(),
+        side: side.into(),
+        order_type: "market".into(),
+        price: None,
+        size: leg.size,
+        reduce_only,
+        signal_price: Some(leg.price),
+        tp_sl: None,
+    }
+}
+
+/// Sends one leg; production goes through `trading_engine::execute_trade`
+#[async_trait::async_trait]
+pub trait Legs: Send + Sync {
+    async fn send(&self, req: TradeRequest) -> Result<TradeResponse, TradeError>;
+}
+
+struct ProdLegs<'a> {
+    db: &'a PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &'a [u8],
+}
+
+#[async_trait::async_trait]
+impl Legs for ProdLegs<'_> {
+    async fn send(&self, req: TradeRequest) -> Result<TradeResponse, TradeError> {
+        trading_engine::execute_trade(req, self.db, self.user_id, self.is_demo, self.master_key)
+            .await
+    }
+}
+
+/// `Ok` only for an accepted order
+async fn send_ok(legs: &dyn Legs, req: TradeRequest) -> Result<(), String> {
+    match legs.send(req).await {
+        Ok(r) if r.success => Ok(()),
+        Ok(r) => Err(format!("rejected: {}", r.data)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// How an attempt to open a hedge ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Opened {
+    /// Long on the buy venue, short on the sell venue
+    Hedged,
+    /// The first leg didn't go through – nothing is open
+    NotFilled(String),
+    /// The second leg failed and the first was flattened again
+    Unwound(String),
+    /// The second leg and the unwind failed: one leg is open
+    Broken(String),
+}
+
+/// Buy leg, then sell leg; undo the buy if the sell doesn't go through
+pub async fn open_legs(legs: &dyn Legs, plan: &Plan) -> Opened {
+    if let Err(e) = send_ok(legs, market(&plan.buy, "buy", false)).await {
+        return Opened::NotFilled(e);
+    }
+    let Err(sell) = send_ok(legs, market(&plan.sell, "sell", false)).await else {
+        return Opened::Hedged;
+    };
+    match send_ok(legs, market(&plan.buy, "sell", true)).await {
+        Ok(()) => Opened::Unwound(sell),
+        Err(unwind) => Opened::Broken(format!("sell leg: {sell}; unwind: {unwind}")),
+    }
+}
+
+/// Close both legs reduce-only; `Err` names the leg(s) still open
+pub async fn close_legs(legs: &dyn Legs, plan: &Plan) -> Result<(), String> {
+    let long = send_ok(legs, market(&plan.buy, "sell", true)).await;
+    let short = send_ok(legs, market(&plan.sell, "buy", true)).await;
+    match (long, short) {
+        (Ok(()), Ok(())) => Ok(()),
+        (l, s) => Err(format!(
+            "long leg: {}; short leg: {}",
+            l.err().unwrap_or_else(|| "closed".into()),
+            s.err().unwrap_or_else(|| "closed".into())
+        )),
+    }
+}
+
+/// Trade `spread` for `w` and record what came of it
+async fn execute(
+    db: &PgPool,
+    w: &ArbSettings,
+    spread: &Spread,
+    legs: &dyn Legs,
+) -> Result<Opened, ArbError> {
+    if has_open(db, w.user_id, &w.symbol).await? {
+        return Ok(Opened::NotFilled("a hedge is already open".into()));
+    }
+    let plan = plan(
+        spread,
+        &w.symbol,
+        w.qty,
+        instruments::get(&w.symbol).as_ref(),
+    )?;
+    let opened = open_legs(legs, &plan).await;
+    let (status, note) = match &opened {
+        Opened::Hedged => ("open", None),
+        Opened::Unwound(e) => ("unwound", Some(e.as_str())),
+        Opened::Broken(e) => ("broken", Some(e.as_str())),
+        Opened::NotFilled(_) => return Ok(opened),
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO arbitrage_positions
+            (user_id, symbol, buy_on, sell_on, qty, entry_buy, entry_sell,
+             entry_edge_bps, status, note, closed_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                CASE WHEN $9 = 'unwound' THEN now() END)
+        "#,
+    )
+    .bind(w.user_id)
+    .bind(&w.symbol)
+    .bind(spread.buy_on.as_str())
+    .bind(spread.sell_on.as_str())
+    .bind(plan.qty)
+    .bind(spread.buy_price)
+    .bind(spread.sell_price)
+    .bind(spread.net_edge_bps)
+    .bind(status)
+    .bind(note)
+    .execute(db)
+    .await?;
+    Ok(opened)
+}
+
+/// Unwind an open hedge and record the exit
+async fn close(
+    db: &PgPool,
+    pos: &ArbPosition,
+    quotes: &[VenueQuote],
+    legs: &dyn Legs,
+) -> Result<Result<(), String>, ArbError> {
+    let venue = |name: &str| {
+        name.parse::<Exchange>()
+            .map_err(|_| ArbError::Invalid(format!("unknown venue {name}")))
+    };
+    let price = |name: &str, bid: bool| {
+        quotes
+            .iter()
+            .find(|q| q.exchange.as_str() == name)
+            .map(|q| if bid { q.bid } else { q.ask })
+    };
+    let (exit_sell, exit_buy) = (price(&pos.buy_on, true), price(&pos.sell_on, false));
+    let spread = Spread {
+        buy_on: venue(&pos.buy_on)?,
+        sell_on: venue(&pos.sell_on)?,
+        buy_price: exit_sell.unwrap_or(pos.entry_buy),
+        sell_price: exit_buy.unwrap_or(pos.entry_sell),
+        net_edge_bps: 0.0,
+    };
+    let plan = plan(
+        &spread,
+        &pos.symbol,
+        pos.qty,
+        instruments::get(&pos.symbol).as_ref(),
+    )?;
+    let closed = close_legs(legs, &plan).await;
+    let (status, note) = match &closed {
+        Ok(()) => ("closed", None),
+        Err(e) => ("broken", Some(e.as_str())),
+    };
+    sqlx::query(
+        r#"
+        UPDATE arbitrage_positions
+           SET status = $2, exit_sell = $3, exit_buy = $4, note = $5, closed_at = now()
+         WHERE position_id = $1 AND status = 'open'
+        "#,
+    )
+    .bind(pos.position_id)
+    .bind(status)
+    .bind(exit_sell)
+    .bind(exit_buy)
+    .bind(note)
+    .execute(db)
+    .await?;
+    Ok(closed)
+}
+
+// ─── Monitor ─────────────────────────────────────────────────────────────
+
+fn describe(symbol: &str, s: &Spread) -> String {
+    format!(
+        "{symbol}: buy {} at {} / sell {} at {} – {:.1} bps after fees",
+        s.buy_on.as_str(),
+        s.buy_price,
+        s.sell_on.as_str(),
+        s.sell_price,
+        s.net_edge_bps
+    )
+}
+
+/// Notify about `spread` unless this user & symbol heard within the cooldown
+async fn alert(cache: &SharedCache, w: &ArbSettings, spread: &Spread) {
+    let key = format!("arb:alert:{}:{}", w.user_id, w.symbol);
+    match cache.set_nx(&key, "1", ALERT_COOLDOWN_SECS).await {
+        Ok(true) => notify::send(
+            w.user_id,
+            "arbitrage.spread",
+            &describe(&w.symbol, spread),
+            json!({ "symbol": w.symbol, "spread": spread }),
+        ),
+        Ok(false) => {}
+        Err(e) => tracing::warn!("arbitrage: alert cooldown for {}: {e}", w.user_id),
+    }
+}
+
+/// Act for one watched symbol; `true` when a hedge opened or closed
+async fn step(
+    db: &PgPool,
+    cache: &SharedCache,
+    w: &ArbSettings,
+    open: Option<&ArbPosition>,
+    is_demo: bool,
+    master_key: &[u8],
+) -> bool {
+    let quotes = venue_quotes(db, w.user_id, &w.symbol).await;
+    if quotes.len() < 2 {
+        return false;
+    }
+    let spread = match open {
+        Some(pos) if should_close(pos, &quotes) => None,
+        Some(_) => return false,
+        None => match best_spread(&quotes, w.min_edge_bps) {
+            Some(s) => Some(s),
+            None => return false,
+        },
+    };
+    if !w.executes() {
+        if let Some(s) = &spread {
+            alert(cache, w, s).await;
+        }
+        return false;
+    }
+
+    // one instance acts; the others see the claim and skip this round
+    let claim = format!("arb:claim:{}:{}", w.user_id, w.symbol);
+    match cache.set_nx(&claim, "1", CLAIM_TTL_SECS).await {
+        Ok(true) => {}
+        Ok(false) => return false,
+        Err(e) => {
+            tracing::warn!("arbitrage: claim for {}: {e}", w.user_id);
+            return false;
+        }
+    }
+    let legs = ProdLegs {
+        db,
+        user_id: w.user_id,
+        is_demo,
+        master_key,
+    };
+    let acted = match (spread, open) {
+        (Some(s), _) => match execute(db, w, &s, &legs).await {
+            Ok(opened) => {
+                report_open(w, &s, &opened);
+                !matches!(opened, Opened::NotFilled(_))
+            }
+            Err(e) => {
+                tracing::warn!("arbitrage: {} {}: {e}", w.user_id, w.symbol);
+                false
+            }
+        },
+        (None, Some(pos)) => match close(db, pos, &quotes, &legs).await {
+            Ok(closed) => {
+                report_close(pos, &closed);
+                true
+            }
+            Err(e) => {
+                tracing::warn!("arbitrage: closing {}: {e}", pos.position_id);
+                false
+            }
+        },
+        (None, None) => false,
+    };
+    if let Err(e) = cache.del(&claim).await {
+        tracing::warn!("arbitrage: releasing claim for {}: {e}", w.user_id);
+    }
+    acted
+}
+
+fn report_open(w: &ArbSettings, s: &Spread, opened: &Opened) {
+    let what = describe(&w.symbol, s);
+    let (kind, message) = match opened {
+        Opened::Hedged => ("arbitrage.opened", format!("hedge opened – {what}")),
+        Opened::NotFilled(e) => {
+            tracing::info!("arbitrage: {} {what} not filled: {e}", w.user_id);
+            return;
+        }
+        Opened::Unwound(e) => (
+            "arbitrage.unwound",
+            format!("second leg failed ({e}), first leg flattened – {what}"),
+        ),
+        Opened::Broken(e) => (
+            "arbitrage.broken",
+            format!("ONE-LEGGED POSITION – close it by hand: {e} – {what}"),
+        ),
+    };
+    let details = json!({ "symbol": w.symbol, "spread": s, "outcome": format!("{opened:?}") });
+    audit::record(Some(w.user_id), kind, details.clone());
+    notify::send(w.user_id, kind, &message, details);
+}
+
+fn report_close(pos: &ArbPosition, closed: &Result<(), String>) {
+    let (kind, message) = match closed {
+        Ok(()) => ("arbitrage.closed", format!("{} hedge closed", pos.symbol)),
+        Err(e) => (
+            "arbitrage.broken",
+            format!(
+                "{} hedge only partly closed – check it by hand: {e}",
+                pos.symbol
+            ),
+        ),
+    };
+    let details = json!({ "position_id": pos.position_id, "symbol": pos.symbol });
+    audit::record(Some(pos.user_id), kind, details.clone());
+    notify::send(pos.user_id, kind, &message, details);
+}
+
+/// Scan every enabled setting each `SCAN_EVERY` until the process drains
+pub fn spawn_monitor(pg: PgPool, cache: SharedCache, is_demo: bool) {
+    tokio::spawn(async move {
+        let master_key = std::env::var("MASTER_KEY").unwrap_or_default().into_bytes();
+        let (mut watches, mut open) = (Vec::new(), Vec::new());
+        let mut loaded: Option<Instant> = None;
+        let mut iv = tokio::time::interval(SCAN_EVERY);
+        iv.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = iv.tick() => {}
+                _ = drain::stopping() => return,
+            }
+            if loaded.is_none_or(|t| t.elapsed() >= RELOAD_EVERY) {
+                match (enabled_settings(&pg).await, open_positions(&pg).await) {
+                    (Ok(w), Ok(o)) => {
+                        metrics::gauge!("arbitrage_watches", w.len() as f64);
+                        metrics::gauge!("arbitrage_open_hedges", o.len() as f64);
+                        (watches, open) = (w, o);
+                        loaded = Some(Instant::now());
+                    }
+                    (Err(e), _) | (_, Err(e)) => tracing::warn!("arbitrage: reload: {e}"),
+                }
+            }
+            for w in &watches {
+                let pos = open
+                    .iter()
+                    .find(|p| p.user_id == w.user_id && p.symbol == w.symbol);
+                if step(&pg, &cache, w, pos, is_demo, &master_key).await {
+                    // pick the new / closed hedge up before the next scan
+                    loaded = None;
+                }
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn edge_is_net_of_both_taker_fees() {
+        // 20 bps gap, 6 bps a leg
+        let edge = net_edge_bps(10_000.0, 6.0, 10_020.0, 6.0).unwrap();
+        assert!((edge - 8.0).abs() < 1e-9);
+        assert!(net_edge_bps(10_000.0, 6.0, 10_005.0, 6.0).unwrap() < 0.0);
+        assert_eq!(net_edge_bps(0.0, 6.0, 10_005.0, 6.0), None);
+    }
+
+    #[test]
+    fn one_venue_has_no_spread() {
+        let q = VenueQuote {
+            exchange: Exchange::Blowfin,
+            bid: 10_020.0,
+            ask: 9_000.0,
+            fees: FeeSchedule::default(),
+        };
+        assert_eq!(best_spread(&[q, q], f64::MIN), None);
+    }
+
+    fn quote(exchange: Exchange, bid: f64, ask: f64, taker_bps: f64) -> VenueQuote {
+        VenueQuote {
+            exchange,
+            bid,
+            ask,
+            fees: FeeSchedule {
+                maker_bps: 2.0,
+                taker_bps,
+            },
+        }
+    }
+
+    fn btc() -> Instrument {
+        Instrument {
+            inst_id: "BTC-USDT".into(),
+            base_currency: "BTC".into(),
+            quote_currency: "USDT".into(),
+            contract_value: 0.001,
+            min_size: 0.1,
+            lot_size: 0.1,
+            tick_size: 0.1,
+            state: "live".into(),
+        }
+    }
+
+    /// BlowFin rich, Binance cheap: 30 bps apart, 10 bps of fees
+    fn quotes() -> Vec<VenueQuote> {
+        vec![
+            quote(Exchange::Blowfin, 60_180.0, 60_181.0, 6.0),
+            quote(Exchange::Binance, 59_999.0, 60_000.0, 4.0),
+        ]
+    }
+
+    #[test]
+    fn a_cross_venue_gap_buys_cheap_and_sells_rich() {
+        let s = best_spread(&quotes(), 5.0).expect("spread");
+        assert_eq!(
+            (s.buy_on, s.sell_on),
+            (Exchange::Binance, Exchange::Blowfin)
+        );
+        assert_eq!((s.buy_price, s.sell_price), (60_000.0, 60_180.0));
+        assert!((s.net_edge_bps - 20.0).abs() < 1e-9, "{}", s.net_edge_bps);
+
+        // the same gap doesn't clear a threshold above it…
+        assert_eq!(best_spread(&quotes(), 25.0), None);
+        // …and dearer fees eat it
+        let mut dear = quotes();
+        dear[1].fees.taker_bps = 30.0;
+        assert_eq!(best_spread(&dear, 5.0), None);
+    }
+
+    #[test]
+    fn both_legs_get_the_same_base_quantity() {
+        let s = best_spread(&quotes(), 5.0).unwrap();
+        let p = plan(&s, "BTCUSDT", 0.01234, Some(&btc())).unwrap();
+        // BlowFin: 12.3 contracts of 0.001 → 0.0123 BTC on both venues
+        assert_eq!(p.qty, 0.0123);
+        assert_eq!(
+            p.buy,
+            Leg {
+                exchange: Exchange::Binance,
+                symbol: "BTCUSDT".into(),
+                size: 0.0123,
+                price: 60_000.0
+            }
+        );
+        assert_eq!((p.sell.symbol.as_str(), p.sell.size), ("BTC-USDT", 12.3));
+
+        assert!(plan(&s, "BTCUSDT", 0.00005, Some(&btc())).is_err());
+        assert!(plan(&s, "BTCUSDT", 0.01, None).is_err());
+    }
+
+    /// Accepts every order except the listed ones (1-based), recording what
+    /// it was sent
+    struct MockLegs {
+        fail: Vec<usize>,
+        sent: Mutex<Vec<(Exchange, String, f64, bool)>>,
+    }
+
+    impl MockLegs {
+        fn failing(fail: &[usize]) -> Self {
+            Self {
+                fail: fail.to_vec(),
+                sent: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn sent(&self) -> Vec<(Exchange, String, f64, bool)> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Legs for MockLegs {
+        async fn send(&self, req: TradeRequest) -> Result<TradeResponse, TradeError> {
+            let n = {
+                let mut sent = self.sent.lock().unwrap();
+                sent.push((req.exchange, req.side.clone(), req.size, req.reduce_only));
+                sent.len()
+            };
+            if self.fail.contains(&n) {
+                return Err(TradeError::Other("venue said no".into()));
+            }
+            Ok(TradeResponse {
+                success: true,
+                exchange: req.exchange,
+                symbol: req.symbol,
+                side: req.side,
+                order_type: req.order_type,
+                price: req.price,
+                size: req.size,
+                reduce_only: req.reduce_only,
+                signal_price: req.signal_price,
+                mid_at_submit: None,
+                order_id: None,
+                client_order_id: None,
+                data: json!({}),
+            })
+        }
+    }
+
+    fn btc_plan() -> Plan {
+        let s = best_spread(&quotes(), 5.0).unwrap();
+        plan(&s, "BTCUSDT", 0.01, Some(&btc())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_real_spread_opens_both_legs() {
+        let legs = MockLegs::failing(&[]);
+        assert_eq!(open_legs(&legs, &btc_plan()).await, Opened::Hedged);
+        assert_eq!(
+            legs.sent(),
+            vec![
+                (Exchange::Binance, "buy".into(), 0.01, false),
+                (Exchange::Blowfin, "sell".into(), 10.0, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_failed_second_leg_flattens_the_first() {
+        let legs = MockLegs::failing(&[2]);
+        assert!(matches!(
+            open_legs(&legs, &btc_plan()).await,
+            Opened::Unwound(_)
+        ));
+        assert_eq!(
+            legs.sent()[2],
+            (Exchange::Binance, "sell".into(), 0.01, true),
+            "the unwind is the buy leg reversed, reduce-only"
+        );
+
+        let legs = MockLegs::failing(&[2, 3]);
+        assert!(matches!(
+            open_legs(&legs, &btc_plan()).await,
+            Opened::Broken(_)
+        ));
+
+        let legs = MockLegs::failing(&[1]);
+        assert!(matches!(
+            open_legs(&legs, &btc_plan()).await,
+            Opened::NotFilled(_)
+        ));
+        assert_eq!(legs.sent().len(), 1, "no sell without a buy");
+    }
+
+    #[tokio::test]
+    async fn closing_reverses_both_legs_reduce_only() {
+        let legs = MockLegs::failing(&[]);
+        close_legs(&legs, &btc_plan()).await.unwrap();
+        assert_eq!(
+            legs.sent(),
+            vec![
+                (Exchange::Binance, "sell".into(), 0.01, true),
+                (Exchange::Blowfin, "buy".into(), 10.0, true),
+            ]
+        );
+        let legs = MockLegs::failing(&[2]);
+        let err = close_legs(&legs, &btc_plan()).await.unwrap_err();
+        assert!(err.contains("long leg: closed"), "{err}");
+    }
+
+    fn hedge(entry_edge_bps: f64) -> ArbPosition {
+        ArbPosition {
+            position_id: Uuid::nil(),
+            user_id: 1,
+            symbol: "BTCUSDT".into(),
+            buy_on: "binance".into(),
+            sell_on: "blowfin".into(),
+            qty: 0.01,
+            entry_buy: 60_000.0,
+            entry_sell: 60_180.0,
+            entry_edge_bps,
+            status: "open".into(),
+            exit_buy: None,
+            exit_sell: None,
+            note: None,
+            opened_at: Utc::now(),
+            closed_at: None,
+        }
+    }
+
+    #[test]
+    fn a_hedge_closes_once_the_gap_has_come_back() {
+        let pos = hedge(20.0);
+        // the gap is still open: unwinding now costs more than entry made
+        assert!(!should_close(&pos, &quotes()));
+
+        // the venues have converged: exit costs fees and the spread only
+        let converged = vec![
+            quote(Exchange::Blowfin, 60_050.0, 60_051.0, 6.0),
+            quote(Exchange::Binance, 60_050.0, 60_051.0, 4.0),
+        ];
+        let exit = exit_edge_bps(&pos, &converged).unwrap();
+        assert!((-11.0..-10.0).contains(&exit), "{exit}");
+        assert!(should_close(&pos, &converged));
+        // without both venues' books there's nothing to decide on
+        assert!(!should_close(&pos, &converged[..1]));
+    }
+
+    #[test]
+    fn quotes_are_kept_per_venue_and_sanity_checked() {
+        record_quote(Exchange::Blowfin, "ETH-USDT-SWAP", 3_001.0, 3_002.0);
+        record_quote(Exchange::Binance, "ethusdt", 2_999.0, 3_000.0);
+        record_quote(Exchange::Binance, "ETHUSDT", 3_005.0, 3_000.0); // crossed
+        assert_eq!(
+            fresh_tops("ETH-USDT"),
+            vec![
+                (Exchange::Binance, 2_999.0, 3_000.0),
+                (Exchange::Blowfin, 3_001.0, 3_002.0),
+            ]
+        );
+        assert!(fresh_tops("SOLUSDT").is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_gap_between_the_two_feeds_is_traded_end_to_end() {
+        // what BlowFin's depth stream and Binance's book ticker report
+        record_quote(Exchange::Blowfin, "BTC-USDT-SWAP", 60_180.0, 60_181.0);
+        record_quote(Exchange::Binance, "BTCUSDT", 59_999.0, 60_000.0);
+        let quotes: Vec<VenueQuote> = fresh_tops("BTCUSDT")
+            .into_iter()
+            .map(|(e, bid, ask)| quote(e, bid, ask, 5.0))
+            .collect();
+
+        let s = best_spread(&quotes, 5.0).expect("a 30 bps gap clears 10 bps of fees");
+        assert_eq!(
+            (s.buy_on, s.sell_on),
+            (Exchange::Binance, Exchange::Blowfin)
+        );
+        let p = plan(&s, "BTCUSDT", 0.02, Some(&btc())).unwrap();
+        let legs = MockLegs::failing(&[]);
+        assert_eq!(open_legs(&legs, &p).await, Opened::Hedged);
+        assert_eq!(
+            legs.sent(),
+            vec![
+                (Exchange::Binance, "buy".into(), 0.02, false),
+                (Exchange::Blowfin, "sell".into(), 20.0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_are_validated() {
+        let req = |min_edge_bps, qty| SettingsReq {
+            min_edge_bps,
+            qty,
+            mode: Mode::Alert,
+            enabled: true,
+        };
+        assert!(req(5.0, 0.01).validate().is_ok());
+        assert!(req(0.0, 0.01).validate().is_err());
+        assert!(req(5_000.0, 0.01).validate().is_err());
+        assert!(req(5.0, 0.0).validate().is_err());
+        assert!(req(f64::NAN, 0.01).validate().is_err());
+        assert_eq!(normalize("btc/usdt").unwrap(), "BTCUSDT");
+        assert!(normalize("BTC;DROP").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+//! Fire-and-forget audit trail (`audit_log`), written through the batched
+//! writer so request handlers never wait on it.
+//!
+//! ```ignore
+//! audit::init(pg_pool.clone());                 // once, in main
+//! audit::record(Some(uid), "strategy.start", json!({ "id": id }));
+//! ```
+
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+use sqlx::query_builder::Separated;
+use sqlx::{PgPool, Postgres};
+
+use crate::db::batch::{BatchConfig, BatchRow, BatchWriter};
+
+static WRITER: OnceCell<BatchWriter<AuditRow>> = OnceCell::new();
+
+#[derive(Debug, Clone)]
+pub struct AuditRow {
+    pub user_id: Option<i64>,
+    pub action: String,
+    pub details: Value,
+}
+
+impl BatchRow for AuditRow {
+    const TABLE: &'static str = "audit_log";
+    const COLUMNS: &'static [&'static str] = &["user_id", "action", "details"];
+
+    fn bind_row<'args>(&self, b: &mut Separated<'_, 'args, Postgres, &'static str>) {
+        b.push_bind(self.user_id)
+            .push_bind(self.action.clone())
+            .push_bind(self.details.clone());
+    }
+}
+
+/// Start the audit writer (idempotent).
+pub fn init(pool: PgPool) {
+    WRITER.get_or_init(|| BatchWriter::spawn(pool, BatchConfig::default()));
+}
+
+/// Queue an audit event; a no-op (debug-logged) before `init`.
+pub fn record(user_id: Option<i64>, action: &str, details: Value) {
+    match WRITER.get() {
+        Some(w) => {
+            w.try_push(AuditRow {
+                user_id,
+                action: action.into(),
+                details,
+            });
+        }
+        None => tracing::debug!("audit (not initialised): {action} {details}"),
+    }
+}
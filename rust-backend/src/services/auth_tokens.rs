@@ -0,0 +1,381 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Auth tokens – minting the JWTs the auth middleware checks
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Access tokens are HS256 JWTs (`sub` + `provider`, like the ones the
+//!   middleware already accepts) signed with `DISCORD_JWT_SECRET`, valid for
+//!   `AUTH_ACCESS_TTL_SECS`; being stateless they can't be revoked, only
+//!   left to expire
+//! * They're issued after a Discord OAuth code exchange, or to a trusted
+//!   service proving itself with an HMAC-signed bootstrap request
+//! * An identity linked to an account also gets a refresh token: 32 random
+//!   bytes, stored only as their SHA-256 in `auth_refresh_tokens`. Each
+//!   refresh spends the token and hands out the next one of its family;
+//!   spending one twice means it leaked, and the whole family is revoked
+//! * A refresh re-resolves the identity, so an unlinked sign-in or a
+//!   deleted account can't keep its session alive
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::middleware::auth::jwt_secret;
+use crate::services::identities::{self, IdentityRef, Provider};
+
+const DISCORD_API: &str = "https://discord.com/api";
+
+#[derive(thiserror::Error, Debug)]
+pub enum TokenError {
+    #[error("token issuance disabled: {0}")]
+    Disabled(&'static str),
+    #[error("{0}")]
+    Invalid(String),
+    #[error("refresh token invalid, expired or revoked")]
+    BadRefresh,
+    #[error("discord rejected the sign-in: {0}")]
+    Discord(String),
+    #[error("jwt: {0}")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// Discord application the OAuth code exchange runs as
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscordOAuth {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Default `redirect_uri`; must match the one the code was issued for
+    pub redirect_uri: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenConfig {
+    pub access_ttl_secs: i64,
+    pub refresh_ttl_days: i64,
+    /// `None` = no `/api/auth/discord`
+    pub discord: Option<DiscordOAuth>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    provider: String,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Issued {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    /// Only for identities linked to an account
+    pub refresh_token: Option<String>,
+    pub user_id: Option<i64>,
+}
+
+#[derive(Debug, FromRow)]
+struct RefreshRow {
+    family_id: Uuid,
+    user_id: i64,
+    provider: String,
+    subject: String,
+}
+
+fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn new_refresh_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn mint_with(
+    secret: &[u8],
+    id: &IdentityRef,
+    ttl_secs: i64,
+    now: DateTime<Utc>,
+) -> Result<String, TokenError> {
+    let claims = Claims {
+        sub: id.subject.clone(),
+        provider: id.provider.as_str().into(),
+        iat: now.timestamp(),
+        exp: now.timestamp() + ttl_secs,
+    };
+    Ok(encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )?)
+}
+
+/// An access token for `id`, as the auth middleware expects it
+pub fn mint(id: &IdentityRef, ttl_secs: i64) -> Result<String, TokenError> {
+    let secret = jwt_secret();
+    if secret.is_empty() {
+        return Err(TokenError::Disabled("DISCORD_JWT_SECRET unset"));
+    }
+    mint_with(secret.as_bytes(), id, ttl_secs, Utc::now())
+}
+
+async fn store_refresh(
+    db: &PgPool,
+    cfg: &TokenConfig,
+    family_id: Uuid,
+    user_id: i64,
+    id: &IdentityRef,
+) -> Result<String, sqlx::Error> {
+    let token = new_refresh_token();
+    sqlx::query(
+        r#"
+        INSERT INTO auth_refresh_tokens
+               (token_hash, family_id, user_id, provider, subject, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(hash(&token))
+    .bind(family_id)
+    .bind(user_id)
+    .bind(id.provider.as_str())
+    .bind(&id.subject)
+    .bind(Utc::now() + Duration::days(cfg.refresh_ttl_days))
+    .execute(db)
+    .await?;
+    Ok(token)
+}
+
+/// Access token for `id`, plus a refresh token when it signs in to an account
+pub async fn issue(db: &PgPool, cfg: &TokenConfig, id: &IdentityRef) -> Result<Issued, TokenError> {
+    let access_token = mint(id, cfg.access_ttl_secs)?;
+    let user_id = identities::resolve(db, id).await?;
+    let refresh_token = match user_id {
+        Some(uid) => Some(store_refresh(db, cfg, Uuid::new_v4(), uid, id).await?),
+        None => None,
+    };
+    Ok(Issued {
+        access_token,
+        token_type: "Bearer",
+        expires_in: cfg.access_ttl_secs,
+        refresh_token,
+        user_id,
+    })
+}
+
+async fn revoke_family(db: &PgPool, family_id: Uuid) -> Result<u64, sqlx::Error> {
+    let done = sqlx::query(
+        r#"
+        UPDATE auth_refresh_tokens
+           SET revoked_at = now()
+         WHERE family_id = $1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(family_id)
+    .execute(db)
+    .await?;
+    Ok(done.rows_affected())
+}
+
+/// Spend a refresh token for a new access + refresh token pair
+pub async fn refresh(
+    db: &PgPool,
+    cfg: &TokenConfig,
+    refresh_token: &str,
+) -> Result<Issued, TokenError> {
+    let token_hash = hash(refresh_token.trim());
+    // claiming the row (used_at) is the one step that decides, so two racing
+    // refreshes of one token can't both succeed
+    let claimed = sqlx::query_as::<_, RefreshRow>(
+        r#"
+        UPDATE auth_refresh_tokens
+           SET used_at = now()
+         WHERE token_hash = $1
+           AND used_at IS NULL AND revoked_at IS NULL AND expires_at > now()
+        RETURNING family_id, user_id, provider, subject
+        "#,
+    )
+    .bind(&token_hash)
+    .fetch_optional(db)
+    .await?;
+    let Some(row) = claimed else {
+        let spent: Option<(Uuid, i64)> = sqlx::query_as(
+            r#"
+            SELECT family_id, user_id
+              FROM auth_refresh_tokens
+             WHERE token_hash = $1 AND used_at IS NOT NULL AND revoked_at IS NULL
+            "#,
+        )
+        .bind(&token_hash)
+        .fetch_optional(db)
+        .await?;
+        if let Some((family_id, user_id)) = spent {
+            let n = revoke_family(db, family_id).await?;
+            tracing::warn!(
+                "refresh token of user {user_id} reused – revoked its family ({n} tokens)"
+            );
+        }
+        return Err(TokenError::BadRefresh);
+    };
+
+    let id = Provider::parse(&row.provider)
+        .and_then(|p| IdentityRef::new(p, &row.subject))
+        .map_err(|e| TokenError::Invalid(e.to_string()))?;
+    if identities::resolve(db, &id).await? != Some(row.user_id) {
+        revoke_family(db, row.family_id).await?;
+        return Err(TokenError::BadRefresh);
+    }
+    let access_token = mint(&id, cfg.access_ttl_secs)?;
+    let next = store_refresh(db, cfg, row.family_id, row.user_id, &id).await?;
+    Ok(Issued {
+        access_token,
+        token_type: "Bearer",
+        expires_in: cfg.access_ttl_secs,
+        refresh_token: Some(next),
+        user_id: Some(row.user_id),
+    })
+}
+
+/// Revoke the family `refresh_token` belongs to; unknown tokens are a no-op
+pub async fn revoke(db: &PgPool, refresh_token: &str) -> Result<u64, sqlx::Error> {
+    let family: Option<Uuid> =
+        sqlx::query_scalar("SELECT family_id FROM auth_refresh_tokens WHERE token_hash = $1")
+            .bind(hash(refresh_token.trim()))
+            .fetch_optional(db)
+            .await?;
+    match family {
+        Some(f) => revoke_family(db, f).await,
+        None => Ok(0),
+    }
+}
+
+/// Sign the account out everywhere (refresh-wise)
+pub async fn revoke_all(db: &PgPool, user_id: i64) -> Result<u64, sqlx::Error> {
+    let done = sqlx::query(
+        r#"
+        UPDATE auth_refresh_tokens
+           SET revoked_at = now()
+         WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(db)
+    .await?;
+    Ok(done.rows_affected())
+}
+
+#[derive(Deserialize)]
+struct DiscordToken {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct DiscordUser {
+    id: String,
+}
+
+/// Trade an OAuth authorization code for the Discord identity behind it
+pub async fn discord_identity(
+    oauth: &DiscordOAuth,
+    code: &str,
+    redirect_uri: Option<&str>,
+) -> Result<IdentityRef, TokenError> {
+    let http = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| TokenError::Discord(e.to_string()))?;
+    let form = [
+        ("client_id", oauth.client_id.as_str()),
+        ("client_secret", oauth.client_secret.as_str()),
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", redirect_uri.unwrap_or(&oauth.redirect_uri)),
+    ];
+    let resp = http
+        .post(format!("{DISCORD_API}/oauth2/token"))
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| TokenError::Discord(e.to_string()))?;
+    if !resp.status().is_success() {
+        return Err(TokenError::Discord(format!(
+            "code exchange: {}",
+            resp.status()
+        )));
+    }
+    let token: DiscordToken = resp
+        .json()
+        .await
+        .map_err(|e| TokenError::Discord(e.to_string()))?;
+
+    let user: DiscordUser = http
+        .get(format!("{DISCORD_API}/users/@me"))
+        .bearer_auth(&token.access_token)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| TokenError::Discord(e.to_string()))?
+        .json()
+        .await
+        .map_err(|e| TokenError::Discord(e.to_string()))?;
+    IdentityRef::new(Provider::Discord, &user.id).map_err(|e| TokenError::Discord(e.to_string()))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+
+    #[test]
+    fn minted_tokens_carry_identity_and_expiry() {
+        let id = IdentityRef::new(Provider::Email, "Trader@Example.com").unwrap();
+        let now = Utc::now();
+        let jwt = mint_with(b"k3y", &id, 900, now).unwrap();
+
+        let claims = decode::<Claims>(
+            &jwt,
+            &DecodingKey::from_secret(b"k3y"),
+            &Validation::new(Algorithm::HS256),
+        )
+        .unwrap()
+        .claims;
+        assert_eq!(claims.sub, "trader@example.com");
+        assert_eq!(claims.provider, "email");
+        assert_eq!(claims.exp - claims.iat, 900);
+
+        // another key, or an expired token, doesn't pass
+        assert!(decode::<Claims>(
+            &jwt,
+            &DecodingKey::from_secret(b"other"),
+            &Validation::new(Algorithm::HS256),
+        )
+        .is_err());
+        let stale = mint_with(b"k3y", &id, 900, now - Duration::hours(1)).unwrap();
+        assert!(decode::<Claims>(
+            &stale,
+            &DecodingKey::from_secret(b"k3y"),
+            &Validation::new(Algorithm::HS256),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn refresh_tokens_are_random_and_stored_hashed() {
+        let a = new_refresh_token();
+        let b = new_refresh_token();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 64);
+        assert_eq!(hash(&a).len(), 64);
+        assert_ne!(hash(&a), a);
+        assert_eq!(hash(&a), hash(&a));
+    }
+}
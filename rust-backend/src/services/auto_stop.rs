@@ -0,0 +1,175 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Performance-based auto-stop
+//! ──────────────────────────────────────────────────────────────────────────
+//! An optional guardrail per strategy (`user_strategies.auto_stop`):
+//!
+//! * `max_consecutive_losses` – trip after this many losing closes in a row
+//! * `max_drawdown_pct`       – trip once the strategy's realised equity
+//!   (`allocated_capital + Σ strategy_pnl`) is this far below its peak;
+//!   needs an allocation, since there is no capital to measure against
+//!   otherwise
+//!
+//! Evaluated off the `strategy_pnl` ledger after every fill that realised
+//! PnL (`allocation::record_fill`), so strategies need no code of their own.
+//! A tripped guard disables the strategy with the reason in `status_error`,
+//! audit-logs it and notifies the user; the scheduler reaps the task on its
+//! next reconcile. Open positions are left alone.
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use metrics::increment_counter;
+use serde_json::json;
+use sqlx::{types::Json, FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::services::{audit, notify};
+
+// the config (and its `validate`) is shared with API clients through
+// `rustraptor-types`
+pub use rustraptor_types::strategy::AutoStop;
+
+/// Why a strategy guarded by `stop` must stop after realising `pnls`
+/// (oldest first), if it must
+pub fn breach(stop: &AutoStop, pnls: &[f64], capital: Option<f64>) -> Option<String> {
+    if let Some(max) = stop.max_consecutive_losses {
+        let streak = pnls.iter().rev().take_while(|p| **p < 0.0).count();
+        if streak >= max as usize {
+            return Some(format!("auto-stopped: {streak} losing trades in a row"));
+        }
+    }
+    if let (Some(max), Some(capital)) = (stop.max_drawdown_pct, capital) {
+        let dd = drawdown_pct(capital, pnls);
+        if dd >= max {
+            return Some(format!(
+                "auto-stopped: drawdown {dd:.1}% exceeds the {max}% limit"
+            ));
+        }
+    }
+    None
+}
+
+/// Current drop of `capital + cumulative pnl` below its running peak, in
+/// percent
+pub fn drawdown_pct(capital: f64, pnls: &[f64]) -> f64 {
+    let (mut equity, mut peak) = (capital, capital);
+    for p in pnls {
+        equity += p;
+        peak = peak.max(equity);
+    }
+    if peak <= 0.0 {
+        return 100.0;
+    }
+    ((peak - equity) / peak * 100.0).clamp(0.0, 100.0)
+}
+
+#[derive(FromRow)]
+struct Guarded {
+    user_id: i64,
+    strategy: String,
+    auto_stop: Json<AutoStop>,
+    capital: Option<f64>,
+}
+
+/// Evaluate `strategy_id`'s guard and disable it on a breach; returns the
+/// reason when this call stopped it
+pub async fn check(db: &PgPool, strategy_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query_as::<_, Guarded>(
+        r#"
+        SELECT user_id, strategy, auto_stop, allocated_capital::float8 AS capital
+          FROM user_strategies
+         WHERE strategy_id = $1 AND status = 'enabled' AND auto_stop IS NOT NULL
+        "#,
+    )
+    .bind(strategy_id)
+    .fetch_optional(db)
+    .await?;
+    let Some(g) = row else {
+        return Ok(None);
+    };
+
+    let pnls: Vec<f64> = sqlx::query_scalar(
+        "SELECT pnl::float8 FROM strategy_pnl WHERE strategy_id = $1 ORDER BY entry_id",
+    )
+    .bind(strategy_id)
+    .fetch_all(db)
+    .await?;
+    let Some(reason) = breach(&g.auto_stop, &pnls, g.capital) else {
+        return Ok(None);
+    };
+
+    let stopped = sqlx::query(
+        r#"
+        UPDATE user_strategies
+           SET status = 'disabled', status_error = $2
+         WHERE strategy_id = $1 AND status = 'enabled'
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(&reason)
+    .execute(db)
+    .await?
+    .rows_affected()
+        == 1;
+    if !stopped {
+        return Ok(None);
+    }
+
+    tracing::warn!("strategy {strategy_id}: {reason}");
+    increment_counter!("strategy_auto_stops_total");
+    let details = json!({ "strategy_id": strategy_id, "reason": reason });
+    audit::record(Some(g.user_id), "strategy.auto_stop", details.clone());
+    notify::send(
+        g.user_id,
+        "strategy.auto_stop",
+        &format!("Your {} strategy was stopped – {reason}", g.strategy),
+        details,
+    );
+    Ok(Some(reason))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(losses: Option<u32>, dd: Option<f64>) -> AutoStop {
+        AutoStop {
+            max_consecutive_losses: losses,
+            max_drawdown_pct: dd,
+        }
+    }
+
+    #[test]
+    fn losing_streak_trips_only_when_unbroken() {
+        let g = guard(Some(3), None);
+        assert_eq!(breach(&g, &[-1.0, -1.0, 5.0, -1.0, -1.0], None), None);
+        let why = breach(&g, &[5.0, -1.0, -2.0, -0.5], None).unwrap();
+        assert!(why.contains("3 losing trades"), "{why}");
+        assert_eq!(breach(&g, &[], None), None);
+    }
+
+    #[test]
+    fn drawdown_is_measured_from_the_equity_peak() {
+        // 1000 → 1200 peak → 1020: 15 % down
+        let pnls = [200.0, -100.0, -80.0];
+        assert!((drawdown_pct(1_000.0, &pnls) - 15.0).abs() < 1e-9);
+        assert!(breach(&guard(None, Some(15.0)), &pnls, Some(1_000.0)).is_some());
+        assert_eq!(breach(&guard(None, Some(20.0)), &pnls, Some(1_000.0)), None);
+        // no allocation, nothing to measure against
+        assert_eq!(breach(&guard(None, Some(1.0)), &pnls, None), None);
+        assert_eq!(drawdown_pct(100.0, &[-150.0]), 100.0);
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(guard(Some(3), Some(10.0)).validate().is_ok());
+        assert!(AutoStop::default().validate().is_ok());
+        assert!(guard(Some(0), None).validate().is_err());
+        assert!(guard(None, Some(0.0)).validate().is_err());
+        assert!(guard(None, Some(100.0)).validate().is_err());
+        let bad: Result<AutoStop, _> = serde_json::from_value(json!({ "max_losses": 3 }));
+        assert!(bad.is_err(), "typos are rejected");
+    }
+}
@@ -0,0 +1,411 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Backtest results
+//! ──────────────────────────────────────────────────────────────────────────
+//! * A finished run (strategy, params, data range, trade list) is stored in
+//!   `backtest_runs` by [`record`]; its [`Metrics`] are derived from the
+//!   trades there so every run is scored the same way
+//! * [`list`] returns summaries (no trades) newest first, [`get`] the full run
+//! * [`compare`] lines two runs up metric by metric (`b - a`) and lists the
+//!   params that differ, for side-by-side views
+//! * Randomness (slippage draws, Monte-Carlo paths) comes from [`rng`] –
+//!   ChaCha8, portable across platforms and releases – seeded with the seed
+//!   stored on the run, so any run can be reproduced exactly; sub-runs
+//!   (walk-forward windows) get their own seed from [`derive_seed`]
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Upper bound for `list`
+pub const MAX_LIST: i64 = 200;
+/// Seeds stay below 2^53 so they survive JSON clients that parse numbers
+/// as doubles
+const SEED_MASK: u64 = (1 << 53) - 1;
+
+pub type BacktestRng = rand_chacha::ChaCha8Rng;
+
+/// Fresh seed for a run that wasn't given one
+pub fn new_seed() -> u64 {
+    rand::random::<u64>() & SEED_MASK
+}
+
+pub fn rng(seed: u64) -> BacktestRng {
+    BacktestRng::seed_from_u64(seed)
+}
+
+fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Seed of sub-run `index`; independent of how many sub-runs there are or
+/// the order they're evaluated in
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(index)) & SEED_MASK
+}
+
+/// One closed round trip
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BacktestTrade {
+    pub entry_ts: DateTime<Utc>,
+    pub exit_ts: DateTime<Utc>,
+    /// `long` / `short`
+    pub side: String,
+    pub entry: f64,
+    pub exit: f64,
+    pub qty: f64,
+    /// Net of fees and slippage
+    pub pnl: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub start_equity: f64,
+    pub end_equity: f64,
+    pub net_pnl: f64,
+    pub return_pct: f64,
+    pub trades: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+    /// Gross profit / gross loss; `None` without losing trades
+    pub profit_factor: Option<f64>,
+    /// Deepest peak-to-trough drop of the trade-by-trade equity curve
+    pub max_drawdown_pct: f64,
+    /// Mean / std-dev of per-trade returns, ×√252
+    pub sharpe: f64,
+}
+
+impl Metrics {
+    pub fn compute(start_equity: f64, trades: &[BacktestTrade]) -> Self {
+        let mut equity = start_equity;
+        let mut peak = start_equity;
+        let mut max_dd: f64 = 0.0;
+        let mut rets = Vec::with_capacity(trades.len());
+        let (mut gross_win, mut gross_loss, mut wins) = (0.0, 0.0, 0);
+
+        for t in trades {
+            if equity != 0.0 {
+                rets.push(t.pnl / equity);
+            }
+            equity += t.pnl;
+            if t.pnl > 0.0 {
+                wins += 1;
+                gross_win += t.pnl;
+            } else {
+                gross_loss -= t.pnl;
+            }
+            peak = peak.max(equity);
+            if peak > 0.0 {
+                max_dd = max_dd.max((peak - equity) / peak * 100.0);
+            }
+        }
+
+        let n = trades.len() as u32;
+        let sharpe = if rets.len() > 1 {
+            let mean = rets.iter().sum::<f64>() / rets.len() as f64;
+            let var =
+                rets.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (rets.len() - 1) as f64;
+            mean / var.sqrt().max(1e-6) * 252_f64.sqrt()
+        } else {
+            0.0
+        };
+        Self {
+            start_equity,
+            end_equity: equity,
+            net_pnl: equity - start_equity,
+            return_pct: if start_equity != 0.0 {
+                (equity - start_equity) / start_equity * 100.0
+            } else {
+                0.0
+            },
+            trades: n,
+            wins,
+            win_rate: if n > 0 { wins as f64 / n as f64 } else { 0.0 },
+            profit_factor: (gross_loss > 0.0).then(|| gross_win / gross_loss),
+            max_drawdown_pct: max_dd,
+            sharpe,
+        }
+    }
+}
+
+/// What a backtest produced, before it's stored
+#[derive(Debug, Clone)]
+pub struct NewRun {
+    pub strategy: String,
+    pub symbol: String,
+    pub interval: String,
+    pub params: Value,
+    pub data_from: DateTime<Utc>,
+    pub data_to: DateTime<Utc>,
+    pub start_equity: f64,
+    pub trades: Vec<BacktestTrade>,
+    /// `None` for runs that draw no random numbers
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct RunSummary {
+    pub run_id: Uuid,
+    pub strategy: String,
+    pub symbol: String,
+    pub interval: String,
+    pub params: Value,
+    pub data_from: DateTime<Utc>,
+    pub data_to: DateTime<Utc>,
+    pub metrics: Value,
+    /// Feed to [`rng`] to reproduce the run
+    pub seed: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunDetail {
+    #[serde(flatten)]
+    pub summary: RunSummary,
+    pub trades: Value,
+}
+
+/// Stores the run and returns its id
+pub async fn record(db: &PgPool, user_id: i64, run: &NewRun) -> Result<Uuid, sqlx::Error> {
+    let metrics = Metrics::compute(run.start_equity, &run.trades);
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO backtest_runs
+              (user_id, strategy, symbol, interval, params, data_from, data_to, metrics, trades,
+               seed)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING run_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(&run.strategy)
+    .bind(&run.symbol)
+    .bind(&run.interval)
+    .bind(&run.params)
+    .bind(run.data_from)
+    .bind(run.data_to)
+    .bind(sqlx::types::Json(&metrics))
+    .bind(sqlx::types::Json(&run.trades))
+    .bind(run.seed.map(|s| (s & SEED_MASK) as i64))
+    .fetch_one(db)
+    .await
+}
+
+/// The caller's runs, newest first, optionally for one strategy
+pub async fn list(
+    db: &PgPool,
+    user_id: i64,
+    strategy: Option<&str>,
+    limit: i64,
+) -> Result<Vec<RunSummary>, sqlx::Error> {
+    sqlx::query_as::<_, RunSummary>(
+        r#"
+        SELECT run_id, strategy, symbol, interval, params,
+               data_from, data_to, metrics, seed, created_at
+          FROM backtest_runs
+         WHERE user_id = $1 AND ($2::TEXT IS NULL OR strategy = $2)
+         ORDER BY created_at DESC
+         LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(strategy)
+    .bind(limit.clamp(1, MAX_LIST))
+    .fetch_all(db)
+    .await
+}
+
+/// Only the owner can read a run
+pub async fn get(
+    db: &PgPool,
+    user_id: i64,
+    run_id: Uuid,
+) -> Result<Option<RunDetail>, sqlx::Error> {
+    #[derive(FromRow)]
+    struct Row {
+        #[sqlx(flatten)]
+        summary: RunSummary,
+        trades: Value,
+    }
+    let row = sqlx::query_as::<_, Row>(
+        r#"
+        SELECT run_id, strategy, symbol, interval, params,
+               data_from, data_to, metrics, seed, created_at, trades
+          FROM backtest_runs
+         WHERE run_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(run_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(row.map(|r| RunDetail {
+        summary: r.summary,
+        trades: r.trades,
+    }))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricDiff {
+    pub metric: String,
+    pub a: Option<f64>,
+    pub b: Option<f64>,
+    /// `b - a` when both are set
+    pub delta: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamDiff {
+    pub param: String,
+    pub a: Option<Value>,
+    pub b: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub a: RunSummary,
+    pub b: RunSummary,
+    pub metrics: Vec<MetricDiff>,
+    /// Only params whose values differ
+    pub params: Vec<ParamDiff>,
+}
+
+/// Keys of two JSON objects, sorted and deduplicated
+fn keys<'a>(a: &'a Value, b: &'a Value) -> BTreeSet<&'a str> {
+    [a, b]
+        .into_iter()
+        .filter_map(Value::as_object)
+        .flat_map(|o| o.keys().map(String::as_str))
+        .collect()
+}
+
+/// Numeric metrics side by side, in name order
+pub fn diff_metrics(a: &Value, b: &Value) -> Vec<MetricDiff> {
+    keys(a, b)
+        .into_iter()
+        .filter_map(|k| {
+            let (va, vb) = (
+                a.get(k).and_then(Value::as_f64),
+                b.get(k).and_then(Value::as_f64),
+            );
+            (va.is_some() || vb.is_some()).then(|| MetricDiff {
+                metric: k.to_string(),
+                a: va,
+                b: vb,
+                delta: va.zip(vb).map(|(x, y)| y - x),
+            })
+        })
+        .collect()
+}
+
+/// Top-level params that are missing on one side or differ
+pub fn diff_params(a: &Value, b: &Value) -> Vec<ParamDiff> {
+    keys(a, b)
+        .into_iter()
+        .filter(|k| a.get(k) != b.get(k))
+        .map(|k| ParamDiff {
+            param: k.to_string(),
+            a: a.get(k).cloned(),
+            b: b.get(k).cloned(),
+        })
+        .collect()
+}
+
+pub fn compare(a: RunSummary, b: RunSummary) -> Comparison {
+    Comparison {
+        metrics: diff_metrics(&a.metrics, &b.metrics),
+        params: diff_params(&a.params, &b.params),
+        a,
+        b,
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trade(pnl: f64) -> BacktestTrade {
+        BacktestTrade {
+            entry_ts: Utc::now(),
+            exit_ts: Utc::now(),
+            side: "long".into(),
+            entry: 100.0,
+            exit: 100.0 + pnl,
+            qty: 1.0,
+            pnl,
+        }
+    }
+
+    #[test]
+    fn metrics_follow_the_trade_list() {
+        let m = Metrics::compute(1_000.0, &[trade(100.0), trade(-220.0), trade(50.0)]);
+        assert_eq!(m.trades, 3);
+        assert_eq!(m.wins, 2);
+        assert!((m.net_pnl + 70.0).abs() < 1e-9);
+        assert!((m.return_pct + 7.0).abs() < 1e-9);
+        assert!((m.profit_factor.unwrap() - 150.0 / 220.0).abs() < 1e-9);
+        // peak 1100 → trough 880
+        assert!((m.max_drawdown_pct - 20.0).abs() < 1e-9);
+
+        let empty = Metrics::compute(1_000.0, &[]);
+        assert_eq!(empty.end_equity, 1_000.0);
+        assert_eq!(empty.win_rate, 0.0);
+        assert_eq!(empty.profit_factor, None);
+        assert_eq!(Metrics::compute(1_000.0, &[trade(5.0)]).profit_factor, None);
+    }
+
+    #[test]
+    fn seeded_runs_repeat_exactly() {
+        use rand::Rng;
+        let draws = |seed| {
+            let mut r = rng(seed);
+            (0..16)
+                .map(|_| r.gen_range(-0.0005..0.0005))
+                .collect::<Vec<f64>>()
+        };
+        assert_eq!(draws(42), draws(42));
+        assert_ne!(draws(42), draws(43));
+
+        let windows: Vec<u64> = (0..8).map(|i| derive_seed(42, i)).collect();
+        assert_eq!(
+            windows,
+            (0..8).map(|i| derive_seed(42, i)).collect::<Vec<_>>()
+        );
+        assert_eq!(windows.iter().collect::<BTreeSet<_>>().len(), 8);
+        assert!(windows.iter().all(|&s| s <= SEED_MASK));
+        assert!(new_seed() <= SEED_MASK);
+    }
+
+    #[test]
+    fn runs_diff_by_metric_and_param() {
+        let a = json!({ "sharpe": 1.0, "trades": 10, "profit_factor": null });
+        let b = json!({ "sharpe": 1.5, "trades": 8, "profit_factor": 2.0 });
+        let d = diff_metrics(&a, &b);
+        assert_eq!(d.len(), 3);
+        assert_eq!(d[0].metric, "profit_factor");
+        assert_eq!((d[0].a, d[0].b, d[0].delta), (None, Some(2.0), None));
+        assert_eq!(d[1].delta, Some(0.5));
+        assert_eq!(d[2].delta, Some(-2.0));
+
+        let p = diff_params(
+            &json!({ "fast": 20, "slow": 100, "qty": 0.01 }),
+            &json!({ "fast": 10, "slow": 100, "stop": 0.02 }),
+        );
+        let names: Vec<_> = p.iter().map(|d| d.param.as_str()).collect();
+        assert_eq!(names, ["fast", "qty", "stop"]);
+        assert_eq!(p[2].a, None);
+    }
+}
@@ -0,0 +1,300 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Backtest CPU pool & progress
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Walk-forward windows and parameter grid points are independent, so they
+//!   run on a dedicated rayon pool (`BACKTEST_THREADS`, default: all cores
+//!   but one) – CPU-heavy work never lands on the tokio workers serving HTTP
+//! * Async callers hand a whole job to the pool with [`run`]; inside it,
+//!   [`par_map`] spreads items over the pool and keeps input order, so a
+//!   seeded run (`backtest::derive_seed` per item) gives the same result no
+//!   matter how the work was scheduled
+//! * Jobs registered with [`track`] count finished items; [`status`] reports
+//!   percent done and an ETA for `GET /api/backtests/progress/{job}`.
+//!   Finished jobs stay visible for `KEEP_FINISHED`
+//! * A cancelled job's [`Progress`] says so; long jobs should check
+//!   [`Progress::is_cancelled`] between items and bail out early
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use once_cell::sync::{Lazy, OnceCell};
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// Largest parameter grid a single job may expand to
+pub const MAX_GRID: usize = 10_000;
+const KEEP_FINISHED: Duration = Duration::from_secs(600);
+
+static THREADS: OnceCell<usize> = OnceCell::new();
+static POOL: Lazy<rayon::ThreadPool> = Lazy::new(|| {
+    let threads = THREADS.get().copied().unwrap_or_else(default_threads);
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("backtest-{i}"))
+        .build()
+        .expect("backtest pool")
+});
+
+static JOBS: Lazy<DashMap<Uuid, Arc<Progress>>> = Lazy::new(DashMap::new);
+
+fn default_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get().saturating_sub(1))
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Pool size; 0 = default. Only the first call before the pool's first use
+/// counts
+pub fn init(threads: usize) {
+    let _ = THREADS.set(if threads == 0 {
+        default_threads()
+    } else {
+        threads
+    });
+}
+
+/// Run `job` on the backtest pool without blocking the calling task
+pub async fn run<R: Send + 'static>(job: impl FnOnce() -> R + Send + 'static) -> R {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    POOL.spawn(move || {
+        let _ = tx.send(job());
+    });
+    rx.await.expect("backtest job panicked")
+}
+
+/// `f` over every item in parallel, results in input order; ticks `progress`
+/// once per item
+pub fn par_map<T, R, F>(items: &[T], progress: Option<&Progress>, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Sync + Send,
+{
+    POOL.install(|| {
+        items
+            .par_iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let r = f(i, item);
+                if let Some(p) = progress {
+                    p.tick();
+                }
+                r
+            })
+            .collect()
+    })
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum GridError {
+    #[error("param `{0}` has no values")]
+    Empty(String),
+    #[error("grid has {0} points (max {MAX_GRID})")]
+    TooLarge(usize),
+}
+
+/// Every combination of `space` as a params object, keys in name order
+pub fn grid(space: &BTreeMap<String, Vec<Value>>) -> Result<Vec<Value>, GridError> {
+    let mut size: usize = 1;
+    for (k, vs) in space {
+        if vs.is_empty() {
+            return Err(GridError::Empty(k.clone()));
+        }
+        size = size.saturating_mul(vs.len());
+    }
+    if size > MAX_GRID {
+        return Err(GridError::TooLarge(size));
+    }
+
+    let mut points = vec![Map::new()];
+    for (k, vs) in space {
+        points = points
+            .into_iter()
+            .flat_map(|p| {
+                vs.iter().map(move |v| {
+                    let mut p = p.clone();
+                    p.insert(k.clone(), v.clone());
+                    p
+                })
+            })
+            .collect();
+    }
+    Ok(points.into_iter().map(Value::Object).collect())
+}
+
+// ─── Progress ────────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub struct Progress {
+    user_id: i64,
+    total: AtomicU64,
+    done: AtomicU64,
+    cancelled: AtomicBool,
+    started: Instant,
+    finished_at: OnceCell<Instant>,
+}
+
+impl Progress {
+    fn new(user_id: i64, total: u64) -> Self {
+        Self {
+            user_id,
+            total: AtomicU64::new(total),
+            done: AtomicU64::new(0),
+            cancelled: AtomicBool::new(false),
+            started: Instant::now(),
+            finished_at: OnceCell::new(),
+        }
+    }
+
+    pub fn tick(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// For jobs that learn about more work as they go
+    pub fn add_total(&self, n: u64) {
+        self.total.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self) {
+        let _ = self.finished_at.set(Instant::now());
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn view(&self, job_id: Uuid) -> ProgressView {
+        let total = self.total.load(Ordering::Relaxed);
+        let done = self.done.load(Ordering::Relaxed).min(total);
+        let finished_at = self.finished_at.get();
+        let finished = finished_at.is_some();
+        let elapsed = finished_at.map_or_else(|| self.started.elapsed(), |t| *t - self.started);
+        let percent = if finished || total == 0 {
+            100.0
+        } else {
+            done as f64 / total as f64 * 100.0
+        };
+        let eta_ms = (!finished && done > 0).then(|| {
+            (elapsed.as_millis() as f64 / done as f64 * (total - done) as f64).round() as u64
+        });
+        ProgressView {
+            job_id,
+            done,
+            total,
+            percent,
+            finished,
+            elapsed_ms: elapsed.as_millis() as u64,
+            eta_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProgressView {
+    pub job_id: Uuid,
+    pub done: u64,
+    pub total: u64,
+    pub percent: f64,
+    pub finished: bool,
+    pub elapsed_ms: u64,
+    /// Linear extrapolation; `None` until the first item is done
+    pub eta_ms: Option<u64>,
+}
+
+/// Register a job of `total` items; call `finish` on the result when done
+pub fn track(job_id: Uuid, user_id: i64, total: u64) -> Arc<Progress> {
+    JOBS.retain(|_, p| {
+        p.finished_at
+            .get()
+            .is_none_or(|t| t.elapsed() < KEEP_FINISHED)
+    });
+    let p = Arc::new(Progress::new(user_id, total));
+    JOBS.insert(job_id, p.clone());
+    p
+}
+
+/// Progress of one of the user's jobs
+pub fn status(job_id: Uuid, user_id: i64) -> Option<ProgressView> {
+    JOBS.get(&job_id)
+        .filter(|p| p.user_id == user_id)
+        .map(|p| p.view(job_id))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn par_map_keeps_order_and_counts_items() {
+        let job = Uuid::new_v4();
+        let p = track(job, 1, 100);
+        let items: Vec<u64> = (0..100).collect();
+        let out = par_map(&items, Some(&p), |i, x| {
+            assert_eq!(i as u64, *x);
+            x * 2
+        });
+        assert_eq!(out, items.iter().map(|x| x * 2).collect::<Vec<_>>());
+
+        let v = status(job, 1).unwrap();
+        assert_eq!((v.done, v.total, v.percent), (100, 100, 100.0));
+        assert!(!v.finished);
+        assert!(status(job, 2).is_none(), "other users can't see the job");
+        p.finish();
+        assert!(status(job, 1).unwrap().finished);
+    }
+
+    #[test]
+    fn progress_reports_percent_and_eta() {
+        let p = Progress::new(1, 4);
+        assert_eq!(p.view(Uuid::nil()).eta_ms, None);
+        p.tick();
+        let v = p.view(Uuid::nil());
+        assert_eq!(v.percent, 25.0);
+        assert!(v.eta_ms.is_some());
+        p.add_total(4);
+        assert_eq!(p.view(Uuid::nil()).percent, 12.5);
+    }
+
+    #[test]
+    fn grids_expand_to_every_combination() {
+        let space = BTreeMap::from([
+            ("fast".to_string(), vec![json!(10), json!(20)]),
+            ("slow".to_string(), vec![json!(50), json!(100), json!(200)]),
+        ]);
+        let g = grid(&space).unwrap();
+        assert_eq!(g.len(), 6);
+        assert_eq!(g[0], json!({ "fast": 10, "slow": 50 }));
+        assert_eq!(g[5], json!({ "fast": 20, "slow": 200 }));
+        assert_eq!(grid(&BTreeMap::new()).unwrap(), vec![json!({})]);
+
+        let empty = BTreeMap::from([("fast".to_string(), vec![])]);
+        assert_eq!(grid(&empty), Err(GridError::Empty("fast".into())));
+        let huge = BTreeMap::from([
+            ("a".to_string(), vec![json!(0); 200]),
+            ("b".to_string(), vec![json!(0); 200]),
+        ]);
+        assert_eq!(grid(&huge), Err(GridError::TooLarge(40_000)));
+    }
+
+    #[tokio::test]
+    async fn jobs_run_off_the_async_runtime() {
+        let name = run(|| std::thread::current().name().map(str::to_owned)).await;
+        assert!(name.unwrap().starts_with("backtest-"));
+    }
+}
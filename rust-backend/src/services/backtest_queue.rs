@@ -0,0 +1,489 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Backtest job queue
+//! ──────────────────────────────────────────────────────────────────────────
+//! Backtests and optimizer sweeps are [`submit`]ted here instead of being
+//! run straight away:
+//!
+//! * At most `BACKTEST_MAX_JOBS` jobs run at once (each one already spreads
+//!   over the whole `backtest_pool`), and one user never has more running
+//!   than their plan's `concurrent_backtests`
+//! * Waiting jobs start by priority, then Pro before Free, then oldest first;
+//!   a job held back by its owner's quota doesn't block anyone else's
+//! * [`cancel`] drops a waiting job; a running one has its [`Progress`]
+//!   flagged and its result thrown away once it returns
+//! * Finished jobs stay visible for `KEEP_FINISHED`; progress of a job is
+//!   also served by `backtest_pool::status`
+//!
+//! Metrics: `backtest_queue_depth` / `backtest_jobs_running` gauges,
+//! `backtest_queue_wait_seconds` and `backtest_job_seconds{outcome}`.
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use metrics::{gauge, histogram};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::services::backtest_pool::{self, Progress, ProgressView};
+use crate::services::usage::Plan;
+
+/// Waiting jobs one user may have
+pub const MAX_QUEUED_PER_USER: usize = 20;
+const KEEP_FINISHED: Duration = Duration::from_secs(600);
+
+static MAX_RUNNING: OnceCell<usize> = OnceCell::new();
+static QUEUE: Lazy<Mutex<Queue>> =
+    Lazy::new(|| Mutex::new(Queue::new(MAX_RUNNING.get().copied().unwrap_or(2))));
+
+/// Jobs running at once across all users; only the first call before the
+/// queue's first use counts
+pub fn init(max_running: usize) {
+    let _ = MAX_RUNNING.set(max_running.max(1));
+}
+
+/// What a job computes; `Err` is reported as the job's error
+pub type JobFn = Box<dyn FnOnce(&Progress) -> Result<Value, String> + Send>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum QueueError {
+    #[error("too many queued backtests (max {MAX_QUEUED_PER_USER})")]
+    TooManyQueued,
+    #[error("backtest job not found")]
+    NotFound,
+    #[error("backtest job already finished")]
+    Finished,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobView {
+    pub job_id: Uuid,
+    pub state: JobState,
+    pub priority: Priority,
+    pub submitted_at: DateTime<Utc>,
+    /// Jobs ahead of this one while it waits (0 = next in line)
+    pub queue_position: Option<usize>,
+    pub progress: ProgressView,
+    pub error: Option<String>,
+    pub result: Option<Value>,
+}
+
+struct Waiting {
+    job_id: Uuid,
+    user_id: i64,
+    /// Owner's `concurrent_backtests`
+    quota: usize,
+    key: (Priority, bool, std::cmp::Reverse<u64>),
+    job: JobFn,
+}
+
+struct Record {
+    user_id: i64,
+    priority: Priority,
+    state: JobState,
+    submitted_at: DateTime<Utc>,
+    enqueued: Instant,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+    progress: Arc<Progress>,
+    error: Option<String>,
+    result: Option<Value>,
+}
+
+struct Queue {
+    max_running: usize,
+    seq: u64,
+    waiting: Vec<Waiting>,
+    /// Running jobs per user
+    running: HashMap<i64, usize>,
+    jobs: HashMap<Uuid, Record>,
+}
+
+impl Queue {
+    fn new(max_running: usize) -> Self {
+        Self {
+            max_running,
+            seq: 0,
+            waiting: Vec::new(),
+            running: HashMap::new(),
+            jobs: HashMap::new(),
+        }
+    }
+
+    fn running_total(&self) -> usize {
+        self.running.values().sum()
+    }
+
+    fn push(
+        &mut self,
+        job_id: Uuid,
+        user_id: i64,
+        plan: Plan,
+        priority: Priority,
+        job: JobFn,
+    ) -> Result<(), QueueError> {
+        self.jobs
+            .retain(|_, r| r.finished.is_none_or(|t| t.elapsed() < KEEP_FINISHED));
+        if self.waiting.iter().filter(|w| w.user_id == user_id).count() >= MAX_QUEUED_PER_USER {
+            return Err(QueueError::TooManyQueued);
+        }
+
+        self.seq += 1;
+        self.waiting.push(Waiting {
+            job_id,
+            user_id,
+            quota: plan.limits().concurrent_backtests.max(1) as usize,
+            key: (priority, plan == Plan::Pro, std::cmp::Reverse(self.seq)),
+            job,
+        });
+        self.jobs.insert(
+            job_id,
+            Record {
+                user_id,
+                priority,
+                state: JobState::Queued,
+                submitted_at: Utc::now(),
+                enqueued: Instant::now(),
+                started: None,
+                finished: None,
+                progress: backtest_pool::track(job_id, user_id, 0),
+                error: None,
+                result: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Best waiting job whose owner is under quota, marked running
+    fn take_next(&mut self) -> Option<(Uuid, JobFn, Arc<Progress>)> {
+        if self.running_total() >= self.max_running {
+            return None;
+        }
+        let idx = self
+            .waiting
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| self.running.get(&w.user_id).copied().unwrap_or(0) < w.quota)
+            .max_by_key(|(_, w)| w.key)
+            .map(|(i, _)| i)?;
+        let w = self.waiting.swap_remove(idx);
+        *self.running.entry(w.user_id).or_default() += 1;
+
+        let rec = self
+            .jobs
+            .get_mut(&w.job_id)
+            .expect("waiting job has a record");
+        rec.state = JobState::Running;
+        rec.started = Some(Instant::now());
+        histogram!(
+            "backtest_queue_wait_seconds",
+            rec.enqueued.elapsed().as_secs_f64()
+        );
+        Some((w.job_id, w.job, rec.progress.clone()))
+    }
+
+    /// Record what a running job returned and free its slot
+    fn finish(&mut self, job_id: Uuid, out: Result<Value, String>) {
+        let Some(rec) = self.jobs.get_mut(&job_id) else {
+            return;
+        };
+        if let Some(n) = self.running.get_mut(&rec.user_id) {
+            *n -= 1;
+            if *n == 0 {
+                self.running.remove(&rec.user_id);
+            }
+        }
+        rec.finished = Some(Instant::now());
+        rec.progress.finish();
+        if rec.state != JobState::Cancelled {
+            match out {
+                Ok(v) => {
+                    rec.state = JobState::Done;
+                    rec.result = Some(v);
+                }
+                Err(e) => {
+                    rec.state = JobState::Failed;
+                    rec.error = Some(e);
+                }
+            }
+        }
+        let outcome = match rec.state {
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+            _ => "cancelled",
+        };
+        let secs = rec.started.map_or(0.0, |t| t.elapsed().as_secs_f64());
+        histogram!("backtest_job_seconds", secs, "outcome" => outcome);
+    }
+
+    fn cancel(&mut self, job_id: Uuid, user_id: i64) -> Result<JobView, QueueError> {
+        let rec = self
+            .jobs
+            .get_mut(&job_id)
+            .filter(|r| r.user_id == user_id)
+            .ok_or(QueueError::NotFound)?;
+        match rec.state {
+            JobState::Queued => {
+                self.waiting.retain(|w| w.job_id != job_id);
+                rec.state = JobState::Cancelled;
+                rec.finished = Some(Instant::now());
+                rec.progress.cancel();
+                rec.progress.finish();
+            }
+            JobState::Running => {
+                // the slot is freed once the job returns
+                rec.state = JobState::Cancelled;
+                rec.progress.cancel();
+            }
+            _ => return Err(QueueError::Finished),
+        }
+        Ok(self.view(job_id).expect("record exists"))
+    }
+
+    fn position(&self, job_id: Uuid) -> Option<usize> {
+        let key = self.waiting.iter().find(|w| w.job_id == job_id)?.key;
+        Some(self.waiting.iter().filter(|w| w.key > key).count())
+    }
+
+    fn view(&self, job_id: Uuid) -> Option<JobView> {
+        let r = self.jobs.get(&job_id)?;
+        Some(JobView {
+            job_id,
+            state: r.state,
+            priority: r.priority,
+            submitted_at: r.submitted_at,
+            queue_position: self.position(job_id),
+            progress: r.progress.view(job_id),
+            error: r.error.clone(),
+            result: r.result.clone(),
+        })
+    }
+
+    fn report(&self) {
+        gauge!("backtest_queue_depth", self.waiting.len() as f64);
+        gauge!("backtest_jobs_running", self.running_total() as f64);
+    }
+}
+
+/// Queue `job` for `user_id`; it starts as soon as a slot and the user's
+/// quota allow. Must be called from within the tokio runtime.
+pub fn submit(
+    user_id: i64,
+    plan: Plan,
+    priority: Priority,
+    job: impl FnOnce(&Progress) -> Result<Value, String> + Send + 'static,
+) -> Result<Uuid, QueueError> {
+    let job_id = Uuid::new_v4();
+    QUEUE.lock().expect("backtest queue poisoned").push(
+        job_id,
+        user_id,
+        plan,
+        priority,
+        Box::new(job),
+    )?;
+    pump();
+    Ok(job_id)
+}
+
+/// Start every job that may run now
+fn pump() {
+    let mut q = QUEUE.lock().expect("backtest queue poisoned");
+    while let Some((job_id, job, progress)) = q.take_next() {
+        tokio::spawn(async move {
+            let out = backtest_pool::run(move || {
+                std::panic::catch_unwind(AssertUnwindSafe(|| job(&progress)))
+                    .unwrap_or_else(|_| Err("backtest job panicked".into()))
+            })
+            .await;
+            QUEUE
+                .lock()
+                .expect("backtest queue poisoned")
+                .finish(job_id, out);
+            pump();
+        });
+    }
+    q.report();
+}
+
+pub fn cancel(job_id: Uuid, user_id: i64) -> Result<JobView, QueueError> {
+    let mut q = QUEUE.lock().expect("backtest queue poisoned");
+    let view = q.cancel(job_id, user_id)?;
+    q.report();
+    Ok(view)
+}
+
+/// One of the user's jobs
+pub fn status(job_id: Uuid, user_id: i64) -> Option<JobView> {
+    let q = QUEUE.lock().expect("backtest queue poisoned");
+    q.jobs
+        .get(&job_id)
+        .filter(|r| r.user_id == user_id)
+        .and_then(|_| q.view(job_id))
+}
+
+/// The user's queued, running and recently finished jobs, newest first
+pub fn list(user_id: i64) -> Vec<JobView> {
+    let q = QUEUE.lock().expect("backtest queue poisoned");
+    let mut jobs: Vec<JobView> = q
+        .jobs
+        .iter()
+        .filter(|(_, r)| r.user_id == user_id)
+        .filter_map(|(id, _)| q.view(*id))
+        .collect();
+    jobs.sort_by_key(|j| std::cmp::Reverse(j.submitted_at));
+    jobs
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn noop() -> JobFn {
+        Box::new(|_| Ok(json!(null)))
+    }
+
+    fn queue(q: &mut Queue, user_id: i64, plan: Plan, priority: Priority) -> Uuid {
+        let id = Uuid::new_v4();
+        q.push(id, user_id, plan, priority, noop()).unwrap();
+        id
+    }
+
+    fn next(q: &mut Queue) -> Option<Uuid> {
+        q.take_next().map(|(id, _, _)| id)
+    }
+
+    #[test]
+    fn jobs_start_by_priority_then_plan_then_age() {
+        let mut q = Queue::new(10);
+        let free_old = queue(&mut q, 1, Plan::Free, Priority::Normal);
+        let free_low = queue(&mut q, 2, Plan::Free, Priority::Low);
+        let pro = queue(&mut q, 3, Plan::Pro, Priority::Normal);
+        let urgent = queue(&mut q, 4, Plan::Free, Priority::High);
+        let free_new = queue(&mut q, 5, Plan::Free, Priority::Normal);
+
+        assert_eq!(q.position(urgent), Some(0));
+        assert_eq!(q.position(free_low), Some(4));
+        let order: Vec<_> = std::iter::from_fn(|| next(&mut q)).collect();
+        assert_eq!(order, vec![urgent, pro, free_old, free_new, free_low]);
+        assert_eq!(q.view(pro).unwrap().state, JobState::Running);
+        assert_eq!(q.view(pro).unwrap().queue_position, None);
+    }
+
+    #[test]
+    fn plan_quota_and_global_cap_hold_jobs_back() {
+        let mut q = Queue::new(3);
+        let a1 = queue(&mut q, 1, Plan::Free, Priority::High);
+        let a2 = queue(&mut q, 1, Plan::Free, Priority::High);
+        let b1 = queue(&mut q, 2, Plan::Pro, Priority::Normal);
+        let b2 = queue(&mut q, 2, Plan::Pro, Priority::Normal);
+        let b3 = queue(&mut q, 2, Plan::Pro, Priority::Normal);
+
+        // user 1 (free) gets one slot; user 2 isn't blocked by a2 waiting
+        assert_eq!(next(&mut q), Some(a1));
+        assert_eq!(next(&mut q), Some(b1));
+        assert_eq!(next(&mut q), Some(b2));
+        assert_eq!(next(&mut q), None, "global cap reached");
+
+        q.finish(b1, Ok(json!(1)));
+        assert_eq!(next(&mut q), Some(b3), "a2 still over its quota");
+        q.finish(a1, Err("boom".into()));
+        assert_eq!(q.view(a1).unwrap().error.as_deref(), Some("boom"));
+        q.finish(b2, Ok(json!(2)));
+        assert_eq!(next(&mut q), Some(a2));
+        assert_eq!(q.view(b1).unwrap().result, Some(json!(1)));
+    }
+
+    #[test]
+    fn cancelling_drops_waiting_jobs_and_discards_running_results() {
+        let mut q = Queue::new(1);
+        let running = queue(&mut q, 1, Plan::Pro, Priority::Normal);
+        let waiting = queue(&mut q, 1, Plan::Pro, Priority::Normal);
+        assert_eq!(next(&mut q), Some(running));
+
+        assert_eq!(q.cancel(waiting, 2).unwrap_err(), QueueError::NotFound);
+        let v = q.cancel(waiting, 1).unwrap();
+        assert_eq!(v.state, JobState::Cancelled);
+        assert!(v.progress.finished);
+        assert!(q.waiting.is_empty());
+
+        q.cancel(running, 1).unwrap();
+        assert!(q.jobs[&running].progress.is_cancelled());
+        q.finish(running, Ok(json!("late")));
+        let v = q.view(running).unwrap();
+        assert_eq!((v.state, v.result), (JobState::Cancelled, None));
+        assert_eq!(q.running_total(), 0, "slot is freed");
+        assert_eq!(q.cancel(running, 1).unwrap_err(), QueueError::Finished);
+    }
+
+    #[test]
+    fn users_cannot_flood_the_queue() {
+        let mut q = Queue::new(1);
+        for _ in 0..MAX_QUEUED_PER_USER {
+            queue(&mut q, 1, Plan::Free, Priority::Normal);
+        }
+        let err = q.push(Uuid::new_v4(), 1, Plan::Free, Priority::Normal, noop());
+        assert_eq!(err, Err(QueueError::TooManyQueued));
+        queue(&mut q, 2, Plan::Free, Priority::Normal);
+    }
+
+    #[tokio::test]
+    async fn submitted_jobs_run_on_the_backtest_pool() {
+        let user = 7_430_001;
+        let job = submit(user, Plan::Pro, Priority::Normal, |p| {
+            p.add_total(1);
+            p.tick();
+            Ok(json!(std::thread::current().name().map(str::to_owned)))
+        })
+        .unwrap();
+        let failing = submit(user, Plan::Pro, Priority::Normal, |_| panic!("bad params")).unwrap();
+
+        for _ in 0..200 {
+            let done = [job, failing]
+                .iter()
+                .all(|id| status(*id, user).is_some_and(|v| v.progress.finished));
+            if done {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let v = status(job, user).unwrap();
+        assert_eq!(v.state, JobState::Done);
+        assert!(v.result.unwrap().as_str().unwrap().starts_with("backtest-"));
+        assert_eq!(v.progress.percent, 100.0);
+        let v = status(failing, user).unwrap();
+        assert_eq!(v.state, JobState::Failed);
+        assert_eq!(v.error.as_deref(), Some("backtest job panicked"));
+
+        assert!(status(job, user + 1).is_none());
+        assert_eq!(list(user).len(), 2);
+    }
+}
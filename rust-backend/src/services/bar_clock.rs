@@ -0,0 +1,184 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Bar clock – candles close on the clock, not on the last update
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Exchange kline updates are collected per bar instead of published; the
+//!   latest one for a bar wins
+//! * At each interval boundary + `CANDLE_CLOSE_GRACE_MS` every bar closing on
+//!   that boundary goes out on the bus at once (boundary, then symbol order),
+//!   so strategies acting on bar close see exactly one bar per boundary
+//! * Updates arriving after their bar went out are late: counted in
+//!   `candle_late_updates_total{interval}` and dropped
+//! * Boundaries are aligned to the UTC epoch, as the exchanges' 1h / 4h bars
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use metrics::increment_counter;
+
+use crate::services::{
+    drain, liquidity,
+    market_data::{BusCandle, MarketBus, INTERVALS},
+    strategies::Candle,
+};
+
+/// Length of one bar of a bus interval
+pub fn interval_len(interval: &str) -> Option<chrono::Duration> {
+    match interval {
+        "1h" => Some(chrono::Duration::hours(1)),
+        "4h" => Some(chrono::Duration::hours(4)),
+        _ => None,
+    }
+}
+
+/// First boundary of a `len` grid strictly after `t`
+pub fn next_boundary(t: DateTime<Utc>, len: chrono::Duration) -> DateTime<Utc> {
+    let step = len.num_milliseconds().max(1);
+    let next = (t.timestamp_millis().div_euclid(step) + 1) * step;
+    DateTime::from_timestamp_millis(next).unwrap_or(t)
+}
+
+#[derive(Default)]
+struct State {
+    /// Bars not out yet, by close boundary, symbol and interval
+    pending: BTreeMap<(DateTime<Utc>, String, &'static str), Candle>,
+    /// Close boundary of the last bar out per symbol and interval
+    emitted: HashMap<(String, &'static str), DateTime<Utc>>,
+}
+
+pub struct BarClock {
+    grace: chrono::Duration,
+    state: Mutex<State>,
+}
+
+impl BarClock {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace: chrono::Duration::from_std(grace).unwrap_or_else(|_| chrono::Duration::zero()),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Take the latest state of the bar closing at `close`; `false` when
+    /// that bar already went out
+    pub fn update(
+        &self,
+        symbol: &str,
+        interval: &'static str,
+        close: DateTime<Utc>,
+        candle: Candle,
+    ) -> bool {
+        let mut s = self.state.lock().unwrap();
+        let key = (symbol.to_string(), interval);
+        if s.emitted.get(&key).is_some_and(|last| close <= *last) {
+            increment_counter!("candle_late_updates_total", "interval" => interval);
+            return false;
+        }
+        s.pending.insert((close, key.0, interval), candle);
+        true
+    }
+
+    /// Bars whose boundary + grace is not after `now`, in emission order
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<BusCandle> {
+        let cutoff = now - self.grace + chrono::Duration::nanoseconds(1);
+        let mut s = self.state.lock().unwrap();
+        let later = s.pending.split_off(&(cutoff, String::new(), ""));
+        let due = std::mem::replace(&mut s.pending, later);
+        due.into_iter()
+            .map(|((close, symbol, interval), candle)| {
+                s.emitted.insert((symbol.clone(), interval), close);
+                BusCandle {
+                    symbol,
+                    interval,
+                    candle,
+                }
+            })
+            .collect()
+    }
+
+    /// When the next boundary's bars fall due
+    pub fn next_due(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        INTERVALS
+            .iter()
+            .filter_map(|i| interval_len(i))
+            .map(|len| next_boundary(now - self.grace, len) + self.grace)
+            .min()
+            .unwrap_or(now + chrono::Duration::hours(1))
+    }
+}
+
+/// Publish each boundary's bars as it falls due; returns on a drain
+pub async fn run(clock: Arc<BarClock>, bus: Arc<MarketBus>) {
+    loop {
+        let now = Utc::now();
+        let wait = (clock.next_due(now) - now).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = drain::stopping() => return,
+        }
+        for bc in clock.due(Utc::now()) {
+            if bc.interval == "1h" {
+                liquidity::record_candle(&bc.symbol, &bc.candle);
+            }
+            bus.publish_candle(&bc.symbol, bc.interval, bc.candle);
+        }
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, h, m, s).unwrap()
+    }
+
+    fn bar(close: f64) -> Candle {
+        Candle {
+            close,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn boundaries_follow_the_utc_grid() {
+        let four = interval_len("4h").unwrap();
+        assert_eq!(next_boundary(at(5, 30, 0), four), at(8, 0, 0));
+        assert_eq!(next_boundary(at(8, 0, 0), four), at(12, 0, 0));
+
+        let clock = BarClock::new(Duration::from_secs(2));
+        assert_eq!(clock.next_due(at(5, 30, 0)), at(6, 0, 2));
+        // still inside the previous boundary's grace
+        assert_eq!(clock.next_due(at(6, 0, 1)), at(6, 0, 2));
+    }
+
+    #[test]
+    fn bars_go_out_once_after_the_grace() {
+        let clock = BarClock::new(Duration::from_secs(2));
+        clock.update("ETHUSDT", "1h", at(6, 0, 0), bar(1.0));
+        clock.update("BTCUSDT", "1h", at(6, 0, 0), bar(2.0));
+        clock.update("BTCUSDT", "1h", at(7, 0, 0), bar(9.0));
+        // the exchange's final update lands just after the boundary
+        assert!(clock.update("ETHUSDT", "1h", at(6, 0, 0), bar(1.5)));
+
+        assert!(clock.due(at(6, 0, 1)).is_empty());
+        let out = clock.due(at(6, 0, 2));
+        let got: Vec<(&str, f64)> = out
+            .iter()
+            .map(|b| (b.symbol.as_str(), b.candle.close))
+            .collect();
+        assert_eq!(got, [("BTCUSDT", 2.0), ("ETHUSDT", 1.5)]);
+        assert!(clock.due(at(6, 0, 3)).is_empty());
+
+        // too late for a bar that already went out
+        assert!(!clock.update("ETHUSDT", "1h", at(6, 0, 0), bar(1.7)));
+        assert_eq!(clock.due(at(7, 0, 2)).len(), 1);
+    }
+}
@@ -0,0 +1,416 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Billing – Stripe checkout + subscription webhooks
+//! ──────────────────────────────────────────────────────────────────────────
+//! * `create_checkout_session` – hosted Checkout URL for a premium plan
+//! * `verify_signature`        – `Stripe-Signature` header check (v1, HMAC)
+//! * `handle_event`            – idempotently mirrors subscription state
+//!   into `user_plans` (upgrade / downgrade)
+//!
+//! We talk to the REST API directly with `reqwest` (form-encoded, as Stripe
+//! expects) instead of pulling in a full SDK.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
+
+use crate::config::settings::Settings;
+use crate::services::{audit, referrals, usage::Plan};
+
+const STRIPE_API: &str = "https://api.stripe.com/v1";
+/// Max age of a signed webhook (Stripe's own default)
+pub const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BillingError {
+    #[error("billing not configured: {0}")]
+    NotConfigured(&'static str),
+    #[error("invalid webhook signature")]
+    BadSignature,
+    #[error("unsupported plan: {0}")]
+    UnsupportedPlan(String),
+    #[error("stripe: {0}")]
+    Stripe(String),
+    #[error("http: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// ─── Checkout ────────────────────────────────────────────────────────────
+pub async fn create_checkout_session(
+    settings: &Settings,
+    user_id: i64,
+    plan: Plan,
+) -> Result<String, BillingError> {
+    let secret = settings
+        .stripe_secret_key
+        .as_deref()
+        .ok_or(BillingError::NotConfigured("STRIPE_SECRET_KEY"))?;
+    let price = match plan {
+        Plan::Pro => settings
+            .stripe_price_pro
+            .as_deref()
+            .ok_or(BillingError::NotConfigured("STRIPE_PRICE_PRO"))?,
+        Plan::Free => return Err(BillingError::UnsupportedPlan(plan.as_str().into())),
+    };
+
+    let uid = user_id.to_string();
+    let form = [
+        ("mode", "subscription"),
+        ("line_items[0][price]", price),
+        ("line_items[0][quantity]", "1"),
+        ("success_url", settings.billing_success_url.as_str()),
+        ("cancel_url", settings.billing_cancel_url.as_str()),
+        ("client_reference_id", uid.as_str()),
+        ("subscription_data[metadata][user_id]", uid.as_str()),
+        ("subscription_data[metadata][plan]", plan.as_str()),
+    ];
+
+    let resp: Value = reqwest::Client::new()
+        .post(format!("{STRIPE_API}/checkout/sessions"))
+        .bearer_auth(secret)
+        .form(&form)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    resp.get("url")
+        .and_then(Value::as_str)
+        .map(str::to_owned)
+        .ok_or_else(|| {
+            let msg = resp
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("checkout session without url");
+            BillingError::Stripe(msg.into())
+        })
+}
+
+/// ─── Webhook signature ───────────────────────────────────────────────────
+/// `Stripe-Signature: t=<unix>,v1=<hex>[,v1=<hex>…]` over `"{t}.{payload}"`
+pub fn verify_signature(
+    payload: &[u8],
+    header: &str,
+    secret: &str,
+    now: i64,
+) -> Result<(), BillingError> {
+    let mut ts: Option<&str> = None;
+    let mut sigs = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", v)) => ts = Some(v),
+            Some(("v1", v)) => sigs.push(v),
+            _ => {}
+        }
+    }
+    let ts = ts.ok_or(BillingError::BadSignature)?;
+    let t: i64 = ts.parse().map_err(|_| BillingError::BadSignature)?;
+    // abs_diff: a hostile `t` near i64::MIN/MAX must not overflow
+    if now.abs_diff(t) > SIGNATURE_TOLERANCE_SECS.unsigned_abs() {
+        return Err(BillingError::BadSignature);
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|_| BillingError::BadSignature)?;
+    mac.update(ts.as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    let expected = mac.finalize().into_bytes();
+
+    let ok = sigs.iter().any(|s| {
+        hex::decode(s)
+            .map(|given| bool::from(expected.ct_eq(&given)))
+            .unwrap_or(false)
+    });
+    if ok {
+        Ok(())
+    } else {
+        Err(BillingError::BadSignature)
+    }
+}
+
+/// ─── Events ──────────────────────────────────────────────────────────────
+#[derive(Debug, Deserialize)]
+pub struct StripeEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub data: StripeEventData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StripeEventData {
+    pub object: Value,
+}
+
+/// Subscription state distilled from an event
+#[derive(Debug, PartialEq)]
+pub struct PlanChange {
+    pub user_id: Option<i64>,
+    pub customer: Option<String>,
+    pub subscription: Option<String>,
+    pub status: String,
+    pub plan: Plan,
+    pub period_end: Option<DateTime<Utc>>,
+}
+
+/// Statuses that keep premium features on (past_due = Stripe is retrying)
+fn is_entitled(status: &str) -> bool {
+    matches!(status, "active" | "trialing" | "past_due")
+}
+
+/// Map a Stripe event onto a plan change; `None` for events we ignore.
+pub fn plan_change(ev: &StripeEvent) -> Option<PlanChange> {
+    let o = &ev.data.object;
+    let s = |k: &str| o.get(k).and_then(Value::as_str).map(str::to_owned);
+    let uid_from = |v: Option<&Value>| {
+        v.and_then(Value::as_str)
+            .and_then(|u| u.parse::<i64>().ok())
+    };
+    let meta_plan = o
+        .pointer("/metadata/plan")
+        .and_then(Value::as_str)
+        .map(Plan::parse)
+        .unwrap_or(Plan::Pro);
+
+    match ev.kind.as_str() {
+        "checkout.session.completed" => Some(PlanChange {
+            user_id: uid_from(o.get("client_reference_id")),
+            customer: s("customer"),
+            subscription: s("subscription"),
+            status: "active".into(),
+            plan: Plan::Pro,
+            period_end: None,
+        }),
+        "customer.subscription.created" | "customer.subscription.updated" => {
+            let status = s("status")?;
+            Some(PlanChange {
+                user_id: uid_from(o.pointer("/metadata/user_id")),
+                customer: s("customer"),
+                subscription: s("id"),
+                plan: if is_entitled(&status) {
+                    meta_plan
+                } else {
+                    Plan::Free
+                },
+                status,
+                period_end: o
+                    .get("current_period_end")
+                    .and_then(Value::as_i64)
+                    .and_then(|t| DateTime::from_timestamp(t, 0)),
+            })
+        }
+        "customer.subscription.deleted" => Some(PlanChange {
+            user_id: uid_from(o.pointer("/metadata/user_id")),
+            customer: s("customer"),
+            subscription: s("id"),
+            status: "canceled".into(),
+            plan: Plan::Free,
+            period_end: None,
+        }),
+        _ => None,
+    }
+}
+
+/// Apply a verified webhook event. Returns `false` for duplicates.
+///
+/// The event is marked seen in the same transaction that applies it: if the
+/// apply fails nothing is recorded, so Stripe's retry applies it again
+/// instead of being dropped as a duplicate.
+pub async fn handle_event(db: &PgPool, ev: &StripeEvent) -> Result<bool, BillingError> {
+    let mut tx = db.begin().await?;
+    let fresh = sqlx::query(
+        "INSERT INTO stripe_events (event_id, event_type) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(&ev.id)
+    .bind(&ev.kind)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        == 1;
+    if !fresh {
+        return Ok(false);
+    }
+
+    let Some(ch) = plan_change(ev) else {
+        tx.commit().await?;
+        return Ok(true);
+    };
+
+    let rows = match ch.user_id {
+        Some(uid) => {
+            sqlx::query(
+                r#"
+                INSERT INTO user_plans
+                      (user_id, plan, stripe_customer_id, stripe_subscription_id,
+                       subscription_status, current_period_end)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (user_id) DO UPDATE
+                   SET plan                   = EXCLUDED.plan,
+                       stripe_customer_id     = COALESCE(EXCLUDED.stripe_customer_id, user_plans.stripe_customer_id),
+                       stripe_subscription_id = COALESCE(EXCLUDED.stripe_subscription_id, user_plans.stripe_subscription_id),
+                       subscription_status    = EXCLUDED.subscription_status,
+                       current_period_end     = COALESCE(EXCLUDED.current_period_end, user_plans.current_period_end),
+                       updated_at             = now()
+                "#,
+            )
+            .bind(uid)
+            .bind(ch.plan.as_str())
+            .bind(&ch.customer)
+            .bind(&ch.subscription)
+            .bind(&ch.status)
+            .bind(ch.period_end)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+        }
+        None => {
+            sqlx::query(
+                r#"
+                UPDATE user_plans
+                   SET plan                = $2,
+                       subscription_status = $3,
+                       current_period_end  = COALESCE($4, current_period_end),
+                       updated_at          = now()
+                 WHERE stripe_customer_id = $1
+                "#,
+            )
+            .bind(&ch.customer)
+            .bind(ch.plan.as_str())
+            .bind(&ch.status)
+            .bind(ch.period_end)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+        }
+    };
+
+    // first paid plan → referral conversion reward (idempotent)
+    if let (Some(uid), Plan::Pro) = (ch.user_id, ch.plan) {
+        referrals::on_paid_conversion(&mut tx, uid).await?;
+    }
+    tx.commit().await?;
+
+    if rows == 0 {
+        tracing::warn!("billing: {} ({}) matched no user", ev.kind, ev.id);
+    } else {
+        tracing::info!("billing: {} → plan {}", ev.kind, ch.plan.as_str());
+        audit::record(
+            ch.user_id,
+            "billing.plan_change",
+            serde_json::json!({
+                "event": ev.id,
+                "type": ev.kind,
+                "plan": ch.plan.as_str(),
+                "status": ch.status,
+            }),
+        );
+    }
+    Ok(true)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sign(secret: &str, t: i64, payload: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{t}.").as_bytes());
+        mac.update(payload);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn event(kind: &str, object: Value) -> StripeEvent {
+        serde_json::from_value(json!({"id":"evt_1","type":kind,"data":{"object":object}})).unwrap()
+    }
+
+    #[test]
+    fn signature_roundtrip() {
+        let body = br#"{"id":"evt_1"}"#;
+        let hdr = format!("t=1000,v1={}", sign("whsec", 1000, body));
+        assert!(verify_signature(body, &hdr, "whsec", 1010).is_ok());
+    }
+
+    #[test]
+    fn signature_rejects_tamper_and_replay() {
+        let body = br#"{"id":"evt_1"}"#;
+        let hdr = format!("t=1000,v1={}", sign("whsec", 1000, body));
+        assert!(verify_signature(br#"{"id":"evt_2"}"#, &hdr, "whsec", 1000).is_err());
+        assert!(verify_signature(body, &hdr, "other", 1000).is_err());
+        assert!(
+            verify_signature(body, &hdr, "whsec", 1000 + SIGNATURE_TOLERANCE_SECS + 1).is_err()
+        );
+        assert!(verify_signature(body, "v1=abc", "whsec", 1000).is_err());
+    }
+
+    #[test]
+    fn signature_with_an_extreme_timestamp_is_rejected_not_overflowed() {
+        let body = b"{}";
+        for t in [i64::MIN, i64::MAX] {
+            let hdr = format!("t={t},v1={}", sign("whsec", t, body));
+            assert!(verify_signature(body, &hdr, "whsec", 1000).is_err());
+            assert!(verify_signature(body, &hdr, "whsec", -1000).is_err());
+        }
+    }
+
+    #[test]
+    fn signature_accepts_any_v1_during_rotation() {
+        let body = b"{}";
+        let hdr = format!("t=5,v1=deadbeef,v1={}", sign("new", 5, body));
+        assert!(verify_signature(body, &hdr, "new", 5).is_ok());
+    }
+
+    #[test]
+    fn checkout_completed_upgrades() {
+        let ch = plan_change(&event(
+            "checkout.session.completed",
+            json!({"client_reference_id":"42","customer":"cus_1","subscription":"sub_1"}),
+        ))
+        .unwrap();
+        assert_eq!(ch.user_id, Some(42));
+        assert_eq!(ch.plan, Plan::Pro);
+        assert_eq!(ch.customer.as_deref(), Some("cus_1"));
+    }
+
+    #[test]
+    fn subscription_status_drives_plan() {
+        let upd = |status: &str| {
+            plan_change(&event(
+                "customer.subscription.updated",
+                json!({"id":"sub_1","customer":"cus_1","status":status,
+                       "current_period_end":1_752_000_000,
+                       "metadata":{"user_id":"42","plan":"pro"}}),
+            ))
+            .unwrap()
+        };
+        assert_eq!(upd("active").plan, Plan::Pro);
+        assert_eq!(upd("past_due").plan, Plan::Pro);
+        assert_eq!(upd("unpaid").plan, Plan::Free);
+        assert!(upd("active").period_end.is_some());
+
+        let del = plan_change(&event(
+            "customer.subscription.deleted",
+            json!({"id":"sub_1","customer":"cus_1"}),
+        ))
+        .unwrap();
+        assert_eq!(del.plan, Plan::Free);
+        assert_eq!(del.user_id, None);
+    }
+
+    #[test]
+    fn unrelated_events_are_ignored() {
+        assert!(plan_change(&event("invoice.paid", json!({}))).is_none());
+    }
+}
@@ -0,0 +1,376 @@
+//! Production adapter for Binance USDⓈ-M futures (`/fapi`).
+//! Implements the `ApiClient` / `ExchangeAdapter` traits; its market data
+//! comes off the public streams in `market_data`.
+//!
+//! * Signed Binance-style: `signature = hex(hmac_sha256(secret, query))`
+//!   over the query string, API key in `X-MBX-APIKEY`
+//! * Symbols are canonical (`BTCUSDT`) and sizes are base-asset quantities
+//!   (BlowFin's are contracts)
+//! * Take-profit / stop-loss can't ride along with an order here; an entry
+//!   asking for them is refused rather than sent unprotected
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use sqlx::PgPool;
+
+use crate::db::api_keys::DecryptedApiKey;
+use crate::services::blowfin::api::{Credentials, OrderRequest};
+use crate::services::blowfin::client::shared_http;
+use crate::services::exchange_log;
+use crate::services::exchanges::ExchangeAdapter;
+use crate::services::market_data::bus_symbol;
+use crate::services::trading_engine::{ApiClient, ApiResponse, Exchange, OrderState, OrderStatus};
+use crate::utils::errors::TradeError;
+
+pub(crate) const BASE_URL: &str = "https://fapi.binance.com";
+pub(crate) const DEMO_URL: &str = "https://testnet.binancefuture.com";
+
+/// Server-side tolerance for our clock, in ms
+const RECV_WINDOW_MS: u32 = 5_000;
+/// "Unknown order sent." – on a status query: we never placed it
+const UNKNOWN_ORDER: i64 = -2013;
+
+pub struct BinanceClient {
+    creds: DecryptedApiKey,
+}
+
+impl BinanceClient {
+    pub fn new(creds: DecryptedApiKey) -> Self {
+        Self { creds }
+    }
+
+    /// Sign `params` and send them; the body comes back as text with its
+    /// status so callers can tell rejections from transport errors
+    async fn signed(
+        &self,
+        method: Method,
+        path: &str,
+        params: Vec<(&str, String)>,
+        is_demo: bool,
+    ) -> Result<(StatusCode, String), TradeError> {
+        signed_call(
+            &self.creds.api_key,
+            &self.creds.api_secret,
+            method,
+            path,
+            params,
+            is_demo,
+        )
+        .await
+    }
+}
+
+fn base_url(is_demo: bool) -> &'static str {
+    if is_demo {
+        DEMO_URL
+    } else {
+        BASE_URL
+    }
+}
+
+/// Query string with `timestamp`, `recvWindow` and the signature appended.
+/// Every value is a plain token (symbols, numbers, our hex order ids), so
+/// nothing needs escaping.
+pub fn signed_query(secret: &str, mut params: Vec<(&str, String)>, now_ms: i64) -> String {
+    params.push(("recvWindow", RECV_WINDOW_MS.to_string()));
+    params.push(("timestamp", now_ms.to_string()));
+    let query = params
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&");
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key size");
+    mac.update(query.as_bytes());
+    format!(
+        "{query}&signature={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+async fn signed_call(
+    api_key: &str,
+    secret: &str,
+    method: Method,
+    path: &str,
+    params: Vec<(&str, String)>,
+    is_demo: bool,
+) -> Result<(StatusCode, String), TradeError> {
+    let query = signed_query(secret, params, chrono::Utc::now().timestamp_millis());
+    let url = format!("{}{path}?{query}", base_url(is_demo));
+    let headers = [("X-MBX-APIKEY", api_key.to_string())];
+    let trace = exchange_log::trace::<()>(method.as_str(), &url, &headers, None);
+    let req = shared_http()
+        .request(method, &url)
+        .header(headers[0].0, headers[0].1.as_str());
+    exchange_log::send(req, trace)
+        .await
+        .map_err(|e| TradeError::Api(e.into()))
+}
+
+/// Binance's error body
+#[derive(Debug, Deserialize)]
+struct Rejection {
+    code: i64,
+    msg: String,
+}
+
+fn rejection(text: &str) -> Rejection {
+    serde_json::from_str(text).unwrap_or_else(|_| Rejection {
+        code: 0,
+        msg: text.chars().take(200).collect(),
+    })
+}
+
+/// Our `OrderRequest` as Binance order parameters
+pub fn order_params(order: &OrderRequest) -> Result<Vec<(&'static str, String)>, TradeError> {
+    if order.tp_trigger_price.is_some() || order.sl_trigger_price.is_some() {
+        return Err(TradeError::InvalidRequest(
+            "binance: take-profit / stop-loss can't be attached to an order".into(),
+        ));
+    }
+    let mut params = vec![
+        ("symbol", bus_symbol(&order.inst_id)),
+        ("side", order.side.to_ascii_uppercase()),
+    ];
+    match order.order_type.to_ascii_lowercase().as_str() {
+        "market" => params.push(("type", "MARKET".into())),
+        kind @ ("limit" | "post_only") => {
+            let price = order.price.clone().ok_or_else(|| {
+                TradeError::InvalidRequest("binance: a limit order needs a price".into())
+            })?;
+            let tif = if kind == "post_only" { "GTX" } else { "GTC" };
+            params.extend([
+                ("type", "LIMIT".into()),
+                ("price", price),
+                ("timeInForce", tif.into()),
+            ]);
+        }
+        other => {
+            return Err(TradeError::InvalidRequest(format!(
+                "binance: unsupported order type `{other}`"
+            )))
+        }
+    }
+    params.push(("quantity", order.size.clone()));
+    if order.reduce_only.is_some() {
+        params.push(("reduceOnly", "true".into()));
+    }
+    if let Some(id) = &order.client_order_id {
+        params.push(("newClientOrderId", id.clone()));
+    }
+    Ok(params)
+}
+
+/// Binance's order `status` (+ executed quantity) as ours
+pub fn order_state(status: &str, executed_qty: f64) -> OrderState {
+    match status {
+        "FILLED" => OrderState::Filled,
+        "CANCELED" | "EXPIRED" | "EXPIRED_IN_MATCH" if executed_qty > 0.0 => {
+            OrderState::PartiallyFilled
+        }
+        "CANCELED" | "EXPIRED" | "EXPIRED_IN_MATCH" | "REJECTED" => OrderState::Canceled,
+        _ => OrderState::Live,
+    }
+}
+
+/// One authenticated read with freshly submitted credentials
+pub async fn prove(creds: &Credentials, is_demo: bool) -> Result<(), TradeError> {
+    let (status, text) = signed_call(
+        &creds.api_key,
+        &creds.api_secret,
+        Method::GET,
+        "/fapi/v2/balance",
+        Vec::new(),
+        is_demo,
+    )
+    .await?;
+    if status == StatusCode::OK {
+        return Ok(());
+    }
+    let r = rejection(&text);
+    Err(TradeError::InvalidRequest(format!(
+        "code {}: {}",
+        r.code, r.msg
+    )))
+}
+
+#[async_trait]
+impl ApiClient for BinanceClient {
+    async fn place_order(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        order: &OrderRequest,
+        is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<ApiResponse, TradeError> {
+        let params = order_params(order)?;
+        let (status, text) = self
+            .signed(Method::POST, "/fapi/v1/order", params, is_demo)
+            .await?;
+        let data: Value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        // a rejection is an answer, not a transport error – same as BlowFin's
+        // non-zero codes
+        let code = if status == StatusCode::OK {
+            "0".to_string()
+        } else {
+            data.get("code")
+                .and_then(Value::as_i64)
+                .map_or_else(|| status.as_u16().to_string(), |c| c.to_string())
+        };
+        Ok(ApiResponse { code, data })
+    }
+
+    async fn cancel_by_client_id(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        symbol: &str,
+        client_order_id: &str,
+        is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let params = vec![
+            ("symbol", bus_symbol(symbol)),
+            ("origClientOrderId", client_order_id.to_string()),
+        ];
+        let (status, text) = self
+            .signed(Method::DELETE, "/fapi/v1/order", params, is_demo)
+            .await?;
+        if status == StatusCode::OK {
+            Ok(())
+        } else {
+            Err(TradeError::Other(format!(
+                "cancel rejected: {}",
+                rejection(&text).msg
+            )))
+        }
+    }
+
+    async fn order_status(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        symbol: &str,
+        client_order_id: &str,
+        is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<OrderStatus, TradeError> {
+        let params = vec![
+            ("symbol", bus_symbol(symbol)),
+            ("origClientOrderId", client_order_id.to_string()),
+        ];
+        let (status, text) = self
+            .signed(Method::GET, "/fapi/v1/order", params, is_demo)
+            .await?;
+        if status != StatusCode::OK {
+            let r = rejection(&text);
+            if r.code == UNKNOWN_ORDER {
+                return Ok(OrderStatus {
+                    state: OrderState::NotFound,
+                    data: Value::Null,
+                });
+            }
+            return Err(TradeError::Other(format!("order status: {}", r.msg)));
+        }
+        let data: Value = serde_json::from_str(&text).map_err(|e| TradeError::Api(e.into()))?;
+        let executed = data["executedQty"]
+            .as_str()
+            .and_then(|q| q.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        let state = order_state(data["status"].as_str().unwrap_or_default(), executed);
+        Ok(OrderStatus { state, data })
+    }
+}
+
+#[async_trait]
+impl ExchangeAdapter for BinanceClient {
+    fn exchange(&self) -> Exchange {
+        Exchange::Binance
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(order_type: &str, price: Option<&str>) -> OrderRequest {
+        OrderRequest {
+            inst_id: "BTC-USDT".into(),
+            margin_mode: "isolated".into(),
+            side: "sell".into(),
+            order_type: order_type.into(),
+            price: price.map(Into::into),
+            size: "0.002".into(),
+            reduce_only: Some("true".into()),
+            client_order_id: Some("rrabc".into()),
+            tp_trigger_price: None,
+            tp_order_price: None,
+            sl_trigger_price: None,
+            sl_order_price: None,
+        }
+    }
+
+    #[test]
+    fn signature_matches_binances_documented_example() {
+        // the HMAC example from Binance's "SIGNED endpoint" docs
+        let secret = "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+        let params = vec![
+            ("symbol", "LTCBTC".to_string()),
+            ("side", "BUY".into()),
+            ("type", "LIMIT".into()),
+            ("timeInForce", "GTC".into()),
+            ("quantity", "1".into()),
+            ("price", "0.1".into()),
+        ];
+        assert_eq!(
+            signed_query(secret, params, 1_499_827_319_559),
+            "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1\
+             &recvWindow=5000&timestamp=1499827319559\
+             &signature=c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+
+    #[test]
+    fn orders_translate_to_binance_params() {
+        let p = order_params(&order("limit", Some("30000.5"))).unwrap();
+        let get = |k: &str| p.iter().find(|(n, _)| *n == k).map(|(_, v)| v.as_str());
+        assert_eq!(get("symbol"), Some("BTCUSDT"));
+        assert_eq!(get("side"), Some("SELL"));
+        assert_eq!(
+            (get("type"), get("timeInForce")),
+            (Some("LIMIT"), Some("GTC"))
+        );
+        assert_eq!(get("price"), Some("30000.5"));
+        assert_eq!(get("quantity"), Some("0.002"));
+        assert_eq!(get("reduceOnly"), Some("true"));
+        assert_eq!(get("newClientOrderId"), Some("rrabc"));
+
+        let m = order_params(&order("market", Some("1"))).unwrap();
+        assert!(m.iter().all(|(k, _)| *k != "price" && *k != "timeInForce"));
+        assert!(order_params(&order("limit", None)).is_err());
+        assert!(order_params(&order("trigger", None)).is_err());
+
+        let mut with_sl = order("market", None);
+        with_sl.sl_trigger_price = Some("29000".into());
+        assert!(order_params(&with_sl).is_err());
+    }
+
+    #[test]
+    fn statuses_map_to_order_states() {
+        assert_eq!(order_state("NEW", 0.0), OrderState::Live);
+        assert_eq!(order_state("PARTIALLY_FILLED", 0.1), OrderState::Live);
+        assert_eq!(order_state("FILLED", 1.0), OrderState::Filled);
+        assert_eq!(order_state("CANCELED", 0.1), OrderState::PartiallyFilled);
+        assert_eq!(order_state("EXPIRED", 0.0), OrderState::Canceled);
+        assert_eq!(order_state("REJECTED", 0.0), OrderState::Canceled);
+    }
+}
@@ -5,12 +5,17 @@
 //! signatures so nothing upstream breaks; test-harness uses the generic
 //! `*_with` versions that accept mock implementations.
 
-use crate::db::api_keys::ApiKey;
+use crate::db::api_keys::{ApiKey, CredsError};
+use crate::services::blowfin::client::shared_http;
+pub use crate::services::blowfin::dto::BlowFinResponse;
+use crate::services::blowfin::dto::{Balance, Deposit, Fill, OrderAck, Position, Withdrawal};
+use crate::services::crypto::GLOBAL_CRYPTO;
+use crate::services::exchange_log;
+use crate::services::trading_engine::timeouts;
 use crate::utils::errors::ApiError;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde::Serialize;
 use sqlx::PgPool;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 // ───────────────────────────────────────────────────────────────
 // Domain types
@@ -26,18 +31,27 @@ pub struct OrderRequest {
     pub order_type: String,
     pub price: Option<String>,
     pub size: String,
+    #[serde(rename = "reduceOnly", skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<String>,
+    /// Our id for the order – lets a timed-out submission be cancelled/queried
+    #[serde(rename = "clientOrderId", skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    /// Take-profit attached to the order and kept by the exchange
+    #[serde(rename = "tpTriggerPrice", skip_serializing_if = "Option::is_none")]
+    pub tp_trigger_price: Option<String>,
+    /// `-1` = market once triggered
+    #[serde(rename = "tpOrderPrice", skip_serializing_if = "Option::is_none")]
+    pub tp_order_price: Option<String>,
+    /// Stop-loss attached to the order – survives our downtime
+    #[serde(rename = "slTriggerPrice", skip_serializing_if = "Option::is_none")]
+    pub sl_trigger_price: Option<String>,
+    #[serde(rename = "slOrderPrice", skip_serializing_if = "Option::is_none")]
+    pub sl_order_price: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct BlowFinResponse {
-    pub code: String,
-    #[allow(dead_code)]
-    pub msg: String,
-    pub data: Value,
-}
-
-/// Convenience container returned by the `ApiKeyRepo`
-#[derive(Debug, Clone)]
+/// Convenience container returned by the `ApiKeyRepo` (wiped from memory
+/// when dropped)
+#[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct Credentials {
     pub api_key: String,
     pub api_secret: String,
@@ -64,22 +78,37 @@ impl ApiKeyRepo for ProdApiKeys {
         &self,
         db: &PgPool,
         user_id: i64,
-        master_key: &[u8],
+        _master_key: &[u8],
     ) -> Result<Credentials, ApiError> {
-        let row = ApiKey::get_by_user_and_exchange(db, user_id, "blowfin")
-            .await?
+        let c = ApiKey::decrypted_cached(db, &GLOBAL_CRYPTO, user_id, "blowfin")
+            .await
+            .map_err(|e| match e {
+                CredsError::Db(e) => ApiError::from(e),
+                e => ApiError::Custom(e.to_string()),
+            })?
             .ok_or_else(|| ApiError::Custom("Missing API key for BlowFin".into()))?;
-        let c = row
-            .decrypt(master_key)
-            .map_err(|e| ApiError::Custom(format!("decrypt failed: {e}")))?;
         Ok(Credentials {
-            api_key: c.api_key,
-            api_secret: c.api_secret,
-            api_passphrase: c.api_passphrase,
+            api_key: c.api_key.clone(),
+            api_secret: c.api_secret.clone(),
+            api_passphrase: c.api_passphrase.clone(),
         })
     }
 }
 
+/// Credentials that aren't stored yet (e.g. validated during onboarding)
+pub struct GivenKeys(pub Credentials);
+#[async_trait::async_trait]
+impl ApiKeyRepo for GivenKeys {
+    async fn fetch_creds(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _master_key: &[u8],
+    ) -> Result<Credentials, ApiError> {
+        Ok(self.0.clone())
+    }
+}
+
 /// Small wrapper around the three “auth” helpers so we can stub them.
 pub trait Signer: Send + Sync {
     fn ts(&self) -> String;
@@ -142,12 +171,13 @@ impl Http for ReqwestClient {
         headers: Vec<(&str, String)>,
         body: &OrderRequest,
     ) -> Result<T, ApiError> {
-        let client = Client::new();
-        let mut req = client.post(url);
+        let trace = exchange_log::trace("POST", url, &headers, Some(body));
+        let mut req = shared_http().post(url).timeout(timeouts().submit);
         for (k, v) in headers {
             req = req.header(k, v);
         }
-        Ok(req.json(body).send().await?.json::<T>().await?)
+        let (_, text) = exchange_log::send(req.json(body), trace).await?;
+        Ok(serde_json::from_str(&text)?)
     }
 
     async fn get_json<T: serde::de::DeserializeOwned + Send>(
@@ -155,12 +185,13 @@ impl Http for ReqwestClient {
         url: &str,
         headers: Vec<(&str, String)>,
     ) -> Result<T, ApiError> {
-        let client = Client::new();
-        let mut req = client.get(url);
+        let trace = exchange_log::trace::<()>("GET", url, &headers, None);
+        let mut req = shared_http().get(url).timeout(timeouts().submit);
         for (k, v) in headers {
             req = req.header(k, v);
         }
-        Ok(req.send().await?.json::<T>().await?)
+        let (_, text) = exchange_log::send(req, trace).await?;
+        Ok(serde_json::from_str(&text)?)
     }
 }
 
@@ -177,7 +208,7 @@ pub async fn place_order_with<K: ApiKeyRepo, S: Signer, H: Http>(
     keys: &K,
     signer: &S,
     http: &H,
-) -> Result<BlowFinResponse, ApiError> {
+) -> Result<BlowFinResponse<Vec<OrderAck>>, ApiError> {
     // ------------------------------------------------------------------
     // 1. Resolve URL
     let path = "/api/v1/trade/order";
@@ -200,17 +231,16 @@ pub async fn place_order_with<K: ApiKeyRepo, S: Signer, H: Http>(
     let sig = signer.sign(&cred.api_secret, "POST", path, &ts, &nonce, &body);
 
     let headers = vec![
-        ("ACCESS-KEY", cred.api_key),
+        ("ACCESS-KEY", cred.api_key.clone()),
         ("ACCESS-SIGN", sig),
         ("ACCESS-TIMESTAMP", ts),
         ("ACCESS-NONCE", nonce),
-        ("ACCESS-PASSPHRASE", cred.api_passphrase),
+        ("ACCESS-PASSPHRASE", cred.api_passphrase.clone()),
     ];
 
     // ------------------------------------------------------------------
     // 4. HTTP POST
-    http.post_json::<BlowFinResponse>(&url, headers, order)
-        .await
+    http.post_json(&url, headers, order).await
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -222,8 +252,8 @@ pub async fn get_balance_with<K: ApiKeyRepo, S: Signer, H: Http>(
     keys: &K,
     signer: &S,
     http: &H,
-) -> Result<BlowFinResponse, ApiError> {
-    let path = "/api/v1/asset/balances?accountType=futures";
+) -> Result<BlowFinResponse<Balance>, ApiError> {
+    let path = "/api/v1/account/balance";
     let base = if is_demo {
         "https://demo-trading-openapi.blofin.com"
     } else {
@@ -238,26 +268,189 @@ pub async fn get_balance_with<K: ApiKeyRepo, S: Signer, H: Http>(
     let sig = signer.sign(&cred.api_secret, "GET", path, &ts, &nonce, "");
 
     let headers = vec![
-        ("ACCESS-KEY", cred.api_key),
+        ("ACCESS-KEY", cred.api_key.clone()),
+        ("ACCESS-SIGN", sig),
+        ("ACCESS-TIMESTAMP", ts),
+        ("ACCESS-NONCE", nonce),
+        ("ACCESS-PASSPHRASE", cred.api_passphrase.clone()),
+    ];
+
+    http.get_json(&url, headers).await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn get_positions_with<K: ApiKeyRepo, S: Signer, H: Http>(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    keys: &K,
+    signer: &S,
+    http: &H,
+) -> Result<BlowFinResponse<Vec<Position>>, ApiError> {
+    let path = "/api/v1/account/positions";
+    let base = if is_demo {
+        "https://demo-trading-openapi.blofin.com"
+    } else {
+        "https://openapi.blofin.com"
+    };
+    let url = format!("{base}{path}");
+
+    let cred = keys.fetch_creds(db, user_id, master_key).await?;
+
+    let ts = signer.ts();
+    let nonce = signer.nonce();
+    let sig = signer.sign(&cred.api_secret, "GET", path, &ts, &nonce, "");
+
+    let headers = vec![
+        ("ACCESS-KEY", cred.api_key.clone()),
+        ("ACCESS-SIGN", sig),
+        ("ACCESS-TIMESTAMP", ts),
+        ("ACCESS-NONCE", nonce),
+        ("ACCESS-PASSPHRASE", cred.api_passphrase.clone()),
+    ];
+
+    http.get_json(&url, headers).await
+}
+
+/// Most recent fills (newest first, up to 100) – fees as charged
+#[allow(clippy::too_many_arguments)]
+pub async fn get_fills_with<K: ApiKeyRepo, S: Signer, H: Http>(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    keys: &K,
+    signer: &S,
+    http: &H,
+) -> Result<BlowFinResponse<Vec<Fill>>, ApiError> {
+    let path = "/api/v1/trade/fills-history?limit=100";
+    let base = if is_demo {
+        "https://demo-trading-openapi.blofin.com"
+    } else {
+        "https://openapi.blofin.com"
+    };
+    let url = format!("{base}{path}");
+
+    let cred = keys.fetch_creds(db, user_id, master_key).await?;
+
+    let ts = signer.ts();
+    let nonce = signer.nonce();
+    let sig = signer.sign(&cred.api_secret, "GET", path, &ts, &nonce, "");
+
+    let headers = vec![
+        ("ACCESS-KEY", cred.api_key.clone()),
+        ("ACCESS-SIGN", sig),
+        ("ACCESS-TIMESTAMP", ts),
+        ("ACCESS-NONCE", nonce),
+        ("ACCESS-PASSPHRASE", cred.api_passphrase.clone()),
+    ];
+
+    http.get_json(&url, headers).await
+}
+
+/// Deposits into the account (newest first, up to 100); `after` pages to
+/// records older than that timestamp (ms)
+#[allow(clippy::too_many_arguments)]
+pub async fn get_deposits_with<K: ApiKeyRepo, S: Signer, H: Http>(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    after: Option<i64>,
+    keys: &K,
+    signer: &S,
+    http: &H,
+) -> Result<BlowFinResponse<Vec<Deposit>>, ApiError> {
+    let path = match after {
+        Some(ts) => format!("/api/v1/asset/deposit-history?limit=100&after={ts}"),
+        None => "/api/v1/asset/deposit-history?limit=100".to_string(),
+    };
+    let base = if is_demo {
+        "https://demo-trading-openapi.blofin.com"
+    } else {
+        "https://openapi.blofin.com"
+    };
+    let url = format!("{base}{path}");
+
+    let cred = keys.fetch_creds(db, user_id, master_key).await?;
+
+    let ts = signer.ts();
+    let nonce = signer.nonce();
+    let sig = signer.sign(&cred.api_secret, "GET", &path, &ts, &nonce, "");
+
+    let headers = vec![
+        ("ACCESS-KEY", cred.api_key.clone()),
+        ("ACCESS-SIGN", sig),
+        ("ACCESS-TIMESTAMP", ts),
+        ("ACCESS-NONCE", nonce),
+        ("ACCESS-PASSPHRASE", cred.api_passphrase.clone()),
+    ];
+
+    http.get_json(&url, headers).await
+}
+
+/// Withdrawals out of the account – paged like [`get_deposits_with`]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_withdrawals_with<K: ApiKeyRepo, S: Signer, H: Http>(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    after: Option<i64>,
+    keys: &K,
+    signer: &S,
+    http: &H,
+) -> Result<BlowFinResponse<Vec<Withdrawal>>, ApiError> {
+    let path = match after {
+        Some(ts) => format!("/api/v1/asset/withdrawal-history?limit=100&after={ts}"),
+        None => "/api/v1/asset/withdrawal-history?limit=100".to_string(),
+    };
+    let base = if is_demo {
+        "https://demo-trading-openapi.blofin.com"
+    } else {
+        "https://openapi.blofin.com"
+    };
+    let url = format!("{base}{path}");
+
+    let cred = keys.fetch_creds(db, user_id, master_key).await?;
+
+    let ts = signer.ts();
+    let nonce = signer.nonce();
+    let sig = signer.sign(&cred.api_secret, "GET", &path, &ts, &nonce, "");
+
+    let headers = vec![
+        ("ACCESS-KEY", cred.api_key.clone()),
         ("ACCESS-SIGN", sig),
         ("ACCESS-TIMESTAMP", ts),
         ("ACCESS-NONCE", nonce),
-        ("ACCESS-PASSPHRASE", cred.api_passphrase),
+        ("ACCESS-PASSPHRASE", cred.api_passphrase.clone()),
     ];
 
-    http.get_json::<BlowFinResponse>(&url, headers).await
+    http.get_json(&url, headers).await
 }
 
 // ──────────────────────────────────────────────────────────────
 //  Production wrappers (unchanged signatures)
 // ──────────────────────────────────────────────────────────────
+#[cfg(not(feature = "chaos"))]
+fn prod_http() -> ReqwestClient {
+    ReqwestClient
+}
+
+/// Staging builds route exchange calls through the fault injector
+#[cfg(feature = "chaos")]
+fn prod_http() -> crate::services::chaos::Chaos<ReqwestClient> {
+    crate::services::chaos::Chaos(ReqwestClient)
+}
+
 pub async fn place_order(
     db: &PgPool,
     user_id: i64,
     order: &OrderRequest,
     is_demo: bool,
     master_key: &[u8],
-) -> Result<BlowFinResponse, ApiError> {
+) -> Result<BlowFinResponse<Vec<OrderAck>>, ApiError> {
     place_order_with(
         db,
         user_id,
@@ -266,7 +459,7 @@ pub async fn place_order(
         master_key,
         &ProdApiKeys,
         &ProdSigner,
-        &ReqwestClient,
+        &prod_http(),
     )
     .await
 }
@@ -276,7 +469,7 @@ pub async fn get_balance(
     user_id: i64,
     is_demo: bool,
     master_key: &[u8],
-) -> Result<BlowFinResponse, ApiError> {
+) -> Result<BlowFinResponse<Balance>, ApiError> {
     get_balance_with(
         db,
         user_id,
@@ -284,7 +477,102 @@ pub async fn get_balance(
         master_key,
         &ProdApiKeys,
         &ProdSigner,
-        &ReqwestClient,
+        &prod_http(),
+    )
+    .await
+}
+
+/// Balance using `creds` instead of the stored key – proves a key works
+/// before it is saved
+pub async fn get_balance_as(
+    db: &PgPool,
+    creds: Credentials,
+    is_demo: bool,
+) -> Result<BlowFinResponse<Balance>, ApiError> {
+    get_balance_with(
+        db,
+        0,
+        is_demo,
+        &[],
+        &GivenKeys(creds),
+        &ProdSigner,
+        &prod_http(),
+    )
+    .await
+}
+
+pub async fn get_positions(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<BlowFinResponse<Vec<Position>>, ApiError> {
+    get_positions_with(
+        db,
+        user_id,
+        is_demo,
+        master_key,
+        &ProdApiKeys,
+        &ProdSigner,
+        &prod_http(),
+    )
+    .await
+}
+
+pub async fn get_fills(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<BlowFinResponse<Vec<Fill>>, ApiError> {
+    get_fills_with(
+        db,
+        user_id,
+        is_demo,
+        master_key,
+        &ProdApiKeys,
+        &ProdSigner,
+        &prod_http(),
+    )
+    .await
+}
+
+pub async fn get_deposits(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    after: Option<i64>,
+) -> Result<BlowFinResponse<Vec<Deposit>>, ApiError> {
+    get_deposits_with(
+        db,
+        user_id,
+        is_demo,
+        master_key,
+        after,
+        &ProdApiKeys,
+        &ProdSigner,
+        &prod_http(),
+    )
+    .await
+}
+
+pub async fn get_withdrawals(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    after: Option<i64>,
+) -> Result<BlowFinResponse<Vec<Withdrawal>>, ApiError> {
+    get_withdrawals_with(
+        db,
+        user_id,
+        is_demo,
+        master_key,
+        after,
+        &ProdApiKeys,
+        &ProdSigner,
+        &prod_http(),
     )
     .await
 }
@@ -375,8 +663,8 @@ mod tests {
             *self.last_url.lock().unwrap() = u.into();
             *self.last_hdrs.lock().unwrap() =
                 h.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
-            let resp = json!({"code":self.code,"msg":"","data":{"order_id":"X"}}).to_string();
-            Ok(serde_json::from_str(&resp)?)
+            let resp = json!({"code":self.code,"msg":"","data":[{"orderId":"X","code":"0"}]});
+            Ok(serde_json::from_value(resp)?)
         }
         async fn get_json<T: serde::de::DeserializeOwned + Send>(
             &self,
@@ -387,8 +675,15 @@ mod tests {
             *self.last_url.lock().unwrap() = u.into();
             *self.last_hdrs.lock().unwrap() =
                 h.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
-            let resp = json!({"code":self.code,"msg":"","data":{"bal":123}}).to_string();
-            Ok(serde_json::from_str(&resp)?)
+            let data = if u.contains("/account/balance") {
+                json!({ "ts": "1", "totalEquity": "123",
+                        "details": [{ "currency": "USDT", "equity": "123", "available": "100" }] })
+            } else {
+                json!([])
+            };
+            Ok(serde_json::from_value(
+                json!({"code":self.code,"msg":"","data":data}),
+            )?)
         }
     }
 
@@ -401,6 +696,12 @@ mod tests {
             order_type: "market".into(),
             price: None,
             size: "1".into(),
+            reduce_only: None,
+            client_order_id: None,
+            tp_trigger_price: None,
+            tp_order_price: None,
+            sl_trigger_price: None,
+            sl_order_price: None,
         }
     }
 
@@ -425,6 +726,7 @@ mod tests {
         .expect("ok");
 
         assert_eq!(resp.code, "0");
+        assert_eq!(resp.data[0].order_id.as_deref(), Some("X"));
         assert_eq!(*http.hit_post.lock().unwrap(), 1);
         assert!(http.last_url.lock().unwrap().contains("/trade/order"));
         assert!(http
@@ -483,6 +785,33 @@ mod tests {
 
         assert_eq!(resp.code, "0");
         assert_eq!(*http.hit_get.lock().unwrap(), 1);
-        assert_eq!(resp.data["bal"], json!(123));
+        assert_eq!(resp.data.currency("USDT").unwrap().equity, 123.0);
+    }
+
+    // ——————————————————————————————————————————
+    // GET positions path
+    // ——————————————————————————————————————————
+    #[tokio::test]
+    async fn get_positions_hits_account_endpoint() {
+        let db = lazy_pg();
+        let http = StubHttp::new("0");
+        let resp = get_positions_with(
+            &db,
+            7,
+            false,
+            b"K",
+            &MockKeys { bad_decrypt: false },
+            &MockSigner,
+            &http,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(resp.code, "0");
+        assert_eq!(*http.hit_get.lock().unwrap(), 1);
+        assert_eq!(
+            *http.last_url.lock().unwrap(),
+            "https://openapi.blofin.com/api/v1/account/positions"
+        );
     }
 }
@@ -1,14 +1,14 @@
 // src/services/blowfin/auth.rs
 
+use crate::services::time_sync::{self, ClockSource};
 use base64::{engine::general_purpose, Engine as _};
-use chrono::Utc;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use uuid::Uuid;
 
-/// Millisecond timestamp
+/// Millisecond timestamp, corrected for measured skew to BlowFin's clock
 pub fn current_timestamp() -> String {
-    Utc::now().timestamp_millis().to_string()
+    time_sync::now_ms(ClockSource::Blowfin).to_string()
 }
 
 /// Random nonce for replay protection
@@ -16,6 +16,41 @@ pub fn generate_nonce() -> String {
     Uuid::new_v4().to_string()
 }
 
+/// REST signer keyed once per credential: every request clones the keyed
+/// HMAC state and streams the prehash parts into it, so signing neither
+/// re-derives the key nor builds the prehash string.
+#[derive(Clone)]
+pub struct RestSigner {
+    keyed: Hmac<Sha256>,
+}
+
+impl RestSigner {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            keyed: Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                .expect("HMAC can take key bits of any size"),
+        }
+    }
+
+    /// base64(hmac_sha256(path ‖ method ‖ timestamp ‖ nonce ‖ body))
+    pub fn sign(
+        &self,
+        method: &str,
+        path: &str,
+        timestamp: &str,
+        nonce: &str,
+        body: &[u8],
+    ) -> String {
+        let mut mac = self.keyed.clone();
+        mac.update(path.as_bytes());
+        mac.update(method.as_bytes());
+        mac.update(timestamp.as_bytes());
+        mac.update(nonce.as_bytes());
+        mac.update(body);
+        general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+    }
+}
+
 /// Sign REST requests (HMAC SHA-256 + Base64)
 pub fn sign_rest(
     secret: &str,
@@ -25,11 +60,7 @@ pub fn sign_rest(
     nonce: &str,
     body: &str,
 ) -> String {
-    let prehash = format!("{}{}{}{}{}", path, method, timestamp, nonce, body);
-    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
-        .expect("HMAC can take key bits of any size");
-    mac.update(prehash.as_bytes());
-    general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+    RestSigner::new(secret).sign(method, path, timestamp, nonce, body.as_bytes())
 }
 
 /// Sign WebSocket login operation
@@ -76,6 +107,20 @@ mod tests {
         assert_eq!(sig, EXPECT_REST);
     }
 
+    // ---------- a reused signer gives the same signatures ----------
+    #[test]
+    fn rest_signer_matches_one_shot_signing() {
+        let signer = RestSigner::new(SECRET);
+        for _ in 0..2 {
+            let sig = signer.sign(METHOD, PATH, TS, NONCE, BODY.as_bytes());
+            assert_eq!(sig, EXPECT_REST);
+        }
+        assert_eq!(
+            signer.sign("GET", "/api/ping", TS, NONCE, b""),
+            sign_rest(SECRET, "GET", "/api/ping", TS, NONCE, "")
+        );
+    }
+
     // ---------- sign_ws happy path ----------
     #[test]
     fn sign_ws_matches_reference() {
@@ -3,49 +3,136 @@
 
 use crate::db::api_keys::DecryptedApiKey;
 use crate::services::blowfin::api::OrderRequest;
+use crate::services::blowfin::auth::{current_timestamp, generate_nonce, RestSigner};
+use crate::services::blowfin::dto::{BlowFinResponse, Order, OrderAck, TpslAck, TpslOrder};
+use crate::services::exchange_log;
+use crate::services::trading_engine::{
+    timeouts, ApiClient, ApiResponse, NativeStop, OpenOrder, OrderState, OrderStatus,
+};
 use crate::utils::errors::TradeError;
-use crate::utils::types::OrderResp;                // make sure this struct exists
-use crate::services::trading_engine::{ApiClient, ApiResponse};
 use async_trait::async_trait;
-use reqwest::{Client, StatusCode};
+use once_cell::sync::Lazy;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
 use sqlx::PgPool;
-use serde_json::Value;
+use std::time::Duration;
+
+pub(crate) const BASE_URL: &str = "https://api.blowfin.com";
+
+/// Idle connections outlive a few keep-alive rounds – see `services::keepalive`
+const POOL_IDLE: Duration = Duration::from_secs(300);
+
+/// One connection pool for every trade – building a `Client` per order
+/// threw away its TLS sessions and paid a fresh handshake on the hot path.
+/// Only the connect phase is bounded here: a request that reached the
+/// exchange must run into the engine's timeout, which cancels it.
+static HTTP: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .connect_timeout(timeouts().followup)
+        .pool_idle_timeout(POOL_IDLE)
+        .tcp_keepalive(Duration::from_secs(30))
+        .build()
+        .unwrap_or_default()
+});
+
+/// The pooled client every signed BlowFin call goes through
+pub(crate) fn shared_http() -> &'static Client {
+    &HTTP
+}
 
 pub struct BlowfinClient {
-    http:  Client,
+    http: Client,
+    signer: RestSigner,
     creds: DecryptedApiKey,
 }
 
 impl BlowfinClient {
-    /// Factory – you’ll usually call this inside `execute_trade`.
-    pub async fn new(creds: DecryptedApiKey) -> Self {
-        Self { http: Client::new(), creds }
+    /// Factory – you’ll usually call this inside `execute_trade`. Cheap: the
+    /// HTTP client is shared and the HMAC key is derived once here.
+    pub fn new(creds: DecryptedApiKey) -> Self {
+        Self {
+            http: HTTP.clone(),
+            signer: RestSigner::new(&creds.api_secret),
+            creds,
+        }
+    }
+
+    /// `ACCESS-*` headers for one request; `path` includes any query string
+    fn auth_headers(&self, method: &str, path: &str, body: &[u8]) -> [(&'static str, String); 5] {
+        let ts = current_timestamp();
+        let nonce = generate_nonce();
+        let sign = self.signer.sign(method, path, &ts, &nonce, body);
+        [
+            ("ACCESS-KEY", self.creds.api_key.clone()),
+            ("ACCESS-SIGN", sign),
+            ("ACCESS-TIMESTAMP", ts),
+            ("ACCESS-NONCE", nonce),
+            ("ACCESS-PASSPHRASE", self.creds.api_passphrase.clone()),
+        ]
     }
 
-    /// Low-level helper used only inside the trait impl below.
-    async fn signed_post(
+    /// Low-level helper used only inside the trait impl below. The body is
+    /// serialised exactly once: the signed bytes are the bytes sent.
+    async fn signed_post<T: DeserializeOwned, B: Serialize + ?Sized>(
         &self,
         endpoint: &str,
-        body: &Value,
-    ) -> Result<OrderResp, TradeError> {
-        // TODO: real HMAC with self.creds.api_secret
-        let resp = self
+        body: &B,
+    ) -> Result<T, TradeError> {
+        let payload = serde_json::to_vec(body).map_err(|e| TradeError::Api(e.into()))?;
+        let headers = self.auth_headers("POST", endpoint, &payload);
+        let url = format!("{BASE_URL}{endpoint}");
+        let trace = exchange_log::trace("POST", &url, &headers, Some(body));
+        let mut req = self
             .http
-            .post(format!("https://api.blowfin.com{endpoint}"))
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| TradeError::Api(e.into()))?;
-
-        if resp.status() != StatusCode::OK {
-            return Err(TradeError::Api(format!("http {}", resp.status()).into()));
+            .post(&url)
+            .header(CONTENT_TYPE, "application/json");
+        for (k, v) in headers {
+            req = req.header(k, v);
+        }
+        decode(req.body(payload), trace).await
+    }
+
+    async fn signed_get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, TradeError> {
+        let headers = self.auth_headers("GET", endpoint, b"");
+        let url = format!("{BASE_URL}{endpoint}");
+        let trace = exchange_log::trace::<()>("GET", &url, &headers, None);
+        let mut req = self.http.get(&url);
+        for (k, v) in headers {
+            req = req.header(k, v);
         }
+        decode(req, trace).await
+    }
 
-        Ok(resp.json::<OrderResp>().await.map_err(|e| TradeError::Api(e.into()))?)
+    /// First record in `data` carrying our client id
+    async fn find_order(
+        &self,
+        endpoint: &str,
+        client_order_id: &str,
+    ) -> Result<Option<Order>, TradeError> {
+        let body: BlowFinResponse<Vec<Order>> = self.signed_get(endpoint).await?;
+        Ok(body
+            .into_data()?
+            .into_iter()
+            .find(|o| o.client_order_id.as_deref() == Some(client_order_id)))
     }
 }
 
-
+/// Send through the payload log; anything but a 200 is an error
+async fn decode<T: DeserializeOwned>(
+    req: RequestBuilder,
+    trace: exchange_log::Trace,
+) -> Result<T, TradeError> {
+    let (status, text) = exchange_log::send(req, trace)
+        .await
+        .map_err(|e| TradeError::Api(e.into()))?;
+    if status != StatusCode::OK {
+        return Err(TradeError::Other(format!("http {status}")));
+    }
+    serde_json::from_str(&text).map_err(|e| TradeError::Api(e.into()))
+}
 
 #[async_trait]
 impl ApiClient for BlowfinClient {
@@ -57,12 +144,175 @@ impl ApiClient for BlowfinClient {
         _is_demo: bool,
         _master_key: &[u8],
     ) -> Result<ApiResponse, TradeError> {
-        let payload = serde_json::to_value(order).expect("serialise order");
-        let raw     = self.signed_post("/v1/order", &payload).await?;
+        let raw: BlowFinResponse<Vec<OrderAck>> = self.signed_post("/v1/order", order).await?;
 
         Ok(ApiResponse {
-            code: raw.status.clone(),
-            data: serde_json::to_value(raw).expect("serialise OrderResp"),
+            code: raw.code,
+            data: serde_json::to_value(raw.data).expect("serialise OrderAck"),
+        })
+    }
+
+    async fn cancel_by_client_id(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        symbol: &str,
+        client_order_id: &str,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let body = json!({ "instId": symbol, "clientOrderId": client_order_id });
+        let resp: BlowFinResponse<Vec<OrderAck>> = self
+            .signed_post("/api/v1/trade/cancel-order", &body)
+            .await?;
+        match resp.data.first() {
+            Some(ack) if resp.is_ok() && ack.is_ok() => Ok(()),
+            Some(ack) => Err(TradeError::Other(format!("cancel rejected: {}", ack.msg))),
+            None => Err(TradeError::Other(format!("cancel rejected: {}", resp.msg))),
+        }
+    }
+
+    async fn order_status(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        symbol: &str,
+        client_order_id: &str,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<OrderStatus, TradeError> {
+        let query = format!("instId={symbol}&clientOrderId={client_order_id}");
+        for endpoint in [
+            "/api/v1/trade/orders-pending",
+            "/api/v1/trade/orders-history",
+        ] {
+            let url = format!("{endpoint}?{query}");
+            if let Some(order) = self.find_order(&url, client_order_id).await? {
+                let state = OrderState::from_exchange(&order.state);
+                let data = serde_json::to_value(&order).expect("serialise Order");
+                return Ok(OrderStatus { state, data });
+            }
+        }
+        Ok(OrderStatus {
+            state: OrderState::NotFound,
+            data: Value::Null,
         })
     }
+
+    async fn pending_stops(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        symbol: &str,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<Vec<NativeStop>, TradeError> {
+        let url = format!("/api/v1/trade/orders-tpsl-pending?instId={symbol}");
+        let body: BlowFinResponse<Vec<TpslOrder>> = self.signed_get(&url).await?;
+        Ok(body
+            .into_data()?
+            .into_iter()
+            .filter_map(|o| {
+                Some(NativeStop {
+                    trigger_price: o.sl_trigger_price?,
+                    id: Some(o.tpsl_id),
+                    symbol: o.inst_id,
+                    side: o.side,
+                })
+            })
+            .collect())
+    }
+
+    async fn place_stop(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        stop: &NativeStop,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        // size -1 + reduce-only: close whatever is open when it triggers
+        let body = json!({
+            "instId": stop.symbol,
+            "marginMode": "isolated",
+            "positionSide": "net",
+            "side": stop.side,
+            "slTriggerPrice": stop.trigger_price.to_string(),
+            "slOrderPrice": "-1",
+            "size": "-1",
+            "reduceOnly": "true",
+        });
+        let resp: BlowFinResponse<TpslAck> =
+            self.signed_post("/api/v1/trade/order-tpsl", &body).await?;
+        if resp.is_ok() && resp.data.is_ok() {
+            Ok(())
+        } else {
+            Err(TradeError::Other(format!("stop rejected: {}", resp.msg)))
+        }
+    }
+
+    async fn cancel_stop(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        stop: &NativeStop,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let Some(id) = &stop.id else {
+            return Ok(());
+        };
+        let body = json!([{ "instId": stop.symbol, "tpslId": id }]);
+        let resp: BlowFinResponse<Vec<TpslAck>> =
+            self.signed_post("/api/v1/trade/cancel-tpsl", &body).await?;
+        match resp.data.first() {
+            Some(ack) if resp.is_ok() && ack.is_ok() => Ok(()),
+            Some(ack) => Err(TradeError::Other(format!(
+                "stop cancel rejected: {}",
+                ack.msg
+            ))),
+            None => Err(TradeError::Other(format!(
+                "stop cancel rejected: {}",
+                resp.msg
+            ))),
+        }
+    }
+
+    async fn open_orders(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<Vec<OpenOrder>, TradeError> {
+        let body: BlowFinResponse<Vec<Order>> =
+            self.signed_get("/api/v1/trade/orders-pending").await?;
+        Ok(body
+            .into_data()?
+            .into_iter()
+            .map(|o| OpenOrder {
+                order_id: o.order_id,
+                symbol: o.inst_id,
+            })
+            .collect())
+    }
+
+    async fn cancel_order(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        order: &OpenOrder,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let body = json!({ "instId": order.symbol, "orderId": order.order_id });
+        let resp: BlowFinResponse<Vec<OrderAck>> = self
+            .signed_post("/api/v1/trade/cancel-order", &body)
+            .await?;
+        match resp.data.first() {
+            Some(ack) if resp.is_ok() && ack.is_ok() => Ok(()),
+            Some(ack) => Err(TradeError::Other(format!("cancel rejected: {}", ack.msg))),
+            None => Err(TradeError::Other(format!("cancel rejected: {}", resp.msg))),
+        }
+    }
 }
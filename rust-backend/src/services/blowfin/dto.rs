@@ -0,0 +1,582 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Typed BlowFin REST payloads
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every response is `{ code, msg, data }` – [`BlowFinResponse`] carries
+//!   the payload as `T`; `code == "0"` is success and failures may come with
+//!   `data: null`
+//! * BlowFin sends numbers, flags and timestamps as strings (`"0.04"`,
+//!   `"false"`, `"1697031301187"`, `""` when unset); the `de` helpers accept
+//!   either form, and fields we don't use are ignored
+//! * `tests/fixtures/blowfin/` holds captured responses – a change on their
+//!   side should break those tests before it breaks trading
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::errors::ApiError;
+
+#[derive(Debug, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de> + Default"))]
+pub struct BlowFinResponse<T = Value> {
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+    #[serde(default, deserialize_with = "de::null_default")]
+    pub data: T,
+}
+
+impl<T> BlowFinResponse<T> {
+    pub fn is_ok(&self) -> bool {
+        self.code == "0"
+    }
+
+    /// The payload, or the exchange's rejection as an error
+    #[allow(clippy::result_large_err)]
+    pub fn into_data(self) -> Result<T, ApiError> {
+        if self.is_ok() {
+            Ok(self.data)
+        } else {
+            Err(ApiError::Other(format!(
+                "blowfin code {}: {}",
+                self.code, self.msg
+            )))
+        }
+    }
+}
+
+/// Per-order result of place / cancel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderAck {
+    #[serde(default, deserialize_with = "de::opt_str")]
+    pub order_id: Option<String>,
+    #[serde(default, deserialize_with = "de::opt_str")]
+    pub client_order_id: Option<String>,
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+}
+
+impl OrderAck {
+    pub fn is_ok(&self) -> bool {
+        self.code == "0"
+    }
+}
+
+/// `/trade/orders-pending` and `/trade/orders-history` rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Order {
+    pub order_id: String,
+    #[serde(default, deserialize_with = "de::opt_str")]
+    pub client_order_id: Option<String>,
+    pub inst_id: String,
+    pub margin_mode: String,
+    pub position_side: String,
+    pub side: String,
+    pub order_type: String,
+    /// `None` for market orders
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub price: Option<f64>,
+    #[serde(deserialize_with = "de::num")]
+    pub size: f64,
+    #[serde(default, deserialize_with = "de::flag")]
+    pub reduce_only: bool,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub leverage: Option<f64>,
+    /// `live`, `partially_filled`, `filled`, `canceled`, …
+    pub state: String,
+    #[serde(deserialize_with = "de::num")]
+    pub filled_size: f64,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub filled_amount: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub average_price: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub fee: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub pnl: Option<f64>,
+    /// Unix ms
+    #[serde(deserialize_with = "de::ms")]
+    pub create_time: i64,
+    #[serde(deserialize_with = "de::ms")]
+    pub update_time: i64,
+}
+
+/// `/trade/orders-tpsl-pending` rows – take-profit / stop-loss triggers
+/// resting on the exchange
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TpslOrder {
+    pub tpsl_id: String,
+    pub inst_id: String,
+    pub side: String,
+    /// `-1` = the whole position
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub size: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub tp_trigger_price: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub sl_trigger_price: Option<f64>,
+    #[serde(default)]
+    pub state: String,
+}
+
+/// Result of placing / cancelling a tp/sl order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TpslAck {
+    #[serde(default, deserialize_with = "de::opt_str")]
+    pub tpsl_id: Option<String>,
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+}
+
+impl TpslAck {
+    pub fn is_ok(&self) -> bool {
+        self.code == "0"
+    }
+}
+
+/// `/account/balance` – futures account equity
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Balance {
+    #[serde(deserialize_with = "de::ms")]
+    pub ts: i64,
+    #[serde(deserialize_with = "de::num")]
+    pub total_equity: f64,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub isolated_equity: Option<f64>,
+    #[serde(default)]
+    pub details: Vec<BalanceDetail>,
+}
+
+impl Balance {
+    pub fn currency(&self, ccy: &str) -> Option<&BalanceDetail> {
+        self.details.iter().find(|d| d.currency == ccy)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceDetail {
+    pub currency: String,
+    #[serde(deserialize_with = "de::num")]
+    pub equity: f64,
+    #[serde(deserialize_with = "de::num")]
+    pub available: f64,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub balance: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub available_equity: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub frozen: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub order_frozen: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub equity_usd: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub isolated_unrealized_pnl: Option<f64>,
+}
+
+/// `/account/positions` rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    #[serde(default)]
+    pub position_id: String,
+    pub inst_id: String,
+    #[serde(default)]
+    pub margin_mode: String,
+    /// `long` / `short` in hedge mode, `net` otherwise
+    pub position_side: String,
+    /// Signed in net mode, unsigned per leg in hedge mode
+    #[serde(deserialize_with = "de::num")]
+    pub positions: f64,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub available_positions: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub average_price: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub mark_price: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub margin: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub margin_ratio: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub liquidation_price: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub unrealized_pnl: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub maintenance_margin: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub leverage: Option<f64>,
+}
+
+impl Position {
+    /// Positive long, negative short, whichever position mode
+    pub fn signed_qty(&self) -> f64 {
+        match self.position_side.as_str() {
+            "short" => -self.positions.abs(),
+            "long" => self.positions.abs(),
+            _ => self.positions,
+        }
+    }
+}
+
+/// `/trade/fills-history` rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fill {
+    pub inst_id: String,
+    pub trade_id: String,
+    pub order_id: String,
+    #[serde(deserialize_with = "de::num")]
+    pub fill_price: f64,
+    #[serde(deserialize_with = "de::num")]
+    pub fill_size: f64,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub fill_pnl: Option<f64>,
+    pub side: String,
+    #[serde(default)]
+    pub position_side: String,
+    #[serde(deserialize_with = "de::num")]
+    pub fee: f64,
+    #[serde(deserialize_with = "de::ms")]
+    pub ts: i64,
+}
+
+/// `/asset/deposit-history` rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deposit {
+    pub deposit_id: String,
+    pub currency: String,
+    #[serde(deserialize_with = "de::num")]
+    pub amount: f64,
+    #[serde(default)]
+    pub chain: String,
+    #[serde(default)]
+    pub tx_id: String,
+    /// 0 pending, 1 credited, 2 successful
+    pub state: String,
+    #[serde(deserialize_with = "de::ms")]
+    pub ts: i64,
+}
+
+/// `/asset/withdrawal-history` rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    pub withdraw_id: String,
+    pub currency: String,
+    #[serde(deserialize_with = "de::num")]
+    pub amount: f64,
+    #[serde(default, deserialize_with = "de::opt_num")]
+    pub fee: Option<f64>,
+    #[serde(default)]
+    pub chain: String,
+    #[serde(default)]
+    pub tx_id: String,
+    /// 0 waiting, 1 processing, 2 successful, 3 cancelled, 4 failed
+    pub state: String,
+    #[serde(deserialize_with = "de::ms")]
+    pub ts: i64,
+}
+
+/// `/market/instruments` rows – sizes are in contracts of `contract_value`
+/// base units
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Instrument {
+    pub inst_id: String,
+    #[serde(default)]
+    pub base_currency: String,
+    #[serde(default)]
+    pub quote_currency: String,
+    #[serde(deserialize_with = "de::num")]
+    pub contract_value: f64,
+    #[serde(deserialize_with = "de::num")]
+    pub min_size: f64,
+    #[serde(deserialize_with = "de::num")]
+    pub lot_size: f64,
+    #[serde(deserialize_with = "de::num")]
+    pub tick_size: f64,
+    #[serde(default)]
+    pub state: String,
+}
+
+/// `/market/funding-rate` row – the rate predicted for `funding_time`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRateRow {
+    pub inst_id: String,
+    #[serde(deserialize_with = "de::num")]
+    pub funding_rate: f64,
+    #[serde(deserialize_with = "de::ms")]
+    pub funding_time: i64,
+}
+
+/// Lenient field parsers for BlowFin's stringly-typed JSON
+mod de {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use serde_json::Number;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Num(Number),
+        Bool(bool),
+        Str(String),
+    }
+
+    fn number<E: Error>(raw: Raw) -> Result<Option<f64>, E> {
+        match raw {
+            Raw::Num(n) => n
+                .as_f64()
+                .map(Some)
+                .ok_or_else(|| E::custom("number out of range")),
+            Raw::Str(s) if s.trim().is_empty() => Ok(None),
+            Raw::Str(s) => s
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| E::custom(format!("invalid number `{s}`"))),
+            Raw::Bool(b) => Err(E::custom(format!("expected a number, got {b}"))),
+        }
+    }
+
+    pub fn num<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+        number(Raw::deserialize(d)?)?.ok_or_else(|| D::Error::custom("empty number"))
+    }
+
+    /// `null`, `""` and a missing field are all `None`
+    pub fn opt_num<'de, D: Deserializer<'de>>(d: D) -> Result<Option<f64>, D::Error> {
+        match Option::<Raw>::deserialize(d)? {
+            Some(raw) => number(raw),
+            None => Ok(None),
+        }
+    }
+
+    /// Unix ms, as a string or a number
+    pub fn ms<'de, D: Deserializer<'de>>(d: D) -> Result<i64, D::Error> {
+        match Raw::deserialize(d)? {
+            Raw::Num(n) => n
+                .as_i64()
+                .ok_or_else(|| D::Error::custom("invalid timestamp")),
+            Raw::Str(s) => s
+                .trim()
+                .parse()
+                .map_err(|_| D::Error::custom(format!("invalid timestamp `{s}`"))),
+            Raw::Bool(_) => Err(D::Error::custom("invalid timestamp")),
+        }
+    }
+
+    pub fn flag<'de, D: Deserializer<'de>>(d: D) -> Result<bool, D::Error> {
+        match Option::<Raw>::deserialize(d)? {
+            Some(Raw::Bool(b)) => Ok(b),
+            Some(Raw::Str(s)) => match s.as_str() {
+                "true" => Ok(true),
+                "false" | "" => Ok(false),
+                _ => Err(D::Error::custom(format!("invalid flag `{s}`"))),
+            },
+            Some(Raw::Num(_)) => Err(D::Error::custom("invalid flag")),
+            None => Ok(false),
+        }
+    }
+
+    /// `""` and `null` are `None`
+    pub fn opt_str<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+        Ok(Option::<String>::deserialize(d)?.filter(|s| !s.is_empty()))
+    }
+
+    pub fn null_default<'de, D, T>(d: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de> + Default,
+    {
+        Ok(Option::<T>::deserialize(d)?.unwrap_or_default())
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!("../../../tests/fixtures/blowfin/", $name))
+        };
+    }
+
+    fn parse<T: for<'de> Deserialize<'de> + Default>(raw: &str) -> BlowFinResponse<T> {
+        serde_json::from_str(raw).expect("fixture parses")
+    }
+
+    #[test]
+    fn order_ack_fixture() {
+        let r = parse::<Vec<OrderAck>>(fixture!("place_order.json"));
+        assert!(r.is_ok());
+        let ack = &r.into_data().unwrap()[0];
+        assert!(ack.is_ok());
+        assert_eq!(ack.order_id.as_deref(), Some("28150801"));
+        assert_eq!(
+            ack.client_order_id.as_deref(),
+            Some("rr3f9c1a7d20be44e1a0c5b2d9e8f7")
+        );
+    }
+
+    #[test]
+    fn rejected_order_keeps_the_per_order_reason() {
+        let r = parse::<Vec<OrderAck>>(fixture!("order_rejected.json"));
+        assert!(!r.is_ok());
+        assert_eq!(r.data[0].order_id, None);
+        assert_eq!(r.data[0].code, "102002");
+        assert_eq!(r.data[0].msg, "Insufficient balance");
+        assert!(r.into_data().is_err());
+    }
+
+    #[test]
+    fn null_data_on_errors_is_tolerated() {
+        let r = parse::<Balance>(fixture!("error_no_data.json"));
+        assert_eq!(r.code, "152401");
+        assert_eq!(r.data, Balance::default());
+        let err = parse::<Vec<Position>>(fixture!("error_no_data.json"))
+            .into_data()
+            .unwrap_err();
+        assert!(err.to_string().contains("Access key does not exist"));
+    }
+
+    #[test]
+    fn orders_fixture() {
+        let orders = parse::<Vec<Order>>(fixture!("orders_pending.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(orders.len(), 2);
+
+        let limit = &orders[0];
+        assert_eq!(limit.price, Some(10_000.0));
+        assert_eq!(limit.size, 1.0);
+        assert!(!limit.reduce_only);
+        assert_eq!(limit.state, "live");
+        assert_eq!(limit.create_time, 1_697_031_292_505);
+
+        let market = &orders[1];
+        assert_eq!(market.client_order_id, None, "empty id is no id");
+        assert_eq!(market.price, None);
+        assert!(market.reduce_only);
+        assert_eq!(market.state, "partially_filled");
+        assert_eq!(market.filled_size, 0.04);
+        assert_eq!(market.average_price, Some(30_034.0));
+    }
+
+    #[test]
+    fn tpsl_pending_fixture() {
+        let stops = parse::<Vec<TpslOrder>>(fixture!("tpsl_pending.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(stops.len(), 2);
+        assert_eq!(stops[0].tpsl_id, "1012");
+        assert_eq!(stops[0].side, "sell");
+        assert_eq!(stops[0].sl_trigger_price, Some(26_500.0));
+        assert_eq!(stops[0].tp_trigger_price, None);
+        assert_eq!(stops[1].tp_trigger_price, Some(31_000.0));
+        assert_eq!(stops[1].sl_trigger_price, None);
+    }
+
+    #[test]
+    fn balance_fixture() {
+        let b = parse::<Balance>(fixture!("balance.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(b.ts, 1_697_021_343_571);
+        let usdt = b.currency("USDT").unwrap();
+        assert!((usdt.equity - 10_014_042.988_958_415).abs() < 1e-6);
+        assert!((usdt.available - 9_996_399.470_869_116).abs() < 1e-6);
+        assert_eq!(b.currency("BTC").unwrap().equity_usd, Some(13_500.25));
+        assert!(b.currency("ETH").is_none());
+    }
+
+    #[test]
+    fn positions_fixture() {
+        let p = parse::<Vec<Position>>(fixture!("positions.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(p[0].inst_id, "ETH-USDT");
+        assert_eq!(p[0].signed_qty(), -1.0);
+        assert_eq!(p[0].leverage, Some(3.0));
+        assert_eq!(p[1].signed_qty(), 0.04);
+        assert_eq!(p[1].liquidation_price, None, "empty string is unset");
+        assert_eq!(p[1].mark_price, Some(30_051.5));
+    }
+
+    #[test]
+    fn fills_fixture() {
+        let f = parse::<Vec<Fill>>(fixture!("fills.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(f[0].order_id, "23010711");
+        assert_eq!(f[0].fill_price, 30_034.0);
+        assert_eq!(f[0].fill_size, 0.04);
+        assert_eq!(f[0].fee, 0.720816);
+        assert_eq!(f[0].ts, 1_697_031_301_187);
+    }
+
+    #[test]
+    fn transfer_history_fixtures() {
+        let d = parse::<Vec<Deposit>>(fixture!("deposits.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(d[0].deposit_id, "20231011153514");
+        assert_eq!(d[0].amount, 1_500.0);
+        assert_eq!(d[0].state, "2");
+        assert_eq!(d[0].ts, 1_697_030_101_187);
+
+        let w = parse::<Vec<Withdrawal>>(fixture!("withdrawals.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(w[0].withdraw_id, "2023101116284800001");
+        assert_eq!(w[0].amount, 250.0);
+        assert_eq!(w[0].fee, Some(1.0));
+        assert_eq!(w[0].tx_id, "");
+    }
+
+    #[test]
+    fn instruments_fixture() {
+        let i = parse::<Vec<Instrument>>(fixture!("instruments.json"))
+            .into_data()
+            .unwrap();
+        assert_eq!(i[0].inst_id, "BTC-USDT");
+        assert_eq!(i[0].quote_currency, "USDT");
+        assert_eq!(i[0].contract_value, 0.001);
+        assert_eq!(i[0].tick_size, 0.1);
+        assert_eq!(i[1].tick_size, 0.00001);
+        assert_eq!(i[1].lot_size, 1.0);
+    }
+
+    #[test]
+    fn numbers_may_be_numbers_but_not_garbage() {
+        let p: Position = serde_json::from_value(json!({
+            "instId": "BTC-USDT", "positionSide": "short", "positions": 2,
+            "markPrice": 100.5,
+        }))
+        .unwrap();
+        assert_eq!(p.signed_qty(), -2.0);
+        assert_eq!(p.mark_price, Some(100.5));
+
+        let bad = serde_json::from_value::<Position>(json!({
+            "instId": "BTC-USDT", "positionSide": "net", "positions": "lots",
+        }));
+        assert!(bad.unwrap_err().to_string().contains("invalid number"));
+    }
+}
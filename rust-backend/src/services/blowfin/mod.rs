@@ -1,4 +1,5 @@
-pub(crate) mod api;
+pub mod api;
 pub mod auth;
-pub mod ws;
 pub(crate) mod client;
+pub mod dto;
+pub mod ws;
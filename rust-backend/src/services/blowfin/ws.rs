@@ -22,16 +22,28 @@ use super::auth;
 /// Lightweight depth snapshot used by strategies
 #[derive(Debug, Clone)]
 pub struct DepthFrame {
+    /// `instId` the snapshot belongs to (`BTC-USDT-SWAP`)
+    pub inst_id: String,
     pub bid_sum: f64,
     pub ask_sum: f64,
+    /// Top-of-book prices (books5 levels arrive best-first)
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+    /// `(price, size)` levels, best first
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
     /* optional raw fields for verification */
     pub raw_header: Vec<(String, String)>,
     pub raw_bytes: Vec<u8>,
 }
 
-/// Spawn the WebSocket task and pipe decoded `DepthFrame`s out.
+/// Spawn the WebSocket task and pipe decoded `DepthFrame`s of `insts` out.
 /// *Returns* once the socket closes / errors.
-pub async fn connect_private(settings: &Settings, out: Sender<DepthFrame>) -> Result<(), ApiError> {
+pub async fn connect_private(
+    settings: &Settings,
+    insts: &[String],
+    out: Sender<DepthFrame>,
+) -> Result<(), ApiError> {
     // ----------- 1) Connect ------------------------------------------------
     let url = if settings.is_demo() {
         "wss://demo-trading-openapi.blofin.com/ws/private"
@@ -60,10 +72,7 @@ pub async fn connect_private(settings: &Settings, out: Sender<DepthFrame>) -> Re
     ws.send(Message::Text(login.into())).await?;
 
     // ----------- 3) Subscribe to depth channel ----------------------------
-    let sub = r#"{
-        "op":"subscribe",
-        "args":[{"channel":"books5","instId":"BTC-USDT-SWAP"}]
-    }"#;
+    let sub = subscribe_books5(insts);
     ws.send(Message::Text(sub.into())).await?;
 
     // ----------- 4) Main read-loop ----------------------------------------
@@ -85,6 +94,14 @@ pub async fn connect_private(settings: &Settings, out: Sender<DepthFrame>) -> Re
 
 // ---------- Private helpers -----------------------------------------------
 
+fn subscribe_books5(insts: &[String]) -> String {
+    let args: Vec<Value> = insts
+        .iter()
+        .map(|i| serde_json::json!({ "channel": "books5", "instId": i }))
+        .collect();
+    serde_json::json!({ "op": "subscribe", "args": args }).to_string()
+}
+
 #[derive(Debug, Deserialize)]
 struct WsEvent {
     #[serde(rename = "arg")]
@@ -95,6 +112,8 @@ struct WsEvent {
 #[derive(Debug, Deserialize)]
 struct WsArg {
     channel: String,
+    #[serde(rename = "instId", default)]
+    inst_id: String,
 }
 
 /// Convert the raw JSON → DepthFrame
@@ -112,9 +131,37 @@ fn depth_from_event(ev: &WsEvent) -> Option<DepthFrame> {
             })
             .unwrap_or(0.0)
     };
+    let best = |side: &str| -> Option<f64> {
+        obj.get(side)?
+            .as_array()?
+            .first()?
+            .get(0)?
+            .as_str()?
+            .parse::<f64>()
+            .ok()
+    };
+    let levels = |side: &str| -> Vec<(f64, f64)> {
+        obj.get(side)
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|lvl| {
+                        let px = lvl.get(0)?.as_str()?.parse::<f64>().ok()?;
+                        let sz = lvl.get(1)?.as_str()?.parse::<f64>().ok()?;
+                        Some((px, sz))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
     Some(DepthFrame {
+        inst_id: ev.arg.inst_id.clone(),
         bid_sum: sum_side("bids"),
         ask_sum: sum_side("asks"),
+        best_bid: best("bids"),
+        best_ask: best("asks"),
+        bids: levels("bids"),
+        asks: levels("asks"),
         raw_header: Vec::new(),
         raw_bytes: Vec::new(),
     })
@@ -136,7 +183,7 @@ mod tests {
                 .collect::<Vec<_>>()
         };
         let raw = json!({
-            "arg": { "channel": "books5" },
+            "arg": { "channel": "books5", "instId": "BTC-USDT-SWAP" },
             "data": [{
                 "bids": arrify(bids),
                 "asks": arrify(asks)
@@ -155,6 +202,24 @@ mod tests {
         let df = depth_from_event(&ev).expect("DepthFrame");
         assert!((df.bid_sum - 3.5).abs() < 1e-9);
         assert!((df.ask_sum - 4.0).abs() < 1e-9);
+        assert_eq!(df.best_bid, Some(30000.0));
+        assert_eq!(df.best_ask, Some(30010.0));
+        assert_eq!(df.inst_id, "BTC-USDT-SWAP");
+        assert_eq!(df.bids, [(30000.0, 2.0), (29990.0, 1.5)]);
+    }
+
+    #[test]
+    fn subscribes_books5_per_instrument() {
+        let sub: Value = serde_json::from_str(&subscribe_books5(&[
+            "BTC-USDT-SWAP".into(),
+            "ETH-USDT-SWAP".into(),
+        ]))
+        .unwrap();
+        assert_eq!(sub["op"], "subscribe");
+        assert_eq!(
+            sub["args"][1],
+            json!({ "channel": "books5", "instId": "ETH-USDT-SWAP" })
+        );
     }
 
     // ──────────────────────────────────────────────────────────
@@ -0,0 +1,407 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Market calendars
+//! ──────────────────────────────────────────────────────────────────────────
+//! When a symbol trades. Crypto is 24/7; equity and FX connectors follow
+//! exchange sessions. Calendars come from `MARKET_CALENDARS`, one
+//! `;`-separated entry per symbol class:
+//!
+//! ```text
+//! crypto:24/7;equity:America/New_York mon-fri 09:30-16:00 closed=2025-12-25
+//! ```
+//!
+//! * A session opens at `start` (exchange-local time, DST-aware) on every
+//!   listed day and closes at `end` – on the next day when `end <= start`,
+//!   so FX's `sun-thu 17:00-17:00` runs Sunday 17:00 → Friday 17:00
+//! * `closed=` lists local trading dates whose session never opens
+//! * `SYMBOL_CLASSES` assigns symbols to classes (`equity:AAPL,MSFT`);
+//!   unlisted symbols – and classes without a calendar – trade 24/7
+//! * Orders on a closed market are refused (`risk::check_market_open`);
+//!   session filters ask [`Calendar::session`] for the current session
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+
+use crate::services::market_data::bus_symbol;
+
+/// How far `next_open` looks ahead (long holiday runs included)
+const MAX_SCAN_DAYS: i64 = 31;
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolClass {
+    Crypto,
+    Equity,
+    Fx,
+}
+
+impl FromStr for SymbolClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "crypto" => Ok(Self::Crypto),
+            "equity" => Ok(Self::Equity),
+            "fx" => Ok(Self::Fx),
+            other => Err(format!(
+                "unknown symbol class `{other}` (crypto, equity, fx)"
+            )),
+        }
+    }
+}
+
+/// One trading session
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Session {
+    pub open: DateTime<Utc>,
+    pub close: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calendar {
+    AlwaysOpen,
+    Sessions(Sessions),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sessions {
+    pub tz: Tz,
+    /// Trading days, Monday first
+    pub days: [bool; 7],
+    pub start: NaiveTime,
+    /// `<= start` closes on the following day
+    pub end: NaiveTime,
+    /// Local trading dates without a session
+    pub closed: BTreeSet<NaiveDate>,
+}
+
+impl Sessions {
+    /// The session trading date `d` opens, if it trades
+    fn on(&self, d: NaiveDate) -> Option<Session> {
+        if !self.days[d.weekday().num_days_from_monday() as usize] || self.closed.contains(&d) {
+            return None;
+        }
+        let close_day = if self.end <= self.start {
+            d.succ_opt()?
+        } else {
+            d
+        };
+        Some(Session {
+            open: local(self.tz, d, self.start)?,
+            close: local(self.tz, close_day, self.end)?,
+        })
+    }
+
+    fn trading_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.tz).date_naive()
+    }
+}
+
+/// `d` at `t` in `tz`; a time skipped by a DST change moves an hour later
+fn local(tz: Tz, d: NaiveDate, t: NaiveTime) -> Option<DateTime<Utc>> {
+    let naive = d.and_time(t);
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+impl Calendar {
+    /// The session `at` falls in; `None` when closed – and always for 24/7
+    /// markets, which have no sessions
+    pub fn session(&self, at: DateTime<Utc>) -> Option<Session> {
+        let Calendar::Sessions(s) = self else {
+            return None;
+        };
+        let today = s.trading_date(at);
+        // an overnight session may have opened the day before
+        [today.pred_opt(), Some(today)]
+            .into_iter()
+            .flatten()
+            .filter_map(|d| s.on(d))
+            .find(|sess| sess.open <= at && at < sess.close)
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        match self {
+            Calendar::AlwaysOpen => true,
+            Calendar::Sessions(_) => self.session(at).is_some(),
+        }
+    }
+
+    /// `at` itself when open, else the next session's open within
+    /// `MAX_SCAN_DAYS`
+    pub fn next_open(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let Calendar::Sessions(s) = self else {
+            return Some(at);
+        };
+        if self.session(at).is_some() {
+            return Some(at);
+        }
+        let today = s.trading_date(at);
+        (0..=MAX_SCAN_DAYS)
+            .filter_map(|i| s.on(today + Duration::days(i)))
+            .map(|sess| sess.open)
+            .find(|open| *open > at)
+    }
+}
+
+fn parse_day(s: &str) -> Result<usize, String> {
+    DAY_NAMES
+        .iter()
+        .position(|d| *d == s.trim())
+        .ok_or(format!("bad day `{s}` (mon … sun)"))
+}
+
+/// `mon-fri`, `sun-thu` (wraps), `sat,sun`, `mon-wed,fri`
+fn parse_days(s: &str) -> Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    for item in s.split(',') {
+        match item.split_once('-') {
+            Some((a, b)) => {
+                let (a, b) = (parse_day(a)?, parse_day(b)?);
+                let mut d = a;
+                loop {
+                    days[d] = true;
+                    if d == b {
+                        break;
+                    }
+                    d = (d + 1) % 7;
+                }
+            }
+            None => days[parse_day(item)?] = true,
+        }
+    }
+    Ok(days)
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| format!("bad time `{s}` (HH:MM)"))
+}
+
+impl FromStr for Calendar {
+    type Err = String;
+
+    /// `24/7` or `TZ DAYS HH:MM-HH:MM [closed=YYYY-MM-DD,…]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "24/7" {
+            return Ok(Calendar::AlwaysOpen);
+        }
+        let mut parts = s.split_whitespace();
+        let (Some(tz), Some(days), Some(hours)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(format!(
+                "expected `24/7` or `TZ DAYS HH:MM-HH:MM`, got `{s}`"
+            ));
+        };
+        let tz: Tz = tz
+            .parse()
+            .map_err(|_| format!("unknown time zone `{tz}`"))?;
+        let days = parse_days(days)?;
+        let (start, end) = hours
+            .split_once('-')
+            .ok_or(format!("expected HH:MM-HH:MM, got `{hours}`"))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+
+        let mut closed = BTreeSet::new();
+        for extra in parts {
+            let dates = extra
+                .strip_prefix("closed=")
+                .ok_or(format!("unknown option `{extra}`"))?;
+            for d in dates.split(',').filter(|d| !d.is_empty()) {
+                let d = NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                    .map_err(|_| format!("closed: bad date `{d}` (YYYY-MM-DD)"))?;
+                closed.insert(d);
+            }
+        }
+        Ok(Calendar::Sessions(Sessions {
+            tz,
+            days,
+            start,
+            end,
+            closed,
+        }))
+    }
+}
+
+/// Parse a whole `MARKET_CALENDARS` value
+pub fn parse_calendars(spec: &str) -> Result<HashMap<SymbolClass, Calendar>, String> {
+    let mut out = HashMap::new();
+    for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (class, cal) = entry
+            .split_once(':')
+            .ok_or(format!("expected CLASS:calendar, got `{entry}`"))?;
+        let class: SymbolClass = class.parse()?;
+        let cal = cal.parse().map_err(|e| format!("{class:?}: {e}"))?;
+        if out.insert(class, cal).is_some() {
+            return Err(format!("{class:?}: listed twice"));
+        }
+    }
+    Ok(out)
+}
+
+/// Parse a whole `SYMBOL_CLASSES` value, keyed by normalised symbol
+pub fn parse_classes(spec: &str) -> Result<HashMap<String, SymbolClass>, String> {
+    let mut out = HashMap::new();
+    for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (class, symbols) = entry
+            .split_once(':')
+            .ok_or(format!("expected CLASS:SYMBOL,…, got `{entry}`"))?;
+        let class: SymbolClass = class.parse()?;
+        for sym in symbols.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if let Some(prev) = out.insert(bus_symbol(sym), class) {
+                if prev != class {
+                    return Err(format!("{sym}: in both {prev:?} and {class:?}"));
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+struct Registry {
+    calendars: HashMap<SymbolClass, Calendar>,
+    classes: HashMap<String, SymbolClass>,
+}
+
+static REGISTRY: OnceCell<Registry> = OnceCell::new();
+static ALWAYS_OPEN: Calendar = Calendar::AlwaysOpen;
+
+/// Install `MARKET_CALENDARS` / `SYMBOL_CLASSES` (call once from `main`;
+/// everything trades 24/7 otherwise)
+pub fn init(calendars: HashMap<SymbolClass, Calendar>, classes: HashMap<String, SymbolClass>) {
+    let _ = REGISTRY.set(Registry { calendars, classes });
+}
+
+pub fn class_of(symbol: &str) -> SymbolClass {
+    REGISTRY
+        .get()
+        .and_then(|r| r.classes.get(&bus_symbol(symbol)).copied())
+        .unwrap_or(SymbolClass::Crypto)
+}
+
+pub fn calendar_for(symbol: &str) -> &'static Calendar {
+    let class = class_of(symbol);
+    REGISTRY
+        .get()
+        .and_then(|r| r.calendars.get(&class))
+        .unwrap_or(&ALWAYS_OPEN)
+}
+
+/// `Err(reason)` when `symbol`'s market is closed at `now`
+pub fn check_open(symbol: &str, now: DateTime<Utc>) -> Result<(), String> {
+    let cal = calendar_for(symbol);
+    if cal.is_open(now) {
+        return Ok(());
+    }
+    Err(match cal.next_open(now) {
+        Some(t) => format!("{symbol} market is closed until {}", t.to_rfc3339()),
+        None => format!("{symbol} market is closed"),
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn nyse() -> Calendar {
+        "America/New_York mon-fri 09:30-16:00 closed=2025-07-04"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn equity_sessions_follow_local_time_and_dst() {
+        let cal = nyse();
+        // Tue 2025-07-08 (EDT, UTC-4): 09:30 local = 13:30 UTC
+        assert!(!cal.is_open(utc(2025, 7, 8, 13, 29)));
+        assert!(cal.is_open(utc(2025, 7, 8, 13, 30)));
+        assert!(!cal.is_open(utc(2025, 7, 8, 20, 0)));
+        // Tue 2025-01-07 (EST, UTC-5): 09:30 local = 14:30 UTC
+        assert!(!cal.is_open(utc(2025, 1, 7, 14, 0)));
+        assert_eq!(
+            cal.session(utc(2025, 1, 7, 15, 0)),
+            Some(Session {
+                open: utc(2025, 1, 7, 14, 30),
+                close: utc(2025, 1, 7, 21, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn weekends_and_holidays_skip_to_the_next_session() {
+        let cal = nyse();
+        // Thu 2025-07-03 after the close → Fri 07-04 is a holiday → Mon 07-07
+        let after = utc(2025, 7, 3, 21, 0);
+        assert!(!cal.is_open(utc(2025, 7, 4, 15, 0)));
+        assert_eq!(cal.next_open(after), Some(utc(2025, 7, 7, 13, 30)));
+        let open = utc(2025, 7, 7, 14, 0);
+        assert_eq!(cal.next_open(open), Some(open));
+    }
+
+    #[test]
+    fn overnight_sessions_wrap_into_the_next_day() {
+        let fx: Calendar = "America/New_York sun-thu 17:00-17:00".parse().unwrap();
+        // Sunday 2025-07-06 17:00 EDT = 21:00 UTC opens the week
+        assert!(!fx.is_open(utc(2025, 7, 6, 20, 59)));
+        assert!(fx.is_open(utc(2025, 7, 6, 21, 0)));
+        assert!(fx.is_open(utc(2025, 7, 9, 3, 0)), "mid-week, overnight");
+        // Friday 17:00 EDT closes it until Sunday
+        assert!(fx.is_open(utc(2025, 7, 11, 20, 59)));
+        assert!(!fx.is_open(utc(2025, 7, 12, 12, 0)));
+        assert_eq!(
+            fx.next_open(utc(2025, 7, 11, 21, 0)),
+            Some(utc(2025, 7, 13, 21, 0))
+        );
+    }
+
+    #[test]
+    fn crypto_never_closes() {
+        let at = utc(2025, 12, 25, 3, 0);
+        assert!(Calendar::AlwaysOpen.is_open(at));
+        assert_eq!(Calendar::AlwaysOpen.session(at), None);
+        assert_eq!(Calendar::AlwaysOpen.next_open(at), Some(at));
+    }
+
+    #[test]
+    fn specs_parse_and_reject_garbage() {
+        let cals = parse_calendars(
+            "crypto:24/7;equity:America/New_York mon-fri 09:30-16:00;fx:UTC sun-thu 22:00-22:00",
+        )
+        .unwrap();
+        assert_eq!(cals[&SymbolClass::Crypto], Calendar::AlwaysOpen);
+        let Calendar::Sessions(fx) = &cals[&SymbolClass::Fx] else {
+            panic!("fx has sessions");
+        };
+        assert_eq!(fx.days, [true, true, true, true, false, false, true]);
+
+        assert!(parse_calendars("stocks:24/7").is_err());
+        assert!(parse_calendars("equity:Mars/Olympus mon-fri 09:30-16:00").is_err());
+        assert!(parse_calendars("equity:UTC monday 09:30-16:00").is_err());
+        assert!(parse_calendars("equity:UTC mon-fri 9h-16h").is_err());
+        assert!(parse_calendars("equity:UTC mon-fri 09:30-16:00 closed=xmas").is_err());
+        assert!(parse_calendars("crypto:24/7;crypto:24/7").is_err());
+
+        let classes = parse_classes("equity:AAPL,msft;fx:EUR/USD").unwrap();
+        assert_eq!(classes["MSFT"], SymbolClass::Equity);
+        assert_eq!(classes["EURUSD"], SymbolClass::Fx);
+        assert!(parse_classes("equity:AAPL;fx:AAPL").is_err());
+    }
+}
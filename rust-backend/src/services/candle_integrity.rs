@@ -0,0 +1,537 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Candle provenance & integrity
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every row in `candles` carries the `source` it came from (`binance:ws`,
+//!   `binance:rest`, `blowfin:rest`, `import:csv`, `import:s3`, `rollup`)
+//!   and a [`checksum`] over its values; writers that can't compute one in
+//!   Rust (SQL rollups, rows from before labelling) leave it NULL and
+//!   [`seal`] fills it in
+//! * [`verify`] re-hashes a window of stored bars – a changed bar means the
+//!   row was edited behind our back – and cross-checks them against the
+//!   configured `history` provider: differing values and bars the reference
+//!   has but we don't are flagged too
+//! * Findings land in `candle_discrepancies` (one row per bar and kind,
+//!   refreshed on every run) and `candle_discrepancies_total{kind}`
+//! * [`spawn`] seals and verifies the last `VERIFY_WINDOW` of every series
+//!   each `CANDLE_VERIFY_INTERVAL_SECS`
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use metrics::increment_counter;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+
+use crate::services::history::{self, HistoryError, HistoryProvider};
+use crate::services::strategies::Candle;
+
+/// How far back the periodic job looks
+pub const VERIFY_WINDOW: Duration = Duration::hours(48);
+/// Relative difference below which two values count as equal
+const TOLERANCE: f64 = 1e-9;
+const SEAL_BATCH: i64 = 5_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum IntegrityError {
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("{0}")]
+    History(#[from] HistoryError),
+}
+
+/// First 16 hex chars of sha256 over the bar's identity and values
+pub fn checksum(symbol: &str, interval: &str, c: &Candle) -> String {
+    let canonical = format!(
+        "{symbol}|{interval}|{}|{:?}|{:?}|{:?}|{:?}|{:?}",
+        c.ts.timestamp_millis(),
+        c.open,
+        c.high,
+        c.low,
+        c.close,
+        c.volume
+    );
+    hex::encode(&Sha256::digest(canonical.as_bytes())[..8])
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mismatch {
+    pub field: &'static str,
+    pub stored: f64,
+    pub reference: f64,
+}
+
+fn differs(a: f64, b: f64) -> bool {
+    (a - b).abs() > TOLERANCE * a.abs().max(b.abs()).max(1e-12)
+}
+
+/// Fields where `stored` and `reference` disagree
+pub fn diff(stored: &Candle, reference: &Candle) -> Vec<Mismatch> {
+    [
+        ("open", stored.open, reference.open),
+        ("high", stored.high, reference.high),
+        ("low", stored.low, reference.low),
+        ("close", stored.close, reference.close),
+        ("volume", stored.volume, reference.volume),
+    ]
+    .into_iter()
+    .filter(|(_, a, b)| differs(*a, *b))
+    .map(|(field, stored, reference)| Mismatch {
+        field,
+        stored,
+        reference,
+    })
+    .collect()
+}
+
+// ─── Writing ─────────────────────────────────────────────────────────────
+
+/// Store bars fetched from `source`; bars already on file are kept
+pub async fn store(
+    db: &PgPool,
+    symbol: &str,
+    interval: &str,
+    source: &str,
+    bars: &[Candle],
+) -> Result<u64, sqlx::Error> {
+    if bars.is_empty() {
+        return Ok(0);
+    }
+    let mut qb = sqlx::QueryBuilder::new(
+        "INSERT INTO candles (symbol, interval, ts, open, high, low, close, volume, source, checksum) ",
+    );
+    qb.push_values(bars, |mut b, c| {
+        b.push_bind(symbol)
+            .push_bind(interval)
+            .push_bind(c.ts)
+            .push_bind(c.open)
+            .push_bind(c.high)
+            .push_bind(c.low)
+            .push_bind(c.close)
+            .push_bind(c.volume)
+            .push_bind(source)
+            .push_bind(checksum(symbol, interval, c));
+    });
+    qb.push(" ON CONFLICT (symbol, interval, ts) DO NOTHING");
+    Ok(qb.build().execute(db).await?.rows_affected())
+}
+
+#[derive(FromRow)]
+struct Unsealed {
+    symbol: String,
+    interval: String,
+    ts: DateTime<Utc>,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+}
+
+/// Checksum every bar that has none yet; returns how many were sealed
+pub async fn seal(db: &PgPool) -> Result<u64, sqlx::Error> {
+    let mut sealed = 0;
+    loop {
+        let rows = sqlx::query_as::<_, Unsealed>(
+            r#"
+            SELECT symbol, interval, ts, open, high, low, close, volume
+              FROM candles
+             WHERE checksum IS NULL
+             LIMIT $1
+            "#,
+        )
+        .bind(SEAL_BATCH)
+        .fetch_all(db)
+        .await?;
+        if rows.is_empty() {
+            return Ok(sealed);
+        }
+
+        let (mut symbols, mut intervals, mut stamps, mut sums) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for r in rows {
+            let c = Candle {
+                ts: r.ts,
+                open: r.open,
+                high: r.high,
+                low: r.low,
+                close: r.close,
+                volume: r.volume,
+                delta: None,
+            };
+            sums.push(checksum(&r.symbol, &r.interval, &c));
+            symbols.push(r.symbol);
+            intervals.push(r.interval);
+            stamps.push(r.ts);
+        }
+        sealed += sqlx::query(
+            r#"
+            UPDATE candles c
+               SET checksum = v.checksum
+              FROM UNNEST($1::text[], $2::text[], $3::timestamptz[], $4::text[])
+                   AS v(symbol, interval, ts, checksum)
+             WHERE c.symbol = v.symbol AND c.interval = v.interval AND c.ts = v.ts
+               AND c.checksum IS NULL
+            "#,
+        )
+        .bind(&symbols)
+        .bind(&intervals)
+        .bind(&stamps)
+        .bind(&sums)
+        .execute(db)
+        .await?
+        .rows_affected();
+    }
+}
+
+// ─── Verification ────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub ts: DateTime<Utc>,
+    /// `checksum` | `mismatch` | `gap`
+    pub kind: &'static str,
+    pub details: Value,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct StoredBar {
+    pub ts: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub source: String,
+    pub checksum: Option<String>,
+}
+
+impl StoredBar {
+    fn candle(&self) -> Candle {
+        Candle {
+            ts: self.ts,
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume,
+            delta: None,
+        }
+    }
+}
+
+/// Compare stored bars (oldest first) with their checksums and, when given,
+/// with the `(source, bars)` of a reference provider. Bars that came from
+/// that same source aren't compared with it
+pub fn check(
+    symbol: &str,
+    interval: &str,
+    stored: &[StoredBar],
+    reference: Option<(&str, &[Candle])>,
+) -> Vec<Finding> {
+    let mut out = Vec::new();
+    for bar in stored {
+        let expected = checksum(symbol, interval, &bar.candle());
+        if let Some(sum) = bar.checksum.as_deref().filter(|s| *s != expected) {
+            out.push(Finding {
+                ts: bar.ts,
+                kind: "checksum",
+                details: json!({ "source": bar.source, "stored": sum, "expected": expected }),
+            });
+        }
+    }
+
+    let Some((name, reference)) = reference else {
+        return out;
+    };
+    let by_ts: HashMap<DateTime<Utc>, &StoredBar> = stored.iter().map(|b| (b.ts, b)).collect();
+    let (first, last) = match (stored.first(), stored.last()) {
+        (Some(f), Some(l)) => (f.ts, l.ts),
+        _ => return out,
+    };
+    for r in reference {
+        match by_ts.get(&r.ts) {
+            Some(bar) if bar.source != name => {
+                let fields = diff(&bar.candle(), r);
+                if !fields.is_empty() {
+                    out.push(Finding {
+                        ts: r.ts,
+                        kind: "mismatch",
+                        details: json!({
+                            "source": bar.source,
+                            "reference": name,
+                            "fields": fields,
+                        }),
+                    });
+                }
+            }
+            Some(_) => {}
+            // only holes inside what we recorded; the edges are just range
+            None if r.ts > first && r.ts < last => out.push(Finding {
+                ts: r.ts,
+                kind: "gap",
+                details: json!({ "reference": name }),
+            }),
+            None => {}
+        }
+    }
+    out.sort_by_key(|f| f.ts);
+    out
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub symbol: String,
+    pub interval: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub checked: usize,
+    /// Provider the bars were compared with, if any
+    pub reference: Option<&'static str>,
+    pub findings: Vec<Finding>,
+}
+
+/// Verify `[from, to)` of one series and record what was found
+pub async fn verify(
+    db: &PgPool,
+    reference: Option<&dyn HistoryProvider>,
+    symbol: &str,
+    interval: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<VerifyReport, IntegrityError> {
+    let stored = sqlx::query_as::<_, StoredBar>(
+        r#"
+        SELECT ts, open, high, low, close, volume, source, checksum
+          FROM candles
+         WHERE symbol = $1 AND interval = $2 AND ts >= $3 AND ts < $4
+         ORDER BY ts
+        "#,
+    )
+    .bind(symbol)
+    .bind(interval)
+    .bind(from)
+    .bind(to)
+    .fetch_all(db)
+    .await?;
+
+    let ref_bars = match reference {
+        Some(p) if !stored.is_empty() => {
+            Some((p.name(), p.candles(symbol, interval, from, to).await?))
+        }
+        _ => None,
+    };
+    let findings = check(
+        symbol,
+        interval,
+        &stored,
+        ref_bars.as_ref().map(|(n, b)| (*n, b.as_slice())),
+    );
+
+    for f in &findings {
+        increment_counter!("candle_discrepancies_total", "kind" => f.kind);
+        sqlx::query(
+            r#"
+            INSERT INTO candle_discrepancies (symbol, interval, ts, kind, details)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (symbol, interval, ts, kind)
+            DO UPDATE SET details = EXCLUDED.details, detected_at = now()
+            "#,
+        )
+        .bind(symbol)
+        .bind(interval)
+        .bind(f.ts)
+        .bind(f.kind)
+        .bind(&f.details)
+        .execute(db)
+        .await?;
+    }
+    if !findings.is_empty() {
+        tracing::warn!(
+            "candle integrity: {symbol} {interval}: {} discrepancies in {from}..{to}",
+            findings.len()
+        );
+    }
+
+    Ok(VerifyReport {
+        symbol: symbol.into(),
+        interval: interval.into(),
+        from,
+        to,
+        checked: stored.len(),
+        reference: ref_bars.map(|(n, _)| n),
+        findings,
+    })
+}
+
+/// Seal, then verify the last `window` of every series with recent bars
+pub async fn run_once(db: &PgPool, window: Duration) -> Result<Vec<VerifyReport>, IntegrityError> {
+    let sealed = seal(db).await?;
+    if sealed > 0 {
+        tracing::info!("candle integrity: sealed {sealed} bars");
+    }
+
+    let to = Utc::now();
+    let from = to - window;
+    let series: Vec<(String, String)> = sqlx::query_as(
+        "SELECT DISTINCT symbol, interval FROM candles WHERE ts >= $1 ORDER BY symbol, interval",
+    )
+    .bind(from)
+    .fetch_all(db)
+    .await?;
+
+    let provider = history::provider();
+    let mut out = Vec::with_capacity(series.len());
+    for (symbol, interval) in series {
+        out.push(verify(db, provider.as_deref(), &symbol, &interval, from, to).await?);
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Discrepancy {
+    pub symbol: String,
+    pub interval: String,
+    pub ts: DateTime<Utc>,
+    pub kind: String,
+    pub details: Value,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Most recently detected first
+pub async fn discrepancies(db: &PgPool, limit: i64) -> Result<Vec<Discrepancy>, sqlx::Error> {
+    sqlx::query_as::<_, Discrepancy>(
+        r#"
+        SELECT symbol, interval, ts, kind, details, detected_at
+          FROM candle_discrepancies
+         ORDER BY detected_at DESC, ts DESC
+         LIMIT $1
+        "#,
+    )
+    .bind(limit.clamp(1, 1_000))
+    .fetch_all(db)
+    .await
+}
+
+/// Periodic seal + verify; 0 disables it
+pub fn spawn(db: PgPool, every: StdDuration) {
+    if every.is_zero() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(every);
+        loop {
+            tick.tick().await;
+            if let Err(e) = run_once(&db, VERIFY_WINDOW).await {
+                tracing::error!("candle integrity: {e}");
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn bar(h: u32, close: f64) -> Candle {
+        Candle {
+            ts: Utc.with_ymd_and_hms(2025, 7, 1, h, 0, 0).unwrap(),
+            open: 100.0,
+            high: 110.0,
+            low: 90.0,
+            close,
+            volume: 5.0,
+            delta: None,
+        }
+    }
+
+    fn stored(c: &Candle, source: &str, sum: Option<String>) -> StoredBar {
+        StoredBar {
+            ts: c.ts,
+            open: c.open,
+            high: c.high,
+            low: c.low,
+            close: c.close,
+            volume: c.volume,
+            source: source.into(),
+            checksum: sum,
+        }
+    }
+
+    #[test]
+    fn checksums_cover_identity_and_every_value() {
+        let c = bar(4, 105.0);
+        let sum = checksum("BTCUSDT", "4h", &c);
+        assert_eq!(sum.len(), 16);
+        assert_eq!(sum, checksum("BTCUSDT", "4h", &{ c }));
+        assert_ne!(sum, checksum("ETHUSDT", "4h", &c));
+        assert_ne!(sum, checksum("BTCUSDT", "1h", &c));
+        assert_ne!(sum, checksum("BTCUSDT", "4h", &bar(4, 105.000_000_1)));
+        assert_ne!(sum, checksum("BTCUSDT", "4h", &bar(8, 105.0)));
+    }
+
+    #[test]
+    fn edited_rows_fail_their_checksum() {
+        let good = bar(0, 100.0);
+        let mut edited = stored(&bar(4, 101.0), "binance:ws", None);
+        edited.checksum = Some(checksum("BTCUSDT", "4h", &bar(4, 100.0)));
+        let rows = [
+            stored(&good, "binance:ws", Some(checksum("BTCUSDT", "4h", &good))),
+            edited,
+            stored(&bar(8, 100.0), "rollup", None), // not sealed yet
+        ];
+        let found = check("BTCUSDT", "4h", &rows, None);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].ts, found[0].kind), (bar(4, 0.0).ts, "checksum"));
+        assert_eq!(found[0].details["source"], "binance:ws");
+    }
+
+    #[test]
+    fn sources_are_cross_checked() {
+        let rows: Vec<StoredBar> = [
+            (0, 100.0, "binance:ws"),
+            (4, 101.0, "binance:ws"),
+            (12, 103.0, "binance:rest"),
+        ]
+        .iter()
+        .map(|(h, c, s)| {
+            let b = bar(*h, *c);
+            stored(&b, s, Some(checksum("BTCUSDT", "4h", &b)))
+        })
+        .collect();
+        let reference = [
+            bar(0, 100.0),
+            bar(4, 101.5),  // disagrees with the WS bar
+            bar(8, 102.0),  // missing locally
+            bar(12, 999.0), // came from the reference itself – not compared
+            bar(16, 104.0), // past what we recorded – not a gap
+        ];
+        let found = check("BTCUSDT", "4h", &rows, Some(("binance:rest", &reference)));
+        let kinds: Vec<_> = found.iter().map(|f| f.kind).collect();
+        assert_eq!(kinds, vec!["mismatch", "gap"]);
+        assert_eq!(
+            found[0].details["fields"],
+            json!([{ "field": "close", "stored": 101.0, "reference": 101.5 }])
+        );
+        assert_eq!(found[0].details["source"], "binance:ws");
+        assert_eq!(found[1].ts, bar(8, 0.0).ts);
+    }
+
+    #[test]
+    fn tiny_float_noise_is_not_a_mismatch() {
+        let a = bar(0, 100.0);
+        let mut b = a;
+        b.close = 100.0 + 1e-12;
+        assert!(diff(&a, &b).is_empty());
+        b.volume = 5.01;
+        assert_eq!(diff(&a, &b)[0].field, "volume");
+    }
+}
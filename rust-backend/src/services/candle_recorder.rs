@@ -0,0 +1,73 @@
+//! Persists every candle published on the `MarketBus`, whatever its symbol,
+//! into `candles` through the batched writer (duplicates from reconnects are
+//! ignored), labelled `binance:ws` and checksummed – see `candle_integrity`.
+
+use sqlx::query_builder::Separated;
+use sqlx::{PgPool, Postgres};
+use std::sync::Arc;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::db::batch::{BatchConfig, BatchRow, BatchWriter};
+use crate::services::candle_integrity::checksum;
+use crate::services::market_data::{BusCandle, MarketBus};
+use crate::services::strategies::Candle;
+
+/// `candles.source` of bars recorded off the bus
+pub const BUS_SOURCE: &str = "binance:ws";
+
+#[derive(Debug, Clone)]
+pub struct CandleRow {
+    pub symbol: String,
+    pub interval: &'static str,
+    pub source: &'static str,
+    pub candle: Candle,
+}
+
+impl BatchRow for CandleRow {
+    const TABLE: &'static str = "candles";
+    const COLUMNS: &'static [&'static str] = &[
+        "symbol", "interval", "ts", "open", "high", "low", "close", "volume", "source", "checksum",
+    ];
+    const ON_CONFLICT: &'static str = "ON CONFLICT (symbol, interval, ts) DO NOTHING";
+
+    fn bind_row<'args>(&self, b: &mut Separated<'_, 'args, Postgres, &'static str>) {
+        let c = &self.candle;
+        b.push_bind(self.symbol.clone())
+            .push_bind(self.interval)
+            .push_bind(c.ts)
+            .push_bind(c.open)
+            .push_bind(c.high)
+            .push_bind(c.low)
+            .push_bind(c.close)
+            .push_bind(c.volume)
+            .push_bind(self.source)
+            .push_bind(checksum(&self.symbol, self.interval, c));
+    }
+}
+
+/// Spawn the recorder task over every symbol and interval.
+pub fn spawn(pool: PgPool, bus: Arc<MarketBus>) {
+    let writer = BatchWriter::<CandleRow>::spawn(pool, BatchConfig::default());
+    tokio::spawn(record(bus.all_candles(), writer));
+}
+
+async fn record(mut rx: Receiver<BusCandle>, w: BatchWriter<CandleRow>) {
+    loop {
+        match rx.recv().await {
+            Ok(BusCandle {
+                symbol,
+                interval,
+                candle,
+            }) => {
+                w.try_push(CandleRow {
+                    symbol,
+                    interval,
+                    source: BUS_SOURCE,
+                    candle,
+                });
+            }
+            Err(RecvError::Lagged(n)) => tracing::warn!("candle recorder: lagged {n}"),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Candle retention & compaction
+//! ──────────────────────────────────────────────────────────────────────────
+//! Policies come from `CANDLE_RETENTION`, one `;`-separated entry per
+//! interval; intervals without an entry are kept forever:
+//!
+//! ```text
+//! 1m:90d>1h;5m:90d>1h;1h:forever
+//! ```
+//!
+//! * `1m:90d>1h` – 1 m bars older than 90 days are aggregated into 1 h bars
+//!   (first open, max high, min low, last close, summed volume) and then
+//!   deleted; without `>rollup` they are simply deleted
+//! * The cutoff is rounded down to a whole rollup bar, so a bucket is always
+//!   compacted in one go and never from half its inputs
+//! * A rollup bar that already exists (recorded live) wins over the
+//!   aggregate; aggregates are labelled `rollup` and checksummed later by
+//!   `candle_integrity::seal`
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::str::FromStr;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use metrics::counter;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+/// Bar length of an interval label (`1m`, `15m`, `1h`, `4h`, `1d`, `1w`)
+pub fn interval_secs(interval: &str) -> Option<i64> {
+    let (n, unit) = interval.split_at(interval.len().checked_sub(1)?);
+    let n: i64 = n.parse().ok().filter(|n| *n > 0)?;
+    let unit = match unit {
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return None,
+    };
+    Some(n * unit)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetentionPolicy {
+    pub interval: String,
+    /// `None` = forever
+    pub keep_days: Option<i64>,
+    /// Coarser interval expiring bars are folded into
+    pub rollup: Option<String>,
+}
+
+impl RetentionPolicy {
+    /// Bars closing at or before this are compacted; `None` when kept forever
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let keep = Duration::days(self.keep_days?);
+        let step = interval_secs(self.rollup.as_deref().unwrap_or(&self.interval))?;
+        let edge = (now - keep).timestamp();
+        Utc.timestamp_opt(edge - edge.rem_euclid(step), 0).single()
+    }
+}
+
+impl FromStr for RetentionPolicy {
+    type Err = String;
+
+    /// `INTERVAL:KEEP[>ROLLUP]`, `KEEP` being `Nd` or `forever`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (interval, rest) = s
+            .split_once(':')
+            .ok_or(format!("expected INTERVAL:KEEP, got `{s}`"))?;
+        let interval = interval.trim().to_string();
+        let src = interval_secs(&interval).ok_or(format!("bad interval `{interval}`"))?;
+        let (keep, rollup) = match rest.split_once('>') {
+            Some((k, r)) => (k.trim(), Some(r.trim().to_string())),
+            None => (rest.trim(), None),
+        };
+        let keep_days = match keep {
+            "forever" => None,
+            k => match k.strip_suffix('d').and_then(|d| d.parse::<i64>().ok()) {
+                Some(d) if d > 0 => Some(d),
+                _ => return Err(format!("{interval}: bad retention `{k}` (Nd or forever)")),
+            },
+        };
+        if let Some(r) = &rollup {
+            let dst = interval_secs(r).ok_or(format!("{interval}: bad rollup interval `{r}`"))?;
+            if dst <= src || dst % src != 0 {
+                return Err(format!(
+                    "{interval}: rollup `{r}` must be a coarser multiple of it"
+                ));
+            }
+            if keep_days.is_none() {
+                return Err(format!("{interval}: nothing to roll up when kept forever"));
+            }
+        }
+        Ok(Self {
+            interval,
+            keep_days,
+            rollup,
+        })
+    }
+}
+
+/// Parse a whole `CANDLE_RETENTION` value
+pub fn parse_policies(spec: &str) -> Result<Vec<RetentionPolicy>, String> {
+    let mut out: Vec<RetentionPolicy> = Vec::new();
+    for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let p: RetentionPolicy = entry.parse()?;
+        if out.iter().any(|q| q.interval == p.interval) {
+            return Err(format!("{}: listed twice", p.interval));
+        }
+        out.push(p);
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Compacted {
+    pub interval: String,
+    pub rollup: Option<String>,
+    pub cutoff: DateTime<Utc>,
+    /// Rollup bars written
+    pub rolled_up: u64,
+    pub deleted: u64,
+}
+
+/// One compaction pass over every expiring policy
+pub async fn compact(
+    db: &PgPool,
+    policies: &[RetentionPolicy],
+    now: DateTime<Utc>,
+) -> Result<Vec<Compacted>, sqlx::Error> {
+    let mut out = Vec::new();
+    for p in policies {
+        let Some(cutoff) = p.cutoff(now) else {
+            continue;
+        };
+        let mut tx = db.begin().await?;
+        let mut rolled_up = 0;
+        if let Some(rollup) = &p.rollup {
+            let step = interval_secs(rollup).unwrap_or_default() as f64;
+            rolled_up = sqlx::query(
+                r#"
+                INSERT INTO candles (symbol, interval, ts, open, high, low, close, volume, source)
+                SELECT symbol, $2, bucket,
+                       (array_agg(open ORDER BY ts))[1], max(high), min(low),
+                       (array_agg(close ORDER BY ts DESC))[1], sum(volume), 'rollup'
+                  FROM (SELECT *,
+                               date_bin(make_interval(secs => $3),
+                                        ts - interval '1 microsecond',
+                                        TIMESTAMPTZ 'epoch')
+                                 + make_interval(secs => $3) AS bucket
+                          FROM candles
+                         WHERE interval = $1 AND ts <= $4) c
+                 GROUP BY symbol, bucket
+                ON CONFLICT (symbol, interval, ts) DO NOTHING
+                "#,
+            )
+            .bind(&p.interval)
+            .bind(rollup)
+            .bind(step)
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        let deleted = sqlx::query("DELETE FROM candles WHERE interval = $1 AND ts <= $2")
+            .bind(&p.interval)
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+
+        if deleted > 0 {
+            counter!("candles_compacted_total", deleted, "interval" => p.interval.clone());
+            tracing::info!(
+                "candle retention: {}: {deleted} bars up to {cutoff} removed, {rolled_up} {} bars written",
+                p.interval,
+                p.rollup.as_deref().unwrap_or("-"),
+            );
+        }
+        out.push(Compacted {
+            interval: p.interval.clone(),
+            rollup: p.rollup.clone(),
+            cutoff,
+            rolled_up,
+            deleted,
+        });
+    }
+    Ok(out)
+}
+
+/// Periodic compaction; the first pass runs immediately
+pub fn spawn(db: PgPool, policies: Vec<RetentionPolicy>, every: StdDuration) {
+    if policies.iter().all(|p| p.keep_days.is_none()) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(every);
+        loop {
+            tick.tick().await;
+            if let Err(e) = compact(&db, &policies, Utc::now()).await {
+                tracing::error!("candle retention: DB error: {e}");
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SeriesUsage {
+    pub symbol: String,
+    pub interval: String,
+    pub bars: i64,
+    pub oldest: DateTime<Utc>,
+    pub newest: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReport {
+    /// `candles` incl. indexes and TOAST
+    pub table_bytes: i64,
+    pub series: Vec<SeriesUsage>,
+}
+
+pub async fn storage(db: &PgPool) -> Result<StorageReport, sqlx::Error> {
+    let table_bytes = sqlx::query_scalar::<_, i64>("SELECT pg_total_relation_size('candles')")
+        .fetch_one(db)
+        .await?;
+    let series = sqlx::query_as::<_, SeriesUsage>(
+        r#"
+        SELECT symbol, interval, COUNT(*) AS bars, MIN(ts) AS oldest, MAX(ts) AS newest
+          FROM candles
+         GROUP BY symbol, interval
+         ORDER BY symbol, interval
+        "#,
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(StorageReport {
+        table_bytes,
+        series,
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_labels() {
+        assert_eq!(interval_secs("1m"), Some(60));
+        assert_eq!(interval_secs("4h"), Some(14_400));
+        assert_eq!(interval_secs("1w"), Some(604_800));
+        assert_eq!(interval_secs("0h"), None);
+        assert_eq!(interval_secs("h"), None);
+        assert_eq!(interval_secs(""), None);
+        assert_eq!(interval_secs("3x"), None);
+    }
+
+    #[test]
+    fn policies_parse() {
+        let p = parse_policies("1m:90d>1h; 1h:forever;15m:30d").unwrap();
+        assert_eq!(
+            p,
+            vec![
+                RetentionPolicy {
+                    interval: "1m".into(),
+                    keep_days: Some(90),
+                    rollup: Some("1h".into()),
+                },
+                RetentionPolicy {
+                    interval: "1h".into(),
+                    keep_days: None,
+                    rollup: None,
+                },
+                RetentionPolicy {
+                    interval: "15m".into(),
+                    keep_days: Some(30),
+                    rollup: None,
+                },
+            ]
+        );
+        assert!(parse_policies("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn bad_policies_are_rejected() {
+        for spec in [
+            "1m",
+            "1m:90",
+            "1m:0d",
+            "1x:90d",
+            "1h:90d>1m",
+            "1h:90d>90m",
+            "1m:forever>1h",
+            "1m:90d;1m:30d",
+        ] {
+            assert!(parse_policies(spec).is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn cutoff_lands_on_a_rollup_boundary() {
+        let now = Utc.with_ymd_and_hms(2025, 7, 28, 13, 47, 5).unwrap();
+        let p: RetentionPolicy = "1m:90d>4h".parse().unwrap();
+        assert_eq!(
+            p.cutoff(now),
+            Some(Utc.with_ymd_and_hms(2025, 4, 29, 12, 0, 0).unwrap())
+        );
+        let plain: RetentionPolicy = "1m:1d".parse().unwrap();
+        assert_eq!(
+            plain.cutoff(now),
+            Some(Utc.with_ymd_and_hms(2025, 7, 27, 13, 47, 0).unwrap())
+        );
+        assert_eq!(
+            "1h:forever".parse::<RetentionPolicy>().unwrap().cutoff(now),
+            None
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Fault injection for staging (cargo feature `chaos`)
+//! ──────────────────────────────────────────────────────────────────────────
+//! * [`Chaos`] wraps the exchange HTTP client (`Http`), the execution
+//!   adapter (`ApiClient` / `ExchangeAdapter`) and the cache (`Cache`, i.e. Redis) and can add
+//!   latency, fail calls with a 5xx or drop their responses
+//! * Faults are configured per [`Target`] at runtime via `/api/admin/chaos` and
+//!   start disabled; without the feature none of this is compiled in
+//! * A dropped response still performs the call – the order may well have
+//!   reached the exchange – but the answer only arrives as an error after
+//!   `DROP_HANG`, which is what a partition looks like to the caller
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use metrics::increment_counter;
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::db::cache::{Cache, CacheError, SharedCache};
+use crate::services::blowfin::api::{Http, OrderRequest};
+use crate::services::blowfin::dto::{Balance, Position};
+use crate::services::exchanges::ExchangeAdapter;
+use crate::services::trading_engine::{
+    ApiClient, ApiResponse, Exchange, NativeStop, OpenOrder, OrderStatus,
+};
+use crate::utils::errors::{ApiError, TradeError};
+
+/// How long a dropped response keeps the caller waiting
+const DROP_HANG: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// Exchange REST calls through the `Http` trait (balances, positions)
+    Http,
+    /// Order placement / cancel / status through `ApiClient`
+    Exchange,
+    /// Every `Cache` command
+    Cache,
+}
+
+impl Target {
+    pub fn label(self) -> &'static str {
+        match self {
+            Target::Http => "http",
+            Target::Exchange => "exchange",
+            Target::Cache => "cache",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Faults {
+    /// Extra delay per call, uniform in `[latency_min_ms, latency_max_ms]`
+    pub latency_min_ms: u64,
+    pub latency_max_ms: u64,
+    /// Share of calls whose response is lost
+    pub drop_rate: f64,
+    /// Share of calls failed up front with a 503
+    pub error_rate: f64,
+}
+
+impl Faults {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.latency_min_ms > self.latency_max_ms {
+            return Err("latency_min_ms must be <= latency_max_ms".into());
+        }
+        let rates = [self.drop_rate, self.error_rate];
+        if rates.iter().any(|r| !(0.0..=1.0).contains(r)) {
+            return Err("rates must be between 0 and 1".into());
+        }
+        if self.drop_rate + self.error_rate > 1.0 {
+            return Err("drop_rate + error_rate must be <= 1".into());
+        }
+        Ok(())
+    }
+
+    /// What happens to a call, given a uniform draw `r` in `[0, 1)`
+    pub fn outcome(&self, r: f64) -> Outcome {
+        if r < self.error_rate {
+            Outcome::Fail
+        } else if r < self.error_rate + self.drop_rate {
+            Outcome::Drop
+        } else {
+            Outcome::Pass
+        }
+    }
+
+    fn latency(&self) -> Duration {
+        let ms = if self.latency_max_ms > self.latency_min_ms {
+            rand::Rng::gen_range(
+                &mut rand::thread_rng(),
+                self.latency_min_ms..=self.latency_max_ms,
+            )
+        } else {
+            self.latency_min_ms
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Drop,
+}
+
+static FAULTS: Lazy<DashMap<Target, Faults>> = Lazy::new(DashMap::new);
+
+pub fn set(target: Target, faults: Faults) {
+    tracing::warn!("chaos: {} faults set to {faults:?}", target.label());
+    FAULTS.insert(target, faults);
+}
+
+pub fn clear() {
+    tracing::warn!("chaos: all faults cleared");
+    FAULTS.clear();
+}
+
+pub fn current() -> BTreeMap<Target, Faults> {
+    FAULTS.iter().map(|f| (*f.key(), *f.value())).collect()
+}
+
+/// Run `call` under `target`'s faults; `fail` builds the caller's error type
+async fn run<T, E, F>(target: Target, fail: impl FnOnce(&str) -> E, call: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let Some(faults) = FAULTS.get(&target).map(|f| *f) else {
+        return call.await;
+    };
+    let delay = faults.latency();
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    match faults.outcome(rand::random()) {
+        Outcome::Pass => call.await,
+        Outcome::Fail => {
+            increment_counter!("chaos_faults_total", "target" => target.label(), "kind" => "error");
+            Err(fail("injected HTTP 503"))
+        }
+        Outcome::Drop => {
+            increment_counter!("chaos_faults_total", "target" => target.label(), "kind" => "drop");
+            let _ = call.await;
+            tokio::time::sleep(DROP_HANG).await;
+            Err(fail("response dropped"))
+        }
+    }
+}
+
+fn api_err(what: &str) -> ApiError {
+    ApiError::Other(format!("chaos: {what}"))
+}
+
+fn trade_err(what: &str) -> TradeError {
+    TradeError::Api(api_err(what))
+}
+
+fn cache_err(what: &str) -> CacheError {
+    CacheError::Chaos(what.to_string())
+}
+
+/// Fault-injecting decorator
+pub struct Chaos<T>(pub T);
+
+/// Cache handle with faults applied (installed by `main` under the feature)
+pub fn wrap_cache(inner: SharedCache) -> SharedCache {
+    std::sync::Arc::new(Chaos(inner))
+}
+
+#[async_trait]
+impl<H: Http> Http for Chaos<H> {
+    async fn post_json<T: DeserializeOwned + Send>(
+        &self,
+        url: &str,
+        headers: Vec<(&str, String)>,
+        body: &OrderRequest,
+    ) -> Result<T, ApiError> {
+        run(Target::Http, api_err, self.0.post_json(url, headers, body)).await
+    }
+
+    async fn get_json<T: DeserializeOwned + Send>(
+        &self,
+        url: &str,
+        headers: Vec<(&str, String)>,
+    ) -> Result<T, ApiError> {
+        run(Target::Http, api_err, self.0.get_json(url, headers)).await
+    }
+}
+
+#[async_trait]
+impl<A: ApiClient> ApiClient for Chaos<A> {
+    async fn place_order(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        order: &OrderRequest,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<ApiResponse, TradeError> {
+        let call = self.0.place_order(db, user_id, order, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+
+    async fn cancel_by_client_id(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        symbol: &str,
+        client_order_id: &str,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let call =
+            self.0
+                .cancel_by_client_id(db, user_id, symbol, client_order_id, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+
+    async fn order_status(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        symbol: &str,
+        client_order_id: &str,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<OrderStatus, TradeError> {
+        let call = self
+            .0
+            .order_status(db, user_id, symbol, client_order_id, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+
+    async fn pending_stops(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        symbol: &str,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<Vec<NativeStop>, TradeError> {
+        let call = self
+            .0
+            .pending_stops(db, user_id, symbol, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+
+    async fn place_stop(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        stop: &NativeStop,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let call = self.0.place_stop(db, user_id, stop, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+
+    async fn cancel_stop(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        stop: &NativeStop,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let call = self.0.cancel_stop(db, user_id, stop, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+
+    async fn open_orders(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<Vec<OpenOrder>, TradeError> {
+        let call = self.0.open_orders(db, user_id, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+
+    async fn cancel_order(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        order: &OpenOrder,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        let call = self.0.cancel_order(db, user_id, order, is_demo, master_key);
+        run(Target::Exchange, trade_err, call).await
+    }
+}
+
+#[async_trait]
+impl<A: ExchangeAdapter> ExchangeAdapter for Chaos<A> {
+    fn exchange(&self) -> Exchange {
+        self.0.exchange()
+    }
+
+    async fn get_balance(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<Balance, TradeError> {
+        let call = self.0.get_balance(db, user_id, is_demo, master_key);
+        run(Target::Http, trade_err, call).await
+    }
+
+    async fn get_positions(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<Vec<Position>, TradeError> {
+        let call = self.0.get_positions(db, user_id, is_demo, master_key);
+        run(Target::Http, trade_err, call).await
+    }
+}
+
+#[async_trait]
+impl Cache for Chaos<SharedCache> {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        run(Target::Cache, cache_err, self.0.get(key)).await
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), CacheError> {
+        run(Target::Cache, cache_err, self.0.set(key, value, ttl_secs)).await
+    }
+
+    async fn set_nx(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        run(
+            Target::Cache,
+            cache_err,
+            self.0.set_nx(key, value, ttl_secs),
+        )
+        .await
+    }
+
+    async fn del(&self, key: &str) -> Result<(), CacheError> {
+        run(Target::Cache, cache_err, self.0.del(key)).await
+    }
+
+    async fn renew_if(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        run(
+            Target::Cache,
+            cache_err,
+            self.0.renew_if(key, value, ttl_secs),
+        )
+        .await
+    }
+
+    async fn del_if(&self, key: &str, value: &str) -> Result<bool, CacheError> {
+        run(Target::Cache, cache_err, self.0.del_if(key, value)).await
+    }
+
+    async fn incr_by(&self, key: &str, by: i64) -> Result<i64, CacheError> {
+        run(Target::Cache, cache_err, self.0.incr_by(key, by)).await
+    }
+
+    async fn expire(&self, key: &str, ttl_secs: u64) -> Result<(), CacheError> {
+        run(Target::Cache, cache_err, self.0.expire(key, ttl_secs)).await
+    }
+
+    async fn sadd(&self, key: &str, members: &[String]) -> Result<(), CacheError> {
+        run(Target::Cache, cache_err, self.0.sadd(key, members)).await
+    }
+
+    async fn srem(&self, key: &str, member: &str) -> Result<(), CacheError> {
+        run(Target::Cache, cache_err, self.0.srem(key, member)).await
+    }
+
+    async fn smembers(&self, key: &str) -> Result<Vec<String>, CacheError> {
+        run(Target::Cache, cache_err, self.0.smembers(key)).await
+    }
+
+    async fn lpush(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        run(Target::Cache, cache_err, self.0.lpush(key, value)).await
+    }
+
+    async fn lrange_all(&self, key: &str) -> Result<Vec<String>, CacheError> {
+        run(Target::Cache, cache_err, self.0.lrange_all(key)).await
+    }
+
+    async fn ltrim(&self, key: &str, keep: usize) -> Result<(), CacheError> {
+        run(Target::Cache, cache_err, self.0.ltrim(key, keep)).await
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::cache::MemoryCache;
+
+    #[test]
+    fn outcome_bands_follow_the_rates() {
+        let f = Faults {
+            error_rate: 0.2,
+            drop_rate: 0.3,
+            ..Faults::default()
+        };
+        assert_eq!(f.outcome(0.0), Outcome::Fail);
+        assert_eq!(f.outcome(0.19), Outcome::Fail);
+        assert_eq!(f.outcome(0.2), Outcome::Drop);
+        assert_eq!(f.outcome(0.49), Outcome::Drop);
+        assert_eq!(f.outcome(0.5), Outcome::Pass);
+        assert_eq!(Faults::default().outcome(0.0), Outcome::Pass);
+    }
+
+    #[test]
+    fn faults_are_validated() {
+        assert!(Faults::default().validate().is_ok());
+        let bad = |f: Faults| f.validate().is_err();
+        assert!(bad(Faults {
+            error_rate: 1.5,
+            ..Faults::default()
+        }));
+        assert!(bad(Faults {
+            error_rate: 0.6,
+            drop_rate: 0.6,
+            ..Faults::default()
+        }));
+        assert!(bad(Faults {
+            latency_min_ms: 10,
+            latency_max_ms: 5,
+            ..Faults::default()
+        }));
+    }
+
+    #[tokio::test]
+    async fn cache_faults_switch_on_and_off() {
+        let cache = wrap_cache(std::sync::Arc::new(MemoryCache::new()));
+        cache.set("k", "v", 0).await.unwrap();
+
+        set(
+            Target::Cache,
+            Faults {
+                error_rate: 1.0,
+                ..Faults::default()
+            },
+        );
+        assert!(matches!(cache.get("k").await, Err(CacheError::Chaos(_))));
+
+        clear();
+        assert_eq!(cache.get("k").await.unwrap().as_deref(), Some("v"));
+    }
+}
@@ -0,0 +1,532 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Aggregated copy execution (omnibus mode)
+//! ──────────────────────────────────────────────────────────────────────────
+//! With `COPY_AGGREGATE_USER_ID` set, a leader fill is no longer copied as
+//! one market order per follower. Instead:
+//!
+//! 1. follower sizes are netted into one parent order on the omnibus
+//!    account (`COPY_AGGREGATE_USER_ID`'s exchange keys);
+//! 2. parents of at least `COPY_AGGREGATE_MIN_SIZE` are worked as child
+//!    orders – `twap:<slices>:<secs>` or `iceberg:<clip>` – smaller ones go
+//!    out as a single child;
+//! 3. the filled total is allocated back pro-rata (largest remainder at the
+//!    leader's lot precision) and every follower gets the same average
+//!    price, recorded in `copy_parent_orders` / `copy_allocations`.
+//!
+//! Mode is all-or-nothing: exits are aggregated too, because the positions
+//! they reduce live on the omnibus account. Child prices come from the
+//! submit response (limit price, else book mid) until fills are reconciled.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use metrics::increment_counter;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    services::{
+        drain,
+        instruments::decimals,
+        trading_engine::{execute_trade, TradeRequest},
+    },
+    utils::errors::TradeError,
+};
+
+/// Pause between iceberg clips
+const ICEBERG_PAUSE: Duration = Duration::from_secs(2);
+
+// ─── Parent styles ────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParentStyle {
+    /// `slices` equal children spread evenly over `over`
+    Twap { slices: u32, over: Duration },
+    /// Children of at most `clip`, back to back
+    Iceberg { clip: f64 },
+}
+
+impl FromStr for ParentStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        match parts.as_slice() {
+            ["twap", n, secs] => {
+                let slices: u32 = n.parse().map_err(|_| format!("bad slice count `{n}`"))?;
+                let secs: u64 = secs.parse().map_err(|_| format!("bad seconds `{secs}`"))?;
+                if slices == 0 {
+                    return Err("twap needs at least one slice".into());
+                }
+                Ok(ParentStyle::Twap {
+                    slices,
+                    over: Duration::from_secs(secs),
+                })
+            }
+            ["iceberg", clip] => match clip.parse::<f64>() {
+                Ok(clip) if clip > 0.0 => Ok(ParentStyle::Iceberg { clip }),
+                _ => Err(format!("bad iceberg clip `{clip}`")),
+            },
+            _ => Err(format!(
+                "unknown style `{s}` (twap:<slices>:<secs> | iceberg:<clip>)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ParentStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParentStyle::Twap { slices, over } => write!(f, "twap:{slices}:{}", over.as_secs()),
+            ParentStyle::Iceberg { clip } => write!(f, "iceberg:{clip}"),
+        }
+    }
+}
+
+impl ParentStyle {
+    /// Child sizes summing exactly to `total` at `dp` decimal places
+    pub fn child_sizes(&self, total: f64, dp: usize) -> Vec<f64> {
+        let scale = 10f64.powi(dp as i32);
+        let lots = (total * scale).round() as u64;
+        let sizes: Vec<u64> = match *self {
+            ParentStyle::Twap { slices, .. } => {
+                let n = (slices as u64).min(lots.max(1));
+                (0..n).map(|i| lots / n + u64::from(i < lots % n)).collect()
+            }
+            ParentStyle::Iceberg { clip } => {
+                let clip = ((clip * scale).round() as u64).max(1);
+                let mut left = lots;
+                std::iter::from_fn(|| {
+                    (left > 0).then(|| {
+                        let c = clip.min(left);
+                        left -= c;
+                        c
+                    })
+                })
+                .collect()
+            }
+        };
+        sizes
+            .into_iter()
+            .filter(|&l| l > 0)
+            .map(|l| l as f64 / scale)
+            .collect()
+    }
+
+    pub fn pause(&self) -> Duration {
+        match *self {
+            ParentStyle::Twap { slices, over } => over / slices.max(1),
+            ParentStyle::Iceberg { .. } => ICEBERG_PAUSE,
+        }
+    }
+}
+
+// ─── Fills & allocation ───────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChildFill {
+    pub size: f64,
+    pub price: Option<f64>,
+}
+
+/// Total filled and its volume-weighted price (over priced children)
+pub fn vwap(fills: &[ChildFill]) -> (f64, Option<f64>) {
+    let filled = fills.iter().map(|f| f.size).sum();
+    let (pv, v) = fills
+        .iter()
+        .filter_map(|f| f.price.map(|p| (p * f.size, f.size)))
+        .fold((0.0, 0.0), |(a, b), (pv, v)| (a + pv, b + v));
+    (filled, (v > 0.0).then(|| pv / v))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Allocation {
+    pub follower_id: i64,
+    pub wanted: f64,
+    pub size: f64,
+    pub avg_price: Option<f64>,
+}
+
+/// Split `filled` across `wants` pro-rata. Works in whole lots of `dp`
+/// decimals; leftover lots go to the largest fractional shares (ties →
+/// lower follower id) and nobody gets more than they asked for.
+pub fn allocate(
+    wants: &[(i64, f64)],
+    filled: f64,
+    avg_price: Option<f64>,
+    dp: usize,
+) -> Vec<Allocation> {
+    let scale = 10f64.powi(dp as i32);
+    let want_lots: Vec<u64> = wants
+        .iter()
+        .map(|&(_, w)| (w * scale).round() as u64)
+        .collect();
+    let total: u64 = want_lots.iter().sum();
+    let filled_lots = ((filled * scale).round() as u64).min(total);
+
+    let mut lots: Vec<u64> = Vec::with_capacity(wants.len());
+    let mut rems: Vec<(u128, i64, usize)> = Vec::with_capacity(wants.len());
+    for (i, (&w, &(fid, _))) in want_lots.iter().zip(wants).enumerate() {
+        let exact = w as u128 * filled_lots as u128;
+        let base = exact.checked_div(total as u128).unwrap_or(0);
+        lots.push(base as u64);
+        rems.push((exact - base * total as u128, fid, i));
+    }
+    let mut left = filled_lots - lots.iter().sum::<u64>();
+    rems.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for &(_, _, i) in &rems {
+        if left == 0 {
+            break;
+        }
+        if lots[i] < want_lots[i] {
+            lots[i] += 1;
+            left -= 1;
+        }
+    }
+
+    wants
+        .iter()
+        .zip(lots)
+        .map(|(&(follower_id, wanted), l)| Allocation {
+            follower_id,
+            wanted,
+            size: l as f64 / scale,
+            avg_price: avg_price.filter(|_| l > 0),
+        })
+        .collect()
+}
+
+// ─── Execution ────────────────────────────────────────────────────────────
+#[async_trait]
+pub trait ChildExecutor: Send + Sync + 'static {
+    async fn place(&self, req: TradeRequest) -> Result<ChildFill, TradeError>;
+}
+
+/// Children go out on the omnibus account's keys
+pub struct OmnibusExecutor {
+    pub pg: PgPool,
+    pub account_user_id: i64,
+    pub is_demo: bool,
+    pub master_key: Vec<u8>,
+}
+
+#[async_trait]
+impl ChildExecutor for OmnibusExecutor {
+    async fn place(&self, req: TradeRequest) -> Result<ChildFill, TradeError> {
+        let size = req.size;
+        let resp = execute_trade(
+            req,
+            &self.pg,
+            self.account_user_id,
+            self.is_demo,
+            &self.master_key,
+        )
+        .await?;
+        if !resp.success {
+            return Err(TradeError::Other(format!("child rejected: {}", resp.data)));
+        }
+        Ok(ChildFill {
+            size,
+            price: resp.price.or(resp.mid_at_submit),
+        })
+    }
+}
+
+/// Work the parent; stops at the first rejected child (the unfilled rest
+/// simply shrinks every follower's allocation).
+pub async fn work_parent<E: ChildExecutor>(
+    exec: &E,
+    style: ParentStyle,
+    template: &TradeRequest,
+    sizes: &[f64],
+) -> Vec<ChildFill> {
+    let mut fills = Vec::with_capacity(sizes.len());
+    for (i, &size) in sizes.iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(style.pause()).await;
+        }
+        let mut child = template.clone();
+        child.size = size;
+        match exec.place(child).await {
+            Ok(f) => fills.push(f),
+            Err(e) => {
+                increment_counter!("copy_child_orders_total", "result" => "error");
+                tracing::warn!(
+                    "copy parent {}: child {i} failed, stopping: {e}",
+                    template.symbol
+                );
+                break;
+            }
+        }
+        increment_counter!("copy_child_orders_total", "result" => "ok");
+    }
+    fills
+}
+
+#[derive(Debug, Clone)]
+pub struct AggregateConfig {
+    pub account_user_id: i64,
+    /// Parents at least this big are sliced; smaller go out in one child
+    pub min_size: f64,
+    pub style: ParentStyle,
+}
+
+/// Net, execute, allocate and persist one leader fill. Returns the parent id.
+pub async fn run_parent<E: ChildExecutor>(
+    pg: &PgPool,
+    cfg: &AggregateConfig,
+    exec: &E,
+    leader_id: i64,
+    template: &TradeRequest,
+    wants: &[(i64, f64)],
+) -> Result<Uuid, sqlx::Error> {
+    let dp = decimals(template.size) as usize;
+    let total: f64 = wants.iter().map(|w| w.1).sum();
+    let sizes = if total >= cfg.min_size {
+        cfg.style.child_sizes(total, dp)
+    } else {
+        ParentStyle::Twap {
+            slices: 1,
+            over: Duration::ZERO,
+        }
+        .child_sizes(total, dp)
+    };
+
+    let fills = work_parent(exec, cfg.style, template, &sizes).await;
+    let (filled, avg_price) = vwap(&fills);
+    let allocations = allocate(wants, filled, avg_price, dp);
+    persist(
+        pg,
+        leader_id,
+        template,
+        cfg.style,
+        total,
+        filled,
+        avg_price,
+        &allocations,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn persist(
+    pg: &PgPool,
+    leader_id: i64,
+    template: &TradeRequest,
+    style: ParentStyle,
+    wanted: f64,
+    filled: f64,
+    avg_price: Option<f64>,
+    allocations: &[Allocation],
+) -> Result<Uuid, sqlx::Error> {
+    let mut tx = pg.begin().await?;
+    let parent_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO copy_parent_orders
+              (leader_id, symbol, side, style, wanted_size, filled_size, avg_price)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING parent_id
+        "#,
+    )
+    .bind(leader_id)
+    .bind(&template.symbol)
+    .bind(template.side.to_ascii_lowercase())
+    .bind(style.to_string())
+    .bind(wanted)
+    .bind(filled)
+    .bind(avg_price)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if !allocations.is_empty() {
+        let mut qb: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO copy_allocations (parent_id, follower_id, wanted_size, size, avg_price) ",
+        );
+        qb.push_values(allocations, |mut b, a| {
+            b.push_bind(parent_id)
+                .push_bind(a.follower_id)
+                .push_bind(a.wanted)
+                .push_bind(a.size)
+                .push_bind(a.avg_price);
+        });
+        qb.build().execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(parent_id)
+}
+
+// ─── Global handle ────────────────────────────────────────────────────────
+struct Aggregator {
+    pg: PgPool,
+    cfg: AggregateConfig,
+    exec: OmnibusExecutor,
+}
+
+static AGGREGATOR: OnceCell<Aggregator> = OnceCell::new();
+
+/// Enable omnibus mode (call once from `main` when configured)
+pub fn init(pg: PgPool, cfg: AggregateConfig, exec: OmnibusExecutor) {
+    let _ = AGGREGATOR.set(Aggregator { pg, cfg, exec });
+}
+
+pub fn enabled() -> bool {
+    AGGREGATOR.get().is_some()
+}
+
+/// Work the parent in the background; a drain waits for it to finish.
+pub fn spawn_parent(leader_id: i64, template: TradeRequest, wants: Vec<(i64, f64)>) -> bool {
+    let Some(agg) = AGGREGATOR.get() else {
+        return false;
+    };
+    let in_flight = drain::track();
+    tokio::spawn(async move {
+        let _in_flight = in_flight;
+        match run_parent(&agg.pg, &agg.cfg, &agg.exec, leader_id, &template, &wants).await {
+            Ok(id) => tracing::info!(
+                "copy parent {id}: leader {leader_id}, {} followers",
+                wants.len()
+            ),
+            Err(e) => tracing::error!("copy parent for leader {leader_id}: persist failed: {e}"),
+        }
+    });
+    true
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::trading_engine::Exchange;
+    use std::sync::Mutex;
+
+    fn template(size: f64) -> TradeRequest {
+        TradeRequest {
+            exchange: Exchange::Blowfin,
+            symbol: "BTC-USDT".into(),
+            side: "buy".into(),
+            order_type: "market".into(),
+            price: None,
+            size,
+            reduce_only: false,
+            signal_price: None,
+            tp_sl: None,
+        }
+    }
+
+    #[test]
+    fn style_parses_and_round_trips() {
+        let t: ParentStyle = "twap:5:60".parse().unwrap();
+        assert_eq!(t.pause(), Duration::from_secs(12));
+        assert_eq!(t.to_string(), "twap:5:60");
+        let i: ParentStyle = "iceberg:0.5".parse().unwrap();
+        assert_eq!(i.to_string(), "iceberg:0.5");
+        assert!("twap:0:60".parse::<ParentStyle>().is_err());
+        assert!("iceberg:-1".parse::<ParentStyle>().is_err());
+        assert!("vwap".parse::<ParentStyle>().is_err());
+    }
+
+    #[test]
+    fn children_sum_to_parent() {
+        let twap = ParentStyle::Twap {
+            slices: 3,
+            over: Duration::ZERO,
+        };
+        assert_eq!(twap.child_sizes(1.0, 2), vec![0.34, 0.33, 0.33]);
+        // fewer lots than slices → one lot per child
+        assert_eq!(twap.child_sizes(0.02, 2), vec![0.01, 0.01]);
+
+        let ice = ParentStyle::Iceberg { clip: 0.4 };
+        assert_eq!(ice.child_sizes(1.0, 1), vec![0.4, 0.4, 0.2]);
+    }
+
+    #[test]
+    fn vwap_ignores_unpriced_children_for_price_only() {
+        let fills = [
+            ChildFill {
+                size: 1.0,
+                price: Some(100.0),
+            },
+            ChildFill {
+                size: 3.0,
+                price: Some(104.0),
+            },
+            ChildFill {
+                size: 2.0,
+                price: None,
+            },
+        ];
+        let (filled, px) = vwap(&fills);
+        assert_eq!(filled, 6.0);
+        assert_eq!(px, Some(103.0));
+        assert_eq!(vwap(&[]), (0.0, None));
+    }
+
+    #[test]
+    fn full_fill_gives_everyone_their_size() {
+        let a = allocate(&[(1, 0.5), (2, 1.5)], 2.0, Some(10.0), 2);
+        assert_eq!(a[0].size, 0.5);
+        assert_eq!(a[1].size, 1.5);
+        assert!(a.iter().all(|x| x.avg_price == Some(10.0)));
+    }
+
+    #[test]
+    fn partial_fill_is_pro_rata_and_exact() {
+        // 3 equal followers, 1.00 filled of 3.00 → 0.34 / 0.33 / 0.33
+        let a = allocate(&[(3, 1.0), (1, 1.0), (2, 1.0)], 1.0, Some(50.0), 2);
+        let sizes: Vec<(i64, f64)> = a.iter().map(|x| (x.follower_id, x.size)).collect();
+        assert_eq!(sizes, vec![(3, 0.33), (1, 0.34), (2, 0.33)]);
+        let sum: f64 = a.iter().map(|x| x.size).sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nothing_filled_allocates_nothing() {
+        let a = allocate(&[(1, 1.0), (2, 2.0)], 0.0, None, 3);
+        assert!(a.iter().all(|x| x.size == 0.0 && x.avg_price.is_none()));
+        // overfill is capped at what followers asked for
+        let a = allocate(&[(1, 1.0)], 5.0, Some(1.0), 3);
+        assert_eq!(a[0].size, 1.0);
+    }
+
+    struct Scripted {
+        prices: Mutex<Vec<Option<f64>>>,
+        placed: Mutex<Vec<f64>>,
+    }
+
+    #[async_trait]
+    impl ChildExecutor for Scripted {
+        async fn place(&self, req: TradeRequest) -> Result<ChildFill, TradeError> {
+            self.placed.lock().unwrap().push(req.size);
+            match self.prices.lock().unwrap().pop() {
+                Some(price) => Ok(ChildFill {
+                    size: req.size,
+                    price,
+                }),
+                None => Err(TradeError::Other("rejected".into())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn work_parent_stops_at_first_rejection() {
+        let exec = Scripted {
+            // popped from the back: 2 fills, then rejection
+            prices: Mutex::new(vec![Some(101.0), Some(100.0)]),
+            placed: Mutex::new(vec![]),
+        };
+        let style = ParentStyle::Twap {
+            slices: 4,
+            over: Duration::ZERO,
+        };
+        let sizes = style.child_sizes(4.0, 0);
+        let fills = work_parent(&exec, style, &template(1.0), &sizes).await;
+        assert_eq!(fills.len(), 2);
+        assert_eq!(exec.placed.lock().unwrap().len(), 3);
+        assert_eq!(vwap(&fills), (2.0, Some(100.5)));
+    }
+}
@@ -0,0 +1,716 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Copy-trading replication queue + worker pool
+//! ──────────────────────────────────────────────────────────────────────────
+//! `copy_trading::replicate_to_followers` turns a leader fill into one job
+//! per follower and pushes it here; `COPY_WORKERS` tasks drain the queue.
+//!
+//! * Two priority classes – **exits** (reduce-only) always run before
+//!   **entries**, and have their own capacity, so a flood of entry copies
+//!   can never starve a follower's risk-reducing order.
+//! * Per-leader fairness – inside a class, leaders are served round-robin;
+//!   one busy leader with 500 followers doesn't delay everybody else.
+//! * Backpressure – a full class rejects new jobs (`QueueError::Full`)
+//!   instead of growing without bound.
+//! * Jitter – entry copies wait a random `0..COPY_JITTER_MAX_MS` and get
+//!   their size nudged by up to ±`COPY_SIZE_JITTER_PCT`, so a hundred
+//!   followers don't hit the book with identical market orders at once.
+//!   Exits are never delayed or resized.
+//! * Retries – a transient exchange error (unreachable, 429 / 5xx) puts the
+//!   job back after an exponential backoff, up to `Retry::max_attempts`;
+//!   then it is dead-lettered.
+//! * Results – every job ends in one `copy_events` row: `ok` with the
+//!   follower order and its slippage against the leader's price at
+//!   submission, `failed`, or `dead_letter` with the request for a replay.
+//!
+//! Every queued job holds a `drain` guard, so a drain waits for the queue to
+//! flush, not just for the job currently executing.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use metrics::{gauge, histogram, increment_counter};
+use once_cell::sync::OnceCell;
+use rand::Rng;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::{
+    db::cache::SharedCache,
+    services::{
+        analytics,
+        drain::{self, InFlight},
+        instruments::decimals,
+        risk,
+        trading_engine::{execute_trade, TradeRequest},
+    },
+    utils::errors::TradeError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Reduce-only: closes / partial closes / stops
+    Exit,
+    Entry,
+}
+
+impl Priority {
+    pub fn of(req: &TradeRequest) -> Self {
+        if req.reduce_only {
+            Priority::Exit
+        } else {
+            Priority::Entry
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Priority::Exit => "exit",
+            Priority::Entry => "entry",
+        }
+    }
+}
+
+pub struct CopyJob {
+    pub leader_id: i64,
+    pub follower_id: i64,
+    pub req: TradeRequest,
+    /// The leader's `orders` row this copies, when it was recorded
+    pub leader_order_id: Option<Uuid>,
+    /// Executions tried so far
+    pub attempts: u32,
+    _in_flight: InFlight<'static>,
+}
+
+impl CopyJob {
+    pub fn new(leader_id: i64, follower_id: i64, req: TradeRequest) -> Self {
+        Self {
+            leader_id,
+            follower_id,
+            req,
+            leader_order_id: None,
+            attempts: 0,
+            _in_flight: drain::track(),
+        }
+    }
+
+    pub fn for_order(mut self, leader_order_id: Option<Uuid>) -> Self {
+        self.leader_order_id = leader_order_id;
+        self
+    }
+
+    pub fn priority(&self) -> Priority {
+        Priority::of(&self.req)
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum QueueError {
+    #[error("{0} queue full")]
+    Full(&'static str),
+    #[error("copy workers not running")]
+    NotRunning,
+}
+
+// ─── Queue ────────────────────────────────────────────────────────────────
+/// One priority class: per-leader FIFOs served round-robin
+#[derive(Default)]
+struct ClassQueue {
+    per_leader: HashMap<i64, VecDeque<CopyJob>>,
+    turn: VecDeque<i64>,
+    len: usize,
+}
+
+impl ClassQueue {
+    fn push(&mut self, job: CopyJob) {
+        let q = self.per_leader.entry(job.leader_id).or_default();
+        if q.is_empty() {
+            self.turn.push_back(job.leader_id);
+        }
+        q.push_back(job);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<CopyJob> {
+        let leader = self.turn.pop_front()?;
+        let q = self.per_leader.get_mut(&leader)?;
+        let job = q.pop_front();
+        if q.is_empty() {
+            self.per_leader.remove(&leader);
+        } else {
+            self.turn.push_back(leader);
+        }
+        self.len -= 1;
+        job
+    }
+}
+
+#[derive(Default)]
+struct Classes {
+    exits: ClassQueue,
+    entries: ClassQueue,
+}
+
+pub struct CopyQueue {
+    classes: Mutex<Classes>,
+    ready: Notify,
+    /// Per-class limit
+    capacity: usize,
+}
+
+impl CopyQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            classes: Mutex::new(Classes::default()),
+            ready: Notify::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&self, job: CopyJob) -> Result<(), QueueError> {
+        self.push_back(job).map_err(|(_, e)| e)
+    }
+
+    /// [`push`](Self::push) that hands the job back when its class is full
+    #[allow(clippy::result_large_err)]
+    fn push_back(&self, job: CopyJob) -> Result<(), (CopyJob, QueueError)> {
+        let prio = job.priority();
+        {
+            let mut c = self.classes.lock().expect("copy queue lock");
+            let class = match prio {
+                Priority::Exit => &mut c.exits,
+                Priority::Entry => &mut c.entries,
+            };
+            if class.len >= self.capacity {
+                increment_counter!("copy_queue_rejected_total", "class" => prio.label());
+                return Err((job, QueueError::Full(prio.label())));
+            }
+            class.push(job);
+            Self::publish_depth(&c);
+        }
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// Next job: any exit first, else an entry
+    pub fn try_pop(&self) -> Option<CopyJob> {
+        let mut c = self.classes.lock().expect("copy queue lock");
+        let job = c.exits.pop().or_else(|| c.entries.pop());
+        Self::publish_depth(&c);
+        job
+    }
+
+    pub async fn pop(&self) -> CopyJob {
+        loop {
+            let notified = self.ready.notified();
+            if let Some(job) = self.try_pop() {
+                return job;
+            }
+            notified.await;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        let c = self.classes.lock().expect("copy queue lock");
+        c.exits.len + c.entries.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn publish_depth(c: &Classes) {
+        gauge!("copy_queue_depth", c.exits.len as f64, "class" => "exit");
+        gauge!("copy_queue_depth", c.entries.len as f64, "class" => "entry");
+    }
+}
+
+// ─── Jitter ───────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Jitter {
+    pub max_delay: Duration,
+    /// ± fraction of size, e.g. `0.05` = up to 5 % either way
+    pub size_frac: f64,
+}
+
+impl Jitter {
+    pub fn is_off(&self) -> bool {
+        self.max_delay.is_zero() && self.size_frac <= 0.0
+    }
+
+    /// Random delay and size for one entry copy. The size keeps the
+    /// original's decimal places so we never invent a finer lot step.
+    pub fn sample<R: Rng>(&self, rng: &mut R, size: f64) -> (Duration, f64) {
+        let delay = if self.max_delay.is_zero() {
+            Duration::ZERO
+        } else {
+            rng.gen_range(Duration::ZERO..=self.max_delay)
+        };
+        if self.size_frac <= 0.0 {
+            return (delay, size);
+        }
+        let factor = 1.0 + rng.gen_range(-self.size_frac..=self.size_frac);
+        let scale = 10f64.powi(decimals(size) as i32);
+        let jittered = (size * factor * scale).round() / scale;
+        (delay, if jittered > 0.0 { jittered } else { size })
+    }
+}
+
+// ─── Retries ──────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retry {
+    /// Executions before a job is dead-lettered
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubles with each one after
+    pub base_delay: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl Retry {
+    /// Wait before running a job again after `attempts` executions
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        self.base_delay * 2u32.saturating_pow(attempts.saturating_sub(1))
+    }
+}
+
+// ─── Outcomes ─────────────────────────────────────────────────────────────
+/// A follower order that went through
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Placed {
+    pub order_id: Option<Uuid>,
+    /// vs. the leader's price, + = adverse
+    pub slippage_bps: Option<f64>,
+}
+
+/// How a copy job ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Placed(Placed),
+    /// Not worth retrying (risk guard, rejection, ambiguous timeout)
+    Failed(String),
+    /// Transient errors outlasted the retries
+    DeadLetter(String),
+}
+
+impl Outcome {
+    pub fn status(&self) -> &'static str {
+        match self {
+            Outcome::Placed(_) => "ok",
+            Outcome::Failed(_) => "failed",
+            Outcome::DeadLetter(_) => "dead_letter",
+        }
+    }
+}
+
+// ─── Workers ──────────────────────────────────────────────────────────────
+#[async_trait]
+pub trait CopyExecutor: Send + Sync + 'static {
+    async fn execute(&self, job: &CopyJob) -> Result<Placed, TradeError>;
+
+    /// Keep a job's final outcome; nothing by default
+    async fn record(&self, _job: &CopyJob, _outcome: &Outcome) {}
+
+    fn is_transient(&self, e: &TradeError) -> bool {
+        e.is_transient()
+    }
+}
+
+/// Production path: DD guard on entries, then place the follower order
+pub struct TradeExecutor {
+    pub pg: PgPool,
+    pub cache: SharedCache,
+    pub is_demo: bool,
+    pub master_key: Vec<u8>,
+}
+
+#[async_trait]
+impl CopyExecutor for TradeExecutor {
+    async fn execute(&self, job: &CopyJob) -> Result<Placed, TradeError> {
+        // never block an exit on the draw-down guard – it reduces risk
+        if job.priority() == Priority::Entry {
+            risk::check_drawdown(self.cache.as_ref(), job.follower_id).await?;
+        }
+        let resp = execute_trade(
+            job.req.clone(),
+            &self.pg,
+            job.follower_id,
+            self.is_demo,
+            &self.master_key,
+        )
+        .await?;
+        if !resp.success {
+            return Err(TradeError::Other(format!("rejected: {}", resp.data)));
+        }
+        // the fill itself lands later; the submission price is what we know
+        let submitted = resp.price.or(resp.mid_at_submit);
+        Ok(Placed {
+            order_id: resp.order_id,
+            slippage_bps: job
+                .req
+                .signal_price
+                .zip(submitted)
+                .and_then(|(leader, px)| analytics::slippage_bps(&resp.side, leader, px)),
+        })
+    }
+
+    async fn record(&self, job: &CopyJob, outcome: &Outcome) {
+        let (placed, error) = match outcome {
+            Outcome::Placed(p) => (*p, None),
+            Outcome::Failed(e) | Outcome::DeadLetter(e) => (Placed::default(), Some(e.as_str())),
+        };
+        let request = matches!(outcome, Outcome::DeadLetter(_)).then(|| request_json(&job.req));
+        let res = sqlx::query(
+            r#"
+            INSERT INTO copy_events
+                  (leader_order_id, follower_order_id, leader_user_id, follower_user_id,
+                   status, attempts, slippage_bps, error, request)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(job.leader_order_id)
+        .bind(placed.order_id)
+        .bind(job.leader_id)
+        .bind(job.follower_id)
+        .bind(outcome.status())
+        .bind(job.attempts as i32)
+        .bind(placed.slippage_bps)
+        .bind(error)
+        .bind(request)
+        .execute(&self.pg)
+        .await;
+        if let Err(e) = res {
+            tracing::warn!("copy event for follower {}: {e}", job.follower_id);
+        }
+    }
+}
+
+/// A dead-lettered order, as much of it as a replay needs
+fn request_json(req: &TradeRequest) -> Value {
+    json!({
+        "exchange": req.exchange,
+        "symbol": req.symbol,
+        "side": req.side,
+        "order_type": req.order_type,
+        "price": req.price,
+        "size": req.size,
+        "reduce_only": req.reduce_only,
+        "signal_price": req.signal_price,
+    })
+}
+
+/// Workers sleep through an entry's jitter delay themselves, so size
+/// `COPY_WORKERS` with `COPY_JITTER_MAX_MS` in mind. Retries wait out their
+/// backoff off the workers and rejoin the back of the queue.
+pub fn spawn_workers<E: CopyExecutor>(
+    queue: Arc<CopyQueue>,
+    workers: usize,
+    jitter: Jitter,
+    retry: Retry,
+    exec: Arc<E>,
+) {
+    for _ in 0..workers.max(1) {
+        let queue = queue.clone();
+        let exec = exec.clone();
+        tokio::spawn(async move {
+            loop {
+                let mut job = queue.pop().await;
+                // a retry keeps the size it was first jittered to
+                if job.priority() == Priority::Entry && job.attempts == 0 && !jitter.is_off() {
+                    let (delay, size) = jitter.sample(&mut rand::thread_rng(), job.req.size);
+                    histogram!("copy_jitter_ms", delay.as_secs_f64() * 1_000.0);
+                    job.req.size = size;
+                    tokio::time::sleep(delay).await;
+                }
+                let result = exec.execute(&job).await;
+                job.attempts += 1;
+                match result {
+                    Ok(placed) => finish(exec.as_ref(), job, Outcome::Placed(placed)).await,
+                    Err(e) if exec.is_transient(&e) && job.attempts < retry.max_attempts => {
+                        let prio = job.priority().label();
+                        increment_counter!("copy_jobs_total", "class" => prio, "result" => "retry");
+                        tracing::info!(
+                            "copy {prio} leader {} → follower {} retrying: {e}",
+                            job.leader_id,
+                            job.follower_id
+                        );
+                        let (queue, exec) = (queue.clone(), exec.clone());
+                        tokio::spawn(async move {
+                            tokio::time::sleep(retry.backoff(job.attempts)).await;
+                            if let Err((job, e)) = queue.push_back(job) {
+                                finish(exec.as_ref(), job, Outcome::DeadLetter(e.to_string()))
+                                    .await;
+                            }
+                        });
+                    }
+                    Err(e) if exec.is_transient(&e) => {
+                        finish(exec.as_ref(), job, Outcome::DeadLetter(e.to_string())).await
+                    }
+                    Err(e) => finish(exec.as_ref(), job, Outcome::Failed(e.to_string())).await,
+                }
+            }
+        });
+    }
+}
+
+/// Count, log and record a job's final outcome
+async fn finish<E: CopyExecutor>(exec: &E, job: CopyJob, outcome: Outcome) {
+    let prio = job.priority().label();
+    increment_counter!("copy_jobs_total", "class" => prio, "result" => outcome.status());
+    match &outcome {
+        Outcome::Placed(_) => {}
+        Outcome::Failed(e) | Outcome::DeadLetter(e) => tracing::warn!(
+            "copy {prio} leader {} → follower {} {} after {} attempt(s): {e}",
+            job.leader_id,
+            job.follower_id,
+            outcome.status(),
+            job.attempts
+        ),
+    }
+    exec.record(&job, &outcome).await;
+}
+
+// ─── Global handle ────────────────────────────────────────────────────────
+static QUEUE: OnceCell<Arc<CopyQueue>> = OnceCell::new();
+
+/// Start the worker pool (call once from `main`)
+pub fn init(capacity: usize, workers: usize, jitter: Jitter, exec: TradeExecutor) {
+    let queue = Arc::new(CopyQueue::new(capacity));
+    if QUEUE.set(queue.clone()).is_ok() {
+        spawn_workers(queue, workers, jitter, Retry::default(), Arc::new(exec));
+    }
+}
+
+pub fn enqueue(job: CopyJob) -> Result<(), QueueError> {
+    QUEUE.get().ok_or(QueueError::NotRunning)?.push(job)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::trading_engine::Exchange;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn job(leader: i64, follower: i64, reduce_only: bool) -> CopyJob {
+        CopyJob::new(
+            leader,
+            follower,
+            TradeRequest {
+                exchange: Exchange::Blowfin,
+                symbol: "BTC-USDT".into(),
+                side: "buy".into(),
+                order_type: "market".into(),
+                price: None,
+                size: 1.0,
+                reduce_only,
+                signal_price: None,
+                tp_sl: None,
+            },
+        )
+    }
+
+    fn drain_ids(q: &CopyQueue) -> Vec<(i64, i64)> {
+        std::iter::from_fn(|| q.try_pop())
+            .map(|j| (j.leader_id, j.follower_id))
+            .collect()
+    }
+
+    #[test]
+    fn exits_jump_ahead_of_entries() {
+        let q = CopyQueue::new(100);
+        q.push(job(1, 10, false)).unwrap();
+        q.push(job(1, 11, false)).unwrap();
+        q.push(job(2, 20, true)).unwrap();
+        assert_eq!(drain_ids(&q), vec![(2, 20), (1, 10), (1, 11)]);
+    }
+
+    #[test]
+    fn leaders_are_served_round_robin() {
+        let q = CopyQueue::new(100);
+        for f in 0..3 {
+            q.push(job(1, 100 + f, false)).unwrap();
+        }
+        q.push(job(2, 200, false)).unwrap();
+        q.push(job(3, 300, false)).unwrap();
+        assert_eq!(
+            drain_ids(&q),
+            vec![(1, 100), (2, 200), (3, 300), (1, 101), (1, 102)]
+        );
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn full_entry_class_still_accepts_exits() {
+        let q = CopyQueue::new(2);
+        q.push(job(1, 1, false)).unwrap();
+        q.push(job(1, 2, false)).unwrap();
+        assert_eq!(q.push(job(1, 3, false)), Err(QueueError::Full("entry")));
+        q.push(job(1, 4, true)).unwrap();
+        assert_eq!(q.len(), 3);
+    }
+
+    #[test]
+    fn queued_jobs_hold_drain_guards() {
+        let before = drain::in_flight();
+        let q = CopyQueue::new(10);
+        q.push(job(1, 1, false)).unwrap();
+        assert!(drain::in_flight() > before);
+        drop(q.try_pop());
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let j = Jitter {
+            max_delay: Duration::from_millis(500),
+            size_frac: 0.05,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut delays = std::collections::HashSet::new();
+        let mut sizes = std::collections::HashSet::new();
+        for _ in 0..200 {
+            let (d, s) = j.sample(&mut rng, 10.25);
+            assert!(d <= j.max_delay);
+            assert!((9.73..=10.77).contains(&s), "size {s}");
+            assert!(decimals(s) <= 2, "no finer than the input lot: {s}");
+            delays.insert(d);
+            sizes.insert(s.to_string());
+        }
+        assert!(delays.len() > 100, "delays should actually vary");
+        assert!(sizes.len() > 20, "sizes should actually vary");
+    }
+
+    #[test]
+    fn jitter_off_is_identity_and_never_zeroes_size() {
+        let mut rng = rand::thread_rng();
+        assert!(Jitter::default().is_off());
+        assert_eq!(
+            Jitter::default().sample(&mut rng, 0.25),
+            (Duration::ZERO, 0.25)
+        );
+
+        // one-lot orders can't shrink to nothing
+        let j = Jitter {
+            max_delay: Duration::ZERO,
+            size_frac: 0.5,
+        };
+        for _ in 0..50 {
+            assert!(j.sample(&mut rng, 1.0).1 >= 1.0);
+        }
+    }
+
+    struct Counting(AtomicUsize);
+    #[async_trait]
+    impl CopyExecutor for Counting {
+        async fn execute(&self, _job: &CopyJob) -> Result<Placed, TradeError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(Placed::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn workers_drain_the_queue() {
+        let q = Arc::new(CopyQueue::new(100));
+        let exec = Arc::new(Counting(AtomicUsize::new(0)));
+        let jitter = Jitter {
+            max_delay: Duration::from_millis(3),
+            size_frac: 0.1,
+        };
+        spawn_workers(q.clone(), 3, jitter, Retry::default(), exec.clone());
+        for f in 0..20 {
+            q.push(job(f % 4, f, f % 5 == 0)).unwrap();
+        }
+        for _ in 0..100 {
+            if exec.0.load(Ordering::SeqCst) == 20 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(exec.0.load(Ordering::SeqCst), 20);
+        assert!(q.is_empty());
+    }
+
+    /// Fails follower 1 once, follower 2 always (transiently), follower 3
+    /// for good
+    #[derive(Default)]
+    struct Flaky {
+        tries: Mutex<HashMap<i64, u32>>,
+        outcomes: Mutex<Vec<(i64, &'static str, u32)>>,
+    }
+    #[async_trait]
+    impl CopyExecutor for Flaky {
+        async fn execute(&self, job: &CopyJob) -> Result<Placed, TradeError> {
+            let n = {
+                let mut t = self.tries.lock().unwrap();
+                let n = t.entry(job.follower_id).or_default();
+                *n += 1;
+                *n
+            };
+            match (job.follower_id, n) {
+                (1, 1) | (2, _) => Err(TradeError::Other("busy".into())),
+                (3, _) => Err(TradeError::InvalidRequest("bad size".into())),
+                _ => Ok(Placed::default()),
+            }
+        }
+
+        async fn record(&self, job: &CopyJob, outcome: &Outcome) {
+            let row = (job.follower_id, outcome.status(), job.attempts);
+            self.outcomes.lock().unwrap().push(row);
+        }
+
+        fn is_transient(&self, e: &TradeError) -> bool {
+            matches!(e, TradeError::Other(m) if m == "busy")
+        }
+    }
+
+    #[tokio::test]
+    async fn transient_failures_retry_then_dead_letter() {
+        let q = Arc::new(CopyQueue::new(100));
+        let exec = Arc::new(Flaky::default());
+        let retry = Retry {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        spawn_workers(q.clone(), 2, Jitter::default(), retry, exec.clone());
+        for f in 1..=3 {
+            q.push(job(7, f, true)).unwrap();
+        }
+        for _ in 0..200 {
+            if exec.outcomes.lock().unwrap().len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let mut got = exec.outcomes.lock().unwrap().clone();
+        got.sort();
+        assert_eq!(
+            got,
+            vec![(1, "ok", 2), (2, "dead_letter", 3), (3, "failed", 1)]
+        );
+    }
+
+    #[test]
+    fn retry_backoff_doubles() {
+        let r = Retry {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+        };
+        assert_eq!(r.backoff(1), Duration::from_millis(100));
+        assert_eq!(r.backoff(3), Duration::from_millis(400));
+    }
+}
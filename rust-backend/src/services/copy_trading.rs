@@ -2,15 +2,24 @@
 
 // use std::{fmt, time::Duration};
 
-use crate::services::risk;
-use redis::AsyncCommands;
+use std::{collections::HashMap, future::Future};
+
+use crate::services::{
+    audit,
+    blowfin::api,
+    copy_aggregate,
+    copy_queue::{self, CopyJob},
+    market_data, notify, portfolio, positions, risk, usage,
+};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::postgres::PgPool;
 use uuid::Uuid;
 
 use crate::{
-    db::redis::RedisPool,
-    services::trading_engine::{execute_trade, TradeRequest, TradeResponse},
+    db::cache::{Cache, CacheError},
+    services::trading_engine::{TradeRequest, TradeResponse},
     utils::errors::TradeError,
 };
 
@@ -18,10 +27,20 @@ use crate::{
 pub enum CopyError {
     #[error("db: {0}")]
     Db(#[from] sqlx::Error),
-    #[error("redis: {0}")]
-    Redis(#[from] redis::RedisError),
+    #[error("cache: {0}")]
+    Cache(#[from] CacheError),
     #[error("trade: {0}")]
     Trade(#[from] TradeError),
+    #[error("already copying this leader")]
+    AlreadyFollowing,
+    #[error("the trial with this leader has been used")]
+    TrialUsed,
+    #[error("no running trial with this leader")]
+    NoTrial,
+    #[error("not copying this leader")]
+    NotFollowing,
+    #[error("invalid copy config: {0}")]
+    InvalidConfig(&'static str),
 }
 
 /// Persistent model (matches `copy_relations` table)
@@ -35,21 +54,29 @@ pub struct CopyRelation {
     pub status: String,
 }
 
-/// TTL for Redis follower sets (in seconds)
-const FOLLOWER_SET_TTL: usize = 300; // 5 min
+/// TTL for cached follower sets (in seconds)
+const FOLLOWER_SET_TTL: u64 = 300; // 5 min
+
+fn followers_key(leader_id: i64) -> String {
+    format!("copy:{leader_id}")
+}
 
 //  ================  Public API  ==================================================================
 
-/// Follow a leader.  Persists to Postgres **and** adds follower to Redis set.
+/// Follow a leader.  Persists to Postgres **and** adds follower to the cached set.
 ///
 /// * `leader_id` – Discord snowflake of the leader
 /// * `follower_id` – Discord snowflake of the follower
 pub async fn add_follower(
     pg: &PgPool,
-    redis: &RedisPool,
+    cache: &dyn Cache,
     leader_id: i64,
     follower_id: i64,
 ) -> Result<(), CopyError> {
+    // following outright is an explicit confirmation of a running trial
+    if confirm_trial(pg, cache, leader_id, follower_id).await? {
+        return Ok(());
+    }
     sqlx::query!(
         r#"
         INSERT INTO copy_relations (leader_user_id, follower_user_id)
@@ -63,17 +90,17 @@ pub async fn add_follower(
     .execute(pg)
     .await?;
 
-    let key = redis.with_prefix("copy", leader_id.to_string());
-    let mut conn = redis.manager().as_ref().clone();
-    conn.sadd::<_, _, ()>(&key, follower_id).await?;
-    conn.expire::<_, ()>(&key, FOLLOWER_SET_TTL as i64).await?;
+    let key = followers_key(leader_id);
+    cache.sadd(&key, &[follower_id.to_string()]).await?;
+    cache.expire(&key, FOLLOWER_SET_TTL).await?;
+    usage::invalidate_copy(cache, follower_id).await;
     Ok(())
 }
 
-/// Remove follower (soft delete) & update Redis.
+/// Remove follower (soft delete) & update the cached set.
 pub async fn remove_follower(
     pg: &PgPool,
-    redis: &RedisPool,
+    cache: &dyn Cache,
     leader_id: i64,
     follower_id: i64,
 ) -> Result<(), CopyError> {
@@ -83,7 +110,7 @@ pub async fn remove_follower(
            SET status = 'ended', until = now()
          WHERE leader_user_id = $1
            AND follower_user_id = $2
-           AND status = 'active'
+           AND status IN ('active', 'trial')
         "#,
         leader_id,
         follower_id
@@ -91,22 +118,23 @@ pub async fn remove_follower(
     .execute(pg)
     .await?;
 
-    let key = redis.with_prefix("copy", leader_id.to_string());
-    let mut conn = redis.manager().as_ref().clone();
-    conn.srem::<_, _, ()>(&key, follower_id).await?;
+    cache
+        .srem(&followers_key(leader_id), &follower_id.to_string())
+        .await?;
+    usage::invalidate_copy(cache, follower_id).await;
     Ok(())
 }
 
-/// Returns the current follower list, served from Redis when possible.
+/// Returns the current follower list, served from the cache when possible.
 pub async fn followers_for_leader(
     pg: &PgPool,
-    redis: &RedisPool,
+    cache: &dyn Cache,
     leader_id: i64,
 ) -> Result<Vec<i64>, CopyError> {
-    let key = redis.with_prefix("copy", leader_id.to_string());
-    let mut conn = redis.manager().as_ref().clone();
+    let key = followers_key(leader_id);
 
-    if let Ok(ids) = conn.smembers::<_, Vec<i64>>(&key).await {
+    if let Ok(ids) = cache.smembers(&key).await {
+        let ids: Vec<i64> = ids.iter().filter_map(|s| s.parse().ok()).collect();
         if !ids.is_empty() {
             return Ok(ids);
         }
@@ -117,7 +145,7 @@ pub async fn followers_for_leader(
         SELECT follower_user_id
           FROM copy_relations
          WHERE leader_user_id = $1
-           AND status = 'active'
+           AND (status = 'active' OR (status = 'trial' AND trial_ends_at > now()))
         "#,
     )
     .bind(leader_id)
@@ -126,60 +154,602 @@ pub async fn followers_for_leader(
 
     let followers: Vec<i64> = rows.into_iter().map(|r| r.0).collect();
     if !followers.is_empty() {
-        conn.sadd::<_, _, ()>(&key, &followers).await?; // <--- annotate type as ()
-        conn.expire::<_, ()>(&key, FOLLOWER_SET_TTL as i64).await?;
+        let members: Vec<String> = followers.iter().map(i64::to_string).collect();
+        cache.sadd(&key, &members).await?;
+        cache.expire(&key, FOLLOWER_SET_TTL).await?;
     }
     Ok(followers)
 }
 
+//  ================  Sizing  =====================================================================
+
+/// How a follower's copies are sized against the leader's orders
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum CopySizing {
+    /// `ratio` × the leader's size
+    Ratio { ratio: f64 },
+    /// Scaled by account size, as if the follower put `pct` % of their
+    /// equity behind the leader's whole account
+    EquityPct { pct: f64 },
+}
+
+impl Default for CopySizing {
+    fn default() -> Self {
+        CopySizing::Ratio { ratio: 1.0 }
+    }
+}
+
+/// Per-relation copy settings (columns of `copy_relations`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CopyConfig {
+    #[serde(flatten)]
+    pub sizing: CopySizing,
+    /// Largest entry copied, in USDT notional; exits are never capped
+    #[serde(default)]
+    pub max_notional: Option<f64>,
+}
+
+impl CopyConfig {
+    pub fn validate(&self) -> Result<(), CopyError> {
+        let pos = |v: f64| v.is_finite() && v > 0.0;
+        match self.sizing {
+            CopySizing::Ratio { ratio } if !(pos(ratio) && ratio <= 100.0) => {
+                return Err(CopyError::InvalidConfig("ratio must be in (0, 100]"));
+            }
+            CopySizing::EquityPct { pct } if !(pos(pct) && pct <= 100.0) => {
+                return Err(CopyError::InvalidConfig("pct must be in (0, 100]"));
+            }
+            _ => {}
+        }
+        if self.max_notional.is_some_and(|n| !pos(n)) {
+            return Err(CopyError::InvalidConfig("max_notional must be positive"));
+        }
+        Ok(())
+    }
+
+    fn from_row(mode: &str, value: f64, max_notional: Option<f64>) -> Self {
+        let sizing = match mode {
+            "equity_pct" => CopySizing::EquityPct { pct: value },
+            _ => CopySizing::Ratio { ratio: value },
+        };
+        Self {
+            sizing,
+            max_notional,
+        }
+    }
+
+    fn to_row(self) -> (&'static str, f64) {
+        match self.sizing {
+            CopySizing::Ratio { ratio } => ("ratio", ratio),
+            CopySizing::EquityPct { pct } => ("equity_pct", pct),
+        }
+    }
+}
+
+/// A follower's copy settings for `leader_id`
+pub async fn copy_config(
+    pg: &PgPool,
+    leader_id: i64,
+    follower_id: i64,
+) -> Result<CopyConfig, CopyError> {
+    let row: Option<(String, f64, Option<f64>)> = sqlx::query_as(
+        r#"
+        SELECT size_mode, size_value::float8, max_notional::float8
+          FROM copy_relations
+         WHERE leader_user_id = $1 AND follower_user_id = $2
+           AND status IN ('active', 'trial')
+        "#,
+    )
+    .bind(leader_id)
+    .bind(follower_id)
+    .fetch_optional(pg)
+    .await?;
+    let (mode, value, cap) = row.ok_or(CopyError::NotFollowing)?;
+    Ok(CopyConfig::from_row(&mode, value, cap))
+}
+
+/// Replace a follower's copy settings for `leader_id`
+pub async fn set_copy_config(
+    pg: &PgPool,
+    leader_id: i64,
+    follower_id: i64,
+    cfg: CopyConfig,
+) -> Result<(), CopyError> {
+    cfg.validate()?;
+    let (mode, value) = cfg.to_row();
+    let updated = sqlx::query(
+        r#"
+        UPDATE copy_relations
+           SET size_mode = $3, size_value = $4, max_notional = $5
+         WHERE leader_user_id = $1 AND follower_user_id = $2
+           AND status IN ('active', 'trial')
+        "#,
+    )
+    .bind(leader_id)
+    .bind(follower_id)
+    .bind(mode)
+    .bind(value)
+    .bind(cfg.max_notional)
+    .execute(pg)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Err(CopyError::NotFollowing);
+    }
+    audit::record(
+        Some(follower_id),
+        "copy.config",
+        json!({ "leader_id": leader_id, "config": cfg }),
+    );
+    Ok(())
+}
+
+/// Copy settings of every current follower of a leader
+async fn copy_configs(pg: &PgPool, leader_id: i64) -> Result<HashMap<i64, CopyConfig>, CopyError> {
+    let rows: Vec<(i64, String, f64, Option<f64>)> = sqlx::query_as(
+        r#"
+        SELECT follower_user_id, size_mode, size_value::float8, max_notional::float8
+          FROM copy_relations
+         WHERE leader_user_id = $1 AND status IN ('active', 'trial')
+        "#,
+    )
+    .bind(leader_id)
+    .fetch_all(pg)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(fid, mode, value, cap)| (fid, CopyConfig::from_row(&mode, value, cap)))
+        .collect())
+}
+
+/// Follower size for a leader order of `leader_size`; `None` when it can't
+/// be sized (equity unknown, no price for the notional cap) or rounds to 0.
+/// `equity` is (follower, leader) and only read for `EquityPct`.
+pub fn scaled_size(
+    leader_size: f64,
+    cfg: &CopyConfig,
+    equity: Option<(f64, f64)>,
+    price: Option<f64>,
+    reduce_only: bool,
+) -> Option<f64> {
+    let size = match cfg.sizing {
+        CopySizing::Ratio { ratio } => leader_size * ratio,
+        CopySizing::EquityPct { pct } => {
+            let (follower, leader) = equity.filter(|(_, l)| *l > 0.0)?;
+            leader_size * follower * pct / 100.0 / leader
+        }
+    };
+    let size = match cfg.max_notional {
+        Some(cap) if !reduce_only => size.min(cap / price.filter(|p| *p > 0.0)?),
+        _ => size,
+    };
+    (size.is_finite() && size > 0.0).then_some(size)
+}
+
+/// One follower's size of the leader's order. `equity` (the follower's
+/// balance) is only awaited for `EquityPct`, `open` (their net position in
+/// the symbol) only when an exit can't be sized otherwise. Exits are never
+/// dropped: without a balance they close what the follower holds, else the
+/// leader's size – reduce-only, so the exchange caps it at the position.
+pub async fn follower_size(
+    leader: &TradeRequest,
+    cfg: &CopyConfig,
+    leader_equity: Option<f64>,
+    price: Option<f64>,
+    equity: impl Future<Output = Option<f64>>,
+    open: impl Future<Output = Option<f64>>,
+) -> Option<f64> {
+    let equity = match cfg.sizing {
+        CopySizing::EquityPct { .. } => equity.await,
+        CopySizing::Ratio { .. } => None,
+    };
+    let sized = scaled_size(
+        leader.size,
+        cfg,
+        equity.zip(leader_equity),
+        price,
+        leader.reduce_only,
+    );
+    if sized.is_some() || !leader.reduce_only {
+        return sized;
+    }
+    let held = open.await.map(f64::abs).filter(|q| *q > 0.0);
+    Some(held.unwrap_or(leader.size))
+}
+
+/// The user's net open quantity in `symbol`, `None` when flat or unknown
+async fn open_qty(
+    pg: &PgPool,
+    user_id: i64,
+    symbol: &str,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Option<f64> {
+    let rows = api::get_positions(pg, user_id, is_demo, master_key)
+        .await
+        .and_then(|r| r.into_data())
+        .map_err(|e| tracing::debug!("copy: positions for user {user_id}: {e}"))
+        .ok()?;
+    positions::net_position(&rows, symbol).map(|(_, qty)| qty)
+}
+
+//  ================  Trials  =====================================================================
+
+/// A running trial's terms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrialTerms {
+    pub ends_at: DateTime<Utc>,
+    pub max_size: f64,
+}
+
+/// Start a time-boxed trial copy: every copied order is capped at
+/// `max_size` and the relation expires after `days` unless confirmed.
+/// One trial per leader; returns when it ends.
+pub async fn start_trial(
+    pg: &PgPool,
+    cache: &dyn Cache,
+    leader_id: i64,
+    follower_id: i64,
+    days: i64,
+    max_size: f64,
+) -> Result<DateTime<Utc>, CopyError> {
+    let (following, tried): (bool, bool) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(bool_or(status IN ('active', 'trial')), FALSE),
+               COALESCE(bool_or(trial_ends_at IS NOT NULL), FALSE)
+          FROM copy_relations
+         WHERE leader_user_id = $1 AND follower_user_id = $2
+        "#,
+    )
+    .bind(leader_id)
+    .bind(follower_id)
+    .fetch_one(pg)
+    .await?;
+    if following {
+        return Err(CopyError::AlreadyFollowing);
+    }
+    if tried {
+        return Err(CopyError::TrialUsed);
+    }
+
+    let ends_at = Utc::now() + Duration::days(days);
+    // a concurrent call may have started the trial since the check above
+    let started = sqlx::query(
+        r#"
+        INSERT INTO copy_relations
+              (leader_user_id, follower_user_id, status, trial_ends_at, trial_max_size)
+        VALUES ($1, $2, 'trial', $3, $4)
+        ON CONFLICT (leader_user_id, follower_user_id) WHERE trial_ends_at IS NOT NULL
+        DO NOTHING
+        "#,
+    )
+    .bind(leader_id)
+    .bind(follower_id)
+    .bind(ends_at)
+    .bind(max_size)
+    .execute(pg)
+    .await?
+    .rows_affected();
+    if started == 0 {
+        return Err(CopyError::TrialUsed);
+    }
+
+    let key = followers_key(leader_id);
+    cache.sadd(&key, &[follower_id.to_string()]).await?;
+    cache.expire(&key, FOLLOWER_SET_TTL).await?;
+    usage::invalidate_copy(cache, follower_id).await;
+    audit::record(
+        Some(follower_id),
+        "copy.trial_start",
+        json!({ "leader_id": leader_id, "ends_at": ends_at, "max_size": max_size }),
+    );
+    Ok(ends_at)
+}
+
+/// Turn a running trial into a full relation; `false` when there is none
+pub async fn confirm_trial(
+    pg: &PgPool,
+    cache: &dyn Cache,
+    leader_id: i64,
+    follower_id: i64,
+) -> Result<bool, CopyError> {
+    // trial_ends_at stays: it marks the trial as used
+    let confirmed = sqlx::query(
+        r#"
+        UPDATE copy_relations
+           SET status = 'active', trial_max_size = NULL
+         WHERE leader_user_id = $1 AND follower_user_id = $2
+           AND status = 'trial' AND trial_ends_at > now()
+        "#,
+    )
+    .bind(leader_id)
+    .bind(follower_id)
+    .execute(pg)
+    .await?
+    .rows_affected()
+        > 0;
+    if confirmed {
+        usage::invalidate_copy(cache, follower_id).await;
+        audit::record(
+            Some(follower_id),
+            "copy.trial_confirm",
+            json!({ "leader_id": leader_id }),
+        );
+    }
+    Ok(confirmed)
+}
+
+/// End every trial past its window; run from the scheduler's reconcile
+pub async fn expire_trials(pg: &PgPool, cache: &dyn Cache) -> Result<usize, CopyError> {
+    let expired: Vec<(i64, i64)> = sqlx::query_as(
+        r#"
+        UPDATE copy_relations
+           SET status = 'expired', until = now()
+         WHERE status = 'trial' AND trial_ends_at <= now()
+        RETURNING leader_user_id, follower_user_id
+        "#,
+    )
+    .fetch_all(pg)
+    .await?;
+    for &(leader_id, follower_id) in &expired {
+        cache
+            .srem(&followers_key(leader_id), &follower_id.to_string())
+            .await?;
+        usage::invalidate_copy(cache, follower_id).await;
+        notify::send(
+            follower_id,
+            "copy.trial_expired",
+            "Your trial copy has ended – positions it opened stay open; follow the leader to keep copying",
+            json!({ "leader_id": leader_id }),
+        );
+    }
+    Ok(expired.len())
+}
+
+/// Trial followers of a leader, expired ones included until they are reaped
+async fn trial_terms(pg: &PgPool, leader_id: i64) -> Result<HashMap<i64, TrialTerms>, CopyError> {
+    let rows: Vec<(i64, DateTime<Utc>, f64)> = sqlx::query_as(
+        r#"
+        SELECT follower_user_id, trial_ends_at, trial_max_size::float8
+          FROM copy_relations
+         WHERE leader_user_id = $1 AND status = 'trial'
+        "#,
+    )
+    .bind(leader_id)
+    .fetch_all(pg)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(fid, ends_at, max_size)| (fid, TrialTerms { ends_at, max_size }))
+        .collect())
+}
+
+/// Size a follower gets of a leader order; `None` once their trial is over
+pub fn copy_size(size: f64, trial: Option<&TrialTerms>, now: DateTime<Utc>) -> Option<f64> {
+    match trial {
+        None => Some(size),
+        Some(t) if t.ends_at <= now => None,
+        Some(t) => Some(size.min(t.max_size)),
+    }
+}
+
 /// Propagate a filled order **from leader** to every follower.
 ///
-///  function is the bridge between the leader’s trading logic and follower replication.
-/// In v1 **synchronously** loop – for ≤ ~100 followers.
-/// Later: spawn tasks / use a queue.
+/// Each follower's size comes from their [`CopyConfig`], then any trial
+/// cap. Queues one job per follower on `copy_queue` (exits ahead of entries,
+/// leaders round-robin) and returns how many were accepted; the worker pool
+/// applies the draw-down guard and places the orders.
 pub async fn replicate_to_followers(
     pg: &PgPool,
-    redis: &RedisPool,
+    cache: &dyn Cache,
     leader_id: i64,
     leader_fill: &TradeResponse,
-    settings: &crate::config::settings::Settings,
-) -> Result<(), CopyError> {
-    let followers = followers_for_leader(pg, redis, leader_id).await?;
-
-    // -- Grab master key (for decrypting each follower’s API key) --
-    let master_key = std::env::var("MASTER_KEY").unwrap_or_default();
-    let master_key_bytes = master_key.as_bytes();
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<usize, CopyError> {
+    let followers = followers_for_leader(pg, cache, leader_id).await?;
+    let trials = trial_terms(pg, leader_id).await?;
+    let configs = copy_configs(pg, leader_id).await?;
+    let now = Utc::now();
 
-    let is_demo = settings.is_demo();
+    // the leader's order; each follower gets their own size of it
+    let template = TradeRequest {
+        exchange: leader_fill.exchange,
+        symbol: leader_fill.symbol.clone(),
+        side: leader_fill.side.clone(),
+        order_type: leader_fill.order_type.clone(),
+        price: leader_fill.price,
+        size: leader_fill.size,
+        reduce_only: leader_fill.reduce_only,
+        // follower slippage is measured against the leader's price
+        signal_price: leader_fill.signal_price.or(leader_fill.price),
+        tp_sl: None,
+    };
+    let price = template
+        .price
+        .or(template.signal_price)
+        .or_else(|| market_data::mark_price(&template.symbol).map(|m| m.mark));
+    let by_equity = |c: &CopyConfig| matches!(c.sizing, CopySizing::EquityPct { .. });
+    let leader_equity = if configs.values().any(by_equity) {
+        portfolio::account_equity(pg, leader_id, is_demo, master_key).await
+    } else {
+        None
+    };
 
+    let mut sized = Vec::with_capacity(followers.len());
     for fid in followers {
-        if let Err(e) = risk::check_drawdown(redis, fid).await {
-            log::warn!("follower {fid}: DD limit hit – skipping copy: {e}");
-            continue; // just skip this follower
+        let cfg = configs.get(&fid).copied().unwrap_or_default();
+        let Some(size) = follower_size(
+            &template,
+            &cfg,
+            leader_equity,
+            price,
+            portfolio::account_equity(pg, fid, is_demo, master_key),
+            open_qty(pg, fid, &template.symbol, is_demo, master_key),
+        )
+        .await
+        else {
+            tracing::info!("copy for follower {fid} of leader {leader_id} skipped: cannot size it");
+            continue;
+        };
+        let Some(size) = copy_size(size, trials.get(&fid), now) else {
+            continue;
+        };
+        sized.push((fid, size));
+    }
+
+    if copy_aggregate::enabled() {
+        let mut wants = Vec::with_capacity(sized.len());
+        for (fid, size) in sized {
+            // same rule as the queue path: exits skip the draw-down guard;
+            // the omnibus order is placed on another account, so a
+            // follower's exit-only window is checked here
+            if !template.reduce_only {
+                if let Err(e) = risk::check_drawdown(cache, fid).await {
+                    tracing::info!("copy for follower {fid} of leader {leader_id} skipped: {e}");
+                    continue;
+                }
+                if let Err(e) = risk::check_exit_only(pg, fid).await {
+                    tracing::info!("copy for follower {fid} of leader {leader_id} skipped: {e}");
+                    continue;
+                }
+            }
+            wants.push((fid, size));
+        }
+        let n = wants.len();
+        if n > 0 {
+            copy_aggregate::spawn_parent(leader_id, template, wants);
         }
+        return Ok(n);
+    }
 
-        // naïve 1-for-1 copy; in practice scale, slippage & balance checks apply
+    let mut queued = 0;
+    for (fid, size) in sized {
         let req = TradeRequest {
-            exchange: leader_fill.exchange.clone(),
-            symbol: leader_fill.symbol.clone(),
-            side: leader_fill.side.clone(),
-            order_type: leader_fill.order_type.clone(),
-            price: leader_fill.price,
-            size: leader_fill.size,
+            size,
+            ..template.clone()
         };
+        let job = CopyJob::new(leader_id, fid, req).for_order(leader_fill.order_id);
+        match copy_queue::enqueue(job) {
+            Ok(()) => queued += 1,
+            Err(e) => tracing::warn!("copy for follower {fid} of leader {leader_id} dropped: {e}"),
+        }
+    }
+    Ok(queued)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trials_cap_size_until_they_end() {
+        let now = Utc::now();
+        let trial = TrialTerms {
+            ends_at: now + Duration::days(1),
+            max_size: 0.5,
+        };
+        assert_eq!(copy_size(3.0, None, now), Some(3.0));
+        assert_eq!(copy_size(3.0, Some(&trial), now), Some(0.5));
+        assert_eq!(copy_size(0.2, Some(&trial), now), Some(0.2));
+        // expired but not reaped yet: nothing more is copied
+        assert_eq!(copy_size(3.0, Some(&trial), trial.ends_at), None);
+    }
+
+    #[test]
+    fn follower_size_scales_and_caps_entries() {
+        let half = CopyConfig {
+            sizing: CopySizing::Ratio { ratio: 0.5 },
+            max_notional: Some(1_000.0),
+        };
+        assert_eq!(
+            scaled_size(2.0, &CopyConfig::default(), None, None, false),
+            Some(2.0)
+        );
+        // 1.0 is 2 000 USDT at 2 000: capped to 0.5; exits are not capped
+        assert_eq!(
+            scaled_size(2.0, &half, None, Some(2_000.0), false),
+            Some(0.5)
+        );
+        assert_eq!(
+            scaled_size(2.0, &half, None, Some(2_000.0), true),
+            Some(1.0)
+        );
+        assert_eq!(scaled_size(2.0, &half, None, None, false), None);
+
+        // 50 % of a 10k follower behind a 100k leader
+        let equity = CopyConfig {
+            sizing: CopySizing::EquityPct { pct: 50.0 },
+            max_notional: None,
+        };
+        let size = scaled_size(1.0, &equity, Some((10_000.0, 100_000.0)), None, false);
+        assert!((size.unwrap() - 0.05).abs() < 1e-12);
+        assert_eq!(scaled_size(1.0, &equity, None, None, false), None);
+    }
 
-        // Now, execute for the follower!
-        if let Err(e) = execute_trade(
-            req,
-            pg,  // Pass DB connection
-            fid, // Follower's user ID
-            is_demo,
-            master_key_bytes,
+    #[tokio::test]
+    async fn an_exit_is_copied_when_the_balance_fetch_fails() {
+        let equity = CopyConfig {
+            sizing: CopySizing::EquityPct { pct: 50.0 },
+            max_notional: None,
+        };
+        let order = |reduce_only| TradeRequest {
+            exchange: crate::services::trading_engine::Exchange::Blowfin,
+            symbol: "BTC-USDT".into(),
+            side: "sell".into(),
+            order_type: "market".into(),
+            price: None,
+            size: 1.0,
+            reduce_only,
+            signal_price: None,
+            tp_sl: None,
+        };
+        let no_balance = || async { None };
+        let (exit, entry) = (order(true), order(false));
+
+        // an entry that can't be sized is skipped ...
+        let size = follower_size(&entry, &equity, Some(1e5), None, no_balance(), async {
+            Some(0.3)
+        })
+        .await;
+        assert_eq!(size, None);
+        // ... an exit closes what the follower holds (short legs are negative)
+        let size = follower_size(&exit, &equity, Some(1e5), None, no_balance(), async {
+            Some(-0.3)
+        })
+        .await;
+        assert_eq!(size, Some(0.3));
+        // or, with no position read either, goes out at the leader's size
+        let size = follower_size(&exit, &equity, Some(1e5), None, no_balance(), no_balance()).await;
+        assert_eq!(size, Some(1.0));
+        // a sizeable exit is still scaled
+        let size = follower_size(
+            &exit,
+            &equity,
+            Some(1e5),
+            None,
+            async { Some(1e4) },
+            async { unreachable!("not read when the exit can be sized") },
         )
-        .await
-        {
-            log::warn!("copy trade for follower {} failed: {}", fid, e);
-        }
+        .await;
+        assert!((size.unwrap() - 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn config_round_trips_as_tagged_json() {
+        let cfg: CopyConfig =
+            serde_json::from_value(json!({ "mode": "equity_pct", "pct": 5.0 })).unwrap();
+        assert_eq!(cfg.sizing, CopySizing::EquityPct { pct: 5.0 });
+        assert_eq!(cfg.max_notional, None);
+        assert!(cfg.validate().is_ok());
+        let bad = CopyConfig {
+            sizing: CopySizing::Ratio { ratio: 0.0 },
+            max_notional: None,
+        };
+        assert!(matches!(bad.validate(), Err(CopyError::InvalidConfig(_))));
     }
-    Ok(())
 }
@@ -9,17 +9,17 @@ use aes_gcm::{
 };
 use anyhow::Result;
 use base64::engine::general_purpose as b64;
+use base64::Engine;
 use once_cell::sync::Lazy;
-use rand_core::RngCore;                                // gives fill_bytes()
+use rand_core::RngCore; // gives fill_bytes()
 use sodiumoxide::{
     crypto::{
-        box_::{PublicKey, SecretKey},                  // <— correct types
+        box_::{PublicKey, SecretKey}, // <— correct types
         sealedbox,
     },
     init as sodium_init,
 };
 use std::env;
-use base64::Engine;
 use zeroize::Zeroizing;
 
 // ──────────────────────────────────────────────────────────────
@@ -47,8 +47,12 @@ impl EnvelopeCrypto {
         let sk_raw = b64::STANDARD.decode(env::var("MASTER_SK_B64")?)?;
 
         Ok(Self::new(
-            pk_raw.try_into().map_err(|_| anyhow::anyhow!("pk length"))?,
-            sk_raw.try_into().map_err(|_| anyhow::anyhow!("sk length"))?,
+            pk_raw
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("pk length"))?,
+            sk_raw
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("sk length"))?,
         ))
     }
 }
@@ -57,39 +61,56 @@ impl EnvelopeCrypto {
 pub static GLOBAL_CRYPTO: Lazy<EnvelopeCrypto> =
     Lazy::new(|| EnvelopeCrypto::from_env().expect("master keys in .env"));
 
+/// One field sealed under a row's data key: (nonce, ciphertext)
+pub type Sealed = (Vec<u8>, Vec<u8>);
+
 // ──────────────────────────────────────────────────────────────
 //  Envelope seal / open
 // ──────────────────────────────────────────────────────────────
 impl EnvelopeCrypto {
     /// Encrypt → (wrapped_data_key, nonce, ciphertext)
     pub fn seal(&self, plaintext: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let (wrapped_key, mut sealed) = self.seal_all(&[plaintext]);
+        let (nonce, ciphertext) = sealed.remove(0);
+        (wrapped_key, nonce, ciphertext)
+    }
+
+    /// Encrypt several fields of one row under a single data key →
+    /// (wrapped_data_key, [(nonce, ciphertext)]), so one wrapped key opens
+    /// them all
+    pub fn seal_all(&self, plaintexts: &[&[u8]]) -> (Vec<u8>, Vec<Sealed>) {
         // 1) fresh 256-bit data key
         let mut dk = [0u8; 32];
         OsRng.fill_bytes(&mut dk);
         let data_key: Zeroizing<Vec<u8>> = Zeroizing::new(dk.to_vec());
 
-        // 2) AES-GCM
-        let cipher = Aes256Gcm::new(Key::from_slice(&data_key));
-        let mut nonce = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce);
-        let ciphertext = cipher
-            .encrypt(Nonce::from_slice(&nonce), plaintext)
-            .expect("AES-GCM encrypt");
+        // 2) AES-GCM, a fresh nonce per field
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key));
+        let sealed = plaintexts
+            .iter()
+            .map(|plaintext| {
+                let mut nonce = [0u8; 12];
+                OsRng.fill_bytes(&mut nonce);
+                let ciphertext = cipher
+                    .encrypt(Nonce::from_slice(&nonce), *plaintext)
+                    .expect("AES-GCM encrypt");
+                (nonce.to_vec(), ciphertext)
+            })
+            .collect();
 
         // 3) wrap data key
         let wrapped_key = sealedbox::seal(&data_key, &self.master_pk);
 
-        (wrapped_key, nonce.to_vec(), ciphertext)
+        (wrapped_key, sealed)
     }
 
     /// Decrypt triplet back to UTF-8 string
     pub fn open(&self, wrapped: &[u8], nonce: &[u8], cipher: &[u8]) -> Result<String> {
-        let data_key =
-            sealedbox::open(wrapped, &self.master_pk, &self.master_sk)
-                .map_err(|_| anyhow::anyhow!("sealed-box unwrap failed"))?;
+        let data_key = sealedbox::open(wrapped, &self.master_pk, &self.master_sk)
+            .map_err(|_| anyhow::anyhow!("sealed-box unwrap failed"))?;
 
-        let cipher_aes = Aes256Gcm::new(Key::from_slice(&data_key));
-        let plaintext  = cipher_aes
+        let cipher_aes = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key));
+        let plaintext = cipher_aes
             .decrypt(Nonce::from_slice(nonce), cipher)
             .map_err(|_| anyhow::anyhow!("AES-GCM decrypt failed"))?;
 
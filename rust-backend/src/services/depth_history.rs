@@ -0,0 +1,302 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Order-book depth history
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every depth snapshot on the `MarketBus` is folded into a per-symbol,
+//!   per-minute [`Aggregate`] (mean depths, imbalance and depth slopes) and
+//!   written to `depth_aggregates` through the batched writer once the
+//!   minute is over
+//! * [`recent`] hands strategies the newest minutes as `OrderBookSnapshot`s
+//!   for `indicators::book_flow`; [`report`] is the analytics view
+//! * Minutes older than `KEEP_FOR` are pruned every `PRUNE_EVERY`
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::Serialize;
+use sqlx::query_builder::Separated;
+use sqlx::{FromRow, PgPool, Postgres};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::db::batch::{BatchConfig, BatchRow, BatchWriter};
+use crate::services::market_data::{bus_symbol, BusBook, MarketBus};
+use crate::services::strategies::{
+    indicators::{self, BookFlow},
+    OrderBookSnapshot,
+};
+
+const BUCKET: Duration = Duration::minutes(1);
+const KEEP_FOR: Duration = Duration::days(30);
+const PRUNE_EVERY: StdDuration = StdDuration::from_secs(3_600);
+
+/// One symbol's book over one minute
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct Aggregate {
+    pub symbol: String,
+    pub bucket: DateTime<Utc>,
+    pub samples: i32,
+    pub bid_depth: f64,
+    pub ask_depth: f64,
+    pub imbalance: Option<f64>,
+    pub bid_slope: Option<f64>,
+    pub ask_slope: Option<f64>,
+}
+
+impl Aggregate {
+    pub fn book(&self) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            bid_depth: self.bid_depth,
+            ask_depth: self.ask_depth,
+            bid_slope: self.bid_slope,
+            ask_slope: self.ask_slope,
+            ..Default::default()
+        }
+    }
+}
+
+impl BatchRow for Aggregate {
+    const TABLE: &'static str = "depth_aggregates";
+    const COLUMNS: &'static [&'static str] = &[
+        "symbol",
+        "bucket",
+        "samples",
+        "bid_depth",
+        "ask_depth",
+        "imbalance",
+        "bid_slope",
+        "ask_slope",
+    ];
+    const ON_CONFLICT: &'static str = "ON CONFLICT (symbol, bucket) DO NOTHING";
+
+    fn bind_row<'args>(&self, b: &mut Separated<'_, 'args, Postgres, &'static str>) {
+        b.push_bind(self.symbol.clone())
+            .push_bind(self.bucket)
+            .push_bind(self.samples)
+            .push_bind(self.bid_depth)
+            .push_bind(self.ask_depth)
+            .push_bind(self.imbalance)
+            .push_bind(self.bid_slope)
+            .push_bind(self.ask_slope);
+    }
+}
+
+/// Running sums for the minute being filled
+#[derive(Debug, Default)]
+struct Acc {
+    samples: usize,
+    bid: f64,
+    ask: f64,
+    imb: (f64, usize),
+    bid_slope: (f64, usize),
+    ask_slope: (f64, usize),
+}
+
+impl Acc {
+    fn add(&mut self, ob: &OrderBookSnapshot) {
+        let add = |(sum, n): &mut (f64, usize), x: Option<f64>| {
+            if let Some(x) = x {
+                *sum += x;
+                *n += 1;
+            }
+        };
+        self.samples += 1;
+        self.bid += ob.bid_depth;
+        self.ask += ob.ask_depth;
+        add(&mut self.imb, indicators::imbalance(ob));
+        add(&mut self.bid_slope, ob.bid_slope);
+        add(&mut self.ask_slope, ob.ask_slope);
+    }
+
+    fn finish(&self, symbol: &str, bucket: DateTime<Utc>) -> Aggregate {
+        let mean = |(sum, n): (f64, usize)| (n > 0).then(|| sum / n as f64);
+        let n = self.samples.max(1) as f64;
+        Aggregate {
+            symbol: symbol.to_string(),
+            bucket,
+            samples: self.samples as i32,
+            bid_depth: self.bid / n,
+            ask_depth: self.ask / n,
+            imbalance: mean(self.imb),
+            bid_slope: mean(self.bid_slope),
+            ask_slope: mean(self.ask_slope),
+        }
+    }
+}
+
+/// Folds snapshots into minutes; a snapshot in a later minute closes the
+/// symbol's open one
+#[derive(Debug, Default)]
+pub struct Aggregator {
+    open: HashMap<String, (DateTime<Utc>, Acc)>,
+}
+
+impl Aggregator {
+    /// Add `ob` seen at `at`; returns the minute it closed, if any
+    pub fn push(
+        &mut self,
+        symbol: &str,
+        ob: &OrderBookSnapshot,
+        at: DateTime<Utc>,
+    ) -> Option<Aggregate> {
+        let bucket = at.duration_trunc(BUCKET).unwrap_or(at);
+        let (open_bucket, acc) = self
+            .open
+            .entry(symbol.to_string())
+            .or_insert_with(|| (bucket, Acc::default()));
+        let mut closed = None;
+        if bucket > *open_bucket {
+            closed = Some(acc.finish(symbol, *open_bucket));
+            *open_bucket = bucket;
+            *acc = Acc::default();
+        }
+        acc.add(ob);
+        closed
+    }
+}
+
+/// Record the bus's depth snapshots and prune old minutes
+pub fn spawn(pool: PgPool, bus: Arc<MarketBus>) {
+    let writer = BatchWriter::<Aggregate>::spawn(pool.clone(), BatchConfig::default());
+    let mut rx = bus.all_books();
+    tokio::spawn(async move {
+        let mut agg = Aggregator::default();
+        loop {
+            match rx.recv().await {
+                Ok(BusBook { symbol, book }) => {
+                    if let Some(row) = agg.push(&symbol, &book, Utc::now()) {
+                        writer.try_push(row);
+                    }
+                }
+                // a lagged minute is only thinner, not wrong
+                Err(RecvError::Lagged(n)) => tracing::warn!("depth history: lagged {n}"),
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(PRUNE_EVERY);
+        loop {
+            tick.tick().await;
+            if let Err(e) = sqlx::query("DELETE FROM depth_aggregates WHERE bucket < $1")
+                .bind(Utc::now() - KEEP_FOR)
+                .execute(&pool)
+                .await
+            {
+                tracing::warn!("depth history: prune: {e}");
+            }
+        }
+    });
+}
+
+async fn fetch(
+    db: &PgPool,
+    symbol: &str,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Aggregate>, sqlx::Error> {
+    let mut rows = sqlx::query_as::<_, Aggregate>(
+        r#"
+        SELECT symbol, bucket, samples, bid_depth, ask_depth,
+               imbalance, bid_slope, ask_slope
+          FROM depth_aggregates
+         WHERE symbol = $1 AND bucket >= $2 AND bucket < $3
+         ORDER BY bucket DESC
+         LIMIT $4
+        "#,
+    )
+    .bind(bus_symbol(symbol))
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(db)
+    .await?;
+    rows.reverse();
+    Ok(rows)
+}
+
+/// The newest `n` minutes of `symbol` (any spelling), oldest first
+pub async fn recent(
+    db: &PgPool,
+    symbol: &str,
+    n: usize,
+) -> Result<Vec<OrderBookSnapshot>, sqlx::Error> {
+    let rows = fetch(db, symbol, Utc::now() - KEEP_FOR, Utc::now(), n as i64).await?;
+    Ok(rows.iter().map(Aggregate::book).collect())
+}
+
+/// [`BookFlow`] over the newest `n` minutes; `None` while fewer are recorded
+pub async fn flow(db: &PgPool, symbol: &str, n: usize) -> Result<Option<BookFlow>, sqlx::Error> {
+    Ok(indicators::book_flow(&recent(db, symbol, n).await?, n))
+}
+
+/// One minute of the analytics view
+#[derive(Debug, Serialize)]
+pub struct FlowPoint {
+    #[serde(flatten)]
+    pub minute: Aggregate,
+    /// Rolling over the `window` minutes up to this one
+    pub rolling_imbalance: Option<f64>,
+    pub rolling_slope_ratio: Option<f64>,
+}
+
+/// Minutes of `symbol` in `[since, until)` with `window`-minute rolling flow
+pub async fn report(
+    db: &PgPool,
+    symbol: &str,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    window: usize,
+) -> Result<Vec<FlowPoint>, sqlx::Error> {
+    // the first point's window reaches back before `since`
+    let from = since - BUCKET * window as i32;
+    let rows = fetch(db, symbol, from, until, i64::MAX).await?;
+    let books: Vec<OrderBookSnapshot> = rows.iter().map(Aggregate::book).collect();
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .filter(|(_, r)| r.bucket >= since)
+        .map(|(i, minute)| {
+            let flow = indicators::book_flow(&books[..=i], window);
+            FlowPoint {
+                minute,
+                rolling_imbalance: flow.map(|f| f.imbalance),
+                rolling_slope_ratio: flow.and_then(|f| f.slope_ratio()),
+            }
+        })
+        .collect())
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn snapshots_fold_into_minutes() {
+        let t = |m: u32, s: u32| Utc.with_ymd_and_hms(2025, 8, 1, 12, m, s).unwrap();
+        let ob = |bid_depth, ask_depth| OrderBookSnapshot {
+            bid_depth,
+            ask_depth,
+            bid_slope: Some(bid_depth),
+            ..Default::default()
+        };
+        let mut agg = Aggregator::default();
+        assert_eq!(agg.push("BTCUSDT", &ob(3.0, 1.0), t(0, 5)), None);
+        assert_eq!(agg.push("BTCUSDT", &ob(1.0, 1.0), t(0, 40)), None);
+        // another symbol keeps its own minute
+        assert_eq!(agg.push("ETHUSDT", &ob(1.0, 3.0), t(1, 0)), None);
+
+        let m = agg.push("BTCUSDT", &ob(5.0, 5.0), t(1, 2)).unwrap();
+        assert_eq!((m.bucket, m.samples), (t(0, 0), 2));
+        assert_eq!((m.bid_depth, m.ask_depth), (2.0, 1.0));
+        assert_eq!(m.imbalance, Some(0.25));
+        assert_eq!((m.bid_slope, m.ask_slope), (Some(2.0), None));
+    }
+}
@@ -0,0 +1,254 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Drain mode for rolling deploys
+//! ──────────────────────────────────────────────────────────────────────────
+//! Triggered by SIGTERM / Ctrl-C or `POST /health/drain`:
+//!
+//! 1. flip the process into *draining* – `/health/ready` answers 503 so the
+//!    load balancer stops routing here, new trades & strategy starts are
+//!    refused, the scheduler stops spawning tasks;
+//! 2. signal [`stopping`]: strategy loops finish the bar they are on, save
+//!    their position state and return, market-data feeds close their
+//!    sockets, batched writers flush what they hold;
+//! 3. wait until every tracked operation (order submission, copy
+//!    replication, strategy task) has finished, bounded by
+//!    `DRAIN_TIMEOUT_SECS`;
+//! 4. stop the HTTP server gracefully.
+//!
+//! Work is tracked with an RAII [`InFlight`] guard from [`track`]; loops
+//! wait on their next input through [`or_stop`] so they only ever stop
+//! between two iterations, never mid-trade.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use actix_web::dev::ServerHandle;
+use once_cell::sync::{Lazy, OnceCell};
+use tokio::sync::{watch, Notify};
+
+pub struct DrainState {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+    /// Flips to `true` once, when the drain begins
+    stop: watch::Sender<bool>,
+}
+
+impl Default for DrainState {
+    fn default() -> Self {
+        Self {
+            draining: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+            stop: watch::channel(false).0,
+        }
+    }
+}
+
+/// Decrements the in-flight count when dropped
+pub struct InFlight<'a> {
+    state: &'a DrainState,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.state.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.state.idle.notify_waiters();
+        }
+    }
+}
+
+impl DrainState {
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// `true` only for the caller that actually started the drain
+    pub fn begin(&self) -> bool {
+        let started = !self.draining.swap(true, Ordering::SeqCst);
+        if started {
+            self.stop.send_replace(true);
+        }
+        started
+    }
+
+    /// Resolves once the drain has begun (at once if it already has)
+    pub async fn stopping(&self) {
+        let mut rx = self.stop.subscribe();
+        // the sender lives as long as the state, so this can't fail
+        let _ = rx.wait_for(|stop| *stop).await;
+    }
+
+    /// `fut`'s output, or `None` when the drain begins first
+    pub async fn or_stop<F: Future>(&self, fut: F) -> Option<F::Output> {
+        tokio::select! {
+            biased;
+            _ = self.stopping() => None,
+            out = fut => Some(out),
+        }
+    }
+
+    pub fn track(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight { state: self }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Wait for the in-flight count to hit zero; `false` on timeout
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        let settle = async {
+            loop {
+                // register before re-checking so a wake-up can't slip between
+                let notified = self.idle.notified();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(timeout, settle).await.is_ok()
+    }
+}
+
+static STATE: Lazy<DrainState> = Lazy::new(DrainState::default);
+static SERVER: OnceCell<ServerHandle> = OnceCell::new();
+
+pub fn is_draining() -> bool {
+    STATE.is_draining()
+}
+
+/// Count an operation that must finish before shutdown
+pub fn track() -> InFlight<'static> {
+    STATE.track()
+}
+
+pub fn in_flight() -> usize {
+    STATE.in_flight()
+}
+
+/// Resolves once the process starts draining
+pub async fn stopping() {
+    STATE.stopping().await
+}
+
+/// Await `fut` unless the process starts draining first – `None` then.
+/// For the input a loop waits on (`rx.recv()`), so the loop winds down
+/// between iterations.
+pub async fn or_stop<F: Future>(fut: F) -> Option<F::Output> {
+    STATE.or_stop(fut).await
+}
+
+/// Register the running server so a drain can stop it
+pub fn install_server(handle: ServerHandle) {
+    let _ = SERVER.set(handle);
+}
+
+/// Enter drain mode, wait for in-flight work, then stop the server.
+/// Repeated calls while a drain is running are no-ops.
+pub async fn drain_and_stop(timeout: Duration) {
+    if !STATE.begin() {
+        return;
+    }
+    tracing::warn!("drain: started, {} operation(s) in flight", in_flight());
+
+    if STATE.wait_idle(timeout).await {
+        tracing::info!("drain: all operations settled");
+    } else {
+        tracing::error!(
+            "drain: timed out after {timeout:?} with {} operation(s) in flight",
+            in_flight()
+        );
+    }
+
+    if let Some(server) = SERVER.get() {
+        server.stop(true).await;
+    }
+}
+
+/// Resolves on SIGTERM (orchestrator) or Ctrl-C (local)
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = signal(SignalKind::terminate()).expect("SIGTERM handler");
+        tokio::select! {
+            _ = term.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn begin_is_one_shot() {
+        let s = DrainState::default();
+        assert!(!s.is_draining());
+        assert!(s.begin());
+        assert!(!s.begin());
+        assert!(s.is_draining());
+    }
+
+    #[test]
+    fn guards_count_in_flight() {
+        let s = DrainState::default();
+        let a = s.track();
+        let b = s.track();
+        assert_eq!(s.in_flight(), 2);
+        drop(a);
+        assert_eq!(s.in_flight(), 1);
+        drop(b);
+        assert_eq!(s.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn wait_idle_returns_when_last_guard_drops() {
+        let s: &'static DrainState = Box::leak(Box::default());
+        let g = s.track();
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(g);
+        });
+        assert!(s.wait_idle(Duration::from_secs(2)).await);
+        release.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn wait_idle_times_out() {
+        let s = DrainState::default();
+        let _g = s.track();
+        assert!(!s.wait_idle(Duration::from_millis(20)).await);
+    }
+
+    #[tokio::test]
+    async fn or_stop_gives_way_to_the_drain() {
+        let s = DrainState::default();
+        assert_eq!(s.or_stop(async { 1 }).await, Some(1));
+
+        let pending = s.or_stop(std::future::pending::<()>());
+        let (out, started) = tokio::join!(pending, async { s.begin() });
+        assert!(started);
+        assert_eq!(out, None);
+        // already draining: resolves straight away
+        assert_eq!(s.or_stop(async { 1 }).await, None);
+    }
+
+    #[tokio::test]
+    async fn idle_state_settles_immediately() {
+        let s = DrainState::default();
+        assert!(s.wait_idle(Duration::from_millis(1)).await);
+    }
+}
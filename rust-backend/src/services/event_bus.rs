@@ -0,0 +1,340 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Durable internal event bus on Redis Streams
+//! ──────────────────────────────────────────────────────────────────────────
+//! `MarketBus` (tokio::broadcast) is perfect for hot, lossy market data but
+//! drops events for late subscribers and never leaves the process. Business
+//! events – fills, signals, copy jobs – go through here instead:
+//!
+//! * `publish`        – `XADD events:<topic> MAXLEN ~ N * data <json>`
+//! * consumer groups  – `XREADGROUP` + `XACK`; unacked entries are retried
+//! * `replay`         – `XRANGE` from any stream id (audits, backfills)
+//!
+//! ```ignore
+//! bus.publish(Topic::Fills, &fill).await?;
+//! spawn_consumer(bus, Topic::Fills, "copy", "worker-1".into(), |ev: Event<TradeResponse>| async move {
+//!     replicate(ev.payload).await.map_err(|e| e.to_string())
+//! });
+//! ```
+//! ──────────────────────────────────────────────────────────────────────────
+
+use redis::streams::{
+    StreamId, StreamMaxlen, StreamRangeReply, StreamReadOptions, StreamReadReply,
+};
+use redis::{AsyncCommands, RedisError};
+use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::db::redis::RedisPool;
+
+/// Approximate per-stream retention (XADD MAXLEN ~)
+const DEFAULT_MAXLEN: usize = 100_000;
+const READ_BLOCK_MS: usize = 5_000;
+const READ_COUNT: usize = 64;
+const FIELD: &str = "data";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+    Fills,
+    Signals,
+    CopyJobs,
+    /// User-facing messages (see `services::notify`)
+    Notifications,
+}
+
+impl Topic {
+    pub fn stream_key(self) -> &'static str {
+        match self {
+            Topic::Fills => "events:fills",
+            Topic::Signals => "events:signals",
+            Topic::CopyJobs => "events:copy_jobs",
+            Topic::Notifications => "events:notifications",
+        }
+    }
+}
+
+/// Where a new consumer group starts reading
+#[derive(Debug, Clone)]
+pub enum StartFrom {
+    /// Only events published after the group is created
+    Latest,
+    /// Everything still retained in the stream
+    Beginning,
+    /// Right after this stream id
+    Id(String),
+}
+
+impl StartFrom {
+    fn as_id(&self) -> &str {
+        match self {
+            StartFrom::Latest => "$",
+            StartFrom::Beginning => "0",
+            StartFrom::Id(id) => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Event<T> {
+    /// Stream id (`<ms>-<seq>`), usable as a replay offset
+    pub id: String,
+    pub payload: T,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BusError {
+    #[error("redis: {0}")]
+    Redis(#[from] RedisError),
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    redis: RedisPool,
+    maxlen: usize,
+}
+
+impl EventBus {
+    pub fn new(redis: RedisPool) -> Self {
+        Self {
+            redis,
+            maxlen: DEFAULT_MAXLEN,
+        }
+    }
+
+    pub fn with_maxlen(mut self, maxlen: usize) -> Self {
+        self.maxlen = maxlen;
+        self
+    }
+
+    fn conn(&self) -> redis::aio::ConnectionManager {
+        self.redis.manager().as_ref().clone()
+    }
+
+    /// Append an event; returns its stream id.
+    pub async fn publish<T: Serialize + ?Sized>(
+        &self,
+        topic: Topic,
+        payload: &T,
+    ) -> Result<String, BusError> {
+        let json = serde_json::to_string(payload)?;
+        let id: String = self
+            .conn()
+            .xadd_maxlen(
+                topic.stream_key(),
+                StreamMaxlen::Approx(self.maxlen),
+                "*",
+                &[(FIELD, json)],
+            )
+            .await?;
+        Ok(id)
+    }
+
+    /// Create the consumer group (and stream) if missing.
+    pub async fn ensure_group(
+        &self,
+        topic: Topic,
+        group: &str,
+        start: StartFrom,
+    ) -> Result<(), BusError> {
+        let res: Result<(), RedisError> = self
+            .conn()
+            .xgroup_create_mkstream(topic.stream_key(), group, start.as_id())
+            .await;
+        match res {
+            Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+            other => Ok(other?),
+        }
+    }
+
+    /// Read for `consumer`. `pending = true` re-delivers this consumer's
+    /// unacked entries (non-blocking); otherwise blocks for new ones.
+    pub async fn read_group<T: DeserializeOwned>(
+        &self,
+        topic: Topic,
+        group: &str,
+        consumer: &str,
+        pending: bool,
+    ) -> Result<Vec<Event<T>>, BusError> {
+        let mut opts = StreamReadOptions::default()
+            .group(group, consumer)
+            .count(READ_COUNT);
+        if !pending {
+            opts = opts.block(READ_BLOCK_MS);
+        }
+        let id = if pending { "0" } else { ">" };
+        let reply: Option<StreamReadReply> = self
+            .conn()
+            .xread_options(&[topic.stream_key()], &[id], &opts)
+            .await?;
+        Ok(reply
+            .into_iter()
+            .flat_map(|r| r.keys)
+            .flat_map(|k| k.ids)
+            .filter_map(decode)
+            .collect())
+    }
+
+    pub async fn ack(&self, topic: Topic, group: &str, ids: &[String]) -> Result<(), BusError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.conn()
+            .xack::<_, _, _, ()>(topic.stream_key(), group, ids)
+            .await?;
+        Ok(())
+    }
+
+    /// Up to `count` events strictly after `after` (`"0"` = from the start).
+    pub async fn replay<T: DeserializeOwned>(
+        &self,
+        topic: Topic,
+        after: &str,
+        count: usize,
+    ) -> Result<Vec<Event<T>>, BusError> {
+        let reply: StreamRangeReply = self
+            .conn()
+            .xrange_count(topic.stream_key(), exclusive(after), "+", count)
+            .await?;
+        Ok(reply.ids.into_iter().filter_map(decode).collect())
+    }
+}
+
+/// XRANGE start that skips `id` itself (`"0"` stays inclusive)
+fn exclusive(id: &str) -> String {
+    if id == "0" || id == "-" {
+        "-".into()
+    } else {
+        format!("({id}")
+    }
+}
+
+fn decode<T: DeserializeOwned>(sid: StreamId) -> Option<Event<T>> {
+    let raw: String = sid.get(FIELD)?;
+    match serde_json::from_str(&raw) {
+        Ok(payload) => Some(Event {
+            id: sid.id,
+            payload,
+        }),
+        Err(e) => {
+            tracing::warn!("event {}: undecodable payload: {e}", sid.id);
+            None
+        }
+    }
+}
+
+/// Long-running consumer: drains its own pending entries first, then
+/// blocks for new ones. Events are acked only when `handler` succeeds;
+/// failures stay pending and are retried after a short pause.
+pub fn spawn_consumer<T, F, Fut>(
+    bus: EventBus,
+    topic: Topic,
+    group: &'static str,
+    consumer: String,
+    handler: F,
+) where
+    T: DeserializeOwned + Send + 'static,
+    F: Fn(Event<T>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), String>> + Send,
+{
+    tokio::spawn(async move {
+        while let Err(e) = bus.ensure_group(topic, group, StartFrom::Latest).await {
+            tracing::error!(
+                "event bus {}: create group {group}: {e}",
+                topic.stream_key()
+            );
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+
+        let mut backlog = true;
+        loop {
+            let batch = match bus.read_group::<T>(topic, group, &consumer, backlog).await {
+                Ok(b) => b,
+                Err(e) => {
+                    tracing::warn!("event bus {}: read: {e}", topic.stream_key());
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            if backlog && batch.is_empty() {
+                backlog = false;
+                continue;
+            }
+
+            let mut done = Vec::with_capacity(batch.len());
+            let mut failed = false;
+            for ev in batch {
+                let id = ev.id.clone();
+                match handler(ev).await {
+                    Ok(()) => done.push(id),
+                    Err(e) => {
+                        tracing::warn!("event {id} on {}: handler failed: {e}", topic.stream_key());
+                        failed = true;
+                    }
+                }
+            }
+            if let Err(e) = bus.ack(topic, group, &done).await {
+                tracing::warn!("event bus {}: ack: {e}", topic.stream_key());
+            }
+            if failed {
+                backlog = true;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn sid(id: &str, data: &str) -> StreamId {
+        let mut map = HashMap::new();
+        map.insert(
+            FIELD.to_string(),
+            redis::Value::Data(data.as_bytes().to_vec()),
+        );
+        StreamId { id: id.into(), map }
+    }
+
+    #[test]
+    fn topics_have_distinct_streams() {
+        let keys = [
+            Topic::Fills,
+            Topic::Signals,
+            Topic::CopyJobs,
+            Topic::Notifications,
+        ]
+        .map(Topic::stream_key);
+        assert_eq!(
+            keys,
+            [
+                "events:fills",
+                "events:signals",
+                "events:copy_jobs",
+                "events:notifications"
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_offsets_are_exclusive() {
+        assert_eq!(exclusive("0"), "-");
+        assert_eq!(exclusive("1700000000000-3"), "(1700000000000-3");
+        assert_eq!(StartFrom::Latest.as_id(), "$");
+        assert_eq!(StartFrom::Id("5-0".into()).as_id(), "5-0");
+    }
+
+    #[test]
+    fn decode_roundtrip_and_garbage() {
+        let ev: Event<serde_json::Value> = decode(sid("1-0", r#"{"a":1}"#)).unwrap();
+        assert_eq!(ev.id, "1-0");
+        assert_eq!(ev.payload["a"], 1);
+        assert!(decode::<serde_json::Value>(sid("2-0", "not json")).is_none());
+    }
+}
@@ -0,0 +1,371 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Exchange payload log (debug mode)
+//! ──────────────────────────────────────────────────────────────────────────
+//! * With `EXCHANGE_LOG_CAPACITY > 0` every outbound exchange request and
+//!   its response lands in a bounded in-memory ring buffer, oldest first out
+//! * Keys, signatures, passphrases and the like are masked *before* an entry
+//!   is stored – headers, query parameters and JSON fields alike, matched by
+//!   name – so nothing secret ever sits in the buffer
+//! * Read back via `/api/admin/exchange-log` to see exactly what the exchange said
+//!   when it rejected an order; off (and free) by default
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Substrings (lower-case) that mark a header, parameter or field as secret
+const SENSITIVE: &[&str] = &[
+    "key",
+    "secret",
+    "sign",
+    "passphrase",
+    "password",
+    "token",
+    "authorization",
+];
+const MASK: &str = "***";
+/// Non-JSON response bodies are cut to this many characters
+const MAX_TEXT: usize = 2_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+    pub method: String,
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    pub request: Option<Value>,
+    /// HTTP status; `None` when the request never got an answer
+    pub status: Option<u16>,
+    pub response: Option<Value>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+impl Entry {
+    /// Transport error, non-200, or a BlowFin `code` other than `"0"` on
+    /// the envelope or any per-order result
+    pub fn failed(&self) -> bool {
+        fn rejected(v: &Value) -> bool {
+            matches!(v.get("code").and_then(Value::as_str), Some(c) if c != "0")
+        }
+        if self.error.is_some() || self.status != Some(200) {
+            return true;
+        }
+        let Some(resp) = &self.response else {
+            return false;
+        };
+        rejected(resp)
+            || resp
+                .get("data")
+                .and_then(Value::as_array)
+                .is_some_and(|rows| rows.iter().any(rejected))
+    }
+}
+
+/// Fixed-size buffer of the latest entries
+pub struct Ring {
+    capacity: usize,
+    entries: Mutex<VecDeque<Entry>>,
+}
+
+impl Ring {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, entry: Entry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Newest first
+    pub fn recent(&self, limit: usize, failed_only: bool) -> Vec<Entry> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .rev()
+            .filter(|e| !failed_only || e.failed())
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+static RING: OnceCell<Ring> = OnceCell::new();
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Turn the log on (called once from `main`); `capacity == 0` leaves it off
+pub fn init(capacity: usize) {
+    if capacity == 0 {
+        return;
+    }
+    if RING.set(Ring::new(capacity)).is_ok() {
+        tracing::warn!("exchange payload log on – keeping the last {capacity} calls");
+    }
+}
+
+pub fn enabled() -> bool {
+    RING.get().is_some()
+}
+
+pub fn recent(limit: usize, failed_only: bool) -> Vec<Entry> {
+    RING.get()
+        .map(|r| r.recent(limit, failed_only))
+        .unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Some(r) = RING.get() {
+        r.clear();
+    }
+}
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE.iter().any(|s| name.contains(s))
+}
+
+/// Mask every sensitive field, at any depth
+pub fn redact(v: &mut Value) {
+    match v {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if is_sensitive(k) && !v.is_null() {
+                    *v = Value::String(MASK.into());
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Mask sensitive query parameters
+pub fn redact_url(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((k, _)) if is_sensitive(k) => format!("{k}={MASK}"),
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{base}?{}", query.join("&"))
+}
+
+fn response_body(text: &str) -> Value {
+    match serde_json::from_str::<Value>(text) {
+        Ok(mut v) => {
+            redact(&mut v);
+            v
+        }
+        Err(_) => Value::String(text.chars().take(MAX_TEXT).collect()),
+    }
+}
+
+/// An in-flight call; a no-op when the log is off
+pub struct Trace(Option<Entry>, Instant);
+
+/// Start recording a call – headers and body are redacted right here
+pub fn trace<B: Serialize + ?Sized>(
+    method: &str,
+    url: &str,
+    headers: &[(&str, String)],
+    body: Option<&B>,
+) -> Trace {
+    if !enabled() {
+        return Trace(None, Instant::now());
+    }
+    let headers = headers
+        .iter()
+        .map(|(k, v)| {
+            let v = if is_sensitive(k) {
+                MASK.into()
+            } else {
+                v.clone()
+            };
+            (k.to_string(), v)
+        })
+        .collect();
+    let request = body
+        .and_then(|b| serde_json::to_value(b).ok())
+        .map(|mut v| {
+            redact(&mut v);
+            v
+        });
+    let entry = Entry {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        at: Utc::now(),
+        method: method.to_string(),
+        url: redact_url(url),
+        headers,
+        request,
+        status: None,
+        response: None,
+        error: None,
+        elapsed_ms: 0,
+    };
+    Trace(Some(entry), Instant::now())
+}
+
+impl Trace {
+    /// The exchange answered (whatever the status)
+    pub fn response(self, status: u16, body: &str) {
+        self.finish(|e| {
+            e.status = Some(status);
+            e.response = Some(response_body(body));
+        });
+    }
+
+    /// The call failed before a full answer came back
+    pub fn error(self, err: &dyn std::fmt::Display) {
+        self.finish(|e| e.error = Some(err.to_string()));
+    }
+
+    fn finish(self, fill: impl FnOnce(&mut Entry)) {
+        let (Some(mut entry), Some(ring)) = (self.0, RING.get()) else {
+            return;
+        };
+        fill(&mut entry);
+        entry.elapsed_ms = self.1.elapsed().as_millis() as u64;
+        ring.push(entry);
+    }
+}
+
+/// Send `req` and record the exchange; the body comes back as text so the
+/// caller decodes exactly what was logged
+pub async fn send(
+    req: reqwest::RequestBuilder,
+    trace: Trace,
+) -> Result<(reqwest::StatusCode, String), reqwest::Error> {
+    let resp = match req.send().await {
+        Ok(resp) => resp,
+        Err(e) => {
+            trace.error(&e);
+            return Err(e);
+        }
+    };
+    let status = resp.status();
+    match resp.text().await {
+        Ok(text) => {
+            trace.response(status.as_u16(), &text);
+            Ok((status, text))
+        }
+        Err(e) => {
+            trace.error(&e);
+            Err(e)
+        }
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(id: u64, status: u16, response: Value) -> Entry {
+        Entry {
+            id,
+            at: Utc::now(),
+            method: "POST".into(),
+            url: "/api/v1/trade/order".into(),
+            headers: BTreeMap::new(),
+            request: None,
+            status: Some(status),
+            response: Some(response),
+            error: None,
+            elapsed_ms: 0,
+        }
+    }
+
+    #[test]
+    fn secrets_are_masked_at_any_depth() {
+        let mut v = json!({
+            "instId": "BTC-USDT",
+            "clientOrderId": "rr-1",
+            "apiKey": "AK",
+            "auth": { "passphrase": "pp", "sig": "ok", "signature": "abc" },
+            "legs": [{ "api_secret": "s", "size": "1" }],
+            "token": null
+        });
+        redact(&mut v);
+        assert_eq!(
+            v,
+            json!({
+                "instId": "BTC-USDT",
+                "clientOrderId": "rr-1",
+                "apiKey": "***",
+                "auth": { "passphrase": "***", "sig": "ok", "signature": "***" },
+                "legs": [{ "api_secret": "***", "size": "1" }],
+                "token": null
+            })
+        );
+    }
+
+    #[test]
+    fn query_secrets_are_masked() {
+        assert_eq!(
+            redact_url("https://x/api?instId=BTC-USDT&apiKey=AK&sign=S"),
+            "https://x/api?instId=BTC-USDT&apiKey=***&sign=***"
+        );
+        assert_eq!(redact_url("https://x/api"), "https://x/api");
+    }
+
+    #[test]
+    fn ring_keeps_the_newest() {
+        let ring = Ring::new(2);
+        for id in 1..=3 {
+            ring.push(entry(id, 200, json!({ "code": "0" })));
+        }
+        let ids: Vec<u64> = ring.recent(10, false).iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![3, 2]);
+        assert_eq!(ring.recent(1, false).len(), 1);
+        ring.clear();
+        assert!(ring.recent(10, false).is_empty());
+    }
+
+    #[test]
+    fn rejections_count_as_failed() {
+        assert!(!entry(1, 200, json!({ "code": "0", "data": [{ "code": "0" }] })).failed());
+        assert!(entry(2, 200, json!({ "code": "152401", "msg": "x" })).failed());
+        assert!(entry(
+            3,
+            200,
+            json!({ "code": "0", "data": [{ "code": "102015" }] })
+        )
+        .failed());
+        assert!(entry(4, 502, Value::String("bad gateway".into())).failed());
+
+        let ring = Ring::new(8);
+        ring.push(entry(1, 200, json!({ "code": "0" })));
+        ring.push(entry(2, 200, json!({ "code": "1" })));
+        let failed: Vec<u64> = ring.recent(10, true).iter().map(|e| e.id).collect();
+        assert_eq!(failed, vec![2]);
+    }
+}
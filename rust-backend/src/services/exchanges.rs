@@ -0,0 +1,218 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Exchange adapters & registry
+//! ──────────────────────────────────────────────────────────────────────────
+//! * [`ExchangeAdapter`] is everything the engine needs from one venue for
+//!   one user: orders, cancels and stops (the `ApiClient` seam it extends),
+//!   plus balance and positions
+//! * Each venue registers an [`AdapterSpec`] – how to build its client from
+//!   the user's stored key, and how to start its market-data feeds
+//! * `execute_trade` picks the adapter by `TradeRequest::exchange`; a venue
+//!   without a registration is refused before anything is signed
+//!
+//! Adding a venue (Bybit, …): a `trading_engine::Exchange` variant, a client
+//! implementing [`ExchangeAdapter`] that translates the engine's
+//! `OrderRequest`, and a [`register`] call at start-up. BlowFin and Binance
+//! USDⓈ-M are registered out of the box.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+use sqlx::PgPool;
+
+use crate::{
+    config::settings::Settings,
+    db::api_keys::{ApiKey, CredsError, DecryptedApiKey},
+    services::{
+        binance::client::BinanceClient,
+        blowfin::{
+            self,
+            client::BlowfinClient,
+            dto::{Balance, Position},
+        },
+        crypto::GLOBAL_CRYPTO,
+        market_data::{self, MarketBus},
+        trading_engine::{ApiClient, Exchange},
+    },
+    utils::errors::TradeError,
+};
+
+#[async_trait::async_trait]
+pub trait ExchangeAdapter: ApiClient {
+    fn exchange(&self) -> Exchange;
+
+    async fn get_balance(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<Balance, TradeError> {
+        Err(TradeError::Other("balance not supported".into()))
+    }
+
+    async fn get_positions(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<Vec<Position>, TradeError> {
+        Err(TradeError::Other("positions not supported".into()))
+    }
+}
+
+/// How to reach one venue
+#[derive(Clone, Copy)]
+pub struct AdapterSpec {
+    pub exchange: Exchange,
+    /// Trading client for a user, from their decrypted key
+    pub connect: fn(DecryptedApiKey) -> Box<dyn ExchangeAdapter>,
+    /// Start the venue's market-data streams onto the bus (once, at boot);
+    /// the pool tells them which symbols strategies trade
+    pub spawn_feeds: fn(&Settings, PgPool, Arc<MarketBus>),
+}
+
+fn blowfin_spec() -> AdapterSpec {
+    AdapterSpec {
+        exchange: Exchange::Blowfin,
+        connect: |creds| {
+            let client = BlowfinClient::new(creds);
+            #[cfg(feature = "chaos")]
+            let client = crate::services::chaos::Chaos(client);
+            Box::new(client)
+        },
+        spawn_feeds: market_data::spawn_blowfin_feeds,
+    }
+}
+
+fn binance_spec() -> AdapterSpec {
+    AdapterSpec {
+        exchange: Exchange::Binance,
+        connect: |creds| {
+            let client = BinanceClient::new(creds);
+            #[cfg(feature = "chaos")]
+            let client = crate::services::chaos::Chaos(client);
+            Box::new(client)
+        },
+        spawn_feeds: market_data::spawn_binance_feeds,
+    }
+}
+
+static REGISTRY: Lazy<RwLock<HashMap<Exchange, AdapterSpec>>> = Lazy::new(|| {
+    let specs = [blowfin_spec(), binance_spec()];
+    RwLock::new(specs.into_iter().map(|s| (s.exchange, s)).collect())
+});
+
+/// Add (or replace) a venue; call before the server starts
+pub fn register(spec: AdapterSpec) {
+    REGISTRY
+        .write()
+        .expect("exchange registry poisoned")
+        .insert(spec.exchange, spec);
+}
+
+pub fn spec(exchange: Exchange) -> Option<AdapterSpec> {
+    REGISTRY
+        .read()
+        .expect("exchange registry poisoned")
+        .get(&exchange)
+        .copied()
+}
+
+/// Every registered venue, in name order
+pub fn registered() -> Vec<AdapterSpec> {
+    let mut all: Vec<AdapterSpec> = REGISTRY
+        .read()
+        .expect("exchange registry poisoned")
+        .values()
+        .copied()
+        .collect();
+    all.sort_by_key(|s| s.exchange.as_str());
+    all
+}
+
+/// The user's client for `exchange`, built from their stored key
+pub async fn connect(
+    db: &PgPool,
+    user_id: i64,
+    exchange: Exchange,
+) -> Result<Box<dyn ExchangeAdapter>, TradeError> {
+    let spec = spec(exchange).ok_or_else(|| {
+        TradeError::Other(format!("no adapter registered for {}", exchange.as_str()))
+    })?;
+    let creds = ApiKey::decrypted_cached(db, &GLOBAL_CRYPTO, user_id, exchange.as_str())
+        .await
+        .map_err(|e| match e {
+            CredsError::Db(e) => TradeError::Db(e),
+            e => TradeError::Other(e.to_string()),
+        })?
+        .ok_or(TradeError::MissingKey)?;
+    Ok((spec.connect)(creds))
+}
+
+/// ─── BlowFin ─────────────────────────────────────────────────────────────
+/// Account reads go through the `blowfin::api` wrappers (OpenAPI host,
+/// demo-aware) like they always have.
+#[async_trait::async_trait]
+impl ExchangeAdapter for BlowfinClient {
+    fn exchange(&self) -> Exchange {
+        Exchange::Blowfin
+    }
+
+    async fn get_balance(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<Balance, TradeError> {
+        blowfin::api::get_balance(db, user_id, is_demo, master_key)
+            .await
+            .and_then(|r| r.into_data())
+            .map_err(TradeError::Api)
+    }
+
+    async fn get_positions(
+        &self,
+        db: &PgPool,
+        user_id: i64,
+        is_demo: bool,
+        master_key: &[u8],
+    ) -> Result<Vec<Position>, TradeError> {
+        blowfin::api::get_positions(db, user_id, is_demo, master_key)
+            .await
+            .and_then(|r| r.into_data())
+            .map_err(TradeError::Api)
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_venues_are_registered_out_of_the_box() {
+        for exchange in [Exchange::Blowfin, Exchange::Binance] {
+            let spec = spec(exchange).expect("venue spec");
+            assert_eq!(spec.exchange, exchange);
+        }
+        let names: Vec<&str> = registered().iter().map(|s| s.exchange.as_str()).collect();
+        assert_eq!(names, ["binance", "blowfin"]);
+    }
+
+    #[test]
+    fn exchange_names_round_trip() {
+        assert_eq!("BlowFin".parse::<Exchange>().unwrap(), Exchange::Blowfin);
+        assert_eq!(
+            Exchange::Blowfin.as_str().parse::<Exchange>().unwrap(),
+            Exchange::Blowfin
+        );
+        assert_eq!("BINANCE".parse::<Exchange>().unwrap(), Exchange::Binance);
+        assert!("kraken".parse::<Exchange>().is_err());
+    }
+}
@@ -0,0 +1,536 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Exposure dashboard
+//! ──────────────────────────────────────────────────────────────────────────
+//! Net / gross exposure per symbol and per strategy, account leverage in use
+//! and margin utilisation.
+//!
+//! * Account side: a live BlowFin position + balance pull, snapshotted into
+//!   `positions` / `balances`. When the exchange can't be reached (or the
+//!   user has no keys) the latest stored snapshot is served instead and
+//!   `source` says so.
+//! * Strategy side: each strategy's own net position from
+//!   `strategy_positions` (see `services::allocation`).
+//! * Notional uses the mark-price feed, else the mark the exchange reported,
+//!   else the book mid, else the entry price. Sizes are taken as the
+//!   exchange reports them.
+//! * With a live mark, unrealised PnL is re-marked to it (a stored
+//!   snapshot's figure is stale); `liquidation_distance` is how far the
+//!   mark may move before the nearest leg of the symbol is liquidated.
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    services::{
+        analytics,
+        blowfin::{
+            api,
+            dto::{Balance, BlowFinResponse, Position},
+        },
+        market_data,
+    },
+    utils::errors::ApiError,
+};
+
+/// Upper bound on the live pull before falling back to the snapshot
+const LIVE_TIMEOUT: Duration = Duration::from_secs(5);
+const EXCHANGE: &str = "blowfin";
+const QTY_EPSILON: f64 = 1e-12;
+
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct ExchangePosition {
+    pub symbol: String,
+    /// Signed: positive long, negative short
+    pub qty: f64,
+    pub avg_price: Option<f64>,
+    pub mark_price: Option<f64>,
+    pub unrealised_pnl: Option<f64>,
+    pub leverage: Option<f64>,
+    pub liquidation_price: Option<f64>,
+}
+
+impl ExchangePosition {
+    fn price(&self) -> Option<f64> {
+        market_data::mark_price(&self.symbol)
+            .map(|m| m.mark)
+            .or(self.mark_price)
+            .or_else(|| analytics::mid_for(&self.symbol))
+            .or(self.avg_price)
+    }
+
+    fn unrealised(&self) -> Option<f64> {
+        match (market_data::mark_price(&self.symbol), self.avg_price) {
+            (Some(m), Some(entry)) => Some(self.qty * (m.mark - entry)),
+            _ => self.unrealised_pnl,
+        }
+    }
+
+    /// Share of the mark price left before liquidation
+    fn liquidation_distance(&self) -> Option<f64> {
+        let mark = market_data::mark_price(&self.symbol)
+            .map(|m| m.mark)
+            .or(self.mark_price)?;
+        let liq = self.liquidation_price.filter(|l| *l > 0.0)?;
+        (mark > 0.0).then(|| (mark - liq).abs() / mark)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccountBalance {
+    pub equity: f64,
+    pub available: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Pulled from the exchange for this request
+    Live,
+    /// Latest stored snapshot (exchange unreachable)
+    Snapshot,
+    /// Neither – account figures are empty
+    None,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolExposure {
+    pub symbol: String,
+    pub net_qty: f64,
+    pub net_notional: f64,
+    pub gross_notional: f64,
+    pub unrealised_pnl: f64,
+    /// Highest leverage set on any leg of the symbol
+    pub leverage: Option<f64>,
+    /// Nearest leg's `|mark − liquidation| / mark`
+    pub liquidation_distance: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct StrategyExposure {
+    pub strategy_id: Uuid,
+    pub strategy: String,
+    pub symbol: String,
+    pub qty: f64,
+    #[sqlx(default)]
+    pub notional: f64,
+    #[serde(skip)]
+    pub avg_price: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureReport {
+    pub source: Source,
+    pub as_of: Option<DateTime<Utc>>,
+    pub equity: Option<f64>,
+    pub net_notional: f64,
+    pub gross_notional: f64,
+    /// `gross_notional / equity`
+    pub leverage: Option<f64>,
+    /// `(equity − available) / equity`
+    pub margin_utilization: Option<f64>,
+    pub by_symbol: Vec<SymbolExposure>,
+    pub by_strategy: Vec<StrategyExposure>,
+}
+
+/// BlowFin `/account/positions` rows → open positions
+pub fn parse_positions(rows: &[Position]) -> Vec<ExchangePosition> {
+    rows.iter()
+        .map(|p| ExchangePosition {
+            symbol: p.inst_id.clone(),
+            qty: p.signed_qty(),
+            avg_price: p.average_price,
+            mark_price: p.mark_price,
+            unrealised_pnl: p.unrealized_pnl,
+            leverage: p.leverage,
+            liquidation_price: p.liquidation_price,
+        })
+        .filter(|p| p.qty.abs() >= QTY_EPSILON)
+        .collect()
+}
+
+/// BlowFin futures `/account/balance` → USDT equity / available
+pub fn parse_balance(balance: &Balance) -> Option<AccountBalance> {
+    let usdt = balance.currency("USDT")?;
+    Some(AccountBalance {
+        equity: usdt.equity,
+        available: usdt.available,
+    })
+}
+
+/// Pure aggregation – everything but the I/O
+pub fn summarize(
+    source: Source,
+    as_of: Option<DateTime<Utc>>,
+    positions: &[ExchangePosition],
+    balance: Option<AccountBalance>,
+    mut by_strategy: Vec<StrategyExposure>,
+) -> ExposureReport {
+    let mut symbols: BTreeMap<&str, SymbolExposure> = BTreeMap::new();
+    for p in positions {
+        let notional = p.price().map_or(0.0, |px| p.qty * px);
+        let e = symbols
+            .entry(p.symbol.as_str())
+            .or_insert_with(|| SymbolExposure {
+                symbol: p.symbol.clone(),
+                net_qty: 0.0,
+                net_notional: 0.0,
+                gross_notional: 0.0,
+                unrealised_pnl: 0.0,
+                leverage: None,
+                liquidation_distance: None,
+            });
+        e.net_qty += p.qty;
+        e.net_notional += notional;
+        e.gross_notional += notional.abs();
+        e.unrealised_pnl += p.unrealised().unwrap_or(0.0);
+        e.leverage = match (e.leverage, p.leverage) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        e.liquidation_distance = match (e.liquidation_distance, p.liquidation_distance()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+    let by_symbol: Vec<SymbolExposure> = symbols.into_values().collect();
+
+    for s in &mut by_strategy {
+        let px = market_data::mark_price(&s.symbol)
+            .map(|m| m.mark)
+            .or_else(|| analytics::mid_for(&s.symbol))
+            .unwrap_or(s.avg_price);
+        s.notional = s.qty * px;
+    }
+
+    let net_notional = by_symbol.iter().map(|s| s.net_notional).sum();
+    let gross_notional: f64 = by_symbol.iter().map(|s| s.gross_notional).sum();
+    let equity = balance.map(|b| b.equity).filter(|e| *e > 0.0);
+
+    ExposureReport {
+        source,
+        as_of,
+        equity,
+        net_notional,
+        gross_notional,
+        leverage: equity.map(|e| gross_notional / e),
+        margin_utilization: balance
+            .filter(|b| b.equity > 0.0)
+            .map(|b| ((b.equity - b.available) / b.equity).clamp(0.0, 1.0)),
+        by_symbol,
+        by_strategy,
+    }
+}
+
+async fn fetch_live(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<(Vec<ExchangePosition>, Option<AccountBalance>), ApiError> {
+    let (pos, bal) = tokio::join!(
+        api::get_positions(db, user_id, is_demo, master_key),
+        api::get_balance(db, user_id, is_demo, master_key),
+    );
+    let positions = pos?.into_data()?;
+    let balance = bal
+        .and_then(BlowFinResponse::into_data)
+        .ok()
+        .and_then(|b| parse_balance(&b));
+    Ok((parse_positions(&positions), balance))
+}
+
+async fn store_snapshot(
+    db: &PgPool,
+    user_id: i64,
+    at: DateTime<Utc>,
+    positions: &[ExchangePosition],
+    balance: Option<AccountBalance>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    for p in positions {
+        sqlx::query(
+            r#"
+            INSERT INTO positions
+                   (user_id, exchange, symbol, market_type, side, size,
+                    avg_entry_price, unrealised_pnl, leverage, liquidation_price, captured_at)
+            VALUES ($1, $2, $3, 'swap', $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(user_id)
+        .bind(EXCHANGE)
+        .bind(&p.symbol)
+        .bind(if p.qty < 0.0 { "short" } else { "long" })
+        .bind(p.qty.abs())
+        .bind(p.avg_price)
+        .bind(p.unrealised_pnl)
+        .bind(p.leverage)
+        .bind(p.liquidation_price)
+        .bind(at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    // written even when flat, so the snapshot time marks "no positions"
+    sqlx::query(
+        r#"
+        INSERT INTO balances (user_id, exchange, currency, equity, available, captured_at)
+        VALUES ($1, $2, 'USDT', $3, $4, $5)
+        "#,
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .bind(balance.map(|b| b.equity))
+    .bind(balance.map(|b| b.available))
+    .bind(at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+type Snapshot = (DateTime<Utc>, Vec<ExchangePosition>, Option<AccountBalance>);
+
+async fn latest_snapshot(db: &PgPool, user_id: i64) -> Result<Option<Snapshot>, sqlx::Error> {
+    let row: Option<(DateTime<Utc>, Option<f64>, Option<f64>)> = sqlx::query_as(
+        r#"
+        SELECT captured_at, equity::float8, available::float8
+          FROM balances
+         WHERE user_id = $1 AND exchange = $2 AND currency = 'USDT'
+         ORDER BY captured_at DESC
+         LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .fetch_optional(db)
+    .await?;
+    let Some((at, equity, available)) = row else {
+        return Ok(None);
+    };
+
+    let positions = sqlx::query_as::<_, ExchangePosition>(
+        r#"
+        SELECT symbol,
+               (CASE WHEN side = 'short' THEN -size ELSE size END)::float8 AS qty,
+               avg_entry_price::float8   AS avg_price,
+               NULL::float8              AS mark_price,
+               unrealised_pnl::float8    AS unrealised_pnl,
+               leverage::float8          AS leverage,
+               liquidation_price::float8 AS liquidation_price
+          FROM positions
+         WHERE user_id = $1 AND exchange = $2 AND captured_at = $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .bind(at)
+    .fetch_all(db)
+    .await?;
+
+    let balance = equity
+        .zip(available)
+        .map(|(equity, available)| AccountBalance { equity, available });
+    Ok(Some((at, positions, balance)))
+}
+
+async fn strategy_positions(
+    db: &PgPool,
+    user_id: i64,
+) -> Result<Vec<StrategyExposure>, sqlx::Error> {
+    sqlx::query_as::<_, StrategyExposure>(
+        r#"
+        SELECT s.strategy_id, s.strategy, s.symbol,
+               p.qty::float8       AS qty,
+               p.avg_price::float8 AS avg_price
+          FROM strategy_positions p
+          JOIN user_strategies s ON s.strategy_id = p.strategy_id
+         WHERE s.user_id = $1
+           AND p.qty <> 0
+         ORDER BY s.symbol, s.strategy_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+/// Live pull (snapshotted) with fallback to the last stored snapshot
+pub async fn report(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<ExposureReport, sqlx::Error> {
+    let strategies = strategy_positions(db, user_id).await?;
+
+    let live = match tokio::time::timeout(
+        LIVE_TIMEOUT,
+        fetch_live(db, user_id, is_demo, master_key),
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(_) => Err(ApiError::Other("timed out".into())),
+    };
+
+    match live {
+        Ok((positions, balance)) => {
+            let now = Utc::now();
+            if let Err(e) = store_snapshot(db, user_id, now, &positions, balance).await {
+                tracing::warn!("exposure: snapshot for user {user_id} not stored: {e}");
+            }
+            Ok(summarize(
+                Source::Live,
+                Some(now),
+                &positions,
+                balance,
+                strategies,
+            ))
+        }
+        Err(e) => {
+            tracing::warn!("exposure: live sync for user {user_id} failed: {e}");
+            Ok(match latest_snapshot(db, user_id).await? {
+                Some((at, positions, balance)) => {
+                    summarize(Source::Snapshot, Some(at), &positions, balance, strategies)
+                }
+                None => summarize(Source::None, None, &[], None, strategies),
+            })
+        }
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pos(symbol: &str, qty: f64, mark: f64) -> ExchangePosition {
+        ExchangePosition {
+            symbol: symbol.into(),
+            qty,
+            avg_price: None,
+            mark_price: Some(mark),
+            unrealised_pnl: Some(1.0),
+            leverage: Some(3.0),
+            liquidation_price: None,
+        }
+    }
+
+    #[test]
+    fn parses_net_and_hedge_mode_positions() {
+        let data = json!([
+            { "instId": "BTC-USDT", "positionSide": "net", "positions": "-2",
+              "averagePrice": "60000", "markPrice": "61000", "leverage": "5",
+              "unrealizedPnl": "-2000" },
+            { "instId": "ETH-USDT", "positionSide": "short", "positions": "3",
+              "markPrice": "3000" },
+            { "instId": "SOL-USDT", "positionSide": "long", "positions": "0" },
+        ]);
+        let p = parse_positions(&serde_json::from_value::<Vec<Position>>(data).unwrap());
+        assert_eq!(p.len(), 2, "flat rows are dropped");
+        assert_eq!(p[0].qty, -2.0);
+        assert_eq!(p[0].leverage, Some(5.0));
+        assert_eq!(p[0].unrealised_pnl, Some(-2000.0));
+        assert_eq!(p[1].qty, -3.0);
+        assert!(parse_positions(&[]).is_empty());
+    }
+
+    #[test]
+    fn parses_usdt_balance() {
+        let data = json!({ "ts": "1697021343571", "totalEquity": "1100", "details": [
+            { "currency": "BTC", "equity": "1", "available": "1" },
+            { "currency": "USDT", "equity": "1000.5", "available": "400" },
+        ]});
+        assert_eq!(
+            parse_balance(&serde_json::from_value(data).unwrap()),
+            Some(AccountBalance {
+                equity: 1000.5,
+                available: 400.0
+            })
+        );
+        assert_eq!(parse_balance(&Balance::default()), None);
+    }
+
+    #[test]
+    fn hedged_legs_net_out_but_count_gross() {
+        let positions = [
+            pos("BTC-USDT", 1.0, 100.0),
+            pos("BTC-USDT", -0.5, 100.0),
+            pos("ETH-USDT", -2.0, 10.0),
+        ];
+        let r = summarize(
+            Source::Live,
+            None,
+            &positions,
+            Some(AccountBalance {
+                equity: 100.0,
+                available: 25.0,
+            }),
+            Vec::new(),
+        );
+        assert_eq!(r.by_symbol.len(), 2);
+        let btc = &r.by_symbol[0];
+        assert_eq!(btc.symbol, "BTC-USDT");
+        assert_eq!(btc.net_qty, 0.5);
+        assert_eq!(btc.net_notional, 50.0);
+        assert_eq!(btc.gross_notional, 150.0);
+        assert_eq!(btc.unrealised_pnl, 2.0);
+
+        assert_eq!(r.net_notional, 30.0);
+        assert_eq!(r.gross_notional, 170.0);
+        assert_eq!(r.leverage, Some(1.7));
+        assert_eq!(r.margin_utilization, Some(0.75));
+    }
+
+    #[test]
+    fn nearest_leg_sets_the_liquidation_distance() {
+        let leg = |qty, liq| ExchangePosition {
+            liquidation_price: liq,
+            ..pos("ETH-USDT", qty, 100.0)
+        };
+        let r = summarize(
+            Source::Live,
+            None,
+            &[leg(1.0, Some(80.0)), leg(-1.0, Some(110.0)), leg(1.0, None)],
+            None,
+            Vec::new(),
+        );
+        assert_eq!(r.by_symbol[0].liquidation_distance, Some(0.1));
+        assert_eq!(leg(1.0, Some(0.0)).liquidation_distance(), None);
+    }
+
+    #[test]
+    fn no_equity_means_no_ratios() {
+        let r = summarize(
+            Source::None,
+            None,
+            &[pos("BTC-USDT", 1.0, 100.0)],
+            None,
+            Vec::new(),
+        );
+        assert_eq!(r.leverage, None);
+        assert_eq!(r.margin_utilization, None);
+        assert_eq!(r.gross_notional, 100.0);
+    }
+
+    #[test]
+    fn strategy_notional_falls_back_to_entry_price() {
+        let s = StrategyExposure {
+            strategy_id: Uuid::nil(),
+            strategy: "vcsr".into(),
+            symbol: "NOBOOK-USDT".into(),
+            qty: -2.0,
+            notional: 0.0,
+            avg_price: 50.0,
+        };
+        let r = summarize(Source::None, None, &[], None, vec![s]);
+        assert_eq!(r.by_strategy[0].notional, -100.0);
+    }
+}
@@ -0,0 +1,282 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Runtime feature flags
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Postgres (`feature_flags`) is the source of truth; the whole table is
+//!   cached under `flags:all` in the shared cache and for `LOCAL_TTL` in
+//!   process, so evaluating a flag on a hot path costs a map lookup
+//! * Writes go through [`upsert`] / [`delete`], which drop both copies – other
+//!   instances pick the change up within `LOCAL_TTL`
+//! * A flag is on for a user when it is `enabled` and the user is either
+//!   listed in `user_ids` or lands in the first `rollout_pct` of 100 buckets.
+//!   Buckets hash flag name + user id, so a user keeps their answer as the
+//!   percentage grows and different flags pick different users
+//! * Unknown flags and unreadable storage mean *off* – experiments fail closed
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+
+use crate::db::cache::Cache;
+
+/// Flags gating the experimental subsystems
+pub mod names {
+    pub const SMART_EXECUTION: &str = "smart_execution";
+    pub const ML_FILTER: &str = "ml_filter";
+    pub const EXCHANGE_ADAPTERS: &str = "exchange_adapters";
+}
+
+const CACHE_KEY: &str = "flags:all";
+const CACHE_TTL_SECS: u64 = 60;
+const LOCAL_TTL: Duration = Duration::from_secs(5);
+
+#[derive(thiserror::Error, Debug)]
+pub enum FlagError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct Flag {
+    pub name: String,
+    pub enabled: bool,
+    pub rollout_pct: i16,
+    pub user_ids: Vec<i64>,
+    pub description: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Flag {
+    pub fn allows(&self, user_id: i64) -> bool {
+        self.enabled
+            && (self.user_ids.contains(&user_id)
+                || i16::from(bucket(&self.name, user_id)) < self.rollout_pct)
+    }
+}
+
+/// Stable 0..100 bucket of `user_id` for flag `name`
+pub fn bucket(name: &str, user_id: i64) -> u8 {
+    let digest = Sha256::new()
+        .chain_update(name.as_bytes())
+        .chain_update(user_id.to_be_bytes())
+        .finalize();
+    let head = u64::from_be_bytes(digest[..8].try_into().expect("8 bytes"));
+    (head % 100) as u8
+}
+
+/// Body of `PUT /api/admin/flags/{name}`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FlagUpdate {
+    pub enabled: bool,
+    pub rollout_pct: i16,
+    pub user_ids: Vec<i64>,
+    pub description: String,
+}
+
+fn validate(name: &str, update: &FlagUpdate) -> Result<(), FlagError> {
+    let name_ok = (1..=64).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"_.-".contains(&b));
+    if !name_ok {
+        return Err(FlagError::Invalid(
+            "flag names are 1-64 chars of a-z, 0-9, `_`, `.` or `-`".into(),
+        ));
+    }
+    if !(0..=100).contains(&update.rollout_pct) {
+        return Err(FlagError::Invalid(
+            "rollout_pct must be between 0 and 100".into(),
+        ));
+    }
+    Ok(())
+}
+
+type Snapshot = Arc<HashMap<String, Flag>>;
+
+static LOCAL: Lazy<Mutex<Option<(Instant, Snapshot)>>> = Lazy::new(|| Mutex::new(None));
+
+fn remember(flags: Vec<Flag>) -> Snapshot {
+    let snap: Snapshot = Arc::new(flags.into_iter().map(|f| (f.name.clone(), f)).collect());
+    *LOCAL.lock().unwrap() = Some((Instant::now(), snap.clone()));
+    snap
+}
+
+async fn load(db: &PgPool, cache: &dyn Cache) -> Snapshot {
+    let stale = {
+        let local = LOCAL.lock().unwrap();
+        match local.as_ref() {
+            Some((at, snap)) if at.elapsed() < LOCAL_TTL => return snap.clone(),
+            other => other.map(|(_, snap)| snap.clone()),
+        }
+    };
+
+    match cache.get_json::<Vec<Flag>>(CACHE_KEY).await {
+        Ok(Some(flags)) => return remember(flags),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("flags: cache read: {e}"),
+    }
+    match list(db).await {
+        Ok(flags) => {
+            if let Err(e) = cache.set_json(CACHE_KEY, &flags, CACHE_TTL_SECS).await {
+                tracing::warn!("flags: cache write: {e}");
+            }
+            remember(flags)
+        }
+        Err(e) => {
+            tracing::error!("flags: DB error: {e}");
+            stale.unwrap_or_default()
+        }
+    }
+}
+
+async fn invalidate(cache: &dyn Cache) {
+    *LOCAL.lock().unwrap() = None;
+    if let Err(e) = cache.del(CACHE_KEY).await {
+        tracing::warn!("flags: invalidate: {e}");
+    }
+}
+
+/// Is `name` on for `user_id`?
+pub async fn is_enabled(db: &PgPool, cache: &dyn Cache, name: &str, user_id: i64) -> bool {
+    load(db, cache)
+        .await
+        .get(name)
+        .is_some_and(|f| f.allows(user_id))
+}
+
+/// Every flag's answer for one user (what the UI needs)
+pub async fn evaluate_all(db: &PgPool, cache: &dyn Cache, user_id: i64) -> BTreeMap<String, bool> {
+    load(db, cache)
+        .await
+        .values()
+        .map(|f| (f.name.clone(), f.allows(user_id)))
+        .collect()
+}
+
+pub async fn list(db: &PgPool) -> Result<Vec<Flag>, sqlx::Error> {
+    sqlx::query_as::<_, Flag>(
+        r#"
+        SELECT name, enabled, rollout_pct, user_ids, description, updated_at
+          FROM feature_flags
+         ORDER BY name
+        "#,
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn upsert(
+    db: &PgPool,
+    cache: &dyn Cache,
+    name: &str,
+    update: &FlagUpdate,
+) -> Result<Flag, FlagError> {
+    validate(name, update)?;
+    let flag = sqlx::query_as::<_, Flag>(
+        r#"
+        INSERT INTO feature_flags (name, enabled, rollout_pct, user_ids, description)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (name) DO UPDATE
+           SET enabled     = EXCLUDED.enabled,
+               rollout_pct = EXCLUDED.rollout_pct,
+               user_ids    = EXCLUDED.user_ids,
+               description = EXCLUDED.description,
+               updated_at  = now()
+        RETURNING name, enabled, rollout_pct, user_ids, description, updated_at
+        "#,
+    )
+    .bind(name)
+    .bind(update.enabled)
+    .bind(update.rollout_pct)
+    .bind(&update.user_ids)
+    .bind(&update.description)
+    .fetch_one(db)
+    .await?;
+    invalidate(cache).await;
+    Ok(flag)
+}
+
+/// `false` if there was no such flag
+pub async fn delete(db: &PgPool, cache: &dyn Cache, name: &str) -> Result<bool, sqlx::Error> {
+    let done = sqlx::query("DELETE FROM feature_flags WHERE name = $1")
+        .bind(name)
+        .execute(db)
+        .await?
+        .rows_affected()
+        > 0;
+    invalidate(cache).await;
+    Ok(done)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(enabled: bool, rollout_pct: i16, user_ids: Vec<i64>) -> Flag {
+        Flag {
+            name: "smart_execution".into(),
+            enabled,
+            rollout_pct,
+            user_ids,
+            description: String::new(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn kill_switch_beats_everything() {
+        let f = flag(false, 100, vec![7]);
+        assert!(!f.allows(7));
+        assert!(!f.allows(8));
+    }
+
+    #[test]
+    fn listed_users_and_full_rollout() {
+        assert!(flag(true, 0, vec![7]).allows(7));
+        assert!(!flag(true, 0, vec![7]).allows(8));
+        assert!((1..200).all(|u| flag(true, 100, vec![]).allows(u)));
+    }
+
+    #[test]
+    fn rollout_is_stable_and_roughly_proportional() {
+        let f = flag(true, 25, vec![]);
+        let on: Vec<i64> = (0..10_000).filter(|&u| f.allows(u)).collect();
+        assert!((2_000..3_000).contains(&on.len()), "{}", on.len());
+
+        // growing the rollout never turns anyone off
+        let wider = flag(true, 50, vec![]);
+        assert!(on.iter().all(|&u| wider.allows(u)));
+
+        assert_eq!(bucket("a", 42), bucket("a", 42));
+        assert!((0..100).any(|u| bucket("a", u) != bucket("b", u)));
+    }
+
+    #[test]
+    fn updates_are_validated() {
+        let ok = FlagUpdate {
+            rollout_pct: 10,
+            ..FlagUpdate::default()
+        };
+        assert!(validate("ml_filter", &ok).is_ok());
+        assert!(validate("", &ok).is_err());
+        assert!(validate("Has Space", &ok).is_err());
+        let bad_pct = FlagUpdate {
+            rollout_pct: 101,
+            ..FlagUpdate::default()
+        };
+        assert!(validate("ml_filter", &bad_pct).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Maker / taker fee schedules per user
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Each user's tier lives in `user_fee_tiers` (per exchange), either set
+//!   by hand (`PUT /api/fees`) or inferred from their recent exchange fills
+//!   (`POST /api/fees/sync`) – the highest observed rate is the taker rate,
+//!   a clearly lower one the maker rate
+//! * Users without a row pay `DEFAULT_MAKER_FEE_BPS` / `DEFAULT_TAKER_FEE_BPS`
+//! * Used by the entry gate in `execute_trade` (a take-profit must clear the
+//!   round trip), by `allocation::record_fill` (booked PnL is net of the
+//!   fill's fee) and by backtests
+//! * Limit orders are priced as maker, market orders and native TP/SL
+//!   triggers (they fill at market) as taker
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::services::{blowfin, instruments, trading_engine::TradeRequest};
+use crate::utils::errors::TradeError;
+
+const CACHE_TTL: Duration = Duration::from_secs(60);
+/// Rates closer than this are one tier, not a maker and a taker rate
+const TIER_GAP_BPS: f64 = 0.5;
+/// Anything above this is a bad row, not a fee
+const MAX_BPS: f64 = 100.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum FeeError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("no recent fills to infer fees from")]
+    NoFills,
+    #[error("exchange: {0}")]
+    Exchange(String),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// Rates in basis points of notional; negative maker = rebate
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+}
+
+impl Default for FeeSchedule {
+    /// BlowFin's entry tier
+    fn default() -> Self {
+        Self {
+            maker_bps: 2.0,
+            taker_bps: 6.0,
+        }
+    }
+}
+
+impl FeeSchedule {
+    pub fn validate(&self) -> Result<(), FeeError> {
+        let ok = |v: f64| v.is_finite() && v.abs() <= MAX_BPS;
+        if !ok(self.maker_bps) || !ok(self.taker_bps) {
+            return Err(FeeError::Invalid(format!(
+                "fee rates must be within ±{MAX_BPS} bps"
+            )));
+        }
+        if self.taker_bps < 0.0 || self.maker_bps > self.taker_bps {
+            return Err(FeeError::Invalid(
+                "need maker_bps <= taker_bps and taker_bps >= 0".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn bps(&self, maker: bool) -> f64 {
+        if maker {
+            self.maker_bps
+        } else {
+            self.taker_bps
+        }
+    }
+
+    /// Fee on one fill of `notional`
+    pub fn fee(&self, notional: f64, maker: bool) -> f64 {
+        notional.abs() * self.bps(maker) / 10_000.0
+    }
+}
+
+/// Whether an order of this type rests on the book
+pub fn is_maker(order_type: &str) -> bool {
+    order_type.eq_ignore_ascii_case("limit") || order_type.eq_ignore_ascii_case("post_only")
+}
+
+static DEFAULT: OnceCell<FeeSchedule> = OnceCell::new();
+
+/// Schedule for users without a tier (idempotent)
+pub fn init(default: FeeSchedule) {
+    let _ = DEFAULT.set(default);
+}
+
+pub fn default_schedule() -> FeeSchedule {
+    DEFAULT.get().copied().unwrap_or_default()
+}
+
+/// ─── Storage ─────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct FeeTier {
+    pub exchange: String,
+    /// Exchange's name for the tier, e.g. `VIP1`
+    pub tier: Option<String>,
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+    /// `manual` / `fills`
+    pub source: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+static CACHE: Lazy<DashMap<(i64, String), (Instant, FeeSchedule)>> = Lazy::new(DashMap::new);
+
+pub async fn get(
+    db: &PgPool,
+    user_id: i64,
+    exchange: &str,
+) -> Result<Option<FeeTier>, sqlx::Error> {
+    sqlx::query_as::<_, FeeTier>(
+        r#"
+        SELECT exchange, tier, maker_bps::float8 AS maker_bps, taker_bps::float8 AS taker_bps,
+               source, updated_at
+          FROM user_fee_tiers
+         WHERE user_id = $1 AND exchange = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(exchange)
+    .fetch_optional(db)
+    .await
+}
+
+/// The user's schedule, else the default; a DB error falls back to the
+/// default too – fees are an estimate, not a reason to stop trading
+pub async fn schedule_for(db: &PgPool, user_id: i64, exchange: &str) -> FeeSchedule {
+    let key = (user_id, exchange.to_string());
+    if let Some(hit) = CACHE.get(&key) {
+        if hit.0.elapsed() < CACHE_TTL {
+            return hit.1;
+        }
+    }
+    let sched = match get(db, user_id, exchange).await {
+        Ok(Some(t)) => FeeSchedule {
+            maker_bps: t.maker_bps,
+            taker_bps: t.taker_bps,
+        },
+        Ok(None) => default_schedule(),
+        Err(e) => {
+            tracing::warn!("fees: tier for {user_id}/{exchange}: {e}");
+            return default_schedule();
+        }
+    };
+    CACHE.insert(key, (Instant::now(), sched));
+    sched
+}
+
+pub async fn set(
+    db: &PgPool,
+    user_id: i64,
+    exchange: &str,
+    tier: Option<&str>,
+    sched: FeeSchedule,
+    source: &str,
+) -> Result<FeeTier, FeeError> {
+    sched.validate()?;
+    let row = sqlx::query_as::<_, FeeTier>(
+        r#"
+        INSERT INTO user_fee_tiers (user_id, exchange, tier, maker_bps, taker_bps, source)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, exchange) DO UPDATE
+           SET tier = EXCLUDED.tier, maker_bps = EXCLUDED.maker_bps,
+               taker_bps = EXCLUDED.taker_bps, source = EXCLUDED.source, updated_at = now()
+        RETURNING exchange, tier, maker_bps::float8 AS maker_bps, taker_bps::float8 AS taker_bps,
+                  source, updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(exchange)
+    .bind(tier)
+    .bind(sched.maker_bps)
+    .bind(sched.taker_bps)
+    .bind(source)
+    .fetch_one(db)
+    .await?;
+    CACHE.remove(&(user_id, exchange.to_string()));
+    Ok(row)
+}
+
+/// ─── Inference from fills ────────────────────────────────────────────────
+/// Schedule implied by observed per-fill rates (bps). With one cluster only
+/// it's taken as the taker rate – bots mostly cross the spread – and the
+/// maker rate of `current` is kept (capped at the new taker rate).
+pub fn infer(rates_bps: &[f64], current: FeeSchedule) -> Option<FeeSchedule> {
+    let rates: Vec<f64> = rates_bps
+        .iter()
+        .copied()
+        .filter(|r| r.is_finite() && r.abs() <= MAX_BPS)
+        .map(|r| (r * 10.0).round() / 10.0)
+        .collect();
+    let hi = rates.iter().copied().reduce(f64::max)?;
+    let lo = rates.iter().copied().reduce(f64::min)?;
+    Some(if hi - lo >= TIER_GAP_BPS {
+        FeeSchedule {
+            maker_bps: lo,
+            taker_bps: hi,
+        }
+    } else {
+        FeeSchedule {
+            maker_bps: current.maker_bps.min(hi),
+            taker_bps: hi,
+        }
+    })
+}
+
+/// Re-derive the user's BlowFin schedule from their last fills and store it
+pub async fn sync_from_exchange(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<FeeTier, FeeError> {
+    let fills = blowfin::api::get_fills(db, user_id, is_demo, master_key)
+        .await
+        .and_then(|r| r.into_data())
+        .map_err(|e| FeeError::Exchange(e.to_string()))?;
+    let rates: Vec<f64> = fills
+        .iter()
+        .filter_map(|f| {
+            let cv = instruments::get(&f.inst_id)?.contract_value;
+            let notional = f.fill_price * f.fill_size * cv;
+            (notional > 0.0).then(|| f.fee / notional * 10_000.0)
+        })
+        .collect();
+    let current = schedule_for(db, user_id, "blowfin").await;
+    let sched = infer(&rates, current).ok_or(FeeError::NoFills)?;
+    set(db, user_id, "blowfin", None, sched, "fills").await
+}
+
+/// ─── Entry gate ──────────────────────────────────────────────────────────
+/// Take-profit distance left after a round trip (entry at the order's rate,
+/// exit by the native TP at taker), in bps; `None` without a TP or price
+pub fn edge_after_fees_bps(req: &TradeRequest, sched: FeeSchedule) -> Option<f64> {
+    let tp = req.tp_sl.and_then(|t| t.take_profit)?;
+    let entry = req.price.or(req.signal_price).filter(|p| *p > 0.0)?;
+    let gross = if req.side.eq_ignore_ascii_case("sell") {
+        (entry - tp) / entry
+    } else {
+        (tp - entry) / entry
+    } * 10_000.0;
+    Some(gross - sched.bps(is_maker(&req.order_type)) - sched.taker_bps)
+}
+
+/// Refuse entries whose take-profit can't pay the user's fees
+pub async fn check_edge(db: &PgPool, user_id: i64, req: &TradeRequest) -> Result<(), TradeError> {
+    let sched = schedule_for(db, user_id, req.exchange.as_str()).await;
+    match edge_after_fees_bps(req, sched) {
+        Some(edge) if edge <= 0.0 => Err(TradeError::RiskViolation(format!(
+            "take-profit doesn't cover fees ({edge:.1} bps after maker {:.1} / taker {:.1} bps)",
+            sched.maker_bps, sched.taker_bps
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::trading_engine::{Exchange, TpSl};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn entry(side: &str, order_type: &str, price: f64, tp: Option<f64>) -> TradeRequest {
+        TradeRequest {
+            exchange: Exchange::Blowfin,
+            symbol: "BTC-USDT".into(),
+            side: side.into(),
+            order_type: order_type.into(),
+            price: (order_type == "limit").then_some(price),
+            size: 1.0,
+            reduce_only: false,
+            signal_price: Some(price),
+            tp_sl: tp.map(|tp| TpSl {
+                take_profit: Some(tp),
+                stop_loss: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn fees_follow_the_side_of_the_book() {
+        let s = FeeSchedule::default();
+        assert!(close(s.fee(10_000.0, true), 2.0));
+        assert!(close(s.fee(-10_000.0, false), 6.0));
+        assert!(is_maker("LIMIT") && !is_maker("market"));
+        assert!(s.validate().is_ok());
+        let inverted = FeeSchedule {
+            maker_bps: 7.0,
+            taker_bps: 5.0,
+        };
+        assert!(inverted.validate().is_err());
+        let rebate = FeeSchedule {
+            maker_bps: -1.0,
+            taker_bps: 4.0,
+        };
+        assert!(rebate.validate().is_ok());
+    }
+
+    #[test]
+    fn tiers_are_inferred_from_fill_rates() {
+        let cur = FeeSchedule::default();
+        let both = infer(&[4.02, 1.49, 3.98, 1.5], cur).unwrap();
+        assert_eq!((both.maker_bps, both.taker_bps), (1.5, 4.0));
+        // takers only: keep the maker rate, capped at the taker rate
+        let takers = infer(&[5.0, 5.01], cur).unwrap();
+        assert_eq!((takers.maker_bps, takers.taker_bps), (2.0, 5.0));
+        let cheap = infer(&[1.0], cur).unwrap();
+        assert_eq!((cheap.maker_bps, cheap.taker_bps), (1.0, 1.0));
+        assert!(infer(&[], cur).is_none());
+        assert!(infer(&[f64::NAN, 1e6], cur).is_none());
+    }
+
+    #[test]
+    fn take_profit_must_clear_the_round_trip() {
+        let s = FeeSchedule::default();
+        // 10 bps move, market in (6) + TP out (6)
+        let edge = edge_after_fees_bps(&entry("buy", "market", 100.0, Some(100.1)), s).unwrap();
+        assert!(close(edge, -2.0));
+        // limit in (2) + TP out (6)
+        let edge = edge_after_fees_bps(&entry("buy", "limit", 100.0, Some(100.1)), s).unwrap();
+        assert!(close(edge, 2.0));
+        let edge = edge_after_fees_bps(&entry("sell", "market", 100.0, Some(99.0)), s).unwrap();
+        assert!(close(edge, 88.0));
+        assert!(edge_after_fees_bps(&entry("buy", "market", 100.0, None), s).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Funding rates – history and the entry filter
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every funding update on the bus (`MarketBus::all_funding`) is kept in
+//!   `funding_rates`, one row per exchange, symbol and settlement; the row
+//!   is rewritten at most every `PERSIST_EVERY` while the prediction moves,
+//!   so the last write before a settlement is about what was paid
+//! * Any strategy may set `max_funding_bps` in its params: an entry is
+//!   refused while the side it opens would pay more than that many basis
+//!   points at the next settlement – longs when funding is positive, shorts
+//!   when it is negative
+//! * Without a fresh rate (feed down, symbol not a perpetual) entries go
+//!   ahead; a missing feed mustn't stop trading
+//! * Checked in `allocation::execute_traced` for the strategies routing
+//!   entries through it, and per quote side by the market maker
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::query_builder::Separated;
+use sqlx::{PgPool, Postgres};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use uuid::Uuid;
+
+use crate::db::batch::{BatchConfig, BatchRow, BatchWriter};
+use crate::services::market_data::{self, BusFunding, FundingRate, MarketBus};
+
+/// The params key every strategy reads the limit from
+pub const PARAM: &str = "max_funding_bps";
+/// A moving prediction is written at most this often per settlement
+const PERSIST_EVERY: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone)]
+pub struct FundingRow {
+    pub symbol: String,
+    pub funding: FundingRate,
+}
+
+impl BatchRow for FundingRow {
+    const TABLE: &'static str = "funding_rates";
+    const COLUMNS: &'static [&'static str] =
+        &["exchange", "symbol", "funding_time", "rate", "captured_at"];
+    const ON_CONFLICT: &'static str = "ON CONFLICT (exchange, symbol, funding_time) \
+        DO UPDATE SET rate = EXCLUDED.rate, captured_at = EXCLUDED.captured_at";
+
+    fn bind_row<'args>(&self, b: &mut Separated<'_, 'args, Postgres, &'static str>) {
+        let f = &self.funding;
+        b.push_bind(f.exchange)
+            .push_bind(self.symbol.clone())
+            .push_bind(f.next_funding)
+            .push_bind(f.rate)
+            .push_bind(f.ts);
+    }
+}
+
+/// Spawn the recorder over every symbol and exchange.
+pub fn spawn(pool: PgPool, bus: Arc<MarketBus>) {
+    let writer = BatchWriter::<FundingRow>::spawn(pool, BatchConfig::default());
+    tokio::spawn(record(bus.all_funding(), writer));
+}
+
+async fn record(mut rx: Receiver<BusFunding>, w: BatchWriter<FundingRow>) {
+    // (symbol, exchange) → (settlement last written, when)
+    let mut written: HashMap<(String, &'static str), (DateTime<Utc>, Instant)> = HashMap::new();
+    loop {
+        match rx.recv().await {
+            Ok(BusFunding { symbol, funding }) => {
+                let key = (symbol.clone(), funding.exchange);
+                let due = match written.get(&key) {
+                    Some((settlement, at)) => {
+                        *settlement != funding.next_funding || at.elapsed() >= PERSIST_EVERY
+                    }
+                    None => true,
+                };
+                if due && w.try_push(FundingRow { symbol, funding }) {
+                    written.insert(key, (funding.next_funding, Instant::now()));
+                }
+            }
+            Err(RecvError::Lagged(n)) => tracing::warn!("funding recorder: lagged {n}"),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// A strategy's `max_funding_bps`, if it set one
+pub fn limit(params: &Value) -> Option<f64> {
+    params.get(PARAM).and_then(Value::as_f64)
+}
+
+/// [`limit`] of the strategy's current params
+pub async fn limit_for(db: &PgPool, strategy_id: Uuid) -> Option<f64> {
+    let params =
+        sqlx::query_scalar::<_, Value>("SELECT params FROM user_strategies WHERE strategy_id = $1")
+            .bind(strategy_id)
+            .fetch_optional(db)
+            .await;
+    match params {
+        Ok(params) => limit(&params?),
+        Err(e) => {
+            tracing::warn!("funding limit of {strategy_id}: {e}");
+            None
+        }
+    }
+}
+
+/// Basis points a position opened by `side` pays per settlement at `rate`
+/// (negative: it is paid)
+pub fn cost_bps(rate: f64, side: &str) -> f64 {
+    let bps = rate * 10_000.0;
+    if side.eq_ignore_ascii_case("sell") {
+        -bps
+    } else {
+        bps
+    }
+}
+
+/// Whether an entry on `side` may go ahead under `funding`
+pub fn check(funding: Option<FundingRate>, side: &str, max_bps: f64) -> Result<(), String> {
+    let Some(f) = funding else {
+        return Ok(());
+    };
+    let cost = cost_bps(f.rate, side);
+    if cost > max_bps {
+        return Err(format!(
+            "{side} would pay {cost:.2} bps funding ({}) at {}, above the {max_bps} bps limit",
+            f.exchange,
+            f.next_funding.format("%H:%M UTC")
+        ));
+    }
+    Ok(())
+}
+
+/// [`check`] against the latest funding of `symbol`
+pub fn check_entry(symbol: &str, side: &str, max_bps: f64) -> Result<(), String> {
+    check(market_data::funding_rate(symbol), side, max_bps)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn the_paying_side_is_held_back() {
+        let f = FundingRate {
+            exchange: "blowfin",
+            rate: 0.0008,
+            next_funding: Utc::now(),
+            ts: Utc::now(),
+        };
+        // longs pay 8 bps, shorts are paid
+        assert!(check(Some(f), "buy", 5.0).is_err());
+        assert!(check(Some(f), "buy", 10.0).is_ok());
+        assert!(check(Some(f), "sell", 0.0).is_ok());
+        let negative = FundingRate { rate: -0.0008, ..f };
+        assert!(check(Some(negative), "sell", 5.0).is_err());
+        // no rate, no filter
+        assert!(check(None, "buy", 0.0).is_ok());
+
+        assert_eq!(limit(&json!({ "max_funding_bps": 5 })), Some(5.0));
+        assert_eq!(limit(&json!({ "symbol": "BTCUSDT" })), None);
+    }
+}
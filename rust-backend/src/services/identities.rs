@@ -0,0 +1,553 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Linked sign-in identities
+//! ──────────────────────────────────────────────────────────────────────────
+//! * A trading account (`users.user_id`, the Discord snowflake it was
+//!   created from) can be reached through several identities – other
+//!   Discord accounts, an email login – kept in `user_identities`
+//! * JWTs carry `sub` (+ optional `provider`, default `discord`); [`resolve`]
+//!   maps that to the account. An account with no rows yet still answers to
+//!   its own snowflake, so nothing changes until someone links
+//! * Exactly one identity per account is primary (notifications go there);
+//!   the primary and the last identity can't be unlinked
+//! * [`merge`] folds a second account – e.g. an alt that already traded –
+//!   into the current one: every snowflake-keyed row is re-keyed, rows that
+//!   would collide with the survivor's are dropped, the alt's identities
+//!   follow and its `users` row goes
+//! * Resolutions are cached in-process for `CACHE_TTL`, so an unlink made on
+//!   another instance lands within that window
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug)]
+pub enum IdentityError {
+    #[error("identity not linked to this account")]
+    NotFound,
+    #[error("identity already linked to this account")]
+    AlreadyLinked,
+    #[error("identity belongs to another account – merge that account instead")]
+    OtherAccount,
+    #[error("the primary identity can't be unlinked – make another one primary first")]
+    IsPrimary,
+    #[error("an account needs at least one identity")]
+    LastIdentity,
+    #[error("cannot merge an account into itself")]
+    SameAccount,
+    #[error("stop the other account's {0} running strategies before merging")]
+    Busy(i64),
+    #[error("{0}")]
+    Invalid(String),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Discord,
+    Email,
+}
+
+impl Provider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Provider::Discord => "discord",
+            Provider::Email => "email",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, IdentityError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "discord" => Ok(Provider::Discord),
+            "email" => Ok(Provider::Email),
+            other => Err(IdentityError::Invalid(format!(
+                "unknown provider `{other}`"
+            ))),
+        }
+    }
+}
+
+/// Who a token says the caller is, normalised
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdentityRef {
+    pub provider: Provider,
+    pub subject: String,
+}
+
+impl IdentityRef {
+    /// Discord subjects must be snowflakes; addresses are compared lower-cased
+    pub fn new(provider: Provider, subject: &str) -> Result<Self, IdentityError> {
+        let subject = subject.trim();
+        let subject = match provider {
+            Provider::Discord if subject.parse::<u64>().is_ok() => subject.to_string(),
+            Provider::Discord => {
+                return Err(IdentityError::Invalid(
+                    "discord subject must be a snowflake".into(),
+                ))
+            }
+            Provider::Email if subject.contains('@') && subject.len() <= 255 => {
+                subject.to_lowercase()
+            }
+            Provider::Email => return Err(IdentityError::Invalid("invalid email address".into())),
+        };
+        Ok(Self { provider, subject })
+    }
+
+    /// The account id an unlinked Discord identity stands for
+    fn legacy_user_id(&self) -> Option<i64> {
+        match self.provider {
+            Provider::Discord => self.subject.parse().ok(),
+            Provider::Email => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Identity {
+    pub provider: String,
+    pub subject: String,
+    pub is_primary: bool,
+    pub linked_at: DateTime<Utc>,
+}
+
+/// ─── Resolution ──────────────────────────────────────────────────────────
+static CACHE: Lazy<DashMap<IdentityRef, (Instant, Option<i64>)>> = Lazy::new(DashMap::new);
+
+fn forget(id: &IdentityRef) {
+    CACHE.remove(id);
+}
+
+/// Drop every cached resolution (after an account goes away)
+pub fn forget_all() {
+    CACHE.clear();
+}
+
+/// Seed the resolution cache, so middleware tests need no database
+#[cfg(test)]
+pub(crate) fn remember(id: IdentityRef, user_id: Option<i64>) {
+    CACHE.insert(id, (Instant::now(), user_id));
+}
+
+/// Account behind `id`; `None` for an identity nobody linked
+pub async fn resolve(db: &PgPool, id: &IdentityRef) -> Result<Option<i64>, sqlx::Error> {
+    if let Some(hit) = CACHE.get(id) {
+        if hit.0.elapsed() < CACHE_TTL {
+            return Ok(hit.1);
+        }
+    }
+    let linked: Option<i64> = sqlx::query_scalar(
+        "SELECT user_id FROM user_identities WHERE provider = $1 AND subject = $2",
+    )
+    .bind(id.provider.as_str())
+    .bind(&id.subject)
+    .fetch_optional(db)
+    .await?;
+    let user_id = match (linked, id.legacy_user_id()) {
+        (Some(uid), _) => Some(uid),
+        // a snowflake is its own account until that account has identities
+        // of its own (then an unlinked one must stay unlinked) or is being
+        // deleted
+        (None, Some(uid)) => {
+            let managed: bool = sqlx::query_scalar(
+                r#"
+                SELECT EXISTS (SELECT 1 FROM user_identities WHERE user_id = $1)
+                    OR EXISTS (SELECT 1 FROM account_deletions WHERE user_id = $1)
+                "#,
+            )
+            .bind(uid)
+            .fetch_one(db)
+            .await?;
+            (!managed).then_some(uid)
+        }
+        (None, None) => None,
+    };
+    CACHE.insert(id.clone(), (Instant::now(), user_id));
+    Ok(user_id)
+}
+
+/// ─── Management ──────────────────────────────────────────────────────────
+/// Accounts created before linking existed get their own Discord id as the
+/// primary identity the first time they're managed.
+async fn seed(tx: &mut Transaction<'_, Postgres>, user_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_identities (provider, subject, user_id, is_primary)
+        SELECT 'discord', $1::text, $1, true
+        WHERE NOT EXISTS (SELECT 1 FROM user_identities WHERE user_id = $1)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+async fn list_tx(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: i64,
+) -> Result<Vec<Identity>, sqlx::Error> {
+    sqlx::query_as::<_, Identity>(
+        r#"
+        SELECT provider, subject, is_primary, linked_at
+        FROM   user_identities
+        WHERE  user_id = $1
+        ORDER  BY is_primary DESC, linked_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut **tx)
+    .await
+}
+
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<Identity>, IdentityError> {
+    let mut tx = db.begin().await?;
+    seed(&mut tx, user_id).await?;
+    let rows = list_tx(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(rows)
+}
+
+/// Attach `id` (already proven by the caller's token) to `user_id`
+pub async fn link(
+    db: &PgPool,
+    user_id: i64,
+    id: &IdentityRef,
+) -> Result<Vec<Identity>, IdentityError> {
+    let mut tx = db.begin().await?;
+    seed(&mut tx, user_id).await?;
+
+    let owner: Option<i64> = sqlx::query_scalar(
+        "SELECT user_id FROM user_identities WHERE provider = $1 AND subject = $2 FOR UPDATE",
+    )
+    .bind(id.provider.as_str())
+    .bind(&id.subject)
+    .fetch_optional(&mut *tx)
+    .await?;
+    match owner {
+        Some(uid) if uid == user_id => return Err(IdentityError::AlreadyLinked),
+        Some(_) => return Err(IdentityError::OtherAccount),
+        None => {}
+    }
+    // an unmanaged snowflake with an account of its own needs a merge
+    if let Some(uid) = id.legacy_user_id().filter(|&u| u != user_id) {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE user_id = $1)")
+                .bind(uid)
+                .fetch_one(&mut *tx)
+                .await?;
+        if exists {
+            return Err(IdentityError::OtherAccount);
+        }
+    }
+
+    sqlx::query(
+        "INSERT INTO user_identities (provider, subject, user_id, is_primary) VALUES ($1, $2, $3, false)",
+    )
+    .bind(id.provider.as_str())
+    .bind(&id.subject)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    let rows = list_tx(&mut tx, user_id).await?;
+    tx.commit().await?;
+    forget(id);
+    Ok(rows)
+}
+
+pub async fn set_primary(
+    db: &PgPool,
+    user_id: i64,
+    id: &IdentityRef,
+) -> Result<Vec<Identity>, IdentityError> {
+    let mut tx = db.begin().await?;
+    seed(&mut tx, user_id).await?;
+    // clear first: the partial unique index allows one primary at a time
+    sqlx::query("UPDATE user_identities SET is_primary = false WHERE user_id = $1 AND is_primary")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let hit = sqlx::query(
+        r#"
+        UPDATE user_identities SET is_primary = true
+        WHERE  user_id = $1 AND provider = $2 AND subject = $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(id.provider.as_str())
+    .bind(&id.subject)
+    .execute(&mut *tx)
+    .await?;
+    if hit.rows_affected() == 0 {
+        return Err(IdentityError::NotFound);
+    }
+    let rows = list_tx(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(rows)
+}
+
+pub async fn unlink(
+    db: &PgPool,
+    user_id: i64,
+    id: &IdentityRef,
+) -> Result<Vec<Identity>, IdentityError> {
+    let mut tx = db.begin().await?;
+    seed(&mut tx, user_id).await?;
+    let rows = list_tx(&mut tx, user_id).await?;
+    let target = rows
+        .iter()
+        .find(|r| r.provider == id.provider.as_str() && r.subject == id.subject)
+        .ok_or(IdentityError::NotFound)?;
+    if target.is_primary {
+        return Err(IdentityError::IsPrimary);
+    }
+    if rows.len() == 1 {
+        return Err(IdentityError::LastIdentity);
+    }
+    sqlx::query("DELETE FROM user_identities WHERE provider = $1 AND subject = $2")
+        .bind(id.provider.as_str())
+        .bind(&id.subject)
+        .execute(&mut *tx)
+        .await?;
+    let rows = list_tx(&mut tx, user_id).await?;
+    tx.commit().await?;
+    forget(id);
+    Ok(rows)
+}
+
+/// ─── Merge ───────────────────────────────────────────────────────────────
+/// What besides the user column makes a row unique
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unique {
+    /// No constraint – every row moves
+    None,
+    /// Unique on the user column plus these; the survivor's row wins
+    On(&'static [&'static str]),
+}
+
+/// Every snowflake-keyed column. `referrals` / `copy_relations` rows linking
+/// the two accounts are dropped first (their CHECKs forbid self-links).
+pub const USER_COLUMNS: &[(&str, &str, Unique)] = &[
+    ("api_keys", "user_id", Unique::On(&["exchange"])),
+    (
+        "exchange_accts",
+        "user_id",
+        Unique::On(&["exchange", "label"]),
+    ),
+    ("strategies", "user_id", Unique::None),
+    ("user_strategies", "user_id", Unique::None),
+    ("orders", "user_id", Unique::None),
+    ("fees", "user_id", Unique::None),
+    ("positions", "user_id", Unique::None),
+    ("balances", "user_id", Unique::None),
+    ("strategy_pnl", "user_id", Unique::None),
+    ("copy_relations", "leader_user_id", Unique::None),
+    ("copy_relations", "follower_user_id", Unique::None),
+    ("copy_parent_orders", "leader_id", Unique::None),
+    (
+        "copy_allocations",
+        "follower_id",
+        Unique::On(&["parent_id"]),
+    ),
+    ("user_plans", "user_id", Unique::On(&[])),
+    ("user_fee_tiers", "user_id", Unique::On(&["exchange"])),
+    ("leader_profiles", "user_id", Unique::On(&[])),
+    (
+        "transfers",
+        "user_id",
+        Unique::On(&["exchange", "kind", "transfer_id"]),
+    ),
+    ("referral_codes", "user_id", Unique::None),
+    ("referrals", "referred_user_id", Unique::On(&[])),
+    ("referrals", "referrer_user_id", Unique::None),
+    ("referral_rewards", "user_id", Unique::None),
+    ("referral_rewards", "referred_user_id", Unique::None),
+    ("optimizer_jobs", "user_id", Unique::None),
+    ("auth_refresh_tokens", "user_id", Unique::None),
+    ("user_watchlist", "user_id", Unique::On(&["symbol"])),
+    ("arbitrage_settings", "user_id", Unique::On(&["symbol"])),
+    ("arbitrage_positions", "user_id", Unique::None),
+    ("alerts", "user_id", Unique::None),
+    ("strategy_plugins", "user_id", Unique::On(&["name"])),
+    ("backtest_runs", "user_id", Unique::None),
+    ("audit_log", "user_id", Unique::None),
+];
+
+/// `($1 = from, $2 = into)` statements re-keying one column: drop the rows
+/// that would collide, then move the rest
+pub fn rekey_sql(table: &str, column: &str, unique: Unique) -> Vec<String> {
+    let mut stmts = Vec::with_capacity(2);
+    if let Unique::On(cols) = unique {
+        let same: String = cols.iter().map(|c| format!(" AND d.{c} = s.{c}")).collect();
+        stmts.push(format!(
+            "DELETE FROM {table} s WHERE s.{column} = $1 AND EXISTS \
+             (SELECT 1 FROM {table} d WHERE d.{column} = $2{same})"
+        ));
+    }
+    stmts.push(format!(
+        "UPDATE {table} SET {column} = $2 WHERE {column} = $1"
+    ));
+    stmts
+}
+
+/// Re-key every [`USER_COLUMNS`] row of `from` to `into`, recording moves
+/// per `table.column`; returns the colliding rows dropped
+pub(crate) async fn rekey_all(
+    tx: &mut Transaction<'_, Postgres>,
+    from: i64,
+    into: i64,
+    moved: &mut BTreeMap<String, u64>,
+) -> Result<u64, sqlx::Error> {
+    let mut dropped = 0;
+    for &(table, column, unique) in USER_COLUMNS {
+        let stmts = rekey_sql(table, column, unique);
+        let (update, deletes) = stmts.split_last().expect("rekey_sql yields an UPDATE");
+        for sql in deletes {
+            dropped += sqlx::query(sql)
+                .bind(from)
+                .bind(into)
+                .execute(&mut **tx)
+                .await?
+                .rows_affected();
+        }
+        let n = sqlx::query(update)
+            .bind(from)
+            .bind(into)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected();
+        if n > 0 {
+            moved.insert(format!("{table}.{column}"), n);
+        }
+    }
+    Ok(dropped)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MergeReport {
+    pub merged_user_id: i64,
+    /// `table.column` → rows moved
+    pub moved: BTreeMap<String, u64>,
+    /// Rows dropped because the surviving account already had one
+    pub dropped: u64,
+    pub identities: Vec<Identity>,
+}
+
+/// Fold account `from` into `into` in one transaction
+pub async fn merge(db: &PgPool, into: i64, from: i64) -> Result<MergeReport, IdentityError> {
+    if into == from {
+        return Err(IdentityError::SameAccount);
+    }
+    // running tasks hold the old id in memory
+    let running: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM user_strategies WHERE user_id = $1 AND status = 'enabled'",
+    )
+    .bind(from)
+    .fetch_one(db)
+    .await?;
+    if running > 0 {
+        return Err(IdentityError::Busy(running));
+    }
+
+    let mut tx = db.begin().await?;
+    seed(&mut tx, into).await?;
+    seed(&mut tx, from).await?;
+    let mut report = MergeReport {
+        merged_user_id: from,
+        ..Default::default()
+    };
+
+    // links between the two accounts would become self-links
+    for sql in [
+        "DELETE FROM copy_relations WHERE (leader_user_id = $1 AND follower_user_id = $2) \
+         OR (leader_user_id = $2 AND follower_user_id = $1)",
+        "DELETE FROM referrals WHERE (referred_user_id = $1 AND referrer_user_id = $2) \
+         OR (referred_user_id = $2 AND referrer_user_id = $1)",
+    ] {
+        report.dropped += sqlx::query(sql)
+            .bind(from)
+            .bind(into)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+
+    report.dropped += rekey_all(&mut tx, from, into, &mut report.moved).await?;
+
+    // the other account's identities follow; the survivor keeps its primary
+    sqlx::query("UPDATE user_identities SET user_id = $2, is_primary = false WHERE user_id = $1")
+        .bind(from)
+        .bind(into)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM users WHERE user_id = $1")
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+    report.identities = list_tx(&mut tx, into).await?;
+    tx.commit().await?;
+
+    forget_all();
+    Ok(report)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subjects_are_normalised() {
+        let d = IdentityRef::new(Provider::Discord, " 123456789012345678 ").unwrap();
+        assert_eq!(d.subject, "123456789012345678");
+        assert_eq!(d.legacy_user_id(), Some(123_456_789_012_345_678));
+        assert!(IdentityRef::new(Provider::Discord, "alice").is_err());
+
+        let e = IdentityRef::new(Provider::Email, "Alice@Example.COM").unwrap();
+        assert_eq!(e.subject, "alice@example.com");
+        assert_eq!(e.legacy_user_id(), None);
+        assert!(IdentityRef::new(Provider::Email, "nope").is_err());
+
+        assert_eq!(Provider::parse("EMAIL").unwrap(), Provider::Email);
+        assert!(Provider::parse("github").is_err());
+    }
+
+    #[test]
+    fn rekey_drops_collisions_before_moving() {
+        assert_eq!(
+            rekey_sql("orders", "user_id", Unique::None),
+            vec!["UPDATE orders SET user_id = $2 WHERE user_id = $1"]
+        );
+        let stmts = rekey_sql("user_watchlist", "user_id", Unique::On(&["symbol"]));
+        assert_eq!(stmts.len(), 2);
+        assert_eq!(
+            stmts[0],
+            "DELETE FROM user_watchlist s WHERE s.user_id = $1 AND EXISTS \
+             (SELECT 1 FROM user_watchlist d WHERE d.user_id = $2 AND d.symbol = s.symbol)"
+        );
+        let one_per_user = rekey_sql("user_plans", "user_id", Unique::On(&[]));
+        assert!(one_per_user[0].ends_with("WHERE d.user_id = $2)"));
+    }
+
+    #[test]
+    fn every_user_table_is_rekeyed_once() {
+        let mut seen = std::collections::HashSet::new();
+        for (t, c, _) in USER_COLUMNS {
+            assert!(seen.insert((t, c)), "{t}.{c} listed twice");
+        }
+        assert!(seen.contains(&(&"api_keys", &"user_id")));
+        assert!(seen.contains(&(&"audit_log", &"user_id")));
+    }
+}
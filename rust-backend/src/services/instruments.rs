@@ -0,0 +1,193 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Instrument metadata & display hints
+//! ──────────────────────────────────────────────────────────────────────────
+//! * BlowFin's public instrument list (tick size, lot size, contract value,
+//!   currencies) is pulled every `REFRESH` into a process-wide map keyed by
+//!   normalised symbol; a failed pull is retried after `RETRY` and the last
+//!   good list stays in use meanwhile
+//! * [`display`] turns an instrument into what a client needs to render a
+//!   symbol – price decimals, quantity step and decimals, quote currency – so
+//!   the UI doesn't ship its own copy of exchange metadata
+//! * Symbols BlowFin doesn't list (or anything before the first pull) have no
+//!   metadata and are left out of `display` maps
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+
+use crate::services::{
+    blowfin::dto::{BlowFinResponse, Instrument},
+    exchange_log,
+    market_data::bus_symbol,
+};
+
+const REFRESH: Duration = Duration::from_secs(3_600);
+const RETRY: Duration = Duration::from_secs(60);
+/// Decimals beyond this are float noise, not exchange precision
+const MAX_DECIMALS: u32 = 12;
+
+static INSTRUMENTS: Lazy<DashMap<String, Instrument>> = Lazy::new(DashMap::new);
+
+/// How a client should render one symbol; part of the API envelope
+pub use rustraptor_types::api::DisplayMeta;
+
+/// Decimal places of a step size or quantity (`0.001` → 3, `5` → 0)
+pub fn decimals(step: f64) -> u32 {
+    if !step.is_finite() || step <= 0.0 {
+        return 0;
+    }
+    let s = step.to_string();
+    let frac = s
+        .split_once('.')
+        .map(|(_, f)| f.trim_end_matches('0').len())
+        .unwrap_or(0);
+    (frac as u32).min(MAX_DECIMALS)
+}
+
+impl From<&Instrument> for DisplayMeta {
+    fn from(i: &Instrument) -> Self {
+        Self {
+            price_precision: decimals(i.tick_size),
+            tick_size: i.tick_size,
+            qty_step: i.lot_size,
+            qty_precision: decimals(i.lot_size),
+            min_qty: i.min_size,
+            contract_value: i.contract_value,
+            base_currency: i.base_currency.clone(),
+            quote_currency: i.quote_currency.clone(),
+        }
+    }
+}
+
+/// Replace the known instrument set
+pub fn load(rows: Vec<Instrument>) {
+    let keep: Vec<String> = rows.iter().map(|i| bus_symbol(&i.inst_id)).collect();
+    for (key, row) in keep.iter().zip(rows) {
+        INSTRUMENTS.insert(key.clone(), row);
+    }
+    INSTRUMENTS.retain(|k, _| keep.contains(k));
+}
+
+pub fn get(symbol: &str) -> Option<Instrument> {
+    INSTRUMENTS.get(&bus_symbol(symbol)).map(|i| i.clone())
+}
+
+pub fn display(symbol: &str) -> Option<DisplayMeta> {
+    INSTRUMENTS
+        .get(&bus_symbol(symbol))
+        .map(|i| DisplayMeta::from(i.value()))
+}
+
+/// `display` for every listed symbol, keyed as given
+pub fn display_for<'a>(
+    symbols: impl IntoIterator<Item = &'a str>,
+) -> BTreeMap<String, DisplayMeta> {
+    symbols
+        .into_iter()
+        .filter_map(|s| display(s).map(|d| (s.to_string(), d)))
+        .collect()
+}
+
+async fn fetch(http: &reqwest::Client, is_demo: bool) -> Result<Vec<Instrument>, String> {
+    let base = if is_demo {
+        "https://demo-trading-openapi.blofin.com"
+    } else {
+        "https://openapi.blofin.com"
+    };
+    let url = format!("{base}/api/v1/market/instruments?instType=SWAP");
+    let trace = exchange_log::trace::<()>("GET", &url, &[], None);
+    let (_, text) = exchange_log::send(http.get(&url), trace)
+        .await
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str::<BlowFinResponse<Vec<Instrument>>>(&text)
+        .map_err(|e| e.to_string())?
+        .into_data()
+        .map_err(|e| e.to_string())
+}
+
+/// Background refresh; the first pull runs immediately
+pub fn spawn(is_demo: bool) {
+    tokio::spawn(async move {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        loop {
+            let wait = match fetch(&http, is_demo).await {
+                Ok(rows) if !rows.is_empty() => {
+                    tracing::info!("instruments: {} symbols loaded", rows.len());
+                    load(rows);
+                    REFRESH
+                }
+                Ok(_) => {
+                    tracing::warn!("instruments: exchange returned an empty list");
+                    RETRY
+                }
+                Err(e) => {
+                    tracing::warn!("instruments: refresh failed: {e}");
+                    RETRY
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instrument(inst_id: &str, tick_size: f64, lot_size: f64) -> Instrument {
+        Instrument {
+            inst_id: inst_id.into(),
+            base_currency: inst_id.split('-').next().unwrap().into(),
+            quote_currency: "USDT".into(),
+            contract_value: 0.001,
+            min_size: lot_size,
+            lot_size,
+            tick_size,
+            state: "live".into(),
+        }
+    }
+
+    #[test]
+    fn step_sizes_become_decimals() {
+        assert_eq!(decimals(0.1), 1);
+        assert_eq!(decimals(0.001), 3);
+        assert_eq!(decimals(0.00001), 5);
+        assert_eq!(decimals(0.5), 1);
+        assert_eq!(decimals(1.0), 0);
+        assert_eq!(decimals(10.0), 0);
+        assert_eq!(decimals(0.0), 0);
+        assert_eq!(decimals(f64::NAN), 0);
+    }
+
+    #[test]
+    fn display_is_looked_up_by_normalised_symbol() {
+        load(vec![
+            instrument("XRP-USDT", 0.0001, 1.0),
+            instrument("ADA-USDT", 0.00001, 0.1),
+        ]);
+        let meta = display("xrpusdt-swap").unwrap();
+        assert_eq!(meta.price_precision, 4);
+        assert_eq!(meta.qty_precision, 0);
+        assert_eq!(meta.quote_currency, "USDT");
+        assert_eq!(meta.base_currency, "XRP");
+
+        let map = display_for(["ADAUSDT", "NOPE-USDT"]);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map["ADAUSDT"].qty_precision, 1);
+
+        // a reload drops delisted symbols
+        load(vec![instrument("ADA-USDT", 0.00001, 0.1)]);
+        assert!(get("XRP-USDT").is_none());
+        assert!(get("ADA-USDT").is_some());
+    }
+}
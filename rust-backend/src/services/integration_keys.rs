@@ -0,0 +1,357 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Scoped HMAC keys per integration
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Each integration (bot, partner, internal job) signs with its own key,
+//!   picked by `X-RR-KEY-ID`; a key only reaches the route prefixes in its
+//!   `scopes`, so one leaked secret doesn't open the whole API
+//! * Secrets are generated here, returned once and stored envelope-encrypted
+//! * [`rotate`] issues a successor with the same scopes and keeps the old key
+//!   valid for a grace period; [`revoke`] cuts a key off immediately
+//! * Resolved keys are cached in-process for `CACHE_TTL`, so a revocation
+//!   made on another instance lands within that window
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use zeroize::Zeroizing;
+
+use crate::services::crypto::GLOBAL_CRYPTO;
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug)]
+pub enum IntegrationKeyError {
+    #[error("key not found")]
+    NotFound,
+    #[error("{0}")]
+    Invalid(String),
+    #[error("crypto: {0}")]
+    Crypto(#[from] anyhow::Error),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// Why a known key was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    Revoked,
+    Expired,
+    OutOfScope,
+}
+
+/// A resolved key, secret decrypted – never serialised
+#[derive(Clone)]
+pub struct IntegrationKey {
+    pub key_id: String,
+    pub integration: String,
+    pub scopes: Vec<String>,
+    pub secret: Zeroizing<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked: bool,
+}
+
+impl IntegrationKey {
+    pub fn authorize(&self, path: &str, now: DateTime<Utc>) -> Result<(), Denied> {
+        if self.revoked {
+            return Err(Denied::Revoked);
+        }
+        if self.expires_at.is_some_and(|t| t <= now) {
+            return Err(Denied::Expired);
+        }
+        if !self.scopes.iter().any(|s| scope_allows(s, path)) {
+            return Err(Denied::OutOfScope);
+        }
+        Ok(())
+    }
+}
+
+/// Prefix match on whole path segments: `/api/trade` covers `/api/trade`
+/// and `/api/trade/...` but not `/api/trades`; `/` covers everything
+pub fn scope_allows(scope: &str, path: &str) -> bool {
+    let scope = scope.trim_end_matches('/');
+    path == scope
+        || path
+            .strip_prefix(scope)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn validate(integration: &str, scopes: &[String]) -> Result<(), IntegrationKeyError> {
+    if integration.trim().is_empty() {
+        return Err(IntegrationKeyError::Invalid(
+            "integration name required".into(),
+        ));
+    }
+    if scopes.is_empty() {
+        return Err(IntegrationKeyError::Invalid(
+            "at least one scope required".into(),
+        ));
+    }
+    if let Some(bad) = scopes.iter().find(|s| !s.starts_with('/')) {
+        return Err(IntegrationKeyError::Invalid(format!(
+            "scope `{bad}` must be a route prefix starting with `/`"
+        )));
+    }
+    Ok(())
+}
+
+/// Listing view – no secret material
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct KeyInfo {
+    pub key_id: String,
+    pub integration: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// A freshly issued key; the only time the secret leaves the server
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedKey {
+    pub key_id: String,
+    pub integration: String,
+    pub scopes: Vec<String>,
+    pub secret: String,
+}
+
+async fn insert<'e, E>(
+    exec: E,
+    integration: &str,
+    scopes: &[String],
+) -> Result<IssuedKey, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let key_id = format!("rrk_{}", hex::encode(rand::random::<[u8; 8]>()));
+    let secret = hex::encode(rand::random::<[u8; 32]>());
+    let (wrapped, nonce, ct) = GLOBAL_CRYPTO.seal(secret.as_bytes());
+    sqlx::query(
+        r#"
+        INSERT INTO integration_keys
+               (key_id, integration, scopes, encrypted_data_key, nonce_secret, encrypted_secret)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(&key_id)
+    .bind(integration)
+    .bind(scopes)
+    .bind(wrapped)
+    .bind(nonce)
+    .bind(ct)
+    .execute(exec)
+    .await?;
+    Ok(IssuedKey {
+        key_id,
+        integration: integration.to_string(),
+        scopes: scopes.to_vec(),
+        secret,
+    })
+}
+
+pub async fn create(
+    db: &PgPool,
+    integration: &str,
+    scopes: &[String],
+) -> Result<IssuedKey, IntegrationKeyError> {
+    validate(integration, scopes)?;
+    Ok(insert(db, integration.trim(), scopes).await?)
+}
+
+/// New key with the same integration and scopes; the old one stays valid
+/// for `grace` (or until its existing expiry, if sooner)
+pub async fn rotate(
+    db: &PgPool,
+    key_id: &str,
+    grace: chrono::Duration,
+) -> Result<IssuedKey, IntegrationKeyError> {
+    let mut tx = db.begin().await?;
+    let row: Option<(String, Vec<String>)> = sqlx::query_as(
+        r#"
+        SELECT integration, scopes
+          FROM integration_keys
+         WHERE key_id = $1 AND revoked_at IS NULL
+           AND (expires_at IS NULL OR expires_at > now())
+           FOR UPDATE
+        "#,
+    )
+    .bind(key_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((integration, scopes)) = row else {
+        return Err(IntegrationKeyError::NotFound);
+    };
+
+    let issued = insert(&mut *tx, &integration, &scopes).await?;
+    sqlx::query(
+        r#"
+        UPDATE integration_keys
+           SET expires_at = LEAST(COALESCE(expires_at, 'infinity'), now() + $2)
+         WHERE key_id = $1
+        "#,
+    )
+    .bind(key_id)
+    .bind(grace)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    forget(key_id);
+    Ok(issued)
+}
+
+/// `false` if the key doesn't exist or was already revoked
+pub async fn revoke(db: &PgPool, key_id: &str) -> Result<bool, sqlx::Error> {
+    let done = sqlx::query(
+        "UPDATE integration_keys SET revoked_at = now() WHERE key_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(key_id)
+    .execute(db)
+    .await?
+    .rows_affected()
+        > 0;
+    forget(key_id);
+    Ok(done)
+}
+
+pub async fn list(db: &PgPool) -> Result<Vec<KeyInfo>, sqlx::Error> {
+    sqlx::query_as::<_, KeyInfo>(
+        r#"
+        SELECT key_id, integration, scopes, created_at, expires_at, revoked_at
+          FROM integration_keys
+         ORDER BY integration, created_at
+        "#,
+    )
+    .fetch_all(db)
+    .await
+}
+
+static RESOLVED: Lazy<DashMap<String, (Instant, IntegrationKey)>> = Lazy::new(DashMap::new);
+
+fn forget(key_id: &str) {
+    RESOLVED.remove(key_id);
+}
+
+/// Seed the resolve cache, so middleware tests need no database
+#[cfg(test)]
+pub(crate) fn remember(key: IntegrationKey) {
+    RESOLVED.insert(key.key_id.clone(), (Instant::now(), key));
+}
+
+#[derive(FromRow)]
+struct KeyRow {
+    key_id: String,
+    integration: String,
+    scopes: Vec<String>,
+    encrypted_data_key: Vec<u8>,
+    nonce_secret: Vec<u8>,
+    encrypted_secret: Vec<u8>,
+    expires_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Look up `key_id` (cached); `Ok(None)` for unknown ids
+pub async fn resolve(
+    db: &PgPool,
+    key_id: &str,
+) -> Result<Option<IntegrationKey>, IntegrationKeyError> {
+    if let Some(hit) = RESOLVED.get(key_id) {
+        if hit.0.elapsed() < CACHE_TTL {
+            return Ok(Some(hit.1.clone()));
+        }
+    }
+
+    let row = sqlx::query_as::<_, KeyRow>(
+        r#"
+        SELECT key_id, integration, scopes, encrypted_data_key, nonce_secret,
+               encrypted_secret, expires_at, revoked_at
+          FROM integration_keys
+         WHERE key_id = $1
+        "#,
+    )
+    .bind(key_id)
+    .fetch_optional(db)
+    .await?;
+    let Some(row) = row else {
+        forget(key_id);
+        return Ok(None);
+    };
+
+    let secret = GLOBAL_CRYPTO.open(
+        &row.encrypted_data_key,
+        &row.nonce_secret,
+        &row.encrypted_secret,
+    )?;
+    let key = IntegrationKey {
+        key_id: row.key_id,
+        integration: row.integration,
+        scopes: row.scopes,
+        secret: Zeroizing::new(secret),
+        expires_at: row.expires_at,
+        revoked: row.revoked_at.is_some(),
+    };
+    RESOLVED.insert(key_id.to_string(), (Instant::now(), key.clone()));
+    Ok(Some(key))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(scopes: &[&str]) -> IntegrationKey {
+        IntegrationKey {
+            key_id: "rrk_test".into(),
+            integration: "discord-bot".into(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            secret: Zeroizing::new("s3cret".into()),
+            expires_at: None,
+            revoked: false,
+        }
+    }
+
+    #[test]
+    fn scopes_match_whole_segments() {
+        assert!(scope_allows("/api/trade", "/api/trade"));
+        assert!(scope_allows("/api/trade", "/api/trade/close"));
+        assert!(scope_allows("/api/trade/", "/api/trade/close"));
+        assert!(!scope_allows("/api/trade", "/api/trades"));
+        assert!(!scope_allows("/api/trade", "/api"));
+        assert!(scope_allows("/", "/api/strategy/start"));
+    }
+
+    #[test]
+    fn authorize_checks_revocation_expiry_and_scope() {
+        let now = Utc::now();
+        let k = key(&["/api/alerts", "/api/watchlist"]);
+        assert_eq!(k.authorize("/api/watchlist/BTC-USDT", now), Ok(()));
+        assert_eq!(
+            k.authorize("/api/strategy/start", now),
+            Err(Denied::OutOfScope)
+        );
+
+        let mut rotated = k.clone();
+        rotated.expires_at = Some(now + chrono::Duration::minutes(5));
+        assert_eq!(rotated.authorize("/api/alerts", now), Ok(()));
+        rotated.expires_at = Some(now);
+        assert_eq!(rotated.authorize("/api/alerts", now), Err(Denied::Expired));
+
+        let mut revoked = k;
+        revoked.revoked = true;
+        assert_eq!(revoked.authorize("/api/alerts", now), Err(Denied::Revoked));
+    }
+
+    #[test]
+    fn scopes_must_be_route_prefixes() {
+        assert!(validate("bot", &["/api/alerts".into()]).is_ok());
+        assert!(validate("bot", &[]).is_err());
+        assert!(validate(" ", &["/".into()]).is_err());
+        assert!(validate("bot", &["api/alerts".into()]).is_err());
+    }
+}
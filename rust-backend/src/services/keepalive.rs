@@ -0,0 +1,84 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Exchange connection keep-alive
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every `EXCHANGE_KEEPALIVE_SECS` each BlowFin REST host gets one cheap
+//!   public GET through the shared signed-request client, so its pool always
+//!   holds a warm TLS connection and the first order after a quiet spell
+//!   skips the handshake
+//! * Any HTTP answer counts – the point is the socket, not the payload
+//! * Round trips land in `exchange_keepalive_seconds{host}`, failures in
+//!   `exchange_keepalive_failures_total{host}`
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::{Duration, Instant};
+
+use metrics::{histogram, increment_counter};
+
+use crate::services::blowfin::client::{shared_http, BASE_URL};
+
+/// Public, tiny and unthrottled on every BlowFin host
+const PING_PATH: &str = "/api/v1/market/tickers?instId=BTC-USDT";
+/// A ping slower than this is as good as a dead connection
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// REST hosts orders can go to in this mode
+pub fn hosts(is_demo: bool) -> [&'static str; 2] {
+    let openapi = if is_demo {
+        "https://demo-trading-openapi.blofin.com"
+    } else {
+        "https://openapi.blofin.com"
+    };
+    [BASE_URL, openapi]
+}
+
+async fn ping(host: &'static str) {
+    let started = Instant::now();
+    let sent = shared_http()
+        .get(format!("{host}{PING_PATH}"))
+        .timeout(PING_TIMEOUT)
+        .send()
+        .await;
+    match sent {
+        Ok(_) => histogram!(
+            "exchange_keepalive_seconds",
+            started.elapsed().as_secs_f64(),
+            "host" => host
+        ),
+        Err(e) => {
+            increment_counter!("exchange_keepalive_failures_total", "host" => host);
+            tracing::warn!("keepalive: {host} unreachable: {e}");
+        }
+    }
+}
+
+/// Background pinger; `every` zero turns it off. The first round runs
+/// immediately so start-up pays the handshakes instead of the first order.
+pub fn spawn(is_demo: bool, every: Duration) {
+    if every.is_zero() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(every);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tick.tick().await;
+            futures::future::join_all(hosts(is_demo).map(ping)).await;
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pings_every_host_orders_use() {
+        assert_eq!(hosts(false), [BASE_URL, "https://openapi.blofin.com"]);
+        assert_eq!(hosts(true)[1], "https://demo-trading-openapi.blofin.com");
+        assert!(hosts(true).iter().all(|h| h.starts_with("https://")));
+    }
+}
@@ -0,0 +1,307 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Leader capital verification & discovery
+//! ──────────────────────────────────────────────────────────────────────────
+//! * A leader lists themself with the equity [`Bracket`] they claim
+//! * Every `VERIFY_EVERY` the job snapshots each listed leader's live USDT
+//!   equity into `balances` and grants the highest bracket – never above the
+//!   claim – that *every* snapshot of the last `WINDOW` clears, given at
+//!   least `MIN_SNAPSHOTS` of them. A deposit parked for one check and
+//!   withdrawn again doesn't earn a badge; a drop below it costs it
+//! * [`discover`] lists leaders for followers, badged first
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::services::{audit, exchanges, trading_engine::Exchange};
+use crate::utils::errors::TradeError;
+
+const VERIFY_EVERY: StdDuration = StdDuration::from_secs(6 * 3_600);
+/// Snapshots a badge has to hold up over
+const WINDOW: Duration = Duration::days(7);
+/// Fewer snapshots than this in the window prove nothing yet
+const MIN_SNAPSHOTS: usize = 3;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LeaderError {
+    #[error("not listed as a leader")]
+    NotListed,
+    #[error("exchange: {0}")]
+    Exchange(#[from] TradeError),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Bracket {
+    #[serde(rename = "1k")]
+    K1,
+    #[serde(rename = "10k")]
+    K10,
+    #[serde(rename = "100k")]
+    K100,
+    #[serde(rename = "1m")]
+    M1,
+}
+
+impl Bracket {
+    /// Lowest first
+    pub const ALL: [Bracket; 4] = [Bracket::K1, Bracket::K10, Bracket::K100, Bracket::M1];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Bracket::K1 => "1k",
+            Bracket::K10 => "10k",
+            Bracket::K100 => "100k",
+            Bracket::M1 => "1m",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.as_str() == s)
+    }
+
+    /// Minimum equity, USDT
+    pub fn floor(self) -> f64 {
+        match self {
+            Bracket::K1 => 1_000.0,
+            Bracket::K10 => 10_000.0,
+            Bracket::K100 => 100_000.0,
+            Bracket::M1 => 1_000_000.0,
+        }
+    }
+
+    /// ">$10k verified"
+    pub fn badge(self) -> String {
+        format!(">${} verified", self.as_str())
+    }
+}
+
+/// Highest bracket, capped at `reported`, that all `equities` clear
+pub fn verify(reported: Bracket, equities: &[f64]) -> Option<Bracket> {
+    if equities.len() < MIN_SNAPSHOTS {
+        return None;
+    }
+    let low = equities.iter().copied().fold(f64::INFINITY, f64::min);
+    Bracket::ALL
+        .into_iter()
+        .filter(|b| *b <= reported && b.floor() <= low)
+        .max()
+}
+
+/// List (or re-list) the caller with a claimed bracket; the badge follows
+/// on the next verification pass
+pub async fn list(db: &PgPool, user_id: i64, reported: Bracket) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO leader_profiles (user_id, reported_bracket)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+           SET reported_bracket = EXCLUDED.reported_bracket,
+               listed = TRUE,
+               -- a changed claim is unverified until the next pass
+               verified_bracket = CASE
+                   WHEN leader_profiles.reported_bracket = EXCLUDED.reported_bracket
+                   THEN leader_profiles.verified_bracket
+               END
+        "#,
+    )
+    .bind(user_id)
+    .bind(reported.as_str())
+    .execute(db)
+    .await?;
+    audit::record(
+        Some(user_id),
+        "leader.list",
+        serde_json::json!({ "reported_bracket": reported }),
+    );
+    Ok(())
+}
+
+pub async fn unlist(db: &PgPool, user_id: i64) -> Result<(), LeaderError> {
+    let n = sqlx::query("UPDATE leader_profiles SET listed = FALSE WHERE user_id = $1 AND listed")
+        .bind(user_id)
+        .execute(db)
+        .await?
+        .rows_affected();
+    if n == 0 {
+        return Err(LeaderError::NotListed);
+    }
+    audit::record(Some(user_id), "leader.unlist", serde_json::json!({}));
+    Ok(())
+}
+
+/// Snapshot one leader's equity and re-grade their badge
+pub async fn verify_leader(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<Option<Bracket>, LeaderError> {
+    let reported: String = sqlx::query_scalar(
+        "SELECT reported_bracket FROM leader_profiles WHERE user_id = $1 AND listed",
+    )
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?
+    .ok_or(LeaderError::NotListed)?;
+
+    // an exchange failure leaves the current badge as it is
+    let now = Utc::now();
+    let balance = exchanges::connect(db, user_id, Exchange::Blowfin)
+        .await?
+        .get_balance(db, user_id, is_demo, master_key)
+        .await?;
+    let usdt = balance.currency("USDT");
+    sqlx::query(
+        r#"
+        INSERT INTO balances (user_id, exchange, currency, equity, available, captured_at)
+        VALUES ($1, $2, 'USDT', $3, $4, $5)
+        "#,
+    )
+    .bind(user_id)
+    .bind(Exchange::Blowfin.as_str())
+    .bind(usdt.map(|d| d.equity))
+    .bind(usdt.map(|d| d.available))
+    .bind(now)
+    .execute(db)
+    .await?;
+
+    let equities: Vec<f64> = sqlx::query_scalar(
+        r#"
+        SELECT equity::float8
+          FROM balances
+         WHERE user_id = $1 AND exchange = $2 AND currency = 'USDT'
+           AND equity IS NOT NULL AND captured_at > $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(Exchange::Blowfin.as_str())
+    .bind(now - WINDOW)
+    .fetch_all(db)
+    .await?;
+
+    // an unknown stored claim (bracket renamed) verifies nothing
+    let badge = Bracket::parse(&reported).and_then(|r| verify(r, &equities));
+    sqlx::query(
+        r#"
+        UPDATE leader_profiles
+           SET verified_bracket = $2,
+               verified_at = CASE WHEN $2::text IS NULL THEN NULL ELSE $3 END,
+               checked_at = $3
+         WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(badge.map(Bracket::as_str))
+    .bind(now)
+    .execute(db)
+    .await?;
+    Ok(badge)
+}
+
+pub fn spawn(db: PgPool, is_demo: bool) {
+    tokio::spawn(async move {
+        let master_key = std::env::var("MASTER_KEY").unwrap_or_default().into_bytes();
+        let mut tick = tokio::time::interval(VERIFY_EVERY);
+        loop {
+            tick.tick().await;
+            let leaders: Vec<i64> =
+                match sqlx::query_scalar("SELECT user_id FROM leader_profiles WHERE listed")
+                    .fetch_all(&db)
+                    .await
+                {
+                    Ok(l) => l,
+                    Err(e) => {
+                        tracing::error!("leader verification: DB error: {e}");
+                        continue;
+                    }
+                };
+            for user_id in leaders {
+                if let Err(e) = verify_leader(&db, user_id, is_demo, &master_key).await {
+                    tracing::warn!("leader verification: {user_id}: {e}");
+                }
+            }
+        }
+    });
+}
+
+/// One row of the discovery list
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Leader {
+    pub user_id: i64,
+    pub username: String,
+    pub reported_bracket: String,
+    pub verified_bracket: Option<String>,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub followers: i64,
+    #[sqlx(skip)]
+    pub badge: Option<String>,
+}
+
+/// Listed leaders: highest verified bracket first, then by followers
+pub async fn discover(db: &PgPool, limit: i64) -> Result<Vec<Leader>, sqlx::Error> {
+    let mut leaders = sqlx::query_as::<_, Leader>(
+        r#"
+        SELECT p.user_id, u.rr_username AS username, p.reported_bracket,
+               p.verified_bracket, p.verified_at,
+               (SELECT count(*) FROM copy_relations c
+                 WHERE c.leader_user_id = p.user_id AND c.status = 'active') AS followers
+          FROM leader_profiles p
+          JOIN users u ON u.user_id = p.user_id
+         WHERE p.listed AND u.deleted_at IS NULL
+        "#,
+    )
+    .fetch_all(db)
+    .await?;
+    for l in &mut leaders {
+        l.badge = l
+            .verified_bracket
+            .as_deref()
+            .and_then(Bracket::parse)
+            .map(Bracket::badge);
+    }
+    leaders.sort_by(|a, b| {
+        let rank = |l: &Leader| l.verified_bracket.as_deref().and_then(Bracket::parse);
+        rank(b).cmp(&rank(a)).then(b.followers.cmp(&a.followers))
+    });
+    leaders.truncate(limit.max(0) as usize);
+    Ok(leaders)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge_needs_the_whole_window_and_caps_at_the_claim() {
+        // too few snapshots
+        assert_eq!(verify(Bracket::K10, &[50_000.0, 50_000.0]), None);
+        // capped at what was claimed
+        assert_eq!(verify(Bracket::K10, &[500_000.0; 3]), Some(Bracket::K10));
+        // one dip decides
+        assert_eq!(
+            verify(Bracket::K100, &[150_000.0, 9_000.0, 150_000.0]),
+            Some(Bracket::K1)
+        );
+        assert_eq!(verify(Bracket::M1, &[900.0, 5_000.0, 5_000.0]), None);
+    }
+
+    #[test]
+    fn brackets_round_trip() {
+        for b in Bracket::ALL {
+            assert_eq!(Bracket::parse(b.as_str()), Some(b));
+            let json = serde_json::to_string(&b).unwrap();
+            assert_eq!(json, format!("\"{}\"", b.as_str()));
+        }
+        assert_eq!(Bracket::K10.badge(), ">$10k verified");
+        assert_eq!(Bracket::parse("5k"), None);
+    }
+}
@@ -0,0 +1,351 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Per-symbol trading hours & illiquidity guard
+//! ──────────────────────────────────────────────────────────────────────────
+//! Filters come from `SYMBOL_FILTERS`, one `;`-separated entry per symbol
+//! (`*` = every symbol without its own entry):
+//!
+//! ```text
+//! BTC-USDT:min_vol=500,max_spread_bps=8,blackout=22-24/0-1;*:max_spread_bps=20
+//! ```
+//!
+//! * `min_vol`        – rolling 24 h volume (base units) from 1 h candles;
+//!   extrapolated from what is buffered until a full day is in
+//! * `max_spread_bps` – live top-of-book spread from the depth feed
+//! * `blackout`       – UTC hour ranges `start-end` (end exclusive, may wrap)
+//!
+//! Only entries are checked – exits must always be able to reduce risk.
+//! A configured check without live data (no quote / quote older than
+//! `QUOTE_MAX_AGE`, no candles) rejects: we can't show the book is liquid.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Timelike, Utc};
+use dashmap::DashMap;
+use metrics::increment_counter;
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::services::{market_data::bus_symbol, strategies::Candle};
+
+/// A quote older than this no longer describes the book
+const QUOTE_MAX_AGE: Duration = Duration::from_secs(30);
+const VOLUME_WINDOW_HOURS: i64 = 24;
+
+// ─── Filters ──────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolFilter {
+    pub min_volume_24h: Option<f64>,
+    pub max_spread_bps: Option<f64>,
+    /// `(start, end)` UTC hours, end exclusive; `start > end` wraps midnight
+    pub blackout_hours: Vec<(u32, u32)>,
+}
+
+impl SymbolFilter {
+    pub fn in_blackout(&self, hour: u32) -> bool {
+        self.blackout_hours.iter().any(|&(start, end)| {
+            if start <= end {
+                (start..end).contains(&hour)
+            } else {
+                hour >= start || hour < end
+            }
+        })
+    }
+}
+
+impl FromStr for SymbolFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut f = SymbolFilter::default();
+        for kv in s.split(',').map(str::trim).filter(|kv| !kv.is_empty()) {
+            let (k, v) = kv
+                .split_once('=')
+                .ok_or(format!("expected key=value, got `{kv}`"))?;
+            let num = |v: &str| match v.trim().parse::<f64>() {
+                Ok(x) if x >= 0.0 => Ok(x),
+                _ => Err(format!("{k}: bad number `{v}`")),
+            };
+            match k.trim() {
+                "min_vol" => f.min_volume_24h = Some(num(v)?),
+                "max_spread_bps" => f.max_spread_bps = Some(num(v)?),
+                "blackout" => {
+                    for range in v.split('/') {
+                        let (a, b) = range
+                            .split_once('-')
+                            .ok_or(format!("blackout: expected start-end, got `{range}`"))?;
+                        let hour = |h: &str| match h.trim().parse::<u32>() {
+                            Ok(h) if h <= 24 => Ok(h % 24),
+                            _ => Err(format!("blackout: bad hour `{h}`")),
+                        };
+                        let (start, end) = (hour(a)?, hour(b)?);
+                        if start == end {
+                            return Err(format!("blackout: empty range `{range}`"));
+                        }
+                        f.blackout_hours.push((start, end));
+                    }
+                }
+                other => return Err(format!("unknown filter `{other}`")),
+            }
+        }
+        Ok(f)
+    }
+}
+
+/// Parse a whole `SYMBOL_FILTERS` value, keyed by normalised symbol
+pub fn parse_filters(spec: &str) -> Result<HashMap<String, SymbolFilter>, String> {
+    let mut out = HashMap::new();
+    for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (sym, rules) = entry
+            .split_once(':')
+            .ok_or(format!("expected SYMBOL:rules, got `{entry}`"))?;
+        let sym = sym.trim();
+        let key = if sym == "*" {
+            "*".to_string()
+        } else {
+            bus_symbol(sym)
+        };
+        let filter = rules.parse().map_err(|e| format!("{sym}: {e}"))?;
+        out.insert(key, filter);
+    }
+    Ok(out)
+}
+
+// ─── Live stats ───────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    bid: f64,
+    ask: f64,
+    at: Instant,
+}
+
+#[derive(Default)]
+pub struct LiquidityBook {
+    quotes: DashMap<String, Quote>,
+    /// 1 h candles `(close ts, volume)`, oldest first
+    volumes: DashMap<String, VecDeque<(DateTime<Utc>, f64)>>,
+}
+
+impl LiquidityBook {
+    pub fn record_quote(&self, symbol: &str, bid: f64, ask: f64) {
+        if bid.is_finite() && ask.is_finite() && bid > 0.0 && ask >= bid {
+            self.quotes.insert(
+                bus_symbol(symbol),
+                Quote {
+                    bid,
+                    ask,
+                    at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Feed a 1 h candle (in-progress updates share the close ts)
+    pub fn record_candle(&self, symbol: &str, candle: &Candle) {
+        let mut q = self.volumes.entry(bus_symbol(symbol)).or_default();
+        // a later update for the same hour replaces the earlier one
+        if q.back().is_some_and(|(ts, _)| *ts == candle.ts) {
+            q.pop_back();
+        }
+        q.push_back((candle.ts, candle.volume));
+        let cutoff = candle.ts - chrono::Duration::hours(VOLUME_WINDOW_HOURS);
+        while q.front().is_some_and(|(ts, _)| *ts <= cutoff) {
+            q.pop_front();
+        }
+    }
+
+    pub fn spread_bps(&self, symbol: &str) -> Option<f64> {
+        let q = *self.quotes.get(&bus_symbol(symbol))?;
+        if q.at.elapsed() > QUOTE_MAX_AGE {
+            return None;
+        }
+        let mid = (q.bid + q.ask) / 2.0;
+        Some((q.ask - q.bid) / mid * 10_000.0)
+    }
+
+    /// Rolling 24 h volume, scaled up while fewer than 24 candles are held
+    pub fn volume_24h(&self, symbol: &str) -> Option<f64> {
+        let q = self.volumes.get(&bus_symbol(symbol))?;
+        if q.is_empty() {
+            return None;
+        }
+        let sum: f64 = q.iter().map(|(_, v)| v).sum();
+        Some(sum * VOLUME_WINDOW_HOURS as f64 / q.len().min(24) as f64)
+    }
+
+    /// `Err(reason)` when an entry on `symbol` must be refused at `now`
+    pub fn check(
+        &self,
+        filter: &SymbolFilter,
+        symbol: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), String> {
+        if filter.in_blackout(now.hour()) {
+            return Err(format!(
+                "{symbol} is in a blackout window at {:02}:00 UTC",
+                now.hour()
+            ));
+        }
+        if let Some(max) = filter.max_spread_bps {
+            match self.spread_bps(symbol) {
+                Some(s) if s <= max => {}
+                Some(s) => return Err(format!("{symbol} spread {s:.1} bps exceeds {max:.1} bps")),
+                None => return Err(format!("{symbol} has no live quote")),
+            }
+        }
+        if let Some(min) = filter.min_volume_24h {
+            match self.volume_24h(symbol) {
+                Some(v) if v >= min => {}
+                Some(v) => return Err(format!("{symbol} 24h volume {v:.2} below {min:.2}")),
+                None => return Err(format!("{symbol} has no volume data")),
+            }
+        }
+        Ok(())
+    }
+}
+
+// ─── Global state ─────────────────────────────────────────────────────────
+static BOOK: Lazy<LiquidityBook> = Lazy::new(LiquidityBook::default);
+static FILTERS: OnceCell<HashMap<String, SymbolFilter>> = OnceCell::new();
+
+/// Install the parsed `SYMBOL_FILTERS` (call once from `main`)
+pub fn init(filters: HashMap<String, SymbolFilter>) {
+    let _ = FILTERS.set(filters);
+}
+
+pub fn record_quote(symbol: &str, bid: f64, ask: f64) {
+    BOOK.record_quote(symbol, bid, ask);
+}
+
+pub fn record_candle(symbol: &str, candle: &Candle) {
+    BOOK.record_candle(symbol, candle);
+}
+
+/// Filter for `symbol`: its own entry, else `*`, else none
+fn filter_for(symbol: &str) -> Option<&'static SymbolFilter> {
+    let filters = FILTERS.get()?;
+    filters
+        .get(&bus_symbol(symbol))
+        .or_else(|| filters.get("*"))
+}
+
+pub fn check_entry(symbol: &str) -> Result<(), String> {
+    let Some(filter) = filter_for(symbol) else {
+        return Ok(());
+    };
+    BOOK.check(filter, symbol, Utc::now()).inspect_err(|_| {
+        increment_counter!("risk_liquidity_rejections_total", "symbol" => bus_symbol(symbol));
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 7, 21, hour, 15, 0).unwrap()
+    }
+
+    fn candle(ts: DateTime<Utc>, volume: f64) -> Candle {
+        Candle {
+            ts,
+            volume,
+            ..Candle::default()
+        }
+    }
+
+    #[test]
+    fn parses_filter_spec() {
+        let f = parse_filters(
+            "BTC-USDT:min_vol=500,max_spread_bps=8,blackout=22-2/12-13; *:max_spread_bps=20",
+        )
+        .unwrap();
+        let btc = &f["BTCUSDT"];
+        assert_eq!(btc.min_volume_24h, Some(500.0));
+        assert_eq!(btc.max_spread_bps, Some(8.0));
+        assert_eq!(btc.blackout_hours, vec![(22, 2), (12, 13)]);
+        assert_eq!(f["*"].max_spread_bps, Some(20.0));
+
+        assert!(parse_filters("BTC-USDT:min_vol=-1").is_err());
+        assert!(parse_filters("BTC-USDT:blackout=25-3").is_err());
+        assert!(parse_filters("BTC-USDT:blackout=4-4").is_err());
+        assert!(parse_filters("BTC-USDT:depth=3").is_err());
+        assert!(parse_filters("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn blackout_ranges_wrap_midnight() {
+        let f: SymbolFilter = "blackout=22-2/12-13".parse().unwrap();
+        assert!(f.in_blackout(23));
+        assert!(f.in_blackout(0));
+        assert!(f.in_blackout(1));
+        assert!(!f.in_blackout(2));
+        assert!(f.in_blackout(12));
+        assert!(!f.in_blackout(13));
+        let f: SymbolFilter = "blackout=20-24".parse().unwrap();
+        assert!(f.in_blackout(23));
+        assert!(!f.in_blackout(0));
+    }
+
+    #[test]
+    fn spread_filter_uses_live_quote() {
+        let book = LiquidityBook::default();
+        let f: SymbolFilter = "max_spread_bps=5".parse().unwrap();
+        assert!(book
+            .check(&f, "BTC-USDT", at(10))
+            .unwrap_err()
+            .contains("no live quote"));
+
+        book.record_quote("BTC-USDT-SWAP", 99.99, 100.01); // 2 bps
+        assert!(book.check(&f, "btcusdt", at(10)).is_ok());
+
+        book.record_quote("BTC-USDT-SWAP", 99.9, 100.1); // 20 bps
+        assert!(book
+            .check(&f, "BTC-USDT", at(10))
+            .unwrap_err()
+            .contains("spread"));
+    }
+
+    #[test]
+    fn volume_window_rolls_and_extrapolates() {
+        let book = LiquidityBook::default();
+        // 6 hours at 10/h → 240 extrapolated
+        for h in 0..6 {
+            book.record_candle("ETH-USDT", &candle(at(h), 10.0));
+        }
+        assert_eq!(book.volume_24h("ETH-USDT"), Some(240.0));
+
+        // an update for the last hour replaces, doesn't add
+        book.record_candle("ETH-USDT", &candle(at(5), 10.0));
+        assert_eq!(book.volume_24h("ETH-USDT"), Some(240.0));
+
+        // a day later the early hours roll out of the window
+        let next = at(5) + chrono::Duration::hours(24);
+        book.record_candle("ETH-USDT", &candle(next, 34.0));
+        assert_eq!(book.volume_24h("ETH-USDT"), Some(24.0 * 34.0));
+
+        let f: SymbolFilter = "min_vol=1000".parse().unwrap();
+        assert!(book.check(&f, "ETH-USDT", at(10)).is_err());
+        assert!(book
+            .check(&f, "SOL-USDT", at(10))
+            .unwrap_err()
+            .contains("no volume"));
+    }
+
+    #[test]
+    fn blackout_wins_over_good_book() {
+        let book = LiquidityBook::default();
+        book.record_quote("BTC-USDT", 100.0, 100.0);
+        let f: SymbolFilter = "max_spread_bps=5,blackout=9-11".parse().unwrap();
+        assert!(book
+            .check(&f, "BTC-USDT", at(10))
+            .unwrap_err()
+            .contains("blackout"));
+        assert!(book.check(&f, "BTC-USDT", at(11)).is_ok());
+    }
+}
@@ -1,52 +1,458 @@
 //! Centralised market‑data fan‑out for **all** real‑time strategies.
 //! -----------------------------------------------------------------
 //! ‣ Keeps WebSocket code in *one* place (separation of concerns).
-//! ‣ Publishes `Candle` & `OrderBookSnapshot` streams via `tokio::broadcast`.
+//! ‣ Publishes `Candle` & `OrderBookSnapshot` streams via `tokio::broadcast`,
+//!   one topic per symbol (and interval); symbols are canonical (`BTCUSDT`).
+//! ‣ Candles are closed bars only, published on the clock by `bar_clock`
+//!   once their boundary plus `CANDLE_CLOSE_GRACE_MS` has passed.
+//! ‣ Streams follow the symbols of enabled `user_strategies` (plus
+//!   `BASE_SYMBOLS`) and reconnect when that set changes.
 //! ‣ Agnostic to exchange – add new connectors behind `spawn_*_feed()`.
+//! ‣ Watchlisted symbols get a book-ticker stream that keeps mids and
+//!   spreads fresh (`spawn_watchlist_feed`).
+//! ‣ Mark and index prices of the same symbols (Binance USDⓈ-M perpetuals)
+//!   go out on `all_marks`; [`mark_price`] is the latest one, which PnL and
+//!   liquidation distance are measured against.
+//! ‣ Funding rates predicted for the next settlement come off the same
+//!   Binance stream and a BlowFin poll (`FUNDING_POLL`) and go out on
+//!   `all_funding`; [`funding_rate`] is the latest one, BlowFin's first –
+//!   that's where orders go.
+//! ‣ Each feed reports whether it is connected ([`feeds`]), for the
+//!   public status page.
+//! ‣ The newest bar per topic and the last book per symbol are remembered
+//!   ([`MarketBus::freshness`]), so the scheduler can tell a strategy that
+//!   stopped keeping up from a quiet feed.
+//! ‣ Strategies publish advisory `StrategySignal`s (a regime call, a zone
+//!   touch, …) per symbol for other strategies to compose on; a signal is
+//!   never an order.
 //!
 //! Usage from a strategy task:
 //! ```ignore
-//! let mut rx_4h = bus.candles_4h.subscribe();
+//! let mut rx_4h = bus.candles(&cfg.symbol, "4h");
 //! while let Ok(candle) = rx_4h.recv().await { /* feed engine */ }
 //! ```
 //! -----------------------------------------------------------------
 
+use dashmap::DashMap;
+use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::sync::broadcast::{self, Sender};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, Receiver, Sender};
 // use tokio_stream::wrappers::BroadcastStream;
 use chrono::{DateTime, Utc};
 use futures_util::StreamExt;
 use serde::Deserialize;
 // use rust_decimal::Decimal;
 
-use crate::services::strategies::{Candle, OrderBookSnapshot};
+use crate::services::bar_clock::{self, BarClock};
+use crate::services::blowfin::ws::DepthFrame;
+use crate::services::strategies::{indicators, Candle, OrderBookSnapshot};
+use crate::services::trading_engine::Exchange;
+use crate::services::{analytics, arbitrage, drain, liquidity, watchlist};
 use crate::utils::signature::verify_hmac_bytes;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use uuid::Uuid;
 
 const CAPACITY: usize = 256; // ring‑buffer per topic
+/// Re-read the watchlist this often (edits made on other instances)
+const WATCHLIST_REFRESH: Duration = Duration::from_secs(60);
+/// Stay well below Binance's 1024 streams per connection
+const MAX_TICKER_STREAMS: usize = 200;
+/// Candle intervals the feeds publish
+pub const INTERVALS: [&str; 2] = ["1h", "4h"];
+/// Streamed whether or not a strategy trades them, so recorded history and
+/// PnL marks don't gap while nobody does
+const BASE_SYMBOLS: [&str; 1] = ["BTCUSDT"];
+/// Re-read the strategy symbols this often (strategies enabled anywhere)
+const SYMBOL_REFRESH: Duration = Duration::from_secs(30);
+/// Kline streams per symbol × this stays below `MAX_TICKER_STREAMS`
+const MAX_KLINE_SYMBOLS: usize = MAX_TICKER_STREAMS / INTERVALS.len();
+/// A mark older than this (feed down) is no mark at all
+const MARK_MAX_AGE: Duration = Duration::from_secs(60);
+/// BlowFin funding is polled this often
+const FUNDING_POLL: Duration = Duration::from_secs(60);
+/// A funding rate older than this (feed down) is no rate at all
+const FUNDING_MAX_AGE: Duration = Duration::from_secs(600);
+/// Where [`funding_rate`] looks, preferred first
+const FUNDING_SOURCES: [&str; 2] = ["blowfin", "binance"];
 
-#[derive(Clone)]
+/// One candle off the bus with its topic, for consumers of every symbol
+#[derive(Debug, Clone)]
+pub struct BusCandle {
+    pub symbol: String,
+    pub interval: &'static str,
+    pub candle: Candle,
+}
+
+/// One depth snapshot off the bus with its symbol
+#[derive(Debug, Clone)]
+pub struct BusBook {
+    pub symbol: String,
+    pub book: OrderBookSnapshot,
+}
+
+/// Mark and index price of one perpetual
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MarkPrice {
+    pub mark: f64,
+    pub index: Option<f64>,
+    pub ts: DateTime<Utc>,
+}
+
+/// One mark update off the bus with its symbol
+#[derive(Debug, Clone)]
+pub struct BusMark {
+    pub symbol: String,
+    pub mark: MarkPrice,
+}
+
+/// Predicted funding of one perpetual at its next settlement
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FundingRate {
+    pub exchange: &'static str,
+    /// Per settlement, as a fraction (`0.0001` = 1 bp); longs pay shorts
+    /// when positive
+    pub rate: f64,
+    pub next_funding: DateTime<Utc>,
+    pub ts: DateTime<Utc>,
+}
+
+/// One funding update off the bus with its symbol
+#[derive(Debug, Clone)]
+pub struct BusFunding {
+    pub symbol: String,
+    pub funding: FundingRate,
+}
+
+/// Feed name [`MarketBus::freshness`] knows depth snapshots by
+pub const BOOK_FEED: &str = "book";
+
+/// Newest data of one topic: its own timestamp and when it came off the
+/// bus (the same for books)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Freshness {
+    pub ts: DateTime<Utc>,
+    pub received: DateTime<Utc>,
+}
+
+/// Market regime as a strategy reads it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Regime {
+    TrendingUp,
+    TrendingDown,
+    Ranging,
+}
+
+/// What a strategy is telling the others
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SignalKind {
+    Regime {
+        regime: Regime,
+    },
+    /// A bar traded through a high-volume demand zone
+    ZoneTouched {
+        price: f64,
+        width: f64,
+    },
+    /// Anything without a variant of its own yet
+    Custom {
+        name: String,
+        value: serde_json::Value,
+    },
+}
+
+/// One advisory event off the bus; consumers decide what, if anything, it
+/// changes for them (and whose – `user_id` – they listen to)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrategySignal {
+    /// Strategy type that published it (`vcsr`, `trend_follow`, …)
+    pub source: &'static str,
+    pub strategy_id: Uuid,
+    pub user_id: i64,
+    pub symbol: String,
+    pub ts: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: SignalKind,
+}
+
+/// Latest mark per canonical symbol, whichever bus published it
+static MARKS: Lazy<DashMap<String, MarkPrice>> = Lazy::new(DashMap::new);
+
+/// Latest funding per (canonical symbol, exchange)
+static FUNDING: Lazy<DashMap<(String, &'static str), FundingRate>> = Lazy::new(DashMap::new);
+
+/// Whether each feed started on this instance holds a live connection
+static FEEDS: Lazy<DashMap<&'static str, bool>> = Lazy::new(DashMap::new);
+
+/// List a feed as down until it connects
+fn track_feed(name: &'static str) {
+    FEEDS.entry(name).or_insert(false);
+}
+
+/// Marks a feed connected for as long as it's alive
+struct FeedUp(&'static str);
+
+impl FeedUp {
+    fn new(name: &'static str) -> Self {
+        FEEDS.insert(name, true);
+        Self(name)
+    }
+}
+
+impl Drop for FeedUp {
+    fn drop(&mut self) {
+        FEEDS.insert(self.0, false);
+    }
+}
+
+/// (feed, connected) of every feed started on this instance, by name
+pub fn feeds() -> Vec<(&'static str, bool)> {
+    let mut out: Vec<_> = FEEDS.iter().map(|f| (*f.key(), *f.value())).collect();
+    out.sort();
+    out
+}
+
+/// Latest mark of `symbol` (any spelling); `None` once the feed has been
+/// quiet for `MARK_MAX_AGE`
+pub fn mark_price(symbol: &str) -> Option<MarkPrice> {
+    let m = *MARKS.get(&bus_symbol(symbol))?;
+    let age = (Utc::now() - m.ts).to_std().unwrap_or_default();
+    (age <= MARK_MAX_AGE).then_some(m)
+}
+
+/// Latest funding of `symbol` (any spelling), BlowFin's before Binance's;
+/// `None` once both have been quiet for `FUNDING_MAX_AGE`
+pub fn funding_rate(symbol: &str) -> Option<FundingRate> {
+    let symbol = bus_symbol(symbol);
+    let now = Utc::now();
+    FUNDING_SOURCES.into_iter().find_map(|exchange| {
+        let f = *FUNDING.get(&(symbol.clone(), exchange))?;
+        let age = (now - f.ts).to_std().unwrap_or_default();
+        (age <= FUNDING_MAX_AGE).then_some(f)
+    })
+}
+
+/// Topics are created on first subscribe or publish; cloning shares them
+#[derive(Clone, Default)]
 pub struct MarketBus {
-    pub candles_1h: Sender<Candle>,
-    pub candles_4h: Sender<Candle>,
-    pub order_book: Sender<OrderBookSnapshot>,
+    topics: Arc<Topics>,
 }
 
-impl MarketBus {
-    pub fn new() -> Self {
-        let (c1h, _) = broadcast::channel(CAPACITY);
-        let (c4h, _) = broadcast::channel(CAPACITY);
-        let (ob, _) = broadcast::channel(CAPACITY);
+struct Topics {
+    candles: DashMap<(String, &'static str), Sender<Candle>>,
+    books: DashMap<String, Sender<OrderBookSnapshot>>,
+    signals: DashMap<String, Sender<StrategySignal>>,
+    /// (symbol, interval or `BOOK_FEED`) → newest data published
+    latest: DashMap<(String, &'static str), Freshness>,
+    all_candles: Sender<BusCandle>,
+    all_books: Sender<BusBook>,
+    all_marks: Sender<BusMark>,
+    all_funding: Sender<BusFunding>,
+    all_signals: Sender<StrategySignal>,
+}
+
+impl Default for Topics {
+    fn default() -> Self {
         Self {
-            candles_1h: c1h,
-            candles_4h: c4h,
-            order_book: ob,
+            candles: DashMap::new(),
+            books: DashMap::new(),
+            signals: DashMap::new(),
+            latest: DashMap::new(),
+            all_candles: broadcast::channel(CAPACITY).0,
+            all_books: broadcast::channel(CAPACITY).0,
+            all_marks: broadcast::channel(CAPACITY).0,
+            all_funding: broadcast::channel(CAPACITY).0,
+            all_signals: broadcast::channel(CAPACITY).0,
         }
     }
 }
 
-impl Default for MarketBus {
-    fn default() -> Self {
-        Self::new()
+/// "BTC-USDT-SWAP", "btcusdt", "BTC-USDT" → "BTCUSDT"
+pub fn bus_symbol(sym: &str) -> String {
+    let s = sym.to_ascii_uppercase().replace(['-', '_', '/'], "");
+    s.strip_suffix("SWAP").map(str::to_owned).unwrap_or(s)
+}
+
+/// The `INTERVALS` entry spelled `interval`
+fn bus_interval(interval: &str) -> Option<&'static str> {
+    INTERVALS.into_iter().find(|i| *i == interval)
+}
+
+impl MarketBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Candles of `symbol` (any spelling) at `interval` (`1h`, `4h`); an
+    /// interval no feed publishes simply never yields
+    pub fn candles(&self, symbol: &str, interval: &str) -> Receiver<Candle> {
+        let interval = bus_interval(interval).unwrap_or("");
+        self.candle_topic(&bus_symbol(symbol), interval).subscribe()
+    }
+
+    /// Every candle of every symbol
+    pub fn all_candles(&self) -> Receiver<BusCandle> {
+        self.topics.all_candles.subscribe()
+    }
+
+    /// Every depth snapshot of every symbol
+    pub fn all_books(&self) -> Receiver<BusBook> {
+        self.topics.all_books.subscribe()
+    }
+
+    /// Every mark / index update of every symbol
+    pub fn all_marks(&self) -> Receiver<BusMark> {
+        self.topics.all_marks.subscribe()
+    }
+
+    /// Every funding update of every symbol and exchange
+    pub fn all_funding(&self) -> Receiver<BusFunding> {
+        self.topics.all_funding.subscribe()
+    }
+
+    /// Depth snapshots of `symbol` (any spelling)
+    pub fn order_book(&self, symbol: &str) -> Receiver<OrderBookSnapshot> {
+        self.book_topic(&bus_symbol(symbol)).subscribe()
+    }
+
+    /// Strategy signals about `symbol` (any spelling)
+    pub fn signals(&self, symbol: &str) -> Receiver<StrategySignal> {
+        self.signal_topic(&bus_symbol(symbol)).subscribe()
+    }
+
+    /// Every strategy signal of every symbol
+    pub fn all_signals(&self) -> Receiver<StrategySignal> {
+        self.topics.all_signals.subscribe()
+    }
+
+    /// Newest candle of `symbol` (any spelling) at `interval`, or last book
+    /// for `BOOK_FEED`, published on this instance
+    pub fn freshness(&self, symbol: &str, feed: &str) -> Option<Freshness> {
+        let feed = bus_interval(feed).or((feed == BOOK_FEED).then_some(BOOK_FEED))?;
+        self.topics
+            .latest
+            .get(&(bus_symbol(symbol), feed))
+            .map(|f| *f)
+    }
+
+    pub fn publish_candle(&self, symbol: &str, interval: &'static str, candle: Candle) {
+        let symbol = bus_symbol(symbol);
+        let fresh = Freshness {
+            ts: candle.ts,
+            received: Utc::now(),
+        };
+        self.topics.latest.insert((symbol.clone(), interval), fresh);
+        // no subscribers is not an error
+        let _ = self.candle_topic(&symbol, interval).send(candle);
+        let _ = self.topics.all_candles.send(BusCandle {
+            symbol,
+            interval,
+            candle,
+        });
+    }
+
+    pub fn publish_book(&self, symbol: &str, book: OrderBookSnapshot) {
+        let symbol = bus_symbol(symbol);
+        let now = Utc::now();
+        let fresh = Freshness {
+            ts: now,
+            received: now,
+        };
+        self.topics
+            .latest
+            .insert((symbol.clone(), BOOK_FEED), fresh);
+        let _ = self.book_topic(&symbol).send(book);
+        let _ = self.topics.all_books.send(BusBook { symbol, book });
+    }
+
+    pub fn publish_mark(&self, symbol: &str, mark: MarkPrice) {
+        let symbol = bus_symbol(symbol);
+        MARKS.insert(symbol.clone(), mark);
+        let _ = self.topics.all_marks.send(BusMark { symbol, mark });
+    }
+
+    pub fn publish_funding(&self, symbol: &str, funding: FundingRate) {
+        let symbol = bus_symbol(symbol);
+        FUNDING.insert((symbol.clone(), funding.exchange), funding);
+        let _ = self.topics.all_funding.send(BusFunding { symbol, funding });
+    }
+
+    pub fn publish_signal(&self, mut signal: StrategySignal) {
+        signal.symbol = bus_symbol(&signal.symbol);
+        let _ = self.signal_topic(&signal.symbol).send(signal.clone());
+        let _ = self.topics.all_signals.send(signal);
+    }
+
+    fn candle_topic(&self, symbol: &str, interval: &'static str) -> Sender<Candle> {
+        self.topics
+            .candles
+            .entry((symbol.to_string(), interval))
+            .or_insert_with(|| broadcast::channel(CAPACITY).0)
+            .clone()
+    }
+
+    fn book_topic(&self, symbol: &str) -> Sender<OrderBookSnapshot> {
+        self.topics
+            .books
+            .entry(symbol.to_string())
+            .or_insert_with(|| broadcast::channel(CAPACITY).0)
+            .clone()
+    }
+
+    fn signal_topic(&self, symbol: &str) -> Sender<StrategySignal> {
+        self.topics
+            .signals
+            .entry(symbol.to_string())
+            .or_insert_with(|| broadcast::channel(CAPACITY).0)
+            .clone()
+    }
+}
+
+/// Canonical, sorted, de-duplicated feed symbols: `BASE_SYMBOLS` plus `raw`
+fn feed_symbols(raw: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut out: Vec<String> = raw
+        .into_iter()
+        .map(|s| bus_symbol(&s))
+        .chain(BASE_SYMBOLS.iter().map(|s| s.to_string()))
+        .filter(|s| !s.is_empty())
+        .collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Symbols the feeds stream: whatever an enabled strategy trades (the row's
+/// column or its params' `symbol`), every enabled arbitrage watch plus
+/// `BASE_SYMBOLS`
+pub async fn strategy_symbols(pg: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let raw: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT symbol FROM user_strategies WHERE status = 'enabled'
+        UNION
+        SELECT params->>'symbol' FROM user_strategies
+         WHERE status = 'enabled' AND params->>'symbol' IS NOT NULL
+        UNION
+        SELECT symbol FROM arbitrage_settings WHERE enabled
+        "#,
+    )
+    .fetch_all(pg)
+    .await?;
+    Ok(feed_symbols(raw))
+}
+
+/// `strategy_symbols`, retrying until the database answers
+async fn load_symbols(pg: &PgPool, feed: &str) -> Vec<String> {
+    loop {
+        match strategy_symbols(pg).await {
+            Ok(s) => return s,
+            Err(e) => {
+                tracing::warn!("{feed} feed: load symbols: {e}");
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
     }
 }
 
@@ -98,11 +504,11 @@ fn frame_ok(sec: &FeedSecurity, headers_or_json: &str, body: &[u8]) -> bool {
                 if verify_hmac_bytes(body, &secret, sig_hex) {
                     true
                 } else {
-                    log::warn!("feed frame failed HMAC check ({header})");
+                    tracing::warn!("feed frame failed HMAC check ({header})");
                     false
                 }
             } else {
-                log::warn!("feed frame missing signature header/field ({header})");
+                tracing::warn!("feed frame missing signature header/field ({header})");
                 false
             }
         }
@@ -113,63 +519,311 @@ fn frame_ok(sec: &FeedSecurity, headers_or_json: &str, body: &[u8]) -> bool {
 // Exchange connectors – each spawns its own task & forwards to bus
 // ================================================================
 
-pub async fn spawn_all_feeds(settings: &crate::config::settings::Settings) -> Arc<MarketBus> {
+pub async fn spawn_all_feeds(
+    settings: &crate::config::settings::Settings,
+    pg: PgPool,
+) -> Arc<MarketBus> {
     let bus = Arc::new(MarketBus::new());
+    let clock = Arc::new(BarClock::new(Duration::from_millis(
+        settings.candle_close_grace_ms,
+    )));
+    tokio::spawn(bar_clock::run(Arc::clone(&clock), Arc::clone(&bus)));
 
     // Binance – unsigned public stream
-    tokio::spawn(binance_feed(Arc::clone(&bus), FeedSecurity::None));
+    tokio::spawn(binance_feed(pg.clone(), clock, FeedSecurity::None));
+    tokio::spawn(binance_mark_feed(pg.clone(), Arc::clone(&bus)));
+
+    // each registered exchange adapter brings its own streams
+    for spec in crate::services::exchanges::registered() {
+        (spec.spawn_feeds)(settings, pg.clone(), Arc::clone(&bus));
+    }
+
+    bus
+}
 
-    // BlowFin private depth feed – also unsigned
+/// BlowFin private depth feed – also unsigned
+pub(crate) fn spawn_blowfin_feeds(
+    settings: &crate::config::settings::Settings,
+    pg: PgPool,
+    bus: Arc<MarketBus>,
+) {
     tokio::spawn(blowfin_depth_feed(
         settings.clone(),
+        pg.clone(),
         Arc::clone(&bus),
         FeedSecurity::None,
     ));
+    tokio::spawn(blowfin_funding_feed(settings.is_demo(), pg, bus));
+}
 
-    bus
+/// Binance USDⓈ-M book tickers – unsigned public stream; the kline and mark
+/// feeds run for every deployment regardless
+pub(crate) fn spawn_binance_feeds(
+    _settings: &crate::config::settings::Settings,
+    pg: PgPool,
+    _bus: Arc<MarketBus>,
+) {
+    tokio::spawn(binance_book_feed(pg));
 }
 
 /* ─────────────────────────────────────────  Binance WS ────── */
 
-async fn binance_feed(bus: Arc<MarketBus>, sec: FeedSecurity) {
+/// Combined kline stream: "BTCUSDT" → `btcusdt@kline_1h/btcusdt@kline_4h`
+fn kline_url(symbols: &[String]) -> String {
+    let streams: Vec<String> = symbols
+        .iter()
+        .take(MAX_KLINE_SYMBOLS)
+        .flat_map(|s| {
+            let s = s.to_ascii_lowercase();
+            INTERVALS.map(|i| format!("{s}@kline_{i}"))
+        })
+        .collect();
+    format!(
+        "wss://stream.binance.com:9443/stream?streams={}",
+        streams.join("/")
+    )
+}
+
+/// Hand one combined-stream kline frame to the bar clock
+fn on_kline(clock: &BarClock, txt: &str) {
+    let Ok(ev) = serde_json::from_str::<BinanceStreamEvent>(txt) else {
+        return;
+    };
+    let Some(k) = ev.data.kline else {
+        return;
+    };
+    let Some(interval) = bus_interval(&k.interval) else {
+        return;
+    };
+    let candle = Candle {
+        ts: DateTime::<Utc>::from_timestamp_millis(k.close_time as i64).unwrap(),
+        open: k.open(),
+        high: k.high(),
+        low: k.low(),
+        close: k.close(),
+        volume: k.volume(),
+        delta: None,
+    };
+    // `T` is the bar's last millisecond
+    let close = candle.ts + chrono::Duration::milliseconds(1);
+    clock.update(&bus_symbol(&k.symbol), interval, close, candle);
+}
+
+/// One kline connection for every strategy symbol; reconnects whenever
+/// that set changes
+async fn binance_feed(pg: PgPool, clock: Arc<BarClock>, sec: FeedSecurity) {
     use tokio_tungstenite::connect_async;
     use tungstenite::Message;
 
-    let url = "wss://stream.binance.com:9443/stream?streams=btcusdt@kline_1h/btcusdt@kline_4h";
-    let (mut ws, _) = match connect_async(url).await {
-        Ok(t) => t,
-        Err(e) => {
-            log::error!("binance ws connect: {e}");
-            return;
+    track_feed("binance:klines");
+    loop {
+        let symbols = load_symbols(&pg, "binance").await;
+        metrics::gauge!("market_feed_symbols", symbols.len() as f64);
+        if symbols.len() > MAX_KLINE_SYMBOLS {
+            tracing::warn!(
+                "binance feed: {} symbols, streaming the first {MAX_KLINE_SYMBOLS}",
+                symbols.len()
+            );
         }
-    };
 
-    while let Some(Ok(msg)) = ws.next().await {
-        if let Message::Text(txt) = &msg {
-            if !frame_ok(&sec, txt, txt.as_bytes()) {
+        let (mut ws, _) = match connect_async(kline_url(&symbols)).await {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::error!("binance ws connect: {e}");
+                tokio::time::sleep(Duration::from_secs(5)).await;
                 continue;
             }
+        };
+        let _up = FeedUp::new("binance:klines");
 
-            if let Ok(ev) = serde_json::from_str::<BinanceStreamEvent>(txt) {
-                if let Some(k) = ev.data.kline {
-                    let candle = Candle {
-                        ts: DateTime::<Utc>::from_timestamp_millis(k.close_time as i64).unwrap(),
-                        open: k.open(),
-                        high: k.high(),
-                        low: k.low(),
-                        close: k.close(),
-                        volume: k.volume(),
-                        delta: None,
-                    };
-                    match k.interval.as_str() {
-                        "1h" => {
-                            let _ = bus.candles_1h.send(candle);
+        let mut refresh = tokio::time::interval(SYMBOL_REFRESH);
+        refresh.tick().await;
+        loop {
+            tokio::select! {
+                msg = ws.next() => match msg {
+                    Some(Ok(Message::Text(txt))) => {
+                        if frame_ok(&sec, &txt, txt.as_bytes()) {
+                            on_kline(&clock, &txt);
                         }
-                        "4h" => {
-                            let _ = bus.candles_4h.send(candle);
-                        }
-                        _ => {}
-                    };
+                    }
+                    Some(Ok(_)) => {}
+                    _ => {
+                        tracing::warn!("binance feed: stream closed, reconnecting");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        break;
+                    }
+                },
+                _ = refresh.tick() => {
+                    if strategy_symbols(&pg).await.is_ok_and(|s| s != symbols) {
+                        break;
+                    }
+                }
+                _ = drain::stopping() => {
+                    let _ = ws.close(None).await;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Combined mark-price stream: "BTCUSDT" → `btcusdt@markPrice@1s`
+fn mark_url(symbols: &[String]) -> String {
+    let streams: Vec<String> = symbols
+        .iter()
+        .take(MAX_TICKER_STREAMS)
+        .map(|s| format!("{}@markPrice@1s", s.to_ascii_lowercase()))
+        .collect();
+    format!(
+        "wss://fstream.binance.com/stream?streams={}",
+        streams.join("/")
+    )
+}
+
+/// Publish one combined-stream mark-price frame
+fn on_mark(bus: &MarketBus, txt: &str) {
+    let Ok(ev) = serde_json::from_str::<BinanceMarkEvent>(txt) else {
+        return;
+    };
+    let m = ev.data;
+    let (Ok(mark), Some(ts)) = (
+        m.mark.parse::<f64>(),
+        DateTime::<Utc>::from_timestamp_millis(m.event_time as i64),
+    ) else {
+        return;
+    };
+    if !(mark.is_finite() && mark > 0.0) {
+        return;
+    }
+    let index = m.index.parse::<f64>().ok().filter(|i| *i > 0.0);
+    bus.publish_mark(&m.symbol, MarkPrice { mark, index, ts });
+
+    // delivery contracts carry no funding: `r` empty, `T` 0
+    let rate = m.funding_rate.parse::<f64>().ok().filter(|r| r.is_finite());
+    let next = DateTime::<Utc>::from_timestamp_millis(m.next_funding_time as i64)
+        .filter(|_| m.next_funding_time > 0);
+    if let (Some(rate), Some(next_funding)) = (rate, next) {
+        let funding = FundingRate {
+            exchange: "binance",
+            rate,
+            next_funding,
+            ts,
+        };
+        bus.publish_funding(&m.symbol, funding);
+    }
+}
+
+/// Mark / index prices for every strategy symbol; reconnects whenever that
+/// set changes
+async fn binance_mark_feed(pg: PgPool, bus: Arc<MarketBus>) {
+    use tokio_tungstenite::connect_async;
+    use tungstenite::Message;
+
+    track_feed("binance:marks");
+    loop {
+        let symbols = load_symbols(&pg, "binance marks").await;
+        let (mut ws, _) = match connect_async(mark_url(&symbols)).await {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::error!("binance mark ws connect: {e}");
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        let _up = FeedUp::new("binance:marks");
+
+        let mut refresh = tokio::time::interval(SYMBOL_REFRESH);
+        refresh.tick().await;
+        loop {
+            tokio::select! {
+                msg = ws.next() => match msg {
+                    Some(Ok(Message::Text(txt))) => on_mark(&bus, &txt),
+                    Some(Ok(_)) => {}
+                    _ => {
+                        tracing::warn!("binance mark feed: stream closed, reconnecting");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        break;
+                    }
+                },
+                _ = refresh.tick() => {
+                    if strategy_symbols(&pg).await.is_ok_and(|s| s != symbols) {
+                        break;
+                    }
+                }
+                _ = drain::stopping() => {
+                    let _ = ws.close(None).await;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Futures book tickers: "BTCUSDT" → `btcusdt@bookTicker` on fstream
+fn futures_ticker_url(symbols: &[String]) -> String {
+    let streams: Vec<String> = symbols
+        .iter()
+        .take(MAX_TICKER_STREAMS)
+        .map(|s| ticker_stream(s))
+        .collect();
+    format!(
+        "wss://fstream.binance.com/stream?streams={}",
+        streams.join("/")
+    )
+}
+
+/// Hand one futures book-ticker frame to the arbitrage monitor
+fn on_futures_ticker(txt: &str) {
+    if let Ok(ev) = serde_json::from_str::<BinanceTickerEvent>(txt) {
+        let t = ev.data;
+        let (bid, ask) = (
+            BinanceKline::parse_f64(&t.bid),
+            BinanceKline::parse_f64(&t.ask),
+        );
+        arbitrage::record_quote(Exchange::Binance, &t.symbol, bid, ask);
+    }
+}
+
+/// USDⓈ-M top of book for every strategy symbol; reconnects whenever that
+/// set changes
+async fn binance_book_feed(pg: PgPool) {
+    use tokio_tungstenite::connect_async;
+    use tungstenite::Message;
+
+    track_feed("binance:books");
+    loop {
+        let symbols = load_symbols(&pg, "binance books").await;
+        let (mut ws, _) = match connect_async(futures_ticker_url(&symbols)).await {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::error!("binance book ws connect: {e}");
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        let _up = FeedUp::new("binance:books");
+
+        let mut refresh = tokio::time::interval(SYMBOL_REFRESH);
+        refresh.tick().await;
+        loop {
+            tokio::select! {
+                msg = ws.next() => match msg {
+                    Some(Ok(Message::Text(txt))) => on_futures_ticker(&txt),
+                    Some(Ok(_)) => {}
+                    _ => {
+                        tracing::warn!("binance book feed: stream closed, reconnecting");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        break;
+                    }
+                },
+                _ = refresh.tick() => {
+                    if strategy_symbols(&pg).await.is_ok_and(|s| s != symbols) {
+                        break;
+                    }
+                }
+                _ = drain::stopping() => {
+                    let _ = ws.close(None).await;
+                    return;
                 }
             }
         }
@@ -193,6 +847,8 @@ struct BinanceKlineWrapper {
 
 #[derive(Debug, Deserialize)]
 struct BinanceKline {
+    #[serde(rename = "s")]
+    symbol: String,
     #[serde(rename = "T")]
     close_time: u64,
     #[serde(rename = "i")]
@@ -230,36 +886,315 @@ impl BinanceKline {
     }
 }
 
+/* ─────────────────────────────────────────  Watchlist tickers ─ */
+
+/// "BTC-USDT" / "BTC-USDT-SWAP" → "btcusdt@bookTicker"
+fn ticker_stream(symbol: &str) -> String {
+    let s = symbol.to_ascii_lowercase().replace('-', "");
+    let s = s.strip_suffix("swap").unwrap_or(&s);
+    format!("{s}@bookTicker")
+}
+
+fn ticker_url(symbols: &[String]) -> String {
+    let streams: Vec<String> = symbols
+        .iter()
+        .take(MAX_TICKER_STREAMS)
+        .map(|s| ticker_stream(s))
+        .collect();
+    format!(
+        "wss://stream.binance.com:9443/stream?streams={}",
+        streams.join("/")
+    )
+}
+
+/// Feed one combined-stream frame into mids & the liquidity book
+fn on_book_ticker(txt: &str) {
+    if let Ok(ev) = serde_json::from_str::<BinanceTickerEvent>(txt) {
+        let t = ev.data;
+        let (bid, ask) = (
+            BinanceKline::parse_f64(&t.bid),
+            BinanceKline::parse_f64(&t.ask),
+        );
+        if bid > 0.0 && ask > 0.0 {
+            analytics::set_mid(&t.symbol, (bid + ask) / 2.0);
+            liquidity::record_quote(&t.symbol, bid, ask);
+        }
+    }
+}
+
+/// Keep one book-ticker stream open for the union of all watchlists;
+/// reconnects whenever that set changes.
+pub fn spawn_watchlist_feed(pg: PgPool) {
+    use tokio_tungstenite::connect_async;
+    use tungstenite::Message;
+
+    tokio::spawn(async move {
+        let mut edits = watchlist::subscribe();
+        loop {
+            let symbols = match watchlist::all_symbols(&pg).await {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::warn!("watchlist feed: load symbols: {e}");
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+            metrics::gauge!("watchlist_feed_symbols", symbols.len() as f64);
+            if symbols.len() > MAX_TICKER_STREAMS {
+                tracing::warn!(
+                    "watchlist feed: {} symbols, streaming the first {MAX_TICKER_STREAMS}",
+                    symbols.len()
+                );
+            }
+
+            let mut ws = if symbols.is_empty() {
+                None
+            } else {
+                match connect_async(ticker_url(&symbols)).await {
+                    Ok((ws, _)) => Some(ws),
+                    Err(e) => {
+                        tracing::error!("watchlist feed connect: {e}");
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        continue;
+                    }
+                }
+            };
+            // an empty watchlist needs no stream – nor is one down
+            let _up = match &ws {
+                Some(_) => Some(FeedUp::new("binance:tickers")),
+                None => {
+                    FEEDS.remove("binance:tickers");
+                    None
+                }
+            };
+
+            let mut refresh = tokio::time::interval(WATCHLIST_REFRESH);
+            refresh.tick().await;
+            loop {
+                tokio::select! {
+                    msg = async { ws.as_mut().expect("guarded").next().await }, if ws.is_some() => {
+                        match msg {
+                            Some(Ok(Message::Text(txt))) => on_book_ticker(&txt),
+                            Some(Ok(_)) => {}
+                            _ => {
+                                tracing::warn!("watchlist feed: stream closed, reconnecting");
+                                tokio::time::sleep(Duration::from_secs(1)).await;
+                                break;
+                            }
+                        }
+                    }
+                    _ = edits.changed() => {
+                        if watchlist::all_symbols(&pg).await.is_ok_and(|s| s != symbols) {
+                            break;
+                        }
+                    }
+                    _ = refresh.tick() => {
+                        if watchlist::all_symbols(&pg).await.is_ok_and(|s| s != symbols) {
+                            break;
+                        }
+                    }
+                    _ = drain::stopping() => {
+                        if let Some(ws) = ws.as_mut() {
+                            let _ = ws.close(None).await;
+                        }
+                        return;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceTickerEvent {
+    data: BinanceBookTicker,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceBookTicker {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bid: String,
+    #[serde(rename = "a")]
+    ask: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceMarkEvent {
+    data: BinanceMark,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceMark {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "E")]
+    event_time: u64,
+    #[serde(rename = "p")]
+    mark: String,
+    #[serde(rename = "i")]
+    index: String,
+    #[serde(rename = "r", default)]
+    funding_rate: String,
+    #[serde(rename = "T", default)]
+    next_funding_time: u64,
+}
+
 // ───────────────────────────────────────── BlowFin private depth fan-out ────
+/// "BTCUSDT" → "BTC-USDT-SWAP"
+fn blowfin_inst(symbol: &str) -> String {
+    match symbol.strip_suffix("USDT") {
+        Some(base) if !base.is_empty() => format!("{base}-USDT-SWAP"),
+        _ => symbol.to_string(),
+    }
+}
+
+/// Forward one verified frame onto the bus
+fn on_depth(bus: &MarketBus, sec: &FeedSecurity, df: DepthFrame) {
+    if !frame_ok(sec, "", &df.raw_bytes) {
+        tracing::warn!("blowfin depth: bad sig – dropped");
+        return;
+    }
+    let mut snap = OrderBookSnapshot {
+        bid_depth: df.bid_sum,
+        ask_depth: df.ask_sum,
+        ..Default::default()
+    };
+    if let (Some(bid), Some(ask)) = (df.best_bid, df.best_ask) {
+        let mid = (bid + ask) / 2.0;
+        analytics::set_mid(&df.inst_id, mid);
+        liquidity::record_quote(&df.inst_id, bid, ask);
+        arbitrage::record_quote(Exchange::Blowfin, &df.inst_id, bid, ask);
+        snap.bid_slope = indicators::depth_slope(&df.bids, mid);
+        snap.ask_slope = indicators::depth_slope(&df.asks, mid);
+        snap.best_bid = Some(bid);
+        snap.best_ask = Some(ask);
+    }
+    bus.publish_book(&df.inst_id, snap);
+}
+
+/// books5 for every strategy symbol; resubscribes whenever that set changes
 async fn blowfin_depth_feed(
     settings: crate::config::settings::Settings,
+    pg: PgPool,
     bus: Arc<MarketBus>,
     sec: FeedSecurity,
 ) {
-    use crate::services::blowfin::ws::{connect_private, DepthFrame};
+    use crate::services::blowfin::ws::connect_private;
     use tokio::sync::mpsc;
 
-    // channel between WS task ↔ market_data task
-    let (tx, mut rx) = mpsc::channel::<DepthFrame>(64);
+    track_feed("blowfin:depth");
+    loop {
+        let symbols = load_symbols(&pg, "blowfin depth").await;
+        let insts: Vec<String> = symbols.iter().map(|s| blowfin_inst(s)).collect();
 
-    // ❶ spawn WS handler
-    tokio::spawn(async move {
-        if let Err(e) = connect_private(&settings, tx).await {
-            log::error!("blowfin private ws exit: {e}");
+        // channel between WS task ↔ market_data task
+        let (tx, mut rx) = mpsc::channel::<DepthFrame>(64);
+
+        // ❶ spawn WS handler
+        let s = settings.clone();
+        let ws = tokio::spawn(async move {
+            if let Err(e) = connect_private(&s, &insts, tx).await {
+                tracing::error!("blowfin private ws exit: {e}");
+            }
+        });
+
+        // ❷ forward verified frames onto MarketBus; connected once one arrives
+        let mut up = None;
+        let mut refresh = tokio::time::interval(SYMBOL_REFRESH);
+        refresh.tick().await;
+        loop {
+            tokio::select! {
+                df = rx.recv() => match df {
+                    Some(df) => {
+                        up.get_or_insert_with(|| FeedUp::new("blowfin:depth"));
+                        on_depth(&bus, &sec, df)
+                    }
+                    None => {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        break;
+                    }
+                },
+                _ = refresh.tick() => {
+                    if strategy_symbols(&pg).await.is_ok_and(|s| s != symbols) {
+                        break;
+                    }
+                }
+                _ = drain::stopping() => {
+                    ws.abort();
+                    return;
+                }
+            }
         }
-    });
+        ws.abort();
+    }
+}
+
+// ───────────────────────────────────────── BlowFin funding poll ────────────
+/// Predicted funding of one symbol off BlowFin's REST API
+async fn blowfin_funding(
+    http: &reqwest::Client,
+    is_demo: bool,
+    symbol: &str,
+) -> Result<Option<FundingRate>, String> {
+    use crate::services::blowfin::dto::{BlowFinResponse, FundingRateRow};
+    use crate::services::{exchange_log, instruments};
 
-    // ❷ forward verified frames onto MarketBus
-    while let Some(df) = rx.recv().await {
-        if !frame_ok(&sec, "", &df.raw_bytes) {
-            log::warn!("blowfin depth: bad sig – dropped");
-            continue;
+    let base = if is_demo {
+        "https://demo-trading-openapi.blofin.com"
+    } else {
+        "https://openapi.blofin.com"
+    };
+    let inst = instruments::get(symbol)
+        .map(|i| i.inst_id)
+        .unwrap_or_else(|| blowfin_inst(symbol));
+    let url = format!("{base}/api/v1/market/funding-rate?instId={inst}");
+    let trace = exchange_log::trace::<()>("GET", &url, &[], None);
+    let (_, text) = exchange_log::send(http.get(&url), trace)
+        .await
+        .map_err(|e| e.to_string())?;
+    let rows = serde_json::from_str::<BlowFinResponse<Vec<FundingRateRow>>>(&text)
+        .map_err(|e| e.to_string())?
+        .into_data()
+        .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().next().and_then(|r| {
+        Some(FundingRate {
+            exchange: "blowfin",
+            rate: Some(r.funding_rate).filter(|r| r.is_finite())?,
+            next_funding: DateTime::<Utc>::from_timestamp_millis(r.funding_time)?,
+            ts: Utc::now(),
+        })
+    }))
+}
+
+/// Funding of every strategy symbol every `FUNDING_POLL`; the feed counts
+/// as connected while a poll gets any answer
+async fn blowfin_funding_feed(is_demo: bool, pg: PgPool, bus: Arc<MarketBus>) {
+    track_feed("blowfin:funding");
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default();
+    let mut tick = tokio::time::interval(FUNDING_POLL);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {}
+            _ = drain::stopping() => return,
         }
-        let snap = OrderBookSnapshot {
-            bid_depth: df.bid_sum,
-            ask_depth: df.ask_sum,
-        };
-        let _ = bus.order_book.send(snap);
+        let symbols = load_symbols(&pg, "blowfin funding").await;
+        let mut answered = 0;
+        for symbol in &symbols {
+            match blowfin_funding(&http, is_demo, symbol).await {
+                Ok(Some(f)) => {
+                    bus.publish_funding(symbol, f);
+                    answered += 1;
+                }
+                Ok(None) => answered += 1,
+                Err(e) => tracing::debug!("blowfin funding {symbol}: {e}"),
+            }
+        }
+        FEEDS.insert("blowfin:funding", answered > 0);
     }
 }
 
@@ -350,6 +1285,7 @@ mod tests {
     #[test]
     fn binance_kline_parsing_helpers() {
         let kl = BinanceKline {
+            symbol: "BTCUSDT".into(),
             close_time: 0,
             interval: "1h".into(),
             open: "1.23".into(),
@@ -371,4 +1307,172 @@ mod tests {
         };
         assert_eq!(bad.open(), 0.0);
     }
+
+    #[test]
+    fn kline_frames_wait_for_the_bar_clock() {
+        let clock = BarClock::new(Duration::ZERO);
+        let frame = r#"{"stream":"btcusdt@kline_1h","data":{"k":{"s":"BTCUSDT","T":3599999,"i":"1h","o":"1","h":"2","l":"1","c":"2","v":"5"}}}"#;
+        on_kline(&clock, frame);
+        let hour = DateTime::<Utc>::from_timestamp_millis(3_600_000).unwrap();
+        assert!(clock
+            .due(hour - chrono::Duration::milliseconds(1))
+            .is_empty());
+        let out = clock.due(hour);
+        assert_eq!((out[0].symbol.as_str(), out[0].interval), ("BTCUSDT", "1h"));
+        assert_eq!(out[0].candle.close, 2.0);
+    }
+
+    // ──────────────────────────────────────────────────────────
+    // 6. Watchlist book-ticker streams
+    // ──────────────────────────────────────────────────────────
+    #[test]
+    fn ticker_streams_use_binance_names() {
+        assert_eq!(ticker_stream("BTC-USDT"), "btcusdt@bookTicker");
+        assert_eq!(ticker_stream("ETH-USDT-SWAP"), "ethusdt@bookTicker");
+        let url = ticker_url(&["BTC-USDT".into(), "SOL-USDT".into()]);
+        assert!(url.ends_with("streams=btcusdt@bookTicker/solusdt@bookTicker"));
+    }
+
+    #[test]
+    fn book_ticker_frame_updates_mid() {
+        let frame = r#"{"stream":"xrpusdt@bookTicker","data":{"u":1,"s":"XRPUSDT","b":"0.5000","B":"10","a":"0.5002","A":"12"}}"#;
+        on_book_ticker(frame);
+        let mid = analytics::mid_for("XRP-USDT").unwrap();
+        assert!((mid - 0.5001).abs() < 1e-9);
+    }
+
+    // ──────────────────────────────────────────────────────────
+    // 7. Per-symbol topics & strategy-driven subscriptions
+    // ──────────────────────────────────────────────────────────
+    fn candle(close: f64) -> Candle {
+        Candle {
+            ts: Utc::now(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+            delta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn candles_route_by_symbol_and_interval() {
+        let bus = MarketBus::new();
+        let mut btc_4h = bus.candles("BTC-USDT", "4h");
+        let mut eth_4h = bus.candles("ETHUSDT", "4h");
+        let mut all = bus.all_candles();
+
+        bus.publish_candle("BTCUSDT", "1h", candle(1.0));
+        bus.publish_candle("ETH-USDT-SWAP", "4h", candle(2.0));
+        bus.publish_candle("btcusdt", "4h", candle(3.0));
+
+        assert_eq!(btc_4h.recv().await.unwrap().close, 3.0);
+        assert_eq!(eth_4h.recv().await.unwrap().close, 2.0);
+        assert!(btc_4h.try_recv().is_err());
+        let first = all.recv().await.unwrap();
+        assert_eq!((first.symbol.as_str(), first.interval), ("BTCUSDT", "1h"));
+    }
+
+    #[tokio::test]
+    async fn signals_route_by_symbol() {
+        let bus = MarketBus::new();
+        let mut eth = bus.signals("ETHUSDT");
+        let signal = StrategySignal {
+            source: "trend_follow",
+            strategy_id: Uuid::nil(),
+            user_id: 1,
+            symbol: "ETH-USDT".into(),
+            ts: Utc::now(),
+            kind: SignalKind::Regime {
+                regime: Regime::TrendingUp,
+            },
+        };
+        bus.publish_signal(StrategySignal {
+            symbol: "BTC-USDT".into(),
+            ..signal.clone()
+        });
+        bus.publish_signal(signal);
+
+        let got = eth.recv().await.unwrap();
+        assert_eq!(got.symbol, "ETHUSDT");
+        let json = serde_json::to_value(&got).unwrap();
+        assert_eq!(
+            (json["kind"].as_str(), json["regime"].as_str()),
+            (Some("regime"), Some("trending_up"))
+        );
+        assert!(eth.try_recv().is_err());
+    }
+
+    #[test]
+    fn feeds_follow_strategy_symbols() {
+        let syms = feed_symbols(["eth-usdt".into(), "SOL-USDT-SWAP".into(), "ETHUSDT".into()]);
+        assert_eq!(syms, ["BTCUSDT", "ETHUSDT", "SOLUSDT"]);
+        assert!(kline_url(&syms).ends_with(
+            "streams=btcusdt@kline_1h/btcusdt@kline_4h/ethusdt@kline_1h/ethusdt@kline_4h/solusdt@kline_1h/solusdt@kline_4h"
+        ));
+        assert_eq!(blowfin_inst("ETHUSDT"), "ETH-USDT-SWAP");
+    }
+
+    #[tokio::test]
+    async fn mark_frames_update_the_latest_mark() {
+        let bus = MarketBus::new();
+        let mut marks = bus.all_marks();
+        assert_eq!(
+            mark_url(&["MRKUSDT".into()]),
+            "wss://fstream.binance.com/stream?streams=mrkusdt@markPrice@1s"
+        );
+
+        let frame = |mark: &str, at: DateTime<Utc>| {
+            format!(
+                r#"{{"stream":"mrkusdt@markPrice@1s","data":{{"e":"markPriceUpdate","E":{},"s":"MRKUSDT","p":"{mark}","i":"101.5","P":"101.0","r":"0.0001","T":0}}}}"#,
+                at.timestamp_millis()
+            )
+        };
+        on_mark(&bus, &frame("102.25", Utc::now()));
+        let m = mark_price("MRK-USDT-SWAP").unwrap();
+        assert_eq!((m.mark, m.index), (102.25, Some(101.5)));
+        assert_eq!(marks.recv().await.unwrap().symbol, "MRKUSDT");
+
+        // junk is dropped, a quiet feed ages out
+        on_mark(&bus, &frame("nan", Utc::now()));
+        assert_eq!(mark_price("MRKUSDT").unwrap().mark, 102.25);
+        on_mark(
+            &bus,
+            &frame("90", Utc::now() - chrono::Duration::minutes(5)),
+        );
+        assert_eq!(mark_price("MRKUSDT"), None);
+    }
+
+    #[tokio::test]
+    async fn funding_prefers_blowfin_and_ages_out() {
+        let bus = MarketBus::new();
+        let mut funding = bus.all_funding();
+        let next = Utc::now() + chrono::Duration::hours(3);
+        let frame = format!(
+            r#"{{"stream":"fndusdt@markPrice@1s","data":{{"e":"markPriceUpdate","E":{},"s":"FNDUSDT","p":"10.0","i":"10.0","r":"-0.00050000","T":{}}}}}"#,
+            Utc::now().timestamp_millis(),
+            next.timestamp_millis()
+        );
+        on_mark(&bus, &frame);
+        let f = funding_rate("FND-USDT").unwrap();
+        assert_eq!((f.exchange, f.rate), ("binance", -0.0005));
+        assert_eq!(funding.recv().await.unwrap().symbol, "FNDUSDT");
+
+        let blowfin = FundingRate {
+            exchange: "blowfin",
+            rate: 0.0001,
+            next_funding: next,
+            ts: Utc::now(),
+        };
+        bus.publish_funding("FND-USDT", blowfin);
+        assert_eq!(funding_rate("FNDUSDT").unwrap().exchange, "blowfin");
+        // a stale BlowFin rate falls back to Binance's
+        let stale = FundingRate {
+            ts: Utc::now() - chrono::Duration::minutes(30),
+            ..blowfin
+        };
+        bus.publish_funding("FNDUSDT", stale);
+        assert_eq!(funding_rate("FNDUSDT").unwrap().exchange, "binance");
+    }
 }
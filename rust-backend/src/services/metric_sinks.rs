@@ -0,0 +1,661 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Metric sinks – a user's own strategy metrics pushed to their endpoint
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Per strategy: signals acted on (orders placed, a counter), realised and
+//!   unrealised PnL, position and its notional exposure
+//! * A sink is a `webhook` (JSON POST) or a Prometheus `pushgateway` (text
+//!   format PUT to `{url}/metrics/job/rustraptor/user_id/{id}`, one group
+//!   per user, so Grafana can chart it). Prometheus remote-write would need
+//!   protobuf + snappy, which this build doesn't carry
+//! * Every push is signed like our inbound requests: `X-RR-TIMESTAMP` and
+//!   `X-RR-SIG = hex(hmac_sha256(secret, ts || body))`; the per-sink secret
+//!   is sealed with `EnvelopeCrypto` and shown once, on creation
+//! * Rate limited: at most `MAX_SINKS` per user, each pushed at most once
+//!   per its `interval_secs` (≥ `MIN_INTERVAL_SECS`), claimed in Postgres so
+//!   only one instance pushes it. `MAX_FAILURES` failed pushes in a row
+//!   disable the sink and notify its owner
+//! * Only `https` URLs to public hosts: private, loopback and link-local
+//!   addresses are refused when the sink is added and again (after DNS) on
+//!   every push; redirects aren't followed
+//! * Available to users the `metric_sinks` feature flag is on for
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::fmt::Write as _;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use metrics::increment_counter;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::db::cache::{Cache, SharedCache};
+use crate::services::crypto::GLOBAL_CRYPTO;
+use crate::services::{drain, feature_flags, notify, strategy_pnl};
+
+/// Feature flag gating sink creation
+pub const FLAG: &str = "metric_sinks";
+pub const MAX_SINKS: i64 = 3;
+pub const MIN_INTERVAL_SECS: i32 = 60;
+/// Consecutive failed pushes before a sink is switched off
+const MAX_FAILURES: i32 = 10;
+/// How often due sinks are looked for
+const TICK: Duration = Duration::from_secs(15);
+/// Sinks claimed per tick, across all users
+const BATCH: i64 = 50;
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug)]
+pub enum SinkError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("metric sinks aren't enabled for this account")]
+    NotAllowed,
+    #[error("at most {MAX_SINKS} metric sinks per account")]
+    TooMany,
+    #[error("sink not found")]
+    NotFound,
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkKind {
+    Webhook,
+    Pushgateway,
+}
+
+impl SinkKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            SinkKind::Webhook => "webhook",
+            SinkKind::Pushgateway => "pushgateway",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "webhook" => Some(SinkKind::Webhook),
+            "pushgateway" => Some(SinkKind::Pushgateway),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewSink {
+    pub kind: SinkKind,
+    pub url: String,
+    pub interval_secs: Option<i32>,
+}
+
+/// Listing view – no secret
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SinkInfo {
+    pub sink_id: Uuid,
+    pub kind: String,
+    pub url: String,
+    pub interval_secs: i32,
+    pub enabled: bool,
+    pub failures: i32,
+    pub last_push_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A freshly added sink; the only time its secret leaves the server
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedSink {
+    #[serde(flatten)]
+    pub sink: SinkInfo,
+    pub secret: String,
+}
+
+/// One strategy's numbers at push time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrategyMetrics {
+    pub strategy_id: Uuid,
+    pub strategy: String,
+    pub symbol: String,
+    pub status: String,
+    pub signals_total: i64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: Option<f64>,
+    pub position_qty: f64,
+    pub exposure_notional: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub user_id: i64,
+    pub at: DateTime<Utc>,
+    pub strategies: Vec<StrategyMetrics>,
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                // 100.64.0.0/10, carrier-grade NAT
+                || (v4.octets()[0] == 100 && v4.octets()[1] & 0xc0 == 64))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                // fc00::/7 unique local, fe80::/10 link-local
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// `https`, no credentials, a host that isn't obviously internal
+fn check_url(raw: &str) -> Result<Url, SinkError> {
+    let invalid = |m: &str| SinkError::Invalid(m.to_string());
+    let url = Url::parse(raw.trim()).map_err(|_| invalid("url is not a valid URL"))?;
+    if url.scheme() != "https" {
+        return Err(invalid("url must be https"));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(invalid("url must not carry credentials"));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| invalid("url has no host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    let internal = match host.parse::<IpAddr>() {
+        Ok(ip) => !is_public_ip(ip),
+        Err(_) => {
+            host == "localhost"
+                || [".localhost", ".local", ".internal"]
+                    .iter()
+                    .any(|s| host.ends_with(s))
+        }
+    };
+    if internal {
+        return Err(invalid("url must point at a public host"));
+    }
+    Ok(url)
+}
+
+/// What the host resolves to right now must be public too
+async fn resolves_public(url: &Url) -> Result<(), String> {
+    let host = url.host_str().ok_or("no host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|e| format!("dns: {e}"))?;
+    let mut any = false;
+    for addr in addrs {
+        if !is_public_ip(addr.ip()) {
+            return Err(format!("{host} resolves to a non-public address"));
+        }
+        any = true;
+    }
+    if !any {
+        return Err(format!("{host} resolves to nothing"));
+    }
+    Ok(())
+}
+
+fn sign(secret: &[u8], ts: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("hmac accepts any key length");
+    mac.update(ts.to_string().as_bytes());
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Prometheus label value escaping
+fn label(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The snapshot in the Prometheus text exposition format
+fn exposition(snap: &Snapshot) -> String {
+    type Value = fn(&StrategyMetrics) -> Option<f64>;
+    let families: [(&str, &str, Value); 5] = [
+        ("rustraptor_strategy_signals_total", "counter", |m| {
+            Some(m.signals_total as f64)
+        }),
+        ("rustraptor_strategy_realized_pnl", "gauge", |m| {
+            Some(m.realized_pnl)
+        }),
+        ("rustraptor_strategy_unrealized_pnl", "gauge", |m| {
+            m.unrealized_pnl
+        }),
+        ("rustraptor_strategy_position_qty", "gauge", |m| {
+            Some(m.position_qty)
+        }),
+        ("rustraptor_strategy_exposure_notional", "gauge", |m| {
+            Some(m.exposure_notional)
+        }),
+    ];
+    let mut out = String::new();
+    for (name, kind, value) in families {
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for m in &snap.strategies {
+            let Some(v) = value(m) else { continue };
+            let _ = writeln!(
+                out,
+                "{name}{{strategy_id=\"{}\",strategy=\"{}\",symbol=\"{}\"}} {v}",
+                m.strategy_id,
+                label(&m.strategy),
+                label(&m.symbol),
+            );
+        }
+    }
+    out
+}
+
+#[derive(FromRow)]
+struct StrategyRow {
+    strategy_id: Uuid,
+    strategy: String,
+    symbol: String,
+    status: String,
+    signals_total: i64,
+}
+
+pub async fn snapshot(
+    db: &PgPool,
+    cache: &dyn Cache,
+    user_id: i64,
+) -> Result<Snapshot, sqlx::Error> {
+    let rows = sqlx::query_as::<_, StrategyRow>(
+        r#"
+        SELECT s.strategy_id, s.strategy, s.symbol, s.status,
+               (SELECT COUNT(*) FROM orders o WHERE o.strategy_id = s.strategy_id) AS signals_total
+          FROM user_strategies s
+         WHERE s.user_id = $1
+         ORDER BY s.strategy_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    let mut strategies = Vec::with_capacity(rows.len());
+    for row in rows {
+        let pnl = strategy_pnl::get(db, cache, row.strategy_id).await?;
+        let (realized_pnl, unrealized_pnl, position_qty, exposure_notional) = match pnl {
+            Some(p) => (
+                p.realized_pnl,
+                p.unrealized_pnl,
+                p.position_qty,
+                p.position_qty.abs() * p.mark_price.unwrap_or(p.avg_price),
+            ),
+            None => (0.0, None, 0.0, 0.0),
+        };
+        strategies.push(StrategyMetrics {
+            strategy_id: row.strategy_id,
+            strategy: row.strategy,
+            symbol: row.symbol,
+            status: row.status,
+            signals_total: row.signals_total,
+            realized_pnl,
+            unrealized_pnl,
+            position_qty,
+            exposure_notional,
+        });
+    }
+    Ok(Snapshot {
+        user_id,
+        at: Utc::now(),
+        strategies,
+    })
+}
+
+/* ─────────────────────────────── management ─────────────────────────────── */
+
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<SinkInfo>, sqlx::Error> {
+    sqlx::query_as::<_, SinkInfo>(
+        r#"
+        SELECT sink_id, kind, url, interval_secs, enabled, failures,
+               last_push_at, last_error, created_at
+          FROM user_metric_sinks
+         WHERE user_id = $1
+         ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn add(
+    db: &PgPool,
+    cache: &dyn Cache,
+    user_id: i64,
+    req: NewSink,
+) -> Result<IssuedSink, SinkError> {
+    if !feature_flags::is_enabled(db, cache, FLAG, user_id).await {
+        return Err(SinkError::NotAllowed);
+    }
+    let url = check_url(&req.url)?;
+    let interval_secs = req.interval_secs.unwrap_or(MIN_INTERVAL_SECS);
+    if interval_secs < MIN_INTERVAL_SECS {
+        return Err(SinkError::Invalid(format!(
+            "interval_secs must be at least {MIN_INTERVAL_SECS}"
+        )));
+    }
+
+    let secret = hex::encode(rand::random::<[u8; 32]>());
+    let (wrapped, nonce, ct) = GLOBAL_CRYPTO.seal(secret.as_bytes());
+    // the count and the insert in one statement: concurrent adds can't
+    // both slip under the cap
+    let sink = sqlx::query_as::<_, SinkInfo>(
+        r#"
+        INSERT INTO user_metric_sinks
+               (user_id, kind, url, interval_secs, encrypted_data_key, nonce_secret, encrypted_secret)
+        SELECT $1, $2, $3, $4, $5, $6, $7
+         WHERE (SELECT COUNT(*) FROM user_metric_sinks WHERE user_id = $1) < $8
+        RETURNING sink_id, kind, url, interval_secs, enabled, failures,
+                  last_push_at, last_error, created_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(req.kind.as_str())
+    .bind(url.as_str())
+    .bind(interval_secs)
+    .bind(wrapped)
+    .bind(nonce)
+    .bind(ct)
+    .bind(MAX_SINKS)
+    .fetch_optional(db)
+    .await?
+    .ok_or(SinkError::TooMany)?;
+    Ok(IssuedSink { sink, secret })
+}
+
+/// `false` when the user has no such sink
+pub async fn remove(db: &PgPool, user_id: i64, sink_id: Uuid) -> Result<bool, sqlx::Error> {
+    let done = sqlx::query("DELETE FROM user_metric_sinks WHERE sink_id = $1 AND user_id = $2")
+        .bind(sink_id)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+    Ok(done.rows_affected() > 0)
+}
+
+/// Switch a sink (back) on, clearing its failure streak
+pub async fn enable(db: &PgPool, user_id: i64, sink_id: Uuid) -> Result<SinkInfo, SinkError> {
+    sqlx::query_as::<_, SinkInfo>(
+        r#"
+        UPDATE user_metric_sinks
+           SET enabled = true, failures = 0, last_error = NULL
+         WHERE sink_id = $1 AND user_id = $2
+        RETURNING sink_id, kind, url, interval_secs, enabled, failures,
+                  last_push_at, last_error, created_at
+        "#,
+    )
+    .bind(sink_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?
+    .ok_or(SinkError::NotFound)
+}
+
+/* ──────────────────────────────── pushing ───────────────────────────────── */
+
+#[derive(FromRow)]
+struct DueSink {
+    sink_id: Uuid,
+    user_id: i64,
+    kind: String,
+    url: String,
+    encrypted_data_key: Vec<u8>,
+    nonce_secret: Vec<u8>,
+    encrypted_secret: Vec<u8>,
+}
+
+/// Claim the sinks due a push; `last_push_at` moves first, so another
+/// instance (or a slow push) can't take the same one. Accounts pending
+/// deletion are skipped.
+async fn claim_due(db: &PgPool) -> Result<Vec<DueSink>, sqlx::Error> {
+    sqlx::query_as::<_, DueSink>(
+        r#"
+        UPDATE user_metric_sinks
+           SET last_push_at = now()
+         WHERE sink_id IN (
+                SELECT sink_id
+                  FROM user_metric_sinks
+                 WHERE enabled
+                   AND user_id IN (SELECT user_id FROM users WHERE deleted_at IS NULL)
+                   AND (last_push_at IS NULL
+                        OR last_push_at + make_interval(secs => interval_secs) <= now())
+                 ORDER BY last_push_at NULLS FIRST
+                 LIMIT $1
+                   FOR UPDATE SKIP LOCKED)
+        RETURNING sink_id, user_id, kind, url, encrypted_data_key, nonce_secret, encrypted_secret
+        "#,
+    )
+    .bind(BATCH)
+    .fetch_all(db)
+    .await
+}
+
+async fn push(
+    http: &reqwest::Client,
+    sink: &DueSink,
+    secret: &[u8],
+    snap: &Snapshot,
+) -> Result<(), String> {
+    let url = check_url(&sink.url).map_err(|e| e.to_string())?;
+    resolves_public(&url).await?;
+    let (req, body) = match SinkKind::parse(&sink.kind) {
+        Some(SinkKind::Webhook) => {
+            let body = serde_json::to_vec(snap).map_err(|e| e.to_string())?;
+            let req = http
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json");
+            (req, body)
+        }
+        Some(SinkKind::Pushgateway) => {
+            let target = format!(
+                "{}/metrics/job/rustraptor/user_id/{}",
+                url.as_str().trim_end_matches('/'),
+                snap.user_id
+            );
+            let req = http
+                .put(target)
+                .header(reqwest::header::CONTENT_TYPE, "text/plain; version=0.0.4");
+            (req, exposition(snap).into_bytes())
+        }
+        None => return Err(format!("unknown sink kind `{}`", sink.kind)),
+    };
+    let ts = Utc::now().timestamp();
+    let resp = req
+        .header("X-RR-TIMESTAMP", ts.to_string())
+        .header("X-RR-SIG", sign(secret, ts, &body))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    Ok(())
+}
+
+async fn record(db: &PgPool, sink: &DueSink, outcome: &Result<(), String>) {
+    let res = match outcome {
+        Ok(()) => sqlx::query(
+            "UPDATE user_metric_sinks SET failures = 0, last_error = NULL WHERE sink_id = $1",
+        )
+        .bind(sink.sink_id)
+        .execute(db)
+        .await
+        .map(drop),
+        Err(e) => {
+            let disabled: Result<Option<bool>, sqlx::Error> = sqlx::query_scalar(
+                r#"
+                UPDATE user_metric_sinks
+                   SET failures = failures + 1,
+                       last_error = $2,
+                       enabled = failures + 1 < $3
+                 WHERE sink_id = $1
+                RETURNING NOT enabled
+                "#,
+            )
+            .bind(sink.sink_id)
+            .bind(e)
+            .bind(MAX_FAILURES)
+            .fetch_optional(db)
+            .await;
+            if let Ok(Some(true)) = disabled {
+                notify::send(
+                    sink.user_id,
+                    "metrics.sink_disabled",
+                    &format!(
+                        "Metric sink {} switched off after {MAX_FAILURES} failed pushes: {e}",
+                        sink.url
+                    ),
+                    json!({ "sink_id": sink.sink_id, "error": e }),
+                );
+            }
+            disabled.map(drop)
+        }
+    };
+    if let Err(e) = res {
+        tracing::warn!("metric sink {}: recording the push: {e}", sink.sink_id);
+    }
+}
+
+async fn push_due(
+    db: &PgPool,
+    cache: &dyn Cache,
+    http: &reqwest::Client,
+) -> Result<(), sqlx::Error> {
+    for sink in claim_due(db).await? {
+        let secret = match GLOBAL_CRYPTO.open(
+            &sink.encrypted_data_key,
+            &sink.nonce_secret,
+            &sink.encrypted_secret,
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!("metric sink {}: secret unreadable: {e}", sink.sink_id);
+                continue;
+            }
+        };
+        let snap = snapshot(db, cache, sink.user_id).await?;
+        let outcome = push(http, &sink, secret.as_bytes(), &snap).await;
+        increment_counter!(
+            "metric_sink_pushes_total",
+            "kind" => sink.kind.clone(),
+            "outcome" => if outcome.is_ok() { "ok" } else { "error" }
+        );
+        record(db, &sink, &outcome).await;
+    }
+    Ok(())
+}
+
+/// Push due sinks every `TICK` until the instance drains
+pub fn spawn(db: PgPool, cache: SharedCache) {
+    tokio::spawn(async move {
+        let http = match reqwest::Client::builder()
+            .timeout(PUSH_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("metric sinks: http client: {e}");
+                return;
+            }
+        };
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            tick.tick().await;
+            if drain::is_draining() {
+                break;
+            }
+            if let Err(e) = push_due(&db, cache.as_ref(), &http).await {
+                tracing::warn!("metric sinks: {e}");
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_https_urls_are_accepted() {
+        assert!(check_url("https://push.example.com/hook").is_ok());
+        for bad in [
+            "http://push.example.com",
+            "https://user:pw@push.example.com",
+            "https://localhost:9091",
+            "https://127.0.0.1",
+            "https://10.1.2.3",
+            "https://169.254.169.254/latest",
+            "https://[::1]",
+            "https://[fd00::1]",
+            "https://metrics.internal",
+            "not a url",
+        ] {
+            assert!(check_url(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn pushgateway_body_is_prometheus_text() {
+        let snap = Snapshot {
+            user_id: 7,
+            at: Utc::now(),
+            strategies: vec![StrategyMetrics {
+                strategy_id: Uuid::nil(),
+                strategy: "trend_follow".into(),
+                symbol: "BTC\"USDT".into(),
+                status: "enabled".into(),
+                signals_total: 12,
+                realized_pnl: 5.5,
+                unrealized_pnl: None,
+                position_qty: -0.5,
+                exposure_notional: 15_000.0,
+            }],
+        };
+        let text = exposition(&snap);
+        assert!(text.contains("# TYPE rustraptor_strategy_signals_total counter\n"));
+        assert!(text.contains(
+            "rustraptor_strategy_signals_total{strategy_id=\"00000000-0000-0000-0000-000000000000\",strategy=\"trend_follow\",symbol=\"BTC\\\"USDT\"} 12\n"
+        ));
+        assert!(text.contains("rustraptor_strategy_position_qty{"));
+        // no live price, no unrealised sample
+        assert!(!text.contains("rustraptor_strategy_unrealized_pnl{"));
+    }
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let sig = sign(b"s3cret", 1_700_000_000, b"{}");
+        assert_eq!(sig.len(), 64);
+        assert_eq!(sig, sign(b"s3cret", 1_700_000_000, b"{}"));
+        assert_ne!(sig, sign(b"s3cret", 1_700_000_001, b"{}"));
+        assert_ne!(sig, sign(b"other", 1_700_000_000, b"{}"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Fire-and-forget user notifications, published on the `notifications`
+//! event stream for whatever delivers them (Discord bot, mail, …).
+//!
+//! ```ignore
+//! notify::init(event_bus.clone(), settings.notify_digest_secs); // once, in main
+//! notify::send(uid, "strategy.auto_stop", "…", json!({ "strategy_id": id }));
+//! ```
+//!
+//! Bursts are digested per user and channel (the kind's first segment,
+//! `strategy` in `strategy.auto_stop`): the first notification of a window
+//! goes out at once, the rest of the window is folded into one `digest`
+//! notification when it closes. A digest holds counts per kind and the first
+//! few messages only, so a runaway strategy costs a bounded amount of memory
+//! and one publish per window.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::services::event_bus::{EventBus, Topic};
+
+/// Messages a digest quotes; the rest are only counted
+const DIGEST_SAMPLE: usize = 5;
+
+static BUS: OnceCell<EventBus> = OnceCell::new();
+static DIGESTS: Lazy<Mutex<Digester>> = Lazy::new(|| Mutex::new(Digester::new(0)));
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub user_id: i64,
+    /// Dotted event name, like audit actions
+    pub kind: String,
+    /// Human-readable, ready to show
+    pub message: String,
+    pub details: Value,
+    pub at: DateTime<Utc>,
+}
+
+fn channel(kind: &str) -> &str {
+    kind.split('.').next().unwrap_or(kind)
+}
+
+/// Notifications held back in one user's channel window
+#[derive(Debug)]
+struct Window {
+    opened: DateTime<Utc>,
+    held: usize,
+    kinds: BTreeMap<String, usize>,
+    sample: Vec<String>,
+}
+
+impl Window {
+    fn new(opened: DateTime<Utc>) -> Self {
+        Self {
+            opened,
+            held: 0,
+            kinds: BTreeMap::new(),
+            sample: Vec::new(),
+        }
+    }
+}
+
+/// Per (user, channel) digest windows
+#[derive(Debug)]
+struct Digester {
+    window_secs: u64,
+    open: HashMap<(i64, String), Window>,
+}
+
+impl Digester {
+    fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs,
+            open: HashMap::new(),
+        }
+    }
+
+    /// `Some` when `n` should go out now, `None` when it was held back
+    fn offer(&mut self, n: Notification) -> Option<Notification> {
+        if self.window_secs == 0 {
+            return Some(n);
+        }
+        let key = (n.user_id, channel(&n.kind).to_string());
+        let Some(w) = self.open.get_mut(&key) else {
+            self.open.insert(key, Window::new(n.at));
+            return Some(n);
+        };
+        w.held += 1;
+        *w.kinds.entry(n.kind).or_default() += 1;
+        if w.sample.len() < DIGEST_SAMPLE {
+            w.sample.push(n.message);
+        }
+        None
+    }
+
+    /// Digests of the windows closed by `now`. A window that held something
+    /// back reopens, so a steady stream stays at one message per window;
+    /// a quiet one is dropped.
+    fn due(&mut self, now: DateTime<Utc>) -> Vec<Notification> {
+        let len = chrono::Duration::seconds(self.window_secs as i64);
+        let mut out = Vec::new();
+        self.open.retain(|(user_id, channel), w| {
+            if now - w.opened < len {
+                return true;
+            }
+            if w.held == 0 {
+                return false;
+            }
+            let mut message = format!("{} more {channel} notifications", w.held);
+            for m in &w.sample {
+                message.push_str("\n• ");
+                message.push_str(m);
+            }
+            out.push(Notification {
+                user_id: *user_id,
+                kind: "digest".into(),
+                message,
+                details: json!({
+                    "channel": channel,
+                    "count": w.held,
+                    "kinds": w.kinds,
+                    "since": w.opened,
+                }),
+                at: now,
+            });
+            *w = Window::new(now);
+            true
+        });
+        out
+    }
+}
+
+/// Publish through `bus` from now on (idempotent), digesting bursts over
+/// `digest_secs`; 0 = every notification goes out on its own.
+pub fn init(bus: EventBus, digest_secs: u64) {
+    if BUS.set(bus).is_err() {
+        return;
+    }
+    DIGESTS.lock().unwrap().window_secs = digest_secs;
+    if digest_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tick.tick().await;
+            let due = DIGESTS.lock().unwrap().due(Utc::now());
+            for n in due {
+                publish(n);
+            }
+        }
+    });
+}
+
+fn publish(n: Notification) {
+    let Some(bus) = BUS.get() else {
+        tracing::info!("notify (no event bus): user {}: {}", n.user_id, n.message);
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = bus.publish(Topic::Notifications, &n).await {
+            tracing::warn!("notify user {}: {e}", n.user_id);
+        }
+    });
+}
+
+/// Queue a notification; logged only when running without the event bus.
+pub fn send(user_id: i64, kind: &str, message: &str, details: Value) {
+    let n = Notification {
+        user_id,
+        kind: kind.into(),
+        message: message.into(),
+        details,
+        at: Utc::now(),
+    };
+    if let Some(n) = DIGESTS.lock().unwrap().offer(n) {
+        publish(n);
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn note(user_id: i64, kind: &str, at: DateTime<Utc>) -> Notification {
+        Notification {
+            user_id,
+            kind: kind.into(),
+            message: format!("{kind} fired"),
+            details: Value::Null,
+            at,
+        }
+    }
+
+    #[test]
+    fn bursts_fold_into_one_digest_per_user_and_channel() {
+        let t0 = Utc.with_ymd_and_hms(2025, 7, 1, 12, 0, 0).unwrap();
+        let mut d = Digester::new(60);
+
+        // the first of a window goes out at once, per user and channel
+        assert!(d.offer(note(1, "strategy.signal", t0)).is_some());
+        assert!(d.offer(note(1, "security.login", t0)).is_some());
+        assert!(d.offer(note(2, "strategy.signal", t0)).is_some());
+        for _ in 0..10 {
+            assert!(d.offer(note(1, "strategy.signal", t0)).is_none());
+        }
+        assert!(d.offer(note(1, "strategy.auto_stop", t0)).is_none());
+
+        assert!(d.due(t0 + chrono::Duration::seconds(59)).is_empty());
+        let t1 = t0 + chrono::Duration::seconds(60);
+        let out = d.due(t1);
+        assert_eq!(out.len(), 1);
+        let digest = &out[0];
+        assert_eq!((digest.user_id, digest.kind.as_str()), (1, "digest"));
+        assert_eq!(digest.details["count"], 11);
+        assert_eq!(digest.details["kinds"]["strategy.signal"], 10);
+        assert_eq!(digest.message.lines().count(), 1 + DIGEST_SAMPLE);
+
+        // the busy window reopened, the quiet ones are gone
+        assert!(d.offer(note(1, "strategy.signal", t1)).is_none());
+        assert!(d.offer(note(2, "strategy.signal", t1)).is_some());
+    }
+
+    #[test]
+    fn zero_window_sends_everything() {
+        let mut d = Digester::new(0);
+        let t0 = Utc.with_ymd_and_hms(2025, 7, 1, 12, 0, 0).unwrap();
+        for _ in 0..3 {
+            assert!(d.offer(note(1, "strategy.signal", t0)).is_some());
+        }
+        assert!(d.due(t0 + chrono::Duration::days(1)).is_empty());
+    }
+}
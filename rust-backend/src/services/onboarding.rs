@@ -0,0 +1,223 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Onboarding quickstart
+//! ──────────────────────────────────────────────────────────────────────────
+//! One call from "here is my API key" to a running first strategy:
+//!
+//! 1. the key is proven against the exchange (balance call with the submitted
+//!    credentials) before anything is stored
+//! 2. the USDT balance must cover `MIN_AVAILABLE_USDT`
+//! 3. key and a deliberately small `trend_follow` are written in a single
+//!    transaction – a failure leaves nothing half set up
+//!
+//! Users who already stored a key for the exchange use the regular
+//! endpoints; the quickstart never replaces a key.
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::api_keys::{forget_creds, ApiKey};
+use crate::services::blowfin::api::{self, Credentials};
+use crate::services::crypto::GLOBAL_CRYPTO;
+use crate::services::scheduler;
+
+/// The only exchange orders are routed to today
+pub const EXCHANGE: &str = "blowfin";
+pub const STRATEGY: &str = "trend_follow";
+const DEFAULT_SYMBOL: &str = "BTC-USDT";
+/// Smallest BTC contract step – a first strategy shouldn't risk much
+const DEFAULT_QTY: f64 = 0.001;
+/// Free USDT needed before we start anything
+pub const MIN_AVAILABLE_USDT: f64 = 50.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum OnboardingError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("an API key for {0} is already stored")]
+    KeyExists(String),
+    #[error("exchange rejected the API key: {0}")]
+    KeyRejected(String),
+    #[error("exchange unreachable: {0}")]
+    Exchange(String),
+    #[error("available balance {available:.2} USDT is below the {required:.2} USDT minimum")]
+    InsufficientBalance { available: f64, required: f64 },
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickstartReq {
+    pub api_key: String,
+    pub api_secret: String,
+    #[serde(default)]
+    pub api_passphrase: String,
+    /// Defaults to `BTC-USDT`
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuickstartSummary {
+    pub key_id: Uuid,
+    pub strategy_id: Uuid,
+    pub exchange: &'static str,
+    pub strategy: &'static str,
+    pub symbol: String,
+    pub params: Value,
+    pub equity_usdt: f64,
+    pub available_usdt: f64,
+    pub is_demo: bool,
+}
+
+/// Conservative `trend_follow` params for `symbol`
+pub fn default_params(symbol: &str) -> Value {
+    json!({ "symbol": symbol, "fast": 20, "slow": 100, "don": 55, "qty": DEFAULT_QTY })
+}
+
+fn validate(req: &QuickstartReq) -> Result<String, OnboardingError> {
+    if req.api_key.trim().is_empty() || req.api_secret.trim().is_empty() {
+        return Err(OnboardingError::Invalid(
+            "api_key and api_secret are required".into(),
+        ));
+    }
+    let symbol = req
+        .symbol
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_SYMBOL)
+        .to_ascii_uppercase();
+    if symbol.len() > 32
+        || !symbol
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(OnboardingError::Invalid(format!("bad symbol `{symbol}`")));
+    }
+    Ok(symbol)
+}
+
+pub async fn quickstart(
+    db: &PgPool,
+    user_id: i64,
+    req: QuickstartReq,
+    is_demo: bool,
+) -> Result<QuickstartSummary, OnboardingError> {
+    let symbol = validate(&req)?;
+    let params = default_params(&symbol);
+    scheduler::check_params(STRATEGY, &params)
+        .map_err(|e| OnboardingError::Invalid(e.to_string()))?;
+    if ApiKey::get_by_user_and_exchange(db, user_id, EXCHANGE)
+        .await?
+        .is_some()
+    {
+        return Err(OnboardingError::KeyExists(EXCHANGE.into()));
+    }
+
+    // ─── prove the key, check funds ───────────────────────────────────────
+    let creds = Credentials {
+        api_key: req.api_key.trim().to_string(),
+        api_secret: req.api_secret.trim().to_string(),
+        api_passphrase: req.api_passphrase.trim().to_string(),
+    };
+    let resp = api::get_balance_as(db, creds.clone(), is_demo)
+        .await
+        .map_err(|e| OnboardingError::Exchange(e.to_string()))?;
+    if !resp.is_ok() {
+        return Err(OnboardingError::KeyRejected(format!(
+            "code {}: {}",
+            resp.code, resp.msg
+        )));
+    }
+    let usdt = resp.data.currency("USDT");
+    let available_usdt = usdt.map(|d| d.available).unwrap_or(0.0);
+    let equity_usdt = usdt.map(|d| d.equity).unwrap_or(0.0);
+    if available_usdt < MIN_AVAILABLE_USDT {
+        return Err(OnboardingError::InsufficientBalance {
+            available: available_usdt,
+            required: MIN_AVAILABLE_USDT,
+        });
+    }
+
+    // ─── key + strategy, all or nothing ───────────────────────────────────
+    let mut tx = db.begin().await?;
+    let passphrase = Some(creds.api_passphrase.as_str()).filter(|p| !p.is_empty());
+    let key_id = ApiKey::insert(
+        &mut *tx,
+        &GLOBAL_CRYPTO,
+        user_id,
+        EXCHANGE,
+        &creds.api_key,
+        &creds.api_secret,
+        passphrase,
+    )
+    .await?;
+    let strategy_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO user_strategies (user_id, exchange, symbol, strategy, params)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING strategy_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .bind(&symbol)
+    .bind(STRATEGY)
+    .bind(&params)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    forget_creds(user_id, EXCHANGE);
+
+    Ok(QuickstartSummary {
+        key_id,
+        strategy_id,
+        exchange: EXCHANGE,
+        strategy: STRATEGY,
+        symbol,
+        params,
+        equity_usdt,
+        available_usdt,
+        is_demo,
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(symbol: Option<&str>) -> QuickstartReq {
+        QuickstartReq {
+            api_key: "k".into(),
+            api_secret: "s".into(),
+            api_passphrase: String::new(),
+            symbol: symbol.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid_trend_follow_params() {
+        assert!(scheduler::check_params(STRATEGY, &default_params(DEFAULT_SYMBOL)).is_ok());
+    }
+
+    #[test]
+    fn symbol_defaults_and_is_normalised() {
+        assert_eq!(validate(&req(None)).unwrap(), "BTC-USDT");
+        assert_eq!(validate(&req(Some(" "))).unwrap(), "BTC-USDT");
+        assert_eq!(validate(&req(Some("eth-usdt"))).unwrap(), "ETH-USDT");
+        assert!(validate(&req(Some("BTC USDT"))).is_err());
+    }
+
+    #[test]
+    fn credentials_are_required() {
+        let mut r = req(None);
+        r.api_secret = "  ".into();
+        assert!(matches!(validate(&r), Err(OnboardingError::Invalid(_))));
+    }
+}
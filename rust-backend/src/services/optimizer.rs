@@ -0,0 +1,223 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Optimizer results → strategy presets
+//! ──────────────────────────────────────────────────────────────────────────
+//! A finished `optimizer_jobs` row carries the best parameter set found for
+//! one strategy / symbol / data range. `apply_job` turns it into a running
+//! strategy – either a new `user_strategies` row or an update of an existing
+//! one – and stamps `provenance` so the result can be reproduced later:
+//!
+//! ```json
+//! { "optimizer_job": "…", "interval": "4h", "data_from": "…", "data_to": "…",
+//!   "objective": "sharpe", "score": 1.42, "applied_at": "…" }
+//! ```
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct OptimizerJob {
+    pub job_id: Uuid,
+    pub user_id: i64,
+    pub strategy: String,
+    pub exchange: String,
+    pub symbol: String,
+    pub interval: String,
+    pub data_from: DateTime<Utc>,
+    pub data_to: DateTime<Utc>,
+    pub objective: String,
+    pub status: String,
+    pub best_params: Option<Value>,
+    pub best_score: Option<f64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum OptimizerError {
+    #[error("optimizer job not found")]
+    JobNotFound,
+    #[error("optimizer job is {0}, not done")]
+    NotFinished(String),
+    #[error("strategy not found")]
+    StrategyNotFound,
+    #[error("job optimised `{job}` but strategy runs `{strategy}`")]
+    StrategyMismatch { job: String, strategy: String },
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Serialize)]
+pub struct Applied {
+    pub strategy_id: Uuid,
+    /// `false` when an existing strategy was updated
+    pub created: bool,
+    pub params: Value,
+}
+
+/// Only the owner can see or apply a job
+pub async fn get_job(
+    db: &PgPool,
+    user_id: i64,
+    job_id: Uuid,
+) -> Result<OptimizerJob, OptimizerError> {
+    sqlx::query_as::<_, OptimizerJob>(
+        r#"
+        SELECT job_id, user_id, strategy, exchange, symbol, interval,
+               data_from, data_to, objective, status, best_params, best_score
+          FROM optimizer_jobs
+         WHERE job_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(job_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?
+    .ok_or(OptimizerError::JobNotFound)
+}
+
+/// Best params of a finished job, or why it can't be applied yet
+pub fn best_params(job: &OptimizerJob) -> Result<&Value, OptimizerError> {
+    match (job.status.as_str(), &job.best_params) {
+        ("done", Some(p)) => Ok(p),
+        ("done", None) => Err(OptimizerError::NotFinished("done without results".into())),
+        (other, _) => Err(OptimizerError::NotFinished(other.to_string())),
+    }
+}
+
+pub fn provenance(job: &OptimizerJob, applied_at: DateTime<Utc>) -> Value {
+    json!({
+        "optimizer_job": job.job_id,
+        "interval": job.interval,
+        "data_from": job.data_from,
+        "data_to": job.data_to,
+        "objective": job.objective,
+        "score": job.best_score,
+        "applied_at": applied_at,
+    })
+}
+
+/// Create a strategy from the job's best params, or – with `target` – update
+/// that strategy in place (must run the same strategy kind).
+pub async fn apply_job(
+    db: &PgPool,
+    user_id: i64,
+    job_id: Uuid,
+    target: Option<Uuid>,
+) -> Result<Applied, OptimizerError> {
+    let job = get_job(db, user_id, job_id).await?;
+    let params = best_params(&job)?.clone();
+    let prov = provenance(&job, Utc::now());
+
+    let Some(strategy_id) = target else {
+        let strategy_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO user_strategies
+                  (user_id, exchange, symbol, strategy, params, provenance)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING strategy_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(&job.exchange)
+        .bind(&job.symbol)
+        .bind(&job.strategy)
+        .bind(&params)
+        .bind(&prov)
+        .fetch_one(db)
+        .await?;
+        return Ok(Applied {
+            strategy_id,
+            created: true,
+            params,
+        });
+    };
+
+    let kind: Option<String> = sqlx::query_scalar(
+        "SELECT strategy FROM user_strategies WHERE strategy_id = $1 AND user_id = $2",
+    )
+    .bind(strategy_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    match kind {
+        None => return Err(OptimizerError::StrategyNotFound),
+        Some(k) if k != job.strategy => {
+            return Err(OptimizerError::StrategyMismatch {
+                job: job.strategy,
+                strategy: k,
+            })
+        }
+        Some(_) => {}
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE user_strategies
+           SET params = $1, provenance = $2
+         WHERE strategy_id = $3 AND user_id = $4
+        "#,
+    )
+    .bind(&params)
+    .bind(&prov)
+    .bind(strategy_id)
+    .bind(user_id)
+    .execute(db)
+    .await?;
+
+    Ok(Applied {
+        strategy_id,
+        created: false,
+        params,
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn job(status: &str, params: Option<Value>) -> OptimizerJob {
+        OptimizerJob {
+            job_id: Uuid::nil(),
+            user_id: 1,
+            strategy: "trend_follow".into(),
+            exchange: "blowfin".into(),
+            symbol: "BTC-USDT".into(),
+            interval: "1h".into(),
+            data_from: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            data_to: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            objective: "sharpe".into(),
+            status: status.into(),
+            best_params: params,
+            best_score: Some(1.5),
+        }
+    }
+
+    #[test]
+    fn only_finished_jobs_have_params() {
+        let p = json!({ "fast": 10, "slow": 50 });
+        assert_eq!(best_params(&job("done", Some(p.clone()))).unwrap(), &p);
+        assert!(matches!(
+            best_params(&job("running", None)),
+            Err(OptimizerError::NotFinished(s)) if s == "running"
+        ));
+        assert!(best_params(&job("done", None)).is_err());
+    }
+
+    #[test]
+    fn provenance_records_job_and_range() {
+        let at = Utc.with_ymd_and_hms(2025, 7, 19, 12, 0, 0).unwrap();
+        let p = provenance(&job("done", None), at);
+        assert_eq!(p["optimizer_job"], json!(Uuid::nil()));
+        assert_eq!(p["interval"], "1h");
+        assert_eq!(p["data_from"], json!("2024-01-01T00:00:00Z"));
+        assert_eq!(p["data_to"], json!("2025-01-01T00:00:00Z"));
+        assert_eq!(p["score"], json!(1.5));
+        assert_eq!(p["applied_at"], json!(at));
+    }
+}
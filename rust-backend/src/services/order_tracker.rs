@@ -0,0 +1,326 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Order lifecycle tracking
+//! ──────────────────────────────────────────────────────────────────────────
+//! * `execute_trade` stores every submission in `orders` (`live`, or
+//!   `rejected` when the exchange refused it)
+//! * Every `POLL_EVERY` the tracker reads the recent BlowFin fills of each
+//!   user with open orders, records the new ones in `fills` (fee, realised
+//!   PnL, slippage) and feeds closing fills' net PnL to `risk::record_fill`
+//!   ([`record_realised`]), so the draw-down guard sees real losses
+//! * Status follows [`transition`]: `live` → `partially_filled` → `filled`;
+//!   an order that stops filling is asked about by client id and closed as
+//!   `cancelled` (nothing filled) or left `partially_filled` with
+//!   `closed_at` set
+//! * Orders older than `TRACK_FOR` are no longer polled; fills beyond the
+//!   exchange's 100 most recent are not seen
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use metrics::increment_counter;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::db::cache::{Cache, SharedCache};
+use crate::services::{
+    analytics,
+    blowfin::{api, dto::Fill},
+    exchanges, fees, risk,
+    trading_engine::{Exchange, OrderState},
+};
+use crate::utils::errors::{ApiError, TradeError};
+use crate::utils::types::{MakerTaker, OrderStatus};
+
+const POLL_EVERY: StdDuration = StdDuration::from_secs(10);
+/// Open orders older than this are left alone
+const TRACK_FOR: Duration = Duration::days(7);
+/// Ask the exchange about an unfilled order once it is this old
+const SETTLE_AFTER: Duration = Duration::seconds(30);
+/// Filled size within this of the order size counts as complete
+const SIZE_EPSILON: f64 = 1e-9;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TrackError {
+    #[error("exchange: {0}")]
+    Exchange(#[from] ApiError),
+    #[error("trade: {0}")]
+    Trade(#[from] TradeError),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// Next status and whether the order is done
+pub fn transition(
+    current: OrderStatus,
+    filled: f64,
+    size: f64,
+    venue: Option<OrderState>,
+) -> (OrderStatus, bool) {
+    if filled + SIZE_EPSILON >= size || venue == Some(OrderState::Filled) {
+        return (OrderStatus::Filled, true);
+    }
+    let some_filled = filled > SIZE_EPSILON;
+    match venue {
+        Some(OrderState::Canceled | OrderState::PartiallyFilled) if some_filled => {
+            (OrderStatus::PartiallyFilled, true)
+        }
+        Some(OrderState::Canceled | OrderState::PartiallyFilled) => (OrderStatus::Cancelled, true),
+        _ if some_filled => (OrderStatus::PartiallyFilled, false),
+        _ => (current, false),
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct OpenOrder {
+    order_id: Uuid,
+    external_order_id: Option<String>,
+    client_order_id: Option<String>,
+    symbol: String,
+    side: String,
+    order_type: String,
+    size: f64,
+    filled_size: f64,
+    status: OrderStatus,
+    opened_at: DateTime<Utc>,
+    signal_price: Option<f64>,
+    mid_at_submit: Option<f64>,
+}
+
+/// What one pass did for one user
+#[derive(Debug, Default)]
+pub struct Tracked {
+    pub fills: usize,
+    pub closed: usize,
+}
+
+/// Insert one exchange fill; `false` when it was already recorded
+async fn store_fill(db: &PgPool, order: &OpenOrder, f: &Fill) -> Result<bool, sqlx::Error> {
+    let maker_taker = if fees::is_maker(&order.order_type) {
+        MakerTaker::Maker
+    } else {
+        MakerTaker::Taker
+    };
+    let vs = |reference: Option<f64>| {
+        reference.and_then(|p| analytics::slippage_bps(&order.side, p, f.fill_price))
+    };
+    let executed_at = Utc
+        .timestamp_millis_opt(f.ts)
+        .single()
+        .unwrap_or_else(Utc::now);
+    let inserted: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        INSERT INTO fills
+              (order_id, external_trade_id, maker_taker, fill_price, fill_size,
+               trade_fee, realised_pnl, executed_at, slippage_bps, mid_slippage_bps)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (order_id, external_trade_id) DO NOTHING
+        RETURNING fill_id
+        "#,
+    )
+    .bind(order.order_id)
+    .bind(&f.trade_id)
+    .bind(maker_taker)
+    .bind(f.fill_price)
+    .bind(f.fill_size)
+    .bind(f.fee)
+    .bind(f.fill_pnl.unwrap_or(0.0))
+    .bind(executed_at)
+    .bind(vs(order.signal_price))
+    .bind(vs(order.mid_at_submit))
+    .fetch_optional(db)
+    .await?;
+    Ok(inserted.is_some())
+}
+
+/// One polling pass over a user's open orders
+pub async fn track_user(
+    db: &PgPool,
+    cache: &dyn Cache,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<Tracked, TrackError> {
+    let mut open = sqlx::query_as::<_, OpenOrder>(
+        r#"
+        SELECT order_id, external_order_id, client_order_id, symbol, side,
+               order_type::text       AS order_type,
+               size::float8           AS size,
+               filled_size::float8    AS filled_size,
+               status, opened_at,
+               signal_price::float8   AS signal_price,
+               mid_at_submit::float8  AS mid_at_submit
+          FROM orders
+         WHERE user_id = $1 AND exchange = $2
+           AND closed_at IS NULL AND status IN ('live', 'partially_filled')
+           AND opened_at > $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(Exchange::Blowfin.as_str())
+    .bind(Utc::now() - TRACK_FOR)
+    .fetch_all(db)
+    .await?;
+    let mut done = Tracked::default();
+    if open.is_empty() {
+        return Ok(done);
+    }
+
+    // 1. new fills
+    let by_ext: HashMap<String, usize> = open
+        .iter()
+        .enumerate()
+        .filter_map(|(i, o)| Some((o.external_order_id.clone()?, i)))
+        .collect();
+    let fills = api::get_fills(db, user_id, is_demo, master_key)
+        .await?
+        .into_data()?;
+    for f in &fills {
+        let Some(&i) = by_ext.get(&f.order_id) else {
+            continue;
+        };
+        if !store_fill(db, &open[i], f).await? {
+            continue;
+        }
+        open[i].filled_size += f.fill_size;
+        done.fills += 1;
+        increment_counter!("order_fills_tracked_total");
+        record_realised(cache, user_id, f).await;
+    }
+
+    // 2. transitions; an order that isn't complete yet is checked on the venue
+    let mut client = None;
+    for o in &open {
+        let mut venue = None;
+        let stale = Utc::now() - o.opened_at > SETTLE_AFTER;
+        if o.filled_size + SIZE_EPSILON < o.size && stale {
+            if let Some(coid) = o.client_order_id.as_deref() {
+                if client.is_none() {
+                    client = Some(exchanges::connect(db, user_id, Exchange::Blowfin).await?);
+                }
+                let api = client.as_deref().expect("connected above");
+                venue = Some(
+                    api.order_status(db, user_id, &o.symbol, coid, is_demo, master_key)
+                        .await?
+                        .state,
+                );
+            }
+        }
+        let (status, closed) = transition(o.status, o.filled_size, o.size, venue);
+        sqlx::query(
+            r#"
+            UPDATE orders
+               SET status = $2, filled_size = $3,
+                   closed_at = CASE WHEN $4 THEN now() END
+             WHERE order_id = $1
+            "#,
+        )
+        .bind(o.order_id)
+        .bind(status)
+        .bind(o.filled_size)
+        .bind(closed)
+        .execute(db)
+        .await?;
+        done.closed += closed as usize;
+    }
+    Ok(done)
+}
+
+/// Book a fill's PnL net of its fee with the draw-down guard; only closing
+/// fills realise anything. Whether it was booked
+pub async fn record_realised(cache: &dyn Cache, user_id: i64, f: &Fill) -> bool {
+    let Some(pnl) = f.fill_pnl.filter(|p| *p != 0.0) else {
+        return false;
+    };
+    match risk::record_fill(cache, user_id, pnl - f.fee).await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!("order tracker: risk record_fill for {user_id}: {e}");
+            false
+        }
+    }
+}
+
+pub fn spawn(db: PgPool, cache: SharedCache, is_demo: bool) {
+    tokio::spawn(async move {
+        let master_key = std::env::var("MASTER_KEY").unwrap_or_default().into_bytes();
+        let mut tick = tokio::time::interval(POLL_EVERY);
+        loop {
+            tick.tick().await;
+            let users: Vec<i64> = match sqlx::query_scalar(
+                r#"
+                SELECT DISTINCT user_id
+                  FROM orders
+                 WHERE closed_at IS NULL AND status IN ('live', 'partially_filled')
+                   AND opened_at > $1
+                "#,
+            )
+            .bind(Utc::now() - TRACK_FOR)
+            .fetch_all(&db)
+            .await
+            {
+                Ok(u) => u,
+                Err(e) => {
+                    tracing::error!("order tracker: DB error: {e}");
+                    continue;
+                }
+            };
+            for user_id in users {
+                // one unreachable account must not hold up the rest
+                if let Err(e) = track_user(&db, cache.as_ref(), user_id, is_demo, &master_key).await
+                {
+                    tracing::warn!("order tracker: {user_id}: {e}");
+                }
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_drive_the_status() {
+        use OrderStatus::*;
+        assert_eq!(transition(Live, 0.0, 2.0, None), (Live, false));
+        assert_eq!(transition(Live, 0.5, 2.0, None), (PartiallyFilled, false));
+        assert_eq!(transition(PartiallyFilled, 2.0, 2.0, None), (Filled, true));
+        // the venue may know before our fill window does
+        assert_eq!(
+            transition(Live, 0.0, 2.0, Some(OrderState::Filled)),
+            (Filled, true)
+        );
+        assert_eq!(
+            transition(Live, 0.0, 2.0, Some(OrderState::Live)),
+            (Live, false)
+        );
+    }
+
+    #[test]
+    fn cancelled_orders_close_with_what_filled() {
+        use OrderStatus::*;
+        assert_eq!(
+            transition(Live, 0.0, 2.0, Some(OrderState::Canceled)),
+            (Cancelled, true)
+        );
+        assert_eq!(
+            transition(PartiallyFilled, 1.0, 2.0, Some(OrderState::Canceled)),
+            (PartiallyFilled, true)
+        );
+        assert_eq!(
+            transition(Live, 1.0, 2.0, Some(OrderState::PartiallyFilled)),
+            (PartiallyFilled, true)
+        );
+        // the exchange lost it: keep waiting, TRACK_FOR ends it
+        assert_eq!(
+            transition(Live, 0.0, 2.0, Some(OrderState::NotFound)),
+            (Live, false)
+        );
+    }
+}
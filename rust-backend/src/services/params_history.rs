@@ -0,0 +1,270 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Strategy params history (content-addressed)
+//! ──────────────────────────────────────────────────────────────────────────
+//! * A params document is identified by the sha256 of its canonical JSON
+//!   (object keys sorted, no whitespace) – key order and formatting don't
+//!   create new versions
+//! * [`record_run`] is called whenever the scheduler starts a strategy: an
+//!   unseen hash becomes the next version, a known one just bumps
+//!   `last_run_at`; `user_strategies.params_hash` tracks the live one
+//! * Orders and PnL entries copy that hash, so [`versions`] can split
+//!   performance per version; [`revert`] restores any earlier version
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParamsHistoryError {
+    #[error("strategy not found")]
+    StrategyNotFound,
+    #[error("version {0} not found")]
+    VersionNotFound(i32),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// Deterministic serialisation: keys sorted at every level
+pub fn canonical_json(v: &Value) -> String {
+    fn write(v: &Value, out: &mut String) {
+        match v {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                out.push('{');
+                for (i, k) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&Value::String(k.clone()).to_string());
+                    out.push(':');
+                    write(&map[k], out);
+                }
+                out.push('}');
+            }
+            Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write(item, out);
+                }
+                out.push(']');
+            }
+            scalar => out.push_str(&scalar.to_string()),
+        }
+    }
+    let mut out = String::new();
+    write(v, &mut out);
+    out
+}
+
+/// Hex sha256 of [`canonical_json`]
+pub fn params_hash(params: &Value) -> String {
+    hex::encode(Sha256::digest(canonical_json(params).as_bytes()))
+}
+
+/// Register a run with `params`; returns the version it runs as
+pub async fn record_run(
+    db: &PgPool,
+    strategy_id: Uuid,
+    params: &Value,
+) -> Result<i32, sqlx::Error> {
+    let hash = params_hash(params);
+    let mut tx = db.begin().await?;
+    // serialise version assignment per strategy
+    sqlx::query("SELECT 1 FROM user_strategies WHERE strategy_id = $1 FOR UPDATE")
+        .bind(strategy_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let version: i32 = sqlx::query_scalar(
+        r#"
+        INSERT INTO strategy_params_history (strategy_id, version, params_hash, params)
+        SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3
+          FROM strategy_params_history
+         WHERE strategy_id = $1
+        ON CONFLICT (strategy_id, params_hash) DO UPDATE
+           SET last_run_at = now()
+        RETURNING version
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(&hash)
+    .bind(params)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE user_strategies SET params_hash = $1 WHERE strategy_id = $2")
+        .bind(&hash)
+        .bind(strategy_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(version)
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ParamsVersion {
+    pub version: i32,
+    pub params_hash: String,
+    pub params: Value,
+    pub first_run_at: DateTime<Utc>,
+    pub last_run_at: DateTime<Utc>,
+    /// The version the strategy currently runs with
+    pub live: bool,
+    pub orders: i64,
+    /// Realised PnL booked while this version was live
+    pub realized_pnl: f64,
+    pub closing_trades: i64,
+}
+
+/// Every version of the strategy with its performance, oldest first.
+/// `Ok(None)` when the strategy isn't the user's.
+pub async fn versions(
+    db: &PgPool,
+    user_id: i64,
+    strategy_id: Uuid,
+) -> Result<Option<Vec<ParamsVersion>>, sqlx::Error> {
+    let live: Option<Option<String>> = sqlx::query_scalar(
+        "SELECT params_hash::text FROM user_strategies WHERE strategy_id = $1 AND user_id = $2",
+    )
+    .bind(strategy_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    let Some(live) = live else {
+        return Ok(None);
+    };
+
+    let rows = sqlx::query_as::<_, ParamsVersion>(
+        r#"
+        SELECT h.version, h.params_hash::text AS params_hash, h.params,
+               h.first_run_at, h.last_run_at,
+               h.params_hash = $2 AS live,
+               (SELECT COUNT(*) FROM orders o
+                 WHERE o.strategy_id = h.strategy_id
+                   AND o.params_hash = h.params_hash)               AS orders,
+               COALESCE(p.pnl, 0)::float8                           AS realized_pnl,
+               COALESCE(p.n, 0)                                     AS closing_trades
+          FROM strategy_params_history h
+          LEFT JOIN (SELECT params_hash, SUM(pnl) AS pnl, COUNT(*) AS n
+                       FROM strategy_pnl
+                      WHERE strategy_id = $1
+                      GROUP BY params_hash) p
+                 ON p.params_hash = h.params_hash
+         WHERE h.strategy_id = $1
+         ORDER BY h.version
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(live.unwrap_or_default())
+    .fetch_all(db)
+    .await?;
+    Ok(Some(rows))
+}
+
+/// Put `version`'s params back on the strategy. The caller respawns the
+/// task; its next run re-registers the (unchanged) version.
+pub async fn revert(
+    db: &PgPool,
+    user_id: i64,
+    strategy_id: Uuid,
+    version: i32,
+) -> Result<Value, ParamsHistoryError> {
+    let owned: Option<bool> = sqlx::query_scalar(
+        "SELECT true FROM user_strategies WHERE strategy_id = $1 AND user_id = $2",
+    )
+    .bind(strategy_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    if owned.is_none() {
+        return Err(ParamsHistoryError::StrategyNotFound);
+    }
+
+    let row: Option<(Value, String)> = sqlx::query_as(
+        r#"
+        SELECT params, params_hash::text
+          FROM strategy_params_history
+         WHERE strategy_id = $1 AND version = $2
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(version)
+    .fetch_optional(db)
+    .await?;
+    let Some((params, hash)) = row else {
+        return Err(ParamsHistoryError::VersionNotFound(version));
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE user_strategies
+           SET params = $1, params_hash = $2
+         WHERE strategy_id = $3 AND user_id = $4
+        "#,
+    )
+    .bind(&params)
+    .bind(&hash)
+    .bind(strategy_id)
+    .bind(user_id)
+    .execute(db)
+    .await?;
+    Ok(params)
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn key_order_does_not_change_the_hash() {
+        let a = json!({ "fast": 10, "slow": { "period": 50, "kind": "ema" } });
+        let b: Value =
+            serde_json::from_str(r#"{ "slow": {"kind":"ema","period":50}, "fast": 10 }"#).unwrap();
+        assert_eq!(params_hash(&a), params_hash(&b));
+        assert_eq!(
+            canonical_json(&a),
+            r#"{"fast":10,"slow":{"kind":"ema","period":50}}"#
+        );
+    }
+
+    #[test]
+    fn any_value_change_does() {
+        let a = json!({ "fast": 10, "levels": [1, 2] });
+        assert_ne!(
+            params_hash(&a),
+            params_hash(&json!({ "fast": 11, "levels": [1, 2] }))
+        );
+        assert_ne!(
+            params_hash(&a),
+            params_hash(&json!({ "fast": 10, "levels": [2, 1] }))
+        );
+        assert_ne!(
+            params_hash(&a),
+            params_hash(&json!({ "fast": "10", "levels": [1, 2] }))
+        );
+    }
+
+    #[test]
+    fn hash_is_hex_sha256() {
+        let h = params_hash(&json!({}));
+        assert_eq!(h.len(), 64);
+        // sha256("{}")
+        assert_eq!(
+            h,
+            "44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Portfolio – live account equity for position sizing
+//! ──────────────────────────────────────────────────────────────────────────
+//! * A user's equity is their USDT equity from a live `get_balance`, else
+//!   the latest `balances` snapshot (written by `exposure`); whichever was
+//!   found is kept for `EQUITY_TTL` so sizing every bar doesn't hit the
+//!   exchange each time
+//! * A strategy with an allocation sizes off the allocation's equity, the
+//!   rest off the account ([`Portfolio::for_strategy`])
+//! * [`size_for`] risks `risk_pct` of equity over the stop distance;
+//!   strategies ask through [`Sizer`] so tests can pin the size
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::{allocation, exchanges, exposure, trading_engine::Exchange};
+
+/// How long one equity reading is reused
+const EQUITY_TTL: Duration = Duration::from_secs(60);
+
+/// Latest equity per user and when it was read
+static SNAPSHOTS: Lazy<DashMap<i64, (f64, Instant)>> = Lazy::new(DashMap::new);
+
+/// Size that loses `risk_pct` of `equity` if price moves `stop_distance`
+/// against it; `None` when any input makes that meaningless
+pub fn size_for(equity: f64, risk_pct: f64, stop_distance: f64) -> Option<f64> {
+    let ok = |v: f64| v.is_finite() && v > 0.0;
+    if !(ok(equity) && ok(risk_pct) && ok(stop_distance)) {
+        return None;
+    }
+    Some(equity * risk_pct / stop_distance)
+}
+
+/// Equity-based sizing as strategies see it
+#[async_trait]
+pub trait Sizer: Send + Sync {
+    /// Size risking `risk_pct` of equity over `stop_distance`; `None` when
+    /// no equity is known
+    async fn position_size(&self, user_id: i64, risk_pct: f64, stop_distance: f64) -> Option<f64>;
+}
+
+/// Never sizes – callers fall back to their configured qty
+pub struct FixedQty;
+
+#[async_trait]
+impl Sizer for FixedQty {
+    async fn position_size(&self, _: i64, _: f64, _: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// Where equity comes from for one strategy (or the bare account)
+#[derive(Clone)]
+pub struct Portfolio {
+    db: PgPool,
+    strategy_id: Option<Uuid>,
+    is_demo: bool,
+    master_key: Vec<u8>,
+}
+
+impl Portfolio {
+    pub fn new(db: PgPool, is_demo: bool, master_key: Vec<u8>) -> Self {
+        Self {
+            db,
+            strategy_id: None,
+            is_demo,
+            master_key,
+        }
+    }
+
+    /// Sizes off `strategy_id`'s allocation when it has one
+    pub fn for_strategy(mut self, strategy_id: Uuid) -> Self {
+        self.strategy_id = Some(strategy_id);
+        self
+    }
+
+    /// The account's USDT equity, or the strategy's allocated equity
+    pub async fn equity(&self, user_id: i64) -> Option<f64> {
+        let account = account_equity(&self.db, user_id, self.is_demo, &self.master_key).await;
+        match (self.strategy_id, account) {
+            (Some(id), Some(account)) => Some(allocation::equity(&self.db, id, account).await),
+            (Some(id), None) => match allocation::get(&self.db, id).await {
+                Ok(a) => a.map(|a| a.equity()),
+                Err(e) => {
+                    tracing::warn!("portfolio: allocation for {id}: {e}");
+                    None
+                }
+            },
+            (None, account) => account,
+        }
+    }
+}
+
+#[async_trait]
+impl Sizer for Portfolio {
+    async fn position_size(&self, user_id: i64, risk_pct: f64, stop_distance: f64) -> Option<f64> {
+        size_for(self.equity(user_id).await?, risk_pct, stop_distance)
+    }
+}
+
+/// Live USDT equity, else the last stored one; cached for `EQUITY_TTL`
+pub async fn account_equity(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Option<f64> {
+    if let Some(s) = SNAPSHOTS.get(&user_id) {
+        if s.1.elapsed() < EQUITY_TTL {
+            return Some(s.0);
+        }
+    }
+    let equity = match live_equity(db, user_id, is_demo, master_key).await {
+        Some(e) => Some(e),
+        None => stored_equity(db, user_id).await,
+    }?;
+    SNAPSHOTS.insert(user_id, (equity, Instant::now()));
+    Some(equity)
+}
+
+async fn live_equity(db: &PgPool, user_id: i64, is_demo: bool, master_key: &[u8]) -> Option<f64> {
+    let pulled = async {
+        let adapter = exchanges::connect(db, user_id, Exchange::Blowfin).await?;
+        adapter.get_balance(db, user_id, is_demo, master_key).await
+    };
+    match pulled.await {
+        Ok(balance) => exposure::parse_balance(&balance)
+            .map(|b| b.equity)
+            .filter(|e| *e > 0.0),
+        Err(e) => {
+            tracing::debug!("portfolio: live balance for user {user_id}: {e}");
+            None
+        }
+    }
+}
+
+async fn stored_equity(db: &PgPool, user_id: i64) -> Option<f64> {
+    let row = sqlx::query_scalar::<_, Option<f64>>(
+        r#"
+        SELECT equity::float8
+          FROM balances
+         WHERE user_id = $1 AND currency = 'USDT'
+         ORDER BY captured_at DESC
+         LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(db)
+    .await;
+    match row {
+        Ok(equity) => equity.flatten().filter(|e| *e > 0.0),
+        Err(e) => {
+            tracing::warn!("portfolio: stored balance for user {user_id}: {e}");
+            None
+        }
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_risks_a_share_of_equity_over_the_stop() {
+        // 1 % of 50k over a 500 stop
+        assert_eq!(size_for(50_000.0, 0.01, 500.0), Some(1.0));
+        assert_eq!(size_for(0.0, 0.01, 500.0), None);
+        assert_eq!(size_for(50_000.0, 0.01, 0.0), None);
+        assert_eq!(size_for(50_000.0, f64::NAN, 500.0), None);
+    }
+}
@@ -0,0 +1,428 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Portfolio at time T – what the account held at a past moment
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Per exchange, the latest `positions` / `balances` snapshot taken at or
+//!   before T (written by `exposure`) is the starting point; fills executed
+//!   after it, up to T, are replayed on top. An exchange without a snapshot
+//!   that early is rebuilt from its first fill, with no known balance
+//! * The balance moves by what the replayed fills realised (PnL less trade
+//!   and funding fees) and by the settled deposits / withdrawals in between;
+//!   unrealised PnL is the snapshot's and is dropped for a position the
+//!   replay changed – there is no mark to re-price it against
+//! * Open orders are those placed by T and not closed by then, with the
+//!   size still unfilled at T
+//! * Everything is read in one read-only `REPEATABLE READ` transaction, so
+//!   writes landing meanwhile can't mix two states into the answer
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::services::market_data::bus_symbol;
+
+/// Open quantity below this is float dust
+const QTY_EPSILON: f64 = 1e-12;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeldPosition {
+    pub exchange: String,
+    pub symbol: String,
+    /// Signed: positive long, negative short
+    pub qty: f64,
+    pub avg_price: Option<f64>,
+    /// The snapshot's figure; `None` once a replayed fill changed the position
+    pub unrealised_pnl: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeldBalance {
+    pub exchange: String,
+    pub currency: String,
+    pub equity: Option<f64>,
+    pub available: Option<f64>,
+    /// When the snapshot it was rolled forward from was taken
+    pub snapshot_at: DateTime<Utc>,
+    /// Realised PnL, fees and transfers between the snapshot and T
+    pub cash_flow: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct OpenOrder {
+    pub order_id: Uuid,
+    pub exchange: String,
+    pub symbol: String,
+    pub side: String,
+    pub price: Option<f64>,
+    pub size: f64,
+    /// Filled by T
+    pub filled: f64,
+    pub reduce_only: bool,
+    pub opened_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioAt {
+    pub at: DateTime<Utc>,
+    pub positions: Vec<HeldPosition>,
+    pub balances: Vec<HeldBalance>,
+    pub open_orders: Vec<OpenOrder>,
+    /// Fills replayed on top of the snapshots
+    pub fills_replayed: usize,
+}
+
+#[derive(FromRow)]
+struct SnapshotBalance {
+    exchange: String,
+    captured_at: DateTime<Utc>,
+    equity: Option<f64>,
+    available: Option<f64>,
+}
+
+#[derive(FromRow)]
+struct SnapshotPosition {
+    exchange: String,
+    symbol: String,
+    qty: f64,
+    avg_price: Option<f64>,
+    unrealised_pnl: Option<f64>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct Fill {
+    exchange: String,
+    symbol: String,
+    side: String,
+    price: f64,
+    size: f64,
+    /// Realised PnL less trade and funding fees
+    cash: f64,
+}
+
+/// One position being rolled forward
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Book {
+    qty: f64,
+    avg_price: Option<f64>,
+}
+
+impl Book {
+    /// Apply a fill of signed `qty` at `price`: adding keeps a size-weighted
+    /// entry, reducing keeps it, flipping starts over at the fill price
+    fn apply(&mut self, qty: f64, price: f64) {
+        let next = self.qty + qty;
+        if next.abs() < QTY_EPSILON {
+            *self = Book::default();
+            return;
+        }
+        let adding = self.qty.abs() < QTY_EPSILON || self.qty.signum() == qty.signum();
+        self.avg_price = if adding {
+            let entry = self.avg_price.unwrap_or(price);
+            Some((entry * self.qty.abs() + price * qty.abs()) / next.abs())
+        } else if self.qty.signum() != next.signum() {
+            Some(price)
+        } else {
+            self.avg_price
+        };
+        self.qty = next;
+    }
+}
+
+fn signed(side: &str, size: f64) -> f64 {
+    if side.eq_ignore_ascii_case("sell") {
+        -size
+    } else {
+        size
+    }
+}
+
+/// Positions, balances and open orders of `user_id` as of `at`
+pub async fn as_of(
+    db: &PgPool,
+    user_id: i64,
+    at: DateTime<Utc>,
+) -> Result<PortfolioAt, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+    let out = read(&mut tx, user_id, at).await;
+    // nothing was written; ending it either way is all that's left
+    tx.rollback().await?;
+    out
+}
+
+async fn read(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: i64,
+    at: DateTime<Utc>,
+) -> Result<PortfolioAt, sqlx::Error> {
+    let snapshots = sqlx::query_as::<_, SnapshotBalance>(
+        r#"
+        SELECT DISTINCT ON (exchange)
+               exchange, captured_at,
+               equity::float8 AS equity, available::float8 AS available
+          FROM balances
+         WHERE user_id = $1 AND currency = 'USDT' AND captured_at <= $2
+         ORDER BY exchange, captured_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(at)
+    .fetch_all(&mut **tx)
+    .await?;
+    let (exchanges, taken): (Vec<String>, Vec<DateTime<Utc>>) = snapshots
+        .iter()
+        .map(|s| (s.exchange.clone(), s.captured_at))
+        .unzip();
+
+    let held = sqlx::query_as::<_, SnapshotPosition>(
+        r#"
+        SELECT p.exchange, p.symbol,
+               (CASE WHEN p.side = 'short' THEN -p.size ELSE p.size END)::float8 AS qty,
+               p.avg_entry_price::float8 AS avg_price,
+               p.unrealised_pnl::float8  AS unrealised_pnl
+          FROM positions p
+          JOIN UNNEST($2::text[], $3::timestamptz[]) AS s(exchange, captured_at)
+            ON s.exchange = p.exchange AND s.captured_at = p.captured_at
+         WHERE p.user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&exchanges)
+    .bind(&taken)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    // fills after each exchange's snapshot, or all of them without one
+    let fills = sqlx::query_as::<_, Fill>(
+        r#"
+        SELECT o.exchange, o.symbol, o.side,
+               f.fill_price::float8 AS price,
+               f.fill_size::float8  AS size,
+               (COALESCE(f.realised_pnl, 0) - COALESCE(f.trade_fee, 0)
+                 - COALESCE(f.funding_fee, 0))::float8 AS cash
+          FROM fills f
+          JOIN orders o USING (order_id)
+          LEFT JOIN UNNEST($2::text[], $3::timestamptz[]) AS s(exchange, captured_at)
+            ON s.exchange = o.exchange
+         WHERE o.user_id = $1
+           AND f.executed_at <= $4
+           AND (s.captured_at IS NULL OR f.executed_at > s.captured_at)
+         ORDER BY f.executed_at, f.fill_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(&exchanges)
+    .bind(&taken)
+    .bind(at)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let transfers: Vec<(String, f64)> = sqlx::query_as(
+        r#"
+        SELECT t.exchange,
+               SUM(CASE WHEN t.kind = 'deposit' THEN t.amount
+                        ELSE -(t.amount + t.fee) END)::float8
+          FROM transfers t
+          JOIN UNNEST($2::text[], $3::timestamptz[]) AS s(exchange, captured_at)
+            ON s.exchange = t.exchange
+         WHERE t.user_id = $1 AND t.settled AND t.currency = 'USDT'
+           AND t.occurred_at > s.captured_at AND t.occurred_at <= $4
+         GROUP BY t.exchange
+        "#,
+    )
+    .bind(user_id)
+    .bind(&exchanges)
+    .bind(&taken)
+    .bind(at)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let open_orders = sqlx::query_as::<_, OpenOrder>(
+        r#"
+        SELECT o.order_id, o.exchange, o.symbol, o.side,
+               o.price::float8 AS price,
+               o.size::float8  AS size,
+               COALESCE((SELECT SUM(f.fill_size) FROM fills f
+                          WHERE f.order_id = o.order_id AND f.executed_at <= $2), 0)::float8
+                               AS filled,
+               COALESCE(o.reduce_only, false) AS reduce_only,
+               o.opened_at
+          FROM orders o
+         WHERE o.user_id = $1
+           AND o.opened_at <= $2
+           AND (o.closed_at IS NULL OR o.closed_at > $2)
+           AND o.status <> 'rejected'
+         ORDER BY o.opened_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(at)
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .filter(|o| o.size - o.filled > QTY_EPSILON)
+    .collect();
+
+    Ok(assemble(
+        at,
+        snapshots,
+        held,
+        &fills,
+        transfers,
+        open_orders,
+    ))
+}
+
+fn assemble(
+    at: DateTime<Utc>,
+    snapshots: Vec<SnapshotBalance>,
+    held: Vec<SnapshotPosition>,
+    fills: &[Fill],
+    transfers: Vec<(String, f64)>,
+    open_orders: Vec<OpenOrder>,
+) -> PortfolioAt {
+    let mut books: BTreeMap<(String, String), (Book, Option<f64>)> = BTreeMap::new();
+    for p in held {
+        let book = Book {
+            qty: p.qty,
+            avg_price: p.avg_price,
+        };
+        books.insert(
+            (p.exchange, bus_symbol(&p.symbol)),
+            (book, p.unrealised_pnl),
+        );
+    }
+    let mut cash: BTreeMap<String, f64> = transfers.into_iter().collect();
+    for f in fills {
+        let key = (f.exchange.clone(), bus_symbol(&f.symbol));
+        let (book, upnl) = books.entry(key).or_default();
+        book.apply(signed(&f.side, f.size), f.price);
+        *upnl = None;
+        *cash.entry(f.exchange.clone()).or_default() += f.cash;
+    }
+
+    let positions = books
+        .into_iter()
+        .filter(|(_, (b, _))| b.qty.abs() >= QTY_EPSILON)
+        .map(|((exchange, symbol), (b, upnl))| HeldPosition {
+            exchange,
+            symbol,
+            qty: b.qty,
+            avg_price: b.avg_price,
+            unrealised_pnl: upnl,
+        })
+        .collect();
+    let balances = snapshots
+        .into_iter()
+        .map(|s| {
+            let flow = cash.get(&s.exchange).copied().unwrap_or_default();
+            HeldBalance {
+                currency: "USDT".into(),
+                equity: s.equity.map(|e| e + flow),
+                available: s.available.map(|a| a + flow),
+                snapshot_at: s.captured_at,
+                cash_flow: flow,
+                exchange: s.exchange,
+            }
+        })
+        .collect();
+
+    PortfolioAt {
+        at,
+        positions,
+        balances,
+        open_orders,
+        fills_replayed: fills.len(),
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_roll_a_position_forward() {
+        let mut b = Book::default();
+        b.apply(1.0, 100.0);
+        b.apply(1.0, 110.0);
+        assert_eq!(
+            b,
+            Book {
+                qty: 2.0,
+                avg_price: Some(105.0)
+            }
+        );
+        b.apply(-0.5, 120.0);
+        assert_eq!(
+            b,
+            Book {
+                qty: 1.5,
+                avg_price: Some(105.0)
+            }
+        );
+        // through flat: the short left over is entered at the fill
+        b.apply(-2.0, 90.0);
+        assert_eq!(
+            b,
+            Book {
+                qty: -0.5,
+                avg_price: Some(90.0)
+            }
+        );
+        b.apply(0.5, 80.0);
+        assert_eq!(b, Book::default());
+    }
+
+    #[test]
+    fn snapshot_plus_replay() {
+        let t0 = Utc::now() - chrono::Duration::hours(2);
+        let snapshots = vec![SnapshotBalance {
+            exchange: "blowfin".into(),
+            captured_at: t0,
+            equity: Some(1_000.0),
+            available: Some(800.0),
+        }];
+        let held = vec![
+            SnapshotPosition {
+                exchange: "blowfin".into(),
+                symbol: "BTC-USDT".into(),
+                qty: 0.1,
+                avg_price: Some(60_000.0),
+                unrealised_pnl: Some(5.0),
+            },
+            SnapshotPosition {
+                exchange: "blowfin".into(),
+                symbol: "ETH-USDT".into(),
+                qty: -1.0,
+                avg_price: Some(3_000.0),
+                unrealised_pnl: Some(-2.0),
+            },
+        ];
+        let fills = vec![Fill {
+            exchange: "blowfin".into(),
+            symbol: "BTC-USDT".into(),
+            side: "sell".into(),
+            price: 61_000.0,
+            size: 0.1,
+            cash: 99.0,
+        }];
+        let transfers = vec![("blowfin".to_string(), -50.0)];
+
+        let p = assemble(Utc::now(), snapshots, held, &fills, transfers, vec![]);
+        assert_eq!(p.positions.len(), 1);
+        assert_eq!(p.positions[0].symbol, "ETHUSDT");
+        assert_eq!(p.positions[0].unrealised_pnl, Some(-2.0));
+        assert_eq!(p.balances[0].cash_flow, 49.0);
+        assert_eq!(p.balances[0].equity, Some(1_049.0));
+        assert_eq!(p.fills_replayed, 1);
+    }
+}
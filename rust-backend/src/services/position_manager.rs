@@ -0,0 +1,654 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Position manager – shared trade-management rules
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Stop exit   – close whatever is left once the (possibly moved) stop trades;
+//!   when the exchange holds a native stop at that price it has already
+//!   closed the position ([`ExitReason::NativeStop`]) and no order is due
+//! * Target      – close what's left once the position's target trades
+//! * Break-even  – pull the stop up to entry after price has travelled `n` R
+//! * Partial TP  – scale out fractions of the position at given R multiples
+//! * Trailing    – once `n` R in profit, keep the stop a fixed distance (in
+//!   R) behind the best price since entry; it only ever tightens
+//! * Max hold    – flatten after `n` bars / hours regardless of PnL
+//!
+//! Strategies opt in by embedding [`TradeMgmt`] in their params
+//! (`"mgmt": { "break_even_at_r": 1.0, "partial_tps": [...],
+//! "trailing_stop": { "activate_at_r": 1.5, "distance_r": 1.0 } }`) and feeding
+//! every candle through [`ManagedPosition::on_candle`]. The manager only
+//! *decides*; the caller turns the returned actions into reduce-only orders.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::services::strategies::Candle;
+
+/// Sizes below this are treated as fully closed (float dust).
+const SIZE_EPS: f64 = 1e-12;
+
+/// ─── Shared params ───────────────────────────────────────────────────────
+/// One rung of the scale-out ladder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartialTp {
+    /// Trigger distance in R (1.0 = initial risk)
+    pub at_r: f64,
+    /// Fraction of the *original* size to close (0–1)
+    pub fraction: f64,
+}
+
+/// Trail the stop behind the best price once the trade is far enough along.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrailingStop {
+    /// Start trailing once the best price is this many R in profit
+    #[serde(default)]
+    pub activate_at_r: f64,
+    /// Distance kept between the best price and the stop, in R
+    pub distance_r: f64,
+}
+
+/// Per-strategy trade-management settings; everything is off by default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeMgmt {
+    /// Move the stop to entry once price reaches this many R (typically 1.0)
+    #[serde(default)]
+    pub break_even_at_r: Option<f64>,
+    /// Scale-out ladder, evaluated in ascending `at_r` order
+    #[serde(default)]
+    pub partial_tps: Vec<PartialTp>,
+    /// Close the remainder after this many candles since entry, counted
+    /// on whatever feed the strategy manages on
+    #[serde(default)]
+    pub max_hold_bars: Option<u32>,
+    /// Close the remainder once this many hours have passed since entry
+    /// (measured on candle timestamps, not wall-clock)
+    #[serde(default)]
+    pub max_hold_hours: Option<f64>,
+    #[serde(default)]
+    pub trailing_stop: Option<TrailingStop>,
+}
+
+impl TradeMgmt {
+    /// True when at least one rule is switched on
+    pub fn is_set(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// ─── Position state ──────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Long,
+    Short,
+}
+
+impl Side {
+    /// Order side that reduces a position on this side
+    pub fn exit_side(&self) -> &'static str {
+        match self {
+            Side::Long => "sell",
+            Side::Short => "buy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Stop,
+    /// The exchange's own stop-loss closed the position
+    NativeStop,
+    Target,
+    MaxHold,
+}
+
+/// What the caller should do after a candle has been evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MgmtAction {
+    MoveStop { from: f64, to: f64 },
+    PartialClose { size: f64, at_r: f64 },
+    Close { size: f64, reason: ExitReason },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagedPosition {
+    pub symbol: String,
+    pub side: Side,
+    pub entry: f64,
+    pub initial_stop: f64,
+    pub stop: f64,
+    /// Size at entry
+    pub size: f64,
+    /// Size still open after partial closes
+    pub remaining: f64,
+    pub opened_at: DateTime<Utc>,
+    /// Candles seen since entry
+    pub bars_held: u32,
+    /// Trigger of the stop-loss the exchange is known to hold
+    pub native_stop: Option<f64>,
+    /// Take the rest off here; `None` = no fixed target
+    #[serde(default)]
+    pub target: Option<f64>,
+    tps_done: usize,
+    be_done: bool,
+    /// Most favourable price since entry, for the trailing stop
+    #[serde(default)]
+    best: Option<f64>,
+}
+
+impl ManagedPosition {
+    pub fn open(
+        symbol: impl Into<String>,
+        side: Side,
+        entry: f64,
+        stop: f64,
+        size: f64,
+        opened_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            symbol: symbol.into(),
+            side,
+            entry,
+            initial_stop: stop,
+            stop,
+            size,
+            remaining: size,
+            opened_at,
+            bars_held: 0,
+            native_stop: None,
+            target: None,
+            tps_done: 0,
+            be_done: false,
+            best: None,
+        }
+    }
+
+    pub fn with_target(mut self, target: f64) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Initial risk per unit (1 R)
+    pub fn risk(&self) -> f64 {
+        (self.entry - self.initial_stop).abs()
+    }
+
+    /// Favourable excursion of `price` expressed in R
+    pub fn r_multiple(&self, price: f64) -> f64 {
+        let risk = self.risk();
+        if risk <= f64::EPSILON {
+            return 0.0;
+        }
+        match self.side {
+            Side::Long => (price - self.entry) / risk,
+            Side::Short => (self.entry - price) / risk,
+        }
+    }
+
+    /// Record that the exchange holds a stop-loss at `trigger`
+    pub fn attach_native(&mut self, trigger: f64) {
+        self.native_stop = Some(trigger);
+    }
+
+    /// True while the exchange's stop differs from the soft one (moved,
+    /// never placed, or not confirmed)
+    pub fn needs_sync(&self) -> bool {
+        self.native_stop != Some(self.stop)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.remaining <= SIZE_EPS
+    }
+
+    /// True once either holding-time limit has been reached at `now`
+    fn hold_expired(&self, mgmt: &TradeMgmt, now: DateTime<Utc>) -> bool {
+        let by_bars = mgmt.max_hold_bars.is_some_and(|n| self.bars_held >= n);
+        let by_time = mgmt.max_hold_hours.is_some_and(|h| {
+            let held = (now - self.opened_at).num_seconds() as f64 / 3600.0;
+            held >= h
+        });
+        by_bars || by_time
+    }
+
+    /// Apply the rules to one candle and return the resulting actions.
+    ///
+    /// The adverse extreme is checked first: when a bar spans both the stop
+    /// and a profit level we assume the worse outcome happened. A trailing
+    /// move takes effect from the next bar; the holding-time limit is
+    /// applied last, at the bar's close.
+    pub fn on_candle(&mut self, c: &Candle, mgmt: &TradeMgmt) -> Vec<MgmtAction> {
+        let mut out = Vec::new();
+        if self.is_closed() {
+            return out;
+        }
+        self.bars_held += 1;
+
+        let (adverse, favourable) = match self.side {
+            Side::Long => (c.low, c.high),
+            Side::Short => (c.high, c.low),
+        };
+
+        // 1. Stop
+        let stop_hit = match self.side {
+            Side::Long => adverse <= self.stop,
+            Side::Short => adverse >= self.stop,
+        };
+        if stop_hit {
+            let reason = if self.needs_sync() {
+                ExitReason::Stop
+            } else {
+                ExitReason::NativeStop
+            };
+            out.push(MgmtAction::Close {
+                size: self.remaining,
+                reason,
+            });
+            self.remaining = 0.0;
+            return out;
+        }
+
+        self.apply_r_rules(favourable, mgmt, &mut out);
+
+        // 4. Target
+        let target_hit = self.target.is_some_and(|t| match self.side {
+            Side::Long => favourable >= t,
+            Side::Short => favourable <= t,
+        });
+        if target_hit && !self.is_closed() {
+            out.push(MgmtAction::Close {
+                size: self.remaining,
+                reason: ExitReason::Target,
+            });
+            self.remaining = 0.0;
+            return out;
+        }
+
+        // 5. Trailing stop
+        if let Some(trail) = &mgmt.trailing_stop {
+            self.trail(favourable, trail, &mut out);
+        }
+
+        // 6. Max holding time
+        if !self.is_closed() && self.hold_expired(mgmt, c.ts) {
+            out.push(MgmtAction::Close {
+                size: self.remaining,
+                reason: ExitReason::MaxHold,
+            });
+            self.remaining = 0.0;
+        }
+
+        out
+    }
+
+    /// Follow the best price since entry with the stop, never loosening it
+    fn trail(&mut self, favourable: f64, trail: &TrailingStop, out: &mut Vec<MgmtAction>) {
+        if self.is_closed() || self.risk() <= f64::EPSILON {
+            return;
+        }
+        let best = match (self.side, self.best) {
+            (Side::Long, Some(b)) => b.max(favourable),
+            (Side::Short, Some(b)) => b.min(favourable),
+            (_, None) => favourable,
+        };
+        self.best = Some(best);
+        if self.r_multiple(best) < trail.activate_at_r {
+            return;
+        }
+        let gap = trail.distance_r.max(0.0) * self.risk();
+        let (to, tighter) = match self.side {
+            Side::Long => (best - gap, best - gap > self.stop),
+            Side::Short => (best + gap, best + gap < self.stop),
+        };
+        if tighter {
+            out.push(MgmtAction::MoveStop {
+                from: self.stop,
+                to,
+            });
+            self.stop = to;
+        }
+    }
+
+    /// Partial take-profits and break-even, driven by the bar's favourable extreme
+    fn apply_r_rules(&mut self, favourable: f64, mgmt: &TradeMgmt, out: &mut Vec<MgmtAction>) {
+        // R-based rules are meaningless without a valid initial risk
+        if self.risk() <= f64::EPSILON {
+            return;
+        }
+        let r = self.r_multiple(favourable);
+
+        // 2. Partial take-profits
+        let mut ladder = mgmt.partial_tps.clone();
+        ladder.sort_by(|a, b| a.at_r.total_cmp(&b.at_r));
+        while let Some(tp) = ladder.get(self.tps_done) {
+            if r < tp.at_r {
+                break;
+            }
+            let size = (self.size * tp.fraction.clamp(0.0, 1.0)).min(self.remaining);
+            self.tps_done += 1;
+            if size > SIZE_EPS {
+                self.remaining -= size;
+                out.push(MgmtAction::PartialClose {
+                    size,
+                    at_r: tp.at_r,
+                });
+            }
+            if self.is_closed() {
+                return;
+            }
+        }
+
+        // 3. Break-even
+        if let Some(be_r) = mgmt.break_even_at_r {
+            if !self.be_done && r >= be_r {
+                self.be_done = true;
+                let better = match self.side {
+                    Side::Long => self.entry > self.stop,
+                    Side::Short => self.entry < self.stop,
+                };
+                if better {
+                    out.push(MgmtAction::MoveStop {
+                        from: self.stop,
+                        to: self.entry,
+                    });
+                    self.stop = self.entry;
+                }
+            }
+        }
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(high: f64, low: f64) -> Candle {
+        Candle {
+            high,
+            low,
+            close: (high + low) / 2.0,
+            ..Default::default()
+        }
+    }
+
+    fn long() -> ManagedPosition {
+        // entry 100, stop 90 → 1 R = 10
+        ManagedPosition::open("BTCUSDT", Side::Long, 100.0, 90.0, 1.0, Utc::now())
+    }
+
+    fn mgmt() -> TradeMgmt {
+        TradeMgmt {
+            break_even_at_r: Some(1.0),
+            partial_tps: vec![
+                PartialTp {
+                    at_r: 2.0,
+                    fraction: 0.5,
+                },
+                PartialTp {
+                    at_r: 1.0,
+                    fraction: 0.25,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_rules_means_no_actions() {
+        let mut p = long();
+        assert!(p
+            .on_candle(&bar(130.0, 95.0), &TradeMgmt::default())
+            .is_empty());
+        assert_eq!(p.stop, 90.0);
+    }
+
+    #[test]
+    fn stop_hit_closes_everything() {
+        let mut p = long();
+        let acts = p.on_candle(&bar(101.0, 89.0), &mgmt());
+        assert_eq!(
+            acts,
+            vec![MgmtAction::Close {
+                size: 1.0,
+                reason: ExitReason::Stop
+            }]
+        );
+        assert!(p.is_closed());
+    }
+
+    #[test]
+    fn native_stop_at_the_soft_level_owns_the_exit() {
+        let mut p = long();
+        p.attach_native(90.0);
+        assert!(!p.needs_sync());
+        let acts = p.on_candle(&bar(101.0, 89.0), &mgmt());
+        assert_eq!(
+            acts,
+            vec![MgmtAction::Close {
+                size: 1.0,
+                reason: ExitReason::NativeStop
+            }]
+        );
+    }
+
+    #[test]
+    fn moved_stop_falls_back_to_a_soft_exit_until_synced() {
+        let mut p = long();
+        p.attach_native(90.0);
+        let m = TradeMgmt {
+            break_even_at_r: Some(1.0),
+            ..Default::default()
+        };
+        p.on_candle(&bar(110.0, 101.0), &m);
+        assert!(p.needs_sync(), "exchange still holds the old stop");
+        let acts = p.on_candle(&bar(104.0, 99.5), &m);
+        assert!(matches!(
+            acts[..],
+            [MgmtAction::Close {
+                reason: ExitReason::Stop,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn break_even_after_one_r() {
+        let mut p = long();
+        let m = TradeMgmt {
+            break_even_at_r: Some(1.0),
+            ..Default::default()
+        };
+        assert!(p.on_candle(&bar(109.0, 99.0), &m).is_empty());
+        let acts = p.on_candle(&bar(110.0, 101.0), &m);
+        assert_eq!(
+            acts,
+            vec![MgmtAction::MoveStop {
+                from: 90.0,
+                to: 100.0
+            }]
+        );
+        // only once
+        assert!(p.on_candle(&bar(115.0, 101.0), &m).is_empty());
+        // a dip to entry now stops out flat
+        let acts = p.on_candle(&bar(104.0, 99.5), &m);
+        assert!(matches!(acts[0], MgmtAction::Close { .. }));
+    }
+
+    #[test]
+    fn partials_fire_in_r_order() {
+        let mut p = long();
+        let acts = p.on_candle(&bar(121.0, 101.0), &mgmt());
+        assert_eq!(acts.len(), 3);
+        assert_eq!(
+            acts[0],
+            MgmtAction::PartialClose {
+                size: 0.25,
+                at_r: 1.0
+            }
+        );
+        assert_eq!(
+            acts[1],
+            MgmtAction::PartialClose {
+                size: 0.5,
+                at_r: 2.0
+            }
+        );
+        assert!(matches!(acts[2], MgmtAction::MoveStop { .. }));
+        assert!((p.remaining - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn short_side_is_mirrored() {
+        let mut p = ManagedPosition::open("BTCUSDT", Side::Short, 100.0, 110.0, 2.0, Utc::now());
+        let acts = p.on_candle(&bar(99.0, 90.0), &mgmt());
+        assert_eq!(
+            acts[0],
+            MgmtAction::PartialClose {
+                size: 0.5,
+                at_r: 1.0
+            }
+        );
+        assert_eq!(p.stop, 100.0);
+        assert_eq!(p.side.exit_side(), "buy");
+    }
+
+    #[test]
+    fn zero_risk_disables_r_rules() {
+        let mut p = ManagedPosition::open("BTCUSDT", Side::Long, 100.0, 100.0, 1.0, Utc::now());
+        // stop == entry, a bar above entry triggers nothing R-based
+        assert!(p.on_candle(&bar(150.0, 100.5), &mgmt()).is_empty());
+    }
+
+    #[test]
+    fn target_takes_the_rest_after_partials() {
+        let mut p = long().with_target(125.0);
+        assert!(p
+            .on_candle(&bar(109.0, 95.0), &TradeMgmt::default())
+            .is_empty());
+        // both partials and break-even, then the target takes the rest
+        let acts = p.on_candle(&bar(126.0, 101.0), &mgmt());
+        assert_eq!(acts.len(), 4);
+        assert_eq!(
+            acts[3],
+            MgmtAction::Close {
+                size: 0.25,
+                reason: ExitReason::Target
+            }
+        );
+        assert!(p.is_closed());
+
+        // a bar through both levels counts as stopped out
+        let mut p = long().with_target(125.0);
+        let acts = p.on_candle(&bar(126.0, 89.0), &TradeMgmt::default());
+        assert!(matches!(
+            acts[..],
+            [MgmtAction::Close {
+                reason: ExitReason::Stop,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn trailing_stop_follows_the_best_price_and_only_tightens() {
+        let m = TradeMgmt {
+            trailing_stop: Some(TrailingStop {
+                activate_at_r: 1.0,
+                distance_r: 0.5,
+            }),
+            ..Default::default()
+        };
+        let mut p = long();
+        // not far enough along yet
+        assert!(p.on_candle(&bar(105.0, 99.0), &m).is_empty());
+        let acts = p.on_candle(&bar(120.0, 110.0), &m);
+        assert_eq!(
+            acts,
+            vec![MgmtAction::MoveStop {
+                from: 90.0,
+                to: 115.0
+            }]
+        );
+        // a pullback above the stop leaves it where it is
+        assert!(p.on_candle(&bar(118.0, 116.0), &m).is_empty());
+        assert_eq!(p.stop, 115.0);
+        let acts = p.on_candle(&bar(117.0, 114.0), &m);
+        assert!(matches!(
+            acts[..],
+            [MgmtAction::Close {
+                reason: ExitReason::Stop,
+                ..
+            }]
+        ));
+
+        let mut p = ManagedPosition::open("BTCUSDT", Side::Short, 100.0, 110.0, 1.0, Utc::now());
+        p.on_candle(&bar(99.0, 80.0), &m);
+        assert_eq!(p.stop, 85.0);
+    }
+
+    #[test]
+    fn max_hold_bars_flattens_remainder() {
+        let mut p = long();
+        let m = TradeMgmt {
+            max_hold_bars: Some(3),
+            ..Default::default()
+        };
+        assert!(p.on_candle(&bar(101.0, 99.0), &m).is_empty());
+        assert!(p.on_candle(&bar(101.0, 99.0), &m).is_empty());
+        let acts = p.on_candle(&bar(101.0, 99.0), &m);
+        assert_eq!(
+            acts,
+            vec![MgmtAction::Close {
+                size: 1.0,
+                reason: ExitReason::MaxHold
+            }]
+        );
+        assert!(p.is_closed());
+    }
+
+    #[test]
+    fn max_hold_hours_uses_candle_time() {
+        let t0 = Utc::now();
+        let mut p = ManagedPosition::open("BTCUSDT", Side::Long, 100.0, 90.0, 1.0, t0);
+        let m = TradeMgmt {
+            max_hold_hours: Some(8.0),
+            ..Default::default()
+        };
+        let mut c = bar(101.0, 99.0);
+        c.ts = t0 + chrono::Duration::hours(4);
+        assert!(p.on_candle(&c, &m).is_empty());
+        c.ts = t0 + chrono::Duration::hours(8);
+        let acts = p.on_candle(&c, &m);
+        assert!(matches!(
+            acts[..],
+            [MgmtAction::Close {
+                reason: ExitReason::MaxHold,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn stop_takes_precedence_over_max_hold() {
+        let mut p = long();
+        let m = TradeMgmt {
+            max_hold_bars: Some(1),
+            ..Default::default()
+        };
+        let acts = p.on_candle(&bar(101.0, 85.0), &m);
+        assert_eq!(
+            acts,
+            vec![MgmtAction::Close {
+                size: 1.0,
+                reason: ExitReason::Stop
+            }]
+        );
+    }
+
+    #[test]
+    fn mgmt_params_default_when_absent() {
+        let m: TradeMgmt = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(m, TradeMgmt::default());
+    }
+}
@@ -0,0 +1,158 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Public stats – aggregate, non-sensitive numbers for a status page
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Uptime and market-data feeds connected are this instance's; strategies
+//!   running (enabled anywhere) and orders placed in the last 24 h come from
+//!   Postgres – nothing is per user
+//! * The Postgres counts are shared by all instances through the cache
+//!   (`public:stats`) and refreshed every `COUNTS_TTL_SECS`
+//! * Each caller IP gets `PUBLIC_STATS_RATE_PER_MIN` requests per minute
+//!   (a fixed-window counter in the cache); a cache outage lets calls through
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::db::cache::Cache;
+use crate::services::market_data;
+
+const COUNTS_KEY: &str = "public:stats";
+const COUNTS_TTL_SECS: u64 = 30;
+const WINDOW_SECS: i64 = 60;
+
+static STARTED: OnceCell<DateTime<Utc>> = OnceCell::new();
+
+/// Start the uptime clock; call once at boot
+pub fn init() {
+    let _ = STARTED.set(Utc::now());
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Counts {
+    strategies_running: i64,
+    orders_24h: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PublicStats {
+    pub uptime_secs: i64,
+    pub feeds_connected: usize,
+    pub feeds_total: usize,
+    pub strategies_running: i64,
+    pub orders_24h: i64,
+}
+
+async fn counts(db: &PgPool) -> Result<Counts, sqlx::Error> {
+    let (strategies_running, orders_24h): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT (SELECT COUNT(*) FROM user_strategies WHERE status = 'enabled'),
+               (SELECT COUNT(*) FROM orders WHERE opened_at >= now() - interval '24 hours')
+        "#,
+    )
+    .fetch_one(db)
+    .await?;
+    Ok(Counts {
+        strategies_running,
+        orders_24h,
+    })
+}
+
+pub async fn get(db: &PgPool, cache: &dyn Cache) -> Result<PublicStats, sqlx::Error> {
+    let cached = cache
+        .get_json::<Counts>(COUNTS_KEY)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("public stats: cache read: {e}");
+            None
+        });
+    let counts = match cached {
+        Some(c) => c,
+        None => {
+            let c = counts(db).await?;
+            if let Err(e) = cache.set_json(COUNTS_KEY, &c, COUNTS_TTL_SECS).await {
+                tracing::warn!("public stats: cache write: {e}");
+            }
+            c
+        }
+    };
+
+    let feeds = market_data::feeds();
+    let now = Utc::now();
+    Ok(PublicStats {
+        uptime_secs: STARTED.get().map_or(0, |t| (now - *t).num_seconds()),
+        feeds_connected: feeds.iter().filter(|(_, up)| *up).count(),
+        feeds_total: feeds.len(),
+        strategies_running: counts.strategies_running,
+        orders_24h: counts.orders_24h,
+    })
+}
+
+/// The window `now` falls in and the seconds left of it
+fn window(now: DateTime<Utc>) -> (i64, i64) {
+    let t = now.timestamp();
+    (
+        t.div_euclid(WINDOW_SECS),
+        WINDOW_SECS - t.rem_euclid(WINDOW_SECS),
+    )
+}
+
+/// Count a call from `ip`; `Err(retry_after_secs)` once it is over
+/// `per_min` in the minute of `now`. 0 = unlimited.
+pub async fn admit(
+    cache: &dyn Cache,
+    ip: &str,
+    per_min: u32,
+    now: DateTime<Utc>,
+) -> Result<(), i64> {
+    if per_min == 0 {
+        return Ok(());
+    }
+    let (slot, left) = window(now);
+    let key = format!("public:stats:rl:{ip}:{slot}");
+    let n = match cache.incr_by(&key, 1).await {
+        Ok(n) => n,
+        Err(e) => {
+            tracing::warn!("public stats: rate limit counter: {e}");
+            return Ok(());
+        }
+    };
+    if n == 1 {
+        if let Err(e) = cache.expire(&key, WINDOW_SECS as u64).await {
+            tracing::warn!("public stats: rate limit expiry: {e}");
+        }
+    }
+    if n > per_min as i64 {
+        return Err(left);
+    }
+    Ok(())
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::cache::MemoryCache;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn callers_over_the_limit_are_told_when_to_retry() {
+        let cache = MemoryCache::new();
+        let now = Utc.with_ymd_and_hms(2025, 7, 1, 12, 0, 45).unwrap();
+        let ip = "203.0.113.7";
+        for _ in 0..3 {
+            assert_eq!(admit(&cache, ip, 3, now).await, Ok(()));
+        }
+        assert_eq!(admit(&cache, ip, 3, now).await, Err(15));
+        // other callers have their own budget; 0 = unlimited
+        assert_eq!(admit(&cache, "198.51.100.1", 3, now).await, Ok(()));
+        assert_eq!(admit(&cache, ip, 0, now).await, Ok(()));
+        // a new minute, a new budget
+        let later = now + chrono::Duration::seconds(15);
+        assert_eq!(admit(&cache, ip, 3, later).await, Ok(()));
+    }
+}
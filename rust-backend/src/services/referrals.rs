@@ -0,0 +1,365 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Referral program
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Codes       – each user may hold a few active codes (`generate_code`)
+//! * Attribution – a new user redeems one code shortly after signing up
+//! * Ledger      – `referral_rewards` is append-only; balances are sums
+//!
+//! Reward schedule (per referred user):
+//! * signup     → referrer +7 premium days, referee +7 premium days
+//! * conversion → referrer +30 premium days + copy-fee discount (once)
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+/// No 0/O/1/I – codes get read out loud and typed on phones
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LEN: usize = 8;
+const MAX_ACTIVE_CODES: i64 = 5;
+/// A code can only be redeemed this long after the account was created
+pub const ATTRIBUTION_WINDOW_DAYS: i64 = 7;
+
+const SIGNUP_PREMIUM_DAYS: i64 = 7;
+const CONVERSION_PREMIUM_DAYS: i64 = 30;
+const CONVERSION_FEE_DISCOUNT_BPS: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardKind {
+    PremiumDays,
+    FeeDiscountBps,
+}
+
+impl RewardKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            RewardKind::PremiumDays => "premium_days",
+            RewardKind::FeeDiscountBps => "fee_discount_bps",
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReferralError {
+    #[error("unknown or inactive referral code")]
+    UnknownCode,
+    #[error("cannot use your own referral code")]
+    SelfReferral,
+    #[error("referral already recorded")]
+    AlreadyReferred,
+    #[error("referral window has closed")]
+    WindowClosed,
+    #[error("too many active codes (max {MAX_ACTIVE_CODES})")]
+    TooManyCodes,
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// ─── Codes ───────────────────────────────────────────────────────────────
+fn random_code<R: Rng>(rng: &mut R) -> String {
+    (0..CODE_LEN)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Normalise user input: trim, upper-case, drop dashes/spaces
+pub fn normalize_code(raw: &str) -> String {
+    raw.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+pub async fn generate_code(db: &PgPool, user_id: i64) -> Result<String, ReferralError> {
+    let active: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM referral_codes WHERE user_id = $1 AND active")
+            .bind(user_id)
+            .fetch_one(db)
+            .await?;
+    if active >= MAX_ACTIVE_CODES {
+        return Err(ReferralError::TooManyCodes);
+    }
+
+    // collisions are astronomically rare; retry a couple of times anyway
+    for _ in 0..3 {
+        let code = random_code(&mut rand::thread_rng());
+        let inserted = sqlx::query(
+            "INSERT INTO referral_codes (code, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(&code)
+        .bind(user_id)
+        .execute(db)
+        .await?
+        .rows_affected();
+        if inserted == 1 {
+            return Ok(code);
+        }
+    }
+    Err(ReferralError::Db(sqlx::Error::Protocol(
+        "could not allocate a unique referral code".into(),
+    )))
+}
+
+/// ─── Attribution ─────────────────────────────────────────────────────────
+fn within_window(created_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - created_at <= Duration::days(ATTRIBUTION_WINDOW_DAYS)
+}
+
+async fn ledger(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i64,
+    referred: i64,
+    kind: RewardKind,
+    amount: i64,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO referral_rewards (user_id, referred_user_id, kind, amount, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(user_id)
+    .bind(referred)
+    .bind(kind.as_str())
+    .bind(amount)
+    .bind(reason)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Attribute `user_id` (a fresh signup) to the owner of `code`.
+/// Returns the referrer's id.
+pub async fn attribute_signup(db: &PgPool, user_id: i64, code: &str) -> Result<i64, ReferralError> {
+    let code = normalize_code(code);
+    let referrer: i64 =
+        sqlx::query_scalar("SELECT user_id FROM referral_codes WHERE code = $1 AND active")
+            .bind(&code)
+            .fetch_optional(db)
+            .await?
+            .ok_or(ReferralError::UnknownCode)?;
+    if referrer == user_id {
+        return Err(ReferralError::SelfReferral);
+    }
+
+    let created_at: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT created_at FROM users WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(db)
+            .await?
+            .flatten();
+    if !created_at.is_some_and(|t| within_window(t, Utc::now())) {
+        return Err(ReferralError::WindowClosed);
+    }
+
+    let mut tx = db.begin().await?;
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO referrals (referred_user_id, referrer_user_id, code)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (referred_user_id) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(referrer)
+    .bind(&code)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if inserted == 0 {
+        return Err(ReferralError::AlreadyReferred);
+    }
+
+    let days = SIGNUP_PREMIUM_DAYS;
+    ledger(
+        &mut tx,
+        referrer,
+        user_id,
+        RewardKind::PremiumDays,
+        days,
+        "signup",
+    )
+    .await?;
+    ledger(
+        &mut tx,
+        user_id,
+        user_id,
+        RewardKind::PremiumDays,
+        days,
+        "signup",
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(referrer)
+}
+
+/// Credit the referrer when a referred user first pays, inside the caller's
+/// transaction. Idempotent.
+pub async fn on_paid_conversion(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    let referrer: Option<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE referrals
+           SET converted_at = now()
+         WHERE referred_user_id = $1
+           AND converted_at IS NULL
+        RETURNING referrer_user_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    if let Some(referrer) = referrer {
+        let (days, bps) = (CONVERSION_PREMIUM_DAYS, CONVERSION_FEE_DISCOUNT_BPS);
+        ledger(
+            tx,
+            referrer,
+            user_id,
+            RewardKind::PremiumDays,
+            days,
+            "conversion",
+        )
+        .await?;
+        ledger(
+            tx,
+            referrer,
+            user_id,
+            RewardKind::FeeDiscountBps,
+            bps,
+            "conversion",
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// ─── Stats ───────────────────────────────────────────────────────────────
+#[derive(Debug, Serialize, FromRow)]
+pub struct CodeRow {
+    pub code: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub signups: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct RewardRow {
+    pub kind: String,
+    pub amount: f64,
+    pub reason: String,
+    pub referred_user_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferralStats {
+    pub codes: Vec<CodeRow>,
+    pub referred: i64,
+    pub converted: i64,
+    pub premium_days_earned: f64,
+    pub fee_discount_bps_earned: f64,
+    pub recent_rewards: Vec<RewardRow>,
+}
+
+pub async fn stats(db: &PgPool, user_id: i64) -> Result<ReferralStats, sqlx::Error> {
+    let codes = sqlx::query_as::<_, CodeRow>(
+        r#"
+        SELECT c.code, c.active, c.created_at,
+               COUNT(r.referred_user_id) AS signups
+        FROM   referral_codes c
+        LEFT   JOIN referrals r ON r.code = c.code
+        WHERE  c.user_id = $1
+        GROUP  BY c.code
+        ORDER  BY c.created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    let (referred, converted): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*), COUNT(converted_at)
+        FROM   referrals
+        WHERE  referrer_user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+
+    let totals = |kind: RewardKind| {
+        sqlx::query_scalar::<_, f64>(
+            "SELECT COALESCE(SUM(amount), 0)::float8 FROM referral_rewards WHERE user_id = $1 AND kind = $2",
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .fetch_one(db)
+    };
+    let premium_days_earned = totals(RewardKind::PremiumDays).await?;
+    let fee_discount_bps_earned = totals(RewardKind::FeeDiscountBps).await?;
+
+    let recent_rewards = sqlx::query_as::<_, RewardRow>(
+        r#"
+        SELECT kind, amount::float8 AS amount, reason, referred_user_id, created_at
+        FROM   referral_rewards
+        WHERE  user_id = $1
+        ORDER  BY created_at DESC
+        LIMIT  20
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(ReferralStats {
+        codes,
+        referred,
+        converted,
+        premium_days_earned,
+        fee_discount_bps_earned,
+        recent_rewards,
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn codes_use_unambiguous_alphabet() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let c = random_code(&mut rng);
+            assert_eq!(c.len(), CODE_LEN);
+            assert!(c.bytes().all(|b| CODE_ALPHABET.contains(&b)));
+        }
+    }
+
+    #[test]
+    fn user_input_is_normalised() {
+        assert_eq!(normalize_code(" abcd-efgh "), "ABCDEFGH");
+    }
+
+    #[test]
+    fn attribution_window() {
+        let now = Utc::now();
+        assert!(within_window(now - Duration::days(1), now));
+        assert!(!within_window(
+            now - Duration::days(ATTRIBUTION_WINDOW_DAYS + 1),
+            now
+        ));
+    }
+}
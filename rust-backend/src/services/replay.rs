@@ -0,0 +1,465 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Trade replay
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Strategy orders carry a `signal_context` (strategy, params at the time,
+//!   decision trace) written by [`attach`] right after submission
+//! * [`replay`] returns the candles around an order plus entry/exit markers
+//!   and price lines, shaped for lightweight-charts style libraries
+//!   (`time` in unix seconds, markers snapped to the bar they fall in)
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Intervals the candle recorder persists
+pub const INTERVALS: &[&str] = &["1h", "4h"];
+/// Upper bound for `before` / `after`
+pub const MAX_BARS: u32 = 500;
+
+#[derive(thiserror::Error, Debug)]
+#[error("unsupported interval `{0}` (expected one of 1h, 4h)")]
+pub struct BadInterval(pub String);
+
+// ─── Decision trace ──────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub step: String,
+    pub detail: Value,
+}
+
+/// Ordered record of why an order was placed, stored with the order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DecisionTrace(Vec<TraceStep>);
+
+impl DecisionTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, step: &str, detail: Value) -> Self {
+        self.push(step, detail);
+        self
+    }
+
+    pub fn push(&mut self, step: &str, detail: Value) {
+        self.0.push(TraceStep {
+            step: step.to_string(),
+            detail,
+        });
+    }
+
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.0
+    }
+}
+
+/// Link an order to its strategy and snapshot the strategy's params and the
+/// trace alongside it
+pub async fn attach(
+    db: &PgPool,
+    order_id: Uuid,
+    strategy_id: Uuid,
+    trace: &DecisionTrace,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE orders o
+           SET strategy_id    = s.strategy_id,
+               params_hash    = s.params_hash,
+               signal_context = jsonb_build_object(
+                                    'strategy', s.strategy,
+                                    'params',   s.params,
+                                    'trace',    $3::jsonb)
+          FROM user_strategies s
+         WHERE o.order_id = $1
+           AND s.strategy_id = $2
+        "#,
+    )
+    .bind(order_id)
+    .bind(strategy_id)
+    .bind(sqlx::types::Json(trace))
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+// ─── Replay ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReplayOrder {
+    pub order_id: Uuid,
+    pub symbol: String,
+    pub side: String,
+    pub order_type: String,
+    pub status: String,
+    pub size: f64,
+    pub price: Option<f64>,
+    pub reduce_only: bool,
+    pub signal_price: Option<f64>,
+    pub mid_at_submit: Option<f64>,
+    /// Size-weighted average over the order's fills
+    pub fill_price: Option<f64>,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub strategy_id: Option<Uuid>,
+    #[serde(skip)]
+    pub signal_context: Option<Value>,
+}
+
+impl ReplayOrder {
+    fn marker_price(&self) -> Option<f64> {
+        self.fill_price
+            .or(self.price)
+            .or(self.mid_at_submit)
+            .or(self.signal_price)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, FromRow)]
+pub struct ChartCandle {
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChartMarker {
+    pub time: i64,
+    /// `belowBar` for buys, `aboveBar` for sells
+    pub position: &'static str,
+    pub shape: &'static str,
+    pub color: &'static str,
+    pub text: String,
+    /// `entry` or `exit`
+    pub kind: &'static str,
+    pub price: Option<f64>,
+    pub order_id: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceLine {
+    pub price: f64,
+    pub title: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeReplay {
+    pub order: ReplayOrder,
+    pub interval: String,
+    pub candles: Vec<ChartCandle>,
+    pub markers: Vec<ChartMarker>,
+    pub price_lines: Vec<PriceLine>,
+    pub strategy: Option<String>,
+    /// Strategy params as they were when the order was placed
+    pub params: Option<Value>,
+    pub trace: Vec<TraceStep>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayQuery {
+    pub interval: Option<String>,
+    /// Bars before the order (default 60)
+    pub before: Option<u32>,
+    /// Bars from the order's bar onwards (default 30)
+    pub after: Option<u32>,
+}
+
+/// Validated [`ReplayQuery`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub interval: String,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl ReplayQuery {
+    pub fn window(&self) -> Result<Window, BadInterval> {
+        let interval = self.interval.clone().unwrap_or_else(|| "1h".into());
+        if !INTERVALS.contains(&interval.as_str()) {
+            return Err(BadInterval(interval));
+        }
+        Ok(Window {
+            interval,
+            before: self.before.unwrap_or(60).min(MAX_BARS) as i64,
+            after: self.after.unwrap_or(30).min(MAX_BARS) as i64,
+        })
+    }
+}
+
+/// Candles are stamped with their close time: an event belongs to the first
+/// bar closing at or after it (the last bar if the window ends earlier).
+pub fn bar_time(candles: &[ChartCandle], at: DateTime<Utc>) -> Option<i64> {
+    let t = at.timestamp();
+    candles
+        .iter()
+        .find(|c| c.time >= t)
+        .or(candles.last())
+        .map(|c| c.time)
+}
+
+pub fn marker(candles: &[ChartCandle], o: &ReplayOrder) -> Option<ChartMarker> {
+    let time = bar_time(candles, o.opened_at)?;
+    let buy = o.side.eq_ignore_ascii_case("buy");
+    let kind = if o.reduce_only { "exit" } else { "entry" };
+    let text = match o.marker_price() {
+        Some(px) => format!("{kind} {} {} @ {px}", o.side, o.size),
+        None => format!("{kind} {} {}", o.side, o.size),
+    };
+    Some(ChartMarker {
+        time,
+        position: if buy { "belowBar" } else { "aboveBar" },
+        shape: if buy { "arrowUp" } else { "arrowDown" },
+        color: if buy { "#26a69a" } else { "#ef5350" },
+        text,
+        kind,
+        price: o.marker_price(),
+        order_id: o.order_id,
+    })
+}
+
+/// Strategy-owned orders carry `{ strategy, params, trace }`
+fn split_context(ctx: Option<&Value>) -> (Option<String>, Option<Value>, Vec<TraceStep>) {
+    let Some(ctx) = ctx else {
+        return (None, None, Vec::new());
+    };
+    let strategy = ctx["strategy"].as_str().map(str::to_owned);
+    let params = ctx.get("params").cloned().filter(|p| !p.is_null());
+    let trace = serde_json::from_value(ctx["trace"].clone()).unwrap_or_default();
+    (strategy, params, trace)
+}
+
+const ORDER_COLUMNS: &str = r#"
+    o.order_id, o.symbol, o.side, o.order_type::text AS order_type,
+    o.status::text AS status, o.size::float8 AS size, o.price::float8 AS price,
+    COALESCE(o.reduce_only, false) AS reduce_only,
+    o.signal_price::float8 AS signal_price, o.mid_at_submit::float8 AS mid_at_submit,
+    (SELECT (SUM(f.fill_price * f.fill_size) / NULLIF(SUM(f.fill_size), 0))::float8
+       FROM fills f WHERE f.order_id = o.order_id) AS fill_price,
+    COALESCE(o.opened_at, now()) AS opened_at, o.closed_at,
+    o.strategy_id, o.signal_context
+"#;
+
+/// Candle symbols carry no separator (`BTCUSDT`); orders may (`BTC-USDT`)
+fn candle_symbols(symbol: &str) -> Vec<String> {
+    let bare = symbol.replace('-', "");
+    if bare == symbol {
+        vec![bare]
+    } else {
+        vec![symbol.to_string(), bare]
+    }
+}
+
+pub async fn replay(
+    db: &PgPool,
+    user_id: i64,
+    order_id: Uuid,
+    w: &Window,
+) -> Result<Option<TradeReplay>, sqlx::Error> {
+    let order = sqlx::query_as::<_, ReplayOrder>(&format!(
+        "SELECT {ORDER_COLUMNS} FROM orders o WHERE o.order_id = $1 AND o.user_id = $2"
+    ))
+    .bind(order_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    let Some(order) = order else {
+        return Ok(None);
+    };
+
+    let candles = sqlx::query_as::<_, ChartCandle>(
+        r#"
+        SELECT * FROM (
+            (SELECT EXTRACT(EPOCH FROM ts)::int8 AS time, open, high, low, close, volume
+               FROM candles
+              WHERE symbol = ANY($1) AND interval = $2 AND ts < $3
+              ORDER BY ts DESC
+              LIMIT $4)
+            UNION ALL
+            (SELECT EXTRACT(EPOCH FROM ts)::int8 AS time, open, high, low, close, volume
+               FROM candles
+              WHERE symbol = ANY($1) AND interval = $2 AND ts >= $3
+              ORDER BY ts
+              LIMIT $5)
+        ) w
+        ORDER BY time
+        "#,
+    )
+    .bind(candle_symbols(&order.symbol))
+    .bind(&w.interval)
+    .bind(order.opened_at)
+    .bind(w.before)
+    .bind(w.after + 1) // the order's own bar
+    .fetch_all(db)
+    .await?;
+
+    // the order plus its siblings (same strategy, else same symbol) in view
+    let mut markers = Vec::new();
+    if let (Some(first), Some(last)) = (candles.first(), candles.last()) {
+        let siblings = sqlx::query_as::<_, ReplayOrder>(&format!(
+            r#"
+            SELECT {ORDER_COLUMNS}
+              FROM orders o
+             WHERE o.user_id = $1
+               AND o.order_id <> $2
+               AND (CASE WHEN $3::uuid IS NULL THEN o.symbol = $4
+                         ELSE o.strategy_id = $3 END)
+               AND o.opened_at >  to_timestamp($5)
+               AND o.opened_at <= to_timestamp($6)
+             ORDER BY o.opened_at
+            "#
+        ))
+        .bind(user_id)
+        .bind(order_id)
+        .bind(order.strategy_id)
+        .bind(&order.symbol)
+        .bind(first.time as f64 - 1.0)
+        .bind(last.time as f64)
+        .fetch_all(db)
+        .await?;
+        markers.extend(siblings.iter().filter_map(|o| marker(&candles, o)));
+        markers.extend(marker(&candles, &order));
+        markers.sort_by_key(|m| m.time);
+    }
+
+    let price_lines = [
+        (order.signal_price, "signal"),
+        (order.mid_at_submit, "mid at submit"),
+        (order.fill_price, "fill"),
+    ]
+    .into_iter()
+    .filter_map(|(p, title)| p.map(|price| PriceLine { price, title }))
+    .collect();
+
+    let (strategy, params, trace) = split_context(order.signal_context.as_ref());
+    Ok(Some(TradeReplay {
+        order,
+        interval: w.interval.clone(),
+        candles,
+        markers,
+        price_lines,
+        strategy,
+        params,
+        trace,
+    }))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn bar(time: i64) -> ChartCandle {
+        ChartCandle {
+            time,
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume: 10.0,
+        }
+    }
+
+    fn order(side: &str, reduce_only: bool, at: i64) -> ReplayOrder {
+        ReplayOrder {
+            order_id: Uuid::nil(),
+            symbol: "BTCUSDT".into(),
+            side: side.into(),
+            order_type: "market".into(),
+            status: "filled".into(),
+            size: 0.5,
+            price: None,
+            reduce_only,
+            signal_price: Some(100.0),
+            mid_at_submit: Some(101.0),
+            fill_price: None,
+            opened_at: Utc.timestamp_opt(at, 0).unwrap(),
+            closed_at: None,
+            strategy_id: None,
+            signal_context: None,
+        }
+    }
+
+    #[test]
+    fn events_snap_to_the_bar_that_closes_after_them() {
+        let candles = [bar(3600), bar(7200), bar(10800)];
+        let at = |t| Utc.timestamp_opt(t, 0).unwrap();
+        assert_eq!(bar_time(&candles, at(100)), Some(3600));
+        assert_eq!(bar_time(&candles, at(7200)), Some(7200));
+        assert_eq!(bar_time(&candles, at(7201)), Some(10800));
+        assert_eq!(bar_time(&candles, at(99_999)), Some(10800));
+        assert_eq!(bar_time(&[], at(1)), None);
+    }
+
+    #[test]
+    fn markers_reflect_side_and_intent() {
+        let candles = [bar(3600), bar(7200)];
+        let entry = marker(&candles, &order("buy", false, 4000)).unwrap();
+        assert_eq!(entry.time, 7200);
+        assert_eq!((entry.position, entry.shape), ("belowBar", "arrowUp"));
+        assert_eq!(entry.kind, "entry");
+        assert_eq!(entry.price, Some(101.0), "mid beats signal when unfilled");
+        assert_eq!(entry.text, "entry buy 0.5 @ 101");
+
+        let mut exit = order("sell", true, 100);
+        exit.fill_price = Some(99.5);
+        let m = marker(&candles, &exit).unwrap();
+        assert_eq!(
+            (m.position, m.shape, m.kind),
+            ("aboveBar", "arrowDown", "exit")
+        );
+        assert_eq!(m.price, Some(99.5));
+    }
+
+    #[test]
+    fn context_round_trips_the_trace() {
+        let trace = DecisionTrace::new()
+            .with("signal", json!({ "entry": 100.0 }))
+            .with("allocation", json!({ "qty": 0.5 }));
+        let ctx = json!({ "strategy": "vcsr", "params": { "atr_mult": 2 }, "trace": trace });
+        let (strategy, params, steps) = split_context(Some(&ctx));
+        assert_eq!(strategy.as_deref(), Some("vcsr"));
+        assert_eq!(params, Some(json!({ "atr_mult": 2 })));
+        assert_eq!(steps, trace.steps());
+
+        assert_eq!(split_context(None), (None, None, Vec::new()));
+    }
+
+    #[test]
+    fn query_defaults_and_bounds() {
+        let q = |interval: Option<&str>, before| ReplayQuery {
+            interval: interval.map(str::to_owned),
+            before,
+            after: None,
+        };
+        assert_eq!(
+            q(None, None).window().unwrap(),
+            Window {
+                interval: "1h".into(),
+                before: 60,
+                after: 30
+            }
+        );
+        assert_eq!(q(Some("4h"), Some(10_000)).window().unwrap().before, 500);
+        assert!(q(Some("1m"), None).window().is_err());
+    }
+
+    #[test]
+    fn candle_symbol_variants() {
+        assert_eq!(candle_symbols("BTCUSDT"), vec!["BTCUSDT"]);
+        assert_eq!(candle_symbols("BTC-USDT"), vec!["BTC-USDT", "BTCUSDT"]);
+    }
+}
@@ -2,24 +2,37 @@
 //! Per-user risk limits
 //! ──────────────────────────────────────────────────────────────────────────
 //! * Slippage guard  – checked synchronously per order
-//! * Draw-down guard – rolling 24 h realised PnL window (Redis)
-//! * Guardian loop   – background monitor for all active users
+//! * Liquidity guard – per-symbol spread / volume / hours (entries only)
+//! * Draw-down guard – rolling 24 h realised PnL window (cache list)
+//! * Exit-only guard – a window the user sets (`users.exit_only_until`),
+//!   e.g. before moving funds off the exchange, in which every strategy,
+//!   copy and manual order must be reduce-only
+//! * Guardian loop   – background monitor for all active users; a trip is
+//!   counted (`risk_drawdown_trips_total`) and the user told once per
+//!   look-back window
 //!
 //! All limits are hard-coded; later you can persist them in Postgres.
 //! ──────────────────────────────────────────────────────────────────────────
 
-use chrono::Utc;
-use redis::AsyncCommands;
+use chrono::{DateTime, Utc};
+use metrics::increment_counter;
+use serde_json::json;
 use sqlx::PgPool;
 use tokio::time::{interval, Duration};
 
-use crate::{db::redis::RedisPool, utils::errors::TradeError};
+use crate::{
+    db::cache::{Cache, CacheError, SharedCache},
+    services::{calendar, liquidity, notify},
+    utils::errors::TradeError,
+};
 
 /// ─── Constants ───────────────────────────────────────────────────────────
 const MAX_SLIPPAGE_BPS: f64 = 10.0; // 0.10 %
 const MAX_DD_PCT: f64 = 20.0; // −20 % over look-back
 const LOOKBACK_SECS: i64 = 86_400; // 24 h
-const REDIS_TTL: usize = (LOOKBACK_SECS as usize) + 600; // keep a bit longer
+const DD_TTL_SECS: u64 = (LOOKBACK_SECS as u64) + 600; // keep a bit longer
+/// Longest exit-only window one request can set
+pub const MAX_EXIT_ONLY_SECS: i64 = 30 * 86_400;
 
 /// ─── Public helpers ──────────────────────────────────────────────────────
 /// Pre-trade slippage guard (caller passes their own estimate)
@@ -35,25 +48,34 @@ pub fn check_slippage(estimated_bps: f64) -> Result<(), TradeError> {
     }
 }
 
-/// Store every fill’s realised PnL in a rolling Redis list
+/// Per-symbol illiquidity & trading-hours guard (see `services::liquidity`)
+pub fn check_liquidity(symbol: &str) -> Result<(), TradeError> {
+    liquidity::check_entry(symbol).map_err(TradeError::RiskViolation)
+}
+
+/// Exchange-hours guard (see `services::calendar`); 24/7 markets always pass
+pub fn check_market_open(symbol: &str) -> Result<(), TradeError> {
+    calendar::check_open(symbol, Utc::now()).map_err(TradeError::RiskViolation)
+}
+
+/// Store every fill’s realised PnL in a rolling cache list (fed by
+/// `order_tracker::record_realised` as exchange fills come in)
 pub async fn record_fill(
-    redis: &RedisPool,
+    cache: &dyn Cache,
     user_id: i64,
     realised_pnl_usd: f64,
-) -> redis::RedisResult<()> {
-    let key = redis.with_prefix("dd", user_id.to_string());
-    let mut conn = redis.manager().as_ref().clone();
+) -> Result<(), CacheError> {
+    let key = format!("dd:{user_id}");
     let entry = format!("{}|{:.8}", Utc::now().timestamp(), realised_pnl_usd);
-    conn.lpush::<_, _, ()>(&key, entry).await?;
-    conn.expire::<_, ()>(&key, REDIS_TTL as i64).await?;
+    cache.lpush(&key, &entry).await?;
+    cache.expire(&key, DD_TTL_SECS).await?;
     Ok(())
 }
 
 /// Check the 24 h realised PnL window and error on breach
-pub async fn check_drawdown(redis: &RedisPool, user_id: i64) -> Result<(), TradeError> {
-    let key = redis.with_prefix("dd", user_id.to_string());
-    let mut conn = redis.manager().as_ref().clone();
-    let rows: Vec<String> = conn.lrange(&key, 0, -1).await.unwrap_or_default();
+pub async fn check_drawdown(cache: &dyn Cache, user_id: i64) -> Result<(), TradeError> {
+    let key = format!("dd:{user_id}");
+    let rows: Vec<String> = cache.lrange_all(&key).await.unwrap_or_default();
 
     let cutoff = Utc::now().timestamp() - LOOKBACK_SECS;
     let dd: f64 = rows
@@ -77,9 +99,55 @@ pub async fn check_drawdown(redis: &RedisPool, user_id: i64) -> Result<(), Trade
     }
 }
 
+/// The end of `until` if it's still ahead of `now`
+fn active_until(until: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    until.filter(|u| *u > now)
+}
+
+/// When the user's exit-only window ends; `None` while trading normally
+pub async fn exit_only_until(
+    db: &PgPool,
+    user_id: i64,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let until: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT exit_only_until FROM users WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(db)
+            .await?
+            .flatten();
+    Ok(active_until(until, Utc::now()))
+}
+
+/// Start (or replace) an exit-only window ending at `until`, or end it now
+/// with `None`; the window now in force
+pub async fn set_exit_only(
+    db: &PgPool,
+    user_id: i64,
+    until: Option<DateTime<Utc>>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query("UPDATE users SET exit_only_until = $2 WHERE user_id = $1")
+        .bind(user_id)
+        .bind(until)
+        .execute(db)
+        .await?;
+    Ok(active_until(until, Utc::now()))
+}
+
+/// Entry gate for `execute_trade`. Unlike the other guards it fails
+/// closed: an entry the user asked not to happen must not slip through
+pub async fn check_exit_only(db: &PgPool, user_id: i64) -> Result<(), TradeError> {
+    match exit_only_until(db, user_id).await.map_err(TradeError::Db)? {
+        Some(until) => Err(TradeError::RiskViolation(format!(
+            "exit-only until {} – only reducing orders are accepted",
+            until.format("%Y-%m-%d %H:%M UTC")
+        ))),
+        None => Ok(()),
+    }
+}
+
 /// ─── Guardian loop ───────────────────────────────────────────────────────
 /// Runs in the background, polls the DB every minute, applies draw-down check
-pub fn spawn_guardian(pg: PgPool, redis: RedisPool) {
+pub fn spawn_guardian(pg: PgPool, cache: SharedCache) {
     tokio::spawn(async move {
         let mut iv = interval(Duration::from_secs(60));
 
@@ -87,17 +155,41 @@ pub fn spawn_guardian(pg: PgPool, redis: RedisPool) {
             iv.tick().await;
 
             if let Ok(user_ids) = active_users(&pg).await {
-                for uid in user_ids {
-                    if let Err(e) = check_drawdown(&redis, uid).await {
-                        log::warn!("risk DD trip for user {uid}: {e}");
-                        // Future: flip a Redis “tripped” flag → strategies can abort early
-                    }
-                }
+                sweep(cache.as_ref(), &user_ids).await;
             }
         }
     });
 }
 
+/// One guardian pass over `user_ids`; the users whose draw-down guard trips.
+/// Entries are already refused through `check_drawdown` – the notice tells
+/// the user why, once per look-back window
+pub async fn sweep(cache: &dyn Cache, user_ids: &[i64]) -> Vec<i64> {
+    let mut tripped = Vec::new();
+    for &uid in user_ids {
+        let Err(e) = check_drawdown(cache, uid).await else {
+            continue;
+        };
+        tracing::warn!("risk DD trip for user {uid}: {e}");
+        increment_counter!("risk_drawdown_trips_total");
+        let key = format!("dd_tripped:{uid}");
+        if cache
+            .set_nx(&key, "1", LOOKBACK_SECS as u64)
+            .await
+            .unwrap_or(false)
+        {
+            notify::send(
+                uid,
+                "risk.drawdown",
+                &format!("New entries are paused – {e}"),
+                json!({ "lookback_secs": LOOKBACK_SECS }),
+            );
+        }
+        tripped.push(uid);
+    }
+    tripped
+}
+
 /// Query distinct user IDs that still have **enabled** strategies
 async fn active_users(pg: &PgPool) -> sqlx::Result<Vec<i64>> {
     let rows = sqlx::query! {
@@ -152,6 +244,16 @@ mod tests {
         }
     }
 
+    // ───────────────────────────────────────── Exit-only window
+    #[test]
+    fn exit_only_lasts_until_its_end() {
+        let now = Utc::now();
+        let later = now + chrono::Duration::hours(2);
+        assert_eq!(active_until(Some(later), now), Some(later));
+        assert_eq!(active_until(Some(later), later), None);
+        assert_eq!(active_until(None, now), None);
+    }
+
     // ───────────────────────────────────────── Draw-down maths helper
     fn make_row(ts: i64, pnl: f64) -> String {
         format!("{}|{:.4}", ts, pnl)
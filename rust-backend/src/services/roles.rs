@@ -0,0 +1,96 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Account roles – who may use the operator endpoints
+//! ──────────────────────────────────────────────────────────────────────────
+//! * `users.roles` holds role names; every account starts with none and is
+//!   granted roles by an operator in SQL, e.g.
+//!   `UPDATE users SET roles = array_append(roles, 'admin') WHERE user_id = …`
+//! * Unknown names in the column are ignored, so a role can be retired
+//!   without touching the rows first
+//! * Lookups are cached in-process for `CACHE_TTL`: a revoked role stops
+//!   working within that window
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::PgPool;
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// `/api/admin`: route dump, feed status, task list, flags, storage,
+    /// exchange log, integration keys, fault injection
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+static CACHE: Lazy<DashMap<i64, (Instant, Vec<Role>)>> = Lazy::new(DashMap::new);
+
+/// Known roles among the column's names
+fn known(names: &[String]) -> Vec<Role> {
+    let mut roles: Vec<Role> = names.iter().filter_map(|n| Role::parse(n)).collect();
+    roles.dedup();
+    roles
+}
+
+/// Roles of `user_id`; none for an account that doesn't exist
+pub async fn of(db: &PgPool, user_id: i64) -> Result<Vec<Role>, sqlx::Error> {
+    if let Some(hit) = CACHE.get(&user_id) {
+        if hit.0.elapsed() < CACHE_TTL {
+            return Ok(hit.1.clone());
+        }
+    }
+    let names: Option<Vec<String>> =
+        sqlx::query_scalar("SELECT roles FROM users WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(db)
+            .await?;
+    let roles = known(&names.unwrap_or_default());
+    CACHE.insert(user_id, (Instant::now(), roles.clone()));
+    Ok(roles)
+}
+
+pub async fn has(db: &PgPool, user_id: i64, role: Role) -> Result<bool, sqlx::Error> {
+    Ok(of(db, user_id).await?.contains(&role))
+}
+
+/// Seed the role cache, so extractor tests need no database
+#[cfg(test)]
+pub(crate) fn remember(user_id: i64, roles: Vec<Role>) {
+    CACHE.insert(user_id, (Instant::now(), roles));
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_role_names_are_ignored() {
+        let names = vec!["Admin".to_string(), "beta".into(), "admin".into()];
+        assert_eq!(known(&names), [Role::Admin]);
+        assert!(known(&[]).is_empty());
+        assert_eq!(Role::parse(Role::Admin.as_str()), Some(Role::Admin));
+    }
+}
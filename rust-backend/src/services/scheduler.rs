@@ -1,16 +1,42 @@
 use crate::{
     config::settings::Settings,
-    db::redis::RedisPool,
-    services::{market_data::MarketBus, strategies},
+    db::cache::SharedCache,
+    services::{
+        audit, copy_trading, drain,
+        market_data::MarketBus,
+        params_history,
+        sharding::ShardSource,
+        strategies::{
+            self, heartbeat,
+            schema::ParamSchema,
+            warmup::{Need, Warmup, WarmupView},
+            StrategyError,
+        },
+        strategy_performance, usage,
+    },
 };
 use dashmap::DashMap;
 use futures::future::{abortable, AbortHandle};
+use metrics::increment_counter;
+use serde_json::{json, Value};
 use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
 
 type TaskMap = DashMap<Uuid, AbortHandle>;
 static TASKS: once_cell::sync::Lazy<TaskMap> = once_cell::sync::Lazy::new(TaskMap::default);
+/// Tasks still short of their warmup history
+static WARMING: once_cell::sync::Lazy<DashMap<Uuid, Arc<Warmup>>> =
+    once_cell::sync::Lazy::new(DashMap::default);
+
+/// Published warmup progress outlives a few reconcile passes, so it
+/// disappears on its own when the instance running the task goes away
+const WARMUP_TTL_SECS: u64 = 120;
+
+/// Where the status API finds a task's warmup progress
+pub fn warmup_key(strategy_id: Uuid) -> String {
+    format!("warmup:{strategy_id}")
+}
 
 #[derive(sqlx::FromRow, Clone, Default)]
 pub struct StrategyRow {
@@ -19,17 +45,211 @@ pub struct StrategyRow {
     pub exchange: String,
     pub symbol: String,
     pub strategy: String,
-    pub params: serde_json::Value,
+    pub params: Value,
+}
+
+impl StrategyRow {
+    /// What the strategy trades and its candles are streamed for: the
+    /// params' `symbol` when they carry one, else the row's
+    pub fn trade_symbol(&self) -> &str {
+        self.params
+            .get("symbol")
+            .and_then(Value::as_str)
+            .unwrap_or(&self.symbol)
+    }
+}
+
+/// Strategy tasks running on this instance, each with its warmup progress
+/// while it's still short of history
+pub fn tasks() -> Vec<(Uuid, Option<WarmupView>)> {
+    TASKS
+        .iter()
+        .map(|e| {
+            let id = *e.key();
+            (id, WARMING.get(&id).map(|w| w.view()))
+        })
+        .collect()
+}
+
+/// Stop a running task so the next `reconcile` starts it with fresh params
+pub fn respawn(strategy_id: Uuid) {
+    if let Some((_, abort)) = TASKS.remove(&strategy_id) {
+        abort.abort();
+    }
+    heartbeat::forget(strategy_id);
+}
+
+/// Restart the tasks whose heartbeat lags behind data the bus already has
+async fn restart_stalled(cache: &SharedCache, bus: &MarketBus, rows: &[StrategyRow], grace: u64) {
+    let grace = std::time::Duration::from_secs(grace);
+    let now = chrono::Utc::now();
+    for row in rows {
+        let id = row.strategy_id;
+        let Some(beat) = heartbeat::last(id).filter(|_| TASKS.contains_key(&id)) else {
+            continue;
+        };
+        let fresh = bus.freshness(&beat.symbol, &beat.feed);
+        if !heartbeat::stalled(&beat, fresh, now, grace) {
+            continue;
+        }
+        tracing::warn!(
+            "scheduler: {} {id} stalled on {} {} (last {}), restarting",
+            row.strategy,
+            beat.symbol,
+            beat.feed,
+            beat.ts
+        );
+        increment_counter!("strategy_stalls_total", "strategy" => row.strategy.clone());
+        if let Err(e) = heartbeat::record_stall(cache.as_ref(), id).await {
+            tracing::warn!("scheduler: stall count for {id}: {e}");
+        }
+        respawn(id);
+    }
+}
+
+/// Reject params up front for the strategies the scheduler runs; other
+/// names are left to whatever runs them
+pub fn check_params(strategy: &str, params: &Value) -> Result<(), StrategyError> {
+    use strategies::{
+        market_maker::MarketMakerParams, mean_reversion::MeanRevParams, script::ScriptParams,
+        trend_follow::TrendParams, vcsr::VcsrConfig,
+    };
+    match strategy {
+        "market_maker" => MarketMakerParams::parse(params.clone()).map(drop),
+        "mean_reversion" => MeanRevParams::parse(params.clone()).map(drop),
+        "script" => ScriptParams::parse(params.clone()).map(drop),
+        #[cfg(feature = "wasm")]
+        "plugin" => strategies::plugin::PluginParams::parse(params.clone()).map(drop),
+        "trend_follow" => TrendParams::parse(params.clone()).map(drop),
+        "vcsr" => VcsrConfig::parse(params.clone()).map(drop),
+        _ => Ok(()),
+    }
+}
+
+/// Declared params of the strategies the scheduler runs
+pub fn param_schema(strategy: &str) -> Option<ParamSchema> {
+    use strategies::{
+        market_maker::MarketMakerParams, mean_reversion::MeanRevParams, script::ScriptParams,
+        trend_follow::TrendParams, vcsr::VcsrConfig,
+    };
+    match strategy {
+        "market_maker" => Some(MarketMakerParams::SCHEMA),
+        "mean_reversion" => Some(MeanRevParams::SCHEMA),
+        "script" => Some(ScriptParams::SCHEMA),
+        #[cfg(feature = "wasm")]
+        "plugin" => Some(strategies::plugin::PluginParams::SCHEMA),
+        "trend_follow" => Some(TrendParams::SCHEMA),
+        "vcsr" => Some(VcsrConfig::SCHEMA),
+        _ => None,
+    }
+}
+
+/// Candles a strategy needs before it may trade; nothing for names the
+/// scheduler doesn't run or params it would reject anyway
+pub fn warmup_needs(strategy: &str, params: &Value) -> Vec<Need> {
+    use strategies::{
+        market_maker::MarketMakerParams, mean_reversion::MeanRevParams, script::ScriptParams,
+        trend_follow::TrendParams, vcsr::VcsrConfig,
+    };
+    let needs = match strategy {
+        "market_maker" => MarketMakerParams::parse(params.clone()).map(|p| p.warmup()),
+        "mean_reversion" => MeanRevParams::parse(params.clone()).map(|p| p.warmup()),
+        "script" => ScriptParams::parse(params.clone()).map(|p| p.warmup()),
+        #[cfg(feature = "wasm")]
+        "plugin" => strategies::plugin::PluginParams::parse(params.clone()).map(|p| p.warmup()),
+        "trend_follow" => TrendParams::parse(params.clone()).map(|p| p.warmup()),
+        "vcsr" => VcsrConfig::parse(params.clone()).map(|p| p.warmup()),
+        _ => Ok(vec![]),
+    };
+    needs.unwrap_or_default()
+}
+
+async fn run(
+    r: StrategyRow,
+    cache: SharedCache,
+    db: PgPool,
+    bus: MarketBus,
+    master_key: Vec<u8>,
+    is_demo: bool,
+    warm: Arc<Warmup>,
+) -> Result<(), StrategyError> {
+    use strategies::{market_maker, mean_reversion, script, trend_follow, vcsr};
+    let db = Arc::new(db);
+    match r.strategy.as_str() {
+        "market_maker" => {
+            market_maker::loop_forever(r, cache, db, bus, master_key, is_demo, warm).await
+        }
+        "mean_reversion" => {
+            mean_reversion::loop_forever(r, cache, db, bus, master_key, is_demo, warm).await
+        }
+        "script" => script::loop_forever(r, cache, db, bus, master_key, is_demo, warm).await,
+        #[cfg(feature = "wasm")]
+        "plugin" => {
+            strategies::plugin::loop_forever(r, cache, db, bus, master_key, is_demo, warm).await
+        }
+        "trend_follow" => {
+            trend_follow::loop_forever(r, cache, db, bus, master_key, is_demo, warm).await
+        }
+        "vcsr" => vcsr::loop_forever(r, cache, db, bus, master_key, is_demo, warm).await,
+        other => Err(StrategyError::Unknown(other.to_string())),
+    }
+}
+
+/// Park a strategy that can't run; the next `reconcile` reaps its task
+async fn mark_invalid(
+    db: &PgPool,
+    cache: &SharedCache,
+    row: &StrategyRow,
+    err: &StrategyError,
+) -> Result<(), sqlx::Error> {
+    tracing::warn!("scheduler: strategy {} is invalid: {err}", row.strategy_id);
+    sqlx::query(
+        r#"
+        UPDATE user_strategies
+           SET status = 'invalid', status_error = $2
+         WHERE strategy_id = $1 AND status = 'enabled'
+        "#,
+    )
+    .bind(row.strategy_id)
+    .bind(err.to_string())
+    .execute(db)
+    .await?;
+    usage::invalidate_strategies(cache.as_ref(), row.user_id).await;
+    audit::record(
+        Some(row.user_id),
+        "strategy.invalid",
+        json!({ "strategy_id": row.strategy_id, "error": err.to_string() }),
+    );
+    Ok(())
 }
 
 pub async fn reconcile(
     pg: &PgPool,
-    redis: &RedisPool,
+    cache: &SharedCache,
     settings: &Settings,
     bus: &MarketBus,
+    shards: &ShardSource,
 ) -> anyhow::Result<()> {
+    // draining: leave running tasks alone, start nothing new
+    if drain::is_draining() {
+        return Ok(());
+    }
+
+    // copy trials past their window end here, on whichever instance gets there
+    match copy_trading::expire_trials(pg, cache.as_ref()).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!("scheduler: {n} copy trials expired"),
+        Err(e) => tracing::warn!("scheduler: expiring copy trials: {e}"),
+    }
+    // yesterday's performance rollup, likewise once per day on any instance
+    match strategy_performance::rollup_if_due(pg, cache.as_ref(), chrono::Utc::now()).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!("scheduler: performance rolled up for {n} strategies"),
+        Err(e) => tracing::warn!("scheduler: performance rollup: {e}"),
+    }
+
     // ---------------------------------------------------------
-    // 1. Fetch enabled rows
+    // 1. Fetch enabled rows for the users we own and hold a lease on
     // ---------------------------------------------------------
     let rows: Vec<StrategyRow> = sqlx::query_as!(
         StrategyRow,
@@ -46,75 +266,98 @@ pub async fn reconcile(
     )
     .fetch_all(pg)
     .await?;
+    // membership / leases unknown: neither spawn nor reap, or a user could
+    // run twice. Rows that moved away are reaped below
+    let Some(assigned) = shards.assign(rows.iter().map(|r| r.user_id)).await else {
+        return Ok(());
+    };
+    let rows: Vec<StrategyRow> = rows
+        .into_iter()
+        .filter(|r| assigned.contains(&r.user_id))
+        .collect();
 
     let master_key = std::env::var("MASTER_KEY").unwrap_or_default().into_bytes();
     let is_demo = settings.is_demo();
 
     // ---------------------------------------------------------
-    // 2. Spawn missing tasks
+    // 2. Stop stalled tasks, then spawn missing ones
     // ---------------------------------------------------------
+    if settings.strategy_stall_secs > 0 {
+        restart_stalled(cache, bus, &rows, settings.strategy_stall_secs).await;
+    }
     for row in &rows {
         if TASKS.contains_key(&row.strategy_id) {
             continue;
         }
+        // version the params this run starts with
+        if let Err(e) = params_history::record_run(pg, row.strategy_id, &row.params).await {
+            tracing::warn!("scheduler: params history for {}: {e}", row.strategy_id);
+        }
 
         let r = row.clone();
-        let rd = redis.clone();
+        let cache = cache.clone();
         let bus_clone = bus.clone();
         let db = pg.clone();
         let master_key = master_key.clone();
+        let warm = Arc::new(Warmup::new(warmup_needs(&row.strategy, &row.params)));
+        WARMING.insert(row.strategy_id, warm.clone());
 
-        let (task, abort) = abortable(tokio::spawn(async move {
-            match r.strategy.as_str() {
-                "mean_reversion" => {
-                    strategies::mean_reversion::loop_forever(
-                        r,
-                        rd,
-                        Arc::new(db),
-                        bus_clone,
-                        master_key,
-                        is_demo,
-                    )
-                    .await
-                }
-                "trend_follow" => {
-                    strategies::trend_follow::loop_forever(
-                        r,
-                        rd,
-                        Arc::new(db),
-                        bus_clone,
-                        master_key,
-                        is_demo,
-                    )
-                    .await
-                }
-                "vcsr" => {
-                    strategies::vcsr::loop_forever(
-                        r,
-                        rd,
-                        Arc::new(db),
-                        bus_clone,
-                        master_key,
-                        is_demo,
-                    )
-                    .await
+        // abortable around the work itself: aborting a spawned task's
+        // JoinHandle would only detach it
+        let (task, abort) = abortable(async move {
+            // a drain waits for the loop to finish its bar and return
+            let _in_flight = drain::track();
+            warm.bootstrap(&db, r.trade_symbol()).await;
+            let outcome = run(
+                r.clone(),
+                cache.clone(),
+                db.clone(),
+                bus_clone,
+                master_key,
+                is_demo,
+                warm,
+            )
+            .await;
+            if let Err(e) = outcome {
+                if let Err(db_err) = mark_invalid(&db, &cache, &r, &e).await {
+                    tracing::error!("scheduler: marking {} invalid: {db_err}", r.strategy_id);
                 }
-                other => log::warn!("scheduler: unknown strategy '{other}'"),
             }
-        }));
+        });
 
         tokio::spawn(task);
         TASKS.insert(row.strategy_id, abort);
     }
 
     // ---------------------------------------------------------
-    // 3. Reap tasks whose DB row disappeared / disabled
+    // 3. Reap tasks whose DB row disappeared / disabled / changed shard
     // ---------------------------------------------------------
     for id in TASKS.iter().map(|e| *e.key()) {
         if !rows.iter().any(|r| r.strategy_id == id) {
-            if let Some((_, abort)) = TASKS.remove(&id) {
-                abort.abort();
-            }
+            respawn(id);
+        }
+    }
+    // only now hand back leases of users we no longer run
+    shards.release(&assigned).await;
+
+    // ---------------------------------------------------------
+    // 4. Publish warmup progress; drop it once a task is ready or gone
+    // ---------------------------------------------------------
+    let warming: Vec<_> = WARMING
+        .iter()
+        .map(|e| (*e.key(), e.value().clone()))
+        .collect();
+    for (id, warm) in warming {
+        let view = warm.view();
+        let key = warmup_key(id);
+        let outcome = if view.ready || !TASKS.contains_key(&id) {
+            WARMING.remove(&id);
+            cache.del(&key).await
+        } else {
+            cache.set_json(&key, &view, WARMUP_TTL_SECS).await
+        };
+        if let Err(e) = outcome {
+            tracing::warn!("scheduler: warmup progress for {id}: {e}");
         }
     }
 
@@ -0,0 +1,204 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Strategy seasonality
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Realised PnL attributed to strategies (`strategy_pnl`, written by
+//!   `allocation::record_fill`) grouped by hour of day, day of week and
+//!   trading session, all in UTC
+//! * Sessions use the VCSR windows (`vcsr::session_at`) and are named like
+//!   `session_filter` values, so a filter can be tuned straight off the
+//!   report; realisations outside every window land in `Off`
+//! * Every hour and weekday is present, empty ones with zero trades
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::strategies::vcsr::{session_at, TradingSession};
+
+/// Realisations outside every session window
+pub const OFF_SESSION: &str = "Off";
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeasonBucket {
+    pub bucket: String,
+    pub trades: u32,
+    pub wins: u32,
+    /// `None` without trades
+    pub win_rate: Option<f64>,
+    pub total_pnl: f64,
+    pub avg_pnl: Option<f64>,
+}
+
+impl SeasonBucket {
+    fn new(bucket: impl Into<String>) -> Self {
+        Self {
+            bucket: bucket.into(),
+            trades: 0,
+            wins: 0,
+            win_rate: None,
+            total_pnl: 0.0,
+            avg_pnl: None,
+        }
+    }
+
+    fn add(&mut self, pnl: f64) {
+        self.trades += 1;
+        self.wins += (pnl > 0.0) as u32;
+        self.total_pnl += pnl;
+        let n = self.trades as f64;
+        self.win_rate = Some(self.wins as f64 / n);
+        self.avg_pnl = Some(self.total_pnl / n);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Seasonality {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub strategy_id: Option<Uuid>,
+    /// "00" … "23"
+    pub by_hour_utc: Vec<SeasonBucket>,
+    /// "Mon" … "Sun"
+    pub by_weekday: Vec<SeasonBucket>,
+    /// `AsiaOpen`, `NyOpen`, `Off`
+    pub by_session: Vec<SeasonBucket>,
+}
+
+fn session_name(ts: DateTime<Utc>) -> &'static str {
+    match session_at(ts) {
+        Some(TradingSession::AsiaOpen) => "AsiaOpen",
+        Some(TradingSession::NyOpen) => "NyOpen",
+        None => OFF_SESSION,
+    }
+}
+
+/// Bucket `(realised_at, pnl)` pairs
+pub fn breakdown(
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    strategy_id: Option<Uuid>,
+    realised: &[(DateTime<Utc>, f64)],
+) -> Seasonality {
+    let mut by_hour: Vec<SeasonBucket> = (0..24)
+        .map(|h| SeasonBucket::new(format!("{h:02}")))
+        .collect();
+    let mut by_weekday: Vec<SeasonBucket> = WEEKDAYS
+        .iter()
+        .map(|d| SeasonBucket::new(d.to_string()))
+        .collect();
+    let mut by_session: Vec<SeasonBucket> = ["AsiaOpen", "NyOpen", OFF_SESSION]
+        .into_iter()
+        .map(SeasonBucket::new)
+        .collect();
+
+    for &(ts, pnl) in realised {
+        by_hour[ts.hour() as usize].add(pnl);
+        by_weekday[ts.weekday().num_days_from_monday() as usize].add(pnl);
+        let session = session_name(ts);
+        if let Some(b) = by_session.iter_mut().find(|b| b.bucket == session) {
+            b.add(pnl);
+        }
+    }
+
+    Seasonality {
+        since,
+        until,
+        strategy_id,
+        by_hour_utc: by_hour,
+        by_weekday,
+        by_session,
+    }
+}
+
+/// The user's attributed PnL in `[since, until)`, optionally one strategy's
+pub async fn report(
+    db: &PgPool,
+    user_id: i64,
+    strategy_id: Option<Uuid>,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Seasonality, sqlx::Error> {
+    let realised: Vec<(DateTime<Utc>, f64)> = sqlx::query_as(
+        r#"
+        SELECT created_at, pnl::float8
+          FROM strategy_pnl
+         WHERE user_id = $1
+           AND ($2::uuid IS NULL OR strategy_id = $2)
+           AND created_at >= $3 AND created_at < $4
+        "#,
+    )
+    .bind(user_id)
+    .bind(strategy_id)
+    .bind(since)
+    .bind(until)
+    .fetch_all(db)
+    .await?;
+    Ok(breakdown(since, until, strategy_id, &realised))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        // 2025-06-02 is a Monday
+        Utc.with_ymd_and_hms(2025, 6, day, hour, 15, 0).unwrap()
+    }
+
+    #[test]
+    fn buckets_by_hour_weekday_and_session() {
+        let s = breakdown(
+            at(1, 0),
+            at(30, 0),
+            None,
+            &[
+                (at(2, 1), 10.0),
+                (at(2, 1), -4.0),
+                (at(3, 13), 6.0),
+                (at(7, 8), -2.0),
+            ],
+        );
+
+        let h01 = &s.by_hour_utc[1];
+        assert_eq!((h01.bucket.as_str(), h01.trades, h01.wins), ("01", 2, 1));
+        assert_eq!(h01.win_rate, Some(0.5));
+        assert_eq!(h01.avg_pnl, Some(3.0));
+        assert_eq!(s.by_hour_utc.len(), 24);
+        assert_eq!(s.by_hour_utc[5].avg_pnl, None);
+
+        let mon = &s.by_weekday[0];
+        assert_eq!(
+            (mon.bucket.as_str(), mon.trades, mon.total_pnl),
+            ("Mon", 2, 6.0)
+        );
+        assert_eq!(s.by_weekday[5].bucket, "Sat");
+        assert_eq!(s.by_weekday[5].trades, 1);
+
+        let sessions: Vec<_> = s
+            .by_session
+            .iter()
+            .map(|b| (b.bucket.as_str(), b.trades, b.total_pnl))
+            .collect();
+        assert_eq!(
+            sessions,
+            [("AsiaOpen", 2, 6.0), ("NyOpen", 1, 6.0), ("Off", 1, -2.0)]
+        );
+    }
+}
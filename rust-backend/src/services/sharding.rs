@@ -0,0 +1,476 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Strategy-task sharding across backend instances
+//! ──────────────────────────────────────────────────────────────────────────
+//! Each instance's scheduler only spawns strategies for the users it owns,
+//! and only while it holds that user's lease in the shared cache.
+//!
+//! * `SHARD_MODE=off`     – single node, owns everything, no leases (default)
+//! * `SHARD_MODE=static`  – `SHARD_INDEX` / `SHARD_COUNT` from env; users map
+//!   to fixed slots with a jump consistent hash
+//! * `SHARD_MODE=dynamic` – instances heartbeat into the cache and users go to
+//!   the live node with the highest rendezvous weight, so a node leaving or
+//!   joining only moves the users it had or takes — nobody else's
+//!
+//! Ownership alone can't stop a strategy running twice: two nodes may see
+//! different live sets for a tick, or a static slot may be misconfigured.
+//! The lease (`shard:lease:{user_id}` = `INSTANCE_ID`, `SET NX` with a TTL,
+//! renewed every reconcile) is what actually gates a spawn. A user that moves
+//! starts on the new owner once the old one has reaped and released, or
+//! after the lease expires if the old one died.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use metrics::gauge;
+
+use crate::{
+    config::settings::Settings,
+    db::cache::{CacheError, SharedCache},
+};
+
+const NODES_KEY: &str = "shard:nodes";
+/// A node that misses heartbeats this long is dropped from the ring
+const NODE_TTL_SECS: u64 = 90;
+/// Three reconcile ticks: a node that stops renewing loses its users after this
+const LEASE_TTL_SECS: u64 = 90;
+/// Stop our tasks once renewals have failed this long, a tick before the
+/// leases can expire under us and another node starts the same users
+const LEASE_GRACE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl Shard {
+    pub const ALL: Shard = Shard { index: 0, count: 1 };
+
+    pub fn owns(&self, user_id: i64) -> bool {
+        jump_hash(user_id as u64, self.count) == self.index
+    }
+}
+
+/// Lamping & Veach jump consistent hash: key → bucket in `0..buckets`
+pub fn jump_hash(mut key: u64, buckets: u32) -> u32 {
+    let buckets = buckets.max(1) as i64;
+    let (mut b, mut j) = (-1i64, 0i64);
+    while j < buckets {
+        b = j;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as u32
+}
+
+/// Rendezvous (highest-random-weight) score of `node` for `user_id`
+pub fn weight(node: &str, user_id: i64) -> u64 {
+    // FNV-1a over the node name, then a splitmix64 finaliser with the user
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in node.bytes() {
+        h = (h ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+    let mut z = h ^ (user_id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The live node set as seen by one instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ring {
+    pub me: String,
+    /// Sorted node ids, `me` included
+    pub live: Vec<String>,
+}
+
+impl Ring {
+    /// Node with the highest weight for `user_id` (ties broken by id)
+    pub fn owner_of(&self, user_id: i64) -> Option<&str> {
+        self.live
+            .iter()
+            .max_by_key(|n| (weight(n, user_id), n.as_str()))
+            .map(String::as_str)
+    }
+
+    pub fn owns(&self, user_id: i64) -> bool {
+        self.owner_of(user_id) == Some(self.me.as_str())
+    }
+}
+
+/// Which users this node should run, before leases
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    All,
+    Slot(Shard),
+    Ring(Ring),
+}
+
+impl Owner {
+    pub fn owns(&self, user_id: i64) -> bool {
+        match self {
+            Owner::All => true,
+            Owner::Slot(s) => s.owns(user_id),
+            Owner::Ring(r) => r.owns(user_id),
+        }
+    }
+
+    /// (index, count) for the gauges; a ring reports our sorted position
+    fn position(&self) -> (usize, usize) {
+        match self {
+            Owner::All => (0, 1),
+            Owner::Slot(s) => (s.index as usize, s.count as usize),
+            Owner::Ring(r) => (
+                r.live.iter().position(|n| *n == r.me).unwrap_or(0),
+                r.live.len(),
+            ),
+        }
+    }
+}
+
+/// Cache-backed membership for `SHARD_MODE=dynamic`
+pub struct Membership {
+    cache: SharedCache,
+    instance_id: String,
+}
+
+impl Membership {
+    pub fn new(cache: SharedCache, instance_id: impl Into<String>) -> Self {
+        Self {
+            cache,
+            instance_id: instance_id.into(),
+        }
+    }
+
+    fn node_key(id: &str) -> String {
+        format!("shard:node:{id}")
+    }
+
+    /// Refresh our heartbeat, prune dead peers and return the live ring.
+    /// `None` when we are not (yet) part of the live set.
+    pub async fn refresh(&self) -> Result<Option<Ring>, CacheError> {
+        let me = &self.instance_id;
+        self.cache
+            .set(&Self::node_key(me), "1", NODE_TTL_SECS)
+            .await?;
+        self.cache.sadd(NODES_KEY, std::slice::from_ref(me)).await?;
+
+        let mut live = Vec::new();
+        for id in self.cache.smembers(NODES_KEY).await? {
+            if self.cache.get(&Self::node_key(&id)).await?.is_some() {
+                live.push(id);
+            } else {
+                self.cache.srem(NODES_KEY, &id).await?;
+            }
+        }
+        live.sort();
+
+        Ok(live.contains(me).then(|| Ring {
+            me: me.clone(),
+            live,
+        }))
+    }
+}
+
+/// Per-user leases held by this instance
+pub struct Leases {
+    cache: SharedCache,
+    instance_id: String,
+    held: Mutex<HashSet<i64>>,
+    /// Last time every wanted lease was claimed or renewed
+    renewed_at: Mutex<Option<Instant>>,
+}
+
+impl Leases {
+    pub fn new(cache: SharedCache, instance_id: impl Into<String>) -> Self {
+        Self {
+            cache,
+            instance_id: instance_id.into(),
+            held: Mutex::new(HashSet::new()),
+            renewed_at: Mutex::new(None),
+        }
+    }
+
+    fn key(user_id: i64) -> String {
+        format!("shard:lease:{user_id}")
+    }
+
+    /// Renew the leases we hold and try to take the rest; returns the users
+    /// of `wanted` whose lease is ours. Someone else's live lease is left be.
+    pub async fn claim(&self, wanted: &HashSet<i64>) -> Result<HashSet<i64>, CacheError> {
+        let me = &self.instance_id;
+        let mut granted = HashSet::new();
+        for &uid in wanted {
+            let key = Self::key(uid);
+            if self.cache.renew_if(&key, me, LEASE_TTL_SECS).await?
+                || self.cache.set_nx(&key, me, LEASE_TTL_SECS).await?
+            {
+                granted.insert(uid);
+            }
+        }
+        self.held
+            .lock()
+            .expect("lease lock")
+            .extend(granted.iter().copied());
+        *self.renewed_at.lock().expect("lease lock") = Some(Instant::now());
+        Ok(granted)
+    }
+
+    /// Give up every held lease not in `keep`. Call only after the tasks for
+    /// those users have been stopped.
+    pub async fn release(&self, keep: &HashSet<i64>) -> Result<(), CacheError> {
+        let stale: Vec<i64> = {
+            let held = self.held.lock().expect("lease lock");
+            held.difference(keep).copied().collect()
+        };
+        for uid in stale {
+            self.cache
+                .del_if(&Self::key(uid), &self.instance_id)
+                .await?;
+            self.held.lock().expect("lease lock").remove(&uid);
+        }
+        Ok(())
+    }
+
+    pub fn held(&self) -> usize {
+        self.held.lock().expect("lease lock").len()
+    }
+
+    /// Renewals have been failing long enough that our leases may be gone
+    fn lapsed(&self) -> bool {
+        self.renewed_at
+            .lock()
+            .expect("lease lock")
+            .is_none_or(|t| t.elapsed() >= LEASE_GRACE)
+    }
+}
+
+enum Mode {
+    Off,
+    Static(Shard),
+    Dynamic(Membership),
+}
+
+pub struct ShardSource {
+    mode: Mode,
+    /// `None` in `SHARD_MODE=off`: one node, nothing to contend with
+    leases: Option<Leases>,
+    last: Mutex<Option<Owner>>,
+}
+
+impl ShardSource {
+    pub fn from_settings(settings: &Settings, cache: SharedCache) -> Self {
+        let id = settings.instance_id.clone();
+        let mode = match settings.shard_mode.as_str() {
+            "static" => Mode::Static(Shard {
+                index: settings.shard_index,
+                count: settings.shard_count,
+            }),
+            "dynamic" => Mode::Dynamic(Membership::new(cache.clone(), id.clone())),
+            _ => Mode::Off,
+        };
+        Self::new(mode, cache, id)
+    }
+
+    fn new(mode: Mode, cache: SharedCache, instance_id: String) -> Self {
+        let leases = match mode {
+            Mode::Off => None,
+            _ => Some(Leases::new(cache, instance_id)),
+        };
+        Self {
+            mode,
+            leases,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Who this node should run right now. `None` = membership unknown.
+    pub async fn current(&self) -> Option<Owner> {
+        let owner = match &self.mode {
+            Mode::Off => Some(Owner::All),
+            Mode::Static(s) => Some(Owner::Slot(*s)),
+            Mode::Dynamic(membership) => match membership.refresh().await {
+                Ok(ring) => ring.map(Owner::Ring),
+                Err(e) => {
+                    tracing::warn!("sharding: membership refresh failed: {e}");
+                    None
+                }
+            },
+        };
+        if let Some(o) = &owner {
+            let mut last = self.last.lock().expect("shard lock");
+            if last.as_ref() != Some(o) {
+                tracing::info!("sharding: rebalanced {:?} → {:?}", *last, o);
+                *last = Some(o.clone());
+            }
+            let (index, count) = o.position();
+            gauge!("scheduler_shard_index", index as f64);
+            gauge!("scheduler_shard_count", count as f64);
+        }
+        owner
+    }
+
+    /// The subset of `users` this node may run: owned *and* leased to us.
+    /// `None` = unknown right now; callers must neither spawn nor reap. Once
+    /// renewals have failed for [`LEASE_GRACE`] this returns an empty set so
+    /// the caller stops everything before another node can take over.
+    pub async fn assign(&self, users: impl IntoIterator<Item = i64>) -> Option<HashSet<i64>> {
+        let owner = self.current().await;
+        let Some(leases) = &self.leases else {
+            return owner.map(|_| users.into_iter().collect());
+        };
+        let claimed = match owner {
+            Some(o) => {
+                let wanted = users.into_iter().filter(|&u| o.owns(u)).collect();
+                leases
+                    .claim(&wanted)
+                    .await
+                    .map_err(|e| tracing::warn!("sharding: lease claim failed: {e}"))
+                    .ok()
+            }
+            None => None,
+        };
+        let assigned = match claimed {
+            Some(c) => Some(c),
+            None if leases.lapsed() => {
+                tracing::error!("sharding: leases lapsed, stopping every local strategy");
+                Some(HashSet::new())
+            }
+            None => None,
+        };
+        gauge!("scheduler_leases_held", leases.held() as f64);
+        assigned
+    }
+
+    /// Release leases for users no longer in `running`, once they've been reaped
+    pub async fn release(&self, running: &HashSet<i64>) {
+        let Some(leases) = &self.leases else {
+            return;
+        };
+        if let Err(e) = leases.release(running).await {
+            tracing::warn!("sharding: lease release failed: {e}");
+        }
+        gauge!("scheduler_leases_held", leases.held() as f64);
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::cache::MemoryCache;
+    use std::sync::Arc;
+
+    fn ring(me: &str, live: &[&str]) -> Ring {
+        Ring {
+            me: me.into(),
+            live: live.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn jump_hash_stays_in_range_and_is_stable() {
+        for k in 0..1_000u64 {
+            assert!(jump_hash(k, 7) < 7);
+            assert_eq!(jump_hash(k, 7), jump_hash(k, 7));
+        }
+        assert_eq!(jump_hash(42, 1), 0);
+        assert_eq!(jump_hash(42, 0), 0);
+    }
+
+    #[test]
+    fn every_user_owned_by_exactly_one_shard() {
+        let n = 4;
+        for uid in 0..500i64 {
+            let owners = (0..n)
+                .filter(|&i| Shard { index: i, count: n }.owns(uid))
+                .count();
+            assert_eq!(owners, 1, "user {uid}");
+        }
+        let nodes = ["a", "b", "c"];
+        for uid in 0..500i64 {
+            let owners = nodes.iter().filter(|me| ring(me, &nodes).owns(uid)).count();
+            assert_eq!(owners, 1, "user {uid}");
+        }
+    }
+
+    #[test]
+    fn a_node_leaving_only_moves_its_own_users() {
+        let before = ring("a", &["a", "b", "c", "d"]);
+        let after = ring("a", &["a", "b", "d"]);
+        let mut moved = 0;
+        for uid in 0..10_000i64 {
+            let (was, now) = (before.owner_of(uid), after.owner_of(uid));
+            if was != Some("c") {
+                assert_eq!(was, now, "user {uid} moved off a surviving node");
+            } else {
+                moved += 1;
+            }
+        }
+        // roughly a quarter each; allow slack for hash variance. Read
+        // backwards this is `c` joining: it only takes users, never shuffles
+        assert!((1_500..3_500).contains(&moved), "moved {moved}");
+    }
+
+    #[tokio::test]
+    async fn dynamic_membership_tracks_the_live_set() {
+        let cache: SharedCache = Arc::new(MemoryCache::new());
+        let b = Membership::new(cache.clone(), "node-b");
+        let a = Membership::new(cache.clone(), "node-a");
+
+        assert_eq!(
+            b.refresh().await.unwrap(),
+            Some(ring("node-b", &["node-b"]))
+        );
+        assert_eq!(
+            a.refresh().await.unwrap(),
+            Some(ring("node-a", &["node-a", "node-b"]))
+        );
+        assert_eq!(
+            b.refresh().await.unwrap(),
+            Some(ring("node-b", &["node-a", "node-b"]))
+        );
+
+        // node-a stops heartbeating → node-b owns everything again
+        cache.del("shard:node:node-a").await.unwrap();
+        let alone = b.refresh().await.unwrap().unwrap();
+        assert!((0..100).all(|uid| alone.owns(uid)));
+        assert_eq!(cache.smembers(NODES_KEY).await.unwrap(), vec!["node-b"]);
+    }
+
+    #[tokio::test]
+    async fn a_lease_keeps_a_user_on_one_node() {
+        // both nodes think they own everyone (e.g. a stale static config)
+        let cache: SharedCache = Arc::new(MemoryCache::new());
+        let a = ShardSource::new(Mode::Static(Shard::ALL), cache.clone(), "a".into());
+        let b = ShardSource::new(Mode::Static(Shard::ALL), cache.clone(), "b".into());
+
+        assert_eq!(a.assign([1, 2]).await.unwrap(), HashSet::from([1, 2]));
+        assert_eq!(b.assign([1, 2, 3]).await.unwrap(), HashSet::from([3]));
+        // renewals keep them where they are
+        assert_eq!(a.assign([1, 2]).await.unwrap(), HashSet::from([1, 2]));
+        assert_eq!(b.assign([1, 2, 3]).await.unwrap(), HashSet::from([3]));
+
+        // a drops user 2 → releases after reaping → b picks it up
+        let kept = a.assign([1]).await.unwrap();
+        a.release(&kept).await;
+        assert_eq!(b.assign([1, 2, 3]).await.unwrap(), HashSet::from([2, 3]));
+
+        // a dies; once its lease expires b takes over
+        cache.del("shard:lease:1").await.unwrap();
+        assert_eq!(b.assign([1, 2, 3]).await.unwrap(), HashSet::from([1, 2, 3]));
+        assert_eq!(a.assign([1]).await.unwrap(), HashSet::new());
+    }
+
+    #[tokio::test]
+    async fn off_owns_everyone_without_leases() {
+        let cache: SharedCache = Arc::new(MemoryCache::new());
+        let s = ShardSource::new(Mode::Off, cache.clone(), "a".into());
+        assert_eq!(s.assign(0..100).await.unwrap().len(), 100);
+        assert_eq!(cache.get("shard:lease:1").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Bounded candle buffers
+//! ──────────────────────────────────────────────────────────────────────────
+//! * [`CandleBuffer`] keeps the newest `cap` bars in a ring buffer and drops
+//!   older ones, so a strategy task's memory stays fixed however long it runs
+//! * Deep lookbacks (VCSR's HVN map over 180 days) keep only a hot tail in
+//!   memory and [`spill`](CandleBuffer::with_spill) evicted bars to a Redis
+//!   list capped at `depth`; [`history`](CandleBuffer::history) stitches the
+//!   two back together when the full window is needed
+//! * Spilled lists expire `SPILL_TTL_SECS` after the last write, so stopped
+//!   strategies don't leave history behind. A failed spill or read is logged
+//!   and history comes up short – the task keeps trading
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::VecDeque;
+
+use crate::{
+    db::cache::{CacheError, SharedCache},
+    services::strategies::Candle,
+};
+
+/// Spilled history outlives its task by a week
+pub const SPILL_TTL_SECS: u64 = 7 * 24 * 3600;
+
+struct Spill {
+    cache: SharedCache,
+    key: String,
+    depth: usize,
+}
+
+impl Spill {
+    async fn push(&self, c: &Candle) -> Result<(), CacheError> {
+        self.cache
+            .lpush(&self.key, &serde_json::to_string(c)?)
+            .await?;
+        self.cache.ltrim(&self.key, self.depth).await?;
+        self.cache.expire(&self.key, SPILL_TTL_SECS).await
+    }
+
+    /// Newest `n` spilled bars, oldest first
+    async fn load(&self, n: usize) -> Result<Vec<Candle>, CacheError> {
+        let rows = self.cache.lrange_all(&self.key).await?;
+        let mut out = rows
+            .iter()
+            .take(n)
+            .map(|r| serde_json::from_str(r))
+            .collect::<Result<Vec<Candle>, _>>()?;
+        out.reverse();
+        Ok(out)
+    }
+}
+
+pub struct CandleBuffer {
+    cap: usize,
+    bars: VecDeque<Candle>,
+    spill: Option<Spill>,
+}
+
+impl CandleBuffer {
+    /// Keeps the newest `cap` bars (at least one)
+    pub fn new(cap: usize) -> Self {
+        let cap = cap.max(1);
+        Self {
+            cap,
+            bars: VecDeque::with_capacity(cap + 1),
+            spill: None,
+        }
+    }
+
+    /// Push bars that fall out of memory to the Redis list `key`, keeping the
+    /// newest `depth` of them
+    pub fn with_spill(mut self, cache: SharedCache, key: impl Into<String>, depth: usize) -> Self {
+        self.spill = Some(Spill {
+            cache,
+            key: key.into(),
+            depth,
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.bars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bars.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&Candle> {
+        self.bars.back()
+    }
+
+    /// In-memory bars, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &Candle> {
+        self.bars.iter()
+    }
+
+    /// In-memory bars as one slice, oldest first
+    pub fn as_slice(&mut self) -> &[Candle] {
+        self.bars.make_contiguous()
+    }
+
+    /// Add the newest bar; returns the one that fell out of memory (already
+    /// spilled when a spill is configured)
+    pub async fn push(&mut self, c: Candle) -> Option<Candle> {
+        self.bars.push_back(c);
+        if self.bars.len() <= self.cap {
+            return None;
+        }
+        let old = self.bars.pop_front()?;
+        if let Some(s) = &self.spill {
+            if let Err(e) = s.push(&old).await {
+                tracing::warn!("candle buffer: spill to {} failed: {e}", s.key);
+            }
+        }
+        Some(old)
+    }
+
+    /// The newest `n` bars, oldest first, reaching into the spill when memory
+    /// doesn't hold enough
+    pub async fn history(&self, n: usize) -> Vec<Candle> {
+        let hot = self.bars.len();
+        let mut out = Vec::with_capacity(n);
+        if let (Some(s), true) = (&self.spill, n > hot) {
+            match s.load(n - hot).await {
+                Ok(old) => out.extend(old),
+                Err(e) => tracing::warn!("candle buffer: reading {} failed: {e}", s.key),
+            }
+        }
+        out.extend(self.bars.iter().skip(hot.saturating_sub(n)).copied());
+        out
+    }
+
+    /// Forget spilled bars (e.g. left over from an earlier run of the task)
+    pub async fn clear_spill(&self) {
+        if let Some(s) = &self.spill {
+            if let Err(e) = s.cache.del(&s.key).await {
+                tracing::warn!("candle buffer: clearing {} failed: {e}", s.key);
+            }
+        }
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::cache::MemoryCache;
+    use std::sync::Arc;
+
+    fn bar(close: f64) -> Candle {
+        Candle {
+            close,
+            ..Default::default()
+        }
+    }
+
+    fn closes(c: &[Candle]) -> Vec<f64> {
+        c.iter().map(|x| x.close).collect()
+    }
+
+    #[tokio::test]
+    async fn memory_stays_at_cap() {
+        let mut b = CandleBuffer::new(3);
+        for i in 0..10 {
+            let evicted = b.push(bar(i as f64)).await;
+            assert_eq!(evicted.map(|c| c.close), (i >= 3).then(|| (i - 3) as f64));
+        }
+        assert_eq!(b.len(), 3);
+        assert_eq!(closes(b.as_slice()), [7.0, 8.0, 9.0]);
+        assert_eq!(b.last().unwrap().close, 9.0);
+        // nothing spilled → history is what's in memory
+        assert_eq!(closes(&b.history(10).await), [7.0, 8.0, 9.0]);
+        assert_eq!(closes(&b.history(2).await), [8.0, 9.0]);
+    }
+
+    #[tokio::test]
+    async fn spilled_bars_come_back_in_order() {
+        let cache: SharedCache = Arc::new(MemoryCache::new());
+        let mut b = CandleBuffer::new(3).with_spill(cache.clone(), "spill:t", 4);
+        for i in 0..10 {
+            b.push(bar(i as f64)).await;
+        }
+        // 3 in memory, the 4 before them in Redis, older ones trimmed
+        assert_eq!(cache.lrange_all("spill:t").await.unwrap().len(), 4);
+        assert_eq!(
+            closes(&b.history(100).await),
+            [3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]
+        );
+        assert_eq!(closes(&b.history(5).await), [5.0, 6.0, 7.0, 8.0, 9.0]);
+
+        b.clear_spill().await;
+        assert_eq!(closes(&b.history(5).await), [7.0, 8.0, 9.0]);
+    }
+}
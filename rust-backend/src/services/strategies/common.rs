@@ -1,36 +1,80 @@
 // src/services/strategies/common.rs
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct Candle {
-    pub ts: DateTime<Utc>,
-    pub open: f64,
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: f64,
-    /// Optional cumulative delta
-    pub delta: Option<f64>,
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::db::cache::Cache;
+use crate::services::strategies::schema::{self, FieldError, ParamSchema};
+
+// shared with API clients through `rustraptor-types`
+pub use rustraptor_types::market::{Candle, OrderBookSnapshot};
+
+/// Why a strategy can't run with the params it was given; the scheduler
+/// parks the row as `status = 'invalid'` with this message
+#[derive(thiserror::Error, Debug)]
+pub enum StrategyError {
+    #[error("invalid params: {0}")]
+    Params(#[from] serde_json::Error),
+    /// Params that break the strategy's [`ParamSchema`] or a rule across
+    /// fields, one entry per field
+    #[error("invalid params: {}", schema::describe(.0))]
+    Fields(Vec<FieldError>),
+    #[error("invalid config: {0}")]
+    Config(String),
+    #[error("unknown strategy `{0}`")]
+    Unknown(String),
 }
 
-// ----------------------------------- basic candle helpers -------------
-impl Default for Candle {
-    fn default() -> Self {
-        Candle {
-            ts: Default::default(),
-            open: 0.0,
-            high: 0.0,
-            low: 0.0,
-            close: 0.0,
-            volume: 0.0,
-            delta: None,
+impl StrategyError {
+    /// `field` of the params is rejected
+    pub fn field(field: &str, message: impl Into<String>) -> Self {
+        Self::Fields(vec![FieldError::new(field, message)])
+    }
+}
+
+impl ParamSchema {
+    /// Every field error of `params` at once
+    pub fn check(&self, params: &serde_json::Value) -> Result<(), StrategyError> {
+        let errors = self.validate(params);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(StrategyError::Fields(errors))
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct OrderBookSnapshot {
-    pub bid_depth: f64,
-    pub ask_depth: f64,
+// ----------------------------------- graceful restarts ----------------
+/// How long state handed over by a draining task waits for the next run
+pub const HANDOVER_TTL_SECS: u64 = 24 * 3600;
+
+fn handover_key(strategy_id: Uuid) -> String {
+    format!("strategy:handover:{strategy_id}")
+}
+
+/// Leave in-memory position state for the next run of `strategy_id`;
+/// called by loops that return because the process is draining
+pub async fn hand_over<T: Serialize + Sync>(cache: &dyn Cache, strategy_id: Uuid, state: &T) {
+    if let Err(e) = cache
+        .set_json(&handover_key(strategy_id), state, HANDOVER_TTL_SECS)
+        .await
+    {
+        tracing::error!("strategy {strategy_id}: handing over position state: {e}");
+    }
+}
+
+/// What the previous run handed over, if anything; consumed on read so a
+/// later start doesn't pick up a stale position
+pub async fn take_over<T: DeserializeOwned>(cache: &dyn Cache, strategy_id: Uuid) -> Option<T> {
+    let key = handover_key(strategy_id);
+    let state = match cache.get_json(&key).await {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::warn!("strategy {strategy_id}: reading handed-over state: {e}");
+            None
+        }
+    };
+    if state.is_some() {
+        let _ = cache.del(&key).await;
+    }
+    state
 }
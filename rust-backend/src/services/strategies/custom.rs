@@ -0,0 +1,218 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! User-supplied strategy logic – shared driver
+//! ──────────────────────────────────────────────────────────────────────────
+//! Scripts (`script`) and WASM plugins (`plugin`) only answer "buy / sell /
+//! exit / nothing" for the latest 1 h bar; everything around that lives here
+//! so both behave the same:
+//!
+//! * the strategy's own position (1 long, -1 short, 0 flat) is tracked in
+//!   the task; a signal against an open position closes it ([`decide`])
+//! * entries pass the drawdown check, orders go through
+//!   `allocation::execute_traced` with the signal as decision trace
+//! * `MAX_FAILURES` failing calls in a row park the strategy as invalid
+//! * a drain stops the loop between bars; the position is handed over to
+//!   the next run
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::{
+    db::cache::SharedCache,
+    services::{
+        allocation::{self, Sizing},
+        drain,
+        market_data::MarketBus,
+        replay::DecisionTrace,
+        risk,
+        strategies::{
+            buffer::CandleBuffer,
+            common::{self, Candle},
+            heartbeat::{CandleFeed, Heartbeat},
+            warmup::Warmup,
+            StrategyError,
+        },
+        telemetry::candle_span,
+        trading_engine::{Exchange, TradeRequest},
+    },
+};
+
+/// Consecutive failing calls before the strategy is parked
+pub const MAX_FAILURES: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Signal {
+    Buy,
+    Sell,
+    Exit,
+}
+
+/// What a signal does to the current position (1 / 0 / -1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Action {
+    pub side: &'static str,
+    pub reduce_only: bool,
+    pub position: i8,
+}
+
+pub fn decide(signal: Signal, position: i8) -> Option<Action> {
+    let act = |side, reduce_only, position| {
+        Some(Action {
+            side,
+            reduce_only,
+            position,
+        })
+    };
+    match (signal, position.signum()) {
+        (Signal::Buy, 0) => act("buy", false, 1),
+        (Signal::Sell, 0) => act("sell", false, -1),
+        (Signal::Buy, -1) | (Signal::Exit, -1) => act("buy", true, 0),
+        (Signal::Sell, 1) | (Signal::Exit, 1) => act("sell", true, 0),
+        _ => None,
+    }
+}
+
+/// Who is trading what
+pub struct Runner {
+    /// `script` / `plugin`, for logs
+    pub kind: &'static str,
+    pub strategy_id: Uuid,
+    pub user_id: i64,
+    pub symbol: String,
+    pub qty: f64,
+    /// Bars handed to the user code
+    pub history: usize,
+    pub warm: Arc<Warmup>,
+}
+
+/// Feed closed 1 h bars to `on_candle` and trade its signals; returns on a
+/// closed feed, a drain or once the user code keeps failing
+pub async fn drive(
+    run: Runner,
+    cache: SharedCache,
+    db: Arc<PgPool>,
+    bus: MarketBus,
+    master_key: Vec<u8>,
+    is_demo: bool,
+    mut on_candle: impl FnMut(&[Candle], i8) -> Result<Option<Signal>, String> + Send,
+) -> Result<(), StrategyError> {
+    let Runner {
+        kind,
+        strategy_id,
+        user_id,
+        ..
+    } = run;
+    let mut bars = CandleBuffer::new(run.history);
+    for c in run.warm.take("1h") {
+        bars.push(c).await;
+    }
+    let mut position: i8 = common::take_over(cache.as_ref(), strategy_id)
+        .await
+        .unwrap_or(0);
+    let mut failures = 0;
+    let mut rx = CandleFeed::new(
+        bus.candles(&run.symbol, "1h"),
+        Heartbeat::new(cache.clone(), strategy_id, &run.symbol, "1h"),
+    );
+
+    while let Some(Ok(c)) = drain::or_stop(rx.recv()).await {
+        bars.push(c).await;
+        run.warm.bar("1h");
+        if !run.warm.is_ready() {
+            continue;
+        }
+
+        let signal = match on_candle(bars.as_slice(), position) {
+            Ok(s) => {
+                failures = 0;
+                s
+            }
+            Err(e) => {
+                failures += 1;
+                tracing::warn!("{kind} {strategy_id}: on_candle failed ({failures}): {e}");
+                if failures >= MAX_FAILURES {
+                    return Err(StrategyError::Config(format!("{kind}: {e}")));
+                }
+                continue;
+            }
+        };
+        let Some((signal, action)) = signal.and_then(|s| decide(s, position).map(|a| (s, a)))
+        else {
+            continue;
+        };
+
+        if !action.reduce_only {
+            if let Err(e) = risk::check_drawdown(cache.as_ref(), user_id).await {
+                tracing::warn!("{kind} {strategy_id}: DD limit hit – skipping entry: {e}");
+                continue;
+            }
+        }
+        let trace = DecisionTrace::new().with(
+            "signal",
+            json!({
+                "source": kind,
+                "candle_ts": c.ts,
+                "close": c.close,
+                "signal": signal,
+                "position": position,
+            }),
+        );
+        match allocation::execute_traced(
+            &db,
+            strategy_id,
+            TradeRequest {
+                exchange: Exchange::Blowfin,
+                symbol: run.symbol.clone(),
+                side: action.side.into(),
+                order_type: "market".into(),
+                price: None,
+                size: run.qty,
+                reduce_only: action.reduce_only,
+                signal_price: Some(c.close),
+                tp_sl: None,
+            },
+            user_id,
+            is_demo,
+            &master_key,
+            Sizing::ScaleQty,
+            trace,
+        )
+        .instrument(candle_span(kind, &run.symbol, &c))
+        .await
+        {
+            Ok(_) => position = action.position,
+            Err(e) => tracing::error!("{kind} {strategy_id}: trade error: {e:?}"),
+        }
+    }
+    if drain::is_draining() && position != 0 {
+        common::hand_over(cache.as_ref(), strategy_id, &position).await;
+    }
+    Ok(())
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_against_a_position_close_it() {
+        let d = |s, p| decide(s, p).map(|a| (a.side, a.reduce_only, a.position));
+        assert_eq!(d(Signal::Buy, 0), Some(("buy", false, 1)));
+        assert_eq!(d(Signal::Sell, 0), Some(("sell", false, -1)));
+        assert_eq!(d(Signal::Sell, 1), Some(("sell", true, 0)));
+        assert_eq!(d(Signal::Buy, -1), Some(("buy", true, 0)));
+        assert_eq!(d(Signal::Exit, -1), Some(("buy", true, 0)));
+        assert_eq!(d(Signal::Buy, 1), None);
+        assert_eq!(d(Signal::Exit, 0), None);
+    }
+}
@@ -0,0 +1,231 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Strategy heartbeats – is a task still keeping up with its feed?
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every strategy loop [`beat`](Heartbeat::beat)s once per bar it has
+//!   processed – [`CandleFeed`] does it when the loop asks for the next one
+//!   – or, for the market maker, once per book, naming the data's
+//!   timestamp; the latest beat is kept in-process for the scheduler and
+//!   written to `heartbeat:{strategy_id}` for the status API – book beats at
+//!   most every `WRITE_EVERY`
+//! * The scheduler compares a task's beat with the bus' newest data of the
+//!   same feed ([`stalled`]): newer data the task has had `grace` to
+//!   process, but hasn't, means it is stuck and gets restarted; each restart
+//!   counts towards `heartbeat:{strategy_id}:stalls`
+//! * A task is watched from its first beat on; one stuck before it would
+//!   need the bus data to be compared with an unknown starting point
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use uuid::Uuid;
+
+use crate::db::cache::{Cache, CacheError, SharedCache};
+use crate::services::market_data::{bus_symbol, Freshness, BOOK_FEED};
+use crate::services::strategies::Candle;
+
+/// Book beats reach Redis at most this often
+const WRITE_EVERY: Duration = Duration::from_secs(10);
+/// A beat outlives a day of 1 h or 4 h bars; stall counts a month
+const BEAT_TTL_SECS: u64 = 2 * 24 * 3600;
+const STALLS_TTL_SECS: u64 = 30 * 24 * 3600;
+
+/// Latest data a strategy task has processed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Beat {
+    pub symbol: String,
+    /// Candle interval, or `book`
+    pub feed: String,
+    /// Timestamp of the data
+    pub ts: DateTime<Utc>,
+    /// When the task processed it
+    pub at: DateTime<Utc>,
+}
+
+/// What the status API shows
+#[derive(Debug, Clone, Serialize)]
+pub struct HeartbeatView {
+    #[serde(flatten)]
+    pub beat: Option<Beat>,
+    /// Times the task was restarted for stalling
+    pub stalls: i64,
+}
+
+/// (latest beat, when it was last written to Redis)
+static BEATS: Lazy<DashMap<Uuid, (Beat, Instant)>> = Lazy::new(DashMap::new);
+
+pub fn key(strategy_id: Uuid) -> String {
+    format!("heartbeat:{strategy_id}")
+}
+
+fn stalls_key(strategy_id: Uuid) -> String {
+    format!("heartbeat:{strategy_id}:stalls")
+}
+
+/// One strategy task's pulse on one feed
+#[derive(Clone)]
+pub struct Heartbeat {
+    cache: SharedCache,
+    strategy_id: Uuid,
+    symbol: String,
+    feed: &'static str,
+}
+
+impl Heartbeat {
+    pub fn new(cache: SharedCache, strategy_id: Uuid, symbol: &str, feed: &'static str) -> Self {
+        Self {
+            cache,
+            strategy_id,
+            symbol: bus_symbol(symbol),
+            feed,
+        }
+    }
+
+    /// Data of `ts` has been processed
+    pub async fn beat(&self, ts: DateTime<Utc>) {
+        let beat = Beat {
+            symbol: self.symbol.clone(),
+            feed: self.feed.to_string(),
+            ts,
+            at: Utc::now(),
+        };
+        let last_write = BEATS.get(&self.strategy_id).map(|b| b.1);
+        match last_write {
+            Some(w) if self.feed == BOOK_FEED && w.elapsed() < WRITE_EVERY => {
+                BEATS.insert(self.strategy_id, (beat, w));
+                return;
+            }
+            _ => BEATS.insert(self.strategy_id, (beat.clone(), Instant::now())),
+        };
+        let key = key(self.strategy_id);
+        if let Err(e) = self.cache.set_json(&key, &beat, BEAT_TTL_SECS).await {
+            tracing::warn!("heartbeat {}: {e}", self.strategy_id);
+        }
+    }
+}
+
+/// Candles off the bus; asking for the next one beats for the last
+pub struct CandleFeed {
+    rx: Receiver<Candle>,
+    heart: Heartbeat,
+    done: Option<DateTime<Utc>>,
+}
+
+impl CandleFeed {
+    pub fn new(rx: Receiver<Candle>, heart: Heartbeat) -> Self {
+        Self {
+            rx,
+            heart,
+            done: None,
+        }
+    }
+
+    pub async fn recv(&mut self) -> Result<Candle, RecvError> {
+        if let Some(ts) = self.done.take() {
+            self.heart.beat(ts).await;
+        }
+        let c = self.rx.recv().await?;
+        self.done = Some(c.ts);
+        Ok(c)
+    }
+}
+
+/// Latest beat of a task running on this instance
+pub fn last(strategy_id: Uuid) -> Option<Beat> {
+    BEATS.get(&strategy_id).map(|b| b.0.clone())
+}
+
+/// The task is gone; a new one starts unwatched until its first beat
+pub fn forget(strategy_id: Uuid) {
+    BEATS.remove(&strategy_id);
+}
+
+/// Whether the task behind `beat` is stuck: the bus has newer data of its
+/// feed and the task has had `grace` to process it. Bars arrive one at a
+/// time, so the wait starts when the newest one came in; books stream, so
+/// newer ones were there right after the beat.
+pub fn stalled(beat: &Beat, fresh: Option<Freshness>, now: DateTime<Utc>, grace: Duration) -> bool {
+    let Some(fresh) = fresh.filter(|f| f.ts > beat.ts) else {
+        return false;
+    };
+    let waiting_since = if beat.feed == BOOK_FEED {
+        beat.at
+    } else {
+        fresh.received
+    };
+    (now - waiting_since).to_std().unwrap_or_default() > grace
+}
+
+/// Count a restart for stalling; the new total
+pub async fn record_stall(cache: &dyn Cache, strategy_id: Uuid) -> Result<i64, CacheError> {
+    let key = stalls_key(strategy_id);
+    let n = cache.incr_by(&key, 1).await?;
+    cache.expire(&key, STALLS_TTL_SECS).await?;
+    Ok(n)
+}
+
+/// Latest beat and stall count, from whichever instance runs the task
+pub async fn view(cache: &dyn Cache, strategy_id: Uuid) -> Result<HeartbeatView, CacheError> {
+    Ok(HeartbeatView {
+        beat: cache.get_json(&key(strategy_id)).await?,
+        stalls: cache.get_i64(&stalls_key(strategy_id)).await?,
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 8, 1, h, m, 0).unwrap()
+    }
+
+    fn beat(feed: &str, ts: DateTime<Utc>, at: DateTime<Utc>) -> Beat {
+        Beat {
+            symbol: "BTCUSDT".into(),
+            feed: feed.into(),
+            ts,
+            at,
+        }
+    }
+
+    #[test]
+    fn a_bar_counts_once_it_has_waited_out_the_grace() {
+        let grace = Duration::from_secs(120);
+        let b = beat("1h", at(9, 0), at(10, 0));
+        let next = Some(Freshness {
+            ts: at(10, 0),
+            received: at(11, 0),
+        });
+        assert!(!stalled(&b, next, at(11, 1), grace));
+        assert!(stalled(&b, next, at(11, 3), grace));
+        // caught up, or the feed itself is quiet
+        let same = Some(Freshness {
+            ts: at(9, 0),
+            received: at(10, 0),
+        });
+        assert!(!stalled(&b, same, at(15, 0), grace));
+        assert!(!stalled(&b, None, at(15, 0), grace));
+    }
+
+    #[test]
+    fn books_wait_from_the_last_beat() {
+        let grace = Duration::from_secs(120);
+        let b = beat(BOOK_FEED, at(10, 0), at(10, 0));
+        let live = Some(Freshness {
+            ts: at(10, 5),
+            received: at(10, 5),
+        });
+        assert!(stalled(&b, live, at(10, 5), grace));
+        assert!(!stalled(&b, live, at(10, 1), grace));
+    }
+}
@@ -0,0 +1,684 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Indicator maths shared by the strategies
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Pure functions over candle slices, always looking at the *last* `n`
+//!   bars; `None` when there isn't enough history or the value is undefined
+//!   (`n == 0`, no traded volume)
+//! * Property-tested against random series from [`super::testkit`] – new
+//!   indicators should come with their invariants
+//! * Order-book measures work on [`OrderBookSnapshot`] slices the same way;
+//!   strategies feed them the per-minute aggregates from `depth_history`
+//! * Long-running loops use the `Rolling*` state instead: fed one bar at a
+//!   time, O(1) per bar (ring buffer + running sums, monotonic deques for
+//!   Donchian), and tested to agree with the slice functions above. Running
+//!   sums are re-added from the buffer once per window so float error
+//!   doesn't accumulate
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::VecDeque;
+
+use statrs::statistics::{Data as StatsData, Distribution};
+
+use crate::services::strategies::{Candle, OrderBookSnapshot};
+
+fn last<T>(xs: &[T], n: usize) -> Option<&[T]> {
+    if n == 0 || xs.len() < n {
+        return None;
+    }
+    Some(&xs[xs.len() - n..])
+}
+
+/// Simple moving average of the last `n` values
+pub fn sma(xs: &[f64], n: usize) -> Option<f64> {
+    let w = last(xs, n)?;
+    Some(w.iter().sum::<f64>() / n as f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bands {
+    pub lower: f64,
+    pub mid: f64,
+    pub upper: f64,
+}
+
+/// Bollinger bands on closes: SMA ± `k` population standard deviations
+pub fn bollinger(c: &[Candle], n: usize, k: f64) -> Option<Bands> {
+    let w = last(c, n)?;
+    let mid = w.iter().map(|x| x.close).sum::<f64>() / n as f64;
+    let sd = (w.iter().map(|x| (x.close - mid).powi(2)).sum::<f64>() / n as f64).sqrt();
+    Some(Bands {
+        lower: mid - k * sd,
+        mid,
+        upper: mid + k * sd,
+    })
+}
+
+/// Average true range over the last `n` bars (needs one more for the
+/// previous close)
+pub fn atr(c: &[Candle], n: usize) -> Option<f64> {
+    if n == 0 || c.len() <= n {
+        return None;
+    }
+    let sum: f64 = c
+        .windows(2)
+        .rev()
+        .take(n)
+        .map(|w| {
+            let pc = w[0].close;
+            let b = w[1];
+            (b.high - b.low)
+                .max((b.high - pc).abs())
+                .max((b.low - pc).abs())
+        })
+        .sum();
+    Some(sum / n as f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vwap {
+    pub mean: f64,
+    /// Sample standard deviation of the closes in the window
+    pub std_dev: f64,
+}
+
+/// Volume-weighted average close over the last `win` bars; `None` when
+/// nothing traded in the window or it's too short for a spread (`win < 2`)
+pub fn vwap(c: &[Candle], win: usize) -> Option<Vwap> {
+    let w = last(c, win)?;
+    let vol: f64 = w.iter().map(|x| x.volume).sum();
+    if vol <= 0.0 {
+        return None;
+    }
+    let pv: f64 = w.iter().map(|x| x.close * x.volume).sum();
+    let closes: Vec<f64> = w.iter().map(|x| x.close).collect();
+    Some(Vwap {
+        mean: pv / vol,
+        std_dev: StatsData::new(closes).std_dev().filter(|s| s.is_finite())?,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channel {
+    pub high: f64,
+    pub low: f64,
+}
+
+/// Donchian channel: highest high / lowest low of the last `n` bars
+pub fn donchian(c: &[Candle], n: usize) -> Option<Channel> {
+    let w = last(c, n)?;
+    Some(Channel {
+        high: w.iter().map(|x| x.high).fold(f64::MIN, f64::max),
+        low: w.iter().map(|x| x.low).fold(f64::MAX, f64::min),
+    })
+}
+
+/// A volume-profile level: a bar's mid price and what traded there
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Node {
+    pub price: f64,
+    pub volume: f64,
+}
+
+/// High-volume nodes: the heaviest bars, by volume, until they add up to
+/// more than `pct` of the total. Empty when nothing traded.
+pub fn high_volume_nodes(c: &[Candle], pct: f64) -> Vec<Node> {
+    let total: f64 = c.iter().map(|x| x.volume).sum();
+    if total <= 0.0 {
+        return vec![];
+    }
+    let mut nodes: Vec<Node> = c
+        .iter()
+        .map(|x| Node {
+            price: (x.high + x.low) * 0.5,
+            volume: x.volume,
+        })
+        .collect();
+    nodes.sort_by(|a, b| b.volume.total_cmp(&a.volume));
+
+    let mut acc = 0.0;
+    nodes
+        .into_iter()
+        .take_while(|n| {
+            acc += n.volume;
+            acc / total <= pct
+        })
+        .collect()
+}
+
+// ─── Order book ──────────────────────────────────────────────────────────
+
+/// Book imbalance of one snapshot, `(bid − ask) / (bid + ask)` in [-1, 1];
+/// `None` for an empty book
+pub fn imbalance(ob: &OrderBookSnapshot) -> Option<f64> {
+    let total = ob.bid_depth + ob.ask_depth;
+    (total > 0.0).then(|| (ob.bid_depth - ob.ask_depth) / total)
+}
+
+/// Depth slope of one book side: cumulative size gained per basis point
+/// away from `mid` (least squares through the origin over `(price, size)`
+/// levels). Steep = thick book near the touch; `None` without a usable level
+pub fn depth_slope(levels: &[(f64, f64)], mid: f64) -> Option<f64> {
+    if mid <= 0.0 {
+        return None;
+    }
+    let (mut cum, mut xy, mut xx) = (0.0, 0.0, 0.0);
+    for &(price, size) in levels {
+        let dist_bps = (price - mid).abs() / mid * 10_000.0;
+        if size <= 0.0 || dist_bps <= 0.0 || !dist_bps.is_finite() {
+            continue;
+        }
+        cum += size;
+        xy += dist_bps * cum;
+        xx += dist_bps * dist_bps;
+    }
+    (xx > 0.0).then(|| xy / xx)
+}
+
+/// Order flow over a window of snapshots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookFlow {
+    /// Mean [`imbalance`]
+    pub imbalance: f64,
+    /// Mean [`depth_slope`] per side, over the snapshots that had one
+    pub bid_slope: Option<f64>,
+    pub ask_slope: Option<f64>,
+}
+
+impl BookFlow {
+    /// Bid/ask depth ratio implied by the mean imbalance (∞ for a one-sided
+    /// book)
+    pub fn bid_ask_ratio(&self) -> f64 {
+        (1.0 + self.imbalance) / (1.0 - self.imbalance)
+    }
+
+    /// Bid over ask depth slope: > 1 when support sits closer than supply
+    pub fn slope_ratio(&self) -> Option<f64> {
+        match (self.bid_slope, self.ask_slope) {
+            (Some(b), Some(a)) if a > 0.0 => Some(b / a),
+            _ => None,
+        }
+    }
+}
+
+/// [`BookFlow`] over the last `n` snapshots; `None` when there are fewer or
+/// none of them had any depth
+pub fn book_flow(obs: &[OrderBookSnapshot], n: usize) -> Option<BookFlow> {
+    let w = last(obs, n)?;
+    let mean = |xs: Vec<f64>| (!xs.is_empty()).then(|| xs.iter().sum::<f64>() / xs.len() as f64);
+    Some(BookFlow {
+        imbalance: mean(w.iter().filter_map(imbalance).collect())?,
+        bid_slope: mean(w.iter().filter_map(|o| o.bid_slope).collect()),
+        ask_slope: mean(w.iter().filter_map(|o| o.ask_slope).collect()),
+    })
+}
+
+// ─── Rolling state ───────────────────────────────────────────────────────
+
+/// Mean and spread of the last `n` values (sliding Welford update)
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    n: usize,
+    buf: VecDeque<f64>,
+    mean: f64,
+    /// Sum of squared deviations from `mean`
+    m2: f64,
+    /// Pushes since `mean` / `m2` were last recomputed from `buf`
+    since_resync: usize,
+}
+
+impl RollingStats {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            buf: VecDeque::with_capacity(n + 1),
+            mean: 0.0,
+            m2: 0.0,
+            since_resync: 0,
+        }
+    }
+
+    /// Adds `x`; returns the value that fell out of the window
+    pub fn push(&mut self, x: f64) -> Option<f64> {
+        if self.n == 0 {
+            return None;
+        }
+        self.buf.push_back(x);
+        let len = self.buf.len() as f64;
+        let d = x - self.mean;
+        self.mean += d / len;
+        self.m2 += d * (x - self.mean);
+
+        let old = if self.buf.len() > self.n {
+            self.buf.pop_front()
+        } else {
+            None
+        };
+        if let Some(y) = old {
+            let len = self.buf.len() as f64;
+            let d = y - self.mean;
+            self.mean -= d / len;
+            self.m2 -= d * (y - self.mean);
+        }
+
+        self.since_resync += 1;
+        if self.since_resync >= self.n {
+            let len = self.buf.len() as f64;
+            self.mean = self.buf.iter().sum::<f64>() / len;
+            self.m2 = self.buf.iter().map(|x| (x - self.mean).powi(2)).sum();
+            self.since_resync = 0;
+        }
+        old
+    }
+
+    /// The window holds `n` values
+    pub fn is_full(&self) -> bool {
+        self.n > 0 && self.buf.len() == self.n
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        self.is_full().then_some(self.mean)
+    }
+
+    pub fn sum(&self) -> Option<f64> {
+        self.mean().map(|m| m * self.n as f64)
+    }
+
+    /// Population standard deviation
+    pub fn std_dev(&self) -> Option<f64> {
+        self.is_full()
+            .then(|| (self.m2.max(0.0) / self.n as f64).sqrt())
+    }
+
+    /// Sample standard deviation; `None` for windows of one
+    pub fn sample_std_dev(&self) -> Option<f64> {
+        (self.is_full() && self.n > 1).then(|| (self.m2.max(0.0) / (self.n - 1) as f64).sqrt())
+    }
+}
+
+/// [`bollinger`], one close at a time
+#[derive(Debug, Clone)]
+pub struct RollingBollinger {
+    closes: RollingStats,
+    k: f64,
+}
+
+impl RollingBollinger {
+    pub fn new(n: usize, k: f64) -> Self {
+        Self {
+            closes: RollingStats::new(n),
+            k,
+        }
+    }
+
+    pub fn push(&mut self, c: &Candle) {
+        self.closes.push(c.close);
+    }
+
+    pub fn value(&self) -> Option<Bands> {
+        let (mid, sd) = (self.closes.mean()?, self.closes.std_dev()?);
+        Some(Bands {
+            lower: mid - self.k * sd,
+            mid,
+            upper: mid + self.k * sd,
+        })
+    }
+}
+
+/// [`atr`], one bar at a time
+#[derive(Debug, Clone)]
+pub struct RollingAtr {
+    prev_close: Option<f64>,
+    ranges: RollingStats,
+}
+
+impl RollingAtr {
+    pub fn new(n: usize) -> Self {
+        Self {
+            prev_close: None,
+            ranges: RollingStats::new(n),
+        }
+    }
+
+    pub fn push(&mut self, c: &Candle) {
+        if let Some(pc) = self.prev_close {
+            self.ranges.push(
+                (c.high - c.low)
+                    .max((c.high - pc).abs())
+                    .max((c.low - pc).abs()),
+            );
+        }
+        self.prev_close = Some(c.close);
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.ranges.mean()
+    }
+}
+
+/// [`vwap`], one bar at a time
+#[derive(Debug, Clone)]
+pub struct RollingVwap {
+    pv: RollingStats,
+    volume: RollingStats,
+    closes: RollingStats,
+    /// Bars in the window with volume – a running sum of zeros may not be
+    /// exactly zero
+    traded: usize,
+}
+
+impl RollingVwap {
+    pub fn new(win: usize) -> Self {
+        Self {
+            pv: RollingStats::new(win),
+            volume: RollingStats::new(win),
+            closes: RollingStats::new(win),
+            traded: 0,
+        }
+    }
+
+    pub fn push(&mut self, c: &Candle) {
+        self.pv.push(c.close * c.volume);
+        self.closes.push(c.close);
+        if c.volume > 0.0 {
+            self.traded += 1;
+        }
+        if self.volume.push(c.volume).is_some_and(|v| v > 0.0) {
+            self.traded -= 1;
+        }
+    }
+
+    pub fn value(&self) -> Option<Vwap> {
+        let vol = self.volume.sum()?;
+        if self.traded == 0 || vol <= 0.0 {
+            return None;
+        }
+        Some(Vwap {
+            mean: self.pv.sum()? / vol,
+            std_dev: self.closes.sample_std_dev()?,
+        })
+    }
+}
+
+/// [`donchian`], one bar at a time
+#[derive(Debug, Clone)]
+pub struct RollingDonchian {
+    n: usize,
+    seen: usize,
+    /// (bar index, high), highs decreasing
+    highs: VecDeque<(usize, f64)>,
+    /// (bar index, low), lows increasing
+    lows: VecDeque<(usize, f64)>,
+}
+
+impl RollingDonchian {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            seen: 0,
+            highs: VecDeque::new(),
+            lows: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, c: &Candle) {
+        let i = self.seen;
+        self.seen += 1;
+        while self.highs.back().is_some_and(|&(_, h)| h <= c.high) {
+            self.highs.pop_back();
+        }
+        self.highs.push_back((i, c.high));
+        while self.lows.back().is_some_and(|&(_, l)| l >= c.low) {
+            self.lows.pop_back();
+        }
+        self.lows.push_back((i, c.low));
+
+        let oldest = self.seen.saturating_sub(self.n);
+        while self.highs.front().is_some_and(|&(j, _)| j < oldest) {
+            self.highs.pop_front();
+        }
+        while self.lows.front().is_some_and(|&(j, _)| j < oldest) {
+            self.lows.pop_front();
+        }
+    }
+
+    pub fn value(&self) -> Option<Channel> {
+        if self.n == 0 || self.seen < self.n {
+            return None;
+        }
+        Some(Channel {
+            high: self.highs.front()?.1,
+            low: self.lows.front()?.1,
+        })
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::strategies::testkit;
+    use proptest::prelude::*;
+
+    fn tol(x: f64) -> f64 {
+        1e-9 * x.abs().max(1.0)
+    }
+
+    fn max_high(c: &[Candle]) -> f64 {
+        c.iter().map(|x| x.high).fold(f64::MIN, f64::max)
+    }
+
+    fn min_low(c: &[Candle]) -> f64 {
+        c.iter().map(|x| x.low).fold(f64::MAX, f64::min)
+    }
+
+    #[test]
+    fn degenerate_windows_are_none() {
+        let c = vec![Candle::default(); 3];
+        assert_eq!(sma(&[1.0, 2.0], 0), None);
+        assert_eq!(sma(&[1.0, 2.0], 3), None);
+        assert_eq!(bollinger(&c, 0, 2.0), None);
+        assert_eq!(atr(&c, 0), None);
+        assert_eq!(atr(&c, 3), None);
+        assert_eq!(donchian(&c, 4), None);
+        // every bar has zero volume
+        assert_eq!(vwap(&c, 3), None);
+        assert!(high_volume_nodes(&c, 0.7).is_empty());
+
+        assert_eq!(RollingStats::new(0).mean(), None);
+        assert_eq!(RollingDonchian::new(0).value(), None);
+        let mut v = RollingVwap::new(3);
+        c.iter().for_each(|x| v.push(x));
+        assert_eq!(v.value(), None);
+        let traded = [Candle {
+            volume: 1.0,
+            ..Default::default()
+        }];
+        assert_eq!(vwap(&traded, 1), None);
+
+        let empty = OrderBookSnapshot::default();
+        assert_eq!(imbalance(&empty), None);
+        assert_eq!(book_flow(&[empty; 3], 3), None);
+        assert_eq!(book_flow(&[empty; 3], 4), None);
+        assert_eq!(depth_slope(&[(100.0, 1.0)], 0.0), None);
+        assert_eq!(depth_slope(&[(100.0, 1.0)], 100.0), None);
+    }
+
+    #[test]
+    fn book_flow_averages_the_window() {
+        let ob = |bid_depth, ask_depth, bid_slope| OrderBookSnapshot {
+            bid_depth,
+            ask_depth,
+            bid_slope,
+            ask_slope: Some(2.0),
+            ..Default::default()
+        };
+        let obs = [
+            ob(9.0, 1.0, None),
+            ob(3.0, 1.0, Some(4.0)),
+            ob(1.0, 1.0, Some(2.0)),
+        ];
+        let f = book_flow(&obs, 2).unwrap();
+        assert!((f.imbalance - 0.25).abs() < 1e-12);
+        assert!((f.bid_ask_ratio() - 5.0 / 3.0).abs() < 1e-12);
+        assert_eq!(f.bid_slope, Some(3.0));
+        assert_eq!(f.slope_ratio(), Some(1.5));
+
+        // 1 lot every bp: cumulative 1, 2, 3 at 1, 2, 3 bps
+        let mid = 10_000.0;
+        let bids = [(9_999.0, 1.0), (9_998.0, 1.0), (9_997.0, 1.0)];
+        assert!((depth_slope(&bids, mid).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    /// Feeds `c` bar by bar and compares with the slice function on every
+    /// prefix
+    fn agrees<S, T: std::fmt::Debug>(
+        c: &[Candle],
+        mut state: S,
+        push: impl Fn(&mut S, &Candle),
+        value: impl Fn(&S) -> Option<T>,
+        batch: impl Fn(&[Candle]) -> Option<T>,
+        close: impl Fn(&T, &T) -> bool,
+    ) -> Result<(), TestCaseError> {
+        for i in 0..c.len() {
+            push(&mut state, &c[i]);
+            match (value(&state), batch(&c[..=i])) {
+                (Some(a), Some(b)) => prop_assert!(close(&a, &b), "bar {i}: {a:?} vs {b:?}"),
+                (a, b) => prop_assert_eq!(a.is_some(), b.is_some(), "bar {}", i),
+            }
+        }
+        Ok(())
+    }
+
+    fn near(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-6 * b.abs().max(1.0)
+    }
+
+    proptest! {
+        #[test]
+        fn imbalance_is_bounded(bid in 0.0..1e6f64, ask in 1.0..1e6f64) {
+            let ob = OrderBookSnapshot { bid_depth: bid, ask_depth: ask, ..Default::default() };
+            let i = imbalance(&ob).unwrap();
+            prop_assert!((-1.0..=1.0).contains(&i));
+            // the implied ratio recovers the depths
+            let f = book_flow(&[ob], 1).unwrap();
+            prop_assert!(near(f.bid_ask_ratio(), bid / ask));
+        }
+
+        #[test]
+        fn rolling_state_matches_the_slice_functions(
+            c in testkit::series(0..200),
+            n in 1usize..40,
+            k in 0.0..4.0f64,
+        ) {
+            let closes: Vec<f64> = c.iter().map(|x| x.close).collect();
+            agrees(
+                &c,
+                RollingStats::new(n),
+                |s, x| {
+                    s.push(x.close);
+                },
+                RollingStats::mean,
+                |w| sma(&closes[..w.len()], n),
+                |a, b| near(*a, *b),
+            )?;
+            agrees(
+                &c,
+                RollingBollinger::new(n, k),
+                RollingBollinger::push,
+                RollingBollinger::value,
+                |w| bollinger(w, n, k),
+                |x, y| near(x.lower, y.lower) && near(x.mid, y.mid) && near(x.upper, y.upper),
+            )?;
+            agrees(
+                &c,
+                RollingAtr::new(n),
+                RollingAtr::push,
+                RollingAtr::value,
+                |w| atr(w, n),
+                |x, y| near(*x, *y),
+            )?;
+            agrees(
+                &c,
+                RollingVwap::new(n),
+                RollingVwap::push,
+                RollingVwap::value,
+                |w| vwap(w, n),
+                |x, y| near(x.mean, y.mean) && near(x.std_dev, y.std_dev),
+            )?;
+            agrees(
+                &c,
+                RollingDonchian::new(n),
+                RollingDonchian::push,
+                RollingDonchian::value,
+                |w| donchian(w, n),
+                |x, y| x == y,
+            )?;
+        }
+
+        #[test]
+        fn atr_is_bounded_by_the_range(c in testkit::series(2..120), n in 1usize..30) {
+            prop_assume!(c.len() > n);
+            let a = atr(&c, n).unwrap();
+            let w = &c[c.len() - n - 1..];
+            let avg_range = w[1..].iter().map(|x| x.high - x.low).sum::<f64>() / n as f64;
+            prop_assert!(a >= 0.0);
+            prop_assert!(a + tol(a) >= avg_range);
+            prop_assert!(a <= max_high(w) - min_low(w) + tol(a));
+        }
+
+        #[test]
+        fn bands_contain_the_sma(c in testkit::series(1..120), n in 1usize..30, k in 0.0..4.0f64) {
+            prop_assume!(c.len() >= n);
+            let b = bollinger(&c, n, k).unwrap();
+            let closes: Vec<f64> = c.iter().map(|x| x.close).collect();
+            prop_assert_eq!(b.mid, sma(&closes, n).unwrap());
+            prop_assert!(b.lower <= b.mid && b.mid <= b.upper);
+            prop_assert!(((b.upper - b.mid) - (b.mid - b.lower)).abs() <= tol(b.mid));
+        }
+
+        #[test]
+        fn vwap_stays_within_the_closes(c in testkit::series(2..120), win in 2usize..60) {
+            prop_assume!(c.len() >= win);
+            let w = &c[c.len() - win..];
+            match vwap(&c, win) {
+                None => prop_assert!(w.iter().all(|x| x.volume == 0.0)),
+                Some(v) => {
+                    let lo = w.iter().map(|x| x.close).fold(f64::MAX, f64::min);
+                    let hi = w.iter().map(|x| x.close).fold(f64::MIN, f64::max);
+                    prop_assert!(v.mean >= lo - tol(lo) && v.mean <= hi + tol(hi));
+                    prop_assert!(v.std_dev >= 0.0);
+                }
+            }
+        }
+
+        #[test]
+        fn donchian_encloses_the_window(c in testkit::series(1..120), n in 1usize..60) {
+            prop_assume!(c.len() >= n);
+            let ch = donchian(&c, n).unwrap();
+            let w = &c[c.len() - n..];
+            prop_assert!(ch.low <= ch.high);
+            prop_assert_eq!(ch.high, max_high(w));
+            prop_assert_eq!(ch.low, min_low(w));
+            prop_assert!(w.iter().all(|x| ch.low <= x.close && x.close <= ch.high));
+        }
+
+        #[test]
+        fn volume_profile_stays_within_its_share(c in testkit::series(0..120), pct in 0.0..1.0f64) {
+            let nodes = high_volume_nodes(&c, pct);
+            let total: f64 = c.iter().map(|x| x.volume).sum();
+            if total == 0.0 {
+                prop_assert!(nodes.is_empty());
+            } else {
+                let taken: f64 = nodes.iter().map(|n| n.volume).sum();
+                prop_assert!(taken <= pct * total + tol(total));
+                prop_assert!(nodes.windows(2).all(|p| p[0].volume >= p[1].volume));
+                prop_assert!(nodes
+                    .iter()
+                    .all(|n| min_low(&c) <= n.price && n.price <= max_high(&c)));
+            }
+        }
+    }
+}
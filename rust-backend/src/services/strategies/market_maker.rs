@@ -0,0 +1,454 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Spread-capture market maker (premium)
+//! ──────────────────────────────────────────────────────────────────────────
+//! Rests one post-only bid and one post-only ask around the mid of the live
+//! order book and re-quotes every `requote_secs`:
+//!
+//! * quotes sit `spread_bps / 2` either side of a reservation price that is
+//!   skewed away from the inventory – long inventory lowers both quotes by
+//!   up to `skew_bps`, so the ask fills first ([`quotes`])
+//! * the side that would grow inventory past `max_inventory` is not quoted
+//! * a quote that would cross joins the touch instead; the exchange
+//!   rejects a post-only order that would take anyway
+//! * only quotes off the streamed depth feed (`MarketBus::order_book`); a
+//!   book older than `max_book_age_secs` pulls the quotes instead of
+//!   re-quoting off a stale mid
+//! * inventory is what `order_tracker` recorded as filled on this task's
+//!   quotes; quotes bypass the allocation so a cancelled quote isn't booked
+//!   as a fill
+//! * with `max_funding_bps` set, a quote adding to the side that would pay
+//!   more funding than that is left out (`services::funding`)
+//! * Pro plans only – start is refused for free plans already, a lapsed
+//!   plan parks the task on its next start
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::{
+    db::cache::SharedCache,
+    services::{
+        drain, funding,
+        market_data::{MarketBus, BOOK_FEED},
+        risk,
+        scheduler::StrategyRow,
+        strategies::{
+            heartbeat::Heartbeat,
+            schema::{Field, ParamSchema},
+            warmup::{Need, Warmup},
+            OrderBookSnapshot, StrategyError,
+        },
+        trading_engine::{self, Exchange, TradeRequest},
+        usage,
+    },
+};
+
+/// Inventory this close to zero / the limit counts as there
+const SIZE_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MarketMakerParams {
+    pub symbol: String,
+    /// Quoted spread, bid to ask
+    #[serde(default = "d_spread")]
+    pub spread_bps: f64,
+    /// Size of each quote
+    #[serde(default = "dq")]
+    pub qty: f64,
+    /// Largest position either way
+    #[serde(default = "d_max_inventory")]
+    pub max_inventory: f64,
+    /// Reservation price shift at `max_inventory`
+    #[serde(default = "d_skew")]
+    pub skew_bps: f64,
+    #[serde(default = "d_requote")]
+    pub requote_secs: u64,
+    #[serde(default = "d_book_age")]
+    pub max_book_age_secs: u64,
+}
+fn d_spread() -> f64 {
+    10.0
+}
+fn dq() -> f64 {
+    0.01
+}
+fn d_max_inventory() -> f64 {
+    0.05
+}
+fn d_skew() -> f64 {
+    5.0
+}
+fn d_requote() -> u64 {
+    10
+}
+fn d_book_age() -> u64 {
+    5
+}
+
+impl MarketMakerParams {
+    pub const SCHEMA: ParamSchema = ParamSchema(&[
+        Field::string("symbol").required(),
+        Field::num("spread_bps").above(0.0),
+        Field::num("qty").above(0.0),
+        Field::num("max_inventory").above(0.0),
+        Field::num("skew_bps").at_least(0.0),
+        Field::int("requote_secs").at_least(1.0),
+        Field::int("max_book_age_secs").at_least(1.0),
+    ]);
+
+    pub fn parse(params: serde_json::Value) -> Result<Self, StrategyError> {
+        Self::SCHEMA.check(&params)?;
+        let p: Self = serde_json::from_value(params)?;
+        if p.max_inventory < p.qty {
+            return Err(StrategyError::field(
+                "max_inventory",
+                "must be at least qty",
+            ));
+        }
+        Ok(p)
+    }
+
+    /// Quotes off the live book only – no candle history
+    pub fn warmup(&self) -> Vec<Need> {
+        vec![]
+    }
+}
+
+/// One side of the market
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    pub side: &'static str,
+    pub price: f64,
+    pub size: f64,
+}
+
+/// Bid and ask for the current top of book and signed `inventory`; empty
+/// for a crossed or one-sided book
+pub fn quotes(book: &OrderBookSnapshot, inventory: f64, p: &MarketMakerParams) -> Vec<Quote> {
+    let (Some(best_bid), Some(best_ask)) = (book.best_bid, book.best_ask) else {
+        return vec![];
+    };
+    if !(best_bid > 0.0 && best_ask > best_bid) {
+        return vec![];
+    }
+    let mid = (best_bid + best_ask) / 2.0;
+    let skew = p.skew_bps * (inventory / p.max_inventory).clamp(-1.0, 1.0);
+    let reservation = mid * (1.0 - skew / 10_000.0);
+    let half = p.spread_bps / 2.0 / 10_000.0;
+
+    let mut out = Vec::with_capacity(2);
+    let bid_size = p.qty.min(p.max_inventory - inventory);
+    if bid_size > SIZE_EPSILON {
+        out.push(Quote {
+            side: "buy",
+            price: passive(reservation * (1.0 - half), best_ask, best_bid),
+            size: bid_size,
+        });
+    }
+    let ask_size = p.qty.min(p.max_inventory + inventory);
+    if ask_size > SIZE_EPSILON {
+        out.push(Quote {
+            side: "sell",
+            price: passive(reservation * (1.0 + half), best_bid, best_ask),
+            size: ask_size,
+        });
+    }
+    out
+}
+
+/// `price` unless it would take `opposite`; then join `touch`
+fn passive(price: f64, opposite: f64, touch: f64) -> f64 {
+    let crosses = if touch < opposite {
+        price >= opposite
+    } else {
+        price <= opposite
+    };
+    if crosses {
+        touch
+    } else {
+        price
+    }
+}
+
+/// Quotes resting on the exchange; pulled on drop so an aborted task
+/// doesn't leave them behind
+struct Resting {
+    db: Arc<PgPool>,
+    user_id: i64,
+    symbol: String,
+    is_demo: bool,
+    master_key: Vec<u8>,
+    client_ids: Vec<String>,
+}
+
+impl Resting {
+    async fn cancel_all(&mut self) {
+        for coid in std::mem::take(&mut self.client_ids) {
+            cancel(
+                &self.db,
+                self.user_id,
+                &self.symbol,
+                &coid,
+                self.is_demo,
+                &self.master_key,
+            )
+            .await;
+        }
+    }
+}
+
+impl Drop for Resting {
+    fn drop(&mut self) {
+        if self.client_ids.is_empty() {
+            return;
+        }
+        let mut left = Resting {
+            db: self.db.clone(),
+            user_id: self.user_id,
+            symbol: self.symbol.clone(),
+            is_demo: self.is_demo,
+            master_key: self.master_key.clone(),
+            client_ids: std::mem::take(&mut self.client_ids),
+        };
+        if let Ok(rt) = tokio::runtime::Handle::try_current() {
+            rt.spawn(async move { left.cancel_all().await });
+        }
+    }
+}
+
+/// A quote that filled in the meantime is no longer cancellable – that's
+/// fine, its fill shows up in the inventory
+async fn cancel(
+    db: &PgPool,
+    user_id: i64,
+    symbol: &str,
+    client_order_id: &str,
+    is_demo: bool,
+    master_key: &[u8],
+) {
+    if let Err(e) = trading_engine::cancel_by_client_id(
+        db,
+        user_id,
+        Exchange::Blowfin,
+        symbol,
+        client_order_id,
+        is_demo,
+        master_key,
+    )
+    .await
+    {
+        tracing::debug!("market_maker: cancel {client_order_id}: {e}");
+    }
+}
+
+/// Net filled size of this task's quotes, longs positive
+async fn inventory(db: &PgPool, order_ids: &[Uuid]) -> Result<f64, sqlx::Error> {
+    if order_ids.is_empty() {
+        return Ok(0.0);
+    }
+    sqlx::query_scalar::<_, f64>(
+        r#"
+        SELECT COALESCE(SUM(CASE WHEN side = 'buy' THEN filled_size ELSE -filled_size END), 0)::float8
+          FROM orders
+         WHERE order_id = ANY($1)
+        "#,
+    )
+    .bind(order_ids)
+    .fetch_one(db)
+    .await
+}
+
+/// Public Tokio task – returns on a closed feed, a drain, bad params or a
+/// free plan
+pub async fn loop_forever(
+    row: StrategyRow,
+    cache: SharedCache,
+    db: Arc<PgPool>,
+    bus: MarketBus,
+    master_key: Vec<u8>,
+    is_demo: bool,
+    _warm: Arc<Warmup>,
+) -> Result<(), StrategyError> {
+    let strategy_id = row.strategy_id;
+    let user_id = row.user_id;
+    let max_funding = funding::limit(&row.params);
+    let cfg = MarketMakerParams::parse(row.params)?;
+    if usage::plan_for(&db, user_id).await == usage::Plan::Free {
+        return Err(StrategyError::Config(
+            "market_maker requires a premium plan".into(),
+        ));
+    }
+
+    let max_age = Duration::from_secs(cfg.max_book_age_secs);
+    let mut book_rx = bus.order_book(&cfg.symbol);
+    let heart = Heartbeat::new(cache.clone(), strategy_id, &cfg.symbol, BOOK_FEED);
+    let mut tick = tokio::time::interval(Duration::from_secs(cfg.requote_secs));
+    let mut book: Option<(OrderBookSnapshot, Instant)> = None;
+    let mut placed: Vec<Uuid> = Vec::new();
+    let mut resting = Resting {
+        db: db.clone(),
+        user_id,
+        symbol: cfg.symbol.clone(),
+        is_demo,
+        master_key: master_key.clone(),
+        client_ids: Vec::new(),
+    };
+
+    loop {
+        tokio::select! {
+            // quotes come off the book before the process goes away
+            _ = drain::stopping() => break,
+            ob = book_rx.recv() => match ob {
+                Ok(ob) => {
+                    book = Some((ob, Instant::now()));
+                    heart.beat(Utc::now()).await;
+                }
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            },
+            _ = tick.tick() => {
+                resting.cancel_all().await;
+
+                let Some((ob, _)) = book.filter(|(_, at)| at.elapsed() <= max_age) else {
+                    continue;
+                };
+                let inv = match inventory(&db, &placed).await {
+                    Ok(inv) => inv,
+                    Err(e) => {
+                        tracing::warn!("market_maker {strategy_id}: inventory: {e}");
+                        continue;
+                    }
+                };
+                // quoting only grows inventory on one of the two sides
+                if let Err(e) = risk::check_drawdown(cache.as_ref(), user_id).await {
+                    tracing::warn!("market_maker {strategy_id}: DD limit hit – not quoting: {e}");
+                    continue;
+                }
+                for q in quotes(&ob, inv, &cfg) {
+                    let opens = if q.side == "buy" { inv >= 0.0 } else { inv <= 0.0 };
+                    if let Some(max) = max_funding.filter(|_| opens) {
+                        if let Err(e) = funding::check_entry(&cfg.symbol, q.side, max) {
+                            tracing::debug!("market_maker {strategy_id}: {} not quoted: {e}", q.side);
+                            continue;
+                        }
+                    }
+                    let req = TradeRequest {
+                        exchange: Exchange::Blowfin,
+                        symbol: cfg.symbol.clone(),
+                        side: q.side.into(),
+                        order_type: "post_only".into(),
+                        price: Some(q.price),
+                        size: q.size,
+                        reduce_only: false,
+                        signal_price: None,
+                        tp_sl: None,
+                    };
+                    match trading_engine::execute_trade(req, &db, user_id, is_demo, &master_key)
+                        .await
+                    {
+                        Ok(resp) if resp.success => {
+                            placed.extend(resp.order_id);
+                            resting.client_ids.extend(resp.client_order_id);
+                        }
+                        Ok(resp) => tracing::debug!(
+                            "market_maker {strategy_id}: {} quote rejected: {}",
+                            q.side,
+                            resp.data
+                        ),
+                        Err(e) => tracing::warn!("market_maker {strategy_id}: quote error: {e:?}"),
+                    }
+                }
+            }
+        }
+    }
+    resting.cancel_all().await;
+    Ok(())
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params() -> MarketMakerParams {
+        MarketMakerParams::parse(json!({
+            "symbol": "BTCUSDT",
+            "spread_bps": 10.0,
+            "qty": 1.0,
+            "max_inventory": 2.0,
+            "skew_bps": 4.0,
+        }))
+        .unwrap()
+    }
+
+    fn book(bid: f64, ask: f64) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            best_bid: Some(bid),
+            best_ask: Some(ask),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn quotes_skew_away_from_inventory() {
+        let p = params();
+        let ob = book(9_999.0, 10_001.0);
+
+        let flat = quotes(&ob, 0.0, &p);
+        assert_eq!(flat.len(), 2);
+        assert!((flat[0].price - 9_995.0).abs() < 1e-9);
+        assert!((flat[1].price - 10_005.0).abs() < 1e-9);
+
+        // half long: both quotes 2 bps lower
+        let long = quotes(&ob, 1.0, &p);
+        assert!((long[0].price - 9_993.0).abs() < 1e-2);
+        assert!((long[1].price - 10_003.0).abs() < 1e-2);
+        assert_eq!(long[0].size, 1.0);
+
+        // at the limit only the reducing side is quoted
+        let full = quotes(&ob, 2.0, &p);
+        assert_eq!(full.len(), 1);
+        assert_eq!(full[0].side, "sell");
+        let short = quotes(&ob, -1.5, &p);
+        assert_eq!((short[1].side, short[1].size), ("sell", 0.5));
+    }
+
+    #[test]
+    fn quotes_stay_passive() {
+        let p = MarketMakerParams {
+            spread_bps: 0.5,
+            ..params()
+        };
+        let q = quotes(&book(9_990.0, 10_010.0), 0.0, &p);
+        assert!((q[0].price - 9_999.75).abs() < 1e-6);
+
+        // a tight spread on a wide skew joins the touch instead of taking
+        let q = quotes(&book(9_999.0, 10_001.0), -2.0, &p);
+        assert_eq!(q.len(), 1);
+        assert_eq!((q[0].side, q[0].price), ("buy", 9_999.0));
+
+        assert!(quotes(&book(10_001.0, 9_999.0), 0.0, &p).is_empty());
+        assert!(quotes(&OrderBookSnapshot::default(), 0.0, &p).is_empty());
+    }
+
+    #[test]
+    fn params_are_validated() {
+        let parse = |v| MarketMakerParams::parse(v).map(drop);
+        assert!(parse(json!({ "symbol": "BTCUSDT" })).is_ok());
+        assert!(parse(json!({ "symbol": "BTCUSDT", "spread_bps": 0.0 })).is_err());
+        assert!(parse(json!({ "symbol": "BTCUSDT", "qty": 1.0, "max_inventory": 0.5 })).is_err());
+        assert!(parse(json!({ "symbol": "BTCUSDT", "requote_secs": 0 })).is_err());
+    }
+}
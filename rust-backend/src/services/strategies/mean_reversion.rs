@@ -2,11 +2,24 @@
 //! =============================================================
 
 use crate::{
-    db::redis::RedisPool,
+    db::cache::{Cache, SharedCache},
     services::{
-        market_data::MarketBus,
-        strategies::common::Candle,
-        trading_engine::{execute_trade, Exchange, TradeRequest},
+        allocation::{self, Sizing},
+        drain,
+        market_data::{bus_symbol, MarketBus},
+        portfolio::{Portfolio, Sizer},
+        position_manager::{ManagedPosition, MgmtAction, Side, TradeMgmt},
+        strategies::{
+            buffer::CandleBuffer,
+            common::Candle,
+            heartbeat::{CandleFeed, Heartbeat},
+            indicators::{Bands, RollingBollinger},
+            schema::{Field, Kind, ParamSchema},
+            warmup::{Need, Warmup},
+            StrategyError,
+        },
+        telemetry::candle_span,
+        trading_engine::{Exchange, TradeRequest},
     },
 };
 use serde::Deserialize;
@@ -14,10 +27,13 @@ use sqlx::PgPool;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use tokio::sync::broadcast;
+use tracing::Instrument;
+
+/// Bars kept for the `candles:{symbol}:4h` snapshot
+const HIST_BARS: usize = 200;
 
 type TradeExec =
-    dyn Fn(TradeRequest, &(dyn Db), i64, bool, &[u8]) -> Result<(), String> + Send + Sync;
+    dyn Fn(TradeRequest, &dyn Db, i64, bool, &[u8]) -> Result<(), String> + Send + Sync;
 
 /// -------------------------------------------------------------------------
 /// Small async traits so we can inject mocks in unit tests
@@ -43,16 +59,16 @@ pub trait RiskChecker: Send + Sync {
 /// Trait impls for the real types so existing prod code is untouched
 /// -------------------------------------------------------------------------
 #[async_trait]
-impl Redis for RedisPool {
+impl Redis for SharedCache {
     async fn set_json(&self, k: &str, v: &[Candle], e: usize) -> Result<(), ()> {
-        self.set_json(k, v, e).await.map_err(|_| ())
+        (**self).set_json(k, v, e as u64).await.map_err(|_| ())
     }
 }
 #[async_trait]
 impl Db for PgPool {}
 
-/// Broadcast receiver wrapper so it satisfies our trait
-pub struct CandleRx(pub broadcast::Receiver<Candle>);
+/// Heartbeating bus feed wrapper so it satisfies our trait
+pub struct CandleRx(pub CandleFeed);
 #[async_trait]
 impl MarketBusSub for CandleRx {
     async fn recv(&mut self) -> Result<Candle, ()> {
@@ -62,11 +78,11 @@ impl MarketBusSub for CandleRx {
 
 /// Real risk checker (sync wrapper around async call)
 pub struct RealRisk<'a> {
-    pub redis: &'a RedisPool,
+    pub cache: &'a dyn Cache,
 }
 impl RiskChecker for RealRisk<'_> {
     fn check_drawdown(&self, user_id: i64) -> Result<(), String> {
-        futures::executor::block_on(crate::services::risk::check_drawdown(self.redis, user_id))
+        futures::executor::block_on(crate::services::risk::check_drawdown(self.cache, user_id))
             .map_err(|e| e.to_string())
     }
 }
@@ -83,6 +99,15 @@ pub struct MeanRevParams {
     pub sigma: f64,
     #[serde(default = "d_qty")]
     pub qty: f64,
+    /// Size trades to lose this share of equity over one band half-width
+    /// instead of trading a fixed `qty`
+    #[serde(default)]
+    pub risk_pct: Option<f64>,
+    /// Break-even / partial TP / trailing / max-hold rules for the open
+    /// fade, with the stop one band half-width from entry and
+    /// `max_hold_bars` counted in 4h bars; off by default
+    #[serde(default)]
+    pub mgmt: TradeMgmt,
 }
 fn d_period() -> usize {
     20
@@ -94,32 +119,43 @@ fn d_qty() -> f64 {
     0.01
 }
 
+impl MeanRevParams {
+    pub const SCHEMA: ParamSchema = ParamSchema(&[
+        Field::string("symbol").required(),
+        Field::int("period").at_least(1.0),
+        Field::num("sigma").above(0.0),
+        Field::num("qty").above(0.0),
+        Field::num("risk_pct").above(0.0).at_most(1.0).nullable(),
+        Field::new("mgmt", Kind::Object),
+    ]);
+
+    pub fn parse(params: serde_json::Value) -> Result<Self, StrategyError> {
+        Self::SCHEMA.check(&params)?;
+        Ok(serde_json::from_value(params)?)
+    }
+
+    /// Candles needed before the first trade
+    pub fn warmup(&self) -> Vec<Need> {
+        vec![Need::new("4h", self.period)]
+    }
+}
+
 /// -------------------------------------------------------------------------
 /// Maths helpers & signal
 /// -------------------------------------------------------------------------
-fn bollinger(c: &[Candle], n: usize, k: f64) -> Option<(f64, f64)> {
-    if c.len() < n {
-        return None;
-    }
-    let slice = &c[c.len() - n..];
-    let sma = slice.iter().map(|x| x.close).sum::<f64>() / n as f64;
-    let sd = (slice.iter().map(|x| (x.close - sma).powi(2)).sum::<f64>() / n as f64).sqrt();
-    Some((sma - k * sd, sma + k * sd))
-}
-
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum Sig {
     Buy,
     Sell,
     Hold,
 }
-fn decide(candles: &[Candle], cfg: &MeanRevParams) -> Sig {
-    match bollinger(candles, cfg.period, cfg.sigma) {
-        Some((low, high)) => {
-            let p = candles.last().unwrap().close;
-            if p < low {
+/// Outside the bands → fade the move
+fn decide(bands: Option<Bands>, p: f64) -> Sig {
+    match bands {
+        Some(b) => {
+            if p < b.lower {
                 Sig::Buy
-            } else if p > high {
+            } else if p > b.upper {
                 Sig::Sell
             } else {
                 Sig::Hold
@@ -130,36 +166,60 @@ fn decide(candles: &[Candle], cfg: &MeanRevParams) -> Sig {
 }
 
 /// -------------------------------------------------------------------------
-/// Original public API – **signature unchanged**
+/// Public Tokio task – returns only on a closed feed or bad params
 /// -------------------------------------------------------------------------
 pub async fn loop_forever(
     row: crate::services::scheduler::StrategyRow,
-    redis: RedisPool,
+    cache: SharedCache,
     db: Arc<PgPool>, // <-- change here
     bus: MarketBus,
     master_key: Vec<u8>,
     is_demo: bool,
-) {
-    let rx = CandleRx(bus.candles_4h.subscribe());
-    let risk = RealRisk { redis: &redis };
+    warm: Arc<Warmup>,
+) -> Result<(), StrategyError> {
+    let cfg = MeanRevParams::parse(row.params.clone())?;
+    let strategy_id = row.strategy_id;
+    let rx = CandleRx(CandleFeed::new(
+        bus.candles(&cfg.symbol, "4h"),
+        Heartbeat::new(cache.clone(), strategy_id, &cfg.symbol, "4h"),
+    ));
+    let risk = RealRisk { cache: &*cache };
 
     let db_for_closure = db.clone();
+    let portfolio =
+        Portfolio::new((*db).clone(), is_demo, master_key.clone()).for_strategy(strategy_id);
+    // risk-sized trades already size off the allocation's equity
+    let sizing = if cfg.risk_pct.is_some() {
+        Sizing::AsIs
+    } else {
+        Sizing::ScaleQty
+    };
 
     loop_forever_core(
         row,
-        &redis,
+        &cache,
         &*db, // Pass reference to Arc target for trait param
         Box::new(rx),
         &master_key,
         is_demo,
         &risk,
+        &portfolio,
         &move |req, _db, uid, demo, key| {
-            futures::executor::block_on(execute_trade(req, &db_for_closure, uid, demo, key))
-                .map(|_| ())
-                .map_err(|e| e.to_string())
+            futures::executor::block_on(allocation::execute(
+                &db_for_closure,
+                strategy_id,
+                req,
+                uid,
+                demo,
+                key,
+                sizing,
+            ))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
         },
+        &warm,
     )
-    .await;
+    .await
 }
 
 /// -------------------------------------------------------------------------
@@ -168,75 +228,215 @@ pub async fn loop_forever(
 #[allow(clippy::too_many_arguments)]
 pub async fn loop_forever_core(
     row: crate::services::scheduler::StrategyRow,
-    redis: &(dyn Redis),
-    db: &(dyn Db),
+    redis: &dyn Redis,
+    db: &dyn Db,
     mut rx: Box<dyn MarketBusSub>,
     master_key: &[u8],
     is_demo: bool,
     risk: &dyn RiskChecker,
+    sizer: &dyn Sizer,
     trade_exec: &TradeExec,
-) {
-    let cfg: MeanRevParams = serde_json::from_value(row.params).expect("bad mean-reversion params");
-
-    let mut hist: Vec<Candle> = Vec::with_capacity(200);
+    warm: &Warmup,
+) -> Result<(), StrategyError> {
+    let cfg = MeanRevParams::parse(row.params)?;
+
+    let mut hist = CandleBuffer::new(cfg.period.max(HIST_BARS));
+    let mut bands = RollingBollinger::new(cfg.period, cfg.sigma);
+    for c in warm.take("4h") {
+        bands.push(&c);
+        hist.push(c).await;
+    }
     let user_id = row.user_id;
-
-    while let Ok(c) = rx.recv().await {
-        if cfg.symbol.to_uppercase() != "BTCUSDT" {
-            continue;
-        }
-        hist.push(c);
+    let snapshot_key = format!("candles:{}:4h", bus_symbol(&cfg.symbol));
+    // the side of the fade we're in, if any
+    let mut held: Option<&str> = None;
+    // the fade under `cfg.mgmt`, tracked only when a rule is set
+    let mut open: Option<ManagedPosition> = None;
+
+    // a drain stops the loop between bars
+    while let Some(Ok(c)) = drain::or_stop(rx.recv()).await {
+        hist.push(c).await;
+        bands.push(&c);
+        warm.bar("4h");
         if hist.len() < cfg.period {
             continue;
         }
 
-        match decide(&hist, &cfg) {
-            Sig::Hold => {}
-            Sig::Buy => {
-                trade_core(
-                    "buy", &cfg, redis, db, user_id, is_demo, master_key, risk, trade_exec,
-                )
-                .await
+        // --- manage the open fade ------------
+        let mut managed_out = false;
+        if let Some(pos) = open.as_mut() {
+            for action in pos.on_candle(&c, &cfg.mgmt) {
+                match action {
+                    MgmtAction::MoveStop { from, to } => {
+                        tracing::info!("mean-reversion {user_id}: stop {from:.2} → {to:.2}")
+                    }
+                    MgmtAction::PartialClose { size, .. } | MgmtAction::Close { size, .. } => {
+                        close_core(pos, size, db, user_id, is_demo, master_key, trade_exec)
+                            .instrument(candle_span("mean_reversion", &cfg.symbol, &c))
+                            .await;
+                    }
+                }
+            }
+            if pos.is_closed() {
+                (open, held, managed_out) = (None, None, true);
             }
-            Sig::Sell => {
-                trade_core(
-                    "sell", &cfg, redis, db, user_id, is_demo, master_key, risk, trade_exec,
+        }
+
+        let b = bands.value();
+        // the band half-width is how far a fade is expected to run back
+        let stop = b.map(|b| (b.upper - b.lower) / 2.0).unwrap_or_default();
+        let side = match decide(b, c.close) {
+            Sig::Hold => None,
+            Sig::Buy => Some("buy"),
+            Sig::Sell => Some("sell"),
+        };
+        // no re-entry on the bar the manager closed the fade
+        if let Some(side) = side.filter(|_| !managed_out) {
+            let exit = is_exit(side, held);
+            let sent = match open.as_ref().filter(|_| exit) {
+                // close what the manager has left open
+                Some(pos) => close_core(
+                    pos,
+                    pos.remaining,
+                    db,
+                    user_id,
+                    is_demo,
+                    master_key,
+                    trade_exec,
                 )
+                .instrument(candle_span("mean_reversion", &cfg.symbol, &c))
                 .await
+                .then_some(pos.remaining),
+                None => {
+                    trade_core(
+                        side, exit, &cfg, redis, db, user_id, is_demo, master_key, risk, sizer,
+                        stop, trade_exec,
+                    )
+                    .instrument(candle_span("mean_reversion", &cfg.symbol, &c))
+                    .await
+                }
+            };
+            if let Some(size) = sent {
+                held = (!exit).then_some(side);
+                open = match open.take() {
+                    _ if exit || !cfg.mgmt.is_set() => None,
+                    // an add rides the first entry's stop
+                    Some(mut pos) => {
+                        pos.size += size;
+                        pos.remaining += size;
+                        Some(pos)
+                    }
+                    None => {
+                        let (side, stop) = match side {
+                            "buy" => (Side::Long, c.close - stop),
+                            _ => (Side::Short, c.close + stop),
+                        };
+                        Some(ManagedPosition::open(
+                            &cfg.symbol,
+                            side,
+                            c.close,
+                            stop,
+                            size,
+                            c.ts,
+                        ))
+                    }
+                };
             }
         }
 
-        let _ = redis.set_json("candles:BTCUSDT:4h", &hist, 48 * 3600).await;
+        let _ = redis
+            .set_json(&snapshot_key, hist.as_slice(), 48 * 3600)
+            .await;
     }
+    Ok(())
 }
 
+/// A signal against the fade we hold closes it rather than opening the
+/// other way, so it goes out reduce-only (and passes an exit-only window)
+fn is_exit(side: &str, held: Option<&str>) -> bool {
+    held.is_some_and(|h| h != side)
+}
+
+/// Sends one order; the size the engine took, `None` when it was not sent
 #[allow(clippy::too_many_arguments)]
 pub async fn trade_core(
     side: &str,
+    reduce_only: bool,
     cfg: &MeanRevParams,
-    _redis: &(dyn Redis),
-    db: &(dyn Db),
+    _redis: &dyn Redis,
+    db: &dyn Db,
     user_id: i64,
     is_demo: bool,
     master_key: &[u8],
     risk: &dyn RiskChecker,
+    sizer: &dyn Sizer,
+    stop_distance: f64,
     trade_exec: &TradeExec,
-) {
-    if let Err(e) = risk.check_drawdown(user_id) {
-        log::warn!("DD limit hit – aborting order: {e}");
-        return;
+) -> Option<f64> {
+    // a tripped guard still lets the open fade close
+    if !reduce_only {
+        if let Err(e) = risk.check_drawdown(user_id) {
+            tracing::warn!("DD limit hit – aborting order: {e}");
+            return None;
+        }
     }
 
+    // the configured qty when no equity is known
+    let size = match cfg.risk_pct {
+        Some(pct) => sizer
+            .position_size(user_id, pct, stop_distance)
+            .await
+            .unwrap_or(cfg.qty),
+        None => cfg.qty,
+    };
     let req = TradeRequest {
         exchange: Exchange::Blowfin,
         symbol: cfg.symbol.clone(),
         side: side.into(),
         order_type: "market".into(),
         price: None,
-        size: cfg.qty,
+        size,
+        reduce_only,
+        signal_price: None,
+        tp_sl: None,
+    };
+    match trade_exec(req, db, user_id, is_demo, master_key) {
+        Ok(()) => Some(size),
+        Err(e) => {
+            tracing::error!("mean-reversion {side} err: {e:?}");
+            None
+        }
+    }
+}
+
+/// Takes `size` off the managed fade reduce-only; `true` when the engine
+/// took it
+pub async fn close_core(
+    pos: &ManagedPosition,
+    size: f64,
+    db: &dyn Db,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+    trade_exec: &TradeExec,
+) -> bool {
+    let req = TradeRequest {
+        exchange: Exchange::Blowfin,
+        symbol: pos.symbol.clone(),
+        side: pos.side.exit_side().into(),
+        order_type: "market".into(),
+        price: None,
+        size,
+        reduce_only: true,
+        signal_price: None,
+        tp_sl: None,
     };
-    if let Err(e) = trade_exec(req, db, user_id, is_demo, master_key) {
-        log::error!("mean-reversion {side} err: {e:?}");
+    match trade_exec(req, db, user_id, is_demo, master_key) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::error!("mean-reversion exit err: {e:?}");
+            false
+        }
     }
 }
 
@@ -247,8 +447,20 @@ pub async fn trade_core(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::{portfolio::FixedQty, strategies::indicators};
     use std::sync::{Arc, Mutex};
 
+    fn bollinger(c: &[Candle], n: usize, k: f64) -> Option<(f64, f64)> {
+        indicators::bollinger(c, n, k).map(|b| (b.lower, b.upper))
+    }
+
+    fn decide_on(c: &[Candle], cfg: &MeanRevParams) -> Sig {
+        decide(
+            indicators::bollinger(c, cfg.period, cfg.sigma),
+            c.last().unwrap().close,
+        )
+    }
+
     fn seq(prices: &[f64]) -> Vec<Candle> {
         prices
             .iter()
@@ -283,16 +495,18 @@ mod tests {
             period: 20,
             sigma: 2.0,
             qty: 0.1,
+            risk_pct: None,
+            mgmt: TradeMgmt::default(),
         };
-        assert_eq!(decide(&seq(&v), &cfg), Sig::Buy);
+        assert_eq!(decide_on(&seq(&v), &cfg), Sig::Buy);
 
         let mut v = vec![10.0; 19];
         v.push(20.0);
-        assert_eq!(decide(&seq(&v), &cfg), Sig::Sell);
+        assert_eq!(decide_on(&seq(&v), &cfg), Sig::Sell);
 
         let mut v = vec![10.0; 19];
         v.push(10.0);
-        assert_eq!(decide(&seq(&v), &cfg), Sig::Hold);
+        assert_eq!(decide_on(&seq(&v), &cfg), Sig::Hold);
     }
 
     // ----------------------------------- mocks ---------------------------
@@ -345,8 +559,7 @@ mod tests {
     }
     fn exec_mock(
         fail: bool,
-    ) -> impl Fn(TradeRequest, &(dyn Db), i64, bool, &[u8]) -> Result<(), String> + Send + Sync
-    {
+    ) -> impl Fn(TradeRequest, &dyn Db, i64, bool, &[u8]) -> Result<(), String> + Send + Sync {
         move |_, _, _, _, _| if fail { Err("boom".into()) } else { Ok(()) }
     }
 
@@ -355,11 +568,14 @@ mod tests {
     async fn trade_dd_abort() {
         trade_core(
             "buy",
+            false,
             &MeanRevParams {
                 symbol: "BTCUSDT".into(),
                 period: 20,
                 sigma: 2.0,
                 qty: 0.01,
+                risk_pct: None,
+                mgmt: TradeMgmt::default(),
             },
             &RMock::default(),
             &DMock,
@@ -367,6 +583,8 @@ mod tests {
             false,
             &[],
             &RiskMock { fail: true },
+            &FixedQty,
+            0.0,
             &exec_mock(false),
         )
         .await;
@@ -375,11 +593,14 @@ mod tests {
     async fn trade_exec_err() {
         trade_core(
             "sell",
+            false,
             &MeanRevParams {
                 symbol: "BTCUSDT".into(),
                 period: 20,
                 sigma: 2.0,
                 qty: 0.01,
+                risk_pct: None,
+                mgmt: TradeMgmt::default(),
             },
             &RMock::default(),
             &DMock,
@@ -387,6 +608,8 @@ mod tests {
             false,
             &[],
             &RiskMock { fail: false },
+            &FixedQty,
+            0.0,
             &exec_mock(true),
         )
         .await;
@@ -395,11 +618,14 @@ mod tests {
     async fn trade_happy() {
         trade_core(
             "sell",
+            false,
             &MeanRevParams {
                 symbol: "BTCUSDT".into(),
                 period: 20,
                 sigma: 2.0,
                 qty: 0.01,
+                risk_pct: None,
+                mgmt: TradeMgmt::default(),
             },
             &RMock::default(),
             &DMock,
@@ -407,6 +633,8 @@ mod tests {
             false,
             &[],
             &RiskMock { fail: false },
+            &FixedQty,
+            0.0,
             &exec_mock(false),
         )
         .await;
@@ -447,8 +675,220 @@ mod tests {
             &[],
             false,
             &RiskMock { fail: false },
+            &FixedQty,
             &exec_mock(false),
+            &Warmup::default(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_fade_back_goes_out_reduce_only() {
+        let flat = Candle {
+            close: 10.0,
+            ..Default::default()
+        };
+        let mut c = vec![flat; 19];
+        for close in [5.0, 4.0, 20.0, 30.0] {
+            c.push(Candle {
+                close,
+                ..Default::default()
+            });
+        }
+        let row = crate::services::scheduler::StrategyRow {
+            user_id: 42,
+            params: serde_json::json!({ "symbol": "BTCUSDT", "period": 20, "sigma": 2.0 }),
+            ..Default::default()
+        };
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let log = sent.clone();
+        loop_forever_core(
+            row,
+            &RMock::default(),
+            &DMock,
+            Box::new(RxMock { candles: c, idx: 0 }),
+            &[],
+            false,
+            &RiskMock { fail: false },
+            &FixedQty,
+            &move |req: TradeRequest, _: &dyn Db, _, _, _: &[u8]| {
+                log.lock().unwrap().push((req.side, req.reduce_only));
+                Ok(())
+            },
+            &Warmup::default(),
+        )
+        .await
+        .unwrap();
+
+        // buy, add, close the long, then open the short
+        let sent = sent.lock().unwrap().clone();
+        let want = [
+            ("buy", false),
+            ("buy", false),
+            ("sell", true),
+            ("sell", false),
+        ];
+        assert_eq!(sent, want.map(|(side, ro)| (side.to_string(), ro)));
+    }
+
+    fn bar(close: f64, high: f64, low: f64) -> Candle {
+        Candle {
+            close,
+            high,
+            low,
+            ..Default::default()
+        }
+    }
+
+    /// Runs the loop over `candles` and returns `(side, reduce_only, size)`
+    /// of every order sent
+    async fn run_managed(
+        candles: Vec<Candle>,
+        mgmt: serde_json::Value,
+    ) -> Vec<(String, bool, f64)> {
+        let row = crate::services::scheduler::StrategyRow {
+            user_id: 42,
+            params: serde_json::json!({ "symbol": "BTCUSDT", "period": 20, "mgmt": mgmt }),
+            ..Default::default()
+        };
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let log = sent.clone();
+        loop_forever_core(
+            row,
+            &RMock::default(),
+            &DMock,
+            Box::new(RxMock { candles, idx: 0 }),
+            &[],
+            false,
+            &RiskMock { fail: false },
+            &FixedQty,
+            &move |req: TradeRequest, _: &dyn Db, _, _, _: &[u8]| {
+                log.lock()
+                    .unwrap()
+                    .push((req.side, req.reduce_only, req.size));
+                Ok(())
+            },
+            &Warmup::default(),
+        )
+        .await
+        .unwrap();
+        let sent = sent.lock().unwrap().clone();
+        sent
+    }
+
+    #[tokio::test]
+    async fn a_managed_fade_scales_out_then_stops_at_break_even() {
+        let mut c = vec![bar(10.0, 10.0, 10.0); 19];
+        // long at 5 with a ≈1.96 band half-width as 1 R
+        c.push(bar(5.0, 5.0, 5.0));
+        // +1.5 R: half off and the stop to entry, still inside the bands
+        c.push(bar(7.5, 8.0, 7.0));
+        // back through entry: the rest goes, and no new fade on this bar
+        c.push(bar(4.95, 5.0, 4.9));
+
+        let sent = run_managed(
+            c,
+            serde_json::json!({
+                "break_even_at_r": 1.0,
+                "partial_tps": [{ "at_r": 1.0, "fraction": 0.5 }]
+            }),
         )
         .await;
+        let want = [
+            ("buy", false, 0.01),
+            ("sell", true, 0.005),
+            ("sell", true, 0.005),
+        ];
+        assert_eq!(
+            sent,
+            want.map(|(side, ro, size)| (side.to_string(), ro, size))
+        );
+    }
+
+    #[tokio::test]
+    async fn a_stale_fade_is_flattened_after_max_hold_bars() {
+        let mut c = vec![bar(10.0, 10.0, 10.0); 19];
+        c.push(bar(5.0, 5.0, 5.0));
+        // back inside the bands but never far enough for a signal
+        c.extend([bar(9.0, 9.0, 9.0); 3]);
+
+        let sent = run_managed(c, serde_json::json!({ "max_hold_bars": 2 })).await;
+        let want = [("buy", false, 0.01), ("sell", true, 0.01)];
+        assert_eq!(
+            sent,
+            want.map(|(side, ro, size)| (side.to_string(), ro, size))
+        );
+    }
+
+    #[tokio::test]
+    async fn a_tripped_guard_still_lets_the_fade_close() {
+        let cfg = MeanRevParams {
+            symbol: "BTCUSDT".into(),
+            period: 20,
+            sigma: 2.0,
+            qty: 0.01,
+            risk_pct: None,
+            mgmt: TradeMgmt::default(),
+        };
+        let (redis, dd, exec) = (RMock::default(), RiskMock { fail: true }, exec_mock(false));
+        let run = |reduce_only| {
+            trade_core(
+                "sell",
+                reduce_only,
+                &cfg,
+                &redis,
+                &DMock,
+                1,
+                false,
+                &[],
+                &dd,
+                &FixedQty,
+                0.0,
+                &exec,
+            )
+        };
+        assert!(run(false).await.is_none());
+        assert!(run(true).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn bad_params_are_reported_not_panicked() {
+        let run = |params| {
+            let row = crate::services::scheduler::StrategyRow {
+                user_id: 42,
+                params,
+                ..Default::default()
+            };
+            async move {
+                loop_forever_core(
+                    row,
+                    &RMock::default(),
+                    &DMock,
+                    Box::new(RxMock {
+                        candles: vec![],
+                        idx: 0,
+                    }),
+                    &[],
+                    false,
+                    &RiskMock { fail: false },
+                    &FixedQty,
+                    &exec_mock(false),
+                    &Warmup::default(),
+                )
+                .await
+            }
+        };
+        assert!(matches!(
+            run(serde_json::json!({ "period": 20 })).await,
+            Err(StrategyError::Fields(_))
+        ));
+        assert!(matches!(
+            run(serde_json::json!({ "symbol": "BTCUSDT", "period": 0 })).await,
+            Err(StrategyError::Fields(_))
+        ));
+        assert!(run(serde_json::json!({ "symbol": "BTCUSDT" }))
+            .await
+            .is_ok());
     }
 }
@@ -0,0 +1,455 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! WASM strategy plugins (`--features wasm`)
+//! ──────────────────────────────────────────────────────────────────────────
+//! Advanced users upload a compiled module (`/api/plugins`) and run it as
+//! strategy `plugin` with `{ "symbol", "plugin_id", "qty", "history" }`.
+//!
+//! Guest ABI v1 – every export is required:
+//!
+//! ```text
+//! memory
+//! rr_abi_version() -> i32                  must return 1
+//! alloc(len: i32) -> i32                   buffer for the host to fill
+//!                                          (may hand out the same one per call)
+//! on_candles(ptr: i32, n: i32, position: i32) -> i32
+//!     ptr → n bars, oldest first, each 6 × f64 little-endian:
+//!           ts (unix s), open, high, low, close, volume
+//!     position: 1 long, -1 short, 0 flat
+//!     returns 0 nothing, 1 buy, 2 sell, 3 exit
+//! ```
+//!
+//! * The host provides no imports – a module importing anything is rejected,
+//!   so a plugin can't touch the network, files or clock
+//! * Every call (and instantiation) gets `FUEL`; memory is capped at
+//!   `MAX_MEMORY`. Traps, running out of fuel and unknown return codes are
+//!   failures, handled like a failing script (`strategies::custom`)
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use wasmtime::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::{
+    db::cache::SharedCache,
+    services::{
+        market_data::MarketBus,
+        strategies::{
+            common::Candle,
+            custom::{self, Runner, Signal},
+            schema::{Field, Kind, ParamSchema},
+            warmup::{Need, Warmup},
+            StrategyError,
+        },
+    },
+};
+
+pub const ABI_VERSION: i32 = 1;
+/// Largest accepted upload
+pub const MAX_WASM: usize = 4 * 1024 * 1024;
+const MAX_MEMORY: usize = 32 * 1024 * 1024;
+/// Roughly one unit per wasm instruction
+const FUEL: u64 = 10_000_000;
+const MAX_HISTORY: usize = 1_000;
+const BAR_BYTES: usize = 6 * 8;
+/// Wait before re-reading the module after a DB error
+const LOAD_RETRY: std::time::Duration = std::time::Duration::from_secs(30);
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).expect("wasm engine")
+});
+
+#[derive(Clone, Deserialize)]
+pub struct PluginParams {
+    pub symbol: String,
+    pub plugin_id: Uuid,
+    #[serde(default = "dq")]
+    pub qty: f64,
+    #[serde(default = "dh")]
+    pub history: usize,
+}
+fn dq() -> f64 {
+    0.01
+}
+fn dh() -> usize {
+    200
+}
+
+impl PluginParams {
+    pub const SCHEMA: ParamSchema = ParamSchema(&[
+        Field::string("symbol").required(),
+        Field::new("plugin_id", Kind::Uuid).required(),
+        Field::num("qty").above(0.0),
+        Field::int("history")
+            .at_least(1.0)
+            .at_most(MAX_HISTORY as f64),
+    ]);
+
+    pub fn parse(params: Value) -> Result<Self, StrategyError> {
+        Self::SCHEMA.check(&params)?;
+        Ok(serde_json::from_value(params)?)
+    }
+
+    /// Candles needed before the first trade
+    pub fn warmup(&self) -> Vec<Need> {
+        vec![Need::new("1h", self.history)]
+    }
+}
+
+struct Limits(StoreLimits);
+
+/// An instantiated plugin
+pub struct Plugin {
+    store: Store<Limits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    on_candles: TypedFunc<(i32, i32, i32), i32>,
+}
+
+impl Plugin {
+    /// Compile, instantiate and check the ABI
+    pub fn load(wasm: &[u8]) -> Result<Self, String> {
+        let module = Module::new(&ENGINE, wasm).map_err(|e| format!("invalid module: {e}"))?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .instances(1)
+            .memories(1)
+            .tables(4)
+            .build();
+        let mut store = Store::new(&ENGINE, Limits(limits));
+        store.limiter(|l| &mut l.0);
+        store.set_fuel(FUEL).map_err(|e| e.to_string())?;
+
+        let instance = Linker::new(&ENGINE)
+            .instantiate(&mut store, &module)
+            .map_err(|e| format!("instantiate (plugins get no imports): {e}"))?;
+        let export = |what: &str, e: wasmtime::Error| format!("export `{what}`: {e}");
+        let version = instance
+            .get_typed_func::<(), i32>(&mut store, "rr_abi_version")
+            .map_err(|e| export("rr_abi_version", e))?
+            .call(&mut store, ())
+            .map_err(|e| export("rr_abi_version", e))?;
+        if version != ABI_VERSION {
+            return Err(format!(
+                "ABI version {version} (this host speaks {ABI_VERSION})"
+            ));
+        }
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or("missing export `memory`")?;
+        let alloc = instance
+            .get_typed_func(&mut store, "alloc")
+            .map_err(|e| export("alloc", e))?;
+        let on_candles = instance
+            .get_typed_func(&mut store, "on_candles")
+            .map_err(|e| export("on_candles", e))?;
+        Ok(Self {
+            store,
+            memory,
+            alloc,
+            on_candles,
+        })
+    }
+
+    pub fn on_candles(&mut self, bars: &[Candle], position: i8) -> Result<Option<Signal>, String> {
+        let mut buf = Vec::with_capacity(bars.len() * BAR_BYTES);
+        for c in bars {
+            for x in [
+                c.ts.timestamp() as f64,
+                c.open,
+                c.high,
+                c.low,
+                c.close,
+                c.volume,
+            ] {
+                buf.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        let len = i32::try_from(buf.len()).map_err(|_| "too many bars")?;
+        let n = i32::try_from(bars.len()).map_err(|_| "too many bars")?;
+
+        self.store.set_fuel(FUEL).map_err(|e| e.to_string())?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|e| format!("alloc: {e}"))?;
+        let offset = usize::try_from(ptr).map_err(|_| "alloc returned a negative pointer")?;
+        self.memory
+            .write(&mut self.store, offset, &buf)
+            .map_err(|e| format!("alloc returned an unusable buffer: {e}"))?;
+        let code = self
+            .on_candles
+            .call(&mut self.store, (ptr, n, i32::from(position)))
+            .map_err(|e| format!("on_candles: {e}"))?;
+        match code {
+            0 => Ok(None),
+            1 => Ok(Some(Signal::Buy)),
+            2 => Ok(Some(Signal::Sell)),
+            3 => Ok(Some(Signal::Exit)),
+            other => Err(format!("on_candles returned unknown code {other}")),
+        }
+    }
+}
+
+// ─── Storage ──────────────────────────────────────────────────────────────
+#[derive(thiserror::Error, Debug)]
+pub enum PluginError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("a plugin named `{0}` already exists")]
+    Exists(String),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PluginInfo {
+    pub plugin_id: Uuid,
+    pub name: String,
+    pub sha256: String,
+    pub size_bytes: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+fn valid_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"_.-".contains(&b))
+}
+
+/// Validate (compile + ABI) and store a module
+pub async fn upload(
+    db: &PgPool,
+    user_id: i64,
+    name: &str,
+    wasm: Vec<u8>,
+) -> Result<PluginInfo, PluginError> {
+    if !valid_name(name) {
+        return Err(PluginError::Invalid(
+            "plugin names are 1-64 chars of A-Z, a-z, 0-9, `_`, `.` or `-`".into(),
+        ));
+    }
+    if wasm.len() > MAX_WASM {
+        return Err(PluginError::Invalid(format!(
+            "module is larger than {MAX_WASM} bytes"
+        )));
+    }
+    // compiling is CPU work – keep it off the reactor
+    let wasm = tokio::task::spawn_blocking(move || Plugin::load(&wasm).map(|_| wasm))
+        .await
+        .map_err(|e| PluginError::Invalid(e.to_string()))?
+        .map_err(PluginError::Invalid)?;
+    let sha256 = hex::encode(Sha256::digest(&wasm));
+
+    let row = sqlx::query_as::<_, PluginInfo>(
+        r#"
+        INSERT INTO strategy_plugins (user_id, name, sha256, size_bytes, wasm)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, name) DO NOTHING
+        RETURNING plugin_id, name, sha256, size_bytes, created_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(&sha256)
+    .bind(wasm.len() as i32)
+    .bind(&wasm)
+    .fetch_optional(db)
+    .await?;
+    row.ok_or_else(|| PluginError::Exists(name.to_string()))
+}
+
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<PluginInfo>, sqlx::Error> {
+    sqlx::query_as::<_, PluginInfo>(
+        r#"
+        SELECT plugin_id, name, sha256, size_bytes, created_at
+          FROM strategy_plugins
+         WHERE user_id = $1
+         ORDER BY name
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+/// `false` if the user has no such plugin
+pub async fn delete(db: &PgPool, user_id: i64, plugin_id: Uuid) -> Result<bool, sqlx::Error> {
+    Ok(
+        sqlx::query("DELETE FROM strategy_plugins WHERE plugin_id = $1 AND user_id = $2")
+            .bind(plugin_id)
+            .bind(user_id)
+            .execute(db)
+            .await?
+            .rows_affected()
+            > 0,
+    )
+}
+
+/// ------------------------------------------------------------
+/// Public Tokio task – returns on a closed feed, bad params, a missing
+/// module or a plugin that keeps failing
+/// ------------------------------------------------------------
+pub async fn loop_forever(
+    row: crate::services::scheduler::StrategyRow,
+    cache: SharedCache,
+    db: Arc<PgPool>,
+    bus: MarketBus,
+    master_key: Vec<u8>,
+    is_demo: bool,
+    warm: Arc<Warmup>,
+) -> Result<(), StrategyError> {
+    let cfg = PluginParams::parse(row.params)?;
+    let wasm = loop {
+        let found = sqlx::query_scalar::<_, Vec<u8>>(
+            "SELECT wasm FROM strategy_plugins WHERE plugin_id = $1 AND user_id = $2",
+        )
+        .bind(cfg.plugin_id)
+        .bind(row.user_id)
+        .fetch_optional(db.as_ref())
+        .await;
+        match found {
+            Ok(Some(w)) => break w,
+            Ok(None) => {
+                return Err(StrategyError::Config(format!(
+                    "plugin {} not found",
+                    cfg.plugin_id
+                )))
+            }
+            Err(e) => {
+                tracing::error!("plugin {}: DB error: {e}", row.strategy_id);
+                tokio::time::sleep(LOAD_RETRY).await;
+            }
+        }
+    };
+    let mut plugin =
+        Plugin::load(&wasm).map_err(|e| StrategyError::Config(format!("plugin: {e}")))?;
+
+    let run = Runner {
+        kind: "plugin",
+        strategy_id: row.strategy_id,
+        user_id: row.user_id,
+        symbol: cfg.symbol,
+        qty: cfg.qty,
+        history: cfg.history,
+        warm,
+    };
+    custom::drive(
+        run,
+        cache,
+        db,
+        bus,
+        master_key,
+        is_demo,
+        |bars, position| plugin.on_candles(bars, position),
+    )
+    .await
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Buys above 100 when flat, exits below 100 when long
+    const THRESHOLD: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "rr_abi_version") (result i32) i32.const 1)
+          (func (export "alloc") (param i32) (result i32) i32.const 1024)
+          (func (export "on_candles") (param $ptr i32) (param $n i32) (param $pos i32) (result i32)
+            (local $close f64)
+            (local.set $close
+              (f64.load (i32.add (local.get $ptr)
+                (i32.add (i32.mul (i32.sub (local.get $n) (i32.const 1)) (i32.const 48))
+                         (i32.const 32)))))
+            (if (i32.and (i32.eqz (local.get $pos)) (f64.gt (local.get $close) (f64.const 100)))
+              (then (return (i32.const 1))))
+            (if (i32.and (i32.eq (local.get $pos) (i32.const 1)) (f64.lt (local.get $close) (f64.const 100)))
+              (then (return (i32.const 3))))
+            (i32.const 0)))
+    "#;
+
+    fn bars(closes: &[f64]) -> Vec<Candle> {
+        closes
+            .iter()
+            .map(|&close| Candle {
+                close,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn guest_sees_the_bars_and_position() {
+        let mut p = Plugin::load(THRESHOLD.as_bytes()).unwrap();
+        assert_eq!(p.on_candles(&bars(&[90.0, 99.0]), 0), Ok(None));
+        assert_eq!(
+            p.on_candles(&bars(&[90.0, 101.0]), 0),
+            Ok(Some(Signal::Buy))
+        );
+        assert_eq!(
+            p.on_candles(&bars(&[101.0, 99.0]), 1),
+            Ok(Some(Signal::Exit))
+        );
+    }
+
+    #[test]
+    fn modules_off_the_abi_are_rejected() {
+        let imports = r#"(module (import "env" "now" (func (result i64))))"#;
+        assert!(Plugin::load(imports.as_bytes())
+            .err()
+            .unwrap()
+            .contains("no imports"));
+        let old = THRESHOLD.replace("(result i32) i32.const 1)", "(result i32) i32.const 7)");
+        assert!(Plugin::load(old.as_bytes())
+            .err()
+            .unwrap()
+            .contains("ABI version 7"));
+        let no_alloc = r#"(module (memory (export "memory") 1)
+            (func (export "rr_abi_version") (result i32) i32.const 1))"#;
+        assert!(Plugin::load(no_alloc.as_bytes()).is_err());
+        assert!(Plugin::load(b"\0asm garbage").is_err());
+        let huge = r#"(module (memory (export "memory") 1024))"#;
+        assert!(Plugin::load(huge.as_bytes()).is_err(), "64 MiB > cap");
+    }
+
+    #[test]
+    fn runaway_guests_run_out_of_fuel() {
+        let spin = THRESHOLD.replace("(local $close f64)", "(local $close f64) (loop $l (br $l))");
+        let mut p = Plugin::load(spin.as_bytes()).unwrap();
+        assert!(p.on_candles(&bars(&[1.0]), 0).is_err());
+        // the instance stays usable with fresh fuel on the next call
+        assert!(p.on_candles(&bars(&[1.0]), 0).is_err());
+    }
+
+    #[test]
+    fn params_and_names() {
+        let id = Uuid::nil();
+        assert!(
+            PluginParams::parse(serde_json::json!({ "symbol": "BTCUSDT", "plugin_id": id }))
+                .is_ok()
+        );
+        assert!(PluginParams::parse(
+            serde_json::json!({ "symbol": "BTCUSDT", "plugin_id": id, "qty": 0 })
+        )
+        .is_err());
+        assert!(valid_name("sma-cross_v2.1"));
+        assert!(!valid_name("../etc"));
+        assert!(!valid_name(""));
+    }
+}
@@ -0,0 +1,309 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Strategy parameter schemas
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every params type declares its fields once as a [`ParamSchema`] – type,
+//!   required or not, numeric range – and checks a row's `params` against it
+//!   before deserialising, so a bad value is reported per field
+//!   ([`FieldError`]) instead of as the first serde error
+//! * Rules spanning several fields (`fast < slow`, …) stay in the params'
+//!   `parse` and report the field they reject the same way
+//! * [`ParamSchema::json_schema`] renders the declaration as a JSON Schema
+//!   for clients building a params form (`GET /api/strategies/schemas/{name}`)
+//! * Unknown fields are let through: other services read keys of their own
+//!   off the same object. Those every strategy takes ([`SHARED`], e.g.
+//!   `max_funding_bps`) are checked and rendered along with each schema
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Integer,
+    Number,
+    String,
+    Boolean,
+    Uuid,
+    Array,
+    Object,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Integer => "integer",
+            Kind::Number => "number",
+            Kind::String | Kind::Uuid => "string",
+            Kind::Boolean => "boolean",
+            Kind::Array => "array",
+            Kind::Object => "object",
+        }
+    }
+
+    fn accepts(self, v: &Value) -> bool {
+        match self {
+            Kind::Integer => v.is_u64() || v.is_i64(),
+            Kind::Number => v.is_number(),
+            Kind::String => v.is_string(),
+            Kind::Boolean => v.is_boolean(),
+            Kind::Uuid => v.as_str().is_some_and(|s| uuid::Uuid::parse_str(s).is_ok()),
+            Kind::Array => v.is_array(),
+            Kind::Object => v.is_object(),
+        }
+    }
+}
+
+/// One declared parameter
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub required: bool,
+    /// `null` is as good as leaving it out
+    pub nullable: bool,
+    pub min: Option<f64>,
+    /// `min` itself is out of range
+    pub exclusive_min: bool,
+    pub max: Option<f64>,
+    /// Longest string accepted, in bytes
+    pub max_len: Option<usize>,
+}
+
+impl Field {
+    pub const fn new(name: &'static str, kind: Kind) -> Self {
+        Self {
+            name,
+            kind,
+            required: false,
+            nullable: false,
+            min: None,
+            exclusive_min: false,
+            max: None,
+            max_len: None,
+        }
+    }
+
+    pub const fn int(name: &'static str) -> Self {
+        Self::new(name, Kind::Integer)
+    }
+
+    pub const fn num(name: &'static str) -> Self {
+        Self::new(name, Kind::Number)
+    }
+
+    pub const fn string(name: &'static str) -> Self {
+        Self::new(name, Kind::String)
+    }
+
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub const fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+
+    /// `>= min`
+    pub const fn at_least(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// `> min`
+    pub const fn above(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self.exclusive_min = true;
+        self
+    }
+
+    /// `<= max`
+    pub const fn at_most(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub const fn max_len(mut self, len: usize) -> Self {
+        self.max_len = Some(len);
+        self
+    }
+
+    /// What's wrong with `v`, if anything
+    fn check(&self, v: &Value) -> Option<String> {
+        if !self.kind.accepts(v) {
+            return Some(match self.kind {
+                Kind::Uuid => "must be a UUID".into(),
+                Kind::Integer if v.is_number() => "must be a whole number".into(),
+                k => format!("must be of type {}", k.name()),
+            });
+        }
+        if let Some(x) = v.as_f64() {
+            let low = self
+                .min
+                .filter(|&m| x < m || (self.exclusive_min && x == m));
+            if let Some(m) = low {
+                let op = if self.exclusive_min { ">" } else { ">=" };
+                return Some(format!("must be {op} {m}"));
+            }
+            if let Some(m) = self.max.filter(|&m| x > m) {
+                return Some(format!("must be <= {m}"));
+            }
+        }
+        let too_long = v.as_str().zip(self.max_len).filter(|(s, n)| s.len() > *n);
+        if let Some((_, n)) = too_long {
+            return Some(format!("must be at most {n} bytes"));
+        }
+        None
+    }
+
+    fn json_schema(&self) -> Value {
+        let mut s = Map::new();
+        let ty = self.kind.name();
+        s.insert(
+            "type".into(),
+            if self.nullable {
+                json!([ty, "null"])
+            } else {
+                json!(ty)
+            },
+        );
+        if self.kind == Kind::Uuid {
+            s.insert("format".into(), json!("uuid"));
+        }
+        match (self.min, self.exclusive_min) {
+            (Some(m), true) => s.insert("exclusiveMinimum".into(), json!(m)),
+            (Some(m), false) => s.insert("minimum".into(), json!(m)),
+            (None, _) => None,
+        };
+        if let Some(m) = self.max {
+            s.insert("maximum".into(), json!(m));
+        }
+        if let Some(n) = self.max_len {
+            s.insert("maxLength".into(), json!(n));
+        }
+        Value::Object(s)
+    }
+}
+
+/// A rejected parameter and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// "qty: must be > 0; fast: must be shorter than slow"
+pub fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Parameters every strategy accepts, read by services outside it
+pub const SHARED: &[Field] = &[Field::num("max_funding_bps").at_least(0.0).nullable()];
+
+/// The declared parameters of one strategy
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSchema(pub &'static [Field]);
+
+impl ParamSchema {
+    /// Every field of `params` that breaks the declaration; `null` counts
+    /// as an empty object
+    pub fn validate(&self, params: &Value) -> Vec<FieldError> {
+        let empty = Map::new();
+        let obj = match params {
+            Value::Object(m) => m,
+            Value::Null => &empty,
+            _ => return vec![FieldError::new("params", "must be an object")],
+        };
+        let mut errors = Vec::new();
+        for f in self.0.iter().chain(SHARED) {
+            match obj.get(f.name) {
+                None | Some(Value::Null) if f.required => {
+                    errors.push(FieldError::new(f.name, "is required"))
+                }
+                None => {}
+                Some(Value::Null) if f.nullable => {}
+                Some(v) => {
+                    if let Some(msg) = f.check(v) {
+                        errors.push(FieldError::new(f.name, msg));
+                    }
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn json_schema(&self) -> Value {
+        let properties: Map<String, Value> = self
+            .0
+            .iter()
+            .chain(SHARED)
+            .map(|f| (f.name.to_string(), f.json_schema()))
+            .collect();
+        let required: Vec<&str> = self
+            .0
+            .iter()
+            .filter(|f| f.required)
+            .map(|f| f.name)
+            .collect();
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: ParamSchema = ParamSchema(&[
+        Field::string("symbol").required(),
+        Field::int("fast").at_least(1.0).at_most(500.0),
+        Field::num("qty").above(0.0),
+        Field::num("risk_pct").above(0.0).at_most(1.0).nullable(),
+    ]);
+
+    #[test]
+    fn every_bad_field_is_reported() {
+        let errors = SCHEMA.validate(&json!({ "fast": 1.5, "qty": 0, "risk_pct": 2 }));
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["symbol", "fast", "qty", "risk_pct"]);
+        assert_eq!(errors[2].message, "must be > 0");
+
+        let ok = json!({ "symbol": "BTCUSDT", "fast": 20, "qty": 0.1, "risk_pct": null });
+        assert!(SCHEMA.validate(&ok).is_empty());
+        assert_eq!(SCHEMA.validate(&json!([])).len(), 1);
+    }
+
+    #[test]
+    fn renders_as_json_schema() {
+        let s = SCHEMA.json_schema();
+        assert_eq!(s["required"], json!(["symbol"]));
+        assert_eq!(s["properties"]["max_funding_bps"]["minimum"], json!(0.0));
+        assert_eq!(s["properties"]["qty"]["exclusiveMinimum"], json!(0.0));
+        assert_eq!(
+            s["properties"]["risk_pct"]["type"],
+            json!(["number", "null"])
+        );
+    }
+}
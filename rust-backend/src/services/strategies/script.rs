@@ -0,0 +1,390 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Scripted strategies (Rhai)
+//! ──────────────────────────────────────────────────────────────────────────
+//! Pro users can run their own logic as a small Rhai script defining
+//!
+//! ```text
+//! fn on_candle(ctx) {
+//!     let fast = sma(ctx.closes, ctx.inputs.fast);
+//!     let slow = sma(ctx.closes, 50);
+//!     if fast == () || slow == () { return; }
+//!     if ctx.position == 0 && fast > slow { return "buy"; }
+//!     if ctx.position > 0 && fast < slow { return "exit"; }
+//! }
+//! ```
+//!
+//! * `ctx` carries the closed 1 h candle (`open` … `volume`, `ts`), the last
+//!   `history` bars as `closes` / `highs` / `lows` / `volumes` (oldest
+//!   first), the strategy's `position` (1 long, -1 short, 0 flat) and the
+//!   user's `inputs` map
+//! * Helpers: `sma`, `highest`, `lowest` (array, n) and `atr` (highs, lows,
+//!   closes, n) – `()` until enough bars are in
+//! * Return `"buy"`, `"sell"`, `"exit"` or nothing; positions, risk checks
+//!   and order routing are `strategies::custom`'s
+//! * Sandbox: no modules / `eval`, caps on operations, call depth, string,
+//!   array and map sizes, plus a wall-clock budget per call
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rhai::{
+    module_resolvers::DummyModuleResolver, Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::{
+    db::cache::SharedCache,
+    services::{
+        market_data::MarketBus,
+        strategies::{
+            common::Candle,
+            custom::{self, Runner, Signal},
+            indicators,
+            schema::{Field, Kind, ParamSchema},
+            warmup::{Need, Warmup},
+            StrategyError,
+        },
+    },
+};
+
+const MAX_SOURCE: usize = 16 * 1024;
+const MAX_HISTORY: usize = 1_000;
+const MAX_OPERATIONS: u64 = 200_000;
+/// Wall-clock budget for one `on_candle` call
+const CALL_BUDGET: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Deserialize)]
+pub struct ScriptParams {
+    pub symbol: String,
+    pub source: String,
+    #[serde(default = "dq")]
+    pub qty: f64,
+    /// Bars handed to the script
+    #[serde(default = "dh")]
+    pub history: usize,
+    /// Free-form constants, exposed as `ctx.inputs`
+    #[serde(default)]
+    pub inputs: serde_json::Map<String, Value>,
+}
+fn dq() -> f64 {
+    0.01
+}
+fn dh() -> usize {
+    200
+}
+
+impl ScriptParams {
+    pub const SCHEMA: ParamSchema = ParamSchema(&[
+        Field::string("symbol").required(),
+        Field::string("source").required().max_len(MAX_SOURCE),
+        Field::num("qty").above(0.0),
+        Field::int("history")
+            .at_least(1.0)
+            .at_most(MAX_HISTORY as f64),
+        Field::new("inputs", Kind::Object),
+    ]);
+
+    pub fn parse(params: Value) -> Result<Self, StrategyError> {
+        Self::SCHEMA.check(&params)?;
+        let p: Self = serde_json::from_value(params)?;
+        Script::compile(&p)?;
+        Ok(p)
+    }
+
+    /// Candles needed before the first trade
+    pub fn warmup(&self) -> Vec<Need> {
+        vec![Need::new("1h", self.history)]
+    }
+}
+
+fn floats(xs: &Array) -> Vec<f64> {
+    xs.iter()
+        .filter_map(|d| {
+            d.as_float()
+                .ok()
+                .or_else(|| d.as_int().ok().map(|i| i as f64))
+        })
+        .collect()
+}
+
+fn window(xs: &Array, n: i64) -> Option<Vec<f64>> {
+    let xs = floats(xs);
+    let n = usize::try_from(n)
+        .ok()
+        .filter(|n| *n > 0 && *n <= xs.len())?;
+    Some(xs[xs.len() - n..].to_vec())
+}
+
+fn opt(x: Option<f64>) -> Dynamic {
+    x.map(Dynamic::from_float).unwrap_or(Dynamic::UNIT)
+}
+
+/// An engine that can't reach outside its own arguments
+fn sandbox(started: Arc<Mutex<Instant>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4_096)
+        .set_max_array_size(MAX_HISTORY * 2)
+        .set_max_map_size(256)
+        .set_max_modules(0)
+        .disable_symbol("import")
+        .disable_symbol("eval");
+    engine.on_progress(move |ops| {
+        let over = ops % 1_024 == 0 && started.lock().unwrap().elapsed() > CALL_BUDGET;
+        over.then(|| Dynamic::from("time budget exceeded"))
+    });
+    engine.on_print(|s| tracing::debug!("script: {s}"));
+    engine.on_debug(|s, _, _| tracing::debug!("script: {s}"));
+
+    engine.register_fn("sma", |xs: Array, n: i64| {
+        opt(window(&xs, n).and_then(|w| indicators::sma(&w, w.len())))
+    });
+    engine.register_fn("highest", |xs: Array, n: i64| {
+        opt(window(&xs, n).map(|w| w.into_iter().fold(f64::MIN, f64::max)))
+    });
+    engine.register_fn("lowest", |xs: Array, n: i64| {
+        opt(window(&xs, n).map(|w| w.into_iter().fold(f64::MAX, f64::min)))
+    });
+    engine.register_fn("atr", |highs: Array, lows: Array, closes: Array, n: i64| {
+        let bars: Vec<Candle> = floats(&highs)
+            .into_iter()
+            .zip(floats(&lows))
+            .zip(floats(&closes))
+            .map(|((high, low), close)| Candle {
+                high,
+                low,
+                close,
+                open: close,
+                ..Default::default()
+            })
+            .collect();
+        opt(usize::try_from(n)
+            .ok()
+            .and_then(|n| indicators::atr(&bars, n)))
+    });
+    engine
+}
+
+/// A compiled `on_candle` script
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    inputs: Dynamic,
+    started: Arc<Mutex<Instant>>,
+}
+
+impl Script {
+    pub fn compile(cfg: &ScriptParams) -> Result<Self, StrategyError> {
+        let started = Arc::new(Mutex::new(Instant::now()));
+        let engine = sandbox(started.clone());
+        let ast = engine
+            .compile(&cfg.source)
+            .map_err(|e| StrategyError::Config(format!("script: {e}")))?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "on_candle" && f.params.len() == 1)
+        {
+            return Err(StrategyError::Config(
+                "script must define `fn on_candle(ctx)`".into(),
+            ));
+        }
+        let inputs = rhai::serde::to_dynamic(&cfg.inputs)
+            .map_err(|e| StrategyError::Config(format!("inputs: {e}")))?;
+        Ok(Self {
+            engine,
+            ast,
+            inputs,
+            started,
+        })
+    }
+
+    fn context(&self, bars: &[Candle], position: i8) -> Map {
+        let series = |f: fn(&Candle) -> f64| -> Dynamic {
+            bars.iter()
+                .map(|c| Dynamic::from_float(f(c)))
+                .collect::<Array>()
+                .into()
+        };
+        let last = bars.last().copied().unwrap_or_default();
+        let mut ctx = Map::new();
+        ctx.insert("open".into(), last.open.into());
+        ctx.insert("high".into(), last.high.into());
+        ctx.insert("low".into(), last.low.into());
+        ctx.insert("close".into(), last.close.into());
+        ctx.insert("volume".into(), last.volume.into());
+        ctx.insert("ts".into(), last.ts.timestamp().into());
+        ctx.insert("closes".into(), series(|c| c.close));
+        ctx.insert("highs".into(), series(|c| c.high));
+        ctx.insert("lows".into(), series(|c| c.low));
+        ctx.insert("volumes".into(), series(|c| c.volume));
+        ctx.insert("position".into(), i64::from(position).into());
+        ctx.insert("inputs".into(), self.inputs.clone());
+        ctx
+    }
+
+    /// Run `on_candle` over `bars` (oldest first, the new bar last)
+    pub fn on_candle(&self, bars: &[Candle], position: i8) -> Result<Option<Signal>, String> {
+        *self.started.lock().unwrap() = Instant::now();
+        let out: Dynamic = self
+            .engine
+            .call_fn_with_options(
+                CallFnOptions::new().eval_ast(false),
+                &mut Scope::new(),
+                &self.ast,
+                "on_candle",
+                (self.context(bars, position),),
+            )
+            .map_err(|e| e.to_string())?;
+        if out.is_unit() {
+            return Ok(None);
+        }
+        match out.into_string().as_deref() {
+            Ok("buy") => Ok(Some(Signal::Buy)),
+            Ok("sell") => Ok(Some(Signal::Sell)),
+            Ok("exit") => Ok(Some(Signal::Exit)),
+            Ok("hold") => Ok(None),
+            _ => Err("on_candle must return \"buy\", \"sell\", \"exit\" or nothing".into()),
+        }
+    }
+}
+
+/// ------------------------------------------------------------
+/// Public Tokio task – returns on a closed feed, bad params or a script
+/// that keeps failing
+/// ------------------------------------------------------------
+pub async fn loop_forever(
+    row: crate::services::scheduler::StrategyRow,
+    cache: SharedCache,
+    db: Arc<PgPool>,
+    bus: MarketBus,
+    master_key: Vec<u8>,
+    is_demo: bool,
+    warm: Arc<Warmup>,
+) -> Result<(), StrategyError> {
+    let cfg = ScriptParams::parse(row.params)?;
+    let script = Script::compile(&cfg)?;
+    let run = Runner {
+        kind: "script",
+        strategy_id: row.strategy_id,
+        user_id: row.user_id,
+        symbol: cfg.symbol,
+        qty: cfg.qty,
+        history: cfg.history,
+        warm,
+    };
+    custom::drive(
+        run,
+        cache,
+        db,
+        bus,
+        master_key,
+        is_demo,
+        |bars, position| script.on_candle(bars, position),
+    )
+    .await
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(source: &str) -> Value {
+        json!({ "symbol": "BTCUSDT", "source": source, "inputs": { "fast": 3 } })
+    }
+
+    fn script(source: &str) -> Script {
+        Script::compile(&ScriptParams::parse(params(source)).unwrap()).unwrap()
+    }
+
+    fn bars(closes: &[f64]) -> Vec<Candle> {
+        closes
+            .iter()
+            .map(|&close| Candle {
+                open: close,
+                high: close + 1.0,
+                low: close - 1.0,
+                close,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    const CROSS: &str = r#"
+        fn on_candle(ctx) {
+            let fast = sma(ctx.closes, ctx.inputs.fast);
+            let slow = sma(ctx.closes, 5);
+            if fast == () || slow == () { return; }
+            if ctx.position == 0 && fast > slow { return "buy"; }
+            if ctx.position > 0 && fast < slow { return "exit"; }
+        }
+    "#;
+
+    #[test]
+    fn crossover_script_signals() {
+        let s = script(CROSS);
+        assert_eq!(s.on_candle(&bars(&[1.0, 2.0]), 0), Ok(None), "warming up");
+        let rising = bars(&[1.0, 1.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(s.on_candle(&rising, 0), Ok(Some(Signal::Buy)));
+        assert_eq!(s.on_candle(&rising, 1), Ok(None));
+        let falling = bars(&[4.0, 4.0, 4.0, 3.0, 2.0, 1.0]);
+        assert_eq!(s.on_candle(&falling, 1), Ok(Some(Signal::Exit)));
+    }
+
+    #[test]
+    fn helpers_see_the_series() {
+        let s = script(
+            r#"
+            fn on_candle(ctx) {
+                if highest(ctx.highs, 3) == 5.0 && lowest(ctx.lows, 3) == 1.0
+                   && atr(ctx.highs, ctx.lows, ctx.closes, 2) != () && ctx.close == 4.0 {
+                    return "sell";
+                }
+                "hold"
+            }
+            "#,
+        );
+        assert_eq!(
+            s.on_candle(&bars(&[9.0, 2.0, 3.0, 4.0]), 0),
+            Ok(Some(Signal::Sell))
+        );
+    }
+
+    #[test]
+    fn bad_scripts_are_rejected_up_front() {
+        for src in [
+            "fn on_candle(ctx) { ",
+            "fn other(ctx) { }",
+            "fn on_candle() { }",
+            r#"import "os" as os; fn on_candle(ctx) { }"#,
+            r#"fn on_candle(ctx) { eval("1") }"#,
+        ] {
+            assert!(ScriptParams::parse(params(src)).is_err(), "{src}");
+        }
+        let huge = format!("fn on_candle(ctx) {{ }} // {}", "x".repeat(MAX_SOURCE));
+        assert!(ScriptParams::parse(params(&huge)).is_err());
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        let s = script("fn on_candle(ctx) { loop { } }");
+        assert!(s.on_candle(&bars(&[1.0]), 0).is_err());
+
+        let s = script(r#"fn on_candle(ctx) { let s = "x"; loop { s += s; } }"#);
+        assert!(s.on_candle(&bars(&[1.0]), 0).is_err());
+
+        let s = script("fn on_candle(ctx) { 42 }");
+        assert!(s.on_candle(&bars(&[1.0]), 0).is_err(), "bad return value");
+    }
+}
@@ -0,0 +1,87 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Random candle series for property tests (cargo feature `testkit`)
+//! ──────────────────────────────────────────────────────────────────────────
+//! * [`candles`] is a geometric random walk on hourly bars with consistent
+//!   OHLC (`low ≤ open, close ≤ high`) and the odd zero-volume bar
+//! * [`series`] mixes in the edge cases indicators tend to trip over: a
+//!   whole series without volume and a dead-flat market
+//! * Compiled for this crate's tests; other crates and benches enable the
+//!   `testkit` feature
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::ops::Range;
+
+use chrono::{Duration, TimeZone, Utc};
+use proptest::prelude::*;
+
+use crate::services::strategies::Candle;
+
+/// Bar volume: mostly positive, sometimes nothing traded
+pub fn volume() -> impl Strategy<Value = f64> {
+    prop_oneof![9 => 0.0..1e6, 1 => Just(0.0)]
+}
+
+fn bar_ts(i: usize) -> chrono::DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::hours(i as i64)
+}
+
+/// Random-walk series, each bar opening at the previous close
+pub fn candles(len: Range<usize>) -> impl Strategy<Value = Vec<Candle>> {
+    let step = (-0.05..0.05f64, 0.0..0.02f64, 0.0..0.02f64, volume());
+    (1.0..100_000.0f64, prop::collection::vec(step, len)).prop_map(|(start, steps)| {
+        let mut close = start;
+        steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, (ret, up, down, volume))| {
+                let open = close;
+                close = open * (1.0 + ret);
+                Candle {
+                    ts: bar_ts(i),
+                    open,
+                    high: open.max(close) * (1.0 + up),
+                    low: open.min(close) * (1.0 - down),
+                    close,
+                    volume,
+                    delta: None,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Same walk with every volume zeroed
+pub fn zero_volume(len: Range<usize>) -> impl Strategy<Value = Vec<Candle>> {
+    candles(len).prop_map(|mut c| {
+        c.iter_mut().for_each(|x| x.volume = 0.0);
+        c
+    })
+}
+
+/// One price for every OHLC field of every bar
+pub fn flat(len: Range<usize>) -> impl Strategy<Value = Vec<Candle>> {
+    (1.0..100_000.0f64, prop::collection::vec(volume(), len)).prop_map(|(p, vols)| {
+        vols.into_iter()
+            .enumerate()
+            .map(|(i, volume)| Candle {
+                ts: bar_ts(i),
+                open: p,
+                high: p,
+                low: p,
+                close: p,
+                volume,
+                delta: None,
+            })
+            .collect()
+    })
+}
+
+/// Default input for indicator properties
+pub fn series(len: Range<usize>) -> impl Strategy<Value = Vec<Candle>> {
+    prop_oneof![
+        6 => candles(len.clone()),
+        2 => zero_volume(len.clone()),
+        2 => flat(len),
+    ]
+}
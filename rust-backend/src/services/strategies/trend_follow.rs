@@ -1,19 +1,33 @@
 //! Medium-Term Trend-Following strategy
 //! ====================================
-//! Fast/Slow SMA × Donchian breakout with Redis
-//! position-flag and full unit tests.
+//! Fast/Slow SMA × Donchian breakout with a cached
+//! position-flag and full unit tests. Each daily evaluation also publishes
+//! the regime it read as a `StrategySignal`.
 
-use chrono::Timelike;
+use chrono::{DateTime, Timelike, Utc};
 use serde::Deserialize;
 use sqlx::PgPool;
 use std::sync::Arc;
+use tracing::Instrument;
 
 use crate::{
-    db::redis::RedisPool,
+    db::cache::{Cache, SharedCache},
     services::{
-        market_data::MarketBus,
-        strategies::common::Candle,
-        trading_engine::{execute_trade, Exchange, TradeRequest},
+        allocation::{self, Sizing},
+        drain,
+        market_data::{MarketBus, Regime, SignalKind, StrategySignal},
+        portfolio::{Portfolio, Sizer},
+        position_manager::{ManagedPosition, MgmtAction, Side, TradeMgmt},
+        strategies::{
+            common::Candle,
+            heartbeat::{CandleFeed, Heartbeat},
+            indicators,
+            schema::{Field, Kind, ParamSchema},
+            warmup::{Need, Warmup},
+            StrategyError,
+        },
+        telemetry::candle_span,
+        trading_engine::{Exchange, TradeRequest},
     },
 };
 
@@ -31,6 +45,15 @@ pub struct TrendParams {
     pub don: u16,
     #[serde(default = "dq")]
     pub qty: f64,
+    /// Size entries to lose this share of equity at the Donchian-low exit
+    /// instead of trading a fixed `qty`
+    #[serde(default)]
+    pub risk_pct: Option<f64>,
+    /// Break-even / partial TP / trailing / max-hold rules checked on every
+    /// hourly bar, with the Donchian low at entry as the stop; a hold limit
+    /// is measured from the daily close that opened the trade. Off by default
+    #[serde(default)]
+    pub mgmt: TradeMgmt,
 }
 fn d20() -> u16 {
     20
@@ -45,18 +68,54 @@ fn dq() -> f64 {
     0.01
 }
 
+impl TrendParams {
+    pub const SCHEMA: ParamSchema = ParamSchema(&[
+        Field::string("symbol").required(),
+        Field::int("fast").at_least(1.0).at_most(u16::MAX as f64),
+        Field::int("slow").at_least(2.0).at_most(u16::MAX as f64),
+        Field::int("don").at_least(1.0).at_most(u16::MAX as f64),
+        Field::num("qty").above(0.0),
+        Field::num("risk_pct").above(0.0).at_most(1.0).nullable(),
+        Field::new("mgmt", Kind::Object),
+    ]);
+
+    pub fn parse(params: serde_json::Value) -> Result<Self, StrategyError> {
+        Self::SCHEMA.check(&params)?;
+        let p: Self = serde_json::from_value(params)?;
+        if p.fast >= p.slow {
+            return Err(StrategyError::field("fast", "must be shorter than slow"));
+        }
+        Ok(p)
+    }
+
+    /// Candles needed before the first trade
+    pub fn warmup(&self) -> Vec<Need> {
+        vec![Need::new("1d", self.slow as usize)]
+    }
+}
+
 /// ------------------------------------------------------------
 /// Mini-traits so we can inject mocks in tests
 /// ------------------------------------------------------------
 use async_trait::async_trait;
 type TradeExec =
-    dyn Fn(TradeRequest, &(dyn Db), i64, bool, &[u8]) -> Result<(), String> + Send + Sync;
+    dyn Fn(TradeRequest, &dyn Db, i64, bool, &[u8]) -> Result<(), String> + Send + Sync;
+type SignalOut = dyn Fn(SignalKind) + Send + Sync;
 
 #[async_trait]
 pub trait Redis: Send + Sync {
     async fn set_pos_flag(&self, key: &str, value: bool, ttl_secs: usize) -> Result<(), ()>;
 
     async fn get_pos_flag(&self, key: &str) -> Result<Option<bool>, ()>;
+
+    /// Size of the open position, so the exit closes what the entry opened
+    async fn set_pos_size(&self, _key: &str, _size: f64, _ttl_secs: usize) -> Result<(), ()> {
+        Ok(())
+    }
+
+    async fn get_pos_size(&self, _key: &str) -> Result<Option<f64>, ()> {
+        Ok(None)
+    }
 }
 #[async_trait]
 pub trait Db: Send + Sync {}
@@ -70,20 +129,33 @@ pub trait RiskChecker: Send + Sync {
 
 /// ---- impls for real types (prod path unchanged) ------------------------
 #[async_trait]
-impl Redis for RedisPool {
+impl Redis for SharedCache {
     async fn set_pos_flag(&self, key: &str, value: bool, ttl_secs: usize) -> Result<(), ()> {
-        self.set_json(key, &value, ttl_secs).await.map_err(|_| ())
+        (**self)
+            .set_json(key, &value, ttl_secs as u64)
+            .await
+            .map_err(|_| ())
     }
 
     async fn get_pos_flag(&self, key: &str) -> Result<Option<bool>, ()> {
-        self.get_json(key).await.map_err(|_| ())
+        (**self).get_json(key).await.map_err(|_| ())
+    }
+
+    async fn set_pos_size(&self, key: &str, size: f64, ttl_secs: usize) -> Result<(), ()> {
+        (**self)
+            .set_json(key, &size, ttl_secs as u64)
+            .await
+            .map_err(|_| ())
+    }
+
+    async fn get_pos_size(&self, key: &str) -> Result<Option<f64>, ()> {
+        (**self).get_json(key).await.map_err(|_| ())
     }
 }
 #[async_trait]
 impl Db for PgPool {}
 
-use tokio::sync::broadcast;
-pub struct CandleRx(pub broadcast::Receiver<Candle>);
+pub struct CandleRx(pub CandleFeed);
 #[async_trait]
 impl MarketBusSub for CandleRx {
     async fn recv(&mut self) -> Result<Candle, ()> {
@@ -93,50 +165,86 @@ impl MarketBusSub for CandleRx {
 
 /// Real risk wrapper
 pub struct RealRisk<'a> {
-    redis: &'a RedisPool,
+    cache: &'a dyn Cache,
 }
 impl RiskChecker for RealRisk<'_> {
     fn check_drawdown(&self, uid: i64) -> Result<(), String> {
-        futures::executor::block_on(crate::services::risk::check_drawdown(self.redis, uid))
+        futures::executor::block_on(crate::services::risk::check_drawdown(self.cache, uid))
             .map_err(|e| e.to_string())
     }
 }
 
 /// ------------------------------------------------------------
-/// Public Tokio task (signature unchanged)
+/// Public Tokio task – returns only on a closed feed or bad params
 /// ------------------------------------------------------------
 pub async fn loop_forever(
     row: crate::services::scheduler::StrategyRow,
-    redis: RedisPool,
+    cache: SharedCache,
     db: Arc<PgPool>,
     bus: MarketBus,
     master_key: Vec<u8>,
     is_demo: bool,
-) {
-    let cfg: TrendParams = serde_json::from_value(row.params).expect("bad trend params");
-
-    let mut daily: Vec<Candle> = Vec::with_capacity(cfg.slow as usize + 5);
-    let rx = CandleRx(bus.candles_1h.subscribe());
-    let risk = RealRisk { redis: &redis };
+    warm: Arc<Warmup>,
+) -> Result<(), StrategyError> {
+    let strategy_id = row.strategy_id;
+    let cfg = TrendParams::parse(row.params)?;
+
+    let mut daily: Vec<Candle> = Vec::with_capacity(cfg.slow as usize + 11);
+    daily.extend(warm.take("1d"));
+    let rx = CandleRx(CandleFeed::new(
+        bus.candles(&cfg.symbol, "1h"),
+        Heartbeat::new(cache.clone(), strategy_id, &cfg.symbol, "1h"),
+    ));
+    let risk = RealRisk { cache: &*cache };
+    let portfolio =
+        Portfolio::new((*db).clone(), is_demo, master_key.clone()).for_strategy(strategy_id);
+    // risk-sized entries already size off the allocation's equity
+    let sizing = if cfg.risk_pct.is_some() {
+        Sizing::AsIs
+    } else {
+        Sizing::ScaleQty
+    };
     let db_cl = db.clone();
+    let (user_id, symbol, signal_bus) = (row.user_id, cfg.symbol.clone(), bus.clone());
 
     loop_core(
         cfg,
-        &redis,
+        &cache,
         &*db,
         Box::new(rx),
         row.user_id,
         &master_key,
         is_demo,
         &risk,
+        &portfolio,
         &move |req, _, uid, demo, key| {
-            futures::executor::block_on(execute_trade(req, &db_cl, uid, demo, key))
-                .map(|_| ())
-                .map_err(|e| e.to_string())
+            futures::executor::block_on(allocation::execute(
+                &db_cl,
+                strategy_id,
+                req,
+                uid,
+                demo,
+                key,
+                sizing,
+            ))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+        },
+        &move |kind| {
+            signal_bus.publish_signal(StrategySignal {
+                source: "trend_follow",
+                strategy_id,
+                user_id,
+                symbol: symbol.clone(),
+                ts: Utc::now(),
+                kind,
+            })
         },
         &mut daily,
+        &warm,
     )
     .await;
+    Ok(())
 }
 
 /// ------------------------------------------------------------
@@ -145,21 +253,34 @@ pub async fn loop_forever(
 #[allow(clippy::too_many_arguments)]
 pub async fn loop_core(
     cfg: TrendParams,
-    redis: &(dyn Redis),
-    db: &(dyn Db),
+    redis: &dyn Redis,
+    db: &dyn Db,
     mut rx: Box<dyn MarketBusSub>,
     user_id: i64,
     master_key: &[u8],
     is_demo: bool,
     risk: &dyn RiskChecker,
+    sizer: &dyn Sizer,
     trade_exec: &TradeExec,
+    signal: &SignalOut,
     daily_buf: &mut Vec<Candle>, // pass mutable buffer so tests can pre-seed
+    warm: &Warmup,
 ) {
     let mut agg: Option<Candle> = None;
-
-    while let Ok(c) = rx.recv().await {
-        if cfg.symbol.to_uppercase() != "BTCUSDT" {
-            continue;
+    // the position under `cfg.mgmt`, tracked only when a rule is set
+    let mut open: Option<ManagedPosition> = None;
+
+    // a drain stops the loop between bars
+    while let Some(Ok(c)) = drain::or_stop(rx.recv()).await {
+        if let Some(pos) = open.as_mut() {
+            manage(
+                pos, &c, &cfg, redis, db, user_id, master_key, is_demo, trade_exec,
+            )
+            .instrument(candle_span("trend_follow", &cfg.symbol, &c))
+            .await;
+            if pos.is_closed() {
+                open = None;
+            }
         }
 
         match &mut agg {
@@ -175,59 +296,127 @@ pub async fn loop_core(
         if c.ts.hour() == 0 {
             if let Some(finished) = agg.take() {
                 daily_buf.push(finished);
+                warm.bar("1d");
                 if daily_buf.len() > cfg.slow as usize + 10 {
                     daily_buf.remove(0);
                 }
-                evaluate_core(
-                    daily_buf, &cfg, redis, db, user_id, master_key, is_demo, risk, trade_exec,
+                let regime = evaluate_core(
+                    daily_buf, &cfg, redis, db, user_id, master_key, is_demo, risk, sizer,
+                    trade_exec, &mut open, c.ts,
                 )
+                .instrument(candle_span("trend_follow", &cfg.symbol, &c))
                 .await;
+                if let Some(regime) = regime {
+                    signal(SignalKind::Regime { regime });
+                }
+            }
+        }
+    }
+}
+
+/// Redis keys of the position flag and its size
+fn pos_keys(user_id: i64) -> (String, String) {
+    let pos_key = format!("trendpos:{user_id}");
+    let size_key = format!("{pos_key}:size");
+    (pos_key, size_key)
+}
+
+/// Runs one hourly bar through `cfg.mgmt`, sending the resulting exits
+/// reduce-only and keeping the cached flag and size in step
+#[allow(clippy::too_many_arguments)]
+async fn manage(
+    pos: &mut ManagedPosition,
+    c: &Candle,
+    cfg: &TrendParams,
+    redis: &dyn Redis,
+    db: &dyn Db,
+    user_id: i64,
+    master_key: &[u8],
+    is_demo: bool,
+    trade_exec: &TradeExec,
+) {
+    let (pos_key, size_key) = pos_keys(user_id);
+    for action in pos.on_candle(c, &cfg.mgmt) {
+        match action {
+            MgmtAction::MoveStop { from, to } => {
+                tracing::info!("trend-follow {user_id}: stop {from:.2} → {to:.2}")
+            }
+            MgmtAction::PartialClose { size, .. } | MgmtAction::Close { size, .. } => {
+                let req = TradeRequest {
+                    exchange: Exchange::Blowfin,
+                    symbol: pos.symbol.clone(),
+                    side: pos.side.exit_side().into(),
+                    order_type: "market".into(),
+                    price: None,
+                    size,
+                    reduce_only: true,
+                    signal_price: Some(c.close),
+                    tp_sl: None,
+                };
+                if let Err(e) = trade_exec(req, db, user_id, is_demo, master_key) {
+                    tracing::error!("trend-follow exit err: {e}");
+                }
             }
         }
     }
+    if pos.is_closed() {
+        let _ = redis.set_pos_flag(&pos_key, false, 0).await;
+    } else {
+        let _ = redis
+            .set_pos_size(&size_key, pos.remaining, 3600 * 24 * 30)
+            .await;
+    }
+}
+
+/// Trending when price and the fast SMA are both on the same side of the
+/// slow one, else ranging
+pub fn regime(price: f64, fast: f64, slow: f64) -> Regime {
+    if price > slow && fast > slow {
+        Regime::TrendingUp
+    } else if price < slow && fast < slow {
+        Regime::TrendingDown
+    } else {
+        Regime::Ranging
+    }
 }
 
 /// ------------------------------------------------------------
-/// Pure evaluate logic (no networking) – unit-test target
+/// Pure evaluate logic (no networking) – unit-test target; returns the
+/// regime read off the bars, `None` before there are enough of them.
+/// An entry made under `cfg.mgmt` is left in `open`, opened `at`.
 /// ------------------------------------------------------------
 #[allow(clippy::too_many_arguments)]
 pub async fn evaluate_core(
     d: &[Candle],
     cfg: &TrendParams,
-    redis: &(dyn Redis),
-    db: &(dyn Db),
+    redis: &dyn Redis,
+    db: &dyn Db,
     user_id: i64,
     master_key: &[u8],
     is_demo: bool,
     risk: &dyn RiskChecker,
+    sizer: &dyn Sizer,
     trade_exec: &TradeExec,
-) {
+    open: &mut Option<ManagedPosition>,
+    at: DateTime<Utc>,
+) -> Option<Regime> {
     if d.len() < cfg.slow as usize {
-        return;
+        return None;
     }
 
     let closes: Vec<f64> = d.iter().map(|c| c.close).collect();
-    let highs: Vec<f64> = d.iter().map(|c| c.high).collect();
-    let lows: Vec<f64> = d.iter().map(|c| c.low).collect();
-
-    let sma = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
-
-    let fast = sma(&closes[closes.len() - cfg.fast as usize..]);
-    let slow = sma(&closes[closes.len() - cfg.slow as usize..]);
-
-    let don_h = highs
-        .iter()
-        .rev()
-        .take(cfg.don as usize)
-        .fold(f64::MIN, |a, &b| a.max(b));
-    let don_l = lows
-        .iter()
-        .rev()
-        .take(cfg.don as usize)
-        .fold(f64::MAX, |a, &b| a.min(b));
+
+    let (Some(fast), Some(slow), Some(don)) = (
+        indicators::sma(&closes, cfg.fast as usize),
+        indicators::sma(&closes, cfg.slow as usize),
+        indicators::donchian(d, cfg.don as usize),
+    ) else {
+        return None;
+    };
+    let (don_h, don_l) = (don.high, don.low);
     let price = *closes.last().unwrap();
 
-    let pos_key = format!("trendpos:{user_id}");
+    let (pos_key, size_key) = pos_keys(user_id);
     let in_pos: bool = redis
         .get_pos_flag(&pos_key)
         .await
@@ -238,14 +427,28 @@ pub async fn evaluate_core(
     match (in_pos, fast > slow, price >= don_h, price <= don_l) {
         // Exit ↓
         (true, _, _, exit) if exit => {
+            // whatever the manager has left open
+            let managed = open.take().map(|pos| pos.remaining);
             if risk.check_drawdown(user_id).is_ok() {
+                let size = match managed {
+                    Some(size) => size,
+                    None => redis
+                        .get_pos_size(&size_key)
+                        .await
+                        .ok()
+                        .flatten()
+                        .unwrap_or(cfg.qty),
+                };
                 let req = TradeRequest {
                     exchange: Exchange::Blowfin,
                     symbol: cfg.symbol.clone(),
                     side: "sell".into(),
                     order_type: "market".into(),
                     price: None,
-                    size: cfg.qty,
+                    size,
+                    reduce_only: true,
+                    signal_price: Some(price),
+                    tp_sl: None,
                 };
                 let _ = trade_exec(req, db, user_id, is_demo, master_key);
             }
@@ -254,20 +457,43 @@ pub async fn evaluate_core(
         // Entry ↑
         (false, true, entry, _) if entry => {
             if risk.check_drawdown(user_id).is_ok() {
+                // the configured qty when no equity is known
+                let size = match cfg.risk_pct {
+                    Some(pct) => sizer
+                        .position_size(user_id, pct, price - don_l)
+                        .await
+                        .unwrap_or(cfg.qty),
+                    None => cfg.qty,
+                };
+                let _ = redis.set_pos_size(&size_key, size, 3600 * 24 * 30).await;
                 let req = TradeRequest {
                     exchange: Exchange::Blowfin,
                     symbol: cfg.symbol.clone(),
                     side: "buy".into(),
                     order_type: "market".into(),
                     price: None,
-                    size: cfg.qty,
+                    size,
+                    reduce_only: false,
+                    signal_price: Some(price),
+                    tp_sl: None,
                 };
-                let _ = trade_exec(req, db, user_id, is_demo, master_key);
+                let sent = trade_exec(req, db, user_id, is_demo, master_key).is_ok();
+                if sent && cfg.mgmt.is_set() {
+                    *open = Some(ManagedPosition::open(
+                        &cfg.symbol,
+                        Side::Long,
+                        price,
+                        don_l,
+                        size,
+                        at,
+                    ));
+                }
             }
             let _ = redis.set_pos_flag(&pos_key, true, 3600 * 24 * 30).await;
         }
         _ => {}
     }
+    Some(regime(price, fast, slow))
 }
 
 ////////////////////////////////////////////////////////////////
@@ -276,6 +502,8 @@ pub async fn evaluate_core(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::portfolio::{self, FixedQty};
+    use crate::services::position_manager::PartialTp;
     use async_trait::async_trait;
     use std::sync::{Arc, Mutex};
 
@@ -336,8 +564,7 @@ mod tests {
     }
     fn collect(
         vec: Arc<Mutex<Vec<Call>>>,
-    ) -> impl Fn(TradeRequest, &(dyn Db), i64, bool, &[u8]) -> Result<(), String> + Send + Sync
-    {
+    ) -> impl Fn(TradeRequest, &dyn Db, i64, bool, &[u8]) -> Result<(), String> + Send + Sync {
         move |req, _, _, _, _| {
             vec.lock().unwrap().push(Call {
                 side: req.side,
@@ -356,6 +583,8 @@ mod tests {
             slow: 5,
             don: 2,
             qty: 0.1,
+            risk_pct: None,
+            mgmt: TradeMgmt::default(),
         };
 
         // price series makes fast>slow and price == don_h
@@ -371,7 +600,7 @@ mod tests {
         let db = DMock;
         let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
 
-        evaluate_core(
+        let regime = evaluate_core(
             &hist,
             &cfg,
             &redis,
@@ -380,15 +609,69 @@ mod tests {
             &[],
             false,
             &Risk { fail: false },
+            &FixedQty,
             &collect(calls.clone()),
+            &mut None,
+            Utc::now(),
         )
         .await;
 
+        assert_eq!(regime, Some(Regime::TrendingUp));
         assert_eq!(calls.lock().unwrap().len(), 1);
         assert_eq!(*redis.pos.lock().unwrap(), Some(true));
         assert_eq!(calls.lock().unwrap()[0].qty, 0.1);
     }
 
+    struct Equity(f64);
+    #[async_trait]
+    impl Sizer for Equity {
+        async fn position_size(&self, _: i64, pct: f64, stop: f64) -> Option<f64> {
+            portfolio::size_for(self.0, pct, stop)
+        }
+    }
+
+    #[tokio::test]
+    async fn risk_pct_sizes_the_entry_off_equity() {
+        let cfg = TrendParams {
+            symbol: "BTCUSDT".into(),
+            fast: 3,
+            slow: 5,
+            don: 2,
+            qty: 0.1,
+            risk_pct: Some(0.01),
+            mgmt: TradeMgmt::default(),
+        };
+        let mut hist = make(5, 10.0);
+        hist.push(Candle {
+            close: 12.0,
+            high: 12.0,
+            low: 12.0,
+            ..Default::default()
+        });
+
+        let redis = RMock::default();
+        let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+
+        evaluate_core(
+            &hist,
+            &cfg,
+            &redis,
+            &DMock,
+            1,
+            &[],
+            false,
+            &Risk { fail: false },
+            &Equity(1_000.0),
+            &collect(calls.clone()),
+            &mut None,
+            Utc::now(),
+        )
+        .await;
+
+        // 1 % of 1k over the 2.0 drop to the Donchian low
+        assert_eq!(calls.lock().unwrap()[0].qty, 5.0);
+    }
+
     #[tokio::test]
     async fn exit_signal_triggers_sell_and_unsets_flag() {
         let cfg = TrendParams {
@@ -397,6 +680,8 @@ mod tests {
             slow: 5,
             don: 2,
             qty: 0.1,
+            risk_pct: None,
+            mgmt: TradeMgmt::default(),
         };
 
         // start above don_h to mimic open position then drop below don_l
@@ -424,7 +709,10 @@ mod tests {
             &[],
             false,
             &Risk { fail: false },
+            &FixedQty,
             &collect(calls.clone()),
+            &mut None,
+            Utc::now(),
         )
         .await;
 
@@ -440,6 +728,8 @@ mod tests {
             slow: 5,
             don: 2,
             qty: 0.1,
+            risk_pct: None,
+            mgmt: TradeMgmt::default(),
         };
         let hist = make(6, 12.0); // triggers entry
 
@@ -456,7 +746,10 @@ mod tests {
             &[],
             false,
             &Risk { fail: true },
+            &FixedQty,
             &collect(calls.clone()),
+            &mut None,
+            Utc::now(),
         )
         .await;
 
@@ -471,6 +764,8 @@ mod tests {
             slow: 5,
             don: 2,
             qty: 0.1,
+            risk_pct: None,
+            mgmt: TradeMgmt::default(),
         };
         let hist = make(3, 10.0);
 
@@ -487,10 +782,135 @@ mod tests {
             &[],
             false,
             &Risk { fail: false },
+            &FixedQty,
             &collect(calls.clone()),
+            &mut None,
+            Utc::now(),
         )
         .await;
 
         assert!(calls.lock().unwrap().is_empty());
     }
+
+    struct RxMock(std::vec::IntoIter<Candle>);
+    #[async_trait]
+    impl MarketBusSub for RxMock {
+        async fn recv(&mut self) -> Result<Candle, ()> {
+            self.0.next().ok_or(())
+        }
+    }
+
+    /// An hourly bar on 2 Jan 2024
+    fn hour(h: u32, close: f64, high: f64, low: f64) -> Candle {
+        use chrono::TimeZone;
+        Candle {
+            ts: Utc.with_ymd_and_hms(2024, 1, 2, h, 0, 0).unwrap(),
+            close,
+            high,
+            low,
+            ..Default::default()
+        }
+    }
+
+    /// Feeds hourly `bars` after five flat days at 10 and returns the
+    /// orders sent plus the redis mock
+    async fn run_managed(bars: Vec<Candle>, mgmt: TradeMgmt) -> (Vec<Call>, RMock) {
+        let cfg = TrendParams {
+            symbol: "BTCUSDT".into(),
+            fast: 3,
+            slow: 5,
+            don: 2,
+            qty: 0.1,
+            risk_pct: None,
+            mgmt,
+        };
+        let redis = RMock::default();
+        let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+        loop_core(
+            cfg,
+            &redis,
+            &DMock,
+            Box::new(RxMock(bars.into_iter())),
+            1,
+            &[],
+            false,
+            &Risk { fail: false },
+            &FixedQty,
+            &collect(calls.clone()),
+            &|_| {},
+            &mut make(5, 10.0),
+            &Warmup::default(),
+        )
+        .await;
+        let calls = calls.lock().unwrap().clone();
+        (calls, redis)
+    }
+
+    #[tokio::test]
+    async fn a_managed_entry_scales_out_then_stops_at_break_even() {
+        let mgmt = TradeMgmt {
+            break_even_at_r: Some(1.0),
+            partial_tps: vec![PartialTp {
+                at_r: 1.0,
+                fraction: 0.5,
+            }],
+            ..Default::default()
+        };
+        let bars = vec![
+            // the daily close breaks out: long at 12, Donchian low 10 (1 R = 2)
+            hour(0, 12.0, 12.0, 12.0),
+            // +1.25 R intraday: half off and the stop to entry
+            hour(1, 14.0, 14.5, 13.0),
+            // back through entry: the rest goes
+            hour(2, 11.95, 12.5, 11.9),
+        ];
+        let (calls, redis) = run_managed(bars, mgmt).await;
+
+        let sent: Vec<_> = calls.iter().map(|c| (c.side.as_str(), c.qty)).collect();
+        assert_eq!(sent, [("buy", 0.1), ("sell", 0.05), ("sell", 0.05)]);
+        assert_eq!(*redis.pos.lock().unwrap(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn a_stale_entry_is_flattened_after_max_hold_hours() {
+        let mgmt = TradeMgmt {
+            max_hold_hours: Some(2.0),
+            ..Default::default()
+        };
+        let bars = vec![
+            hour(0, 12.0, 12.0, 12.0),
+            hour(1, 12.5, 12.5, 12.2),
+            // two hours after the entry, well above the stop
+            hour(2, 12.5, 12.5, 12.2),
+            hour(3, 12.5, 12.5, 12.2),
+        ];
+        let (calls, redis) = run_managed(bars, mgmt).await;
+
+        let sent: Vec<_> = calls.iter().map(|c| (c.side.as_str(), c.qty)).collect();
+        assert_eq!(sent, [("buy", 0.1), ("sell", 0.1)]);
+        assert_eq!(*redis.pos.lock().unwrap(), Some(false));
+    }
+
+    #[test]
+    fn params_are_validated() {
+        use serde_json::json;
+        let ok = TrendParams::parse(json!({ "symbol": "BTCUSDT" })).unwrap();
+        assert_eq!((ok.fast, ok.slow, ok.don), (20, 100, 55));
+        assert!(matches!(
+            TrendParams::parse(json!({ "fast": 20 })),
+            Err(StrategyError::Fields(_))
+        ));
+        assert!(matches!(
+            TrendParams::parse(json!({ "symbol": "BTCUSDT", "fast": 50, "slow": 20 })),
+            Err(StrategyError::Fields(e)) if e[0].field == "fast"
+        ));
+        assert!(matches!(
+            TrendParams::parse(json!({ "symbol": "BTCUSDT", "qty": -1.0 })),
+            Err(StrategyError::Fields(_))
+        ));
+        assert!(matches!(
+            TrendParams::parse(json!({ "symbol": "BTCUSDT", "risk_pct": 1.5 })),
+            Err(StrategyError::Fields(e)) if e[0].field == "risk_pct"
+        ));
+    }
 }
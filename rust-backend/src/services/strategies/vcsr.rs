@@ -10,7 +10,8 @@
 //! * Risk engine (ATR / LVN driven stops, dynamic sizing)
 //! * Optional strategy enhancements:
 //!     * VWAP −2σ gate
-//!     * Order‑book imbalance confirmation
+//!     * Order‑book imbalance confirmation (rolling, over the recorded
+//!       per-minute depth aggregates – see `depth_history`)
 //!     * Time‑of‑day session filter
 //! * Built‑in walk‑forward & Monte‑Carlo robustness harness (feature‑gated)
 //!
@@ -21,18 +22,43 @@
 //! 3. Provide adapters from your exchange client → [`MarketSnapshot`].
 //! 4. Enable the `robust` cargo feature to compile the back‑test harness.
 
-use crate::db::redis::RedisPool;
-use crate::services::market_data::MarketBus;
-use crate::services::strategies::{Candle, OrderBookSnapshot};
-use crate::services::trading_engine::{execute_trade, Exchange, TradeRequest};
+use crate::db::cache::SharedCache;
+use crate::services::allocation::{self, Sizing};
+use crate::services::market_data::{MarketBus, SignalKind, StrategySignal};
+use crate::services::position_manager::{ExitReason, ManagedPosition, MgmtAction, Side, TradeMgmt};
+use crate::services::replay::DecisionTrace;
+use crate::services::strategies::{
+    buffer::CandleBuffer,
+    common,
+    heartbeat::{CandleFeed, Heartbeat},
+    indicators,
+    indicators::BookFlow,
+    schema::{Field, Kind, ParamSchema},
+    warmup::{Need, Warmup},
+    Candle, StrategyError,
+};
+use crate::services::telemetry::candle_span;
+use crate::services::trading_engine::{self, Exchange, TpSl, TradeRequest};
+use crate::services::{
+    depth_history, drain,
+    portfolio::{self, Portfolio},
+};
 use async_trait::async_trait;
 use chrono::{DateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::PgPool;
 use statrs::statistics::{Data as StatsData, Distribution};
+use std::collections::VecDeque;
 use std::sync::Arc;
+use tracing::Instrument;
 
-#[derive(Debug, Clone, Deserialize)]
+/// Bars in the stop-distance ATR
+const ATR_PERIOD: usize = 14;
+/// Daily bars kept in memory; the rest of the HVN lookback is spilled
+const HOT_DAYS: usize = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VcsrConfig {
     // volume spike
     pub vol_ma_period: usize,
@@ -51,19 +77,24 @@ pub struct VcsrConfig {
 
     // enhancements
     pub vwap_sigma: Option<f64>,
+    /// Minimum bid/ask depth ratio implied by the mean imbalance of the
+    /// last `ob_window` recorded minutes
     pub ob_bid_ask_ratio: Option<f64>,
+    #[serde(default = "default_ob_window")]
+    pub ob_window: usize,
     pub session_filter: Option<Vec<TradingSession>>,
 
     // meta
     pub vwap_window: usize,
+
+    // trade management (break-even / partial TP / max hold), opt-in
+    #[serde(default)]
+    pub mgmt: TradeMgmt,
 }
 
 // -------------------------------------------------------------------------
 // Thin façade traits – give the strategy a seam for mocking
 // -------------------------------------------------------------------------
-type TradeExec =
-    dyn Fn(TradeRequest, &(dyn Db), i64, bool, &[u8]) -> Result<(), String> + Send + Sync;
-
 #[async_trait]
 pub trait Redis: Send + Sync {
     async fn set_eq(&self, key: &str, equity: f64) -> Result<(), ()>; // just as example
@@ -81,14 +112,18 @@ pub trait RiskChecker: Send + Sync {
 
 // ---- prod impls ---------------------------------------------------------
 #[async_trait]
-impl Redis for RedisPool {
+impl Redis for SharedCache {
     async fn set_eq(&self, k: &str, v: f64) -> Result<(), ()> {
-        self.set_json(k, &v, 600).await.map_err(|_| ())
+        (**self).set_json(k, &v, 600).await.map_err(|_| ())
     }
 }
 #[async_trait]
 impl Db for PgPool {}
 
+fn default_ob_window() -> usize {
+    30
+}
+
 impl Default for VcsrConfig {
     fn default() -> Self {
         Self {
@@ -103,12 +138,72 @@ impl Default for VcsrConfig {
             rr_ratio: 2.0,
             vwap_sigma: Some(2.0),
             ob_bid_ask_ratio: Some(1.5),
+            ob_window: default_ob_window(),
             session_filter: Some(vec![TradingSession::AsiaOpen, TradingSession::NyOpen]),
             vwap_window: 390, // ≈ 1-day of 1-min bars
+            mgmt: TradeMgmt::default(),
         }
     }
 }
 
+impl VcsrConfig {
+    /// Checked when params are given – the defaults are known good
+    pub const SCHEMA: ParamSchema = ParamSchema(&[
+        Field::int("vol_ma_period").required().at_least(1.0),
+        Field::num("vol_ma_mult").required().above(0.0),
+        Field::num("vol_zscore").required(),
+        Field::num("vol_percentile")
+            .required()
+            .at_least(0.0)
+            .at_most(1.0),
+        Field::int("hvn_lookback_days").required().at_least(1.0),
+        Field::num("hvn_top_value_area_pct")
+            .required()
+            .above(0.0)
+            .at_most(1.0),
+        Field::num("atr_mult").required().above(0.0),
+        Field::num("risk_per_trade")
+            .required()
+            .above(0.0)
+            .at_most(1.0),
+        Field::num("rr_ratio").required().above(0.0),
+        Field::num("vwap_sigma").above(0.0).nullable(),
+        Field::num("ob_bid_ask_ratio").above(0.0).nullable(),
+        Field::int("ob_window").at_least(0.0),
+        Field::new("session_filter", Kind::Array).nullable(),
+        Field::int("vwap_window").required().at_least(1.0),
+        Field::new("mgmt", Kind::Object),
+    ]);
+
+    /// `null` / `{}` run the defaults; anything else must be a full config
+    pub fn parse(params: serde_json::Value) -> Result<Self, StrategyError> {
+        let cfg = match &params {
+            serde_json::Value::Null => Self::default(),
+            serde_json::Value::Object(m) if m.is_empty() => Self::default(),
+            _ => {
+                Self::SCHEMA.check(&params)?;
+                serde_json::from_value(params)?
+            }
+        };
+        if cfg.ob_bid_ask_ratio.is_some() && cfg.ob_window == 0 {
+            return Err(StrategyError::field(
+                "ob_window",
+                "must be positive with ob_bid_ask_ratio",
+            ));
+        }
+        Ok(cfg)
+    }
+
+    /// Candles needed before the first trade: the HVN map's daily lookback
+    /// and enough 4 h bars for the volume average
+    pub fn warmup(&self) -> Vec<Need> {
+        vec![
+            Need::new("1d", self.hvn_lookback_days),
+            Need::new("4h", self.vol_ma_period + 5),
+        ]
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum TradingSession {
     AsiaOpen,
@@ -136,13 +231,28 @@ pub struct TradeSignal {
 pub struct VcsrStrategy {
     cfg: VcsrConfig,
     hvn_cache: Vec<DemandZone>,
+    rolling: Rolling,
+}
+
+/// Indicator state for bar-by-bar use ([`VcsrStrategy::push`])
+struct Rolling {
+    vwap: indicators::RollingVwap,
+    atr: indicators::RollingAtr,
+    /// Last `max(vol_ma_period, 2)` bars
+    recent: VecDeque<Candle>,
 }
 
 impl VcsrStrategy {
     pub fn new(cfg: VcsrConfig) -> Self {
+        let rolling = Rolling {
+            vwap: indicators::RollingVwap::new(cfg.vwap_window),
+            atr: indicators::RollingAtr::new(ATR_PERIOD),
+            recent: VecDeque::with_capacity(cfg.vol_ma_period.max(2) + 1),
+        };
         Self {
             cfg,
             hvn_cache: vec![],
+            rolling,
         }
     }
 
@@ -150,21 +260,70 @@ impl VcsrStrategy {
         self.hvn_cache = map_hvns(daily, self.cfg.hvn_top_value_area_pct);
     }
 
+    /// The demand zone `c` traded through, if any
+    pub fn zone_at(&self, c: &Candle) -> Option<&DemandZone> {
+        self.hvn_cache
+            .iter()
+            .find(|z| c.low <= z.price && c.high >= z.price)
+    }
+
+    /// Feed the next closed bar to the incremental indicators
+    pub fn push(&mut self, c: Candle) {
+        let r = &mut self.rolling;
+        r.vwap.push(&c);
+        r.atr.push(&c);
+        r.recent.push_back(c);
+        if r.recent.len() > self.cfg.vol_ma_period.max(2) {
+            r.recent.pop_front();
+        }
+    }
+
+    /// [`Self::generate_signal`] for the bars fed through [`Self::push`],
+    /// without rescanning history
+    pub fn signal(&self, book_flow: Option<BookFlow>, equity: f64) -> Option<TradeSignal> {
+        let r = &self.rolling;
+        let recent: Vec<Candle> = r.recent.iter().copied().collect();
+        self.evaluate(
+            &recent,
+            book_flow,
+            equity,
+            || r.vwap.value(),
+            || r.atr.value(),
+        )
+    }
+
     /// Return `Some(signal)` if all filters pass, else `None`.
     pub fn generate_signal(
         &self,
         hist: &[Candle],
-        order_book: Option<OrderBookSnapshot>,
+        book_flow: Option<BookFlow>,
         equity: f64,
     ) -> Option<TradeSignal> {
-        let latest = *hist.last()?;
-        let prev = hist.get(hist.len().wrapping_sub(2)).copied();
+        let recent = &hist[hist.len().saturating_sub(self.cfg.vol_ma_period.max(2))..];
+        self.evaluate(
+            recent,
+            book_flow,
+            equity,
+            || indicators::vwap(hist, self.cfg.vwap_window),
+            || indicators::atr(hist, ATR_PERIOD),
+        )
+    }
+
+    /// The filters on the latest bar of `recent`; indicators are only
+    /// computed once the cheaper filters have passed
+    fn evaluate(
+        &self,
+        recent: &[Candle],
+        book_flow: Option<BookFlow>,
+        equity: f64,
+        vwap: impl FnOnce() -> Option<indicators::Vwap>,
+        atr: impl FnOnce() -> Option<f64>,
+    ) -> Option<TradeSignal> {
+        let latest = *recent.last()?;
+        let prev = recent.get(recent.len().wrapping_sub(2)).copied();
 
         // 1. demand zone
-        let zone = self
-            .hvn_cache
-            .iter()
-            .find(|z| latest.low <= z.price && latest.high >= z.price)?;
+        let zone = self.zone_at(&latest)?;
         // 2. session
         if let Some(sessions) = &self.cfg.session_filter {
             if !sessions.contains(&map_session(latest.ts)) {
@@ -173,14 +332,16 @@ impl VcsrStrategy {
         }
         // 3. VWAP
         if let Some(sig) = self.cfg.vwap_sigma {
-            if let Some(v) = intraday_vwap(hist, self.cfg.vwap_window) {
+            if let Some(v) = vwap() {
                 if latest.close > v.mean - sig * v.std_dev {
                     return None;
                 }
             }
         }
         // 4. volume spike
-        if !volume_spike(&hist[hist.len() - self.cfg.vol_ma_period..], &self.cfg) {
+        if recent.len() < self.cfg.vol_ma_period
+            || !volume_spike(&recent[recent.len() - self.cfg.vol_ma_period..], &self.cfg)
+        {
             return None;
         }
         // 5. PA / flow
@@ -188,17 +349,17 @@ impl VcsrStrategy {
             return None;
         }
         // 6. book imbalance
-        if let (Some(ob), Some(r)) = (order_book, self.cfg.ob_bid_ask_ratio) {
-            if ob.bid_depth / ob.ask_depth < r {
+        if let (Some(flow), Some(r)) = (book_flow, self.cfg.ob_bid_ask_ratio) {
+            if flow.bid_ask_ratio() < r {
                 return None;
             }
         }
 
         // --- risk & sizing -------------------------------------------------
-        let atr = average_true_range(hist, 14)?;
+        let atr = atr()?;
         let stop = (latest.close - self.cfg.atr_mult * atr).min(zone.price - zone.width);
         let risk = latest.close - stop;
-        let size = (equity * self.cfg.risk_per_trade) / risk;
+        let size = portfolio::size_for(equity, self.cfg.risk_per_trade, risk)?;
         let target = latest.close + self.cfg.rr_ratio * risk;
 
         Some(TradeSignal {
@@ -215,50 +376,13 @@ impl VcsrStrategy {
 // ============================================================
 
 fn map_hvns(daily: &[Candle], pct: f64) -> Vec<DemandZone> {
-    let mut vols: Vec<(f64, f64)> = daily
-        .iter()
-        .map(|c| (((c.high + c.low) * 0.5), c.volume))
-        .collect();
-    vols.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-
-    let tot: f64 = vols.iter().map(|v| v.1).sum();
-    let mut acc = 0.0;
-    let mut zones = vec![];
-
-    for (price, v) in vols {
-        acc += v;
-        if acc / tot <= pct {
-            zones.push(DemandZone {
-                price,
-                width: price * 0.002,
-            });
-        } else {
-            break;
-        }
-    }
-    zones
-}
-
-struct Vwap {
-    mean: f64,
-    std_dev: f64,
-}
-fn intraday_vwap(hist: &[Candle], win: usize) -> Option<Vwap> {
-    if hist.len() < win {
-        return None;
-    }
-    let slice = &hist[hist.len() - win..];
-    let (mut pv, mut vol, mut prices) = (0.0, 0.0, Vec::with_capacity(win));
-    for c in slice {
-        pv += c.close * c.volume;
-        vol += c.volume;
-        prices.push(c.close);
-    }
-    let m = pv / vol.max(1e-8);
-    Some(Vwap {
-        mean: m,
-        std_dev: StatsData::new(prices.clone()).std_dev()?,
-    })
+    indicators::high_volume_nodes(daily, pct)
+        .into_iter()
+        .map(|n| DemandZone {
+            price: n.price,
+            width: n.price * 0.002,
+        })
+        .collect()
 }
 
 fn volume_spike(recent: &[Candle], cfg: &VcsrConfig) -> bool {
@@ -305,137 +429,348 @@ fn delta_flip(prev: Option<Candle>, curr: Candle) -> bool {
     false
 }
 
-fn average_true_range(hist: &[Candle], n: usize) -> Option<f64> {
-    if hist.len() <= n {
-        return None;
-    }
-    let mut trs = Vec::with_capacity(n);
-    for w in hist.windows(2).rev().take(n) {
-        let pc = w[0].close;
-        let c = w[1];
-        trs.push(
-            (c.high - c.low)
-                .max((c.high - pc).abs())
-                .max((c.low - pc).abs()),
-        );
-    }
-    Some(trs.iter().sum::<f64>() / n as f64)
+/// The session window `ts` falls in, if any
+pub fn session_at(ts: DateTime<Utc>) -> Option<TradingSession> {
+    match ts.hour() {
+        0..=2 | 23 => Some(TradingSession::AsiaOpen),
+        12..=14 => Some(TradingSession::NyOpen),
+        _ => None,
+    }
 }
 
 fn map_session(ts: DateTime<Utc>) -> TradingSession {
-    match ts.hour() {
-        0..=2 | 23 => TradingSession::AsiaOpen,
-        12..=14 => TradingSession::NyOpen,
-        _ => TradingSession::AsiaOpen,
-    }
+    session_at(ts).unwrap_or(TradingSession::AsiaOpen)
 }
 
 pub async fn loop_forever(
     row: crate::services::scheduler::StrategyRow,
-    redis: RedisPool,
+    cache: SharedCache,
     db: Arc<PgPool>, // HVN cache could be stored later
     bus: MarketBus,
     master_key: Vec<u8>,
     is_demo: bool,
-) {
-    // user-level config or default
-    let cfg: VcsrConfig = serde_json::from_value(row.params).unwrap_or_default();
+    warm: Arc<Warmup>,
+) -> Result<(), StrategyError> {
+    let strategy_id = row.strategy_id;
+    let symbol = row.trade_symbol().to_string();
+    let cfg = VcsrConfig::parse(row.params)?;
 
     let mut engine = VcsrStrategy::new(cfg.clone());
-    let mut daily: Vec<Candle> = Vec::with_capacity(cfg.hvn_lookback_days + 5);
-    let mut hist4h: Vec<Candle> = Vec::with_capacity(600);
+    // the HVN map looks back months; only the newest days stay in memory
+    let mut daily = CandleBuffer::new(HOT_DAYS).with_spill(
+        cache.clone(),
+        format!("vcsr:daily:{strategy_id}"),
+        cfg.hvn_lookback_days.saturating_sub(HOT_DAYS),
+    );
+    daily.clear_spill().await;
+    for d in warm.take("1d") {
+        daily.push(d).await;
+    }
+    engine.refresh_hvn(&daily.history(cfg.hvn_lookback_days).await);
+    for c in warm.take("4h") {
+        engine.push(c);
+    }
 
-    let mut rx = bus.candles_4h.subscribe();
+    let mut rx = CandleFeed::new(
+        bus.candles(&symbol, "4h"),
+        Heartbeat::new(cache.clone(), strategy_id, &symbol, "4h"),
+    );
 
     let user_id = row.user_id;
+    let portfolio =
+        Portfolio::new((*db).clone(), is_demo, master_key.clone()).for_strategy(strategy_id);
+    // a position the run before a restart was still managing
+    let mut open: Option<ManagedPosition> = common::take_over(cache.as_ref(), strategy_id).await;
 
-    while let Ok(c) = rx.recv().await {
+    while let Some(Ok(c)) = drain::or_stop(rx.recv()).await {
         // --- build daily sample for HVN ----
         if daily.last().map(|d| d.ts.date_naive()) != Some(c.ts.date_naive()) {
-            daily.push(c);
-            if daily.len() > cfg.hvn_lookback_days {
-                daily.remove(0);
+            daily.push(c).await;
+            warm.bar("1d");
+            engine.refresh_hvn(&daily.history(cfg.hvn_lookback_days).await);
+        }
+
+        // --- 4-hour indicators ---------------
+        engine.push(c);
+        warm.bar("4h");
+        if let Some(z) = engine.zone_at(&c) {
+            bus.publish_signal(StrategySignal {
+                source: "vcsr",
+                strategy_id,
+                user_id,
+                symbol: symbol.clone(),
+                ts: c.ts,
+                kind: SignalKind::ZoneTouched {
+                    price: z.price,
+                    width: z.width,
+                },
+            });
+        }
+
+        // --- manage the open position -------
+        if let Some(pos) = open.as_mut() {
+            let mut soft_exit = false;
+            for action in pos.on_candle(&c, &cfg.mgmt) {
+                match action {
+                    MgmtAction::MoveStop { from, to } => {
+                        tracing::info!("vcsr {user_id}: stop {from:.2} → {to:.2}")
+                    }
+                    // the exchange already closed it – nothing to send
+                    MgmtAction::Close {
+                        reason: ExitReason::NativeStop,
+                        ..
+                    } => tracing::info!(
+                        "vcsr {user_id}: native stop {:.2} closed the position",
+                        pos.stop
+                    ),
+                    MgmtAction::PartialClose { size, .. } | MgmtAction::Close { size, .. } => {
+                        let trace = DecisionTrace::new().with(
+                            "management",
+                            json!({
+                                "action": format!("{action:?}"),
+                                "close": c.close,
+                                "stop": pos.stop,
+                                "r_multiple": pos.r_multiple(c.close),
+                            }),
+                        );
+                        soft_exit = true;
+                        if let Err(e) = allocation::execute_traced(
+                            &db,
+                            strategy_id,
+                            TradeRequest {
+                                exchange: Exchange::Blowfin,
+                                symbol: pos.symbol.clone(),
+                                side: pos.side.exit_side().into(),
+                                order_type: "market".into(),
+                                price: None,
+                                size,
+                                reduce_only: true,
+                                signal_price: Some(c.close),
+                                tp_sl: None,
+                            },
+                            user_id,
+                            is_demo,
+                            &master_key,
+                            Sizing::AsIs,
+                            trace,
+                        )
+                        .instrument(candle_span("vcsr", &pos.symbol, &c))
+                        .await
+                        {
+                            tracing::error!("vcsr exit error: {e:?}");
+                        }
+                    }
+                }
             }
-            engine.refresh_hvn(&daily);
+            // keep the exchange's stop on the soft one: follow moves, re-place
+            // a stop that went missing, drop it once we closed ourselves
+            let want = (!pos.is_closed()).then_some(pos.stop);
+            if want.is_none() && !soft_exit {
+                open = None;
+                continue;
+            }
+            match trading_engine::sync_stop(
+                &db,
+                user_id,
+                &pos.symbol,
+                pos.side.exit_side(),
+                want,
+                is_demo,
+                &master_key,
+            )
+            .await
+            {
+                Ok(_) => {
+                    if let Some(stop) = want {
+                        pos.attach_native(stop)
+                    }
+                }
+                Err(e) => tracing::warn!("vcsr {user_id}: native stop sync failed: {e}"),
+            }
+            if pos.is_closed() {
+                open = None;
+            }
+            continue;
         }
 
-        // --- 4-hour history buffer ----------
-        hist4h.push(c);
-        if hist4h.len() < cfg.vol_ma_period + 5 {
+        if !warm.is_ready() {
             continue;
         }
 
         // --- generate & execute -------------
-        if let Some(sig) = engine.generate_signal(&hist4h, None, /*equity*/ 100_000.0) {
-            if let Err(e) = crate::services::risk::check_drawdown(&redis, user_id).await {
-                log::warn!("DD limit hit – aborting order: {e}");
-                return;
+        // allocated strategies size off their own sub-account, the rest off
+        // the account's live equity
+        let Some(equity) = portfolio.equity(user_id).await else {
+            tracing::warn!("vcsr {user_id}: account equity unknown – not sizing an entry");
+            continue;
+        };
+        // the book gate reads the recorded minutes; none yet means no gate
+        let flow = match cfg.ob_bid_ask_ratio {
+            Some(_) => depth_history::flow(&db, &symbol, cfg.ob_window)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("vcsr {user_id}: depth history: {e}");
+                    None
+                }),
+            None => None,
+        };
+        if let Some(sig) = engine.signal(flow, equity) {
+            if let Err(e) = crate::services::risk::check_drawdown(cache.as_ref(), user_id).await {
+                tracing::warn!("DD limit hit – aborting order: {e}");
+                return Ok(());
             }
 
-            if let Err(e) = execute_trade(
+            let trace = DecisionTrace::new().with(
+                "signal",
+                json!({
+                    "candle_ts": c.ts,
+                    "entry": sig.entry,
+                    "stop": sig.stop,
+                    "target": sig.target,
+                    "size": sig.size,
+                    "equity": equity,
+                    "book_imbalance": flow.map(|f| f.imbalance),
+                }),
+            );
+            match allocation::execute_traced(
+                &db,
+                strategy_id,
                 TradeRequest {
                     exchange: Exchange::Blowfin,
-                    symbol: "BTCUSDT".into(),
+                    symbol: symbol.clone(),
                     side: "buy".into(),
                     order_type: "market".into(),
                     price: None,
                     size: sig.size,
+                    reduce_only: false,
+                    signal_price: Some(sig.entry),
+                    // rests on the exchange, so the stop survives our downtime
+                    tp_sl: Some(TpSl {
+                        stop_loss: Some(sig.stop),
+                        take_profit: None,
+                    }),
                 },
-                &db,
                 user_id,
                 is_demo,
                 &master_key,
+                Sizing::AsIs,
+                trace,
             )
+            .instrument(candle_span("vcsr", &symbol, &c))
             .await
             {
-                log::error!("vcsr trade error: {e:?}");
+                Ok(_) => {
+                    let mut pos = ManagedPosition::open(
+                        &symbol,
+                        Side::Long,
+                        sig.entry,
+                        sig.stop,
+                        sig.size,
+                        c.ts,
+                    )
+                    .with_target(sig.target);
+                    pos.attach_native(sig.stop);
+                    open = Some(pos);
+                }
+                Err(e) => tracing::error!("vcsr trade error: {e:?}"),
             }
         }
     }
+    if let (true, Some(pos)) = (drain::is_draining(), &open) {
+        common::hand_over(cache.as_ref(), strategy_id, pos).await;
+    }
+    Ok(())
 }
 
 #[cfg(feature = "robust")]
 mod robust {
     use super::*;
-    use rand::prelude::*;
-
-    /// Rolling 2-yr walk-forward + Monte-Carlo slippage
+    use crate::services::backtest::{self, BacktestTrade, Metrics, NewRun};
+    use crate::services::backtest_pool::{self, Progress};
+    use crate::services::fees::FeeSchedule;
+    use rand::Rng;
+
+    /// Rolling 2-yr walk-forward + Monte-Carlo slippage; one run per window,
+    /// ready for `backtest::record`. Windows are evaluated in parallel on the
+    /// backtest pool; window `i` draws its slippage from
+    /// `backtest::derive_seed(seed, i)`, so the same seed gives the same runs.
+    /// Trades pay `fees` – entries at market (taker), exits at the target
+    /// (maker).
     #[allow(dead_code)]
-    pub fn run(history: &[Candle], cfg: &VcsrConfig) {
+    pub fn run(
+        history: &[Candle],
+        cfg: &VcsrConfig,
+        fees: FeeSchedule,
+        seed: u64,
+        progress: Option<&Progress>,
+    ) -> Vec<NewRun> {
         let window = 4_380; // ≈ 2 years of 4-hour bars
-        let mut sharpes = Vec::new();
-        let mut rng = thread_rng();
-
-        for start in (0..history.len().saturating_sub(window)).step_by(window / 4) {
+        let start_equity = 100_000.0;
+        let params = serde_json::to_value(cfg).unwrap_or_default();
+
+        let starts: Vec<usize> = (0..history.len().saturating_sub(window))
+            .step_by(window / 4)
+            .collect();
+        if let Some(p) = progress {
+            p.add_total(starts.len() as u64);
+        }
+        let runs = backtest_pool::par_map(&starts, progress, |i, &start| {
             let slice = &history[start..start + window];
+            let window_seed = backtest::derive_seed(seed, i as u64);
+            let mut rng = backtest::rng(window_seed);
 
             // build daily sample for HVN refresh
             let daily: Vec<Candle> = slice.iter().step_by(6).copied().collect();
             let mut engine = VcsrStrategy::new(cfg.clone());
             engine.refresh_hvn(&daily);
 
-            let mut equity = 100_000.0;
-            let mut curve = vec![equity];
+            let mut equity = start_equity;
+            let mut trades = Vec::new();
 
-            for idx in 30..slice.len() {
-                if let Some(sig) = engine.generate_signal(&slice[..=idx], None, equity) {
+            for (idx, bar) in slice.iter().enumerate() {
+                engine.push(*bar);
+                if idx < 30 {
+                    continue;
+                }
+                if let Some(sig) = engine.signal(None, equity) {
                     let slip = 1.0 + rng.gen_range(-0.0005..0.0005);
-                    let pnl = (sig.target * slip - sig.entry * slip) * sig.size;
+                    let (entry, exit) = (sig.entry * slip, sig.target * slip);
+                    let pnl = (exit - entry) * sig.size
+                        - fees.fee(entry * sig.size, false)
+                        - fees.fee(exit * sig.size, true);
                     equity += pnl;
-                    curve.push(equity);
+                    trades.push(BacktestTrade {
+                        entry_ts: bar.ts,
+                        exit_ts: bar.ts,
+                        side: "long".into(),
+                        entry,
+                        exit,
+                        qty: sig.size,
+                        pnl,
+                    });
                 }
             }
 
-            let rets: Vec<f64> = curve.windows(2).map(|w| (w[1] - w[0]) / w[0]).collect();
-            let stats = StatsData::new(rets.clone());
-            let sd = stats.std_dev().unwrap_or(1e-6).max(1e-6);
-            let mu = stats.mean().unwrap_or(0.0);
-            let sharpe = mu / sd * (252_f64).sqrt();
-            sharpes.push(sharpe);
-        }
+            NewRun {
+                strategy: "vcsr".into(),
+                symbol: "BTCUSDT".into(),
+                interval: "4h".into(),
+                params: params.clone(),
+                data_from: slice[0].ts,
+                data_to: slice[window - 1].ts,
+                start_equity,
+                trades,
+                seed: Some(window_seed),
+            }
+        });
 
-        let avg = StatsData::new(sharpes.clone()).mean().unwrap_or(0.0);
-        println!("ROBUST-TEST   avg Sharpe = {:.2}", avg);
+        let sharpes: Vec<f64> = runs
+            .iter()
+            .map(|r| Metrics::compute(r.start_equity, &r.trades).sharpe)
+            .collect();
+        let avg = StatsData::new(sharpes).mean().unwrap_or(0.0);
+        tracing::info!("ROBUST-TEST   avg Sharpe = {:.2}", avg);
+        runs
     }
 }
 
@@ -478,7 +813,7 @@ mod tests {
     #[test]
     fn vwap_stats() {
         let h = seq(&[1., 2., 3., 4., 5.], 1.0);
-        let v = intraday_vwap(&h, 5).unwrap();
+        let v = indicators::vwap(&h, 5).unwrap();
         assert!((v.mean - 3.0).abs() < 1e-6);
     }
 
@@ -493,9 +828,26 @@ mod tests {
         assert!(volume_spike(&h, &VcsrConfig::default()));
     }
 
+    #[test]
+    fn empty_params_run_defaults_bad_ones_are_reported() {
+        use serde_json::json;
+        assert_eq!(VcsrConfig::parse(json!({})).unwrap().vol_ma_period, 20);
+        assert!(VcsrConfig::parse(serde_json::Value::Null).is_ok());
+        assert!(matches!(
+            VcsrConfig::parse(json!({ "vol_ma_period": 10 })),
+            Err(StrategyError::Fields(_))
+        ));
+        let mut bad = serde_json::to_value(VcsrConfig::default()).unwrap();
+        bad["risk_per_trade"] = json!(5.0);
+        assert!(matches!(
+            VcsrConfig::parse(bad),
+            Err(StrategyError::Fields(e)) if e[0].field == "risk_per_trade"
+        ));
+    }
+
     #[test]
     fn atr_len_guard() {
-        assert!(average_true_range(&[], 14).is_none());
+        assert!(indicators::atr(&[], 14).is_none());
     }
 
     //------------------------------------------------------------------
@@ -518,29 +870,12 @@ mod tests {
     #[async_trait]
     impl Db for DMock {}
 
-    struct Risk {
-        block: bool,
-    }
-    impl RiskChecker for Risk {
-        fn check_drawdown(&self, _: i64) -> Result<(), String> {
-            if self.block {
-                Err("dd".into())
-            } else {
-                Ok(())
-            }
-        }
-    }
-
-    #[derive(Clone)]
-    struct Call {
-        side: String,
-    }
+    /// Records the side of every order placed
     fn collect(
-        out: Arc<Mutex<Vec<Call>>>,
-    ) -> impl Fn(TradeRequest, &(dyn Db), i64, bool, &[u8]) -> Result<(), String> + Send + Sync
-    {
+        out: Arc<Mutex<Vec<String>>>,
+    ) -> impl Fn(TradeRequest, &dyn Db, i64, bool, &[u8]) -> Result<(), String> + Send + Sync {
         move |req, _, _, _, _| {
-            out.lock().unwrap().push(Call { side: req.side });
+            out.lock().unwrap().push(req.side);
             Ok(())
         }
     }
@@ -577,6 +912,81 @@ mod tests {
         assert!(eng.generate_signal(&h, None, 10_000.).is_some());
     }
 
+    #[test]
+    fn book_gate_uses_the_rolling_flow() {
+        let mut eng = VcsrStrategy::new(VcsrConfig {
+            ob_bid_ask_ratio: Some(1.5),
+            ..base_cfg()
+        });
+        eng.hvn_cache = vec![DemandZone {
+            price: 10.0,
+            width: 0.05,
+        }];
+        let mut h = seq(&[10.; 25], 200.);
+        h.last_mut().unwrap().volume = 1_000.;
+        h.last_mut().unwrap().delta = Some(100.);
+        let pen_idx = h.len() - 2;
+        h[pen_idx].delta = Some(-100.);
+
+        let flow = |imbalance| BookFlow {
+            imbalance,
+            bid_slope: None,
+            ask_slope: None,
+        };
+        // balanced book: ratio 1 < 1.5
+        assert!(eng.generate_signal(&h, Some(flow(0.0)), 10_000.).is_none());
+        // bids twice the asks: ratio 2
+        assert!(eng
+            .generate_signal(&h, Some(flow(1.0 / 3.0)), 10_000.)
+            .is_some());
+        // nothing recorded yet: no gate
+        assert!(eng.generate_signal(&h, None, 10_000.).is_some());
+    }
+
+    #[test]
+    fn incremental_signals_match_the_slice_path() {
+        let mut cfg = VcsrConfig {
+            ob_bid_ask_ratio: None,
+            session_filter: None,
+            vwap_window: 30,
+            ..Default::default()
+        };
+        cfg.vwap_sigma = Some(0.5);
+        let mut h = seq(&[10.; 80], 200.);
+        for (i, c) in h.iter_mut().enumerate() {
+            c.close = 10.0 - (i % 7) as f64 * 0.3;
+            // a high-volume hammer into the zone every 21 bars
+            if i % 21 == 0 && i > 0 {
+                *c = Candle {
+                    open: 8.2,
+                    close: 8.0,
+                    low: 7.0,
+                    high: 9.5,
+                    volume: 5_000.,
+                    ..*c
+                };
+            }
+        }
+        let mut eng = VcsrStrategy::new(cfg);
+        eng.hvn_cache = vec![DemandZone {
+            price: 9.0,
+            width: 0.05,
+        }];
+
+        let mut fired = 0;
+        for i in 0..h.len() {
+            eng.push(h[i]);
+            let inc = eng.signal(None, 10_000.);
+            let full = eng.generate_signal(&h[..=i], None, 10_000.);
+            assert_eq!(inc.is_some(), full.is_some(), "bar {i}");
+            if let (Some(a), Some(b)) = (inc, full) {
+                fired += 1;
+                assert!((a.stop - b.stop).abs() < 1e-9 && (a.size - b.size).abs() < 1e-6);
+            }
+        }
+        assert!(fired > 0, "the series should trigger at least once");
+    }
+
     #[tokio::test]
     async fn volume_filter_blocks() {
         let eng = VcsrStrategy::new(base_cfg());
@@ -587,7 +997,7 @@ mod tests {
 
     #[tokio::test]
     async fn risk_block_prevents_exec() {
-        let trade_log = Arc::new(Mutex::new(Vec::<Call>::new()));
+        let trade_log = Arc::new(Mutex::new(Vec::<String>::new()));
         let hist = seq(&[10.; 25], 1_000.);
         let mut eng = VcsrStrategy::new(base_cfg());
         eng.hvn_cache = vec![DemandZone {
@@ -606,6 +1016,9 @@ mod tests {
                     order_type: String::new(),
                     price: None,
                     size: 0.0,
+                    reduce_only: false,
+                    signal_price: None,
+                    tp_sl: None,
                 },
                 &DMock,
                 1,
@@ -634,6 +1047,16 @@ mod tests {
     #[test]
     fn robust_runs() {
         let hist = seq(&[10.; 4_400], 200.); // 2y-ish of 4-h bars
-        robust::run(&hist, &VcsrConfig::default());
+        let fees = crate::services::fees::FeeSchedule::default();
+        let runs = robust::run(&hist, &VcsrConfig::default(), fees, 7, None);
+        assert!(!runs.is_empty());
+        let again = robust::run(&hist, &VcsrConfig::default(), fees, 7, None);
+        assert!(runs
+            .iter()
+            .zip(&again)
+            .all(|(a, b)| a.seed == b.seed && a.trades == b.trades));
+        assert!(runs
+            .iter()
+            .all(|r| r.strategy == "vcsr" && r.params.is_object()));
     }
 }
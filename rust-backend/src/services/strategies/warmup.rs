@@ -0,0 +1,322 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Warmup data requirements
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every strategy declares the candles it needs before it may trade as a
+//!   list of [`Need`]s (VCSR: `hvn_lookback_days` daily + `vol_ma_period + 5`
+//!   4 h bars); `scheduler::warmup_needs` maps a strategy row to them
+//! * Before a task starts the scheduler [`bootstrap`](Warmup::bootstrap)s
+//!   them from the recorded `candles` of the strategy's symbol – daily bars that were never stored are
+//!   rolled up from complete 4 h days, and whatever is still short comes from
+//!   the configured `history` provider – and the strategy seeds its buffers
+//!   with [`take`](Warmup::take)
+//! * Live bars count towards whatever history was missing; until every need
+//!   is met the scheduler publishes a [`WarmupView`] ("warming up (x/y
+//!   candles)") under `warmup:{strategy_id}` for the status API
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::services::{
+    candle_integrity, candle_retention::interval_secs, history, market_data::bus_symbol,
+    strategies::Candle,
+};
+
+/// Finest interval missing coarser bars are rolled up from
+const ROLLUP_FROM: &str = "4h";
+
+/// `bars` candles of `interval` (`1h`, `4h`, `1d`, …)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Need {
+    pub interval: &'static str,
+    pub bars: usize,
+}
+
+impl Need {
+    pub fn new(interval: &'static str, bars: usize) -> Self {
+        Self { interval, bars }
+    }
+}
+
+/// Warmup progress of one strategy task, shared between the task and the
+/// scheduler
+#[derive(Debug, Default)]
+pub struct Warmup {
+    needs: Vec<Need>,
+    have: Vec<AtomicUsize>,
+    history: Mutex<HashMap<&'static str, Vec<Candle>>>,
+}
+
+impl Warmup {
+    pub fn new(needs: Vec<Need>) -> Self {
+        let have = needs.iter().map(|_| AtomicUsize::new(0)).collect();
+        Self {
+            needs,
+            have,
+            history: Mutex::default(),
+        }
+    }
+
+    pub fn needs(&self) -> &[Need] {
+        &self.needs
+    }
+
+    /// Count one live bar of `interval`
+    pub fn bar(&self, interval: &str) {
+        for (n, h) in self.needs.iter().zip(&self.have) {
+            if n.interval == interval {
+                h.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.needs
+            .iter()
+            .zip(&self.have)
+            .all(|(n, h)| h.load(Ordering::Relaxed) >= n.bars)
+    }
+
+    /// Bootstrapped bars of `interval`, oldest first; empty after the first
+    /// call
+    pub fn take(&self, interval: &str) -> Vec<Candle> {
+        self.history
+            .lock()
+            .expect("warmup history")
+            .remove(interval)
+            .unwrap_or_default()
+    }
+
+    /// Load every need for `symbol` from `candles`, topping up from the
+    /// history provider; a failed load is logged and left to the live feed
+    pub async fn bootstrap(&self, db: &PgPool, symbol: &str) {
+        let symbol = bus_symbol(symbol);
+        for (n, h) in self.needs.iter().zip(&self.have) {
+            let mut bars = match load(db, &symbol, n.interval, n.bars).await {
+                Ok(bars) => bars,
+                Err(e) => {
+                    tracing::warn!("warmup: loading {} {} bars: {e}", n.bars, n.interval);
+                    vec![]
+                }
+            };
+            if let Some(p) = history::provider().filter(|_| bars.len() < n.bars) {
+                match history::recent(p.as_ref(), &symbol, n.interval, n.bars).await {
+                    Ok(more) if more.len() > bars.len() => {
+                        // keep them, labelled, so the next bootstrap finds them
+                        if let Err(e) =
+                            candle_integrity::store(db, &symbol, n.interval, p.name(), &more).await
+                        {
+                            tracing::warn!("warmup: storing {} history: {e}", p.name());
+                        }
+                        bars = more;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("warmup: {} history for {}: {e}", p.name(), n.interval)
+                    }
+                }
+            }
+            if bars.is_empty() {
+                continue;
+            }
+            h.fetch_add(bars.len(), Ordering::Relaxed);
+            self.history
+                .lock()
+                .expect("warmup history")
+                .insert(n.interval, bars);
+        }
+    }
+
+    pub fn view(&self) -> WarmupView {
+        let intervals: Vec<IntervalProgress> = self
+            .needs
+            .iter()
+            .zip(&self.have)
+            .map(|(n, h)| IntervalProgress {
+                interval: n.interval.to_string(),
+                have: h.load(Ordering::Relaxed).min(n.bars),
+                need: n.bars,
+            })
+            .collect();
+        let have = intervals.iter().map(|i| i.have).sum();
+        let need = intervals.iter().map(|i| i.need).sum();
+        let ready = have == need;
+        WarmupView {
+            ready,
+            have,
+            need,
+            message: if ready {
+                "ready".into()
+            } else {
+                format!("warming up ({have}/{need} candles)")
+            },
+            intervals,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntervalProgress {
+    pub interval: String,
+    pub have: usize,
+    pub need: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WarmupView {
+    pub ready: bool,
+    /// Bars on hand, each interval capped at what it needs
+    pub have: usize,
+    pub need: usize,
+    pub message: String,
+    pub intervals: Vec<IntervalProgress>,
+}
+
+#[derive(FromRow)]
+struct Bar {
+    ts: DateTime<Utc>,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+}
+
+impl From<Bar> for Candle {
+    fn from(b: Bar) -> Self {
+        Candle {
+            ts: b.ts,
+            open: b.open,
+            high: b.high,
+            low: b.low,
+            close: b.close,
+            volume: b.volume,
+            delta: None,
+        }
+    }
+}
+
+/// Newest `n` recorded bars of `symbol` (as the bus spells it), oldest
+/// first. Coarser intervals that are short in `candles` are rolled up from
+/// `ROLLUP_FROM`
+pub async fn load(
+    db: &PgPool,
+    symbol: &str,
+    interval: &str,
+    n: usize,
+) -> Result<Vec<Candle>, sqlx::Error> {
+    if n == 0 {
+        return Ok(vec![]);
+    }
+    let mut bars = sqlx::query_as::<_, Bar>(
+        r#"
+        SELECT ts, open, high, low, close, volume
+          FROM candles
+         WHERE symbol = $1 AND interval = $2
+         ORDER BY ts DESC
+         LIMIT $3
+        "#,
+    )
+    .bind(symbol)
+    .bind(interval)
+    .bind(n as i64)
+    .fetch_all(db)
+    .await?;
+
+    if let Some((step, per_bar)) = rollup_step(interval).filter(|_| bars.len() < n) {
+        let rolled = sqlx::query_as::<_, Bar>(
+            r#"
+            SELECT bucket AS ts,
+                   (array_agg(open ORDER BY ts))[1] AS open, max(high) AS high,
+                   min(low) AS low, (array_agg(close ORDER BY ts DESC))[1] AS close,
+                   sum(volume) AS volume
+              FROM (SELECT *,
+                           date_bin(make_interval(secs => $3),
+                                    ts - interval '1 microsecond',
+                                    TIMESTAMPTZ 'epoch')
+                             + make_interval(secs => $3) AS bucket
+                      FROM candles
+                     WHERE symbol = $1 AND interval = $2) c
+             GROUP BY bucket
+            HAVING count(*) = $4
+             ORDER BY bucket DESC
+             LIMIT $5
+            "#,
+        )
+        .bind(symbol)
+        .bind(ROLLUP_FROM)
+        .bind(step as f64)
+        .bind(per_bar)
+        .bind(n as i64)
+        .fetch_all(db)
+        .await?;
+        if rolled.len() > bars.len() {
+            bars = rolled;
+        }
+    }
+
+    bars.reverse();
+    Ok(bars.into_iter().map(Candle::from).collect())
+}
+
+/// Bar length of `interval` and how many `ROLLUP_FROM` bars make one, when
+/// it can be rolled up
+fn rollup_step(interval: &str) -> Option<(i64, i64)> {
+    let step = interval_secs(interval)?;
+    let from = interval_secs(ROLLUP_FROM)?;
+    (step > from && step % from == 0).then_some((step, step / from))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_counts_bootstrapped_and_live_bars() {
+        let w = Warmup::new(vec![Need::new("1d", 3), Need::new("4h", 2)]);
+        assert_eq!(w.view().message, "warming up (0/5 candles)");
+        w.have[0].fetch_add(2, Ordering::Relaxed); // as bootstrap would
+        w.bar("4h");
+        w.bar("4h");
+        w.bar("4h");
+        w.bar("1h");
+        let v = w.view();
+        assert!(!w.is_ready() && !v.ready);
+        assert_eq!((v.have, v.need), (4, 5), "4h is capped at what it needs");
+        assert_eq!(v.message, "warming up (4/5 candles)");
+
+        w.bar("1d");
+        assert!(w.is_ready());
+        assert_eq!(w.view().message, "ready");
+        assert!(Warmup::new(vec![]).is_ready());
+    }
+
+    #[test]
+    fn history_is_taken_once() {
+        let w = Warmup::new(vec![Need::new("4h", 1)]);
+        w.history
+            .lock()
+            .unwrap()
+            .insert("4h", vec![Candle::default()]);
+        assert_eq!(w.take("4h").len(), 1);
+        assert!(w.take("4h").is_empty());
+    }
+
+    #[test]
+    fn only_multiples_of_4h_roll_up() {
+        assert_eq!(rollup_step("1d"), Some((86_400, 6)));
+        assert_eq!(rollup_step("1w"), Some((604_800, 42)));
+        assert_eq!(rollup_step("4h"), None);
+        assert_eq!(rollup_step("1h"), None);
+        assert_eq!(rollup_step("6h"), None);
+    }
+}
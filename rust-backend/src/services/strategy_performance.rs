@@ -0,0 +1,333 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Strategy performance – PnL, win rate and equity curve off the fills
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every order a strategy placed is one trade candidate; those whose
+//!   fills realised PnL are its closed trades, the profitable ones its wins
+//! * Aggregates are per UTC day. Complete days are rolled up once a day by
+//!   the scheduler (whichever instance gets there first) and cached under
+//!   `perf:strategy:{id}`; a request adds the days since from Postgres, so
+//!   today's trades show without waiting for the next rollup
+//! * The equity curve is the allocated capital (0 without an allocation)
+//!   plus the realised PnL to date at each day's close; unrealised PnL is
+//!   the live mark from `strategy_pnl`
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{db::cache::Cache, services::strategy_pnl};
+
+/// A rollup outlives one missed run
+const ROLLUP_TTL_SECS: u64 = 2 * 24 * 3600;
+
+/// The day the last rollup on this instance was for
+static ROLLED_UP: Mutex<Option<NaiveDate>> = Mutex::new(None);
+
+/// One strategy's trading on one UTC day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DayRow {
+    pub strategy_id: Uuid,
+    pub day: DateTime<Utc>,
+    pub orders: i64,
+    /// Orders that realised PnL
+    pub trades: i64,
+    pub wins: i64,
+    pub realized_pnl: f64,
+    pub fees: f64,
+}
+
+/// Complete days of one strategy, up to `through` (exclusive)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Rollup {
+    through: DateTime<Utc>,
+    days: Vec<DayRow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EquityPoint {
+    pub day: NaiveDate,
+    /// Realised that day
+    pub pnl: f64,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Performance {
+    pub strategy_id: Uuid,
+    pub realized_pnl: f64,
+    /// `None` while the open position has no live price
+    pub unrealized_pnl: Option<f64>,
+    pub fees: f64,
+    pub orders: i64,
+    pub trades: i64,
+    pub wins: i64,
+    /// `None` before the first closed trade
+    pub win_rate: Option<f64>,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+fn cache_key(strategy_id: Uuid) -> String {
+    format!("perf:strategy:{strategy_id}")
+}
+
+fn day_start(t: DateTime<Utc>) -> DateTime<Utc> {
+    t.date_naive()
+        .and_hms_opt(0, 0, 0)
+        .map(|d| d.and_utc())
+        .unwrap_or(t)
+}
+
+/// Fold day rows (oldest first) into totals and the equity curve
+pub fn summarise(
+    strategy_id: Uuid,
+    capital: f64,
+    days: &[DayRow],
+    unrealized_pnl: Option<f64>,
+) -> Performance {
+    let mut perf = Performance {
+        strategy_id,
+        realized_pnl: 0.0,
+        unrealized_pnl,
+        fees: 0.0,
+        orders: 0,
+        trades: 0,
+        wins: 0,
+        win_rate: None,
+        equity_curve: Vec::with_capacity(days.len()),
+    };
+    for d in days {
+        perf.realized_pnl += d.realized_pnl;
+        perf.fees += d.fees;
+        perf.orders += d.orders;
+        perf.trades += d.trades;
+        perf.wins += d.wins;
+        let day = d.day.date_naive();
+        match perf.equity_curve.last_mut() {
+            Some(last) if last.day == day => {
+                last.pnl += d.realized_pnl;
+                last.equity += d.realized_pnl;
+            }
+            _ => perf.equity_curve.push(EquityPoint {
+                day,
+                pnl: d.realized_pnl,
+                equity: capital + perf.realized_pnl,
+            }),
+        }
+    }
+    if perf.trades > 0 {
+        perf.win_rate = Some(perf.wins as f64 / perf.trades as f64);
+    }
+    perf
+}
+
+/// Per-day aggregates with trades in `[from, to)`; every strategy when
+/// `strategy_id` is `None`. A trade counts on the day of its first fill.
+async fn days(
+    db: &PgPool,
+    strategy_id: Option<Uuid>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<DayRow>, sqlx::Error> {
+    sqlx::query_as::<_, DayRow>(
+        r#"
+        WITH per_order AS (
+            SELECT o.strategy_id, o.order_id,
+                   MIN(f.executed_at)                                       AS at,
+                   SUM(f.realised_pnl)                                      AS pnl,
+                   SUM(COALESCE(f.trade_fee, 0) + COALESCE(f.funding_fee, 0)) AS fees
+              FROM orders o
+              JOIN fills f ON f.order_id = o.order_id
+             WHERE o.strategy_id IS NOT NULL
+               AND ($1::uuid IS NULL OR o.strategy_id = $1)
+             GROUP BY o.strategy_id, o.order_id
+        )
+        SELECT strategy_id,
+               date_trunc('day', at AT TIME ZONE 'UTC')
+                   AT TIME ZONE 'UTC'                      AS day,
+               COUNT(*)                                    AS orders,
+               COUNT(*) FILTER (WHERE pnl <> 0)            AS trades,
+               COUNT(*) FILTER (WHERE pnl > 0)             AS wins,
+               COALESCE(SUM(pnl), 0)::float8               AS realized_pnl,
+               COALESCE(SUM(fees), 0)::float8              AS fees
+          FROM per_order
+         WHERE ($2::timestamptz IS NULL OR at >= $2)
+           AND ($3::timestamptz IS NULL OR at <  $3)
+         GROUP BY 1, 2
+         ORDER BY 1, 2
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(db)
+    .await
+}
+
+async fn store(cache: &dyn Cache, strategy_id: Uuid, rollup: &Rollup) {
+    if let Err(e) = cache
+        .set_json(&cache_key(strategy_id), rollup, ROLLUP_TTL_SECS)
+        .await
+    {
+        tracing::warn!("strategy performance {strategy_id}: cache write: {e}");
+    }
+}
+
+/// Performance of one of `user_id`'s strategies; `None` when it isn't theirs
+pub async fn get(
+    db: &PgPool,
+    cache: &dyn Cache,
+    user_id: i64,
+    strategy_id: Uuid,
+) -> Result<Option<Performance>, sqlx::Error> {
+    let capital: Option<Option<f64>> = sqlx::query_scalar(
+        r#"
+        SELECT allocated_capital::float8
+          FROM user_strategies
+         WHERE strategy_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    let Some(capital) = capital else {
+        return Ok(None);
+    };
+
+    let today = day_start(Utc::now());
+    let cached = match cache.get_json::<Rollup>(&cache_key(strategy_id)).await {
+        Ok(hit) => hit,
+        Err(e) => {
+            tracing::warn!("strategy performance {strategy_id}: cache read: {e}");
+            None
+        }
+    };
+    let mut rollup = match cached {
+        Some(r) => r,
+        None => {
+            let r = Rollup {
+                through: today,
+                days: days(db, Some(strategy_id), None, Some(today)).await?,
+            };
+            store(cache, strategy_id, &r).await;
+            r
+        }
+    };
+    // whatever the rollup hasn't seen yet
+    let recent = days(db, Some(strategy_id), Some(rollup.through), None).await?;
+    rollup.days.extend(recent);
+
+    let unrealized = strategy_pnl::get(db, cache, strategy_id)
+        .await?
+        .and_then(|p| p.unrealized_pnl);
+    Ok(Some(summarise(
+        strategy_id,
+        capital.unwrap_or(0.0),
+        &rollup.days,
+        unrealized,
+    )))
+}
+
+/// Roll up every strategy's complete days, once per UTC day across all
+/// instances; run from the scheduler's reconcile. Returns the strategies
+/// rolled up, 0 when it wasn't due.
+pub async fn rollup_if_due(
+    db: &PgPool,
+    cache: &dyn Cache,
+    now: DateTime<Utc>,
+) -> Result<usize, sqlx::Error> {
+    let today = day_start(now);
+    if *ROLLED_UP.lock().unwrap() == Some(today.date_naive()) {
+        return Ok(0);
+    }
+    let claim = format!("perf:rollup:{}", today.date_naive());
+    match cache.set_nx(&claim, "1", ROLLUP_TTL_SECS).await {
+        Ok(true) => {}
+        // another instance has it
+        Ok(false) => {
+            *ROLLED_UP.lock().unwrap() = Some(today.date_naive());
+            return Ok(0);
+        }
+        // try again next pass
+        Err(e) => {
+            tracing::warn!("strategy performance: rollup claim: {e}");
+            return Ok(0);
+        }
+    }
+
+    let rows = match days(db, None, None, Some(today)).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            // let the next pass (on any instance) retry
+            let _ = cache.del(&claim).await;
+            return Err(e);
+        }
+    };
+    let mut per_strategy: BTreeMap<Uuid, Vec<DayRow>> = BTreeMap::new();
+    for row in rows {
+        per_strategy.entry(row.strategy_id).or_default().push(row);
+    }
+    for (strategy_id, days) in &per_strategy {
+        let rollup = Rollup {
+            through: today,
+            days: days.clone(),
+        };
+        store(cache, *strategy_id, &rollup).await;
+    }
+    *ROLLED_UP.lock().unwrap() = Some(today.date_naive());
+    Ok(per_strategy.len())
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(d: u32, trades: i64, wins: i64, pnl: f64) -> DayRow {
+        DayRow {
+            strategy_id: Uuid::nil(),
+            day: Utc.with_ymd_and_hms(2024, 3, d, 0, 0, 0).unwrap(),
+            orders: trades * 2,
+            trades,
+            wins,
+            realized_pnl: pnl,
+            fees: 1.0,
+        }
+    }
+
+    #[test]
+    fn totals_win_rate_and_curve() {
+        let days = [day(1, 2, 1, 50.0), day(2, 2, 2, 30.0), day(4, 1, 0, -20.0)];
+        let p = summarise(Uuid::nil(), 1_000.0, &days, Some(5.0));
+        assert_eq!(p.realized_pnl, 60.0);
+        assert_eq!(p.fees, 3.0);
+        assert_eq!((p.orders, p.trades, p.wins), (10, 5, 3));
+        assert_eq!(p.win_rate, Some(0.6));
+        let curve: Vec<f64> = p.equity_curve.iter().map(|e| e.equity).collect();
+        assert_eq!(curve, [1_050.0, 1_080.0, 1_060.0]);
+        assert_eq!(p.unrealized_pnl, Some(5.0));
+    }
+
+    #[test]
+    fn no_closed_trades_means_no_win_rate() {
+        let p = summarise(Uuid::nil(), 0.0, &[day(1, 0, 0, 0.0)], None);
+        assert_eq!(p.win_rate, None);
+        assert_eq!(p.equity_curve.len(), 1);
+
+        // a day split between the rollup and the live query is one point
+        let split = [day(1, 1, 1, 10.0), day(1, 1, 0, -4.0)];
+        let p = summarise(Uuid::nil(), 0.0, &split, None);
+        assert_eq!(p.equity_curve.len(), 1);
+        assert_eq!(p.equity_curve[0].pnl, 6.0);
+        assert_eq!(p.equity_curve[0].equity, 6.0);
+    }
+}
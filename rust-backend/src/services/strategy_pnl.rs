@@ -0,0 +1,270 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Live per-strategy PnL
+//! ──────────────────────────────────────────────────────────────────────────
+//! * realised   = Σ `strategy_pnl` (fills attributed by `allocation`)
+//! * unrealised = the strategy's own net position (`strategy_positions`)
+//!   marked to the feed's mark price, else the latest MarketBus close,
+//!   else the watchlist mid
+//! * Snapshots are cached under `pnl:strategy:{id}` and rewritten after
+//!   every attributed fill and, at most every `MARK_REFRESH` per symbol, on
+//!   every hourly bus candle for strategies holding that symbol – polling is
+//!   a cache read
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::db::cache::{Cache, SharedCache};
+use crate::services::{
+    analytics,
+    market_data::{self, bus_symbol, MarketBus},
+};
+
+/// Re-mark open positions at most this often off the candle stream
+const MARK_REFRESH: Duration = Duration::from_secs(5);
+/// Cached snapshots outlive a quiet market (no fills, feed down) this long
+const CACHE_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyPnl {
+    pub strategy_id: Uuid,
+    pub symbol: String,
+    pub realized_pnl: f64,
+    /// Signed: positive long, negative short
+    pub position_qty: f64,
+    pub avg_price: f64,
+    /// `None` while no live price is known for the symbol
+    pub mark_price: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+    pub total_pnl: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct PnlRow {
+    strategy_id: Uuid,
+    symbol: String,
+    realized_pnl: f64,
+    qty: f64,
+    avg_price: f64,
+}
+
+impl PnlRow {
+    fn mark(self, mark_price: Option<f64>, now: DateTime<Utc>) -> StrategyPnl {
+        let unrealized_pnl = if self.qty == 0.0 {
+            Some(0.0)
+        } else {
+            mark_price.map(|px| self.qty * (px - self.avg_price))
+        };
+        StrategyPnl {
+            strategy_id: self.strategy_id,
+            symbol: self.symbol,
+            realized_pnl: self.realized_pnl,
+            position_qty: self.qty,
+            avg_price: self.avg_price,
+            mark_price,
+            unrealized_pnl,
+            total_pnl: self.realized_pnl + unrealized_pnl.unwrap_or(0.0),
+            updated_at: now,
+        }
+    }
+}
+
+/// Latest bus close per normalised symbol
+static BUS_MARKS: Lazy<DashMap<String, f64>> = Lazy::new(DashMap::new);
+
+pub fn mark_price(symbol: &str) -> Option<f64> {
+    market_data::mark_price(symbol)
+        .map(|m| m.mark)
+        .or_else(|| BUS_MARKS.get(&bus_symbol(symbol)).map(|p| *p))
+        .or_else(|| analytics::mid_for(symbol))
+}
+
+fn cache_key(strategy_id: Uuid) -> String {
+    format!("pnl:strategy:{strategy_id}")
+}
+
+const SELECT: &str = r#"
+    SELECT s.strategy_id, s.symbol,
+           COALESCE((SELECT SUM(pnl) FROM strategy_pnl p
+                      WHERE p.strategy_id = s.strategy_id), 0)::float8 AS realized_pnl,
+           COALESCE(pos.qty, 0)::float8       AS qty,
+           COALESCE(pos.avg_price, 0)::float8 AS avg_price
+      FROM user_strategies s
+      LEFT JOIN strategy_positions pos ON pos.strategy_id = s.strategy_id
+"#;
+
+/// Recompute from Postgres and cache; `None` for unknown strategies
+pub async fn refresh(
+    db: &PgPool,
+    cache: &dyn Cache,
+    strategy_id: Uuid,
+) -> Result<Option<StrategyPnl>, sqlx::Error> {
+    let row = sqlx::query_as::<_, PnlRow>(&format!("{SELECT} WHERE s.strategy_id = $1"))
+        .bind(strategy_id)
+        .fetch_optional(db)
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let mark = mark_price(&row.symbol);
+    let pnl = row.mark(mark, Utc::now());
+    store(cache, &pnl).await;
+    Ok(Some(pnl))
+}
+
+async fn store(cache: &dyn Cache, pnl: &StrategyPnl) {
+    if let Err(e) = cache
+        .set_json(&cache_key(pnl.strategy_id), pnl, CACHE_TTL_SECS)
+        .await
+    {
+        tracing::warn!("strategy pnl {}: cache write: {e}", pnl.strategy_id);
+    }
+}
+
+/// Cached snapshot, computed on a miss
+pub async fn get(
+    db: &PgPool,
+    cache: &dyn Cache,
+    strategy_id: Uuid,
+) -> Result<Option<StrategyPnl>, sqlx::Error> {
+    match cache.get_json::<StrategyPnl>(&cache_key(strategy_id)).await {
+        Ok(Some(hit)) => return Ok(Some(hit)),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("strategy pnl {strategy_id}: cache read: {e}"),
+    }
+    refresh(db, cache, strategy_id).await
+}
+
+/// Re-mark every strategy with an open position in `symbol`
+async fn remark_symbol(db: &PgPool, cache: &dyn Cache, symbol: &str) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query_as::<_, PnlRow>(&format!(
+        "{SELECT} WHERE pos.qty <> 0 AND upper(replace(s.symbol, '-', '')) LIKE $1 || '%'"
+    ))
+    .bind(bus_symbol(symbol))
+    .fetch_all(db)
+    .await?;
+    let now = Utc::now();
+    for row in rows {
+        let pnl = row.mark(mark_price(symbol), now);
+        store(cache, &pnl).await;
+    }
+    Ok(())
+}
+
+struct Refresher {
+    db: PgPool,
+    cache: SharedCache,
+    rt: tokio::runtime::Handle,
+}
+
+static REFRESHER: OnceCell<Refresher> = OnceCell::new();
+
+/// Start re-marking off the bus and enable [`refresh_soon`]
+pub fn init(db: PgPool, cache: SharedCache, bus: Arc<MarketBus>) {
+    let refresher = Refresher {
+        db: db.clone(),
+        cache: cache.clone(),
+        rt: tokio::runtime::Handle::current(),
+    };
+    if REFRESHER.set(refresher).is_err() {
+        return;
+    }
+    let mut rx = bus.all_candles();
+    tokio::spawn(async move {
+        let mut last: HashMap<String, Instant> = HashMap::new();
+        loop {
+            match rx.recv().await {
+                Ok(bc) if bc.interval == "1h" => {
+                    BUS_MARKS.insert(bc.symbol.clone(), bc.candle.close);
+                    if last
+                        .get(&bc.symbol)
+                        .is_some_and(|t| t.elapsed() < MARK_REFRESH)
+                    {
+                        continue;
+                    }
+                    last.insert(bc.symbol.clone(), Instant::now());
+                    if let Err(e) = remark_symbol(&db, cache.as_ref(), &bc.symbol).await {
+                        tracing::warn!("strategy pnl: re-mark {}: {e}", bc.symbol);
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
+
+/// Recompute `strategy_id` in the background (after a fill); a no-op before
+/// [`init`]
+pub fn refresh_soon(strategy_id: Uuid) {
+    let Some(r) = REFRESHER.get() else {
+        return;
+    };
+    r.rt.spawn(async move {
+        if let Err(e) = refresh(&r.db, r.cache.as_ref(), strategy_id).await {
+            tracing::warn!("strategy pnl {strategy_id}: {e}");
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(qty: f64, avg_price: f64) -> PnlRow {
+        PnlRow {
+            strategy_id: Uuid::nil(),
+            symbol: "BTC-USDT".into(),
+            realized_pnl: 120.0,
+            qty,
+            avg_price,
+        }
+    }
+
+    #[test]
+    fn open_positions_are_marked_to_market() {
+        let now = Utc::now();
+        let long = row(0.5, 60_000.0).mark(Some(62_000.0), now);
+        assert_eq!(long.unrealized_pnl, Some(1_000.0));
+        assert_eq!(long.total_pnl, 1_120.0);
+
+        let short = row(-0.5, 60_000.0).mark(Some(62_000.0), now);
+        assert_eq!(short.unrealized_pnl, Some(-1_000.0));
+        assert_eq!(short.total_pnl, -880.0);
+    }
+
+    #[test]
+    fn flat_needs_no_price_and_unknown_price_counts_realised_only() {
+        let now = Utc::now();
+        let flat = row(0.0, 0.0).mark(None, now);
+        assert_eq!(flat.unrealized_pnl, Some(0.0));
+        assert_eq!(flat.total_pnl, 120.0);
+
+        let blind = row(1.0, 60_000.0).mark(None, now);
+        assert_eq!(blind.unrealized_pnl, None);
+        assert_eq!(blind.total_pnl, 120.0);
+    }
+
+    #[test]
+    fn bus_close_wins_over_the_mid() {
+        analytics::set_mid("SOL-USDT", 150.0);
+        assert_eq!(mark_price("SOLUSDT"), Some(150.0));
+        BUS_MARKS.insert("SOLUSDT".into(), 151.0);
+        assert_eq!(mark_price("SOL-USDT-SWAP"), Some(151.0));
+    }
+}
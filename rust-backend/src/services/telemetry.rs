@@ -0,0 +1,735 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Logging, metrics & optional OpenTelemetry export
+//! ──────────────────────────────────────────────────────────────────────────
+//! * JSON logs filtered by `RUST_LOG`, Prometheus on `:9000` – always;
+//!   `log` records from dependencies are bridged into the same output
+//! * Log lines go to every sink in `LOG_SINKS`: stdout, a file rotated
+//!   hourly / daily (newest `LOG_FILE_KEEP` kept) and / or batched pushes
+//!   to Loki – see [`LogSink`]
+//! * DEBUG / TRACE events are sampled per call site (`LOG_DEBUG_SAMPLE`),
+//!   so per-candle debug lines stay readable in production
+//! * With the `otel` feature and `OTEL_EXPORTER_OTLP_ENDPOINT` set, spans
+//!   and every `metrics` counter / gauge / histogram are also pushed over
+//!   OTLP (gRPC) – metrics are written to both sinks, nothing moves
+//! * Trade spans nest as `candle → signal → order → fill`, so Tempo /
+//!   Jaeger show where the time between a bar closing and the fill went
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use metrics::increment_counter;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use tokio::sync::mpsc;
+use tracing::{
+    callsite::Identifier, instrument::WithSubscriber, subscriber::NoSubscriber, Level, Metadata,
+    Span,
+};
+use tracing_subscriber::{
+    filter::FilterExt,
+    fmt::{
+        self,
+        writer::{BoxMakeWriter, MakeWriter, MakeWriterExt},
+    },
+    layer::{Context, Filter, SubscriberExt},
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+use crate::services::strategies::Candle;
+
+/// Where OTLP goes; `endpoint: None` keeps everything local
+#[derive(Debug, Clone, Default)]
+pub struct OtelConfig {
+    pub endpoint: Option<String>,
+    pub service_name: String,
+}
+
+/// Where log lines go and how much debug chatter survives
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Every line goes to each of these; empty = stdout
+    pub sinks: Vec<LogSink>,
+    /// Keep 1 in this many DEBUG / TRACE events per call site; 1 = all
+    pub debug_sample: u64,
+    /// `env` label on pushed streams
+    pub environment: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            sinks: vec![LogSink::Stdout],
+            debug_sample: 1,
+            environment: "dev".into(),
+        }
+    }
+}
+
+/// One destination for JSON log lines
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogSink {
+    Stdout,
+    /// `{dir}/{prefix}.{period}`; `keep` = newest files kept, 0 = all
+    File {
+        dir: PathBuf,
+        prefix: String,
+        rotation: Rotation,
+        keep: usize,
+    },
+    /// Batched pushes to `{url}/loki/api/v1/push`
+    Loki {
+        url: String,
+    },
+}
+
+/// How often the file sink starts a new file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            "never" => Ok(Self::Never),
+            other => Err(format!(
+                "unknown rotation `{other}` (hourly | daily | never)"
+            )),
+        }
+    }
+}
+
+impl Rotation {
+    /// Suffix of the file written at `now`; `None` = one file for good
+    fn period(self, now: DateTime<Utc>) -> Option<String> {
+        match self {
+            Self::Hourly => Some(now.format("%Y-%m-%d-%H").to_string()),
+            Self::Daily => Some(now.format("%Y-%m-%d").to_string()),
+            Self::Never => None,
+        }
+    }
+}
+
+/// Install the global subscriber and metrics recorder. Call once, inside
+/// the runtime (the OTLP batch exporters and the Loki pusher spawn onto it).
+pub fn init(cfg: &OtelConfig, log_cfg: &LogConfig) {
+    let filter = EnvFilter::from_default_env().and(Sampler::new(log_cfg.debug_sample));
+    let logs = fmt::layer()
+        .json()
+        .with_writer(writer(log_cfg, &cfg.service_name))
+        .with_filter(filter);
+
+    #[cfg(feature = "otel")]
+    let exported = cfg
+        .endpoint
+        .as_deref()
+        .map(|e| otel::install(e, &cfg.service_name));
+    #[cfg(feature = "otel")]
+    let (spans, recorder) = match exported {
+        Some(Ok((spans, recorder))) => (Some(spans), Some(recorder)),
+        Some(Err(e)) => {
+            eprintln!("otel: export disabled: {e}");
+            (None, None)
+        }
+        None => (None, None),
+    };
+    #[cfg(not(feature = "otel"))]
+    let spans: Option<tracing_subscriber::layer::Identity> = None;
+
+    // the span layer sits right on the registry – its filter is its own
+    tracing_subscriber::registry().with(spans).with(logs).init();
+
+    let prometheus = PrometheusBuilder::new().with_http_listener(([0, 0, 0, 0], 9000));
+    #[cfg(feature = "otel")]
+    if let Some(recorder) = recorder {
+        let (prom, exporter) = prometheus.build().expect("metrics exporter");
+        tokio::spawn(exporter);
+        let fanout = metrics_util::layers::FanoutBuilder::default()
+            .add_recorder(prom)
+            .add_recorder(recorder)
+            .build();
+        metrics::set_boxed_recorder(Box::new(fanout)).expect("metrics recorder");
+        tracing::info!(
+            "otel: exporting to {}",
+            cfg.endpoint.as_deref().unwrap_or_default()
+        );
+        return;
+    }
+    #[cfg(not(feature = "otel"))]
+    if cfg.endpoint.is_some() {
+        tracing::warn!(
+            "OTEL_EXPORTER_OTLP_ENDPOINT set but built without the `otel` feature – ignored"
+        );
+    }
+    prometheus.install().expect("metrics exporter");
+}
+
+/// Every configured sink behind one writer; a file sink that can't be
+/// opened is reported and skipped
+fn writer(cfg: &LogConfig, service: &str) -> BoxMakeWriter {
+    let mut out: Option<BoxMakeWriter> = None;
+    for sink in &cfg.sinks {
+        let w = match sink {
+            LogSink::Stdout => BoxMakeWriter::new(io::stdout),
+            LogSink::File {
+                dir,
+                prefix,
+                rotation,
+                keep,
+            } => match RollingFile::open(dir, prefix, *rotation, *keep) {
+                Ok(f) => BoxMakeWriter::new(f),
+                Err(e) => {
+                    // no subscriber yet to report through
+                    eprintln!("logs: file sink {} disabled: {e}", dir.display());
+                    continue;
+                }
+            },
+            LogSink::Loki { url } => {
+                let labels = serde_json::json!({ "service": service, "env": cfg.environment });
+                BoxMakeWriter::new(LokiSink::spawn(url, labels))
+            }
+        };
+        out = Some(match out {
+            Some(prev) => BoxMakeWriter::new(prev.and(w)),
+            None => w,
+        });
+    }
+    out.unwrap_or_else(|| BoxMakeWriter::new(io::stdout))
+}
+
+/// Keeps 1 in `every` DEBUG / TRACE events per call site – a debug line
+/// logged on every candle still shows up, just not on every bar
+struct Sampler {
+    every: u64,
+    seen: DashMap<Identifier, u64>,
+}
+
+impl Sampler {
+    fn new(every: u64) -> Self {
+        Self {
+            every: every.max(1),
+            seen: DashMap::new(),
+        }
+    }
+}
+
+impl<S> Filter<S> for Sampler {
+    fn enabled(&self, meta: &Metadata<'_>, _: &Context<'_, S>) -> bool {
+        if self.every == 1 || !meta.is_event() || *meta.level() < Level::DEBUG {
+            return true;
+        }
+        let mut n = self.seen.entry(meta.callsite()).or_insert(0);
+        *n += 1;
+        (*n - 1) % self.every == 0
+    }
+}
+
+/// Appends to `{dir}/{prefix}.{period}`, moving on when the period does
+struct RollingFile {
+    dir: PathBuf,
+    prefix: String,
+    rotation: Rotation,
+    keep: usize,
+    current: Mutex<(Option<String>, File)>,
+}
+
+impl RollingFile {
+    fn open(dir: &Path, prefix: &str, rotation: Rotation, keep: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let period = rotation.period(Utc::now());
+        let file = append(&dir.join(file_name(prefix, period.as_deref())))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            prefix: prefix.to_string(),
+            rotation,
+            keep,
+            current: Mutex::new((period, file)),
+        })
+    }
+
+    fn write_line(&self, buf: &[u8]) -> io::Result<()> {
+        let mut cur = self.current.lock().unwrap_or_else(|p| p.into_inner());
+        let period = self.rotation.period(Utc::now());
+        if period != cur.0 {
+            cur.1 = append(&self.dir.join(file_name(&self.prefix, period.as_deref())))?;
+            cur.0 = period;
+            self.prune();
+        }
+        cur.1.write_all(buf)
+    }
+
+    /// Delete all but the newest `keep` files – period suffixes sort by time
+    fn prune(&self) {
+        if self.keep == 0 {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let ours = format!("{}.", self.prefix);
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(&ours))
+            })
+            .collect();
+        files.sort();
+        let excess = files.len().saturating_sub(self.keep);
+        for old in &files[..excess] {
+            let _ = fs::remove_file(old);
+        }
+    }
+}
+
+fn file_name(prefix: &str, period: Option<&str>) -> String {
+    match period {
+        Some(p) => format!("{prefix}.{p}"),
+        None => prefix.to_string(),
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RollingWriter(self)
+    }
+}
+
+struct RollingWriter<'a>(&'a RollingFile);
+
+impl Write for RollingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_line(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Lines waiting for the next push; beyond this they're dropped
+const LOKI_BUFFER: usize = 10_000;
+/// Lines per push
+const LOKI_BATCH: usize = 500;
+/// A partial batch goes out after this long
+const LOKI_FLUSH: Duration = Duration::from_secs(1);
+
+/// Hands each line to a background task pushing batches to Loki
+struct LokiSink {
+    tx: mpsc::Sender<(i64, String)>,
+}
+
+impl LokiSink {
+    fn spawn(url: &str, labels: serde_json::Value) -> Self {
+        let (tx, mut rx) = mpsc::channel(LOKI_BUFFER);
+        let endpoint = format!("{}/loki/api/v1/push", url.trim_end_matches('/'));
+        let pusher = async move {
+            let http = reqwest::Client::new();
+            let mut tick = tokio::time::interval(LOKI_FLUSH);
+            let mut batch = Vec::with_capacity(LOKI_BATCH);
+            loop {
+                let closed = tokio::select! {
+                    line = rx.recv() => match line {
+                        Some(line) => {
+                            batch.push(line);
+                            if batch.len() < LOKI_BATCH {
+                                continue;
+                            }
+                            false
+                        }
+                        None => true,
+                    },
+                    _ = tick.tick() => false,
+                };
+                if !batch.is_empty() {
+                    push_loki(&http, &endpoint, &labels, std::mem::take(&mut batch)).await;
+                }
+                if closed {
+                    return;
+                }
+            }
+        };
+        // the pusher's own HTTP logs would feed straight back into it
+        tokio::spawn(pusher.with_subscriber(NoSubscriber::default()));
+        Self { tx }
+    }
+}
+
+async fn push_loki(
+    http: &reqwest::Client,
+    endpoint: &str,
+    labels: &serde_json::Value,
+    lines: Vec<(i64, String)>,
+) {
+    let values: Vec<[String; 2]> = lines
+        .into_iter()
+        .map(|(ts, line)| [ts.to_string(), line])
+        .collect();
+    let body = serde_json::json!({ "streams": [{ "stream": labels, "values": values }] });
+    let sent = http
+        .post(endpoint)
+        .timeout(Duration::from_secs(5))
+        .json(&body)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    if let Err(e) = sent {
+        increment_counter!("log_push_failures_total", "sink" => "loki");
+        // logging is what failed – stderr is all that's left
+        eprintln!("logs: loki push failed: {e}");
+    }
+}
+
+impl<'a> MakeWriter<'a> for LokiSink {
+    type Writer = LokiLine<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LokiLine {
+            tx: &self.tx,
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// One formatted event, queued when the formatter is done with it
+struct LokiLine<'a> {
+    tx: &'a mpsc::Sender<(i64, String)>,
+    buf: Vec<u8>,
+}
+
+impl Write for LokiLine<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LokiLine<'_> {
+    fn drop(&mut self) {
+        let line = String::from_utf8_lossy(&self.buf).trim_end().to_string();
+        if line.is_empty() {
+            return;
+        }
+        let ts = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        if self.tx.try_send((ts, line)).is_err() {
+            increment_counter!("log_lines_dropped_total", "sink" => "loki");
+        }
+    }
+}
+
+/// Flush buffered spans / metrics; no-op without an exporter
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    otel::shutdown();
+}
+
+/// Root of a trade trace: one bar as a strategy saw it. `lag_ms` is how
+/// long after the bar's open the strategy got to it.
+pub fn candle_span(strategy: &'static str, symbol: &str, c: &Candle) -> Span {
+    tracing::info_span!(
+        "candle",
+        strategy,
+        symbol,
+        candle_ts = %c.ts,
+        close = c.close,
+        lag_ms = (Utc::now() - c.ts).num_milliseconds(),
+    )
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tracing::{Event, Subscriber};
+
+    struct Count(Arc<AtomicUsize>);
+
+    impl<S: Subscriber> Layer<S> for Count {
+        fn on_event(&self, _: &Event<'_>, _: Context<'_, S>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn debug_events_are_sampled_per_call_site() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let subscriber =
+            tracing_subscriber::registry().with(Count(seen.clone()).with_filter(Sampler::new(3)));
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..10 {
+                tracing::debug!("bar closed");
+            }
+            for _ in 0..2 {
+                tracing::info!("order placed");
+            }
+        });
+        // debug 1, 4, 7, 10 + both infos
+        assert_eq!(seen.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn files_are_named_by_rotation_period() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 7, 30, 0).unwrap();
+        let name = |r: Rotation| file_name("rr.log", r.period(now).as_deref());
+        assert_eq!(name(Rotation::Hourly), "rr.log.2024-03-01-07");
+        assert_eq!(name(Rotation::Daily), "rr.log.2024-03-01");
+        assert_eq!(name(Rotation::Never), "rr.log");
+        assert!("weekly".parse::<Rotation>().is_err());
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+    use metrics::{
+        Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Recorder,
+        SharedString, Unit,
+    };
+    use once_cell::sync::OnceCell;
+    use opentelemetry::metrics::{Meter, MeterProvider as _};
+    use opentelemetry::{global, KeyValue};
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{metrics::MeterProvider, runtime, trace, Resource};
+    use tracing_subscriber::{filter::LevelFilter, Layer, Registry};
+
+    static METERS: OnceCell<MeterProvider> = OnceCell::new();
+
+    pub type SpanLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+    /// OTLP span layer + `metrics` recorder writing to the same collector
+    pub fn install(endpoint: &str, service: &str) -> Result<(SpanLayer, OtelRecorder), String> {
+        let resource = Resource::new([KeyValue::new("service.name", service.to_string())]);
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(trace::config().with_resource(resource.clone()))
+            .install_batch(runtime::Tokio)
+            .map_err(|e| e.to_string())?;
+        let meters = opentelemetry_otlp::new_pipeline()
+            .metrics(runtime::Tokio)
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_resource(resource)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let meter = meters.meter("rustraptor");
+        let _ = METERS.set(meters);
+
+        // RUST_LOG only governs the log output; traces always carry info spans
+        let spans = tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(LevelFilter::INFO)
+            .boxed();
+        Ok((spans, OtelRecorder::new(meter)))
+    }
+
+    pub fn shutdown() {
+        global::shutdown_tracer_provider();
+        if let Some(meters) = METERS.get() {
+            if let Err(e) = meters.shutdown() {
+                tracing::warn!("otel: metrics flush failed: {e}");
+            }
+        }
+    }
+
+    fn attributes(key: &Key) -> Vec<KeyValue> {
+        key.labels()
+            .map(|l| KeyValue::new(l.key().to_string(), l.value().to_string()))
+            .collect()
+    }
+
+    struct OtelCounter {
+        inner: opentelemetry::metrics::Counter<u64>,
+        attrs: Vec<KeyValue>,
+        last: AtomicU64,
+    }
+
+    impl CounterFn for OtelCounter {
+        fn increment(&self, value: u64) {
+            self.last.fetch_add(value, Ordering::Relaxed);
+            self.inner.add(value, &self.attrs);
+        }
+
+        /// OTLP counters are monotonic deltas – forward only the growth
+        fn absolute(&self, value: u64) {
+            let prev = self.last.fetch_max(value, Ordering::Relaxed);
+            if value > prev {
+                self.inner.add(value - prev, &self.attrs);
+            }
+        }
+    }
+
+    /// No synchronous OTLP gauge: an up-down counter fed the change
+    struct OtelGauge {
+        inner: opentelemetry::metrics::UpDownCounter<f64>,
+        attrs: Vec<KeyValue>,
+        value: AtomicU64,
+    }
+
+    impl OtelGauge {
+        /// Store `f(current)`, return the delta to forward
+        fn update(&self, f: impl Fn(f64) -> f64) -> f64 {
+            let mut delta = 0.0;
+            let _ = self
+                .value
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                    let cur = f64::from_bits(bits);
+                    let next = f(cur);
+                    delta = next - cur;
+                    Some(next.to_bits())
+                });
+            delta
+        }
+
+        fn forward(&self, delta: f64) {
+            if delta != 0.0 {
+                self.inner.add(delta, &self.attrs);
+            }
+        }
+    }
+
+    impl GaugeFn for OtelGauge {
+        fn increment(&self, value: f64) {
+            self.forward(self.update(|cur| cur + value));
+        }
+
+        fn decrement(&self, value: f64) {
+            self.forward(self.update(|cur| cur - value));
+        }
+
+        fn set(&self, value: f64) {
+            self.forward(self.update(|_| value));
+        }
+    }
+
+    struct OtelHistogram {
+        inner: opentelemetry::metrics::Histogram<f64>,
+        attrs: Vec<KeyValue>,
+    }
+
+    impl HistogramFn for OtelHistogram {
+        fn record(&self, value: f64) {
+            self.inner.record(value, &self.attrs);
+        }
+    }
+
+    /// `metrics` facade → OTel instruments. The macros re-register on every
+    /// call, so handles are cached per key (gauges must keep their value).
+    pub struct OtelRecorder {
+        meter: Meter,
+        counters: DashMap<Key, Arc<OtelCounter>>,
+        gauges: DashMap<Key, Arc<OtelGauge>>,
+        histograms: DashMap<Key, Arc<OtelHistogram>>,
+    }
+
+    impl OtelRecorder {
+        fn new(meter: Meter) -> Self {
+            Self {
+                meter,
+                counters: DashMap::new(),
+                gauges: DashMap::new(),
+                histograms: DashMap::new(),
+            }
+        }
+    }
+
+    impl Recorder for OtelRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key) -> Counter {
+            let c = self.counters.entry(key.clone()).or_insert_with(|| {
+                Arc::new(OtelCounter {
+                    inner: self.meter.u64_counter(key.name().to_string()).init(),
+                    attrs: attributes(key),
+                    last: AtomicU64::new(0),
+                })
+            });
+            Counter::from_arc(c.clone())
+        }
+
+        fn register_gauge(&self, key: &Key) -> Gauge {
+            let g = self.gauges.entry(key.clone()).or_insert_with(|| {
+                Arc::new(OtelGauge {
+                    inner: self
+                        .meter
+                        .f64_up_down_counter(key.name().to_string())
+                        .init(),
+                    attrs: attributes(key),
+                    value: AtomicU64::new(0f64.to_bits()),
+                })
+            });
+            Gauge::from_arc(g.clone())
+        }
+
+        fn register_histogram(&self, key: &Key) -> Histogram {
+            let h = self.histograms.entry(key.clone()).or_insert_with(|| {
+                Arc::new(OtelHistogram {
+                    inner: self.meter.f64_histogram(key.name().to_string()).init(),
+                    attrs: attributes(key),
+                })
+            });
+            Histogram::from_arc(h.clone())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn gauge_forwards_changes_not_levels() {
+            let g = OtelGauge {
+                inner: global::meter("test").f64_up_down_counter("g").init(),
+                attrs: vec![],
+                value: AtomicU64::new(0f64.to_bits()),
+            };
+            assert_eq!(g.update(|_| 5.0), 5.0);
+            assert_eq!(g.update(|cur| cur + 2.5), 2.5);
+            assert_eq!(g.update(|_| 1.0), -6.5);
+            assert_eq!(g.update(|_| 1.0), 0.0);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Exchange clock-skew detection & correction
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Every `TIME_SYNC_INTERVAL_SECS` each exchange's clock is probed a few
+//!   times; the lowest-RTT sample gives `offset = server − local midpoint`
+//! * Signed BlowFin requests (REST `Signer::ts()` and the WS login) stamp
+//!   `local now + offset`, so a drifting host clock doesn't get requests
+//!   rejected
+//! * `|offset| > CLOCK_SKEW_ALERT_MS` is logged as an error on the way in
+//!   (and a recovery on the way out); `clock_skew_ms{exchange}` is exported
+//!   for alerting rules
+//! * BlowFin has no time endpoint: its ticker `ts` is used, checked against
+//!   the HTTP `Date` header (second resolution, taken as mid-second)
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use metrics::{gauge, increment_counter};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Probes per round – the lowest-RTT one wins
+const PROBES: usize = 3;
+/// Samples slower than this say little about the offset
+const MAX_RTT_MS: i64 = 2_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockSource {
+    Blowfin,
+    Binance,
+}
+
+impl ClockSource {
+    pub const ALL: [ClockSource; 2] = [ClockSource::Blowfin, ClockSource::Binance];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ClockSource::Blowfin => "blowfin",
+            ClockSource::Binance => "binance",
+        }
+    }
+
+    fn url(self, is_demo: bool) -> &'static str {
+        match self {
+            ClockSource::Blowfin if is_demo => {
+                "https://demo-trading-openapi.blofin.com/api/v1/market/tickers?instId=BTC-USDT"
+            }
+            ClockSource::Blowfin => {
+                "https://openapi.blofin.com/api/v1/market/tickers?instId=BTC-USDT"
+            }
+            ClockSource::Binance => "https://api.binance.com/api/v3/time",
+        }
+    }
+
+    /// Server time (unix ms) out of a probe response body
+    pub fn parse_body(self, body: &Value) -> Option<i64> {
+        let v = match self {
+            ClockSource::Blowfin => &body["data"][0]["ts"],
+            ClockSource::Binance => &body["serverTime"],
+        };
+        match v {
+            Value::String(s) => s.parse().ok(),
+            Value::Number(n) => n.as_i64(),
+            _ => None,
+        }
+    }
+}
+
+/// `Date: Tue, 15 Nov 1994 08:12:31 GMT` → unix ms at mid-second
+pub fn parse_date_header(h: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(h)
+        .ok()
+        .map(|d| d.timestamp_millis() + 500)
+}
+
+/// Body time is precise but may be stale (a ticker's last update); the
+/// `Date` header is always current but coarse. Prefer the body unless the
+/// two disagree by more than the header's resolution.
+pub fn pick_server_time(body_ms: Option<i64>, date_ms: Option<i64>) -> Option<i64> {
+    match (body_ms, date_ms) {
+        (Some(b), Some(d)) if (b - d).abs() > 1_000 => Some(d),
+        (b, d) => b.or(d),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub sent_ms: i64,
+    pub recv_ms: i64,
+    pub server_ms: i64,
+}
+
+impl Sample {
+    pub fn rtt(&self) -> i64 {
+        self.recv_ms - self.sent_ms
+    }
+
+    /// Assumes the server stamped the response halfway through the RTT
+    pub fn offset(&self) -> i64 {
+        self.server_ms - (self.sent_ms + self.rtt() / 2)
+    }
+}
+
+/// Lowest-RTT usable sample
+pub fn best(samples: &[Sample]) -> Option<Sample> {
+    samples
+        .iter()
+        .filter(|s| (0..=MAX_RTT_MS).contains(&s.rtt()))
+        .min_by_key(|s| s.rtt())
+        .copied()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClockStatus {
+    pub exchange: ClockSource,
+    /// Applied to signed timestamps: `exchange ≈ local + offset_ms`
+    pub offset_ms: i64,
+    pub rtt_ms: i64,
+    pub synced_at: DateTime<Utc>,
+    pub alerting: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Raised,
+    Cleared,
+}
+
+/// Edge-triggered threshold check
+pub fn transition(was_alerting: bool, offset_ms: i64, threshold_ms: i64) -> Option<Transition> {
+    let over = offset_ms.abs() > threshold_ms;
+    match (was_alerting, over) {
+        (false, true) => Some(Transition::Raised),
+        (true, false) => Some(Transition::Cleared),
+        _ => None,
+    }
+}
+
+static CLOCKS: Lazy<DashMap<ClockSource, ClockStatus>> = Lazy::new(DashMap::new);
+
+/// Correction for `source` (0 until the first successful sync)
+pub fn offset_ms(source: ClockSource) -> i64 {
+    CLOCKS.get(&source).map_or(0, |c| c.offset_ms)
+}
+
+/// Local clock corrected to `source`'s clock, unix ms
+pub fn now_ms(source: ClockSource) -> i64 {
+    Utc::now().timestamp_millis() + offset_ms(source)
+}
+
+pub fn status() -> Vec<ClockStatus> {
+    let mut all: Vec<ClockStatus> = CLOCKS.iter().map(|c| c.value().clone()).collect();
+    all.sort_by_key(|c| c.exchange.label());
+    all
+}
+
+/// Fold a round's best sample into the shared state
+pub fn apply(source: ClockSource, sample: Sample, threshold_ms: i64) {
+    let offset = sample.offset();
+    let was_alerting = CLOCKS.get(&source).is_some_and(|c| c.alerting);
+    let alerting = match transition(was_alerting, offset, threshold_ms) {
+        Some(Transition::Raised) => {
+            increment_counter!("clock_skew_alerts_total", "exchange" => source.label());
+            tracing::error!(
+                "time sync: {} clock skew {offset} ms exceeds {threshold_ms} ms – correcting",
+                source.label()
+            );
+            true
+        }
+        Some(Transition::Cleared) => {
+            tracing::info!(
+                "time sync: {} clock skew back to {offset} ms",
+                source.label()
+            );
+            false
+        }
+        None => was_alerting,
+    };
+    gauge!("clock_skew_ms", offset as f64, "exchange" => source.label());
+    CLOCKS.insert(
+        source,
+        ClockStatus {
+            exchange: source,
+            offset_ms: offset,
+            rtt_ms: sample.rtt(),
+            synced_at: Utc::now(),
+            alerting,
+        },
+    );
+}
+
+async fn probe(http: &reqwest::Client, source: ClockSource, is_demo: bool) -> Option<Sample> {
+    let sent_ms = Utc::now().timestamp_millis();
+    let resp = http.get(source.url(is_demo)).send().await.ok()?;
+    let recv_ms = Utc::now().timestamp_millis();
+    let date = resp
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_date_header);
+    let body = resp.json::<Value>().await.ok();
+    let server_ms = pick_server_time(body.and_then(|b| source.parse_body(&b)), date)?;
+    Some(Sample {
+        sent_ms,
+        recv_ms,
+        server_ms,
+    })
+}
+
+async fn sync_once(http: &reqwest::Client, source: ClockSource, is_demo: bool, threshold_ms: i64) {
+    let mut samples = Vec::with_capacity(PROBES);
+    for _ in 0..PROBES {
+        if let Some(s) = probe(http, source, is_demo).await {
+            samples.push(s);
+        }
+    }
+    match best(&samples) {
+        Some(s) => apply(source, s, threshold_ms),
+        None => tracing::warn!("time sync: no usable sample from {}", source.label()),
+    }
+}
+
+/// Background sync for every exchange; the first round runs immediately
+pub fn spawn(is_demo: bool, every: Duration, threshold_ms: i64) {
+    tokio::spawn(async move {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_millis(MAX_RTT_MS as u64))
+            .build()
+            .unwrap_or_default();
+        let mut tick = tokio::time::interval(every);
+        loop {
+            tick.tick().await;
+            for source in ClockSource::ALL {
+                sync_once(&http, source, is_demo, threshold_ms).await;
+            }
+        }
+    });
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn offset_uses_rtt_midpoint() {
+        // local clock 300 ms behind; 100 ms round trip
+        let s = Sample {
+            sent_ms: 1_000,
+            recv_ms: 1_100,
+            server_ms: 1_350,
+        };
+        assert_eq!(s.rtt(), 100);
+        assert_eq!(s.offset(), 300);
+    }
+
+    #[test]
+    fn best_sample_is_lowest_rtt_within_bounds() {
+        let s = |sent, recv| Sample {
+            sent_ms: sent,
+            recv_ms: recv,
+            server_ms: 0,
+        };
+        let picked = best(&[s(0, 400), s(0, 90), s(0, -5), s(0, 150)]).unwrap();
+        assert_eq!(picked.rtt(), 90);
+        assert_eq!(best(&[s(0, MAX_RTT_MS + 1)]), None);
+        assert_eq!(best(&[]), None);
+    }
+
+    #[test]
+    fn alerts_are_edge_triggered() {
+        assert_eq!(transition(false, 1_500, 1_000), Some(Transition::Raised));
+        assert_eq!(transition(false, -1_500, 1_000), Some(Transition::Raised));
+        assert_eq!(transition(true, 1_200, 1_000), None);
+        assert_eq!(transition(true, 200, 1_000), Some(Transition::Cleared));
+        assert_eq!(transition(false, 1_000, 1_000), None);
+    }
+
+    #[test]
+    fn server_time_parsing() {
+        assert_eq!(
+            ClockSource::Binance.parse_body(&json!({ "serverTime": 1_700_000_000_123_i64 })),
+            Some(1_700_000_000_123)
+        );
+        assert_eq!(
+            ClockSource::Blowfin
+                .parse_body(&json!({ "code": "0", "data": [{ "ts": "1700000000456" }] })),
+            Some(1_700_000_000_456)
+        );
+        assert_eq!(
+            ClockSource::Blowfin.parse_body(&json!({ "data": [] })),
+            None
+        );
+        assert_eq!(
+            parse_date_header("Tue, 14 Nov 2023 22:13:20 GMT"),
+            Some(1_700_000_000_500)
+        );
+        assert_eq!(parse_date_header("yesterday"), None);
+    }
+
+    #[test]
+    fn stale_body_time_loses_to_date_header() {
+        assert_eq!(pick_server_time(Some(10_200), Some(10_500)), Some(10_200));
+        assert_eq!(pick_server_time(Some(5_000), Some(10_500)), Some(10_500));
+        assert_eq!(pick_server_time(None, Some(10_500)), Some(10_500));
+        assert_eq!(pick_server_time(Some(7), None), Some(7));
+        assert_eq!(pick_server_time(None, None), None);
+    }
+
+    #[test]
+    fn applied_offset_corrects_signed_time() {
+        let s = Sample {
+            sent_ms: 0,
+            recv_ms: 0,
+            server_ms: -250,
+        };
+        apply(ClockSource::Binance, s, 1_000);
+        assert_eq!(offset_ms(ClockSource::Binance), -250);
+        let now = Utc::now().timestamp_millis();
+        assert!((now_ms(ClockSource::Binance) - (now - 250)).abs() < 50);
+    }
+}
@@ -2,36 +2,68 @@
 //! Thin execution layer that routes *validated* trade requests to the
 //! exchange client, handles risk checks, and post-processes the response.
 //!
-//! The production path still hard-wires Blowfin + risk, but all external
-//! calls are now routed through *traits* so the unit-tests can inject mocks
-//! without `unsafe` or global state hacks.
+//! The production path picks the exchange client from the adapter registry
+//! (`services::exchanges`) by `TradeRequest::exchange`; all external calls
+//! are routed through *traits* so the unit-tests can inject mocks without
+//! `unsafe` or global state hacks.
+
+use std::time::Duration;
 
-use redis::Client;
-use serde_json::Value;
-use sqlx::PgPool;
 use crate::{
-    db::api_keys::ApiKey,
     services::{
-        blowfin::{
-            api::OrderRequest,
-            client::BlowfinClient,
-        },
-        crypto::GLOBAL_CRYPTO,
-        risk,
+        analytics, anomaly,
+        blowfin::api::OrderRequest,
+        drain,
+        exchanges::{self, ExchangeAdapter},
+        fees, risk,
     },
     utils::errors::TradeError,
 };
+use metrics::increment_counter;
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+use sqlx::PgPool;
+use tracing::Instrument;
+
+/// BlowFin's order price for "market once triggered"
+const MARKET_PRICE: &str = "-1";
 
 // ──────────────────────────────────────────────────────────────
 // Public types
 // ──────────────────────────────────────────────────────────────
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum Exchange {
     Blowfin,
-    // placeholder for future variants
+    /// USDⓈ-M futures
+    Binance,
+    // new venues also need an adapter – see `services::exchanges`
 }
 
-#[derive(Debug)]
+impl Exchange {
+    /// Name used in `api_keys.exchange` / `user_fee_tiers.exchange`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Exchange::Blowfin => "blowfin",
+            Exchange::Binance => "binance",
+        }
+    }
+}
+
+impl std::str::FromStr for Exchange {
+    type Err = TradeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blowfin" => Ok(Exchange::Blowfin),
+            "binance" => Ok(Exchange::Binance),
+            other => Err(TradeError::InvalidRequest(format!(
+                "unsupported exchange: {other}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TradeRequest {
     pub exchange: Exchange,
     pub symbol: String,
@@ -39,8 +71,17 @@ pub struct TradeRequest {
     pub order_type: String,
     pub price: Option<f64>,
     pub size: f64,
+    /// Only reduce an existing position (exits, partial closes)
+    pub reduce_only: bool,
+    /// Price the strategy/leader acted on – baseline for slippage reporting
+    pub signal_price: Option<f64>,
+    /// Exits to rest on the exchange with the order (entries only)
+    pub tp_sl: Option<TpSl>,
 }
 
+/// Native take-profit / stop-loss trigger prices; both fill at market
+pub use rustraptor_types::trade::TpSl;
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct TradeResponse {
     pub success: bool,
@@ -50,6 +91,14 @@ pub struct TradeResponse {
     pub order_type: String,
     pub price: Option<f64>,
     pub size: f64,
+    pub reduce_only: bool,
+    pub signal_price: Option<f64>,
+    /// Book mid observed right before submission (if a feed is running)
+    pub mid_at_submit: Option<f64>,
+    /// Our `orders` row, once recorded
+    pub order_id: Option<uuid::Uuid>,
+    /// Ours, sent with the order – how `order_tracker` asks about it later
+    pub client_order_id: Option<String>,
     pub data: Value,
 }
 
@@ -59,6 +108,17 @@ pub struct TradeResponse {
 #[async_trait::async_trait]
 pub trait RiskGuard: Send + Sync {
     fn check_slippage(&self, slip: f64) -> Result<(), TradeError>;
+
+    /// Entry-only market-quality check; no-op unless overridden
+    fn check_liquidity(&self, _symbol: &str) -> Result<(), TradeError> {
+        Ok(())
+    }
+
+    /// Refuses any order while the symbol's market is closed; no-op unless
+    /// overridden
+    fn check_market_open(&self, _symbol: &str) -> Result<(), TradeError> {
+        Ok(())
+    }
 }
 
 pub struct ProdRisk;
@@ -67,6 +127,14 @@ impl RiskGuard for ProdRisk {
     fn check_slippage(&self, slip: f64) -> Result<(), TradeError> {
         risk::check_slippage(slip)
     }
+
+    fn check_liquidity(&self, symbol: &str) -> Result<(), TradeError> {
+        risk::check_liquidity(symbol)
+    }
+
+    fn check_market_open(&self, symbol: &str) -> Result<(), TradeError> {
+        risk::check_market_open(symbol)
+    }
 }
 
 #[derive(Debug)]
@@ -75,6 +143,44 @@ pub struct ApiResponse {
     pub data: Value,
 }
 
+/// Where an order ended up, as reported by the exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderState {
+    /// Still working on the book
+    Live,
+    Filled,
+    /// Cancelled after a partial fill
+    PartiallyFilled,
+    /// Cancelled or rejected without a fill
+    Canceled,
+    /// The exchange has no record of it
+    NotFound,
+}
+
+impl OrderState {
+    /// BlowFin's `state` field; anything unrecognised counts as still live
+    pub fn from_exchange(state: &str) -> Self {
+        match state {
+            "filled" => OrderState::Filled,
+            "partially_canceled" => OrderState::PartiallyFilled,
+            "canceled" | "order_failed" => OrderState::Canceled,
+            _ => OrderState::Live,
+        }
+    }
+
+    fn has_fills(self) -> bool {
+        matches!(self, OrderState::Filled | OrderState::PartiallyFilled)
+    }
+}
+
+#[derive(Debug)]
+pub struct OrderStatus {
+    pub state: OrderState,
+    /// Raw exchange record (empty when not found)
+    pub data: Value,
+}
+
 #[async_trait::async_trait]
 pub trait ApiClient: Send + Sync {
     async fn place_order(
@@ -85,13 +191,283 @@ pub trait ApiClient: Send + Sync {
         is_demo: bool,
         master_key: &[u8],
     ) -> Result<ApiResponse, TradeError>;
+
+    /// Cancel by our `clientOrderId`; used after a submission timed out
+    async fn cancel_by_client_id(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _symbol: &str,
+        _client_order_id: &str,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        Err(TradeError::Other(
+            "cancel by client id not supported".into(),
+        ))
+    }
+
+    async fn order_status(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _symbol: &str,
+        _client_order_id: &str,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<OrderStatus, TradeError> {
+        Err(TradeError::Other("order status not supported".into()))
+    }
+
+    /// Native stop-losses resting on `symbol`
+    async fn pending_stops(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _symbol: &str,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<Vec<NativeStop>, TradeError> {
+        Err(TradeError::Other("native stops not supported".into()))
+    }
+
+    /// Rest a stop-loss for the whole position on the exchange
+    async fn place_stop(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _stop: &NativeStop,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        Err(TradeError::Other("native stops not supported".into()))
+    }
+
+    async fn cancel_stop(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _stop: &NativeStop,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        Err(TradeError::Other("native stops not supported".into()))
+    }
+
+    /// Every resting (unfilled or partially filled) order of the account
+    async fn open_orders(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<Vec<OpenOrder>, TradeError> {
+        Err(TradeError::Other("open orders not supported".into()))
+    }
+
+    async fn cancel_order(
+        &self,
+        _db: &PgPool,
+        _user_id: i64,
+        _order: &OpenOrder,
+        _is_demo: bool,
+        _master_key: &[u8],
+    ) -> Result<(), TradeError> {
+        Err(TradeError::Other("cancel order not supported".into()))
+    }
+}
+
+/// A resting order, as far as cancelling it goes
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct OpenOrder {
+    pub order_id: String,
+    pub symbol: String,
+}
+
+// ──────────────────────────────────────────────────────────────
+//  Native stops
+// ──────────────────────────────────────────────────────────────
+/// A stop-loss held by the exchange; closes the whole position at market
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeStop {
+    /// Exchange id; `None` until placed
+    pub id: Option<String>,
+    pub symbol: String,
+    /// Order side that closes the position
+    pub side: String,
+    pub trigger_price: f64,
+}
+
+/// What [`sync_stop_with`] changed on the exchange
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StopSync {
+    pub placed: bool,
+    pub cancelled: usize,
+}
+
+/// Trigger prices round-trip through strings
+fn same_price(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(1.0)
+}
+
+/// Make the exchange hold exactly one stop at `want` for the position
+/// closed by `side` (none when `want` is `None`): keeps a matching stop,
+/// cancels moved / duplicate ones and re-places a stop that went missing.
+/// Stops on the other side are left alone.
+#[allow(clippy::too_many_arguments)]
+pub async fn sync_stop_with<A: ApiClient + ?Sized>(
+    api: &A,
+    db: &PgPool,
+    user_id: i64,
+    symbol: &str,
+    side: &str,
+    want: Option<f64>,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<StopSync, TradeError> {
+    let pending = api
+        .pending_stops(db, user_id, symbol, is_demo, master_key)
+        .await?;
+    let mut out = StopSync::default();
+    let mut kept = false;
+    for stop in pending.iter().filter(|s| s.side == side) {
+        if !kept && want.is_some_and(|w| same_price(w, stop.trigger_price)) {
+            kept = true;
+            continue;
+        }
+        api.cancel_stop(db, user_id, stop, is_demo, master_key)
+            .await?;
+        out.cancelled += 1;
+    }
+    if let (Some(trigger_price), false) = (want, kept) {
+        let stop = NativeStop {
+            id: None,
+            symbol: symbol.to_string(),
+            side: side.to_string(),
+            trigger_price,
+        };
+        api.place_stop(db, user_id, &stop, is_demo, master_key)
+            .await?;
+        out.placed = true;
+    }
+    if out != StopSync::default() {
+        increment_counter!("native_stop_syncs_total");
+    }
+    Ok(out)
+}
+
+// ──────────────────────────────────────────────────────────────
+//  Latency budget
+// ──────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy)]
+pub struct OrderTimeouts {
+    /// Budget for the order submission itself
+    pub submit: Duration,
+    /// Budget for each cancel / status call after a submission timed out
+    pub followup: Duration,
+}
+
+impl Default for OrderTimeouts {
+    fn default() -> Self {
+        Self {
+            submit: Duration::from_secs(5),
+            followup: Duration::from_secs(3),
+        }
+    }
+}
+
+static TIMEOUTS: OnceCell<OrderTimeouts> = OnceCell::new();
+
+/// Install `ORDER_SUBMIT_TIMEOUT_MS` / `ORDER_FOLLOWUP_TIMEOUT_MS` (call once
+/// from `main`; defaults apply otherwise)
+pub fn init_timeouts(t: OrderTimeouts) {
+    let _ = TIMEOUTS.set(t);
+}
+
+pub fn timeouts() -> OrderTimeouts {
+    TIMEOUTS.get().copied().unwrap_or_default()
+}
+
+/// 32 chars – BlowFin's `clientOrderId` limit
+fn new_client_order_id() -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut id = String::with_capacity(32);
+    id.push_str("rr");
+    for b in rand::random::<[u8; 15]>() {
+        id.push(HEX[(b >> 4) as usize] as char);
+        id.push(HEX[(b & 0xf) as usize] as char);
+    }
+    id
+}
+
+/// A submission blew its budget, so the order may or may not exist. Cancel
+/// it by client id, then ask for its final state: fills are reported as a
+/// normal response, anything else as `TradeError::Timeout`.
+async fn settle_timed_out<A: ApiClient + ?Sized>(
+    api: &A,
+    db: &PgPool,
+    user_id: i64,
+    order: &OrderRequest,
+    is_demo: bool,
+    master_key: &[u8],
+    followup: Duration,
+) -> Result<ApiResponse, TradeError> {
+    let coid = order.client_order_id.as_deref().unwrap_or_default();
+    let symbol = order.inst_id.as_str();
+    increment_counter!("order_submit_timeouts_total");
+    tracing::warn!("order {coid} ({symbol}) timed out on submit – cancelling");
+
+    let cancel = api.cancel_by_client_id(db, user_id, symbol, coid, is_demo, master_key);
+    match tokio::time::timeout(followup, cancel).await {
+        Ok(Ok(())) => {}
+        // nothing to cancel or already final – the status call tells which
+        Ok(Err(e)) => tracing::warn!("cancel {coid}: {e}"),
+        Err(_) => tracing::warn!("cancel {coid}: timed out"),
+    }
+
+    let status = api.order_status(db, user_id, symbol, coid, is_demo, master_key);
+    let status = match tokio::time::timeout(followup, status).await {
+        Ok(Ok(s)) => s,
+        Ok(Err(e)) => {
+            tracing::error!("order {coid}: final state unknown ({e}) – check the exchange");
+            return Err(TradeError::Timeout(format!("order {coid} state unknown")));
+        }
+        Err(_) => {
+            tracing::error!("order {coid}: status query timed out – check the exchange");
+            return Err(TradeError::Timeout(format!("order {coid} state unknown")));
+        }
+    };
+
+    match status.state {
+        s if s.has_fills() => {
+            tracing::warn!("order {coid} traded ({s:?}) despite the submit timeout");
+            Ok(ApiResponse {
+                code: "0".into(),
+                data: status.data,
+            })
+        }
+        OrderState::Live => {
+            tracing::error!("order {coid} still live after cancel – check the exchange");
+            Err(TradeError::Timeout(format!(
+                "order {coid} still live after cancel"
+            )))
+        }
+        s => Err(TradeError::Timeout(format!(
+            "order {coid} not placed ({s:?})"
+        ))),
+    }
 }
 
 // ──────────────────────────────────────────────────────────────
 //  Generic core  (unit-testable)
 // ──────────────────────────────────────────────────────────────
 #[allow(clippy::too_many_arguments)]
-pub async fn execute_trade_with<R: RiskGuard, A: ApiClient>(
+#[tracing::instrument(
+    name = "order",
+    skip_all,
+    fields(symbol = %req.symbol, side = %req.side, size = req.size, client_order_id)
+)]
+pub async fn execute_trade_with<R: RiskGuard, A: ApiClient + ?Sized>(
     req: TradeRequest,
     db: &PgPool,
     user_id: i64,
@@ -102,37 +478,162 @@ pub async fn execute_trade_with<R: RiskGuard, A: ApiClient>(
 ) -> Result<TradeResponse, TradeError> {
     // 1. Pre-trade slippage/risk check
     risk.check_slippage(0.0)?;
+    // a closed exchange takes neither entries nor exits
+    risk.check_market_open(&req.symbol)?;
+    // exits always go through – they reduce exposure
+    if !req.reduce_only {
+        risk.check_liquidity(&req.symbol)?;
+    }
 
     // 2. Build outbound order & call the API
-    let order_req = OrderRequest {
-        inst_id: req.symbol.clone(),
-        margin_mode: "isolated".into(),
-        side: req.side.clone(),
-        order_type: req.order_type.clone(),
-        price: req.price.map(|p| p.to_string()),
-        size: req.size.to_string(),
-    };
+    let mid_at_submit = analytics::mid_for(&req.symbol);
+    let (order_req, meta) = build_order(req);
+    tracing::Span::current().record("client_order_id", order_req.client_order_id.as_deref());
 
-    let api_resp = api
+    let budget = timeouts();
+    let submit = api
         .place_order(db, user_id, &order_req, is_demo, master_key)
-        .await?;
+        .instrument(tracing::info_span!("submit"));
+    let api_resp = match tokio::time::timeout(budget.submit, submit).await {
+        Ok(resp) => resp?,
+        Err(_) => {
+            settle_timed_out(
+                api,
+                db,
+                user_id,
+                &order_req,
+                is_demo,
+                master_key,
+                budget.followup,
+            )
+            .await?
+        }
+    };
 
-    // 3. Shape into canonical response
+    // 3. Shape into canonical response – the strings move back out of the
+    //    order rather than being cloned on the way in
     Ok(TradeResponse {
         success: api_resp.code == "0",
-        exchange: req.exchange.clone(),
-        symbol: req.symbol,
-        side: req.side,
-        order_type: req.order_type,
-        price: req.price,
-        size: req.size,
+        exchange: meta.exchange,
+        symbol: order_req.inst_id,
+        side: order_req.side,
+        order_type: order_req.order_type,
+        price: meta.price,
+        size: meta.size,
+        reduce_only: meta.reduce_only,
+        signal_price: meta.signal_price,
+        mid_at_submit,
+        order_id: None,
+        client_order_id: order_req.client_order_id,
         data: api_resp.data,
     })
 }
 
+/// What of a `TradeRequest` the response echoes besides the order's strings
+pub struct OrderMeta {
+    pub exchange: Exchange,
+    pub price: Option<f64>,
+    pub size: f64,
+    pub reduce_only: bool,
+    pub signal_price: Option<f64>,
+}
+
+/// Consume `req` into the exchange payload; symbol, side and order type move
+/// rather than being copied. Public for the order-path benchmark.
+pub fn build_order(req: TradeRequest) -> (OrderRequest, OrderMeta) {
+    let TradeRequest {
+        exchange,
+        symbol,
+        side,
+        order_type,
+        price,
+        size,
+        reduce_only,
+        signal_price,
+        tp_sl,
+    } = req;
+    // a reduce-only order has no position of its own to protect
+    let tp_sl = tp_sl.filter(|_| !reduce_only).unwrap_or_default();
+    let order = OrderRequest {
+        inst_id: symbol,
+        margin_mode: "isolated".into(),
+        side,
+        order_type,
+        price: price.map(|p| p.to_string()),
+        size: size.to_string(),
+        reduce_only: reduce_only.then(|| "true".into()),
+        client_order_id: Some(new_client_order_id()),
+        tp_trigger_price: tp_sl.take_profit.map(|p| p.to_string()),
+        tp_order_price: tp_sl.take_profit.map(|_| MARKET_PRICE.into()),
+        sl_trigger_price: tp_sl.stop_loss.map(|p| p.to_string()),
+        sl_order_price: tp_sl.stop_loss.map(|_| MARKET_PRICE.into()),
+    };
+    let meta = OrderMeta {
+        exchange,
+        price,
+        size,
+        reduce_only,
+        signal_price,
+    };
+    (order, meta)
+}
+
 // ──────────────────────────────────────────────────────────────
 //  Production wrapper (keeps current call-sites unchanged)
 // ──────────────────────────────────────────────────────────────
+pub(crate) async fn prod_client(
+    db: &PgPool,
+    user_id: i64,
+    exchange: Exchange,
+) -> Result<Box<dyn ExchangeAdapter>, TradeError> {
+    exchanges::connect(db, user_id, exchange).await
+}
+
+/// Production [`sync_stop_with`]
+pub async fn sync_stop(
+    db: &PgPool,
+    user_id: i64,
+    symbol: &str,
+    side: &str,
+    want: Option<f64>,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<StopSync, TradeError> {
+    let _in_flight = drain::track();
+    // native stops are only kept on BlowFin so far
+    let adapter = prod_client(db, user_id, Exchange::Blowfin).await?;
+    sync_stop_with(
+        adapter.as_ref(),
+        db,
+        user_id,
+        symbol,
+        side,
+        want,
+        is_demo,
+        master_key,
+    )
+    .await
+}
+
+/// Production [`ApiClient::cancel_by_client_id`]; pulls a resting order
+/// placed through [`execute_trade`]
+#[allow(clippy::too_many_arguments)]
+pub async fn cancel_by_client_id(
+    db: &PgPool,
+    user_id: i64,
+    exchange: Exchange,
+    symbol: &str,
+    client_order_id: &str,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<(), TradeError> {
+    let _in_flight = drain::track();
+    let adapter = prod_client(db, user_id, exchange).await?;
+    adapter
+        .cancel_by_client_id(db, user_id, symbol, client_order_id, is_demo, master_key)
+        .await
+}
+
 pub async fn execute_trade(
     req: TradeRequest,
     db: &PgPool,
@@ -140,19 +641,46 @@ pub async fn execute_trade(
     is_demo: bool,
     master_key: &[u8],
 ) -> Result<TradeResponse, TradeError> {
-    // 1) fetch & decrypt creds
-    let row = ApiKey::get_by_user_and_exchange(db, user_id, "blowfin")
-        .await
-        .map_err(|e| TradeError::Db(e.into()))?        // ← NEW: convert sqlx::Error ➜ TradeError
-        .ok_or(TradeError::MissingKey)?;
-    let creds = row.decrypt(&GLOBAL_CRYPTO)
-        .map_err(|e| TradeError::Api(e.into()))?;                         // map into TradeError
+    // a drain waits for this order to settle before the server stops
+    let _in_flight = drain::track();
+
+    // a suspected account takeover pauses entries until the user confirms
+    if !req.reduce_only {
+        anomaly::check_not_frozen(user_id).await?;
+        // the user's own withdrawal-safe window: exits only, for everyone
+        risk::check_exit_only(db, user_id).await?;
+        // a take-profit that can't pay the round trip is a sure loss
+        fees::check_edge(db, user_id, &req).await?;
+    }
+
+    // 1) fetch & decrypt creds (cached briefly – see `ApiKey::decrypted_cached`)
+    //    for the venue the request names
+    let adapter = prod_client(db, user_id, req.exchange).await?;
 
-    let adapter = BlowfinClient::new(creds);
+    let mut resp = execute_trade_with(
+        req,
+        db,
+        user_id,
+        is_demo,
+        master_key,
+        &ProdRisk,
+        adapter.as_ref(),
+    )
+    .await?;
 
-    execute_trade_with(
-        req, db, user_id, is_demo, master_key, &ProdRisk, &adapter,
-    ).await
+    // 2) keep an order row – rejections included – for execution-quality
+    //    reporting; `order_tracker` follows accepted ones to their fills
+    //    (best effort)
+    if resp.success {
+        if let Some(px) = resp.price.or(resp.mid_at_submit).or(resp.signal_price) {
+            anomaly::record_trade(user_id, resp.size * px);
+        }
+    }
+    match analytics::record_submission(db, user_id, &resp).await {
+        Ok(order_id) => resp.order_id = Some(order_id),
+        Err(e) => tracing::warn!("record_submission for user {user_id}: {e}"),
+    }
+    Ok(resp)
 }
 
 // ======================================================================
@@ -239,6 +767,9 @@ mod tests {
             order_type: "market".into(),
             price: Some(25_000.0),
             size: 0.3,
+            reduce_only: false,
+            signal_price: Some(25_000.0),
+            tp_sl: None,
         }
     }
 
@@ -309,6 +840,258 @@ mod tests {
         assert_eq!(api.order_seen.load(Ordering::SeqCst), 0);
     }
 
+    // ────────────── Mock exchange after a hung submit ──────────────
+    struct HungApi {
+        state: Option<OrderState>,
+        cancels: AtomicUsize,
+    }
+    #[async_trait::async_trait]
+    impl ApiClient for HungApi {
+        async fn place_order(
+            &self,
+            _db: &PgPool,
+            _uid: i64,
+            _o: &OrderRequest,
+            _demo: bool,
+            _k: &[u8],
+        ) -> Result<ApiResponse, TradeError> {
+            unreachable!("settle_timed_out never resubmits")
+        }
+
+        async fn cancel_by_client_id(
+            &self,
+            _db: &PgPool,
+            _uid: i64,
+            _symbol: &str,
+            _coid: &str,
+            _demo: bool,
+            _k: &[u8],
+        ) -> Result<(), TradeError> {
+            self.cancels.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn order_status(
+            &self,
+            _db: &PgPool,
+            _uid: i64,
+            _symbol: &str,
+            coid: &str,
+            _demo: bool,
+            _k: &[u8],
+        ) -> Result<OrderStatus, TradeError> {
+            match self.state {
+                Some(state) => Ok(OrderStatus {
+                    state,
+                    data: json!({ "clientOrderId": coid }),
+                }),
+                None => Err(TradeError::Other("exchange down".into())),
+            }
+        }
+    }
+
+    async fn settle(state: Option<OrderState>) -> (Result<ApiResponse, TradeError>, usize) {
+        let db = lazy_pg_pool();
+        let api = HungApi {
+            state,
+            cancels: AtomicUsize::new(0),
+        };
+        let order = OrderRequest {
+            inst_id: "BTCUSDT".into(),
+            margin_mode: "isolated".into(),
+            side: "buy".into(),
+            order_type: "market".into(),
+            price: None,
+            size: "0.3".into(),
+            reduce_only: None,
+            client_order_id: Some("rrTEST".into()),
+            tp_trigger_price: None,
+            tp_order_price: None,
+            sl_trigger_price: None,
+            sl_order_price: None,
+        };
+        let out = settle_timed_out(&api, &db, 1, &order, false, b"k", Duration::from_secs(1)).await;
+        (out, api.cancels.load(Ordering::SeqCst))
+    }
+
+    // ────────────────────────────────────────────
+    // Timed-out submit: cancel, then report the verified outcome
+    // ────────────────────────────────────────────
+    #[tokio::test]
+    async fn timed_out_order_that_filled_is_reported_as_traded() {
+        let (out, cancels) = settle(Some(OrderState::Filled)).await;
+        let resp = out.expect("fill must not surface as an error");
+        assert_eq!(resp.code, "0");
+        assert_eq!(resp.data["clientOrderId"], "rrTEST");
+        assert_eq!(cancels, 1);
+    }
+
+    #[tokio::test]
+    async fn timed_out_order_that_never_traded_is_a_timeout() {
+        for state in [Some(OrderState::Canceled), Some(OrderState::NotFound)] {
+            let (out, _) = settle(state).await;
+            assert!(matches!(out, Err(TradeError::Timeout(m)) if m.contains("not placed")));
+        }
+    }
+
+    #[tokio::test]
+    async fn unverifiable_outcome_is_a_timeout() {
+        let (out, _) = settle(Some(OrderState::Live)).await;
+        assert!(matches!(out, Err(TradeError::Timeout(m)) if m.contains("still live")));
+        let (out, _) = settle(None).await;
+        assert!(matches!(out, Err(TradeError::Timeout(m)) if m.contains("unknown")));
+    }
+
+    #[test]
+    fn exchange_states_map_to_final_outcomes() {
+        assert_eq!(OrderState::from_exchange("filled"), OrderState::Filled);
+        assert_eq!(
+            OrderState::from_exchange("partially_canceled"),
+            OrderState::PartiallyFilled
+        );
+        assert_eq!(OrderState::from_exchange("canceled"), OrderState::Canceled);
+        assert_eq!(
+            OrderState::from_exchange("order_failed"),
+            OrderState::Canceled
+        );
+        assert_eq!(
+            OrderState::from_exchange("partially_filled"),
+            OrderState::Live
+        );
+        assert_eq!(OrderState::from_exchange("live"), OrderState::Live);
+        let id = new_client_order_id();
+        assert_eq!(id.len(), 32);
+        assert!(id.starts_with("rr"));
+        assert!(id[2..]
+            .bytes()
+            .all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()));
+    }
+
+    // ────────────── Native stops ──────────────
+    /// Exchange book of resting stops
+    #[derive(Default)]
+    struct StopBook(std::sync::Mutex<Vec<NativeStop>>);
+
+    #[async_trait::async_trait]
+    impl ApiClient for StopBook {
+        async fn place_order(
+            &self,
+            _db: &PgPool,
+            _uid: i64,
+            _o: &OrderRequest,
+            _demo: bool,
+            _k: &[u8],
+        ) -> Result<ApiResponse, TradeError> {
+            unreachable!("no orders in stop sync")
+        }
+
+        async fn pending_stops(
+            &self,
+            _db: &PgPool,
+            _uid: i64,
+            _symbol: &str,
+            _demo: bool,
+            _k: &[u8],
+        ) -> Result<Vec<NativeStop>, TradeError> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        async fn place_stop(
+            &self,
+            _db: &PgPool,
+            _uid: i64,
+            stop: &NativeStop,
+            _demo: bool,
+            _k: &[u8],
+        ) -> Result<(), TradeError> {
+            let mut book = self.0.lock().unwrap();
+            let id = format!("sl{}", book.len() + 1);
+            book.push(NativeStop {
+                id: Some(id),
+                ..stop.clone()
+            });
+            Ok(())
+        }
+
+        async fn cancel_stop(
+            &self,
+            _db: &PgPool,
+            _uid: i64,
+            stop: &NativeStop,
+            _demo: bool,
+            _k: &[u8],
+        ) -> Result<(), TradeError> {
+            self.0.lock().unwrap().retain(|s| s.id != stop.id);
+            Ok(())
+        }
+    }
+
+    async fn sync(api: &StopBook, want: Option<f64>) -> StopSync {
+        sync_stop_with(
+            api,
+            &lazy_pg_pool(),
+            1,
+            "BTC-USDT",
+            "sell",
+            want,
+            true,
+            b"k",
+        )
+        .await
+        .unwrap()
+    }
+
+    fn triggers(api: &StopBook) -> Vec<(String, f64)> {
+        let book = api.0.lock().unwrap();
+        book.iter()
+            .map(|s| (s.side.clone(), s.trigger_price))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn stop_sync_places_moves_and_cancels() {
+        let api = StopBook::default();
+        let placed = StopSync {
+            placed: true,
+            cancelled: 0,
+        };
+        assert_eq!(sync(&api, Some(90.0)).await, placed);
+        assert_eq!(sync(&api, Some(90.0)).await, StopSync::default());
+
+        let moved = StopSync {
+            placed: true,
+            cancelled: 1,
+        };
+        assert_eq!(sync(&api, Some(100.0)).await, moved);
+        assert_eq!(triggers(&api), vec![("sell".into(), 100.0)]);
+
+        assert_eq!(sync(&api, None).await.cancelled, 1);
+        assert!(triggers(&api).is_empty());
+    }
+
+    #[tokio::test]
+    async fn stop_sync_drops_duplicates_and_ignores_the_other_side() {
+        let api = StopBook::default();
+        for (id, side, px) in [
+            ("a", "sell", 90.0),
+            ("b", "sell", 90.0),
+            ("c", "buy", 120.0),
+        ] {
+            api.0.lock().unwrap().push(NativeStop {
+                id: Some(id.into()),
+                symbol: "BTC-USDT".into(),
+                side: side.into(),
+                trigger_price: px,
+            });
+        }
+        let out = sync(&api, Some(90.0)).await;
+        assert_eq!((out.placed, out.cancelled), (false, 1));
+        assert_eq!(
+            triggers(&api),
+            vec![("sell".into(), 90.0), ("buy".into(), 120.0)]
+        );
+    }
+
     // ────────────────────────────────────────────
     // Future-proofing: new enum variant placeholder
     // ────────────────────────────────────────────
@@ -319,6 +1102,7 @@ mod tests {
         fn _cover(e: Exchange) {
             match e {
                 Exchange::Blowfin => {}
+                Exchange::Binance => {}
             }
         }
     }
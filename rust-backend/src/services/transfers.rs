@@ -0,0 +1,504 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Deposits & withdrawals (read-only) and flow-adjusted equity curves
+//! ──────────────────────────────────────────────────────────────────────────
+//! * [`sync`] pages BlowFin's deposit / withdrawal history into `transfers`
+//!   – GET endpoints only, nothing is ever moved from here. A later pass
+//!   re-reads the newest page, so pending transfers pick up their final
+//!   state; [`spawn`] runs it for every key holder every `SYNC_EVERY`
+//! * [`equity_curve`] walks the USDT `balances` snapshots (written by the
+//!   exposure dashboard) and takes the settled transfers between two
+//!   snapshots out of their equity change – a deposit is not trading profit
+//! * The return is time-weighted (Modified Dietz per interval, flows counted
+//!   at half weight) and chained, so funding timing doesn't move it either
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+use crate::services::blowfin::{
+    api,
+    dto::{Deposit, Withdrawal},
+};
+use crate::utils::errors::ApiError;
+
+const EXCHANGE: &str = "blowfin";
+/// Rows per history page (BlowFin's max)
+const PAGE: usize = 100;
+/// First sync of an account reads at most this far back
+const MAX_PAGES: usize = 20;
+const SYNC_EVERY: StdDuration = StdDuration::from_secs(6 * 3_600);
+
+#[derive(thiserror::Error, Debug)]
+pub enum TransferError {
+    #[error("exchange: {0}")]
+    Exchange(#[from] ApiError),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    Deposit,
+    Withdrawal,
+}
+
+impl TransferKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransferKind::Deposit => "deposit",
+            TransferKind::Withdrawal => "withdrawal",
+        }
+    }
+}
+
+/// One history row, normalised across both endpoints
+#[derive(Debug, Clone, PartialEq)]
+struct Fetched {
+    transfer_id: String,
+    currency: String,
+    amount: f64,
+    fee: f64,
+    state: String,
+    settled: bool,
+    tx_id: Option<String>,
+    occurred_at: DateTime<Utc>,
+}
+
+fn ms(ts: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(ts).single().unwrap_or_default()
+}
+
+fn non_empty(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
+}
+
+impl From<Deposit> for Fetched {
+    fn from(d: Deposit) -> Self {
+        Fetched {
+            // 1 = credited, 2 = successful
+            settled: matches!(d.state.as_str(), "1" | "2"),
+            transfer_id: d.deposit_id,
+            currency: d.currency,
+            amount: d.amount,
+            fee: 0.0,
+            state: d.state,
+            tx_id: non_empty(d.tx_id),
+            occurred_at: ms(d.ts),
+        }
+    }
+}
+
+impl From<Withdrawal> for Fetched {
+    fn from(w: Withdrawal) -> Self {
+        Fetched {
+            settled: w.state == "2",
+            transfer_id: w.withdraw_id,
+            currency: w.currency,
+            amount: w.amount,
+            fee: w.fee.unwrap_or(0.0),
+            state: w.state,
+            tx_id: non_empty(w.tx_id),
+            occurred_at: ms(w.ts),
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Transfer {
+    pub exchange: String,
+    pub kind: String,
+    pub transfer_id: String,
+    pub currency: String,
+    pub amount: f64,
+    pub fee: f64,
+    pub state: String,
+    pub settled: bool,
+    pub tx_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// New rows per kind from one [`sync`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Synced {
+    pub deposits: u64,
+    pub withdrawals: u64,
+}
+
+async fn fetch_page(
+    db: &PgPool,
+    user_id: i64,
+    kind: TransferKind,
+    after: Option<i64>,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<Vec<Fetched>, ApiError> {
+    Ok(match kind {
+        TransferKind::Deposit => api::get_deposits(db, user_id, is_demo, master_key, after)
+            .await?
+            .into_data()?
+            .into_iter()
+            .map(Fetched::from)
+            .collect(),
+        TransferKind::Withdrawal => api::get_withdrawals(db, user_id, is_demo, master_key, after)
+            .await?
+            .into_data()?
+            .into_iter()
+            .map(Fetched::from)
+            .collect(),
+    })
+}
+
+/// Upsert one row; true when it was new
+async fn store(
+    db: &PgPool,
+    user_id: i64,
+    kind: TransferKind,
+    t: &Fetched,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO transfers
+            (user_id, exchange, kind, transfer_id, currency, amount, fee, state, settled, tx_id, occurred_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (user_id, exchange, kind, transfer_id) DO UPDATE
+           SET state = EXCLUDED.state, settled = EXCLUDED.settled,
+               fee = EXCLUDED.fee, tx_id = EXCLUDED.tx_id, synced_at = now()
+        RETURNING (xmax = 0)
+        "#,
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .bind(kind.as_str())
+    .bind(&t.transfer_id)
+    .bind(&t.currency)
+    .bind(t.amount)
+    .bind(t.fee)
+    .bind(&t.state)
+    .bind(t.settled)
+    .bind(&t.tx_id)
+    .bind(t.occurred_at)
+    .fetch_one(db)
+    .await
+}
+
+async fn sync_kind(
+    db: &PgPool,
+    user_id: i64,
+    kind: TransferKind,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<u64, TransferError> {
+    // everything settled up to here is final; page back until we reach it
+    let known: Option<DateTime<Utc>> = sqlx::query_scalar(
+        "SELECT max(occurred_at) FROM transfers WHERE user_id = $1 AND exchange = $2 AND kind = $3 AND settled",
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .bind(kind.as_str())
+    .fetch_one(db)
+    .await?;
+
+    let mut added = 0;
+    let mut after = None;
+    for _ in 0..MAX_PAGES {
+        let page = fetch_page(db, user_id, kind, after, is_demo, master_key).await?;
+        for t in &page {
+            added += store(db, user_id, kind, t).await? as u64;
+        }
+        let Some(oldest) = page.iter().map(|t| t.occurred_at).min() else {
+            break;
+        };
+        if page.len() < PAGE || known.is_some_and(|k| oldest <= k) {
+            break;
+        }
+        after = Some(oldest.timestamp_millis());
+    }
+    Ok(added)
+}
+
+/// Pull the user's deposit and withdrawal history from BlowFin
+pub async fn sync(
+    db: &PgPool,
+    user_id: i64,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<Synced, TransferError> {
+    Ok(Synced {
+        deposits: sync_kind(db, user_id, TransferKind::Deposit, is_demo, master_key).await?,
+        withdrawals: sync_kind(db, user_id, TransferKind::Withdrawal, is_demo, master_key).await?,
+    })
+}
+
+pub async fn list(
+    db: &PgPool,
+    user_id: i64,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<Transfer>, sqlx::Error> {
+    sqlx::query_as::<_, Transfer>(
+        r#"
+        SELECT exchange, kind, transfer_id, currency, amount::float8 AS amount, fee::float8 AS fee,
+               state, settled, tx_id, occurred_at
+          FROM transfers
+         WHERE user_id = $1 AND occurred_at >= $2 AND occurred_at < $3
+         ORDER BY occurred_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(until)
+    .fetch_all(db)
+    .await
+}
+
+/// Sync every account holding BlowFin keys, forever
+pub fn spawn(db: PgPool, is_demo: bool) {
+    tokio::spawn(async move {
+        let master_key = std::env::var("MASTER_KEY").unwrap_or_default().into_bytes();
+        let mut tick = tokio::time::interval(SYNC_EVERY);
+        loop {
+            tick.tick().await;
+            let users: Vec<i64> =
+                match sqlx::query_scalar("SELECT user_id FROM api_keys WHERE exchange = $1")
+                    .bind(EXCHANGE)
+                    .fetch_all(&db)
+                    .await
+                {
+                    Ok(u) => u,
+                    Err(e) => {
+                        tracing::error!("transfer sync: DB error: {e}");
+                        continue;
+                    }
+                };
+            for user_id in users {
+                // one bad key must not hold up the rest
+                if let Err(e) = sync(&db, user_id, is_demo, &master_key).await {
+                    tracing::warn!("transfer sync: {user_id}: {e}");
+                }
+            }
+        }
+    });
+}
+
+/// ─── Equity curve ────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    pub at: DateTime<Utc>,
+    pub equity: f64,
+}
+
+/// Signed: positive into the account, negative out (incl. fees)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flow {
+    pub at: DateTime<Utc>,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EquityPoint {
+    pub at: DateTime<Utc>,
+    pub equity: f64,
+    /// Cumulative since the first point
+    pub net_deposits: f64,
+    /// Cumulative since the first point: equity change less net deposits
+    pub trading_pnl: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EquityCurve {
+    pub points: Vec<EquityPoint>,
+    pub net_deposits: f64,
+    pub trading_pnl: f64,
+    /// Time-weighted, in percent
+    pub return_pct: f64,
+}
+
+/// Split each snapshot-to-snapshot equity change into flows and trading.
+/// `snaps` and `flows` oldest first; flows outside the snapshot span are
+/// already in (or not yet in) the equity and are ignored.
+pub fn reconstruct(snaps: &[Snapshot], flows: &[Flow]) -> EquityCurve {
+    let mut points = Vec::with_capacity(snaps.len());
+    let (mut net_deposits, mut trading_pnl, mut growth) = (0.0, 0.0, 1.0);
+    let mut flows = flows.iter().peekable();
+
+    for (i, s) in snaps.iter().enumerate() {
+        if i > 0 {
+            let prev = snaps[i - 1];
+            let mut flow = 0.0;
+            while let Some(f) = flows.next_if(|f| f.at <= s.at) {
+                if f.at > prev.at {
+                    flow += f.amount;
+                }
+            }
+            let pnl = s.equity - prev.equity - flow;
+            let base = prev.equity + flow / 2.0;
+            if base > 0.0 {
+                growth *= 1.0 + pnl / base;
+            }
+            net_deposits += flow;
+            trading_pnl += pnl;
+        }
+        points.push(EquityPoint {
+            at: s.at,
+            equity: s.equity,
+            net_deposits,
+            trading_pnl,
+        });
+    }
+    EquityCurve {
+        points,
+        net_deposits,
+        trading_pnl,
+        return_pct: (growth - 1.0) * 100.0,
+    }
+}
+
+/// The USDT account's equity curve over `[since, until)`
+pub async fn equity_curve(
+    db: &PgPool,
+    user_id: i64,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<EquityCurve, sqlx::Error> {
+    let snaps: Vec<Snapshot> = sqlx::query_as::<_, (DateTime<Utc>, f64)>(
+        r#"
+        SELECT captured_at, equity::float8
+          FROM balances
+         WHERE user_id = $1 AND exchange = $2 AND currency = 'USDT'
+           AND equity IS NOT NULL AND captured_at >= $3 AND captured_at < $4
+         ORDER BY captured_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .bind(since)
+    .bind(until)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|(at, equity)| Snapshot { at, equity })
+    .collect();
+
+    let flows: Vec<Flow> = sqlx::query_as::<_, (DateTime<Utc>, f64)>(
+        r#"
+        SELECT occurred_at,
+               (CASE WHEN kind = 'deposit' THEN amount ELSE -(amount + fee) END)::float8
+          FROM transfers
+         WHERE user_id = $1 AND exchange = $2 AND currency = 'USDT' AND settled
+           AND occurred_at >= $3 AND occurred_at < $4
+         ORDER BY occurred_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(EXCHANGE)
+    .bind(since)
+    .bind(until)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|(at, amount)| Flow { at, amount })
+    .collect();
+
+    Ok(reconstruct(&snaps, &flows))
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn at(h: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::hours(h)
+    }
+
+    #[test]
+    fn a_deposit_is_not_trading_profit() {
+        let snaps = [
+            Snapshot {
+                at: at(0),
+                equity: 1_000.0,
+            },
+            Snapshot {
+                at: at(1),
+                equity: 1_100.0,
+            },
+            Snapshot {
+                at: at(2),
+                equity: 2_100.0,
+            },
+        ];
+        let flows = [Flow {
+            at: at(1) + Duration::minutes(30),
+            amount: 1_000.0,
+        }];
+        let c = reconstruct(&snaps, &flows);
+
+        assert_eq!(c.net_deposits, 1_000.0);
+        assert_eq!(c.trading_pnl, 100.0);
+        assert_eq!(c.points[2].trading_pnl, 100.0);
+        // +10 %, then flat
+        assert!((c.return_pct - 10.0).abs() < 1e-9, "{}", c.return_pct);
+    }
+
+    #[test]
+    fn withdrawals_and_out_of_range_flows() {
+        let snaps = [
+            Snapshot {
+                at: at(0),
+                equity: 1_000.0,
+            },
+            Snapshot {
+                at: at(1),
+                equity: 450.0,
+            },
+        ];
+        let flows = [
+            // before the first snapshot: already in its equity
+            Flow {
+                at: at(-1),
+                amount: 500.0,
+            },
+            Flow {
+                at: at(0) + Duration::minutes(1),
+                amount: -501.0,
+            },
+            // after the last snapshot: not in any equity yet
+            Flow {
+                at: at(2),
+                amount: 300.0,
+            },
+        ];
+        let c = reconstruct(&snaps, &flows);
+        assert_eq!(c.net_deposits, -501.0);
+        assert!((c.trading_pnl + 49.0).abs() < 1e-9);
+        assert!(c.return_pct < 0.0);
+    }
+
+    #[test]
+    fn settled_states() {
+        let d = |state: &str| Deposit {
+            deposit_id: "d".into(),
+            currency: "USDT".into(),
+            amount: 1.0,
+            chain: String::new(),
+            tx_id: String::new(),
+            state: state.into(),
+            ts: 1_697_030_101_187,
+        };
+        assert!(!Fetched::from(d("0")).settled);
+        assert!(Fetched::from(d("2")).settled);
+        assert_eq!(Fetched::from(d("2")).tx_id, None);
+        assert_eq!(
+            Fetched::from(d("2")).occurred_at.timestamp_millis(),
+            1_697_030_101_187
+        );
+    }
+}
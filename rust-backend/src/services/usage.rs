@@ -0,0 +1,431 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Usage & quota accounting
+//! ──────────────────────────────────────────────────────────────────────────
+//! Counters live in the cache (`db::cache`) under `usage:{uid}:…`:
+//!
+//! | key                          | kind    | maintained by                     |
+//! |------------------------------|---------|-----------------------------------|
+//! | `api:{YYYYMMDD}`             | counter | `UsageCounter` middleware         |
+//! | `ep:{YYYYMMDDHH}`            | set     | `UsageCounter` – routes hit       |
+//! | `ep:{YYYYMMDDHH}:{route}`    | counter | `UsageCounter` – calls per route  |
+//! | `backtest_min:{YYYYMM}`      | counter | `add_backtest_minutes`            |
+//! | `strategies`                 | gauge   | seeded from Postgres, invalidated |
+//! | `copy`                       | gauge   |   on start/stop & follow/unfollow |
+//!
+//! Gauges are a cache of a Postgres `COUNT(*)`; writers simply drop the key
+//! so the next read reseeds it – no INCR/DECR drift.
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::db::cache::{Cache, CacheError};
+
+/// Daily counters outlive their day a little so late reads still work
+const DAY_TTL_SECS: u64 = 2 * 24 * 3600;
+const MONTH_TTL_SECS: u64 = 35 * 24 * 3600;
+const GAUGE_TTL_SECS: u64 = 600;
+/// Hourly per-route counters back the last-24 h activity view
+const HOUR_TTL_SECS: u64 = 26 * 3600;
+pub const ACTIVITY_HOURS: i64 = 24;
+
+/// ─── Plans ───────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Plan {
+    Free,
+    Pro,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PlanLimits {
+    pub active_strategies: i64,
+    pub api_calls_per_day: i64,
+    pub copy_relations: i64,
+    pub backtest_minutes_per_month: i64,
+    /// Backtest jobs of one user running at once; more wait in the queue
+    pub concurrent_backtests: i64,
+}
+
+impl Plan {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Plan::Free => "free",
+            Plan::Pro => "pro",
+        }
+    }
+
+    /// Unknown names fall back to `Free` – never grant more than we know
+    pub fn parse(s: &str) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "pro" => Plan::Pro,
+            _ => Plan::Free,
+        }
+    }
+
+    pub fn limits(self) -> PlanLimits {
+        match self {
+            Plan::Free => PlanLimits {
+                active_strategies: 3,
+                api_calls_per_day: 5_000,
+                copy_relations: 1,
+                backtest_minutes_per_month: 60,
+                concurrent_backtests: 1,
+            },
+            Plan::Pro => PlanLimits {
+                active_strategies: 25,
+                api_calls_per_day: 100_000,
+                copy_relations: 10,
+                backtest_minutes_per_month: 1_000,
+                concurrent_backtests: 4,
+            },
+        }
+    }
+}
+
+/// Current plan from `user_plans` (kept in sync by `services::billing`);
+/// no row or a DB hiccup means `Free`.
+pub async fn plan_for(db: &PgPool, user_id: i64) -> Plan {
+    match sqlx::query_scalar::<_, String>("SELECT plan FROM user_plans WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+    {
+        Ok(Some(p)) => Plan::parse(&p),
+        Ok(None) => Plan::Free,
+        Err(e) => {
+            tracing::warn!("plan_for {user_id}: {e} – assuming free");
+            Plan::Free
+        }
+    }
+}
+
+/// ─── Keys & periods ──────────────────────────────────────────────────────
+fn key(user_id: i64, suffix: &str) -> String {
+    format!("usage:{user_id}:{suffix}")
+}
+
+fn api_key(user_id: i64, now: DateTime<Utc>) -> String {
+    key(user_id, &format!("api:{}", now.format("%Y%m%d")))
+}
+
+fn backtest_key(user_id: i64, now: DateTime<Utc>) -> String {
+    key(user_id, &format!("backtest_min:{}", now.format("%Y%m")))
+}
+
+fn endpoints_key(user_id: i64, hour: DateTime<Utc>) -> String {
+    key(user_id, &format!("ep:{}", hour.format("%Y%m%d%H")))
+}
+
+fn endpoint_key(user_id: i64, hour: DateTime<Utc>, route: &str) -> String {
+    format!("{}:{route}", endpoints_key(user_id, hour))
+}
+
+fn strategies_key(user_id: i64) -> String {
+    key(user_id, "strategies")
+}
+
+fn copy_key(user_id: i64) -> String {
+    key(user_id, "copy")
+}
+
+/// Midnight UTC after `now` (daily counters roll over here)
+pub fn next_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.from_utc_datetime(
+        &(now.date_naive() + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight"),
+    )
+}
+
+/// First instant of the next calendar month (UTC)
+pub fn next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (y, m) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    Utc.from_utc_datetime(
+        &NaiveDate::from_ymd_opt(y, m, 1)
+            .expect("valid month")
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight"),
+    )
+}
+
+/// ─── Writers ─────────────────────────────────────────────────────────────
+async fn incr(cache: &dyn Cache, key: &str, by: i64, ttl: u64) -> Result<i64, CacheError> {
+    let n = cache.incr_by(key, by).await?;
+    if n == by {
+        // first write in this period
+        cache.expire(key, ttl).await?;
+    }
+    Ok(n)
+}
+
+pub async fn record_api_call(cache: &dyn Cache, user_id: i64) -> Result<i64, CacheError> {
+    incr(cache, &api_key(user_id, Utc::now()), 1, DAY_TTL_SECS).await
+}
+
+/// One call to `route` (method + matched pattern, e.g. `POST /api/trade`)
+pub async fn record_endpoint_call(
+    cache: &dyn Cache,
+    user_id: i64,
+    route: &str,
+) -> Result<i64, CacheError> {
+    let now = Utc::now();
+    let n = incr(cache, &endpoint_key(user_id, now, route), 1, HOUR_TTL_SECS).await?;
+    if n == 1 {
+        let set = endpoints_key(user_id, now);
+        cache.sadd(&set, &[route.to_string()]).await?;
+        cache.expire(&set, HOUR_TTL_SECS).await?;
+    }
+    Ok(n)
+}
+
+pub async fn add_backtest_minutes(
+    cache: &dyn Cache,
+    user_id: i64,
+    minutes: i64,
+) -> Result<i64, CacheError> {
+    incr(
+        cache,
+        &backtest_key(user_id, Utc::now()),
+        minutes,
+        MONTH_TTL_SECS,
+    )
+    .await
+}
+
+/// Drop the cached active-strategy gauge (call after start/stop)
+pub async fn invalidate_strategies(cache: &dyn Cache, user_id: i64) {
+    if let Err(e) = cache.del(&strategies_key(user_id)).await {
+        tracing::warn!("usage: invalidate strategies for {user_id}: {e}");
+    }
+}
+
+/// Drop the cached copy-relation gauge (call after follow/unfollow)
+pub async fn invalidate_copy(cache: &dyn Cache, user_id: i64) {
+    if let Err(e) = cache.del(&copy_key(user_id)).await {
+        tracing::warn!("usage: invalidate copy for {user_id}: {e}");
+    }
+}
+
+/// ─── Readers ─────────────────────────────────────────────────────────────
+#[derive(Debug, thiserror::Error)]
+pub enum UsageError {
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("cache: {0}")]
+    Cache(#[from] CacheError),
+}
+
+/// Cached gauge; on miss run `sql` (a `COUNT(*)` bound to `$1 = user_id`)
+async fn gauge(
+    db: &PgPool,
+    cache: &dyn Cache,
+    key: &str,
+    user_id: i64,
+    sql: &str,
+) -> Result<i64, UsageError> {
+    if let Some(n) = cache.get(key).await?.and_then(|s| s.parse().ok()) {
+        return Ok(n);
+    }
+    let n: i64 = sqlx::query_scalar(sql).bind(user_id).fetch_one(db).await?;
+    cache.set(key, &n.to_string(), GAUGE_TTL_SECS).await?;
+    Ok(n)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Quota {
+    pub used: i64,
+    pub limit: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub plan: Plan,
+    pub active_strategies: Quota,
+    pub api_calls_today: Quota,
+    pub copy_relations: Quota,
+    pub backtest_minutes: Quota,
+    /// When the daily API counter starts over
+    pub api_calls_reset_at: DateTime<Utc>,
+    /// When the monthly backtest budget starts over
+    pub backtest_reset_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RouteActivity {
+    pub route: String,
+    pub calls: i64,
+    /// Calls in the hour so far
+    pub last_hour: i64,
+}
+
+/// Calls per route over the last [`ACTIVITY_HOURS`], busiest first
+pub async fn endpoint_activity(
+    cache: &dyn Cache,
+    user_id: i64,
+) -> Result<Vec<RouteActivity>, CacheError> {
+    let now = Utc::now();
+    let mut by_route: std::collections::BTreeMap<String, RouteActivity> = Default::default();
+    for h in 0..ACTIVITY_HOURS {
+        let hour = now - Duration::hours(h);
+        for route in cache.smembers(&endpoints_key(user_id, hour)).await? {
+            let n = cache.get_i64(&endpoint_key(user_id, hour, &route)).await?;
+            let row = by_route.entry(route.clone()).or_insert(RouteActivity {
+                route,
+                calls: 0,
+                last_hour: 0,
+            });
+            row.calls += n;
+            if h == 0 {
+                row.last_hour = n;
+            }
+        }
+    }
+    let mut rows: Vec<_> = by_route.into_values().collect();
+    rows.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.route.cmp(&b.route)));
+    Ok(rows)
+}
+
+pub async fn usage_report(
+    db: &PgPool,
+    cache: &dyn Cache,
+    user_id: i64,
+) -> Result<UsageReport, UsageError> {
+    let now = Utc::now();
+    let plan = plan_for(db, user_id).await;
+    let limits = plan.limits();
+
+    let strategies = gauge(
+        db,
+        cache,
+        &strategies_key(user_id),
+        user_id,
+        "SELECT COUNT(*) FROM user_strategies WHERE user_id = $1 AND status = 'enabled'",
+    )
+    .await?;
+    let copies = gauge(
+        db,
+        cache,
+        &copy_key(user_id),
+        user_id,
+        "SELECT COUNT(*) FROM copy_relations WHERE follower_user_id = $1 AND status IN ('active', 'trial')",
+    )
+    .await?;
+
+    Ok(UsageReport {
+        plan,
+        active_strategies: Quota {
+            used: strategies,
+            limit: limits.active_strategies,
+        },
+        api_calls_today: Quota {
+            used: cache.get_i64(&api_key(user_id, now)).await?,
+            limit: limits.api_calls_per_day,
+        },
+        copy_relations: Quota {
+            used: copies,
+            limit: limits.copy_relations,
+        },
+        backtest_minutes: Quota {
+            used: cache.get_i64(&backtest_key(user_id, now)).await?,
+            limit: limits.backtest_minutes_per_month,
+        },
+        api_calls_reset_at: next_day(now),
+        backtest_reset_at: next_month(now),
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn keys_are_period_scoped() {
+        let t = at(2025, 7, 16, 13);
+        assert_eq!(api_key(42, t), "usage:42:api:20250716");
+        assert_eq!(backtest_key(42, t), "usage:42:backtest_min:202507");
+        assert_eq!(strategies_key(42), "usage:42:strategies");
+        assert_eq!(endpoints_key(42, t), "usage:42:ep:2025071613");
+        assert_eq!(
+            endpoint_key(42, t, "POST /api/trade"),
+            "usage:42:ep:2025071613:POST /api/trade"
+        );
+    }
+
+    #[tokio::test]
+    async fn endpoint_activity_sums_routes() {
+        let cache = crate::db::cache::MemoryCache::new();
+        for _ in 0..3 {
+            record_endpoint_call(&cache, 7, "POST /api/trade")
+                .await
+                .unwrap();
+        }
+        record_endpoint_call(&cache, 7, "GET /api/usage")
+            .await
+            .unwrap();
+        record_endpoint_call(&cache, 8, "GET /api/usage")
+            .await
+            .unwrap();
+
+        let rows = endpoint_activity(&cache, 7).await.unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                RouteActivity {
+                    route: "POST /api/trade".into(),
+                    calls: 3,
+                    last_hour: 3
+                },
+                RouteActivity {
+                    route: "GET /api/usage".into(),
+                    calls: 1,
+                    last_hour: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_boundaries() {
+        assert_eq!(
+            next_day(at(2025, 7, 31, 23)),
+            at(2025, 8, 1, 0) - Duration::minutes(30)
+        );
+        assert_eq!(
+            next_month(at(2025, 7, 16, 1)),
+            Utc.with_ymd_and_hms(2025, 8, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_month(at(2025, 12, 5, 1)),
+            Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn plan_parse_is_conservative() {
+        assert_eq!(Plan::parse("PRO"), Plan::Pro);
+        assert_eq!(Plan::parse(Plan::Free.as_str()), Plan::Free);
+        assert_eq!(Plan::parse("enterprise"), Plan::Free);
+    }
+
+    #[test]
+    fn pro_limits_exceed_free() {
+        let (f, p) = (Plan::Free.limits(), Plan::Pro.limits());
+        assert!(p.active_strategies > f.active_strategies);
+        assert!(p.api_calls_per_day > f.api_calls_per_day);
+        assert!(p.copy_relations > f.copy_relations);
+        assert!(p.backtest_minutes_per_month > f.backtest_minutes_per_month);
+    }
+}
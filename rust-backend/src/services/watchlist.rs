@@ -0,0 +1,176 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! Symbol watchlists
+//! ──────────────────────────────────────────────────────────────────────────
+//! * Users keep up to `MAX_SYMBOLS` canonical symbols (`BTC-USDT`)
+//! * `all_symbols` is the union the market-data layer subscribes to; edits
+//!   wake the feed through `subscribe()` (other instances pick them up on
+//!   their next periodic refresh)
+//! * `list` attaches the latest book mid so UI & strategies get prices in
+//!   one call
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use tokio::sync::watch;
+
+use crate::services::analytics;
+
+pub const MAX_SYMBOLS: i64 = 50;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WatchlistError {
+    #[error("invalid symbol `{0}` (expected e.g. BTC-USDT)")]
+    InvalidSymbol(String),
+    #[error("watchlist is full (max {MAX_SYMBOLS} symbols)")]
+    Full,
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WatchItem {
+    pub symbol: String,
+    pub added_at: DateTime<Utc>,
+    /// Latest book mid, if a feed has reported one
+    #[sqlx(skip)]
+    pub last_price: Option<f64>,
+}
+
+/// Bumped on every local edit
+static VERSION: Lazy<watch::Sender<u64>> = Lazy::new(|| watch::channel(0).0);
+
+/// `changed()` on the receiver resolves after the next local edit
+pub fn subscribe() -> watch::Receiver<u64> {
+    VERSION.subscribe()
+}
+
+fn bump() {
+    VERSION.send_modify(|v| *v += 1);
+}
+
+/// "btc-usdt", "BTC/USDT", "btc_usdt" → "BTC-USDT"
+pub fn normalize(symbol: &str) -> Result<String, WatchlistError> {
+    let s = symbol.trim().to_ascii_uppercase().replace(['/', '_'], "-");
+    let ok = (3..=24).contains(&s.len())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !s.starts_with('-')
+        && !s.ends_with('-')
+        && !s.contains("--");
+    if ok {
+        Ok(s)
+    } else {
+        Err(WatchlistError::InvalidSymbol(symbol.to_string()))
+    }
+}
+
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<WatchItem>, WatchlistError> {
+    let mut items = sqlx::query_as::<_, WatchItem>(
+        "SELECT symbol, added_at FROM user_watchlist WHERE user_id = $1 ORDER BY added_at",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    for item in &mut items {
+        item.last_price = analytics::mid_for(&item.symbol);
+    }
+    Ok(items)
+}
+
+/// `Ok(false)` when the symbol was already on the list
+pub async fn add(db: &PgPool, user_id: i64, symbol: &str) -> Result<bool, WatchlistError> {
+    let symbol = normalize(symbol)?;
+    let mut tx = db.begin().await?;
+    // serialise concurrent adds for this user so the cap holds
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let (count, present): (i64, bool) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*), COALESCE(BOOL_OR(symbol = $2), false)
+          FROM user_watchlist
+         WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&symbol)
+    .fetch_one(&mut *tx)
+    .await?;
+    if present {
+        return Ok(false);
+    }
+    if count >= MAX_SYMBOLS {
+        return Err(WatchlistError::Full);
+    }
+
+    sqlx::query("INSERT INTO user_watchlist (user_id, symbol) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(&symbol)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    bump();
+    Ok(true)
+}
+
+/// `Ok(false)` when the symbol wasn't on the list
+pub async fn remove(db: &PgPool, user_id: i64, symbol: &str) -> Result<bool, WatchlistError> {
+    let symbol = normalize(symbol)?;
+    let removed = sqlx::query("DELETE FROM user_watchlist WHERE user_id = $1 AND symbol = $2")
+        .bind(user_id)
+        .bind(&symbol)
+        .execute(db)
+        .await?
+        .rows_affected()
+        == 1;
+    if removed {
+        bump();
+    }
+    Ok(removed)
+}
+
+/// Every symbol on anyone's watchlist, sorted
+pub async fn all_symbols(db: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT DISTINCT symbol FROM user_watchlist ORDER BY symbol")
+        .fetch_all(db)
+        .await
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_are_canonicalised() {
+        assert_eq!(normalize("btc-usdt").unwrap(), "BTC-USDT");
+        assert_eq!(normalize(" eth/usdt ").unwrap(), "ETH-USDT");
+        assert_eq!(normalize("sol_usdt").unwrap(), "SOL-USDT");
+        assert_eq!(normalize("BTC-USDT-SWAP").unwrap(), "BTC-USDT-SWAP");
+    }
+
+    #[test]
+    fn junk_symbols_are_rejected() {
+        for bad in [
+            "",
+            "ab",
+            "BTC USDT",
+            "-BTC",
+            "BTC-",
+            "BTC--USDT",
+            "BTC;DROP",
+            &"X".repeat(25),
+        ] {
+            assert!(
+                matches!(normalize(bad), Err(WatchlistError::InvalidSymbol(_))),
+                "{bad:?}"
+            );
+        }
+    }
+}
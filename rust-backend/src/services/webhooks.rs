@@ -0,0 +1,428 @@
+//! ──────────────────────────────────────────────────────────────────────────
+//! TradingView alerts – trading from a webhook
+//! ──────────────────────────────────────────────────────────────────────────
+//! * A user mints secret tokens ([`issue`]) and puts one in the alert's
+//!   message; TradingView can't sign or set headers, so the token in the
+//!   body is the credential. Only its SHA-256 is stored, and it can be
+//!   revoked at any time
+//! * An alert names a symbol and a side – `buy`/`long`, `sell`/`short`, or
+//!   `close`/`exit`/`flat` – and, for entries, either a `size` in contracts
+//!   or a `risk_pct` of equity to lose at `stop` ([`portfolio::size_for`]);
+//!   the stop then rests on the exchange with the order
+//! * Entries pass the draw-down guard, then `execute_trade` with its usual
+//!   pre-trade checks; exits are reduce-only closes of the live position
+//!   (`positions::close`), all of it or `percent` of it
+//! * TradingView's `EXCHANGE:SYMBOL` and `.P` perpetual suffix are stripped
+//!
+//! ──────────────────────────────────────────────────────────────────────────
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    db::cache::Cache,
+    services::{
+        analytics, instruments, portfolio,
+        positions::{self, CloseAmount, CloseError},
+        risk,
+        trading_engine::{execute_trade, Exchange, TpSl, TradeRequest, TradeResponse},
+    },
+    utils::errors::TradeError,
+};
+
+/// Live tokens per user
+const MAX_TOKENS: i64 = 5;
+const MAX_LABEL_LEN: usize = 64;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookError {
+    #[error("unknown or revoked webhook token")]
+    BadToken,
+    #[error("{0}")]
+    Invalid(String),
+    #[error("at most {MAX_TOKENS} webhook tokens")]
+    TooMany,
+    #[error("webhook token not found")]
+    NotFound,
+    #[error("{0}")]
+    Trade(#[from] TradeError),
+    #[error("{0}")]
+    Close(#[from] CloseError),
+    #[error("db: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+/// A token as listed – never the secret
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TokenInfo {
+    pub token_id: Uuid,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// A fresh token; `token` is shown this once
+#[derive(Debug, Clone, Serialize)]
+pub struct Issued {
+    #[serde(flatten)]
+    pub info: TokenInfo,
+    pub token: String,
+}
+
+/// Whose alert it is
+#[derive(Debug, Clone, Copy, FromRow)]
+pub struct Caller {
+    pub token_id: Uuid,
+    pub user_id: i64,
+}
+
+/// One alert message, e.g. `{"token": "…", "symbol": "{{ticker}}",
+/// "side": "{{strategy.order.action}}", "risk_pct": 1, "stop": 61250,
+/// "price": {{close}}}`
+#[derive(Clone, Deserialize)]
+pub struct Alert {
+    pub token: String,
+    pub symbol: String,
+    pub side: String,
+    /// Contracts
+    #[serde(default)]
+    pub size: Option<f64>,
+    /// Percent of equity lost if `stop` trades; instead of `size`
+    #[serde(default)]
+    pub risk_pct: Option<f64>,
+    #[serde(default)]
+    pub stop: Option<f64>,
+    #[serde(default)]
+    pub take_profit: Option<f64>,
+    /// Price the alert fired at; the book mid when absent
+    #[serde(default)]
+    pub price: Option<f64>,
+    /// `market` (default) or `limit` at `price`
+    #[serde(default)]
+    pub order_type: Option<String>,
+    /// Exits: percent of the position to close; all of it when absent
+    #[serde(default)]
+    pub percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Buy,
+    Sell,
+    Exit,
+}
+
+impl Action {
+    pub fn parse(side: &str) -> Option<Self> {
+        match side.trim().to_ascii_lowercase().as_str() {
+            "buy" | "long" => Some(Action::Buy),
+            "sell" | "short" => Some(Action::Sell),
+            "close" | "exit" | "flat" => Some(Action::Exit),
+            _ => None,
+        }
+    }
+}
+
+/// What an alert did
+#[derive(Debug, Clone, Serialize)]
+pub struct Executed {
+    pub action: Action,
+    pub symbol: String,
+    pub fill: TradeResponse,
+}
+
+fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// "BINANCE:BTCUSDT.P", "btc-usdt" → the exchange's instrument id when it
+/// is listed, else the bare symbol
+pub fn symbol_for(ticker: &str) -> String {
+    let bare = ticker.rsplit(':').next().unwrap_or(ticker).trim();
+    let bare = bare
+        .strip_suffix(".P")
+        .or_else(|| bare.strip_suffix(".p"))
+        .unwrap_or(bare);
+    instruments::get(bare)
+        .map(|i| i.inst_id)
+        .unwrap_or_else(|| bare.to_ascii_uppercase())
+}
+
+/// Contracts an entry alert asks for; `equity` is only needed for
+/// `risk_pct`
+pub fn entry_size(
+    alert: &Alert,
+    action: Action,
+    price: Option<f64>,
+    equity: Option<f64>,
+) -> Result<f64, WebhookError> {
+    let invalid = |m: &str| Err(WebhookError::Invalid(m.into()));
+    match (alert.size, alert.risk_pct) {
+        (Some(_), Some(_)) => invalid("give either size or risk_pct, not both"),
+        (None, None) => invalid("an entry needs size or risk_pct"),
+        (Some(s), None) if !(s.is_finite() && s > 0.0) => invalid("size must be positive"),
+        (Some(s), None) => Ok(s),
+        (None, Some(p)) if !(p > 0.0 && p <= 100.0) => invalid("risk_pct must be in (0, 100]"),
+        (None, Some(p)) => {
+            let Some(stop) = alert.stop else {
+                return invalid("risk_pct needs a stop");
+            };
+            let Some(price) = price else {
+                return invalid("no price to size against – send price");
+            };
+            let wrong_side = match action {
+                Action::Buy => stop >= price,
+                _ => stop <= price,
+            };
+            if wrong_side {
+                return invalid("stop is on the wrong side of price");
+            }
+            let Some(equity) = equity else {
+                return invalid("account equity unknown – send size instead");
+            };
+            portfolio::size_for(equity, p / 100.0, (price - stop).abs())
+                .ok_or_else(|| WebhookError::Invalid("cannot size the entry".into()))
+        }
+    }
+}
+
+/// `size` rounded down to the lot step, if one is known
+fn floor_to_lot(size: f64, step: Option<f64>) -> f64 {
+    match step.filter(|s| *s > 0.0) {
+        Some(step) => {
+            let lots = (size / step + 1e-9).floor() * step;
+            format!("{lots:.*}", instruments::decimals(step) as usize)
+                .parse()
+                .unwrap_or(lots)
+        }
+        None => size,
+    }
+}
+
+// ─── Tokens ──────────────────────────────────────────────────────────────
+
+pub async fn issue(
+    db: &PgPool,
+    user_id: i64,
+    label: Option<String>,
+) -> Result<Issued, WebhookError> {
+    let label = label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty());
+    if label.as_ref().is_some_and(|l| l.len() > MAX_LABEL_LEN) {
+        return Err(WebhookError::Invalid(format!(
+            "label is longer than {MAX_LABEL_LEN} bytes"
+        )));
+    }
+    let live: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM webhook_tokens WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+    if live >= MAX_TOKENS {
+        return Err(WebhookError::TooMany);
+    }
+
+    let token = new_token();
+    let info = sqlx::query_as::<_, TokenInfo>(
+        r#"
+        INSERT INTO webhook_tokens (user_id, token_hash, label)
+        VALUES ($1, $2, $3)
+        RETURNING token_id, label, created_at, last_used_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash(&token))
+    .bind(label)
+    .fetch_one(db)
+    .await?;
+    Ok(Issued { info, token })
+}
+
+pub async fn list(db: &PgPool, user_id: i64) -> Result<Vec<TokenInfo>, sqlx::Error> {
+    sqlx::query_as::<_, TokenInfo>(
+        r#"
+        SELECT token_id, label, created_at, last_used_at
+          FROM webhook_tokens
+         WHERE user_id = $1 AND revoked_at IS NULL
+         ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn revoke(db: &PgPool, user_id: i64, token_id: Uuid) -> Result<bool, sqlx::Error> {
+    let done = sqlx::query(
+        r#"
+        UPDATE webhook_tokens SET revoked_at = now()
+         WHERE token_id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(token_id)
+    .bind(user_id)
+    .execute(db)
+    .await?;
+    Ok(done.rows_affected() > 0)
+}
+
+/// The live token `token` is, marked as used; an account pending deletion
+/// has none
+pub async fn resolve(db: &PgPool, token: &str) -> Result<Caller, WebhookError> {
+    sqlx::query_as::<_, Caller>(
+        r#"
+        UPDATE webhook_tokens t SET last_used_at = now()
+          FROM users u
+         WHERE t.token_hash = $1 AND t.revoked_at IS NULL
+           AND u.user_id = t.user_id AND u.deleted_at IS NULL
+        RETURNING t.token_id, t.user_id
+        "#,
+    )
+    .bind(hash(token.trim()))
+    .fetch_optional(db)
+    .await?
+    .ok_or(WebhookError::BadToken)
+}
+
+// ─── Alerts ──────────────────────────────────────────────────────────────
+
+/// Carry out `alert` for `user_id`
+pub async fn execute(
+    db: &PgPool,
+    cache: &dyn Cache,
+    user_id: i64,
+    alert: &Alert,
+    is_demo: bool,
+    master_key: &[u8],
+) -> Result<Executed, WebhookError> {
+    let action = Action::parse(&alert.side).ok_or_else(|| {
+        WebhookError::Invalid(format!(
+            "side must be buy, sell or close, got {:?}",
+            alert.side
+        ))
+    })?;
+    let symbol = symbol_for(&alert.symbol);
+
+    if action == Action::Exit {
+        let amount = CloseAmount {
+            percent: alert.percent,
+            size: alert.size,
+        };
+        let fill = positions::close(db, user_id, &symbol, amount, is_demo, master_key).await?;
+        return Ok(Executed {
+            action,
+            symbol,
+            fill,
+        });
+    }
+
+    risk::check_drawdown(cache, user_id).await?;
+
+    let order_type = alert
+        .order_type
+        .as_deref()
+        .unwrap_or("market")
+        .to_ascii_lowercase();
+    match order_type.as_str() {
+        "market" => {}
+        "limit" if alert.price.is_some() => {}
+        "limit" => return Err(WebhookError::Invalid("a limit order needs price".into())),
+        _ => {
+            return Err(WebhookError::Invalid(
+                "order_type must be market or limit".into(),
+            ))
+        }
+    }
+    let price = alert.price.or_else(|| analytics::mid_for(&symbol));
+    let equity = match alert.risk_pct {
+        Some(_) => portfolio::account_equity(db, user_id, is_demo, master_key).await,
+        None => None,
+    };
+    let size = entry_size(alert, action, price, equity)?;
+    let size = floor_to_lot(size, instruments::get(&symbol).map(|i| i.lot_size));
+    if size <= 0.0 {
+        return Err(WebhookError::Invalid(
+            "size rounds below the lot size".into(),
+        ));
+    }
+
+    let tp_sl = (alert.stop.is_some() || alert.take_profit.is_some()).then_some(TpSl {
+        take_profit: alert.take_profit,
+        stop_loss: alert.stop,
+    });
+    let req = TradeRequest {
+        exchange: Exchange::Blowfin,
+        symbol: symbol.clone(),
+        side: if action == Action::Buy { "buy" } else { "sell" }.into(),
+        price: alert.price.filter(|_| order_type == "limit"),
+        order_type,
+        size,
+        reduce_only: false,
+        signal_price: price,
+        tp_sl,
+    };
+    let fill = execute_trade(req, db, user_id, is_demo, master_key).await?;
+    Ok(Executed {
+        action,
+        symbol,
+        fill,
+    })
+}
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn alert(v: serde_json::Value) -> Alert {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn tradingview_tickers_and_sides() {
+        assert_eq!(symbol_for("BINANCE:BTCUSDT.P"), "BTCUSDT");
+        assert_eq!(symbol_for("eth-usdt"), "ETH-USDT");
+        assert_eq!(Action::parse("Long"), Some(Action::Buy));
+        assert_eq!(Action::parse("flat"), Some(Action::Exit));
+        assert_eq!(Action::parse("hold"), None);
+        assert_eq!(floor_to_lot(0.0379, Some(0.01)), 0.03);
+    }
+
+    #[test]
+    fn entries_size_by_contracts_or_by_risk() {
+        let fixed = alert(json!({ "token": "t", "symbol": "BTCUSDT", "side": "buy", "size": 0.5 }));
+        assert_eq!(entry_size(&fixed, Action::Buy, None, None).unwrap(), 0.5);
+
+        // 1 % of 50k over a 500 stop
+        let risk = alert(json!({
+            "token": "t", "symbol": "BTCUSDT", "side": "buy", "risk_pct": 1, "stop": 59_500
+        }));
+        let size = entry_size(&risk, Action::Buy, Some(60_000.0), Some(50_000.0)).unwrap();
+        assert!((size - 1.0).abs() < 1e-12);
+        // a long's stop above price, no price, no equity
+        assert!(entry_size(&risk, Action::Sell, Some(60_000.0), Some(50_000.0)).is_err());
+        assert!(entry_size(&risk, Action::Buy, None, Some(50_000.0)).is_err());
+        assert!(entry_size(&risk, Action::Buy, Some(60_000.0), None).is_err());
+
+        let both = alert(json!({
+            "token": "t", "symbol": "BTCUSDT", "side": "buy", "size": 1, "risk_pct": 1
+        }));
+        assert!(entry_size(&both, Action::Buy, Some(60_000.0), Some(50_000.0)).is_err());
+    }
+}
@@ -71,29 +71,60 @@ pub enum TradeError {
     RiskViolation(String),
     MissingKey,
     Db(sqlx::Error),
+    /// Exchange didn't answer within the latency budget
+    Timeout(String),
 }
 
 impl fmt::Display for TradeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TradeError::Api(e)           => write!(f, "{e}"),
-            TradeError::InvalidRequest(m)
-            => write!(f, "Invalid request: {m}"),
+            TradeError::Api(e) => write!(f, "{e}"),
+            TradeError::InvalidRequest(m) => write!(f, "Invalid request: {m}"),
             TradeError::RiskViolation(m) => write!(f, "Risk violation: {m}"),
-            TradeError::MissingKey       => write!(f, "API key not registered"),
-            TradeError::Other(m)         => write!(f, "{m}"),
+            TradeError::MissingKey => write!(f, "API key not registered"),
+            TradeError::Other(m) => write!(f, "{m}"),
             TradeError::Db(_) => write!(f, "Database error:"),
+            TradeError::Timeout(m) => write!(f, "Exchange timeout: {m}"),
+        }
+    }
+}
+
+impl Error for TradeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TradeError::Api(e) => Some(e),
+            TradeError::Db(e) => Some(e),
+            _ => None,
         }
     }
 }
 
 /// Allow `?` to lift any `ApiError` into the domain layer
 impl From<ApiError> for TradeError {
-    fn from(e: ApiError) -> Self { TradeError::Api(e) }
+    fn from(e: ApiError) -> Self {
+        TradeError::Api(e)
+    }
 }
 
 /// Convenience: lift `sqlx::Error` directly into `TradeError`—
 /// lets you keep the plain `?` on async DB calls.
 impl From<sqlx::Error> for TradeError {
-    fn from(e: sqlx::Error) -> Self { TradeError::Api(e.into()) }
-}
\ No newline at end of file
+    fn from(e: sqlx::Error) -> Self {
+        TradeError::Api(e.into())
+    }
+}
+
+impl TradeError {
+    /// Worth placing again as-is: the request never reached the exchange,
+    /// or it answered 429 / 5xx. A timeout is not – the order may be live.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            TradeError::Api(ApiError::Http(e)) => {
+                e.is_connect()
+                    || e.status()
+                        .is_some_and(|s| s.is_server_error() || s.as_u16() == 429)
+            }
+            _ => false,
+        }
+    }
+}
@@ -1,6 +1,5 @@
-pub(crate) mod errors;
+pub mod errors;
 pub mod route_debug;
 pub(crate) mod signature;
 mod time;
 pub(crate) mod types;
-mod keygen;
@@ -1,23 +1,32 @@
+//! Debug handlers, mounted under the admin-only `/api/admin` scope
+//! (see `routes::admin`).
 use actix_web::{get, web, HttpResponse, Responder};
 
-#[get("/debug/routes")]
-pub async fn dump_routes() -> impl Responder {
+use crate::middleware::admin::AdminUser;
+
+#[get("/routes")]
+pub async fn dump_routes(_admin: AdminUser) -> impl Responder {
     let routes = vec![
         "GET /health",
         "GET /api/test",
         "GET /api/balance",
-        "GET /api/routes",
         "GET /api/simple",
         "POST /api/trade",
-        "GET /debug/routes", // This route
+        "GET /api/admin/routes", // This route
+        "GET /api/admin/feeds",
+        "GET /api/admin/tasks",
+        "GET /api/admin/flags",
+        "GET /api/admin/storage/candles",
+        "GET /api/admin/exchange-log",
+        "GET /api/admin/integrations/keys",
     ];
 
     HttpResponse::Ok().json(routes)
 }
 
 // Function to log request info - helps debug what's happening in tests
-#[get("/debug/request-info/{path:.*}")]
-pub async fn request_info(path: web::Path<String>) -> impl Responder {
+#[get("/request-info/{path:.*}")]
+pub async fn request_info(_admin: AdminUser, path: web::Path<String>) -> impl Responder {
     let path_param = path.into_inner();
 
     let info = serde_json::json!({
@@ -29,8 +38,8 @@ pub async fn request_info(path: web::Path<String>) -> impl Responder {
 }
 
 // Add a parameter extraction test route
-#[get("/debug/param/{id}")]
-pub async fn param_test(path: web::Path<i32>) -> impl Responder {
+#[get("/param/{id}")]
+pub async fn param_test(_admin: AdminUser, path: web::Path<i32>) -> impl Responder {
     let id = path.into_inner();
     HttpResponse::Ok().body(format!("Parameter test successful. ID: {}", id))
 }
@@ -1,17 +1,66 @@
 //! HMAC helpers for the X-RR-SIG header (hardened version)
+//!
+//! Signed requests carry `X-RR-TIMESTAMP` (unix secs), `X-RR-NONCE` (unique
+//! per request) and `X-RR-SIG = hex(hmac_sha256(ts || nonce || body))`.
+//! The timestamp bounds how long a captured request stays valid; within
+//! that window [`claim_nonce`] rejects any nonce seen before.
+//!
+//! With `X-RR-KEY-ID` the signature is checked against that integration's
+//! own key (see `services::integration_keys`); without it, against the
+//! shared `RR_HMAC_SECRET` (disabled when unset).
 
 use actix_web::dev::ServiceRequest;
 use actix_web::HttpMessage;
 use hmac::{Hmac, Mac};
-use log::warn;
 use sha2::Sha256;
 use std::time::{SystemTime, UNIX_EPOCH};
 use subtle::ConstantTimeEq;
+use tracing::warn;
+
+use crate::db::cache::{Cache, CacheError};
 
 /// Maximum allowed clock skew (seconds)
 const MAX_SKEW_SECS: i64 = 10;
 
+/// A timestamp is accepted for `2 × MAX_SKEW_SECS`; nonces are remembered
+/// that long
+pub const NONCE_TTL_SECS: u64 = 2 * MAX_SKEW_SECS as u64;
+
+/// `X-RR-NONCE`: 16–128 chars of `[A-Za-z0-9_-]`
+pub fn request_nonce(req: &ServiceRequest) -> Option<String> {
+    let n = req.headers().get("X-RR-NONCE")?.to_str().ok()?;
+    let ok = (16..=128).contains(&n.len())
+        && n.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    ok.then(|| n.to_string())
+}
+
+/// Record `nonce` as used; `Ok(false)` if it was already seen in the window
+pub async fn claim_nonce(cache: &dyn Cache, nonce: &str) -> Result<bool, CacheError> {
+    cache
+        .set_nx(&format!("rr:nonce:{nonce}"), "1", NONCE_TTL_SECS)
+        .await
+}
+
+/// `X-RR-KEY-ID`: up to 64 chars of `[A-Za-z0-9_]`
+pub fn request_key_id(req: &ServiceRequest) -> Option<String> {
+    let id = req.headers().get("X-RR-KEY-ID")?.to_str().ok()?;
+    let ok =
+        (1..=64).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+    ok.then(|| id.to_string())
+}
+
+/// Verify against the shared `RR_HMAC_SECRET`
 pub fn verify_hmac(req: &ServiceRequest) -> bool {
+    let key = std::env::var("RR_HMAC_SECRET").unwrap_or_default();
+    if key.is_empty() {
+        warn!("RR_HMAC_SECRET unset – shared-secret HMAC disabled");
+        return false;
+    }
+    verify_hmac_with(req, key.as_bytes())
+}
+
+pub fn verify_hmac_with(req: &ServiceRequest, key: &[u8]) -> bool {
     // --- Parse signature header ---
     let sig_hdr = match req.headers().get("X-RR-SIG") {
         Some(h) => h,
@@ -56,7 +105,8 @@ pub fn verify_hmac(req: &ServiceRequest) -> bool {
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
 
-    if (ts - now).abs() > MAX_SKEW_SECS {
+    // abs_diff: a hostile timestamp near i64::MIN/MAX must not overflow
+    if ts.abs_diff(now) > MAX_SKEW_SECS as u64 {
         warn!(
             "X-RR-TIMESTAMP out of allowed skew (got {}, now {})",
             ts, now
@@ -64,6 +114,14 @@ pub fn verify_hmac(req: &ServiceRequest) -> bool {
         return false;
     }
 
+    let nonce = match request_nonce(req) {
+        Some(n) => n,
+        None => {
+            warn!("X-RR-NONCE header missing or malformed");
+            return false;
+        }
+    };
+
     // --- Read request payload (from extensions) ---
     let extensions = req.extensions();
     let body_bytes: &[u8] = extensions
@@ -71,15 +129,15 @@ pub fn verify_hmac(req: &ServiceRequest) -> bool {
         .map(|v| v.as_slice())
         .unwrap_or(&[]);
 
-    // --- Compose HMAC input: timestamp (as bytes) || body ---
-    let mut hmac_input = Vec::with_capacity(8 + body_bytes.len());
+    // --- Compose HMAC input: timestamp (as bytes) || nonce || body ---
+    let mut hmac_input = Vec::with_capacity(ts_str.len() + nonce.len() + body_bytes.len());
     hmac_input.extend_from_slice(ts_str.as_bytes());
+    hmac_input.extend_from_slice(nonce.as_bytes());
     hmac_input.extend_from_slice(body_bytes);
 
     // --- Compute HMAC ---
     type HmacSha = Hmac<Sha256>;
-    let key = std::env::var("RR_HMAC_SECRET").unwrap_or_default();
-    let mut mac = HmacSha::new_from_slice(key.as_bytes()).expect("key length");
+    let mut mac = HmacSha::new_from_slice(key).expect("key length");
     mac.update(&hmac_input);
     let calc = mac.finalize().into_bytes();
 
@@ -113,3 +171,24 @@ pub fn verify_hmac_bytes(body: &[u8], secret: &str, sig_hex: &str) -> bool {
         Err(_) => false,
     }
 }
+
+// ======================================================================
+// UNIT TESTS
+// ======================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn extreme_timestamps_are_rejected_not_overflowed() {
+        for ts in [i64::MIN, i64::MAX] {
+            let req = TestRequest::default()
+                .insert_header(("X-RR-SIG", "00".repeat(32)))
+                .insert_header(("X-RR-TIMESTAMP", ts.to_string()))
+                .insert_header(("X-RR-NONCE", "nonce-0000000001"))
+                .to_srv_request();
+            assert!(!verify_hmac_with(&req, b"secret"));
+        }
+    }
+}
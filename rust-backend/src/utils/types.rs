@@ -1,17 +1,28 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use sqlx::Type;
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub message: Option<String>,
-    pub data: Option<T>,
+use crate::services::instruments::{self, DisplayMeta};
+
+// the response envelope is shared with API clients through `rustraptor-types`
+pub use rustraptor_types::api::ApiResponse;
+
+/// `?display=true` on endpoints returning orders / positions
+#[derive(Debug, Default, Deserialize)]
+pub struct DisplayQuery {
+    #[serde(default)]
+    pub display: bool,
 }
 
-#[derive(Debug, serde::Serialize, Deserialize)]
-pub struct OrderResp {
-    pub order_id: String,
-    pub status:   String,
+impl DisplayQuery {
+    /// Display metadata for `symbols` if the caller asked for it
+    pub fn meta<'a>(
+        &self,
+        symbols: impl IntoIterator<Item = &'a str>,
+    ) -> Option<BTreeMap<String, DisplayMeta>> {
+        self.display.then(|| instruments::display_for(symbols))
+    }
 }
 
 /* ------------------------- Postgres ENUMs ------------------------ */
@@ -37,8 +48,8 @@ pub enum OrderType {
     Conditional,
 }
 
-#[derive(Debug, Serialize, Deserialize, Type)]
-#[sqlx(type_name = "order_status", rename_all = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "order_status", rename_all = "snake_case")]
 pub enum OrderStatus {
     Live,
     PartiallyFilled,
@@ -47,7 +58,7 @@ pub enum OrderStatus {
     Rejected,
 }
 
-#[derive(Debug, Serialize, Deserialize, Type)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type)]
 #[sqlx(type_name = "maker_taker_enum", rename_all = "lowercase")]
 pub enum MakerTaker {
     Maker,
@@ -62,20 +73,3 @@ pub enum FeeType {
     Funding,
     Rebate,
 }
-
-impl<T: serde::Serialize> ApiResponse<T> {
-    pub fn ok(data: T) -> Self {
-        Self {
-            success: true,
-            message: None,
-            data: Some(data),
-        }
-    }
-    pub fn err(msg: &str) -> Self {
-        Self {
-            success: false,
-            message: Some(msg.into()),
-            data: None,
-        }
-    }
-}
@@ -0,0 +1,48 @@
+// tests/drawdown.rs
+//! Fills reach the draw-down guard the way `order_tracker` books them, and
+//! the guardian pass trips once a losing streak crosses the limit.
+use rustraptor_backend::db::cache::MemoryCache;
+use rustraptor_backend::services::{blowfin::dto::Fill, order_tracker, risk};
+
+/// A BTC fill; `pnl` is set on closing fills only
+fn fill(trade_id: &str, pnl: Option<f64>) -> Fill {
+    Fill {
+        inst_id: "BTC-USDT".into(),
+        trade_id: trade_id.into(),
+        order_id: format!("o-{trade_id}"),
+        fill_price: 30_000.0,
+        fill_size: 0.01,
+        fill_pnl: pnl,
+        side: "sell".into(),
+        position_side: "long".into(),
+        fee: 0.1,
+        ts: 1_697_031_301_187,
+    }
+}
+
+#[tokio::test]
+async fn guardian_trips_after_a_losing_streak() {
+    let cache = MemoryCache::new();
+    let (loser, winner) = (7, 8);
+
+    // opening fills realise nothing
+    assert!(!order_tracker::record_realised(&cache, loser, &fill("0", Some(0.0))).await);
+    assert!(!order_tracker::record_realised(&cache, loser, &fill("1", None)).await);
+
+    // three closes at −6.1 net: −18.3, still inside the limit
+    for id in ["2", "3", "4"] {
+        assert!(order_tracker::record_realised(&cache, loser, &fill(id, Some(-6.0))).await);
+    }
+    assert!(order_tracker::record_realised(&cache, winner, &fill("5", Some(30.0))).await);
+    assert!(order_tracker::record_realised(&cache, winner, &fill("6", Some(-5.0))).await);
+    assert!(risk::sweep(&cache, &[loser, winner]).await.is_empty());
+
+    // the fourth loss takes it to −24.4
+    order_tracker::record_realised(&cache, loser, &fill("7", Some(-6.0))).await;
+    assert_eq!(risk::sweep(&cache, &[loser, winner]).await, vec![loser]);
+    assert!(risk::check_drawdown(&cache, loser).await.is_err());
+    assert!(risk::check_drawdown(&cache, winner).await.is_ok());
+
+    // still tripped on the next pass (only the notice is once per window)
+    assert_eq!(risk::sweep(&cache, &[loser]).await, vec![loser]);
+}
@@ -1,11 +1,9 @@
 // tests/routes.rs
 use actix_web::{test, web, App, HttpResponse};
+use rustraptor_backend::routes::admin::admin_scope;
 use rustraptor_backend::routes::health::health_scope;
 // Import the individual handlers directly
-use rustraptor_backend::routes::trading::{
-    balance, list_routes, simple_test, test_trade_api, trade,
-};
-use rustraptor_backend::utils::route_debug::{dump_routes, param_test, request_info};
+use rustraptor_backend::routes::trading::{balance, simple_test, test_trade_api, trade};
 
 #[actix_rt::test]
 async fn test_all_routes_exist() {
@@ -18,8 +16,7 @@ async fn test_all_routes_exist() {
                 .service(simple_test)
                 .service(test_trade_api)
                 .service(balance)
-                .service(trade)
-                .service(list_routes),
+                .service(trade),
         ),
     )
     .await;
@@ -41,8 +38,7 @@ async fn test_all_routes_exist() {
                 .service(simple_test)
                 .service(test_trade_api)
                 .service(balance)
-                .service(trade)
-                .service(list_routes),
+                .service(trade),
         ),
     )
     .await;
@@ -55,64 +51,55 @@ async fn test_all_routes_exist() {
     println!("API+health /api/test response: {}", resp.status());
     assert_eq!(resp.status(), 200);
 
-    // Finally add debug routes
-    println!("Now testing with debug routes added");
+    // Finally add the admin scope (debug routes live there)
+    println!("Now testing with admin routes added");
     let app = test::init_service(
         App::new()
             .service(health_scope())
+            .service(admin_scope())
             .service(
                 web::scope("/api")
                     .service(simple_test)
                     .service(test_trade_api)
                     .service(balance)
-                    .service(trade)
-                    .service(list_routes),
-            )
-            .service(dump_routes)
-            .service(request_info)
-            .service(param_test),
+                    .service(trade),
+            ),
     )
     .await;
 
-    println!("API+health+debug app initialized");
+    println!("API+health+admin app initialized");
 
-    println!("Testing /api/test with API+health+debug");
+    println!("Testing /api/test with API+health+admin");
     let req = test::TestRequest::get().uri("/api/test").to_request();
     let resp = test::call_service(&app, req).await;
-    println!("API+health+debug /api/test response: {}", resp.status());
+    println!("API+health+admin /api/test response: {}", resp.status());
     assert_eq!(resp.status(), 200);
 }
 
 #[actix_rt::test]
 async fn test_route_debug_implementation() {
-    use rustraptor_backend::utils::route_debug::{dump_routes, param_test, request_info};
-
-    // Check what routes are registered
+    // The debug routes are only reachable through the admin scope, and never
+    // without a signed-in account
     println!("Testing route_debug implementation");
 
-    // Register just the debug routes
-    let app = test::init_service(
-        App::new()
-            .service(dump_routes)
-            .service(request_info)
-            .service(param_test),
-    )
-    .await;
+    let app = test::init_service(App::new().service(admin_scope())).await;
 
-    // Test a few paths to see what's registered
-    for path in ["/debug/routes", "/debug/param/42", "/api/test"] {
-        println!("Testing path with debug routes only: {}", path);
+    for path in [
+        "/api/admin/routes",
+        "/api/admin/request-info/x",
+        "/api/admin/param/42",
+    ] {
+        println!("Testing admin path without a user: {}", path);
         let req = test::TestRequest::get().uri(path).to_request();
         let resp = test::call_service(&app, req).await;
-        println!("Debug-only response for {}: {}", path, resp.status());
+        println!("Anonymous response for {}: {}", path, resp.status());
+        assert_eq!(resp.status(), 401);
     }
 }
 
 #[actix_rt::test]
 async fn test_direct_registration() {
-    use rustraptor_backend::routes::trading::{
-        balance, list_routes, simple_test, test_trade_api, trade,
-    };
+    use rustraptor_backend::routes::trading::{balance, simple_test, test_trade_api, trade};
 
     println!("Starting test_direct_registration");
 
@@ -123,8 +110,7 @@ async fn test_direct_registration() {
                 .service(simple_test)
                 .service(test_trade_api)
                 .service(balance)
-                .service(trade)
-                .service(list_routes),
+                .service(trade),
         ),
     )
     .await;
@@ -148,9 +134,7 @@ async fn test_direct_registration() {
 // Keeping this additional test can be helpful for debugging
 #[actix_rt::test]
 async fn test_without_middleware() {
-    use rustraptor_backend::routes::trading::{
-        balance, list_routes, simple_test, test_trade_api, trade,
-    };
+    use rustraptor_backend::routes::trading::{balance, simple_test, test_trade_api, trade};
 
     println!("Starting test_without_middleware");
 
@@ -162,8 +146,7 @@ async fn test_without_middleware() {
                 .service(simple_test)
                 .service(test_trade_api)
                 .service(balance)
-                .service(trade)
-                .service(list_routes),
+                .service(trade),
         ),
     )
     .await;